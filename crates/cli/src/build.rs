@@ -14,7 +14,7 @@ use roc_load::{
 use roc_mono::ir::OptLevel;
 use roc_packaging::cache::RocCacheDir;
 use roc_reporting::{
-    cli::Problems,
+    cli::{MessageFormat, Problems},
     report::{RenderTarget, DEFAULT_PALETTE},
 };
 use roc_target::TargetInfo;
@@ -109,6 +109,7 @@ pub fn build_file<'a>(
     wasm_dev_stack_bytes: Option<u32>,
     roc_cache_dir: RocCacheDir<'_>,
     load_config: LoadConfig,
+    message_format: MessageFormat,
 ) -> Result<BuiltFile<'a>, BuildFileError<'a>> {
     let compilation_start = Instant::now();
 
@@ -129,6 +130,7 @@ pub fn build_file<'a>(
         wasm_dev_stack_bytes,
         loaded,
         compilation_start,
+        message_format,
     )
 }
 
@@ -145,6 +147,7 @@ fn build_loaded_file<'a>(
     wasm_dev_stack_bytes: Option<u32>,
     loaded: roc_load::MonomorphizedModule<'a>,
     compilation_start: Instant,
+    message_format: MessageFormat,
 ) -> Result<BuiltFile<'a>, BuildFileError<'a>> {
     let operating_system = roc_target::OperatingSystem::from(target.operating_system);
 
@@ -262,7 +265,7 @@ fn build_loaded_file<'a>(
     // This only needs to be mutable for report_problems. This can't be done
     // inside a nested scope without causing a borrow error!
     let mut loaded = loaded;
-    let problems = program::report_problems_monomorphized(&mut loaded);
+    let problems = program::report_problems_monomorphized(&mut loaded, message_format);
     let loaded = loaded;
 
     enum HostRebuildTiming {
@@ -505,7 +508,29 @@ pub fn check_file<'a>(
     emit_timings: bool,
     roc_cache_dir: RocCacheDir<'_>,
     threading: Threading,
+    message_format: MessageFormat,
 ) -> Result<(Problems, Duration), LoadingProblem<'a>> {
+    check_file_and_module_paths(
+        arena,
+        roc_file_path,
+        emit_timings,
+        roc_cache_dir,
+        threading,
+        message_format,
+    )
+    .map(|(problems, total_time, _)| (problems, total_time))
+}
+
+/// Like [check_file], but also returns the paths of all the (non-builtin) modules that were
+/// loaded, e.g. so they can be watched for changes.
+pub fn check_file_and_module_paths<'a>(
+    arena: &'a Bump,
+    roc_file_path: PathBuf,
+    emit_timings: bool,
+    roc_cache_dir: RocCacheDir<'_>,
+    threading: Threading,
+    message_format: MessageFormat,
+) -> Result<(Problems, Duration, Vec<PathBuf>), LoadingProblem<'a>> {
     let compilation_start = Instant::now();
 
     // only used for generating errors. We don't do code generation, so hardcoding should be fine
@@ -516,8 +541,7 @@ pub fn check_file<'a>(
 
     let load_config = LoadConfig {
         target_info,
-        // TODO: expose this from CLI?
-        render: RenderTarget::ColorTerminal,
+        render: message_format.render_target(),
         palette: DEFAULT_PALETTE,
         threading,
         exec_mode: ExecutionMode::Check,
@@ -568,9 +592,17 @@ pub fn check_file<'a>(
         println!("Finished checking in {} ms\n", compilation_end.as_millis(),);
     }
 
+    let module_paths = loaded
+        .sources
+        .iter()
+        .filter(|(module_id, _)| !module_id.is_builtin())
+        .map(|(_, (path, _))| path.clone())
+        .collect();
+
     Ok((
-        program::report_problems_typechecked(&mut loaded),
+        program::report_problems_typechecked(&mut loaded, message_format),
         compilation_end,
+        module_paths,
     ))
 }
 
@@ -586,6 +618,7 @@ pub fn build_str_test<'a>(
         backend: CodeGenBackend::Llvm,
         opt_level: OptLevel::Normal,
         emit_debug_info: false,
+        profile: false,
     };
 
     let emit_timings = false;
@@ -624,5 +657,6 @@ pub fn build_str_test<'a>(
         wasm_dev_stack_bytes,
         loaded,
         compilation_start,
+        MessageFormat::Human,
     )
 }
@@ -8,11 +8,13 @@ use bumpalo::Bump;
 use clap::{Arg, ArgMatches, Command, ValueSource};
 use roc_build::link::{LinkType, LinkingStrategy};
 use roc_build::program::{CodeGenBackend, CodeGenOptions};
+use roc_docs::DEFAULT_OUTPUT_DIR;
 use roc_error_macros::{internal_error, user_error};
 use roc_load::{ExpectMetadata, LoadingProblem, Threading};
 use roc_mono::ir::OptLevel;
 use roc_packaging::cache::RocCacheDir;
 use roc_packaging::tarball::Compression;
+use roc_reporting::cli::{print_json_formatted_report, MessageFormat};
 use std::env;
 use std::ffi::{CString, OsStr};
 use std::io;
@@ -31,11 +33,24 @@ use tempfile::TempDir;
 
 pub mod build;
 mod format;
+#[cfg(not(windows))]
+mod profile;
+#[cfg(not(windows))]
+mod test_coverage;
+#[cfg(not(windows))]
+mod test_filter;
+#[cfg(not(windows))]
+mod test_report;
+pub mod watch;
 pub use format::format;
 
 use crate::build::{standard_load_config, BuildFileError, BuildOrdering};
 
 const DEFAULT_ROC_FILENAME: &str = "main.roc";
+/// Where `roc test --coverage` writes the coverage, unless it's given a path
+const DEFAULT_LCOV_PATH: &str = "lcov.info";
+/// Where `roc run --profile` writes the samples, unless it's given a path
+const DEFAULT_PROFILE_PATH: &str = "profile.folded";
 
 pub const CMD_BUILD: &str = "build";
 pub const CMD_RUN: &str = "run";
@@ -48,6 +63,7 @@ pub const CMD_VERSION: &str = "version";
 pub const CMD_FORMAT: &str = "format";
 pub const CMD_TEST: &str = "test";
 pub const CMD_GLUE: &str = "glue";
+pub const CMD_LSP: &str = "lsp";
 pub const CMD_GEN_STUB_LIB: &str = "gen-stub-lib";
 
 pub const FLAG_DEBUG: &str = "debug";
@@ -64,11 +80,27 @@ pub const FLAG_LINKER: &str = "linker";
 pub const FLAG_PREBUILT: &str = "prebuilt-platform";
 pub const FLAG_CHECK: &str = "check";
 pub const FLAG_WASM_STACK_SIZE_KB: &str = "wasm-stack-size-kb";
+pub const FLAG_WATCH: &str = "watch";
+pub const FLAG_MESSAGE_FORMAT: &str = "message-format";
+pub const FLAG_EXCLUDE: &str = "exclude";
+pub const FLAG_LIST: &str = "list";
+pub const FLAG_REPORT: &str = "report";
+pub const FLAG_TIMEOUT: &str = "timeout";
+pub const FLAG_SEED: &str = "seed";
+pub const FLAG_COVERAGE: &str = "coverage";
+pub const FLAG_PROFILE: &str = "profile";
+pub const FLAG_PLATFORM: &str = "platform";
+pub const FLAG_OUTPUT: &str = "output";
+pub const FLAG_DOC: &str = "doc";
+pub const FLAG_WASM_DIR: &str = "wasm-dir";
+pub const FLAG_WASM_ENV: &str = "wasm-env";
 pub const ROC_FILE: &str = "ROC_FILE";
 pub const ROC_DIR: &str = "ROC_DIR";
 pub const GLUE_FILE: &str = "GLUE_FILE";
+pub const GLUE_DIR: &str = "GLUE_DIR";
 pub const DIRECTORY_OR_FILES: &str = "DIRECTORY_OR_FILES";
 pub const ARGS_FOR_APP: &str = "ARGS_FOR_APP";
+pub const TEST_FILTERS: &str = "TEST_FILTERS";
 
 const VERSION: &str = include_str!("../../../version.txt");
 
@@ -107,10 +139,17 @@ pub fn build_app<'a>() -> Command<'a> {
 
     let flag_linker = Arg::new(FLAG_LINKER)
         .long(FLAG_LINKER)
-        .help("Set which linker to use\n(The surgical linker is enabled by default only when building for wasm32 or x86_64 Linux, because those are the only targets it currently supports. Otherwise the legacy linker is used by default.)")
+        .help("Set which linker to use\n(The surgical linker is enabled by default only when building for wasm32, x86_64 Linux or aarch64 Linux, because those are the only targets it currently supports. Otherwise the legacy linker is used by default.)")
         .possible_values(["surgical", "legacy"])
         .required(false);
 
+    let flag_message_format = Arg::new(FLAG_MESSAGE_FORMAT)
+        .long(FLAG_MESSAGE_FORMAT)
+        .help("How to print problems\n(`json` prints one JSON object per line, for use by other programs such as CI or editor plugins.)")
+        .possible_values(["human", "json"])
+        .default_value("human")
+        .required(false);
+
     let flag_prebuilt = Arg::new(FLAG_PREBUILT)
         .long(FLAG_PREBUILT)
         .help("Assume the platform has been prebuilt and skip rebuilding the platform\n(This is enabled by default when using `roc build` with a --target other than `--target <current machine>`.)")
@@ -124,6 +163,20 @@ pub fn build_app<'a>() -> Command<'a> {
         .validator(|s| s.parse::<u32>())
         .required(false);
 
+    let flag_wasm_dir = Arg::new(FLAG_WASM_DIR)
+        .long(FLAG_WASM_DIR)
+        .help("Give a wasm32 app access to this directory, at the same path, e.g. `--wasm-dir .`\n(Can be given more than once. By default, the app can't see any files.)")
+        .takes_value(true)
+        .multiple_occurrences(true)
+        .required(false);
+
+    let flag_wasm_env = Arg::new(FLAG_WASM_ENV)
+        .long(FLAG_WASM_ENV)
+        .help("Pass this environment variable to a wasm32 app, e.g. `--wasm-env HOME`\n(Can be given more than once. By default, the app can't see any environment variables.)")
+        .takes_value(true)
+        .multiple_occurrences(true)
+        .required(false);
+
     let roc_file_to_run = Arg::new(ROC_FILE)
         .help("The .roc file of an app to run")
         .allow_invalid_utf8(true)
@@ -152,6 +205,7 @@ pub fn build_app<'a>() -> Command<'a> {
             .arg(flag_linker.clone())
             .arg(flag_prebuilt.clone())
             .arg(flag_wasm_stack_size_kb.clone())
+            .arg(flag_message_format.clone())
             .arg(
                 Arg::new(FLAG_TARGET)
                     .long(FLAG_TARGET)
@@ -202,15 +256,81 @@ pub fn build_app<'a>() -> Command<'a> {
             .arg(flag_prebuilt.clone())
             .arg(
                 Arg::new(ROC_FILE)
-                    .help("The .roc file for the main module")
+                    .help("The .roc file for the main module\n(Only read as one when it ends in .roc or exists, so `roc test Parser` runs the main.roc tests that match `Parser`.)")
                     .allow_invalid_utf8(true)
                     .required(false)
                     .default_value(DEFAULT_ROC_FILENAME)
             )
+            .arg(
+                Arg::new(TEST_FILTERS)
+                    .help("Only run the top-level `expect`s that match one of these patterns\n(A pattern matches part of a module name, file name, or the source code of an `expect`. `Parser.roc:12` matches the `expect` on line 12 of Parser.roc.)")
+                    .multiple_values(true)
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_EXCLUDE)
+                    .long(FLAG_EXCLUDE)
+                    .help("Skip the top-level `expect`s that match this pattern\n(Can be given more than once.)")
+                    .takes_value(true)
+                    .multiple_occurrences(true)
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_LIST)
+                    .long(FLAG_LIST)
+                    .help("List the selected top-level `expect`s without running them")
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_REPORT)
+                    .long(FLAG_REPORT)
+                    .help("Also write the results to a file, as `junit:<path>` or `json:<path>`\n(Can be given more than once.)")
+                    .takes_value(true)
+                    .multiple_occurrences(true)
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_TIMEOUT)
+                    .long(FLAG_TIMEOUT)
+                    .help("Fail any top-level `expect` that runs for longer than this many seconds")
+                    .takes_value(true)
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_SEED)
+                    .long(FLAG_SEED)
+                    .help("Generate the inputs of property `expect`s from this seed\n(By default, every run uses a new seed. A failing property reports the seed it used.)")
+                    .takes_value(true)
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_COVERAGE)
+                    .long(FLAG_COVERAGE)
+                    .help("Count which top-level defs, `when` branches and `if` arms the tests run, and write the counts as lcov\n(To lcov.info, unless given a path like `--coverage=out/lcov.info`.)")
+                    .takes_value(true)
+                    .min_values(0)
+                    .require_equals(true)
+                    .default_missing_value(DEFAULT_LCOV_PATH)
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_DOC)
+                    .long(FLAG_DOC)
+                    .help("Also test the examples in doc comments: fenced `roc` code blocks with `expect`s, and examples like `List.len [1, 2] == 2`")
+                    .required(false)
+            )
             .arg(args_for_app.clone())
         )
         .subcommand(Command::new(CMD_REPL)
             .about("Launch the interactive Read Eval Print Loop (REPL)")
+            .arg(
+                Arg::new(FLAG_PLATFORM)
+                    .long(FLAG_PLATFORM)
+                    .help("Run `Task`s entered at the prompt with the host of the platform whose main.roc is at this path")
+                    .takes_value(true)
+                    .allow_invalid_utf8(true)
+                    .required(false)
+            )
         )
         .subcommand(Command::new(CMD_RUN)
             .about("Run a .roc file even if it has build errors")
@@ -222,6 +342,18 @@ pub fn build_app<'a>() -> Command<'a> {
             .arg(flag_time.clone())
             .arg(flag_linker.clone())
             .arg(flag_prebuilt.clone())
+            .arg(
+                Arg::new(FLAG_PROFILE)
+                    .long(FLAG_PROFILE)
+                    .help("Sample which Roc functions the program spends its time in, count what they allocate, and write both as folded stacks for a flamegraph\n(To profile.folded and profile.alloc.folded, unless given a path like `--profile=out/run.folded`.)")
+                    .takes_value(true)
+                    .min_values(0)
+                    .require_equals(true)
+                    .default_missing_value(DEFAULT_PROFILE_PATH)
+                    .required(false)
+            )
+            .arg(flag_wasm_dir.clone())
+            .arg(flag_wasm_env.clone())
             .arg(roc_file_to_run.clone())
             .arg(args_for_app.clone())
         )
@@ -235,6 +367,8 @@ pub fn build_app<'a>() -> Command<'a> {
             .arg(flag_time.clone())
             .arg(flag_linker.clone())
             .arg(flag_prebuilt.clone())
+            .arg(flag_wasm_dir.clone())
+            .arg(flag_wasm_env.clone())
             .arg(roc_file_to_run.clone())
             .arg(args_for_app.clone())
         )
@@ -259,6 +393,14 @@ pub fn build_app<'a>() -> Command<'a> {
             .about("Check the code for problems, but don’t build or run it")
             .arg(flag_time.clone())
             .arg(flag_max_threads.clone())
+            .arg(flag_message_format.clone())
+            .arg(
+                Arg::new(FLAG_WATCH)
+                    .long(FLAG_WATCH)
+                    .help("Keep running, and check again whenever the file or any module it imports changes")
+                    .conflicts_with(FLAG_MESSAGE_FORMAT)
+                    .required(false),
+            )
             .arg(
                 Arg::new(ROC_FILE)
                     .help("The .roc file of an app to check")
@@ -277,21 +419,40 @@ pub fn build_app<'a>() -> Command<'a> {
                     .required(false)
                     .default_value(DEFAULT_ROC_FILENAME),
                 )
+                .arg(
+                    Arg::new(FLAG_OUTPUT)
+                        .long(FLAG_OUTPUT)
+                        .help("The directory to write the docs to. It must be empty, or have been written by an earlier `roc docs`.\n(Docs of the packages it depends on go in subdirectories named after them.)")
+                        .takes_value(true)
+                        .allow_invalid_utf8(true)
+                        .required(false)
+                        .default_value(DEFAULT_OUTPUT_DIR),
+                )
+        )
+        .subcommand(Command::new(CMD_LSP)
+            .about("Start a language server that speaks the Language Server Protocol over stdio")
         )
         .subcommand(Command::new(CMD_GLUE)
             .about("Generate glue code between a platform's Roc API and its host language")
+            .after_help("To generate glue with a script written in Roc, instead run:\n\n    roc glue path/to/MyGlue.roc path/to/platform/main.roc path/to/output/dir/\n\nThe script is an app whose platform is crates/glue/src/RocType.roc. It gets the platform's types, and returns the files to write into the output directory.")
             .arg(
                 Arg::new(ROC_FILE)
-                    .help("The .roc file for the platform module")
+                    .help("The .roc file for the platform module\n(Or for the glue script, when generating glue with a script.)")
                     .allow_invalid_utf8(true)
                     .required(true)
             )
             .arg(
                 Arg::new(GLUE_FILE)
-                    .help("The filename for the generated glue code\n(Currently, this must be a .rs file because only Rust glue generation is supported so far.)")
+                    .help("The filename for the generated glue code\n(The extension picks the language: .rs for Rust, .h for C, or .zig for Zig.)\n(Or the .roc file for the platform module, when generating glue with a script.)")
                     .allow_invalid_utf8(true)
                     .required(true)
             )
+            .arg(
+                Arg::new(GLUE_DIR)
+                    .help("The directory to write the glue script's files into")
+                    .allow_invalid_utf8(true)
+                    .required(false)
+            )
         )
         .subcommand(Command::new(CMD_GEN_STUB_LIB)
             .about("Generate a stubbed shared library that can be used for linking a platform binary.\nThe stubbed library has prototypes, but no function bodies.\n\nNote: This command will be removed in favor of just using `roc build` once all platforms support the surgical linker")
@@ -321,6 +482,8 @@ pub fn build_app<'a>() -> Command<'a> {
         .arg(flag_time)
         .arg(flag_linker)
         .arg(flag_prebuilt)
+        .arg(flag_wasm_dir)
+        .arg(flag_wasm_env)
         .arg(roc_file_to_run.required(false))
         .arg(args_for_app);
 
@@ -347,6 +510,15 @@ pub enum BuildConfig {
     BuildAndRunIfNoErrors,
 }
 
+/// The parts of the host that a wasm32 app run by the CLI can see. By default, nothing.
+#[derive(Debug, Default)]
+pub struct WasiAccess {
+    /// Directories to preopen, at the same path as on the host
+    pub dirs: Vec<String>,
+    /// The names of environment variables to pass through
+    pub env_vars: Vec<String>,
+}
+
 pub enum FormatMode {
     Format,
     CheckOnly,
@@ -360,14 +532,66 @@ pub fn test(_matches: &ArgMatches, _triple: Triple) -> io::Result<i32> {
 #[cfg(not(windows))]
 pub fn test(matches: &ArgMatches, triple: Triple) -> io::Result<i32> {
     use roc_build::program::report_problems_monomorphized;
+    use roc_collections::{MutMap, MutSet};
     use roc_gen_llvm::llvm::build::LlvmBackendMode;
-    use roc_load::{ExecutionMode, LoadConfig, LoadMonomorphizedError};
+    use roc_load::{ExecutionMode, LoadConfig, LoadMonomorphizedError, TestOptions};
+    use roc_module::symbol::Symbol;
     use roc_packaging::cache;
+    use roc_repl_expect::coverage::CoverageCounters;
+    use roc_repl_expect::run::ExpectRunOptions;
     use roc_target::TargetInfo;
+    use std::time::Duration;
+    use test_coverage::{collect_coverage, print_summary, write_lcov};
+    use test_filter::{select_toplevel_expects, TestFilter};
+    use test_report::{strip_colors, ReportTarget, TestCase};
 
     let start_time = Instant::now();
     let arena = Bump::new();
-    let filename = matches.value_of_os(ROC_FILE).unwrap();
+    let first_arg = matches.value_of_os(ROC_FILE).unwrap();
+    // The main module and the filters are both positional, so `roc test Parser` would read
+    // `Parser` as the main module. Only an argument that looks like a file is one.
+    let first_arg_is_filter = Path::new(first_arg).extension() != Some(OsStr::new("roc"))
+        && !Path::new(first_arg).exists();
+    let (filename, first_filter) = if first_arg_is_filter {
+        (
+            OsStr::new(DEFAULT_ROC_FILENAME),
+            Some(first_arg.to_string_lossy()),
+        )
+    } else {
+        (first_arg, None)
+    };
+    let reports: Vec<ReportTarget> = matches
+        .values_of(FLAG_REPORT)
+        .unwrap_or_default()
+        .map(|value| ReportTarget::parse(value).unwrap_or_else(|problem| user_error!("{problem}")))
+        .collect();
+    let timeout = matches
+        .value_of(FLAG_TIMEOUT)
+        .map(|value| match value.parse::<f64>() {
+            Ok(seconds) if seconds > 0.0 && seconds.is_finite() => Duration::from_secs_f64(seconds),
+            _ => user_error!(
+                "`--{FLAG_TIMEOUT}` must be a positive number of seconds, but it was `{value}`"
+            ),
+        });
+    let seed = match matches.value_of(FLAG_SEED) {
+        Some(value) => value.parse::<u64>().unwrap_or_else(|_| {
+            user_error!("`--{FLAG_SEED}` must be a whole number, but it was `{value}`")
+        }),
+        None => std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|since_epoch| since_epoch.as_nanos() as u64)
+            .unwrap_or_default(),
+    };
+    let lcov_path = matches.value_of(FLAG_COVERAGE).map(PathBuf::from);
+    if lcov_path.is_some() && matches.is_present(FLAG_DEV) {
+        user_error!("`--{FLAG_COVERAGE}` is only supported by the LLVM backend, so it can't be combined with `--{FLAG_DEV}`");
+    }
+    if matches.is_present(FLAG_DEV) && triple.architecture != Architecture::X86_64 {
+        user_error!(
+            "`roc test --{FLAG_DEV}` only supports x86_64 for now, because the dev backend can't compile `expect`s for {} yet. Run `roc test` without `--{FLAG_DEV}` to use the LLVM backend.",
+            triple.architecture
+        );
+    }
     let opt_level = match (
         matches.is_present(FLAG_OPTIMIZE),
         matches.is_present(FLAG_OPT_SIZE),
@@ -404,6 +628,13 @@ pub fn test(matches: &ArgMatches, triple: Triple) -> io::Result<i32> {
                     DEFAULT_ROC_FILENAME
                 )
             }
+            _ if first_arg_is_filter => {
+                eprintln!(
+                    "\nNo `.roc` file was specified, and the current directory does not contain a {} file to use as a default.\n\n(`{}` was read as a filter, because it does not end in .roc and is not a file.)\n\nYou can run `roc help` for more information on how to provide a .roc file.\n",
+                    DEFAULT_ROC_FILENAME,
+                    first_arg.to_string_lossy()
+                )
+            }
             _ => eprintln!("\nThis file was not found: {}\n\nYou can run `roc help` for more information on how to provide a .roc file.\n", path_string),
         }
 
@@ -422,7 +653,10 @@ pub fn test(matches: &ArgMatches, triple: Triple) -> io::Result<i32> {
         render: roc_reporting::report::RenderTarget::ColorTerminal,
         palette: roc_reporting::report::DEFAULT_PALETTE,
         threading,
-        exec_mode: ExecutionMode::Test,
+        exec_mode: ExecutionMode::Test(TestOptions {
+            coverage: lcov_path.is_some(),
+            doc_tests: matches.is_present(FLAG_DOC),
+        }),
     };
     let load_result = roc_load::load_and_monomorphize(
         arena,
@@ -434,27 +668,86 @@ pub fn test(matches: &ArgMatches, triple: Triple) -> io::Result<i32> {
     let mut loaded = match load_result {
         Ok(loaded) => loaded,
         Err(LoadMonomorphizedError::LoadingProblem(problem)) => {
-            return handle_loading_problem(problem);
+            return handle_loading_problem(problem, MessageFormat::Human);
         }
         Err(LoadMonomorphizedError::ErrorModule(module)) => {
-            return handle_error_module(module, start_time.elapsed(), filename, false);
+            return handle_error_module(
+                module,
+                start_time.elapsed(),
+                filename,
+                false,
+                MessageFormat::Human,
+            );
         }
     };
-    let problems = report_problems_monomorphized(&mut loaded);
+    let problems = report_problems_monomorphized(&mut loaded, MessageFormat::Human);
+
+    let filter = TestFilter::new(
+        first_filter
+            .as_deref()
+            .into_iter()
+            .chain(matches.values_of(TEST_FILTERS).unwrap_or_default()),
+        matches.values_of(FLAG_EXCLUDE).unwrap_or_default(),
+    );
+    let selected = select_toplevel_expects(
+        &filter,
+        &loaded.interns,
+        &loaded.sources,
+        [&loaded.toplevel_expects.pure, &loaded.toplevel_expects.fx],
+    );
+
+    if matches.is_present(FLAG_LIST) {
+        for expect in selected.iter() {
+            println!(
+                "{}:{}  {}  {}",
+                expect.path.display(),
+                expect.lines.0,
+                expect.module_name,
+                expect.summary
+            );
+        }
+
+        return Ok(0);
+    }
 
     let mut expectations = std::mem::take(&mut loaded.expectations);
+    let coverage = loaded
+        .coverage
+        .take()
+        .map(|map| (map, std::mem::take(&mut loaded.sources)));
 
     let interns = loaded.interns.clone();
 
-    let (lib, expects, layout_interner) = roc_repl_expect::run::expect_mono_module_to_dylib(
-        arena,
-        target.clone(),
-        loaded,
-        opt_level,
-        LlvmBackendMode::CliTest,
-    )
+    // Like `roc build`, `--dev` uses the dev backend, which doesn't need LLVM
+    let (lib, mut expects, layout_interner) = if matches.is_present(FLAG_DEV) {
+        roc_repl_expect::run::expect_mono_module_to_dylib_dev(arena, target.clone(), loaded)
+    } else {
+        roc_repl_expect::run::expect_mono_module_to_dylib(
+            arena,
+            target.clone(),
+            loaded,
+            opt_level,
+            LlvmBackendMode::CliTest,
+        )
+    }
     .unwrap();
 
+    // Every child process that runs an `expect` adds its hits to these counters
+    let counters = coverage.as_ref().map(|(map, _)| {
+        let counters = CoverageCounters::new(map.counters.len());
+        counters.set_in(&lib);
+        counters
+    });
+
+    // The summary only counts the selected tests
+    let selected_symbols: MutSet<Symbol> = selected.iter().map(|expect| expect.symbol).collect();
+    expects
+        .pure
+        .retain(|expect| selected_symbols.contains(&expect.symbol));
+    expects
+        .fx
+        .retain(|expect| selected_symbols.contains(&expect.symbol));
+
     // Print warnings before running tests.
     {
         debug_assert_eq!(
@@ -473,7 +766,7 @@ pub fn test(matches: &ArgMatches, triple: Triple) -> io::Result<i32> {
 
     let mut writer = std::io::stdout();
 
-    let (failed, passed) = roc_repl_expect::run::run_toplevel_expects(
+    let results = roc_repl_expect::run::run_toplevel_expects(
         &mut writer,
         roc_reporting::report::RenderTarget::ColorTerminal,
         arena,
@@ -482,14 +775,73 @@ pub fn test(matches: &ArgMatches, triple: Triple) -> io::Result<i32> {
         &lib,
         &mut expectations,
         expects,
+        ExpectRunOptions {
+            threading,
+            timeout,
+            seed,
+        },
     )
     .unwrap();
 
     let total_time = start_time.elapsed();
 
+    let failed = results.iter().filter(|result| !result.passed).count();
+    let passed = results.len() - failed;
+
+    if let (Some((map, sources)), Some(counters), Some(lcov_path)) = (coverage, counters, lcov_path)
+    {
+        let modules = collect_coverage(&map, counters.counts(), interns, &sources);
+
+        if let Err(error) = write_lcov(&lcov_path, &modules) {
+            user_error!(
+                "Could not write the coverage to {}: {error}",
+                lcov_path.display()
+            );
+        }
+
+        print_summary(&modules, &lcov_path);
+    }
+
+    if !reports.is_empty() {
+        // Report the tests in source order, like `--list`
+        let results: MutMap<Symbol, _> = results
+            .iter()
+            .map(|result| (result.expect.symbol, result))
+            .collect();
+        let cases: Vec<TestCase> = selected
+            .iter()
+            .filter_map(|expect| {
+                let result = results.get(&expect.symbol)?;
+
+                Some(TestCase {
+                    module_name: expect.module_name.clone(),
+                    path: expect.path.clone(),
+                    lines: expect.lines,
+                    summary: expect.summary.clone(),
+                    passed: result.passed,
+                    duration: result.duration,
+                    output: strip_colors(&result.output),
+                })
+            })
+            .collect();
+
+        for report in reports.iter() {
+            if let Err(error) = report.write(&cases, total_time) {
+                user_error!(
+                    "Could not write the test report to {}: {error}",
+                    report.path.display()
+                );
+            }
+        }
+    }
+
     if failed == 0 && passed == 0 {
         // TODO print this in a more nicely formatted way!
-        println!("No expectations were found.");
+        if filter.is_empty() {
+            println!("No expectations were found.");
+        } else {
+            println!("No expectations matched the given filters.");
+        }
 
         // If no tests ran, treat that as an error. This is perhaps
         // briefly annoying at the very beginning of a project when
@@ -513,6 +865,14 @@ pub fn test(matches: &ArgMatches, triple: Triple) -> io::Result<i32> {
     }
 }
 
+/// The value of `--message-format`, for subcommands that have it
+pub fn message_format(matches: &ArgMatches) -> MessageFormat {
+    match matches.value_of(FLAG_MESSAGE_FORMAT) {
+        Some("json") => MessageFormat::Json,
+        _ => MessageFormat::Human,
+    }
+}
+
 pub fn build(
     matches: &ArgMatches,
     config: BuildConfig,
@@ -621,6 +981,39 @@ pub fn build(
     let emit_debug_info = matches.is_present(FLAG_DEBUG);
     let emit_timings = matches.is_present(FLAG_TIME);
 
+    // Only `roc run` has a --profile flag
+    let profile_path = match config {
+        BuildAndRun => matches.value_of(FLAG_PROFILE).map(PathBuf::from),
+        BuildOnly | BuildAndRunIfNoErrors => None,
+    };
+
+    if profile_path.is_some() && !matches!(code_gen_backend, CodeGenBackend::Llvm) {
+        user_error!("`--{FLAG_PROFILE}` is only supported by the LLVM backend, so it can't be combined with `--{FLAG_DEV}`");
+    }
+
+    // `roc build` doesn't run anything, so it has no --wasm-dir or --wasm-env flags
+    let wasi_access = match config {
+        BuildAndRun | BuildAndRunIfNoErrors => WasiAccess {
+            dirs: matches
+                .values_of(FLAG_WASM_DIR)
+                .unwrap_or_default()
+                .map(String::from)
+                .collect(),
+            env_vars: matches
+                .values_of(FLAG_WASM_ENV)
+                .unwrap_or_default()
+                .map(String::from)
+                .collect(),
+        },
+        BuildOnly => WasiAccess::default(),
+    };
+
+    // Only `roc build` has a --message-format flag
+    let message_format = match config {
+        BuildOnly => message_format(matches),
+        BuildAndRun | BuildAndRunIfNoErrors => MessageFormat::Human,
+    };
+
     let threading = match matches
         .value_of(FLAG_MAX_THREADS)
         .and_then(|s| s.parse::<usize>().ok())
@@ -669,9 +1062,11 @@ pub fn build(
         backend: code_gen_backend,
         opt_level,
         emit_debug_info,
+        profile: profile_path.is_some(),
     };
 
-    let load_config = standard_load_config(&triple, build_ordering, threading);
+    let mut load_config = standard_load_config(&triple, build_ordering, threading);
+    load_config.render = message_format.render_target();
 
     let res_binary_path = build_file(
         &arena,
@@ -685,6 +1080,7 @@ pub fn build(
         wasm_dev_stack_bytes,
         roc_cache_dir,
         load_config,
+        message_format,
     );
 
     match res_binary_path {
@@ -707,8 +1103,13 @@ pub fn build(
                     // since the process is about to exit anyway.
                     // std::mem::forget(arena);
 
-                    problems.print_to_stdout(total_time);
-                    println!(" while successfully building:\n\n    {generated_filename}");
+                    match message_format {
+                        MessageFormat::Human => {
+                            problems.print_to_stdout(total_time);
+                            println!(" while successfully building:\n\n    {generated_filename}");
+                        }
+                        MessageFormat::Json => problems.print_json_to_stdout(total_time),
+                    }
 
                     // Return a nonzero exit code if there were problems
                     Ok(problems.exit_code())
@@ -728,7 +1129,16 @@ pub fn build(
                     // ManuallyDrop will leak the bytes because we don't drop manually
                     let bytes = &ManuallyDrop::new(std::fs::read(&binary_path).unwrap());
 
-                    roc_run(&arena, opt_level, triple, args, bytes, expect_metadata)
+                    roc_run(
+                        &arena,
+                        opt_level,
+                        triple,
+                        args,
+                        bytes,
+                        expect_metadata,
+                        profile_path.as_deref(),
+                        &wasi_access,
+                    )
                 }
                 BuildAndRunIfNoErrors => {
                     debug_assert_eq!(
@@ -749,14 +1159,25 @@ pub fn build(
                     // ManuallyDrop will leak the bytes because we don't drop manually
                     let bytes = &ManuallyDrop::new(std::fs::read(&binary_path).unwrap());
 
-                    roc_run(&arena, opt_level, triple, args, bytes, expect_metadata)
+                    roc_run(
+                        &arena,
+                        opt_level,
+                        triple,
+                        args,
+                        bytes,
+                        expect_metadata,
+                        None,
+                        &wasi_access,
+                    )
                 }
             }
         }
         Err(BuildFileError::ErrorModule { module, total_time }) => {
-            handle_error_module(module, total_time, filename, true)
+            handle_error_module(module, total_time, filename, true, message_format)
+        }
+        Err(BuildFileError::LoadingProblem(problem)) => {
+            handle_loading_problem(problem, message_format)
         }
-        Err(BuildFileError::LoadingProblem(problem)) => handle_loading_problem(problem),
     }
 }
 
@@ -765,10 +1186,17 @@ fn handle_error_module(
     total_time: std::time::Duration,
     filename: &OsStr,
     print_run_anyway_hint: bool,
+    message_format: MessageFormat,
 ) -> io::Result<i32> {
     debug_assert!(module.total_problems() > 0);
 
-    let problems = roc_build::program::report_problems_typechecked(&mut module);
+    let problems = roc_build::program::report_problems_typechecked(&mut module, message_format);
+
+    if message_format == MessageFormat::Json {
+        problems.print_json_to_stdout(total_time);
+
+        return Ok(problems.exit_code());
+    }
 
     problems.print_to_stdout(total_time);
 
@@ -787,10 +1215,20 @@ fn handle_error_module(
     Ok(problems.exit_code())
 }
 
-fn handle_loading_problem(problem: LoadingProblem) -> io::Result<i32> {
+fn handle_loading_problem(
+    problem: LoadingProblem,
+    message_format: MessageFormat,
+) -> io::Result<i32> {
     match problem {
         LoadingProblem::FormattedReport(report) => {
-            print!("{}", report);
+            match message_format {
+                MessageFormat::Human => print!("{}", report),
+                MessageFormat::Json => print_json_formatted_report(&report),
+            }
+            Ok(1)
+        }
+        LoadingProblem::JsonDiagnostic(diagnostic) => {
+            println!("{}", diagnostic);
             Ok(1)
         }
         _ => {
@@ -802,6 +1240,7 @@ fn handle_loading_problem(problem: LoadingProblem) -> io::Result<i32> {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn roc_run<'a, I: IntoIterator<Item = &'a OsStr>>(
     arena: &Bump,
     opt_level: OptLevel,
@@ -809,6 +1248,8 @@ fn roc_run<'a, I: IntoIterator<Item = &'a OsStr>>(
     args: I,
     binary_bytes: &[u8],
     expect_metadata: ExpectMetadata,
+    profile_path: Option<&Path>,
+    wasi_access: &WasiAccess,
 ) -> io::Result<i32> {
     match triple.architecture {
         Architecture::Wasm32 => {
@@ -824,26 +1265,39 @@ fn roc_run<'a, I: IntoIterator<Item = &'a OsStr>>(
                 use std::os::unix::ffi::OsStrExt;
 
                 run_wasm(
+                    arena,
                     generated_filename,
                     args.into_iter().map(|os_str| os_str.as_bytes()),
+                    expect_metadata,
+                    wasi_access,
                 );
             }
 
             #[cfg(not(target_family = "unix"))]
             {
                 run_wasm(
+                    arena,
                     generated_filename,
                     args.into_iter().map(|os_str| {
                         os_str.to_str().expect(
                             "Roc does not currently support passing non-UTF8 arguments to Wasm.",
                         )
                     }),
+                    expect_metadata,
+                    wasi_access,
                 );
             }
 
             Ok(0)
         }
-        _ => roc_run_native(arena, opt_level, args, binary_bytes, expect_metadata),
+        _ => roc_run_native(
+            arena,
+            opt_level,
+            args,
+            binary_bytes,
+            expect_metadata,
+            profile_path,
+        ),
     }
 }
 
@@ -911,6 +1365,7 @@ fn roc_run_native<I: IntoIterator<Item = S>, S: AsRef<OsStr>>(
     args: I,
     binary_bytes: &[u8],
     expect_metadata: ExpectMetadata,
+    profile_path: Option<&Path>,
 ) -> std::io::Result<i32> {
     use bumpalo::collections::CollectIn;
 
@@ -930,6 +1385,16 @@ fn roc_run_native<I: IntoIterator<Item = S>, S: AsRef<OsStr>>(
             .chain([std::ptr::null()])
             .collect_in(arena);
 
+        if let Some(profile_path) = profile_path {
+            roc_profile_native(
+                executable,
+                argv,
+                envp,
+                &expect_metadata.interns,
+                profile_path,
+            );
+        }
+
         match opt_level {
             OptLevel::Development => roc_dev_native(arena, executable, argv, envp, expect_metadata),
             OptLevel::Normal | OptLevel::Size | OptLevel::Optimize => {
@@ -1092,6 +1557,69 @@ fn roc_dev_native(
     }
 }
 
+// with --profile
+#[cfg(target_family = "unix")]
+fn roc_profile_native(
+    executable: ExecutableFile,
+    argv: bumpalo::collections::Vec<*const c_char>,
+    mut envp: bumpalo::collections::Vec<*const c_char>,
+    interns: &roc_module::symbol::Interns,
+    profile_path: &Path,
+) -> ! {
+    let memory = profile::ProfileMemory::create();
+
+    // the child finds the shared memory through this variable; envp ends in a null pointer
+    let env_var = memory.env_var();
+    envp.insert(envp.len() - 1, env_var.as_ptr());
+
+    match unsafe { libc::fork() } {
+        0 => unsafe {
+            // we are the child
+
+            executable.execve(&argv, &envp);
+
+            // Display a human-friendly error message
+            println!("Error {:?}", std::io::Error::last_os_error());
+
+            std::process::exit(1);
+        },
+        -1 => {
+            // something failed
+
+            // Display a human-friendly error message
+            println!("Error {:?}", std::io::Error::last_os_error());
+
+            std::process::exit(1)
+        }
+        pid @ 1.. => {
+            let (status, samples) = memory.sample_until_exit(pid);
+            let stacks = profile::collect_stacks(&memory, &samples, interns);
+
+            // process::exit doesn't run destructors, and this unlinks the shared memory
+            drop(memory);
+
+            if let Err(error) = profile::write_folded(profile_path, &stacks) {
+                user_error!(
+                    "Unable to write the profile to {}: {}",
+                    profile_path.display(),
+                    error
+                );
+            }
+
+            profile::print_summary(&stacks, profile_path);
+
+            let exit_code = if libc::WIFEXITED(status) {
+                libc::WEXITSTATUS(status)
+            } else {
+                1
+            };
+
+            std::process::exit(exit_code)
+        }
+        _ => unreachable!(),
+    }
+}
+
 #[cfg(target_os = "linux")]
 fn roc_run_executable_file_path(binary_bytes: &[u8]) -> std::io::Result<ExecutableFile> {
     // on linux, we use the `memfd_create` function to create an in-memory anonymous file.
@@ -1173,6 +1701,7 @@ fn roc_run_native<I: IntoIterator<Item = S>, S: AsRef<OsStr>>(
     args: I,
     binary_bytes: &[u8],
     _expect_metadata: ExpectMetadata,
+    profile_path: Option<&Path>,
 ) -> io::Result<i32> {
     use bumpalo::collections::CollectIn;
 
@@ -1194,6 +1723,10 @@ fn roc_run_native<I: IntoIterator<Item = S>, S: AsRef<OsStr>>(
             .chain([std::ptr::null()])
             .collect_in(arena);
 
+        if profile_path.is_some() {
+            internal_error!("profiling does not currently work on windows")
+        }
+
         match opt_level {
             OptLevel::Development => {
                 // roc_run_native_debug(executable, &argv, &envp, expectations, interns)
@@ -1209,23 +1742,117 @@ fn roc_run_native<I: IntoIterator<Item = S>, S: AsRef<OsStr>>(
 }
 
 #[cfg(feature = "run-wasm32")]
-fn run_wasm<I: Iterator<Item = S>, S: AsRef<[u8]>>(wasm_path: &std::path::Path, args: I) {
+fn run_wasm<I: Iterator<Item = S>, S: AsRef<[u8]>>(
+    expect_arena: &Bump,
+    wasm_path: &std::path::Path,
+    args: I,
+    expect_metadata: ExpectMetadata,
+    wasi_access: &WasiAccess,
+) {
     use bumpalo::collections::Vec;
-    use roc_wasm_interp::{DefaultImportDispatcher, Instance};
+    use roc_collections::MutMap;
+    use roc_module::symbol::ModuleId;
+    use roc_repl_expect::wasm::{render_wasm_dbg, render_wasm_expect_failure, WasmExpectFrame};
+    use roc_reporting::report::RenderTarget;
+    use roc_wasm_interp::expect::{ENV_MODULE_NAME, ROC_DBG, ROC_EXPECT_FAILED};
+    use roc_wasm_interp::{DefaultImportDispatcher, ExpectReport, ExpectReportKind, Instance};
 
     let bytes = std::fs::read(wasm_path).unwrap();
     let arena = Bump::new();
 
+    let ExpectMetadata {
+        mut expectations,
+        interns,
+        layout_interner,
+    } = expect_metadata;
+    let layout_interner = layout_interner.into_global();
+
+    // Read the source of each module once, rather than once for every report
+    let sources: MutMap<ModuleId, &str> = expectations
+        .iter()
+        .map(|(module_id, data)| {
+            let source = std::fs::read_to_string(&data.path).unwrap_or_default();
+            (*module_id, &*expect_arena.alloc_str(&source))
+        })
+        .collect();
+    let has_dbgs = expectations.values().any(|data| !data.dbgs.is_empty());
+    let has_expects = expectations
+        .values()
+        .any(|data| !data.expectations.is_empty());
+
     let mut argv = Vec::<&[u8]>::new_in(&arena);
     for arg in args {
         let mut arg_copy = Vec::<u8>::new_in(&arena);
         arg_copy.extend_from_slice(arg.as_ref());
         argv.push(arg_copy.into_bump_slice());
     }
-    let import_dispatcher = DefaultImportDispatcher::new(&argv);
+    let mut import_dispatcher = DefaultImportDispatcher::new(&argv);
+    // The app only gets to see the parts of the host that it was explicitly given
+    import_dispatcher.wasi.env = wasi_access
+        .env_vars
+        .iter()
+        .filter_map(|name| {
+            let value = std::env::var(name).ok()?;
+            Some(format!("{}={}", name, value))
+        })
+        .collect();
+    for dir in wasi_access.dirs.iter() {
+        import_dispatcher.wasi.preopen_host_dir(dir, dir);
+    }
+
+    // Render `dbg` and failed `expect`s the same way as for native apps
+    import_dispatcher.expect_handler = Some(Box::new(|report: &ExpectReport, memory: &[u8]| {
+        let frame = WasmExpectFrame {
+            module_id: report.module_id,
+            region_start: report.region_start,
+            region_end: report.region_end,
+            lookups: &report.lookups,
+        };
+        let source = sources[&frame.module_id()];
+
+        let mut stdout = std::io::stdout();
+        match report.kind {
+            ExpectReportKind::Dbg => render_wasm_dbg(
+                &mut stdout,
+                RenderTarget::ColorTerminal,
+                expect_arena,
+                &mut expectations,
+                &interns,
+                &layout_interner,
+                source,
+                memory,
+                frame,
+            ),
+            ExpectReportKind::ExpectFailed => render_wasm_expect_failure(
+                &mut stdout,
+                RenderTarget::ColorTerminal,
+                expect_arena,
+                &mut expectations,
+                &interns,
+                &layout_interner,
+                source,
+                memory,
+                frame,
+            ),
+        }
+        .unwrap();
+    }));
 
     let mut instance = Instance::from_bytes(&arena, &bytes, import_dispatcher, false).unwrap();
 
+    // The wasm backend skips `dbg` and `expect` if the platform's host has no way to report them
+    for (is_used, import, what) in [
+        (has_dbgs, ROC_DBG, "`dbg`s"),
+        (has_expects, ROC_EXPECT_FAILED, "`expect`s"),
+    ] {
+        if is_used && !instance.imports_function(ENV_MODULE_NAME, import) {
+            eprintln!(
+                "Note: the platform's host doesn't import `{}`, so the {} in this app were skipped.",
+                import, what
+            );
+        }
+    }
+
     instance
         .call_export("_start", [])
         .unwrap()
@@ -1235,7 +1862,13 @@ fn run_wasm<I: Iterator<Item = S>, S: AsRef<[u8]>>(wasm_path: &std::path::Path,
 }
 
 #[cfg(not(feature = "run-wasm32"))]
-fn run_wasm<I: Iterator<Item = S>, S: AsRef<[u8]>>(_wasm_path: &std::path::Path, _args: I) {
+fn run_wasm<I: Iterator<Item = S>, S: AsRef<[u8]>>(
+    _expect_arena: &Bump,
+    _wasm_path: &std::path::Path,
+    _args: I,
+    _expect_metadata: ExpectMetadata,
+    _wasi_access: &WasiAccess,
+) {
     println!("Running wasm files is not supported on this target.");
 }
 
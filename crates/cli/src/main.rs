@@ -3,14 +3,15 @@ use roc_build::link::LinkType;
 use roc_cli::build::check_file;
 use roc_cli::{
     build_app, format, test, BuildConfig, FormatMode, Target, CMD_BUILD, CMD_CHECK, CMD_DEV,
-    CMD_DOCS, CMD_EDIT, CMD_FORMAT, CMD_GEN_STUB_LIB, CMD_GLUE, CMD_REPL, CMD_RUN, CMD_TEST,
-    CMD_VERSION, DIRECTORY_OR_FILES, FLAG_CHECK, FLAG_LIB, FLAG_NO_LINK, FLAG_TARGET, FLAG_TIME,
-    GLUE_FILE, ROC_FILE,
+    CMD_DOCS, CMD_EDIT, CMD_FORMAT, CMD_GEN_STUB_LIB, CMD_GLUE, CMD_LSP, CMD_REPL, CMD_RUN,
+    CMD_TEST, CMD_VERSION, DIRECTORY_OR_FILES, FLAG_CHECK, FLAG_LIB, FLAG_NO_LINK, FLAG_OUTPUT,
+    FLAG_PLATFORM, FLAG_TARGET, FLAG_TIME, FLAG_WATCH, GLUE_DIR, GLUE_FILE, ROC_FILE,
 };
 use roc_docs::generate_docs_html;
 use roc_error_macros::user_error;
 use roc_load::{LoadingProblem, Threading};
 use roc_packaging::cache::{self, RocCacheDir};
+use roc_reporting::cli::{print_json_formatted_report, MessageFormat};
 use std::fs::{self, FileType};
 use std::io;
 use std::path::{Path, PathBuf};
@@ -87,15 +88,34 @@ fn main() -> io::Result<()> {
             }
         }
         Some((CMD_GLUE, matches)) => {
-            let input_path = Path::new(matches.value_of_os(ROC_FILE).unwrap());
-            let output_path = Path::new(matches.value_of_os(GLUE_FILE).unwrap());
-
-            if Some("rs") == output_path.extension().and_then(OsStr::to_str) {
-                roc_glue::generate(input_path, output_path)
-            } else {
-                eprintln!("Currently, `roc glue` only supports generating Rust glue files (with the .rs extension). In the future, the plan is to decouple `roc glue` from any particular output format, by having it accept a second .roc file which gets executed as a plugin to generate glue code for any desired language. However, this has not yet been implemented, and for now only .rs is supported.");
-
-                Ok(1)
+            let first_path = Path::new(matches.value_of_os(ROC_FILE).unwrap());
+            let second_path = Path::new(matches.value_of_os(GLUE_FILE).unwrap());
+
+            match matches.value_of_os(GLUE_DIR) {
+                Some(output_dir)
+                    if first_path.extension().and_then(OsStr::to_str) == Some("roc") =>
+                {
+                    // roc glue MyGlue.roc platform/main.roc out/
+                    roc_glue::plugin::generate(first_path, second_path, Path::new(output_dir))
+                }
+                Some(_) => {
+                    user_error!(
+                        "The glue script {} should be a .roc file, given before the platform module:\n\n    roc glue path/to/MyGlue.roc path/to/platform/main.roc path/to/output/dir/",
+                        first_path.display(),
+                    );
+                }
+                None if second_path.extension().and_then(OsStr::to_str) == Some("roc") => {
+                    user_error!(
+                        "To generate glue with the script {}, also give the directory to write its files into:\n\n    roc glue {} {} path/to/output/dir/",
+                        first_path.display(),
+                        first_path.display(),
+                        second_path.display(),
+                    );
+                }
+                None => {
+                    // roc glue platform/main.roc glue.rs
+                    roc_glue::generate(first_path, second_path)
+                }
             }
         }
         Some((CMD_GEN_STUB_LIB, matches)) => {
@@ -143,55 +163,82 @@ fn main() -> io::Result<()> {
                 Some(n) => Threading::AtMost(n),
             };
 
-            match check_file(
-                &arena,
-                roc_file_path,
-                emit_timings,
-                RocCacheDir::Persistent(cache::roc_cache_dir().as_path()),
-                threading,
-            ) {
-                Ok((problems, total_time)) => {
-                    println!(
-                        "\x1B[{}m{}\x1B[39m {} and \x1B[{}m{}\x1B[39m {} found in {} ms.",
-                        if problems.errors == 0 {
-                            32 // green
-                        } else {
-                            33 // yellow
-                        },
-                        problems.errors,
-                        if problems.errors == 1 {
-                            "error"
-                        } else {
-                            "errors"
-                        },
-                        if problems.warnings == 0 {
-                            32 // green
-                        } else {
-                            33 // yellow
-                        },
-                        problems.warnings,
-                        if problems.warnings == 1 {
-                            "warning"
-                        } else {
-                            "warnings"
-                        },
-                        total_time.as_millis(),
-                    );
+            if matches.is_present(FLAG_WATCH) {
+                roc_cli::watch::check_watch(
+                    roc_file_path,
+                    emit_timings,
+                    RocCacheDir::Persistent(cache::roc_cache_dir().as_path()),
+                    threading,
+                )
+            } else {
+                let message_format = roc_cli::message_format(matches);
 
-                    Ok(problems.exit_code())
-                }
+                match check_file(
+                    &arena,
+                    roc_file_path,
+                    emit_timings,
+                    RocCacheDir::Persistent(cache::roc_cache_dir().as_path()),
+                    threading,
+                    message_format,
+                ) {
+                    Ok((problems, total_time)) if message_format == MessageFormat::Json => {
+                        problems.print_json_to_stdout(total_time);
 
-                Err(LoadingProblem::FormattedReport(report)) => {
-                    print!("{}", report);
+                        Ok(problems.exit_code())
+                    }
+                    Ok((problems, total_time)) => {
+                        println!(
+                            "\x1B[{}m{}\x1B[39m {} and \x1B[{}m{}\x1B[39m {} found in {} ms.",
+                            if problems.errors == 0 {
+                                32 // green
+                            } else {
+                                33 // yellow
+                            },
+                            problems.errors,
+                            if problems.errors == 1 {
+                                "error"
+                            } else {
+                                "errors"
+                            },
+                            if problems.warnings == 0 {
+                                32 // green
+                            } else {
+                                33 // yellow
+                            },
+                            problems.warnings,
+                            if problems.warnings == 1 {
+                                "warning"
+                            } else {
+                                "warnings"
+                            },
+                            total_time.as_millis(),
+                        );
+
+                        Ok(problems.exit_code())
+                    }
 
-                    Ok(1)
-                }
-                Err(other) => {
-                    panic!("build_file failed with error:\n{:?}", other);
+                    Err(LoadingProblem::FormattedReport(report)) => {
+                        match message_format {
+                            MessageFormat::Human => print!("{}", report),
+                            MessageFormat::Json => print_json_formatted_report(&report),
+                        }
+
+                        Ok(1)
+                    }
+                    Err(LoadingProblem::JsonDiagnostic(diagnostic)) => {
+                        println!("{}", diagnostic);
+
+                        Ok(1)
+                    }
+                    Err(other) => {
+                        panic!("build_file failed with error:\n{:?}", other);
+                    }
                 }
             }
         }
-        Some((CMD_REPL, _)) => Ok(roc_repl_cli::main()),
+        Some((CMD_REPL, matches)) => Ok(roc_repl_cli::main(
+            matches.value_of_os(FLAG_PLATFORM).map(Path::new),
+        )),
         Some((CMD_EDIT, matches)) => {
             match matches
                 .values_of_os(DIRECTORY_OR_FILES)
@@ -210,11 +257,15 @@ fn main() -> io::Result<()> {
         }
         Some((CMD_DOCS, matches)) => {
             let root_filename = matches.value_of_os(ROC_FILE).unwrap();
+            let output_dir = matches.value_of_os(FLAG_OUTPUT).unwrap();
 
-            generate_docs_html(PathBuf::from(root_filename));
+            generate_docs_html(PathBuf::from(root_filename), Path::new(output_dir));
 
             Ok(0)
         }
+        Some((CMD_LSP, _)) => {
+            roc_lang_srv::run(RocCacheDir::Persistent(cache::roc_cache_dir().as_path()))
+        }
         Some((CMD_FORMAT, matches)) => {
             let maybe_values = matches.values_of_os(DIRECTORY_OR_FILES);
 
@@ -322,3 +373,36 @@ fn launch_editor(project_dir_path: Option<&Path>) -> io::Result<()> {
 fn launch_editor(_project_dir_path: Option<&Path>) -> io::Result<()> {
     panic!("Cannot launch the editor because this build of roc did not include `feature = \"editor\"`!");
 }
+
+// The glue plugin passes roc_std values to glue scripts, so these end up in the roc binary.
+// On Windows, roc_cli already defines them for its own sake.
+#[cfg(not(windows))]
+mod roc_platform_functions {
+    use core::ffi::c_void;
+
+    /// # Safety
+    /// roc_std needs this.
+    #[no_mangle]
+    pub unsafe extern "C" fn roc_alloc(size: usize, _alignment: u32) -> *mut c_void {
+        libc::malloc(size)
+    }
+
+    /// # Safety
+    /// roc_std needs this.
+    #[no_mangle]
+    pub unsafe extern "C" fn roc_realloc(
+        c_ptr: *mut c_void,
+        new_size: usize,
+        _old_size: usize,
+        _alignment: u32,
+    ) -> *mut c_void {
+        libc::realloc(c_ptr, new_size)
+    }
+
+    /// # Safety
+    /// roc_std needs this.
+    #[no_mangle]
+    pub unsafe extern "C" fn roc_dealloc(c_ptr: *mut c_void, _alignment: u32) {
+        libc::free(c_ptr)
+    }
+}
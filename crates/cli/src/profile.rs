@@ -0,0 +1,307 @@
+//! Profile of a `roc run --profile` run.
+//!
+//! A program that is built for profiling keeps a calling context tree in shared memory (see
+//! `profile.zig` in the builtins): every node is a function called from the stack of its
+//! ancestors, with the allocations it made. While the program runs, we periodically sample the
+//! node it is in. The samples and the allocated bytes are then written per stack as folded stacks,
+//! which flamegraph tools like `inferno-flamegraph`, `flamegraph.pl` and speedscope can read.
+
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::Duration;
+
+use roc_error_macros::internal_error;
+use roc_module::symbol::{Interns, Symbol};
+
+/// Must match `MAX_NODES` in `profile.zig`
+const MAX_NODES: usize = 1 << 16;
+/// Must match `TABLE_SIZE` in `profile.zig`
+const TABLE_SIZE: usize = 2 * MAX_NODES;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Must match `BUFFER_ENV_VAR` in `profile.zig`
+const BUFFER_ENV_VAR: &str = "ROC_PROFILE_BUFFER";
+
+/// The time that the program spends outside of Roc functions, like before `main` is called
+const HOST_NAME: &str = "[host]";
+
+/// Must match `Node` in `profile.zig`
+#[repr(C)]
+struct Node {
+    parent: u32,
+    _padding: u32,
+    function: u64,
+    allocations: u64,
+    allocated_bytes: u64,
+    deallocations: u64,
+}
+
+/// Must match `Profile` in `profile.zig`
+#[repr(C)]
+struct Profile {
+    current: AtomicU32,
+    node_count: u32,
+    nodes: [Node; MAX_NODES],
+    _table: [u32; TABLE_SIZE],
+}
+
+/// The shared memory that the profiled program writes its calling context tree to.
+/// The program finds it by its name in the environment, see [ProfileMemory::env_var].
+pub struct ProfileMemory {
+    ptr: *mut Profile,
+    shm_name: CString,
+}
+
+impl ProfileMemory {
+    pub fn create() -> Self {
+        // IMPORTANT: shared memory object names must begin with / and contain no other slashes!
+        let shm_name = CString::new(format!("/roc_profile_buffer_{}", std::process::id())).unwrap();
+        let size = std::mem::size_of::<Profile>();
+
+        let ptr = unsafe {
+            let shared_fd = libc::shm_open(shm_name.as_ptr(), libc::O_RDWR | libc::O_CREAT, 0o666);
+            if shared_fd == -1 {
+                internal_error!("failed to shm_open the profile: {:?}", errno::errno());
+            }
+
+            // truncating to 0 first clears anything that an earlier process with our id left behind
+            if libc::ftruncate(shared_fd, 0) == -1 || libc::ftruncate(shared_fd, size as _) == -1 {
+                internal_error!("failed to truncate the profile: {:?}", errno::errno());
+            }
+
+            let ptr = libc::mmap(
+                std::ptr::null_mut(),
+                size,
+                libc::PROT_WRITE | libc::PROT_READ,
+                libc::MAP_SHARED,
+                shared_fd,
+                0,
+            );
+
+            libc::close(shared_fd);
+
+            if ptr == libc::MAP_FAILED {
+                internal_error!("failed to mmap the profile: {:?}", errno::errno());
+            }
+
+            ptr
+        };
+
+        Self {
+            ptr: ptr.cast(),
+            shm_name,
+        }
+    }
+
+    /// The `NAME=value` environment entry that tells the profiled program where this memory is
+    pub fn env_var(&self) -> CString {
+        let name = self.shm_name.to_str().unwrap();
+
+        CString::new(format!("{BUFFER_ENV_VAR}={name}")).unwrap()
+    }
+
+    fn profile(&self) -> &Profile {
+        unsafe { &*self.ptr }
+    }
+
+    /// Sample the function that the child process `pid` is in, until it exits.
+    /// Returns the wait status of the child, and the number of samples by node.
+    pub fn sample_until_exit(&self, pid: libc::pid_t) -> (libc::c_int, Vec<u64>) {
+        let profile = self.profile();
+        let exited = AtomicBool::new(false);
+
+        std::thread::scope(|scope| {
+            let sampler = scope.spawn(|| {
+                let mut samples = vec![0; MAX_NODES];
+
+                while !exited.load(Ordering::Relaxed) {
+                    let current = profile.current.load(Ordering::Acquire) as usize;
+
+                    if let Some(count) = samples.get_mut(current) {
+                        *count += 1;
+                    }
+
+                    std::thread::sleep(SAMPLE_INTERVAL);
+                }
+
+                samples
+            });
+
+            let mut status = 0;
+            unsafe { libc::waitpid(pid, &mut status, 0) };
+
+            exited.store(true, Ordering::Relaxed);
+
+            (status, sampler.join().unwrap())
+        })
+    }
+}
+
+impl Drop for ProfileMemory {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr.cast(), std::mem::size_of::<Profile>());
+            libc::shm_unlink(self.shm_name.as_ptr());
+        }
+    }
+}
+
+/// A node of the calling context tree, with its stack written out
+#[derive(Debug)]
+pub struct StackProfile {
+    /// The functions on the stack, from the outermost, separated by `;`
+    stack: String,
+    samples: u64,
+    allocations: u64,
+    allocated_bytes: u64,
+    deallocations: u64,
+}
+
+impl StackProfile {
+    /// The innermost function of the stack
+    fn function(&self) -> &str {
+        match self.stack.rsplit_once(';') {
+            Some((_, function)) => function,
+            None => &self.stack,
+        }
+    }
+}
+
+/// Pair up the nodes of a finished run with their samples, in the order of the tree
+pub fn collect_stacks(
+    memory: &ProfileMemory,
+    samples: &[u64],
+    interns: &Interns,
+) -> Vec<StackProfile> {
+    let profile = memory.profile();
+    let node_count = (profile.node_count as usize).clamp(1, MAX_NODES);
+
+    let mut stacks: Vec<StackProfile> = Vec::with_capacity(node_count);
+
+    for (index, node) in profile.nodes[..node_count].iter().enumerate() {
+        let stack = if index == 0 {
+            HOST_NAME.to_string()
+        } else {
+            let name = function_name(node.function, interns);
+
+            // parents are always added to the tree before their children, and the root is not
+            // part of the stacks of Roc functions
+            match stacks.get(node.parent as usize) {
+                Some(parent) if node.parent != 0 => format!("{};{}", parent.stack, name),
+                _ => name,
+            }
+        };
+
+        stacks.push(StackProfile {
+            stack,
+            samples: samples[index],
+            allocations: node.allocations,
+            allocated_bytes: node.allocated_bytes,
+            deallocations: node.deallocations,
+        });
+    }
+
+    stacks
+}
+
+fn function_name(function: u64, interns: &Interns) -> String {
+    let symbol = match Symbol::from_u64(function) {
+        Some(symbol) => symbol,
+        None => return format!("<unknown {function:x}>"),
+    };
+
+    let module_name = interns.module_ids.get_name(symbol.module_id());
+    let ident = interns
+        .all_ident_ids
+        .get(&symbol.module_id())
+        .and_then(|ident_ids| ident_ids.get_name(symbol.ident_id()));
+
+    match (module_name, ident) {
+        (Some(module_name), Some(ident)) => format!("{}.{}", module_name.as_str(), ident),
+        _ => format!("{:?}", symbol),
+    }
+}
+
+/// Where the allocations are written, next to the samples at `path`
+pub fn allocations_path(path: &Path) -> PathBuf {
+    path.with_extension("alloc.folded")
+}
+
+/// Write the samples, and the allocated bytes, per stack as folded stacks
+pub fn write_folded(path: &Path, stacks: &[StackProfile]) -> io::Result<()> {
+    write_folded_help(path, stacks, |stack| stack.samples)?;
+    write_folded_help(&allocations_path(path), stacks, |stack| {
+        stack.allocated_bytes
+    })
+}
+
+fn write_folded_help(
+    path: &Path,
+    stacks: &[StackProfile],
+    count: impl Fn(&StackProfile) -> u64,
+) -> io::Result<()> {
+    let mut counts: BTreeMap<&str, u64> = BTreeMap::new();
+
+    for stack in stacks {
+        let count = count(stack);
+
+        if count > 0 {
+            *counts.entry(stack.stack.as_str()).or_default() += count;
+        }
+    }
+
+    let mut file = io::BufWriter::new(std::fs::File::create(path)?);
+
+    for (stack, count) in counts {
+        writeln!(file, "{} {}", stack, count)?;
+    }
+
+    file.flush()
+}
+
+/// Print the functions that most of the samples were in, and how much was allocated
+pub fn print_summary(stacks: &[StackProfile], path: &Path) {
+    const TOP_FUNCTIONS: usize = 10;
+
+    let total_samples: u64 = stacks.iter().map(|stack| stack.samples).sum();
+
+    let mut functions: BTreeMap<&str, u64> = BTreeMap::new();
+    for stack in stacks {
+        *functions.entry(stack.function()).or_default() += stack.samples;
+    }
+
+    let mut functions: Vec<(&str, u64)> = functions.into_iter().collect();
+    functions.sort_by(|a, b| b.1.cmp(&a.1));
+
+    eprintln!("\nProfile of {total_samples} samples:\n");
+
+    for (function, samples) in functions.iter().take(TOP_FUNCTIONS) {
+        if *samples == 0 {
+            break;
+        }
+
+        eprintln!(
+            "    {:>5.1}%  {}",
+            *samples as f64 * 100.0 / total_samples as f64,
+            function
+        );
+    }
+
+    let allocations: u64 = stacks.iter().map(|stack| stack.allocations).sum();
+    let allocated_bytes: u64 = stacks.iter().map(|stack| stack.allocated_bytes).sum();
+    let deallocations: u64 = stacks.iter().map(|stack| stack.deallocations).sum();
+
+    eprintln!(
+        "\nAllocations: {allocations} ({allocated_bytes} bytes), deallocations: {deallocations}"
+    );
+
+    eprintln!(
+        "\nWrote the samples to {} and the allocated bytes to {}",
+        path.display(),
+        allocations_path(path).display()
+    );
+}
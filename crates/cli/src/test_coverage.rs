@@ -0,0 +1,226 @@
+//! Coverage of a `roc test --coverage` run.
+//!
+//! The counts are written as an lcov tracefile, which most coverage tools can read, and
+//! summarized per module in the terminal. Top-level defs are lcov functions, and each `when`
+//! or `if` is an lcov block with one branch per `when` branch or `if` arm.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use roc_collections::MutMap;
+use roc_module::symbol::{Interns, ModuleId};
+use roc_mono::coverage::{CoverageKind, CoverageMap};
+use roc_region::all::{LineInfo, Region};
+
+#[derive(Debug)]
+pub struct ModuleCoverage {
+    module_name: String,
+    path: PathBuf,
+    defs: Vec<DefCoverage>,
+    branches: Vec<BranchCoverage>,
+    /// The highest count of anything that starts on a line, by line
+    lines: BTreeMap<u32, u64>,
+}
+
+#[derive(Debug)]
+struct DefCoverage {
+    name: String,
+    line: u32,
+    count: u64,
+}
+
+#[derive(Debug)]
+struct BranchCoverage {
+    line: u32,
+    /// The `when` or `if` that this branch belongs to, numbered from 0 within the module
+    block: usize,
+    /// The number of the branch within its block
+    branch: usize,
+    count: u64,
+}
+
+impl ModuleCoverage {
+    fn defs_hit(&self) -> usize {
+        self.defs.iter().filter(|def| def.count > 0).count()
+    }
+
+    fn branches_hit(&self) -> usize {
+        self.branches
+            .iter()
+            .filter(|branch| branch.count > 0)
+            .count()
+    }
+
+    fn lines_hit(&self) -> usize {
+        self.lines.values().filter(|count| **count > 0).count()
+    }
+
+    /// Whether the `when` or `if` that a branch belongs to ran at all
+    fn block_ran(&self, block: usize) -> bool {
+        self.branches
+            .iter()
+            .any(|branch| branch.block == block && branch.count > 0)
+    }
+}
+
+/// Match up the counters with the counts of a run, per module, in the order of their paths
+pub fn collect_coverage(
+    map: &CoverageMap,
+    counts: &[u64],
+    interns: &Interns,
+    sources: &MutMap<ModuleId, (PathBuf, Box<str>)>,
+) -> Vec<ModuleCoverage> {
+    let mut modules: MutMap<ModuleId, (ModuleCoverage, LineInfo)> = MutMap::default();
+    let mut blocks: MutMap<(ModuleId, Region), usize> = MutMap::default();
+    let mut branches_per_block: MutMap<(ModuleId, usize), usize> = MutMap::default();
+
+    for (counter, count) in map.counters.iter().zip(counts.iter().copied()) {
+        let (path, source) = match sources.get(&counter.module_id) {
+            Some(source) => source,
+            None => continue,
+        };
+
+        let (module, line_info) = modules.entry(counter.module_id).or_insert_with(|| {
+            let module = ModuleCoverage {
+                module_name: interns.module_name(counter.module_id).as_str().to_string(),
+                path: path.clone(),
+                defs: Vec::new(),
+                branches: Vec::new(),
+                lines: BTreeMap::new(),
+            };
+
+            (module, LineInfo::new(source))
+        });
+
+        let line = line_info.convert_pos(counter.region.start()).line + 1;
+
+        let line_count = module.lines.entry(line).or_default();
+        *line_count = Ord::max(*line_count, count);
+
+        let block_region = match counter.kind {
+            CoverageKind::Def(symbol) => {
+                module.defs.push(DefCoverage {
+                    name: symbol.as_str(interns).to_string(),
+                    line,
+                    count,
+                });
+
+                continue;
+            }
+            CoverageKind::WhenBranch { when_region } => when_region,
+            CoverageKind::IfArm { if_region } => if_region,
+        };
+
+        let next_block = blocks
+            .keys()
+            .filter(|(module_id, _)| *module_id == counter.module_id)
+            .count();
+        let block = *blocks
+            .entry((counter.module_id, block_region))
+            .or_insert(next_block);
+
+        let branch = branches_per_block
+            .entry((counter.module_id, block))
+            .or_default();
+
+        module.branches.push(BranchCoverage {
+            line,
+            block,
+            branch: *branch,
+            count,
+        });
+
+        *branch += 1;
+    }
+
+    let mut modules: Vec<ModuleCoverage> =
+        modules.into_values().map(|(module, _)| module).collect();
+    modules.sort_by(|a, b| a.path.cmp(&b.path));
+
+    modules
+}
+
+/// Write an lcov tracefile, with one record per module
+pub fn write_lcov(path: &Path, modules: &[ModuleCoverage]) -> io::Result<()> {
+    let mut file = io::BufWriter::new(std::fs::File::create(path)?);
+
+    for module in modules {
+        write_record(&mut file, module)?;
+    }
+
+    file.flush()
+}
+
+fn write_record(writer: &mut impl Write, module: &ModuleCoverage) -> io::Result<()> {
+    writeln!(writer, "TN:")?;
+    writeln!(writer, "SF:{}", module.path.to_string_lossy())?;
+
+    for def in module.defs.iter() {
+        writeln!(writer, "FN:{},{}", def.line, def.name)?;
+    }
+
+    for def in module.defs.iter() {
+        writeln!(writer, "FNDA:{},{}", def.count, def.name)?;
+    }
+
+    writeln!(writer, "FNF:{}", module.defs.len())?;
+    writeln!(writer, "FNH:{}", module.defs_hit())?;
+
+    for branch in module.branches.iter() {
+        // lcov uses `-` for the branches of a block that never ran
+        let taken = if module.block_ran(branch.block) {
+            branch.count.to_string()
+        } else {
+            "-".to_string()
+        };
+
+        writeln!(
+            writer,
+            "BRDA:{},{},{},{}",
+            branch.line, branch.block, branch.branch, taken
+        )?;
+    }
+
+    writeln!(writer, "BRF:{}", module.branches.len())?;
+    writeln!(writer, "BRH:{}", module.branches_hit())?;
+
+    for (line, count) in module.lines.iter() {
+        writeln!(writer, "DA:{},{}", line, count)?;
+    }
+
+    writeln!(writer, "LF:{}", module.lines.len())?;
+    writeln!(writer, "LH:{}", module.lines_hit())?;
+
+    writeln!(writer, "end_of_record")
+}
+
+/// Print how many defs and branches of each module the tests ran
+pub fn print_summary(modules: &[ModuleCoverage], lcov_path: &Path) {
+    println!("\nCoverage:\n");
+
+    let name_width = modules
+        .iter()
+        .map(|module| module.module_name.len())
+        .max()
+        .unwrap_or_default();
+
+    for module in modules {
+        println!(
+            "    {:name_width$}  defs {:<13}  branches {}",
+            module.module_name,
+            fraction(module.defs_hit(), module.defs.len()),
+            fraction(module.branches_hit(), module.branches.len()),
+        );
+    }
+
+    println!("\nWrote the coverage to {}", lcov_path.display());
+}
+
+fn fraction(hit: usize, total: usize) -> String {
+    if total == 0 {
+        "-".to_string()
+    } else {
+        format!("{hit}/{total} ({}%)", hit * 100 / total)
+    }
+}
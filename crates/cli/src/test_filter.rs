@@ -0,0 +1,228 @@
+//! Selecting which top-level `expect`s `roc test` runs.
+//!
+//! A pattern matches an `expect` if it is a substring of the module name, the file name,
+//! or the `expect`'s source code. A pattern like `Parser:12` or `Parser.roc:12` matches
+//! the `expect`s in that module (or file) whose region includes line 12.
+
+use std::path::{Path, PathBuf};
+
+use roc_collections::{MutMap, VecMap};
+use roc_module::symbol::{Interns, ModuleId, Symbol};
+use roc_region::all::{LineInfo, Region};
+
+#[derive(Debug, Default)]
+pub struct TestFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+#[derive(Debug)]
+enum Pattern {
+    Text(String),
+    Line { name: String, line: u32 },
+}
+
+/// What a pattern can match against for one top-level `expect`
+struct ExpectSource<'a> {
+    module_name: &'a str,
+    path: &'a Path,
+    /// The source code of the whole `expect`
+    text: &'a str,
+    /// The first and last lines of the `expect`, starting from 1
+    lines: (u32, u32),
+}
+
+impl ExpectSource<'_> {
+    fn file_name(&self) -> std::borrow::Cow<str> {
+        self.path.file_name().unwrap_or_default().to_string_lossy()
+    }
+}
+
+impl Pattern {
+    fn parse(pattern: &str) -> Self {
+        match pattern.rsplit_once(':') {
+            Some((name, line)) if is_module_or_file_name(name) => match line.parse() {
+                Ok(line) => Pattern::Line {
+                    name: name.to_string(),
+                    line,
+                },
+                Err(_) => Pattern::Text(pattern.to_string()),
+            },
+            _ => Pattern::Text(pattern.to_string()),
+        }
+    }
+
+    fn matches(&self, expect: &ExpectSource) -> bool {
+        match self {
+            Pattern::Text(text) => {
+                expect.module_name.contains(text.as_str())
+                    || expect.file_name().contains(text.as_str())
+                    || expect.text.contains(text.as_str())
+            }
+            Pattern::Line { name, line } => {
+                let (first, last) = expect.lines;
+
+                (first..=last).contains(line)
+                    && (expect.module_name == name || expect.file_name() == *name)
+            }
+        }
+    }
+}
+
+/// Only `Parser:12` and `Parser.roc:12` are line patterns, so that a pattern like `{ x:1 }` still
+/// matches the source code of an `expect`
+fn is_module_or_file_name(name: &str) -> bool {
+    let module_name = name.strip_suffix(".roc").unwrap_or(name);
+
+    !module_name.is_empty()
+        && module_name.split('.').all(|part| {
+            part.starts_with(|c: char| c.is_ascii_uppercase())
+                && part.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+impl TestFilter {
+    pub fn new<'p>(
+        include: impl IntoIterator<Item = &'p str>,
+        exclude: impl IntoIterator<Item = &'p str>,
+    ) -> Self {
+        TestFilter {
+            include: include.into_iter().map(Pattern::parse).collect(),
+            exclude: exclude.into_iter().map(Pattern::parse).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// With no include patterns, everything that isn't excluded is selected
+    fn matches(&self, expect: &ExpectSource) -> bool {
+        let included =
+            self.include.is_empty() || self.include.iter().any(|pattern| pattern.matches(expect));
+
+        included && !self.exclude.iter().any(|pattern| pattern.matches(expect))
+    }
+}
+
+/// A top-level `expect` that was selected, for `roc test --list`
+pub struct SelectedExpect {
+    pub symbol: Symbol,
+    pub module_name: String,
+    pub path: PathBuf,
+    /// The first and last lines of the `expect`, starting from 1
+    pub lines: (u32, u32),
+    /// The `expect`'s source code on a single line, for `roc test --list`
+    pub summary: String,
+}
+
+/// Apply a filter to the top-level `expect`s of every module, in source order
+pub fn select_toplevel_expects<'r>(
+    filter: &TestFilter,
+    interns: &Interns,
+    sources: &MutMap<ModuleId, (PathBuf, Box<str>)>,
+    expects: impl IntoIterator<Item = &'r VecMap<Symbol, Region>>,
+) -> Vec<SelectedExpect> {
+    let mut line_infos = MutMap::default();
+    let mut selected = Vec::new();
+
+    for (symbol, region) in expects.into_iter().flat_map(|expects| expects.iter()) {
+        let module_id = symbol.module_id();
+        let (path, source) = &sources[&module_id];
+        let line_info = line_infos
+            .entry(module_id)
+            .or_insert_with(|| LineInfo::new(source));
+        let line_col_region = line_info.convert_region(*region);
+
+        let text = &source[region.start().offset as usize..region.end().offset as usize];
+        let module_name = interns.module_name(module_id).as_str();
+
+        let expect = ExpectSource {
+            module_name,
+            path,
+            text,
+            lines: (line_col_region.start.line + 1, line_col_region.end.line + 1),
+        };
+
+        if filter.matches(&expect) {
+            selected.push(SelectedExpect {
+                symbol: *symbol,
+                module_name: module_name.to_string(),
+                path: path.clone(),
+                lines: expect.lines,
+                summary: summarize(text),
+            });
+        }
+    }
+
+    selected.sort_by(|a, b| (&a.path, a.lines).cmp(&(&b.path, b.lines)));
+    selected
+}
+
+fn summarize(expect_text: &str) -> String {
+    const MAX_CHARS: usize = 60;
+
+    // the `##`s of doc tests are not part of the code, and examples don't start with `expect`
+    let mut words: Vec<&str> = expect_text
+        .split_whitespace()
+        .filter(|word| *word != "##")
+        .collect();
+
+    if words.first() == Some(&"expect") {
+        words.remove(0);
+    }
+
+    let summary = words.join(" ");
+
+    if summary.chars().count() > MAX_CHARS {
+        let truncated: String = summary.chars().take(MAX_CHARS - 1).collect();
+        format!("{truncated}…")
+    } else {
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expect_source<'a>(text: &'a str, lines: (u32, u32)) -> ExpectSource<'a> {
+        ExpectSource {
+            module_name: "Parser",
+            path: Path::new("src/Parser.roc"),
+            text,
+            lines,
+        }
+    }
+
+    #[test]
+    fn line_patterns() {
+        let expect = expect_source("expect 1 == 1", (12, 14));
+
+        assert!(Pattern::parse("Parser:12").matches(&expect));
+        assert!(Pattern::parse("Parser.roc:14").matches(&expect));
+        assert!(!Pattern::parse("Parser.roc:15").matches(&expect));
+        assert!(!Pattern::parse("Lexer.roc:12").matches(&expect));
+    }
+
+    #[test]
+    fn text_patterns_with_colons() {
+        let expect = expect_source("expect { x:1 } == { x:1 }", (3, 3));
+
+        assert!(matches!(Pattern::parse("{ x:1"), Pattern::Text(_)));
+        assert!(matches!(Pattern::parse("x:1"), Pattern::Text(_)));
+        assert!(matches!(Pattern::parse(":1"), Pattern::Text(_)));
+        assert!(Pattern::parse("{ x:1").matches(&expect));
+        assert!(Pattern::parse("x:1").matches(&expect));
+    }
+
+    #[test]
+    fn include_and_exclude() {
+        let expect = expect_source("expect 1 == 1", (12, 12));
+
+        assert!(TestFilter::new([], []).matches(&expect));
+        assert!(TestFilter::new(["Parser"], []).matches(&expect));
+        assert!(!TestFilter::new(["Lexer"], []).matches(&expect));
+        assert!(!TestFilter::new(["Parser"], ["1 == 1"]).matches(&expect));
+    }
+}
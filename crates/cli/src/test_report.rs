@@ -0,0 +1,218 @@
+//! Machine-readable reports of a `roc test` run, for CI dashboards.
+//!
+//! `--report=junit:<path>` writes JUnit XML and `--report=json:<path>` writes JSON.
+//! Every top-level `expect` that ran is one test case.
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde_json::json;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Junit,
+    Json,
+}
+
+/// The value of one `--report` flag
+#[derive(Debug, Clone)]
+pub struct ReportTarget {
+    pub format: ReportFormat,
+    pub path: PathBuf,
+}
+
+impl ReportTarget {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let (format, path) = match value.split_once(':') {
+            Some(("junit", path)) => (ReportFormat::Junit, path),
+            Some(("json", path)) => (ReportFormat::Json, path),
+            _ => {
+                return Err(format!(
+                    "`{value}` is not a valid report. Use `junit:<path>` or `json:<path>`."
+                ))
+            }
+        };
+
+        if path.is_empty() {
+            return Err(format!("The report `{value}` is missing a path."));
+        }
+
+        Ok(ReportTarget {
+            format,
+            path: PathBuf::from(path),
+        })
+    }
+
+    pub fn write(&self, cases: &[TestCase], total_time: Duration) -> io::Result<()> {
+        let mut file = io::BufWriter::new(std::fs::File::create(&self.path)?);
+
+        match self.format {
+            ReportFormat::Junit => write_junit(&mut file, cases, total_time)?,
+            ReportFormat::Json => write_json(&mut file, cases, total_time)?,
+        }
+
+        file.flush()
+    }
+}
+
+/// One top-level `expect` that ran
+#[derive(Debug)]
+pub struct TestCase {
+    pub module_name: String,
+    pub path: PathBuf,
+    /// The first and last lines of the `expect`, starting from 1
+    pub lines: (u32, u32),
+    /// The `expect`'s source code on a single line
+    pub summary: String,
+    pub passed: bool,
+    pub duration: Duration,
+    /// The rendered failure, including the values of the variables involved, without colors
+    pub output: String,
+}
+
+impl TestCase {
+    /// A name that stays the same as long as the `expect` doesn't move, like `Parser.roc:12`
+    fn name(&self) -> String {
+        let file_name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        format!("{}:{}", file_name, self.lines.0)
+    }
+}
+
+/// Remove the terminal colors from an `expect`'s rendered output
+pub fn strip_colors(output: &str) -> String {
+    match strip_ansi_escapes::strip(output) {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(_) => output.to_string(),
+    }
+}
+
+fn count_failed<'c>(cases: impl IntoIterator<Item = &'c TestCase>) -> usize {
+    cases.into_iter().filter(|case| !case.passed).count()
+}
+
+fn write_junit(
+    writer: &mut impl Write,
+    cases: &[TestCase],
+    total_time: Duration,
+) -> io::Result<()> {
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<testsuites name="roc test" tests="{}" failures="{}" time="{:.3}">"#,
+        cases.len(),
+        count_failed(cases),
+        total_time.as_secs_f64(),
+    )?;
+
+    // One suite per module, in the order the modules first appear
+    let mut modules: Vec<&str> = Vec::new();
+    for case in cases {
+        if !modules.contains(&case.module_name.as_str()) {
+            modules.push(&case.module_name);
+        }
+    }
+
+    for module_name in modules {
+        let suite: Vec<&TestCase> = cases
+            .iter()
+            .filter(|case| case.module_name == module_name)
+            .collect();
+        let suite_time: Duration = suite.iter().map(|case| case.duration).sum();
+
+        writeln!(
+            writer,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" time="{:.3}">"#,
+            escape_xml(module_name),
+            suite.len(),
+            count_failed(suite.iter().copied()),
+            suite_time.as_secs_f64(),
+        )?;
+
+        for case in suite {
+            write!(
+                writer,
+                r#"    <testcase name="{}" classname="{}" file="{}" line="{}" time="{:.3}""#,
+                escape_xml(&case.name()),
+                escape_xml(module_name),
+                escape_xml(&display_path(&case.path)),
+                case.lines.0,
+                case.duration.as_secs_f64(),
+            )?;
+
+            if case.passed {
+                writeln!(writer, "/>")?;
+            } else {
+                writeln!(writer, ">")?;
+                writeln!(
+                    writer,
+                    r#"      <failure message="{}">{}</failure>"#,
+                    escape_xml(&case.summary),
+                    escape_xml(case.output.trim()),
+                )?;
+                writeln!(writer, "    </testcase>")?;
+            }
+        }
+
+        writeln!(writer, "  </testsuite>")?;
+    }
+
+    writeln!(writer, "</testsuites>")
+}
+
+fn write_json(writer: &mut impl Write, cases: &[TestCase], total_time: Duration) -> io::Result<()> {
+    let failed = count_failed(cases);
+
+    let tests: Vec<_> = cases
+        .iter()
+        .map(|case| {
+            json!({
+                "name": case.name(),
+                "module": case.module_name,
+                "path": display_path(&case.path),
+                "region": {
+                    "start_line": case.lines.0,
+                    "end_line": case.lines.1,
+                },
+                "source": case.summary,
+                "passed": case.passed,
+                "duration_ms": case.duration.as_secs_f64() * 1000.0,
+                "output": if case.passed { None } else { Some(case.output.trim()) },
+            })
+        })
+        .collect();
+
+    let report = json!({
+        "passed": cases.len() - failed,
+        "failed": failed,
+        "duration_ms": total_time.as_secs_f64() * 1000.0,
+        "tests": tests,
+    });
+
+    serde_json::to_writer_pretty(&mut *writer, &report)?;
+    writeln!(writer)
+}
+
+fn display_path(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // XML 1.0 doesn't allow most control characters, even escaped
+            '\n' | '\r' | '\t' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
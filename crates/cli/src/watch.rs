@@ -0,0 +1,117 @@
+//! `roc check --watch`: check a module and everything it imports, then check it again
+//! whenever one of those files changes.
+use crate::build::check_file_and_module_paths;
+use bumpalo::Bump;
+use roc_load::{LoadingProblem, Threading};
+use roc_packaging::cache::RocCacheDir;
+use roc_reporting::cli::MessageFormat;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// How often to look at the watched files' modification times
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Editors often write a file in several steps (e.g. truncate, then write), so once we notice a
+/// change we wait this long for things to settle before checking again.
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// Runs until the process is interrupted.
+pub fn check_watch(
+    roc_file_path: PathBuf,
+    emit_timings: bool,
+    roc_cache_dir: RocCacheDir<'_>,
+    threading: Threading,
+) -> io::Result<i32> {
+    // Keep the solved types of the modules that type-check in memory, so that on later runs the
+    // modules that didn't change (and don't import anything that did) are not solved again.
+    roc_load::keep_types_in_memory();
+
+    // If the very first check fails before we learn the module graph, at least watch the root.
+    let mut module_paths = vec![roc_file_path.clone()];
+
+    loop {
+        // Files that are saved while we check count as changes, so note when we started
+        let started = SystemTime::now();
+
+        // Clear the screen and move the cursor to the top left, so every run starts on a clean slate
+        print!("\x1B[2J\x1B[H");
+
+        // Each run gets a fresh arena; the solved types of unchanged modules come from memory
+        let arena = Bump::new();
+
+        match check_file_and_module_paths(
+            &arena,
+            roc_file_path.clone(),
+            emit_timings,
+            roc_cache_dir,
+            threading,
+            MessageFormat::Human,
+        ) {
+            Ok((problems, total_time, paths)) => {
+                problems.print_to_stdout(total_time);
+                println!(".");
+
+                module_paths = paths;
+                module_paths.sort();
+            }
+            Err(LoadingProblem::FormattedReport(report)) => {
+                // We didn't get a new module graph, so keep watching the files we had before.
+                print!("{}", report);
+            }
+            Err(other) => {
+                println!("Checking failed with error:\n{:?}", other);
+            }
+        }
+
+        println!(
+            "\n\x1B[36mWatching {} {} for changes… (press Ctrl+C to stop)\x1B[39m",
+            module_paths.len(),
+            if module_paths.len() == 1 {
+                "file"
+            } else {
+                "files"
+            },
+        );
+        io::stdout().flush()?;
+
+        wait_for_change(&module_paths, started);
+    }
+}
+
+/// Wait until one of the files changes, or return right away if one was changed after `since`
+fn wait_for_change(paths: &[PathBuf], since: SystemTime) {
+    let before = snapshot(paths);
+
+    let changed_since = before
+        .iter()
+        .flatten()
+        .any(|(modified, _)| *modified >= since);
+
+    if changed_since {
+        return;
+    }
+
+    loop {
+        thread::sleep(POLL_INTERVAL);
+
+        if snapshot(paths) != before {
+            thread::sleep(DEBOUNCE);
+
+            return;
+        }
+    }
+}
+
+/// The modification time and length of every file, or `None` for files that don't exist (yet).
+fn snapshot(paths: &[PathBuf]) -> Vec<Option<(SystemTime, u64)>> {
+    paths
+        .iter()
+        .map(|path| {
+            let metadata = std::fs::metadata(path).ok()?;
+
+            Some((metadata.modified().ok()?, metadata.len()))
+        })
+        .collect()
+}
@@ -10,8 +10,9 @@ extern crate roc_module;
 #[cfg(test)]
 mod cli_run {
     use cli_utils::helpers::{
-        extract_valgrind_errors, file_path_from_root, fixture_file, fixtures_dir, known_bad_file,
-        run_cmd, run_roc, run_with_valgrind, strip_colors, Out, ValgrindError, ValgrindErrorXWhat,
+        build_roc_bin_cached, extract_valgrind_errors, file_path_from_root, fixture_file,
+        fixtures_dir, known_bad_file, run_cmd, run_roc, run_with_valgrind, strip_colors, Out,
+        ValgrindError, ValgrindErrorXWhat,
     };
     use const_format::concatcp;
     use indoc::indoc;
@@ -637,6 +638,275 @@ mod cli_run {
         );
     }
 
+    #[test]
+    #[cfg_attr(any(windows, not(target_arch = "x86_64")), ignore)]
+    fn expects_test_dev_backend() {
+        let path = file_path_from_root("crates/cli_testing_examples/expects", "dev_backend.roc");
+        let path = path.to_str().unwrap();
+
+        let out = run_roc([CMD_TEST, path, "--dev"], &[], &[]);
+        assert_eq!(out.status.code(), Some(1), "{}", out.stderr);
+
+        // The values of the lookups are written by the dev backend
+        let stdout = strip_colors(&out.stdout);
+        assert!(stdout.contains("name : Str"), "{stdout}");
+        assert!(stdout.contains(r#"name = "Roc""#), "{stdout}");
+        assert!(stdout.contains("list : List U8"), "{stdout}");
+        assert!(stdout.contains("list = [1, 2, 3]"), "{stdout}");
+        assert!(stdout.contains("2 failed and 1 passed"), "{stdout}");
+    }
+
+    #[test]
+    #[cfg_attr(any(windows, target_arch = "x86_64"), ignore)]
+    fn expects_test_dev_backend_unsupported_target() {
+        let path = file_path_from_root("crates/cli_testing_examples/expects", "dev_backend.roc");
+        let path = path.to_str().unwrap();
+
+        let out = run_roc([CMD_TEST, path, "--dev"], &[], &[]);
+        assert_eq!(out.status.code(), Some(1));
+        assert!(
+            out.stderr.contains("only supports x86_64 for now"),
+            "{}",
+            out.stderr
+        );
+    }
+
+    #[test]
+    #[cfg_attr(windows, ignore)]
+    fn expects_test_list_and_exclude() {
+        let path = file_path_from_root("crates/cli_testing_examples/expects", "expects.roc");
+        let path = path.to_str().unwrap();
+
+        let out = run_roc([CMD_TEST, path, "--list"], &[], &[]);
+        assert!(out.status.success());
+        assert!(out.stdout.contains("expects.roc:6"), "{}", out.stdout);
+        assert!(out.stdout.contains("a = 1 b = 2 a == b"), "{}", out.stdout);
+
+        let out = run_roc([CMD_TEST, path, "expects.roc:8", "--list"], &[], &[]);
+        assert!(out.status.success());
+        assert!(out.stdout.contains("expects.roc:6"), "{}", out.stdout);
+
+        let out = run_roc([CMD_TEST, path, "a == b", "--list"], &[], &[]);
+        assert!(out.status.success());
+        assert!(out.stdout.contains("expects.roc:6"), "{}", out.stdout);
+
+        let out = run_roc([CMD_TEST, path, "expects.roc:12"], &[], &[]);
+        assert_eq!(out.status.code(), Some(2));
+        assert!(out
+            .stdout
+            .contains("No expectations matched the given filters."));
+
+        let out = run_roc([CMD_TEST, path, "--exclude", "expects.roc:8"], &[], &[]);
+        assert_eq!(out.status.code(), Some(2));
+        assert!(out
+            .stdout
+            .contains("No expectations matched the given filters."));
+
+        // Without a .roc file, the first pattern is a filter on the default main.roc
+        let out = run_roc([CMD_TEST, "expects.roc:8"], &[], &[]);
+        assert_eq!(out.status.code(), Some(1));
+        assert!(
+            out.stderr.contains("was read as a filter"),
+            "{}",
+            out.stderr
+        );
+    }
+
+    #[test]
+    #[cfg_attr(windows, ignore)]
+    fn expects_test_crash() {
+        let path = file_path_from_root("crates/cli_testing_examples/expects", "crash.roc");
+        let path = path.to_str().unwrap();
+
+        // The stack overflow only takes down the worker running that `expect`
+        let out = run_roc([CMD_TEST, path], &[], &[]);
+        assert_eq!(out.status.code(), Some(1), "{}", out.stderr);
+
+        let stdout = strip_colors(&out.stdout);
+        assert!(
+            stdout.contains("The process running this expectation was stopped by"),
+            "{stdout}"
+        );
+        assert!(stdout.contains("1 + 1 == 3"), "{stdout}");
+        assert!(stdout.contains("2 failed and 1 passed"), "{stdout}");
+    }
+
+    #[test]
+    #[cfg_attr(windows, ignore)]
+    fn expects_test_timeout() {
+        let path = file_path_from_root("crates/cli_testing_examples/expects", "timeout.roc");
+        let path = path.to_str().unwrap();
+
+        let out = run_roc([CMD_TEST, path, "--timeout=1"], &[], &[]);
+        assert_eq!(out.status.code(), Some(1), "{}", out.stderr);
+
+        let stdout = strip_colors(&out.stdout);
+        assert!(
+            stdout.contains("This expectation was stopped after running for longer than 1s."),
+            "{stdout}"
+        );
+        assert!(stdout.contains("1 failed and 1 passed"), "{stdout}");
+    }
+
+    #[test]
+    #[cfg_attr(windows, ignore)]
+    fn expects_test_properties() {
+        let path = file_path_from_root("crates/cli_testing_examples/expects", "properties.roc");
+        let path = path.to_str().unwrap();
+
+        let out = run_roc([CMD_TEST, path, "--seed=42"], &[], &[]);
+        assert_eq!(out.status.code(), Some(1), "{}", out.stderr);
+
+        // The failing inputs are shrunk to the smallest ones that still fail
+        let stdout = strip_colors(&out.stdout);
+        assert!(stdout.contains("list : List U8"), "{stdout}");
+        assert!(stdout.contains("list = [0, 0, 0]"), "{stdout}");
+        assert!(stdout.contains("n : I64"), "{stdout}");
+        assert!(stdout.contains("n = 10"), "{stdout}");
+        assert!(stdout.contains("point = { x: 5, y: 0 }"), "{stdout}");
+        assert!(stdout.contains("shape = Circle 2"), "{stdout}");
+        assert!(stdout.contains("use `--seed 42`"), "{stdout}");
+        assert!(stdout.contains("4 failed and 1 passed"), "{stdout}");
+    }
+
+    #[test]
+    #[cfg_attr(windows, ignore)]
+    fn expects_test_coverage() {
+        let path = file_path_from_root("crates/cli_testing_examples/expects", "coverage.roc");
+        let path = path.to_str().unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let lcov_path = dir.path().join("lcov.info");
+        let coverage_flag = format!("--coverage={}", lcov_path.display());
+
+        let out = run_roc([CMD_TEST, path, coverage_flag.as_str()], &[], &[]);
+        assert_eq!(out.status.code(), Some(0), "{}", out.stderr);
+
+        let lcov = std::fs::read_to_string(lcov_path).unwrap();
+        assert!(
+            lcov.contains("SF:") && lcov.contains("coverage.roc"),
+            "{lcov}"
+        );
+        assert!(lcov.contains("FNDA:2,describe"), "{lcov}");
+        assert!(lcov.contains("FNDA:1,color"), "{lcov}");
+        assert!(lcov.contains("FNDA:0,untested"), "{lcov}");
+        assert!(lcov.contains("FNF:4\nFNH:2\n"), "{lcov}");
+        // The `if`, with one arm per line
+        assert!(lcov.contains("BRDA:9,0,0,0\n"), "{lcov}");
+        assert!(lcov.contains("BRDA:11,0,1,1\n"), "{lcov}");
+        assert!(lcov.contains("BRDA:13,0,2,1\n"), "{lcov}");
+        // The `when`
+        assert!(lcov.contains("BRDA:18,1,0,1\n"), "{lcov}");
+        assert!(lcov.contains("BRDA:19,1,1,0\n"), "{lcov}");
+        assert!(lcov.contains("BRF:6\nBRH:3\n"), "{lcov}");
+        assert!(lcov.ends_with("end_of_record\n"), "{lcov}");
+
+        let stdout = strip_colors(&out.stdout);
+        assert!(stdout.contains("defs 2/4 (50%)"), "{stdout}");
+        assert!(stdout.contains("branches 3/6 (50%)"), "{stdout}");
+        assert!(stdout.contains("0 failed and 3 passed"), "{stdout}");
+    }
+
+    #[test]
+    #[cfg_attr(windows, ignore)]
+    fn expects_test_doc_tests() {
+        let path = file_path_from_root("crates/cli_testing_examples/expects", "doc_tests.roc");
+        let path = path.to_str().unwrap();
+
+        let out = run_roc([CMD_TEST, path], &[], &[]);
+        assert_eq!(out.status.code(), Some(0), "{}", out.stderr);
+        assert!(
+            strip_colors(&out.stdout).contains("0 failed and 1 passed"),
+            "{}",
+            out.stdout
+        );
+
+        let out = run_roc([CMD_TEST, path, "--doc"], &[], &[]);
+        assert_eq!(out.status.code(), Some(1), "{}", out.stderr);
+
+        // The failure is reported in the doc comment
+        let stdout = strip_colors(&out.stdout);
+        assert!(stdout.contains("28│  ## double 2 == 5"), "{stdout}");
+        assert!(!stdout.contains("double 1 == 3"), "{stdout}");
+        assert!(stdout.contains("1 failed and 4 passed"), "{stdout}");
+
+        let out = run_roc([CMD_TEST, path, "--doc", "--list"], &[], &[]);
+        assert!(out.status.success());
+        assert!(out.stdout.contains("doc_tests.roc:22"), "{}", out.stdout);
+        assert!(out.stdout.contains("increment -1 == 0"), "{}", out.stdout);
+    }
+
+    #[test]
+    #[cfg_attr(windows, ignore)]
+    fn expects_test_doc_tests_interface() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Double.roc");
+
+        std::fs::write(
+            &path,
+            "interface Double\n    exposes [double]\n    imports []\n\n## ```roc\n## double 2 == 5\n## ```\ndouble : I64 -> I64\ndouble = \\n -> n * 2\n\nexpect double 21 == 42\n",
+        )
+        .unwrap();
+
+        let path = path.to_str().unwrap();
+
+        // This caches the types of the module, which has no doc tests yet
+        let out = run_roc([CMD_TEST, path], &[], &[]);
+        assert_eq!(out.status.code(), Some(0), "{}", out.stderr);
+        assert!(
+            strip_colors(&out.stdout).contains("0 failed and 1 passed"),
+            "{}",
+            out.stdout
+        );
+
+        let out = run_roc([CMD_TEST, path, "--doc"], &[], &[]);
+        assert_eq!(out.status.code(), Some(1), "{}", out.stderr);
+
+        let stdout = strip_colors(&out.stdout);
+        assert!(stdout.contains("## double 2 == 5"), "{stdout}");
+        assert!(stdout.contains("1 failed and 1 passed"), "{stdout}");
+    }
+
+    #[test]
+    #[cfg_attr(windows, ignore)]
+    fn expects_test_reports() {
+        let path = file_path_from_root("crates/cli_testing_examples/expects", "expects.roc");
+        let path = path.to_str().unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let junit_path = dir.path().join("report.xml");
+        let json_path = dir.path().join("report.json");
+        let junit_flag = format!("--report=junit:{}", junit_path.display());
+        let json_flag = format!("--report=json:{}", json_path.display());
+
+        let out = run_roc(
+            [CMD_TEST, path, junit_flag.as_str(), json_flag.as_str()],
+            &[],
+            &[],
+        );
+        assert_eq!(out.status.code(), Some(1), "{}", out.stderr);
+
+        let junit = std::fs::read_to_string(junit_path).unwrap();
+        assert!(junit.contains(r#"<testsuites name="roc test" tests="1" failures="1""#));
+        assert!(
+            junit.contains(r#"<testcase name="expects.roc:6""#),
+            "{junit}"
+        );
+        assert!(junit.contains("b = 2"), "{junit}");
+
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(json_path).unwrap()).unwrap();
+        assert_eq!(json["failed"], 1);
+        assert_eq!(json["passed"], 0);
+
+        let test = &json["tests"][0];
+        assert_eq!(test["name"], "expects.roc:6");
+        assert_eq!(test["region"]["start_line"], 6);
+        assert_eq!(test["region"]["end_line"], 10);
+        assert_eq!(test["passed"], false);
+        assert!(test["output"].as_str().unwrap().contains("a = 1"));
+    }
+
     #[test]
     #[cfg_attr(
         windows,
@@ -698,6 +968,64 @@ mod cli_run {
         )
     }
 
+    #[test]
+    #[cfg_attr(windows, ignore)]
+    fn run_profile() {
+        let path =
+            file_path_from_root("crates/cli_testing_examples/algorithms", "countingsort.roc");
+        let path = path.to_str().unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let profile_path = dir.path().join("countingsort.folded");
+        let profile_flag = format!("--profile={}", profile_path.display());
+
+        let out = run_roc([CMD_RUN, profile_flag.as_str(), path], &[], &[]);
+        assert!(out.status.success(), "{}", out.stderr);
+
+        let stdout = strip_colors(&out.stdout);
+        assert!(
+            stdout.starts_with("[0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2]\n"),
+            "{stdout}"
+        );
+
+        // the summary must not mix with the output of the program
+        assert!(!stdout.contains("Profile of"), "{stdout}");
+        let stderr = strip_colors(&out.stderr);
+        assert!(
+            stderr.contains("Allocations: 1 (104 bytes), deallocations: 1"),
+            "{stderr}"
+        );
+
+        // How often each function was sampled depends on the machine, but it's always sampled once
+        let samples = std::fs::read_to_string(&profile_path).unwrap();
+        assert!(!samples.is_empty());
+
+        // The only allocation is the list of counts
+        let allocations =
+            std::fs::read_to_string(dir.path().join("countingsort.alloc.folded")).unwrap();
+        let lines: Vec<&str> = allocations.lines().collect();
+        assert_eq!(lines.len(), 1, "{allocations}");
+        assert!(
+            lines[0].contains("#UserApp.quicksort;#UserApp.countAll;"),
+            "{allocations}"
+        );
+        assert!(lines[0].contains("List.repeat"), "{allocations}");
+        assert!(lines[0].ends_with(" 104"), "{allocations}");
+
+        // this runs long enough that some samples are in Roc functions
+        let path = file_path_from_root("crates/cli_testing_examples/benchmarks", "NQueens.roc");
+        let path = path.to_str().unwrap();
+        let profile_path = dir.path().join("nqueens.folded");
+        let profile_flag = format!("--profile={}", profile_path.display());
+
+        let out = run_roc([CMD_RUN, profile_flag.as_str(), path], &["10"], &[]);
+        assert!(out.status.success(), "{}", out.stderr);
+        assert_eq!(strip_colors(&out.stdout), "724\n");
+
+        let samples = std::fs::read_to_string(&profile_path).unwrap();
+        assert!(samples.contains("#UserApp."), "{samples}");
+    }
+
     #[test]
     #[cfg_attr(windows, ignore = "missing __udivdi3 and some other symbols")]
     #[serial(cli_platform)]
@@ -749,6 +1077,82 @@ mod cli_run {
         assert!(out.status.success());
     }
 
+    #[test]
+    #[cfg_attr(windows, ignore)]
+    fn check_watch_rechecks_on_change() {
+        use std::io::{BufRead, BufReader};
+        use std::process::{Command, Stdio};
+        use std::sync::mpsc;
+        use std::time::Duration;
+
+        let dir = tempfile::tempdir().unwrap();
+        let main = dir.path().join("Main.roc");
+        let dep = dir.path().join("Dep.roc");
+
+        std::fs::write(
+            &main,
+            "interface Main\n    exposes [answer]\n    imports [Dep]\n\nanswer : Str\nanswer = Dep.answer\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &dep,
+            "interface Dep\n    exposes [answer]\n    imports []\n\nanswer : Str\nanswer = \"42\"\n",
+        )
+        .unwrap();
+
+        let mut child = Command::new(build_roc_bin_cached())
+            .args([CMD_CHECK, "--watch", main.to_str().unwrap()])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let stdout = child.stdout.take().unwrap();
+
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().flatten() {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        // The output of one run, up to the line that says it is watching for changes again
+        let next_run = || {
+            let mut output = String::new();
+
+            loop {
+                let line = rx
+                    .recv_timeout(Duration::from_secs(60))
+                    .unwrap_or_else(|_| panic!("`roc check --watch` stopped reporting:\n{output}"));
+                let line = strip_colors(&line);
+
+                output.push_str(&line);
+                output.push('\n');
+
+                if line.contains("Watching 2 files for changes") {
+                    return output;
+                }
+            }
+        };
+
+        let first = next_run();
+        assert!(first.contains("0 errors and 0 warnings"), "{first}");
+
+        // Editing an imported module checks again
+        std::fs::write(
+            &dep,
+            "interface Dep\n    exposes [answer]\n    imports []\n\nanswer : Str\nanswer = 42\n",
+        )
+        .unwrap();
+
+        let second = next_run();
+        child.kill().unwrap();
+
+        assert!(second.contains("TYPE MISMATCH"), "{second}");
+        assert!(second.contains("1 error and 0 warnings"), "{second}");
+    }
+
     #[test]
     #[cfg_attr(windows, ignore)]
     fn interactive_effects() {
@@ -1355,6 +1759,105 @@ mod cli_run {
         );
     }
 
+    #[test]
+    fn known_type_error_as_json() {
+        let out = run_roc(
+            [
+                CMD_CHECK,
+                known_bad_file("TypeError.roc").to_str().unwrap(),
+                "--message-format=json",
+            ],
+            &[],
+            &[],
+        );
+
+        let messages: Vec<serde_json::Value> = out
+            .stdout
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(messages.len(), 2, "{}", out.stdout);
+
+        let diagnostic = &messages[0];
+
+        assert_eq!(diagnostic["kind"], "diagnostic");
+        assert_eq!(diagnostic["severity"], "error");
+        assert_eq!(diagnostic["title"], "TYPE MISMATCH");
+        assert!(diagnostic["file"]
+            .as_str()
+            .unwrap()
+            .replace('\\', "/")
+            .ends_with("tests/known_bad/TypeError.roc"));
+        assert_eq!(
+            diagnostic["region"],
+            serde_json::json!({
+                "start": { "line": 8, "column": 5 },
+                "end": { "line": 8, "column": 77 },
+            })
+        );
+        assert!(diagnostic["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Something is off with the body of the main definition:"));
+
+        let summary = &messages[1];
+
+        assert_eq!(summary["kind"], "summary");
+        assert_eq!(summary["errors"], 1);
+        assert_eq!(summary["warnings"], 0);
+    }
+
+    #[test]
+    fn syntax_error_as_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Broken.roc");
+
+        std::fs::write(
+            &path,
+            "interface Broken\n    exposes [x]\n    imports []\n\nx = [1, 2\n",
+        )
+        .unwrap();
+
+        let out = run_roc(
+            [CMD_CHECK, path.to_str().unwrap(), "--message-format=json"],
+            &[],
+            &[],
+        );
+
+        let diagnostic: serde_json::Value = serde_json::from_str(out.stdout.trim()).unwrap();
+
+        assert_eq!(diagnostic["kind"], "diagnostic");
+        assert_eq!(diagnostic["severity"], "error");
+        assert!(diagnostic["title"].is_string(), "{}", out.stdout);
+        assert!(diagnostic["file"].as_str().unwrap().ends_with("Broken.roc"));
+
+        // The list starts on line 5, and the report points at where it should have ended
+        let start_line = diagnostic["region"]["start"]["line"].as_u64().unwrap();
+        assert!(start_line >= 5, "{}", out.stdout);
+    }
+
+    #[test]
+    fn file_not_found_as_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Missing.roc");
+
+        let out = run_roc(
+            [CMD_CHECK, path.to_str().unwrap(), "--message-format=json"],
+            &[],
+            &[],
+        );
+
+        let diagnostic: serde_json::Value = serde_json::from_str(out.stdout.trim()).unwrap();
+
+        assert_eq!(diagnostic["title"], "FILE NOT FOUND");
+        assert!(diagnostic["file"]
+            .as_str()
+            .unwrap()
+            .ends_with("Missing.roc"));
+        assert!(diagnostic["region"].is_null());
+    }
+
     #[test]
     fn exposed_not_defined() {
         check_compile_error(
@@ -0,0 +1,34 @@
+app "countingsort"
+    packages { pf: "quicksort-platform/main.roc" }
+    imports []
+    provides [quicksort] to pf
+
+# The host only gives us numbers from 0 up to 12, so we can count them instead of comparing them.
+# Unlike quicksort.roc, this allocates, which is what the `roc run --profile` test relies on.
+quicksort = \list ->
+    counts = countAll list
+
+    fill list counts 0 0
+
+countAll : List I64 -> List Nat
+countAll = \list ->
+    List.walk list (List.repeat 0 12) \counts, number ->
+        index = Num.toNat number
+
+        when List.get counts index is
+            Ok count -> List.set counts index (count + 1)
+            Err OutOfBounds -> counts
+
+fill : List I64, List Nat, I64, Nat -> List I64
+fill = \list, counts, number, index ->
+    when List.get counts (Num.toNat number) is
+        Ok 0 ->
+            fill list counts (number + 1) index
+
+        Ok count ->
+            list
+            |> List.set index number
+            |> fill (List.set counts (Num.toNat number) (count - 1)) number (index + 1)
+
+        Err OutOfBounds ->
+            list
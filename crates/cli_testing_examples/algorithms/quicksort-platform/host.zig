@@ -84,6 +84,7 @@ extern fn kill(pid: c_int, sig: c_int) c_int;
 extern fn shm_open(name: *const i8, oflag: c_int, mode: c_uint) c_int;
 extern fn mmap(addr: ?*anyopaque, length: c_uint, prot: c_int, flags: c_int, fd: c_int, offset: c_uint) *anyopaque;
 extern fn getppid() c_int;
+extern fn getenv(name: [*:0]const u8) ?[*:0]const u8;
 
 fn roc_getppid() callconv(.C) c_int {
     return getppid();
@@ -93,6 +94,10 @@ fn roc_getppid_windows_stub() callconv(.C) c_int {
     return 0;
 }
 
+fn roc_getenv(name: [*:0]const u8) callconv(.C) ?[*:0]const u8 {
+    return getenv(name);
+}
+
 fn roc_shm_open(name: *const i8, oflag: c_int, mode: c_uint) callconv(.C) c_int {
     return shm_open(name, oflag, mode);
 }
@@ -105,6 +110,7 @@ comptime {
         @export(roc_getppid, .{ .name = "roc_getppid", .linkage = .Strong });
         @export(roc_mmap, .{ .name = "roc_mmap", .linkage = .Strong });
         @export(roc_shm_open, .{ .name = "roc_shm_open", .linkage = .Strong });
+        @export(roc_getenv, .{ .name = "roc_getenv", .linkage = .Strong });
     }
 
     if (builtin.os.tag == .windows) {
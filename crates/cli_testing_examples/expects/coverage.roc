@@ -0,0 +1,31 @@
+app "expects-coverage"
+    packages { pf: "zig-platform/main.roc" }
+    imports []
+    provides [main] to pf
+
+describe : I64 -> Str
+describe = \n ->
+    if n < 0 then
+        "negative"
+    else if n == 0 then
+        "zero"
+    else
+        "positive"
+
+color : [Red, Green, Blue] -> Str
+color = \c ->
+    when c is
+        Red -> "red"
+        Green -> "green"
+        Blue -> "blue"
+
+untested : I64 -> I64
+untested = \n -> n + 1
+
+expect describe 5 == "positive"
+
+expect describe 0 == "zero"
+
+expect color Red == "red"
+
+main = "Program finished!\n"
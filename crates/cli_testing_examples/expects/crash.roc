@@ -0,0 +1,15 @@
+app "expects-crash"
+    packages { pf: "zig-platform/main.roc" }
+    imports []
+    provides [main] to pf
+
+overflow : U64 -> U64
+overflow = \n -> 1 + overflow (n + 1)
+
+expect overflow 0 == 0
+
+expect 1 + 1 == 3
+
+expect 1 + 1 == 2
+
+main = "Program finished!\n"
@@ -0,0 +1,18 @@
+app "expects-dev-backend"
+    packages { pf: "zig-platform/main.roc" }
+    imports []
+    provides [main] to pf
+
+expect
+    name = "Roc"
+
+    name == "World"
+
+expect
+    list = [1u8, 2, 3]
+
+    List.len list == 4
+
+expect 1 + 1 == 2
+
+main = "Program finished!\n"
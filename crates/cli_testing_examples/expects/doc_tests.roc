@@ -0,0 +1,41 @@
+app "expects-doc-tests"
+    packages { pf: "zig-platform/main.roc" }
+    imports []
+    provides [main] to pf
+
+## Add one to a number.
+##
+## ```roc
+## increment 1 == 2
+##
+## increment -1
+##     == 0
+## ```
+increment : I64 -> I64
+increment = \n -> n + 1
+
+## Double a number.
+##
+## ```roc
+## n = 21
+##
+## expect double n == 42
+## ```
+##
+## This one is wrong on purpose:
+##
+## ```roc
+## double 2 == 5
+## ```
+##
+## Other languages are not tested:
+##
+## ```sh
+## echo "double 1 == 3"
+## ```
+double : I64 -> I64
+double = \n -> n * 2
+
+expect increment 41 == 42
+
+main = "Program finished!\n"
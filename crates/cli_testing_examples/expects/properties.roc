@@ -0,0 +1,38 @@
+app "expects-properties"
+    packages { pf: "zig-platform/main.roc" }
+    imports []
+    provides [main] to pf
+
+isShort : List U8 -> Bool
+isShort = \list -> List.len list < 3
+
+expect \list -> isShort list
+
+isSmall : I64 -> Bool
+isSmall = \n -> n < 10
+
+expect \n -> isSmall n
+
+concatEmpty : Str -> Bool
+concatEmpty = \str -> Str.concat str "" == str
+
+expect \str -> concatEmpty str
+
+Point : { x : I64, y : I64 }
+
+isLeftOfFive : Point -> Bool
+isLeftOfFive = \{ x } -> x < 5
+
+expect \point -> isLeftOfFive point
+
+Shape : [Circle I64, Dot, Square I64]
+
+isSmallCircle : Shape -> Bool
+isSmallCircle = \shape ->
+    when shape is
+        Circle radius -> radius < 2
+        Dot | Square _ -> Bool.true
+
+expect \shape -> isSmallCircle shape
+
+main = "Program finished!\n"
@@ -0,0 +1,13 @@
+app "expects-timeout"
+    packages { pf: "zig-platform/main.roc" }
+    imports []
+    provides [main] to pf
+
+spin : U64 -> U64
+spin = \n -> spin (n + 1)
+
+expect spin 0 == 0
+
+expect 1 + 1 == 2
+
+main = "Program finished!\n"
@@ -1468,8 +1468,49 @@ pub fn llvm_module_to_dylib(
         child
     );
 
-    // Load the dylib
-    let path = dylib_path.as_path().to_str().unwrap();
+    load_dylib(&dylib_path, target)
+}
+
+/// Link an object built by the dev backend, together with the builtins, into a dylib and load it
+pub fn dev_object_to_dylib(object: &[u8], target: &Triple) -> Result<Library, Error> {
+    let dir = tempfile::tempdir().unwrap();
+    let app_o_file = dir.path().join("app.o");
+
+    std::fs::write(&app_o_file, object).expect("Writing .o file failed");
+
+    // The dev backend calls the builtins, but doesn't include them like LLVM does
+    let builtins_host_tempfile =
+        bitcode::host_tempfile().expect("failed to write host builtins object to tempfile");
+
+    let (mut child, dylib_path) = link(
+        &Triple::host(),
+        app_o_file.clone(),
+        &[
+            app_o_file.to_str().unwrap(),
+            builtins_host_tempfile.path().to_str().unwrap(),
+        ],
+        LinkType::Dylib,
+    )
+    .unwrap();
+
+    let exit_status = child.wait().unwrap();
+
+    assert!(
+        exit_status.success(),
+        "\n___________\nLinking command failed with status {:?}:\n\n  {:?}\n___________\n",
+        exit_status,
+        child
+    );
+
+    // Extend the lifetime of the tempfile so it doesn't get dropped
+    // (and thus deleted) before the linking process is done using it!
+    let _ = builtins_host_tempfile;
+
+    load_dylib(&dylib_path, target)
+}
+
+fn load_dylib(dylib_path: &Path, target: &Triple) -> Result<Library, Error> {
+    let path = dylib_path.to_str().unwrap();
 
     if matches!(target.architecture, Architecture::Aarch64(_)) {
         // On AArch64 darwin machines, calling `ldopen` on Roc-generated libs from multiple threads
@@ -4,7 +4,7 @@ use roc_gen_llvm::llvm::build::{module_from_builtins, LlvmBackendMode};
 use roc_gen_llvm::llvm::externs::add_default_roc_externs;
 use roc_load::{EntryPoint, ExpectMetadata, LoadedModule, MonomorphizedModule};
 use roc_mono::ir::{OptLevel, SingleEntryPoint};
-use roc_reporting::cli::{report_problems, Problems};
+use roc_reporting::cli::{report_problems, MessageFormat, Problems};
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
@@ -17,23 +17,31 @@ pub struct CodeGenTiming {
     pub code_gen: Duration,
 }
 
-pub fn report_problems_monomorphized(loaded: &mut MonomorphizedModule) -> Problems {
+pub fn report_problems_monomorphized(
+    loaded: &mut MonomorphizedModule,
+    message_format: MessageFormat,
+) -> Problems {
     report_problems(
         loaded.total_problems(),
         &loaded.sources,
         &loaded.interns,
         &mut loaded.can_problems,
         &mut loaded.type_problems,
+        message_format,
     )
 }
 
-pub fn report_problems_typechecked(loaded: &mut LoadedModule) -> Problems {
+pub fn report_problems_typechecked(
+    loaded: &mut LoadedModule,
+    message_format: MessageFormat,
+) -> Problems {
     report_problems(
         loaded.total_problems(),
         &loaded.sources,
         &loaded.interns,
         &mut loaded.can_problems,
         &mut loaded.type_problems,
+        message_format,
     )
 }
 
@@ -65,6 +73,8 @@ pub struct CodeGenOptions {
     pub backend: CodeGenBackend,
     pub opt_level: OptLevel,
     pub emit_debug_info: bool,
+    /// Instrument the program for `roc run --profile` (LLVM backend only)
+    pub profile: bool,
 }
 
 type GenFromMono<'a> = (CodeObject, CodeGenTiming, ExpectMetadata<'a>);
@@ -84,6 +94,7 @@ pub fn gen_from_mono_module<'a>(
             arena,
             loaded,
             target,
+            code_gen_options.opt_level,
             preprocessed_host_path,
             wasm_dev_stack_bytes,
         ),
@@ -159,6 +170,7 @@ fn gen_from_mono_module_llvm<'a>(
         backend: _,
         opt_level,
         emit_debug_info,
+        profile,
     } = code_gen_options;
 
     let builder = context.create_builder();
@@ -176,6 +188,7 @@ fn gen_from_mono_module_llvm<'a>(
         module,
         target_info,
         mode: match opt_level {
+            _ if profile => LlvmBackendMode::BinaryProfile,
             OptLevel::Development => LlvmBackendMode::BinaryDev,
             OptLevel::Normal | OptLevel::Size | OptLevel::Optimize => LlvmBackendMode::Binary,
         },
@@ -429,6 +442,7 @@ fn gen_from_mono_module_dev<'a>(
     arena: &'a bumpalo::Bump,
     loaded: MonomorphizedModule<'a>,
     target: &target_lexicon::Triple,
+    opt_level: OptLevel,
     preprocessed_host_path: &Path,
     wasm_dev_stack_bytes: Option<u32>,
 ) -> GenFromMono<'a> {
@@ -442,7 +456,7 @@ fn gen_from_mono_module_dev<'a>(
             wasm_dev_stack_bytes,
         ),
         Architecture::X86_64 | Architecture::Aarch64(_) => {
-            gen_from_mono_module_dev_assembly(arena, loaded, target)
+            gen_from_mono_module_dev_assembly(arena, loaded, target, opt_level)
         }
         _ => todo!(),
     }
@@ -453,6 +467,7 @@ pub fn gen_from_mono_module_dev<'a>(
     arena: &'a bumpalo::Bump,
     loaded: MonomorphizedModule<'a>,
     target: &target_lexicon::Triple,
+    opt_level: OptLevel,
     _host_input_path: &Path,
     _wasm_dev_stack_bytes: Option<u32>,
 ) -> GenFromMono<'a> {
@@ -460,7 +475,7 @@ pub fn gen_from_mono_module_dev<'a>(
 
     match target.architecture {
         Architecture::X86_64 | Architecture::Aarch64(_) => {
-            gen_from_mono_module_dev_assembly(arena, loaded, target)
+            gen_from_mono_module_dev_assembly(arena, loaded, target, opt_level)
         }
         _ => todo!(),
     }
@@ -537,11 +552,18 @@ fn gen_from_mono_module_dev_assembly<'a>(
     arena: &'a bumpalo::Bump,
     loaded: MonomorphizedModule<'a>,
     target: &target_lexicon::Triple,
+    opt_level: OptLevel,
 ) -> GenFromMono<'a> {
     let code_gen_start = Instant::now();
 
     let lazy_literals = true;
     let generate_allocators = false; // provided by the platform
+    let mode = match opt_level {
+        OptLevel::Development => roc_gen_dev::AssemblyBackendMode::BinaryDev,
+        OptLevel::Normal | OptLevel::Size | OptLevel::Optimize => {
+            roc_gen_dev::AssemblyBackendMode::Binary
+        }
+    };
 
     let MonomorphizedModule {
         module_id,
@@ -558,6 +580,7 @@ fn gen_from_mono_module_dev_assembly<'a>(
         exposed_to_host: exposed_to_host.values.keys().copied().collect(),
         lazy_literals,
         generate_allocators,
+        mode,
     };
 
     let module_object =
@@ -2,6 +2,7 @@ const std = @import("std");
 const builtin = @import("builtin");
 
 const Atomic = std.atomic.Atomic;
+const RocStr = @import("str.zig").RocStr;
 
 const O_RDWR: c_int = 2;
 const O_CREAT: c_int = 64;
@@ -79,6 +80,17 @@ pub fn readSharedBufferEnv() callconv(.C) void {
     }
 }
 
+/// The `roc_panic` of `roc test --dev`, where there is no host to recover from a crash.
+/// The process that runs the expect reports the crash, and then stops.
+pub fn expectPanic(msg: *const RocStr, tag_id: u32) callconv(.C) void {
+    _ = tag_id;
+
+    const stderr = std.io.getStdErr().writer();
+    stderr.print("Roc crashed with:\n\n    {s}\n\n", .{msg.asSlice()}) catch {};
+
+    std.os.abort();
+}
+
 pub fn notifyParent(shared_buffer: [*]u8, tag: u32) callconv(.C) void {
     if (builtin.os.tag == .macos or builtin.os.tag == .linux) {
         const usize_ptr = @ptrCast([*]u32, @alignCast(@alignOf(usize), shared_buffer));
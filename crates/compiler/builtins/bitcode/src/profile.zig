@@ -0,0 +1,212 @@
+const std = @import("std");
+const builtin = @import("builtin");
+
+const Atomic = std.atomic.Atomic;
+const Ordering = std.atomic.Ordering;
+
+// The calling context tree of a `roc run --profile` run.
+//
+// Every Roc function call is a node in the tree, keyed on its parent node and the function symbol.
+// The node that is currently running is written to shared memory, where `roc` reads it
+// periodically from another process to sample where the time goes. Allocations are counted on
+// the node that made them.
+//
+// Each thread keeps track of its own node. When several threads run Roc functions, the samples
+// go to the node that was entered or returned to most recently.
+//
+// IMPORTANT: the layout of `Profile` must match `crates/cli/src/profile.rs`!
+
+const O_RDWR: c_int = 2;
+
+const PROT_READ: c_int = 1;
+const PROT_WRITE: c_int = 2;
+const MAP_SHARED: c_int = 0x0001;
+
+const MAX_NODES: u32 = 1 << 16;
+const TABLE_SIZE: u32 = 2 * MAX_NODES;
+
+const Node = extern struct {
+    parent: u32,
+    _padding: u32,
+    function: u64,
+    allocations: u64,
+    allocated_bytes: u64,
+    deallocations: u64,
+};
+
+const Profile = extern struct {
+    // only ever accessed atomically, because `roc` samples it while we run
+    current: u32,
+    node_count: u32,
+    // node 0 is the root, which stands for the host
+    nodes: [MAX_NODES]Node,
+    // maps (parent, function) to a node; 0 is an empty slot
+    table: [TABLE_SIZE]u32,
+};
+
+// `roc` puts the name of the shared memory object in this environment variable
+const BUFFER_ENV_VAR = "ROC_PROFILE_BUFFER";
+
+const UNINITIALIZED: u8 = 0;
+const INITIALIZING: u8 = 1;
+const INITIALIZED: u8 = 2;
+
+var INIT_STATE = Atomic(u8).init(UNINITIALIZED);
+var PROFILE: ?*Profile = null;
+
+// guards adding nodes to the tree
+var TREE_LOCK = Atomic(u32).init(0);
+
+// the node that this thread is in
+threadlocal var CURRENT: u32 = 0;
+
+extern fn roc_shm_open(name: *const i8, oflag: c_int, mode: c_uint) c_int;
+extern fn roc_mmap(addr: ?*anyopaque, length: c_uint, prot: c_int, flags: c_int, fd: c_int, offset: c_uint) *anyopaque;
+extern fn roc_getenv(name: [*:0]const u8) ?[*:0]const u8;
+
+fn getProfile() ?*Profile {
+    if (INIT_STATE.load(Ordering.Acquire) == INITIALIZED) {
+        return PROFILE;
+    }
+
+    if (INIT_STATE.compareAndSwap(UNINITIALIZED, INITIALIZING, Ordering.Acquire, Ordering.Acquire) == null) {
+        PROFILE = openProfile();
+        INIT_STATE.store(INITIALIZED, Ordering.Release);
+    } else {
+        // another thread is opening the profile
+        while (INIT_STATE.load(Ordering.Acquire) != INITIALIZED) {
+            std.atomic.spinLoopHint();
+        }
+    }
+
+    return PROFILE;
+}
+
+fn openProfile() ?*Profile {
+    if (builtin.os.tag == .macos or builtin.os.tag == .linux) {
+        // without the name of the profile, we aren't being profiled
+        const name = roc_getenv(BUFFER_ENV_VAR) orelse return null;
+
+        const shared_fd = roc_shm_open(@ptrCast(*const i8, name), O_RDWR, 0o666);
+
+        if (shared_fd < 0) {
+            return null;
+        }
+
+        const shared_ptr = roc_mmap(
+            null,
+            @sizeOf(Profile),
+            PROT_READ | PROT_WRITE,
+            MAP_SHARED,
+            shared_fd,
+            0,
+        );
+
+        // MAP_FAILED
+        if (@ptrToInt(shared_ptr) == std.math.maxInt(usize)) {
+            return null;
+        }
+
+        const profile = @ptrCast(*Profile, @alignCast(@alignOf(Profile), shared_ptr));
+
+        if (profile.node_count == 0) {
+            profile.node_count = 1;
+        }
+
+        return profile;
+    } else {
+        return null;
+    }
+}
+
+fn setCurrent(profile: *Profile, node: u32) void {
+    CURRENT = node;
+
+    const atomic_ptr = @ptrCast(*Atomic(u32), &profile.current);
+    atomic_ptr.store(node, Ordering.Release);
+}
+
+fn hash(parent: u32, function: u64) u32 {
+    const h = (function ^ (@as(u64, parent) << 32 | parent)) *% 0x9E3779B97F4A7C15;
+    return @truncate(u32, h >> 32);
+}
+
+fn findOrInsertChild(profile: *Profile, parent: u32, function: u64) ?u32 {
+    while (TREE_LOCK.compareAndSwap(0, 1, Ordering.Acquire, Ordering.Monotonic) != null) {
+        std.atomic.spinLoopHint();
+    }
+    defer TREE_LOCK.store(0, Ordering.Release);
+
+    var slot = hash(parent, function) & (TABLE_SIZE - 1);
+
+    // the table has room for twice the nodes, so there is always an empty slot
+    while (true) {
+        const index = profile.table[slot];
+
+        if (index == 0) {
+            if (profile.node_count == MAX_NODES) {
+                return null;
+            }
+
+            const new = profile.node_count;
+
+            profile.nodes[new] = Node{
+                .parent = parent,
+                ._padding = 0,
+                .function = function,
+                .allocations = 0,
+                .allocated_bytes = 0,
+                .deallocations = 0,
+            };
+            profile.node_count = new + 1;
+            profile.table[slot] = new;
+
+            return new;
+        }
+
+        const node = &profile.nodes[index];
+        if (node.parent == parent and node.function == function) {
+            return index;
+        }
+
+        slot = (slot + 1) & (TABLE_SIZE - 1);
+    }
+}
+
+// Returns the node of the caller, which `profileExit` goes back to
+pub fn profileEnter(function: u64) callconv(.C) u32 {
+    const caller = CURRENT;
+    const profile = getProfile() orelse return caller;
+
+    // direct recursion stays in the same node, so the tree does not grow with the recursion depth.
+    // When the tree is full, the time is counted on the caller.
+    const next = if (profile.nodes[caller].function == function)
+        caller
+    else
+        findOrInsertChild(profile, caller, function) orelse caller;
+
+    setCurrent(profile, next);
+
+    return caller;
+}
+
+pub fn profileExit(caller: u32) callconv(.C) void {
+    const profile = getProfile() orelse return;
+
+    setCurrent(profile, caller);
+}
+
+pub fn profileAlloc(size: usize) callconv(.C) void {
+    const profile = getProfile() orelse return;
+    const node = &profile.nodes[CURRENT];
+
+    _ = @atomicRmw(u64, &node.allocations, .Add, 1, .Monotonic);
+    _ = @atomicRmw(u64, &node.allocated_bytes, .Add, size, .Monotonic);
+}
+
+pub fn profileDealloc() callconv(.C) void {
+    const profile = getProfile() orelse return;
+    const node = &profile.nodes[CURRENT];
+
+    _ = @atomicRmw(u64, &node.deallocations, .Add, 1, .Monotonic);
+}
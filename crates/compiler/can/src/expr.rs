@@ -3048,7 +3048,7 @@ pub fn toplevel_expect_to_inline_expect_fx(loc_expr: Loc<Expr>) -> Loc<Expr> {
     toplevel_expect_to_inline_expect_help(loc_expr, true)
 }
 
-fn toplevel_expect_to_inline_expect_help(mut loc_expr: Loc<Expr>, has_effects: bool) -> Loc<Expr> {
+fn toplevel_expect_to_inline_expect_help(loc_expr: Loc<Expr>, has_effects: bool) -> Loc<Expr> {
     enum StoredDef {
         NonRecursive(Region, Box<Def>),
         Recursive(Region, Vec<Def>, IllegalCycleMark),
@@ -3057,6 +3057,13 @@ fn toplevel_expect_to_inline_expect_help(mut loc_expr: Loc<Expr>, has_effects: b
     let mut stack = vec![];
     let mut lookups_in_cond = vec![];
 
+    // The generated inputs of a property become defs, so they are shown when it fails
+    let mut loc_expr = if has_effects {
+        loc_expr
+    } else {
+        property_inputs_to_defs(loc_expr)
+    };
+
     loop {
         match loc_expr.value {
             Expr::LetNonRec(boxed_def, remainder) => {
@@ -3117,6 +3124,66 @@ fn toplevel_expect_to_inline_expect_help(mut loc_expr: Loc<Expr>, has_effects: b
     loc_expr
 }
 
+/// A top-level `expect` of a function, like `expect \list -> List.len list >= 0`, is a property.
+/// Its arguments are generated by the host, and read with `LowLevel::ExpectInput`.
+/// Anything other than a function is returned unchanged.
+fn property_inputs_to_defs(loc_expr: Loc<Expr>) -> Loc<Expr> {
+    let closure = match loc_expr.value {
+        Expr::Closure(closure) => closure,
+        value => return Loc::at(loc_expr.region, value),
+    };
+
+    let mut loc_expr = *closure.loc_body;
+
+    for (index, (var, _mark, loc_pattern)) in closure.arguments.into_iter().enumerate().rev() {
+        let region = loc_pattern.region;
+
+        // Only the inputs that are bound to a name can be shown when the property fails
+        let mut pattern_vars = SendMap::default();
+        if let Pattern::Identifier(symbol) = loc_pattern.value {
+            pattern_vars.insert(symbol, var);
+        }
+
+        let index_expr = Expr::Int(
+            Variable::U64,
+            Variable::U64,
+            index.to_string().into_boxed_str(),
+            IntValue::I128((index as i128).to_ne_bytes()),
+            IntBound::None,
+        );
+
+        let input = Expr::RunLowLevel {
+            op: LowLevel::ExpectInput,
+            args: vec![(Variable::U64, index_expr)],
+            ret_var: var,
+        };
+
+        let def = Def {
+            loc_pattern,
+            loc_expr: Loc::at(region, input),
+            expr_var: var,
+            pattern_vars,
+            annotation: None,
+        };
+
+        let let_region = Region::span_across(&region, &loc_expr.region);
+        loc_expr = Loc::at(
+            let_region,
+            Expr::LetNonRec(Box::new(def), Box::new(loc_expr)),
+        );
+    }
+
+    loc_expr
+}
+
+/// The type of each generated input of a top-level `expect`. Empty if it isn't a property.
+pub fn toplevel_expect_property_inputs(loc_expr: &Loc<Expr>) -> Vec<Variable> {
+    match &loc_expr.value {
+        Expr::Closure(closure) => closure.arguments.iter().map(|(var, _, _)| *var).collect(),
+        _ => Vec::new(),
+    }
+}
+
 pub struct ExpectCollector {
     pub expects: VecMap<Region, Vec<ExpectLookup>>,
     pub dbgs: VecMap<Symbol, DbgLookup>,
@@ -2553,21 +2553,60 @@ pub fn constrain_decls(
             Expectation => {
                 let loc_expr = &declarations.expressions[index];
 
-                let bool_type = constraints.push_variable(Variable::BOOL);
-                let expected = constraints.push_expected_type(Expected::ForReason(
-                    Reason::ExpectCondition,
-                    bool_type,
-                    loc_expr.region,
-                ));
+                let expect_constraint = match &loc_expr.value {
+                    // A property: the host generates the arguments,
+                    // and the body of the function is the condition
+                    Closure(ClosureData {
+                        function_type,
+                        return_type,
+                        loc_body,
+                        ..
+                    }) => {
+                        let function_type = constraints.push_variable(*function_type);
+                        let expected = constraints.push_expected_type(NoExpectation(function_type));
+
+                        let closure_constraint = constrain_expr(
+                            types,
+                            constraints,
+                            &mut env,
+                            loc_expr.region,
+                            &loc_expr.value,
+                            expected,
+                        );
 
-                let expect_constraint = constrain_expr(
-                    types,
-                    constraints,
-                    &mut env,
-                    loc_expr.region,
-                    &loc_expr.value,
-                    expected,
-                );
+                        let bool_type = constraints.push_variable(Variable::BOOL);
+                        let expected_bool = constraints.push_expected_type(Expected::ForReason(
+                            Reason::ExpectCondition,
+                            bool_type,
+                            loc_body.region,
+                        ));
+                        let condition_constraint = constraints.equal_types_var(
+                            *return_type,
+                            expected_bool,
+                            Category::Expect,
+                            loc_body.region,
+                        );
+
+                        constraints.and_constraint([closure_constraint, condition_constraint])
+                    }
+                    _ => {
+                        let bool_type = constraints.push_variable(Variable::BOOL);
+                        let expected = constraints.push_expected_type(Expected::ForReason(
+                            Reason::ExpectCondition,
+                            bool_type,
+                            loc_expr.region,
+                        ));
+
+                        constrain_expr(
+                            types,
+                            constraints,
+                            &mut env,
+                            loc_expr.region,
+                            &loc_expr.value,
+                            expected,
+                        )
+                    }
+                };
 
                 constraint = constraints.let_constraint(
                     [],
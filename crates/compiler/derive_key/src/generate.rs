@@ -0,0 +1,147 @@
+use roc_module::{
+    ident::{Lowercase, TagName},
+    symbol::Symbol,
+};
+use roc_types::subs::{Content, FlatType, GetSubsSlice, Subs, Variable};
+
+use crate::{util::check_derivable_ext_var, DeriveError};
+
+/// How `roc test` generates the inputs of a property `expect`. Generators are made by the host
+/// rather than in Roc, but which types they exist for is decided like for the other derivers.
+#[derive(Hash, PartialEq, Eq, Debug)]
+pub enum FlatGenerate {
+    /// A number or a string, by the symbol of its type
+    Immediate(Symbol),
+    Key(FlatGenerateKey),
+}
+
+#[derive(Hash, PartialEq, Eq, Debug, Clone)]
+pub enum FlatGenerateKey {
+    List(/* takes one variable */),
+    // Unfortunate that we must allocate here, c'est la vie
+    Record(Vec<Lowercase>),
+    Tuple(u16),
+    TagUnion(Vec<(TagName, u16)>),
+}
+
+impl FlatGenerate {
+    pub fn from_var(subs: &Subs, var: Variable) -> Result<FlatGenerate, DeriveError> {
+        use DeriveError::*;
+        use FlatGenerate::*;
+        match *subs.get_content_without_compacting(var) {
+            Content::Structure(flat_type) => match flat_type {
+                FlatType::Apply(sym, _) => match sym {
+                    Symbol::LIST_LIST => Ok(Key(FlatGenerateKey::List())),
+                    Symbol::STR_STR => Ok(Immediate(Symbol::STR_STR)),
+                    _ => Err(Underivable),
+                },
+                FlatType::Record(fields, ext) => {
+                    let (fields_iter, ext) = fields.unsorted_iterator_and_ext(subs, ext);
+
+                    check_derivable_ext_var(subs, ext, |ext| {
+                        matches!(ext, Content::Structure(FlatType::EmptyRecord))
+                    })?;
+
+                    let mut field_names = Vec::with_capacity(fields.len());
+                    for (field_name, record_field) in fields_iter {
+                        if record_field.is_optional() {
+                            // An optional field has no value to generate
+                            return Err(Underivable);
+                        }
+                        field_names.push(field_name.clone());
+                    }
+
+                    field_names.sort();
+
+                    Ok(Key(FlatGenerateKey::Record(field_names)))
+                }
+                FlatType::Tuple(elems, ext) => {
+                    let (elems_iter, ext) = elems.sorted_iterator_and_ext(subs, ext);
+
+                    check_derivable_ext_var(subs, ext, |ext| {
+                        matches!(ext, Content::Structure(FlatType::EmptyTuple))
+                    })?;
+
+                    Ok(Key(FlatGenerateKey::Tuple(elems_iter.count() as _)))
+                }
+                FlatType::TagUnion(tags, ext) => {
+                    let (tags_iter, ext) = tags.unsorted_tags_and_ext(subs, ext);
+
+                    check_derivable_ext_var(subs, ext.var(), |ext| {
+                        matches!(ext, Content::Structure(FlatType::EmptyTagUnion))
+                    })?;
+
+                    let mut tag_names_and_payload_sizes: Vec<_> = tags_iter
+                        .tags
+                        .into_iter()
+                        .map(|(name, payload_slice)| {
+                            let payload_size = payload_slice.len();
+                            (name.clone(), payload_size as _)
+                        })
+                        .collect();
+
+                    tag_names_and_payload_sizes.sort_by(|(t1, _), (t2, _)| t1.cmp(t2));
+
+                    Ok(Key(FlatGenerateKey::TagUnion(tag_names_and_payload_sizes)))
+                }
+                // Generating a value of a recursive type needs a way to stop recursing, which
+                // we don't have yet.
+                FlatType::RecursiveTagUnion(..) => Err(Underivable),
+                FlatType::FunctionOrTagUnion(names_index, _, _) => {
+                    Ok(Key(FlatGenerateKey::TagUnion(
+                        subs.get_subs_slice(names_index)
+                            .iter()
+                            .map(|t| (t.clone(), 0))
+                            .collect(),
+                    )))
+                }
+                FlatType::EmptyRecord => Ok(Key(FlatGenerateKey::Record(vec![]))),
+                FlatType::EmptyTuple => Ok(Key(FlatGenerateKey::Tuple(0))),
+                // There are no values of an empty tag union to generate
+                FlatType::EmptyTagUnion => Err(Underivable),
+                //
+                FlatType::Func(..) => Err(Underivable),
+            },
+            Content::Alias(sym, _, real_var, _) => match num_symbol_to_immediate(sym) {
+                Some(immediate) => Ok(immediate),
+                // NB: I believe it is okay to unwrap opaques here because derivers are only used
+                // by the backend, and the backend treats opaques like structural aliases.
+                None => Self::from_var(subs, real_var),
+            },
+            Content::RangedNumber(range) => {
+                // Like for `Hash`, a ranged number is generated at the width it is compiled to
+                let chosen_width = range.default_compilation_width();
+                Ok(num_symbol_to_immediate(chosen_width.symbol()).unwrap())
+            }
+            //
+            Content::RecursionVar { .. } => Err(Underivable),
+            Content::Error => Err(Underivable),
+            Content::FlexVar(_)
+            | Content::RigidVar(_)
+            | Content::FlexAbleVar(_, _)
+            | Content::RigidAbleVar(_, _) => Err(UnboundVar),
+            Content::LambdaSet(_) => Err(Underivable),
+        }
+    }
+}
+
+const fn num_symbol_to_immediate(symbol: Symbol) -> Option<FlatGenerate> {
+    use FlatGenerate::*;
+    match symbol {
+        Symbol::NUM_U8 | Symbol::NUM_UNSIGNED8 => Some(Immediate(Symbol::NUM_U8)),
+        Symbol::NUM_U16 | Symbol::NUM_UNSIGNED16 => Some(Immediate(Symbol::NUM_U16)),
+        Symbol::NUM_U32 | Symbol::NUM_UNSIGNED32 => Some(Immediate(Symbol::NUM_U32)),
+        Symbol::NUM_U64 | Symbol::NUM_UNSIGNED64 => Some(Immediate(Symbol::NUM_U64)),
+        Symbol::NUM_U128 | Symbol::NUM_UNSIGNED128 => Some(Immediate(Symbol::NUM_U128)),
+        Symbol::NUM_I8 | Symbol::NUM_SIGNED8 => Some(Immediate(Symbol::NUM_I8)),
+        Symbol::NUM_I16 | Symbol::NUM_SIGNED16 => Some(Immediate(Symbol::NUM_I16)),
+        Symbol::NUM_I32 | Symbol::NUM_SIGNED32 => Some(Immediate(Symbol::NUM_I32)),
+        Symbol::NUM_I64 | Symbol::NUM_SIGNED64 => Some(Immediate(Symbol::NUM_I64)),
+        Symbol::NUM_I128 | Symbol::NUM_SIGNED128 => Some(Immediate(Symbol::NUM_I128)),
+        Symbol::NUM_NAT | Symbol::NUM_NATURAL => Some(Immediate(Symbol::NUM_NAT)),
+        Symbol::NUM_DEC | Symbol::NUM_DECIMAL => Some(Immediate(Symbol::NUM_DEC)),
+        Symbol::NUM_F32 | Symbol::NUM_BINARY32 => Some(Immediate(Symbol::NUM_F32)),
+        Symbol::NUM_F64 | Symbol::NUM_BINARY64 => Some(Immediate(Symbol::NUM_F64)),
+        _ => None,
+    }
+}
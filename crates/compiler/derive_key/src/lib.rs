@@ -15,6 +15,7 @@
 
 pub mod decoding;
 pub mod encoding;
+pub mod generate;
 pub mod hash;
 mod util;
 
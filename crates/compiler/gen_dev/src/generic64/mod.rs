@@ -1,20 +1,22 @@
 use crate::{
-    single_register_floats, single_register_int_builtins, single_register_integers, Backend, Env,
-    Relocation,
+    single_register_floats, single_register_int_builtins, single_register_integers,
+    AssemblyBackendMode, Backend, Env, Relocation,
 };
 use bumpalo::collections::Vec;
-use roc_builtins::bitcode::{self, FloatWidth, IntWidth};
+use roc_builtins::bitcode::{self, FloatWidth, IntWidth, IntrinsicName};
 use roc_collections::all::MutMap;
-use roc_error_macros::internal_error;
+use roc_error_macros::{internal_error, user_error};
 use roc_module::symbol::{Interns, ModuleId, Symbol};
 use roc_mono::code_gen_help::CodeGenHelp;
 use roc_mono::ir::{
-    BranchInfo, JoinPointId, ListLiteralElement, Literal, Param, ProcLayout, SelfRecursive, Stmt,
+    BranchInfo, JoinPointId, ListLiteralElement, Literal, LookupType, Param, ProcLayout,
+    SelfRecursive, Stmt,
 };
 use roc_mono::layout::{
     Builtin, InLayout, Layout, LayoutInterner, STLayoutInterner, TagIdIntType, UnionLayout,
 };
-use roc_target::TargetInfo;
+use roc_region::all::Region;
+use roc_target::{Architecture, TargetInfo};
 use std::marker::PhantomData;
 
 pub(crate) mod aarch64;
@@ -855,6 +857,73 @@ impl<
         }
     }
 
+    fn build_expect(
+        &mut self,
+        condition: &Symbol,
+        region: Region,
+        lookups: &'a [Symbol],
+        variables: &'a [LookupType],
+        notify_parent: bool,
+    ) {
+        self.check_expects_supported();
+
+        // Writing the failure calls into the builtins, which may clobber any register.
+        // Free everything to the stack so that both paths agree on where symbols live afterwards.
+        self.storage_manager.free_all_to_stack(&mut self.buf);
+        let (cond_offset, _) = self.storage_manager.stack_offset_and_size(condition);
+
+        // Skip over the failure if the condition is true.
+        // Since we don't know the offset yet, set it to 0 and overwrite later.
+        let mut jne = (0, 0, CC::GENERAL_RETURN_REGS[0]);
+        self.storage_manager
+            .with_tmp_general_reg(&mut self.buf, |_storage_manager, buf, reg| {
+                ASM::movzx_reg64_base32(buf, reg, cond_offset, 1);
+                let jne_location = buf.len();
+                let start_offset = ASM::jne_reg64_imm64_imm32(buf, reg, 0, 0);
+                jne = (jne_location, start_offset, reg);
+            });
+
+        let base_storage = self.storage_manager.clone();
+        let notify_parent = if notify_parent {
+            Some(bitcode::NOTIFY_PARENT_EXPECT)
+        } else {
+            None
+        };
+        self.clone_to_shared_memory(*condition, region, lookups, variables, notify_parent);
+
+        // Overwrite the original jne with the correct offset.
+        let (jne_location, start_offset, reg) = jne;
+        let mut tmp = bumpalo::vec![in self.env.arena];
+        let jne_offset = self.buf.len() - start_offset;
+        ASM::jne_reg64_imm64_imm32(&mut tmp, reg, 0, jne_offset as i32);
+        for (i, byte) in tmp.iter().enumerate() {
+            self.buf[jne_location + i] = *byte;
+        }
+
+        // Anything loaded while writing the failure is only loaded on that path.
+        let stack_size = self.storage_manager.stack_size();
+        let fn_call_stack_size = self.storage_manager.fn_call_stack_size();
+        self.storage_manager = base_storage;
+        self.storage_manager.update_stack_size(stack_size);
+        self.storage_manager
+            .update_fn_call_stack_size(fn_call_stack_size);
+    }
+
+    fn build_dbg(&mut self, symbol: &Symbol, variable: LookupType) {
+        self.check_expects_supported();
+
+        // dbg has no region of its own, the symbol is used to identify it instead.
+        let region = unsafe { std::mem::transmute::<_, Region>(*symbol) };
+
+        self.clone_to_shared_memory(
+            *symbol,
+            region,
+            &[*symbol],
+            &[variable],
+            Some(bitcode::NOTIFY_PARENT_DBG),
+        );
+    }
+
     fn build_num_abs(&mut self, dst: &Symbol, src: &Symbol, layout: &InLayout<'a>) {
         match self.interner().get(*layout) {
             Layout::Builtin(Builtin::Int(IntWidth::I64 | IntWidth::U64)) => {
@@ -895,10 +964,50 @@ impl<
                 let src2_reg = self.storage_manager.load_to_float_reg(&mut self.buf, src2);
                 ASM::add_freg32_freg32_freg32(&mut self.buf, dst_reg, src1_reg, src2_reg);
             }
+            Layout::Builtin(Builtin::Int(int_width @ (IntWidth::I128 | IntWidth::U128))) => self
+                .build_num_binop_fn_call(
+                    dst,
+                    &bitcode::NUM_ADD_OR_PANIC_INT[int_width],
+                    src1,
+                    src2,
+                    layout,
+                    layout,
+                ),
+            Layout::Builtin(Builtin::Decimal) => self.build_num_binop_fn_call(
+                dst,
+                bitcode::DEC_ADD_OR_PANIC,
+                src1,
+                src2,
+                layout,
+                layout,
+            ),
             x => todo!("NumAdd: layout, {:?}", x),
         }
     }
 
+    fn build_num_add_wrap(
+        &mut self,
+        dst: &Symbol,
+        src1: &Symbol,
+        src2: &Symbol,
+        layout: &InLayout<'a>,
+    ) {
+        match self.layout_interner.get(*layout) {
+            Layout::Builtin(Builtin::Int(quadword_and_smaller!())) => {
+                self.build_num_add(dst, src1, src2, layout)
+            }
+            Layout::Builtin(Builtin::Int(int_width)) => self.build_num_binop_fn_call(
+                dst,
+                &bitcode::NUM_ADD_WRAP_INT[int_width],
+                src1,
+                src2,
+                layout,
+                layout,
+            ),
+            x => todo!("NumAddWrap: layout, {:?}", x),
+        }
+    }
+
     fn build_num_add_checked(
         &mut self,
         dst: &Symbol,
@@ -907,46 +1016,42 @@ impl<
         num_layout: &InLayout<'a>,
         return_layout: &InLayout<'a>,
     ) {
-        use Builtin::Int;
-
-        let buf = &mut self.buf;
-
-        let struct_size = self.layout_interner.stack_size(*return_layout);
-
-        let base_offset = self.storage_manager.claim_stack_area(dst, struct_size);
-
         match self.layout_interner.get(*num_layout) {
-            Layout::Builtin(Int(IntWidth::I64 | IntWidth::I32 | IntWidth::I16 | IntWidth::I8)) => {
-                let dst_reg = self
-                    .storage_manager
-                    .claim_general_reg(buf, &Symbol::DEV_TMP);
-
-                let overflow_reg = self
-                    .storage_manager
-                    .claim_general_reg(buf, &Symbol::DEV_TMP2);
-
-                let src1_reg = self.storage_manager.load_to_general_reg(buf, src1);
-                let src2_reg = self.storage_manager.load_to_general_reg(buf, src2);
-
-                ASM::add_reg64_reg64_reg64(buf, dst_reg, src1_reg, src2_reg);
-                ASM::set_if_overflow(buf, overflow_reg);
-
-                ASM::mov_base32_reg64(buf, base_offset, dst_reg);
-                ASM::mov_base32_reg64(buf, base_offset + 8, overflow_reg);
-
-                self.free_symbol(&Symbol::DEV_TMP);
-                self.free_symbol(&Symbol::DEV_TMP2);
-            }
-            Layout::Builtin(Int(IntWidth::U64 | IntWidth::U32 | IntWidth::U16 | IntWidth::U8)) => {
-                todo!("addChecked for unsigned integers")
-            }
-            Layout::Builtin(Builtin::Float(FloatWidth::F64)) => {
-                todo!("addChecked for f64")
-            }
-            Layout::Builtin(Builtin::Float(FloatWidth::F32)) => {
-                todo!("addChecked for f32")
+            Layout::Builtin(Builtin::Int(IntWidth::I64)) => {
+                self.build_int64_checked(dst, src1, src2, return_layout, ASM::add_reg64_reg64_reg64)
             }
-            x => todo!("NumAdd: layout, {:?}", x),
+            Layout::Builtin(Builtin::Int(int_width)) => self.build_num_binop_fn_call(
+                dst,
+                &bitcode::NUM_ADD_CHECKED_INT[int_width],
+                src1,
+                src2,
+                num_layout,
+                return_layout,
+            ),
+            Layout::Builtin(Builtin::Float(FloatWidth::F64)) => self.build_float64_checked(
+                dst,
+                src1,
+                src2,
+                return_layout,
+                ASM::add_freg64_freg64_freg64,
+            ),
+            Layout::Builtin(Builtin::Float(float_width)) => self.build_num_binop_fn_call(
+                dst,
+                &bitcode::NUM_ADD_CHECKED_FLOAT[float_width],
+                src1,
+                src2,
+                num_layout,
+                return_layout,
+            ),
+            Layout::Builtin(Builtin::Decimal) => self.build_num_binop_fn_call(
+                dst,
+                bitcode::DEC_ADD_WITH_OVERFLOW,
+                src1,
+                src2,
+                num_layout,
+                return_layout,
+            ),
+            x => todo!("NumAddChecked: layout, {:?}", x),
         }
     }
 
@@ -993,10 +1098,101 @@ impl<
                 let src2_reg = self.storage_manager.load_to_float_reg(&mut self.buf, src2);
                 ASM::mul_freg32_freg32_freg32(&mut self.buf, dst_reg, src1_reg, src2_reg);
             }
+            Layout::Builtin(Int(int_width @ (IntWidth::I128 | IntWidth::U128))) => self
+                .build_num_binop_fn_call(
+                    dst,
+                    &bitcode::NUM_MUL_OR_PANIC_INT[int_width],
+                    src1,
+                    src2,
+                    layout,
+                    layout,
+                ),
+            Layout::Builtin(Builtin::Decimal) => self.build_num_binop_fn_call(
+                dst,
+                bitcode::DEC_MUL_OR_PANIC,
+                src1,
+                src2,
+                layout,
+                layout,
+            ),
             x => todo!("NumMul: layout, {:?}", x),
         }
     }
 
+    fn build_num_mul_wrap(
+        &mut self,
+        dst: &Symbol,
+        src1: &Symbol,
+        src2: &Symbol,
+        layout: &InLayout<'a>,
+    ) {
+        match self.layout_interner.get(*layout) {
+            Layout::Builtin(Builtin::Int(quadword_and_smaller!())) => {
+                self.build_num_mul(dst, src1, src2, layout)
+            }
+            Layout::Builtin(Builtin::Int(int_width)) => self.build_num_binop_fn_call(
+                dst,
+                &bitcode::NUM_MUL_WRAP_INT[int_width],
+                src1,
+                src2,
+                layout,
+                layout,
+            ),
+            x => todo!("NumMulWrap: layout, {:?}", x),
+        }
+    }
+
+    fn build_num_mul_checked(
+        &mut self,
+        dst: &Symbol,
+        src1: &Symbol,
+        src2: &Symbol,
+        num_layout: &InLayout<'a>,
+        return_layout: &InLayout<'a>,
+    ) {
+        match self.layout_interner.get(*num_layout) {
+            Layout::Builtin(Builtin::Int(IntWidth::I64)) => self.build_int64_checked(
+                dst,
+                src1,
+                src2,
+                return_layout,
+                ASM::imul_reg64_reg64_reg64,
+            ),
+            Layout::Builtin(Builtin::Int(int_width)) => self.build_num_binop_fn_call(
+                dst,
+                &bitcode::NUM_MUL_CHECKED_INT[int_width],
+                src1,
+                src2,
+                num_layout,
+                return_layout,
+            ),
+            Layout::Builtin(Builtin::Float(FloatWidth::F64)) => self.build_float64_checked(
+                dst,
+                src1,
+                src2,
+                return_layout,
+                ASM::mul_freg64_freg64_freg64,
+            ),
+            Layout::Builtin(Builtin::Float(float_width)) => self.build_num_binop_fn_call(
+                dst,
+                &bitcode::NUM_MUL_CHECKED_FLOAT[float_width],
+                src1,
+                src2,
+                num_layout,
+                return_layout,
+            ),
+            Layout::Builtin(Builtin::Decimal) => self.build_num_binop_fn_call(
+                dst,
+                bitcode::DEC_MUL_WITH_OVERFLOW,
+                src1,
+                src2,
+                num_layout,
+                return_layout,
+            ),
+            x => todo!("NumMulChecked: layout, {:?}", x),
+        }
+    }
+
     fn build_num_div(&mut self, dst: &Symbol, src1: &Symbol, src2: &Symbol, layout: &InLayout<'a>) {
         match self.layout_interner.get(*layout) {
             Layout::Builtin(Builtin::Int(
@@ -1049,6 +1245,18 @@ impl<
                 let src2_reg = self.storage_manager.load_to_float_reg(&mut self.buf, src2);
                 ASM::div_freg32_freg32_freg32(&mut self.buf, dst_reg, src1_reg, src2_reg);
             }
+            Layout::Builtin(Builtin::Int(int_width @ (IntWidth::I128 | IntWidth::U128))) => self
+                .build_num_binop_fn_call(
+                    dst,
+                    &bitcode::NUM_DIV_TRUNC[int_width],
+                    src1,
+                    src2,
+                    layout,
+                    layout,
+                ),
+            Layout::Builtin(Builtin::Decimal) => {
+                self.build_num_binop_fn_call(dst, bitcode::DEC_DIV, src1, src2, layout, layout)
+            }
             x => todo!("NumDiv: layout, {:?}", x),
         }
     }
@@ -1060,14 +1268,42 @@ impl<
                 let src_reg = self.storage_manager.load_to_general_reg(&mut self.buf, src);
                 ASM::neg_reg64_reg64(&mut self.buf, dst_reg, src_reg);
             }
+            Layout::Builtin(Builtin::Decimal) => self.build_fn_call(
+                dst,
+                bitcode::DEC_NEGATE.to_string(),
+                &[*src],
+                &[*layout],
+                layout,
+            ),
             x => todo!("NumNeg: layout, {:?}", x),
         }
     }
 
     fn build_num_sub(&mut self, dst: &Symbol, src1: &Symbol, src2: &Symbol, layout: &InLayout<'a>) {
-        // for the time being, `num_sub` is implemented as wrapping subtraction. In roc, the normal
-        // `sub` should panic on overflow, but we just don't do that yet
-        self.build_num_sub_wrap(dst, src1, src2, layout)
+        match self.layout_interner.get(*layout) {
+            Layout::Builtin(Builtin::Int(int_width @ (IntWidth::I128 | IntWidth::U128))) => self
+                .build_num_binop_fn_call(
+                    dst,
+                    &bitcode::NUM_SUB_OR_PANIC_INT[int_width],
+                    src1,
+                    src2,
+                    layout,
+                    layout,
+                ),
+            Layout::Builtin(Builtin::Decimal) => self.build_num_binop_fn_call(
+                dst,
+                bitcode::DEC_SUB_OR_PANIC,
+                src1,
+                src2,
+                layout,
+                layout,
+            ),
+            _ => {
+                // for the time being, `num_sub` is implemented as wrapping subtraction. In roc, the normal
+                // `sub` should panic on overflow, but we just don't do that yet
+                self.build_num_sub_wrap(dst, src1, src2, layout)
+            }
+        }
     }
 
     fn build_num_sub_wrap(
@@ -1088,10 +1324,58 @@ impl<
                     .load_to_general_reg(&mut self.buf, src2);
                 ASM::sub_reg64_reg64_reg64(&mut self.buf, dst_reg, src1_reg, src2_reg);
             }
+            Layout::Builtin(Builtin::Int(int_width)) => self.build_num_binop_fn_call(
+                dst,
+                &bitcode::NUM_SUB_WRAP_INT[int_width],
+                src1,
+                src2,
+                layout,
+                layout,
+            ),
             x => todo!("NumSubWrap: layout, {:?}", x),
         }
     }
 
+    fn build_num_sub_checked(
+        &mut self,
+        dst: &Symbol,
+        src1: &Symbol,
+        src2: &Symbol,
+        num_layout: &InLayout<'a>,
+        return_layout: &InLayout<'a>,
+    ) {
+        match self.layout_interner.get(*num_layout) {
+            Layout::Builtin(Builtin::Int(IntWidth::I64)) => {
+                self.build_int64_checked(dst, src1, src2, return_layout, ASM::sub_reg64_reg64_reg64)
+            }
+            Layout::Builtin(Builtin::Int(int_width)) => self.build_num_binop_fn_call(
+                dst,
+                &bitcode::NUM_SUB_CHECKED_INT[int_width],
+                src1,
+                src2,
+                num_layout,
+                return_layout,
+            ),
+            Layout::Builtin(Builtin::Float(FloatWidth::F32)) => self.build_num_binop_fn_call(
+                dst,
+                &bitcode::NUM_SUB_CHECKED_FLOAT[FloatWidth::F32],
+                src1,
+                src2,
+                num_layout,
+                return_layout,
+            ),
+            Layout::Builtin(Builtin::Decimal) => self.build_num_binop_fn_call(
+                dst,
+                bitcode::DEC_SUB_WITH_OVERFLOW,
+                src1,
+                src2,
+                num_layout,
+                return_layout,
+            ),
+            x => todo!("NumSubChecked: layout, {:?}", x),
+        }
+    }
+
     fn build_eq(&mut self, dst: &Symbol, src1: &Symbol, src2: &Symbol, arg_layout: &InLayout<'a>) {
         match *arg_layout {
             single_register_int_builtins!() => {
@@ -1104,6 +1388,13 @@ impl<
                     .load_to_general_reg(&mut self.buf, src2);
                 ASM::eq_reg64_reg64_reg64(&mut self.buf, dst_reg, src1_reg, src2_reg);
             }
+            Layout::I128 | Layout::U128 | Layout::DEC => self.build_int128_eq(
+                dst,
+                src1,
+                src2,
+                ASM::eq_reg64_reg64_reg64,
+                ASM::and_reg64_reg64_reg64,
+            ),
             x => todo!("NumEq: layout, {:?}", x),
         }
     }
@@ -1120,6 +1411,15 @@ impl<
                     .load_to_general_reg(&mut self.buf, src2);
                 ASM::neq_reg64_reg64_reg64(&mut self.buf, dst_reg, src1_reg, src2_reg);
             }
+            Layout::Builtin(Builtin::Int(IntWidth::I128 | IntWidth::U128) | Builtin::Decimal) => {
+                self.build_int128_eq(
+                    dst,
+                    src1,
+                    src2,
+                    ASM::neq_reg64_reg64_reg64,
+                    ASM::or_reg64_reg64_reg64,
+                )
+            }
             x => todo!("NumNeq: layout, {:?}", x),
         }
     }
@@ -1152,6 +1452,9 @@ impl<
                     .load_to_general_reg(&mut self.buf, src2);
                 ASM::ult_reg64_reg64_reg64(&mut self.buf, dst_reg, src1_reg, src2_reg);
             }
+            Layout::Builtin(Builtin::Int(IntWidth::I128 | IntWidth::U128) | Builtin::Decimal) => {
+                self.build_int128_compare(dst, &bitcode::NUM_LESS_THAN, src1, src2, arg_layout)
+            }
             x => todo!("NumLt: layout, {:?}", x),
         }
     }
@@ -1184,6 +1487,10 @@ impl<
                     .load_to_general_reg(&mut self.buf, src2);
                 ASM::ugt_reg64_reg64_reg64(&mut self.buf, dst_reg, src1_reg, src2_reg);
             }
+            Layout::Builtin(Builtin::Int(IntWidth::I128 | IntWidth::U128) | Builtin::Decimal) => {
+                // `a > b` is `b < a`
+                self.build_int128_compare(dst, &bitcode::NUM_LESS_THAN, src2, src1, arg_layout)
+            }
             x => todo!("NumGt: layout, {:?}", x),
         }
     }
@@ -1264,6 +1571,13 @@ impl<
                     .load_to_general_reg(&mut self.buf, src2);
                 ASM::lte_reg64_reg64_reg64(&mut self.buf, dst_reg, src1_reg, src2_reg);
             }
+            Layout::I128 | Layout::U128 | Layout::DEC => self.build_int128_compare(
+                dst,
+                &bitcode::NUM_LESS_THAN_OR_EQUAL,
+                src1,
+                src2,
+                arg_layout,
+            ),
             x => todo!("NumLte: layout, {:?}", x),
         }
     }
@@ -1286,6 +1600,16 @@ impl<
                     .load_to_general_reg(&mut self.buf, src2);
                 ASM::gte_reg64_reg64_reg64(&mut self.buf, dst_reg, src1_reg, src2_reg);
             }
+            Layout::I128 | Layout::U128 | Layout::DEC => {
+                // `a >= b` is `b <= a`
+                self.build_int128_compare(
+                    dst,
+                    &bitcode::NUM_LESS_THAN_OR_EQUAL,
+                    src2,
+                    src1,
+                    arg_layout,
+                )
+            }
             x => todo!("NumGte: layout, {:?}", x),
         }
     }
@@ -1988,6 +2312,29 @@ impl<
                 let val = *x;
                 ASM::mov_reg64_imm64(&mut self.buf, reg, i128::from_ne_bytes(val) as i64);
             }
+            (
+                Literal::Int(bytes) | Literal::U128(bytes),
+                Layout::Builtin(Builtin::Int(IntWidth::I128 | IntWidth::U128)),
+            )
+            | (Literal::Decimal(bytes), Layout::Builtin(Builtin::Decimal)) => {
+                self.storage_manager.with_tmp_general_reg(
+                    &mut self.buf,
+                    |storage_manager, buf, reg| {
+                        let base_offset = storage_manager.claim_stack_area(sym, 16);
+
+                        let mut num_bytes = [0; 8];
+                        num_bytes.copy_from_slice(&bytes[..8]);
+                        let num = i64::from_ne_bytes(num_bytes);
+                        ASM::mov_reg64_imm64(buf, reg, num);
+                        ASM::mov_base32_reg64(buf, base_offset, reg);
+
+                        num_bytes.copy_from_slice(&bytes[8..16]);
+                        let num = i64::from_ne_bytes(num_bytes);
+                        ASM::mov_reg64_imm64(buf, reg, num);
+                        ASM::mov_base32_reg64(buf, base_offset + 8, reg);
+                    },
+                );
+            }
             (Literal::Bool(x), Layout::Builtin(Builtin::Bool)) => {
                 let reg = self.storage_manager.claim_general_reg(&mut self.buf, sym);
                 let val = [*x as u8; 16];
@@ -2273,6 +2620,462 @@ impl<
         CC: CallConv<GeneralReg, FloatReg, ASM>,
     > Backend64Bit<'a, 'r, GeneralReg, FloatReg, ASM, CC>
 {
+    /// Expects and dbgs need instructions that only the x86_64 assembler implements so far.
+    fn check_expects_supported(&self) {
+        if self.target_info.architecture != Architecture::X86_64 {
+            user_error!(
+                "The dev backend can't compile `expect` or `dbg` for {:?} yet.\n\nBuild without `--dev` to use the LLVM backend instead.",
+                self.target_info.architecture
+            );
+        }
+    }
+
+    /// Writes a failed expect (or a dbg) into the shared memory, in the format `roc_repl_expect` reads:
+    /// the region and module, then the offset and variable of each lookup, then the lookups.
+    /// The memory starts with the number of failures so far and the offset of the next one.
+    ///
+    /// All lookups are written before the data they point to (like the bytes of a Str),
+    /// so that the offset of each lookup is known when generating code.
+    fn clone_to_shared_memory(
+        &mut self,
+        condition: Symbol,
+        region: Region,
+        lookups: &[Symbol],
+        variables: &[LookupType],
+        notify_parent: Option<&str>,
+    ) {
+        // The shared memory, and the offset (from its start) to write the next heap data at.
+        let ptr = Symbol::DEV_TMP;
+        let extra_offset = Symbol::DEV_TMP3;
+
+        let start_fn = match self.env.mode {
+            AssemblyBackendMode::BinaryDev => bitcode::UTILS_EXPECT_FAILED_START_SHARED_FILE,
+            AssemblyBackendMode::Binary | AssemblyBackendMode::Test => {
+                bitcode::UTILS_EXPECT_FAILED_START_SHARED_BUFFER
+            }
+        };
+        self.build_fn_call(&ptr, start_fn.to_string(), &[], &[], &Layout::U64);
+
+        const HEADER_SIZE: i32 = 3 * 4;
+        const LOOKUP_SIZE: i32 = 8 + 4;
+
+        let mut value_offsets = bumpalo::vec![in self.env.arena];
+        let mut data_offset = HEADER_SIZE + LOOKUP_SIZE * lookups.len() as i32;
+        for lookup in lookups {
+            let layout = self.layout_map.get(lookup).unwrap();
+            value_offsets.push(data_offset);
+            data_offset += self.layout_interner.stack_size(*layout) as i32;
+        }
+
+        // Every store is 8 bytes, and may overwrite the start of whatever comes next.
+        // That is fine, since everything is written in order.
+        let region_bits = region.start().offset as u64 | (region.end().offset as u64) << 32;
+        let module_id: u32 = unsafe { std::mem::transmute(condition.module_id()) };
+        self.with_failure_address(|storage_manager, buf, offset_reg, addr_reg| {
+            storage_manager.with_tmp_general_reg(buf, |_storage_manager, buf, tmp| {
+                ASM::mov_reg64_imm64(buf, tmp, region_bits as i64);
+                ASM::mov_mem64_offset32_reg64(buf, addr_reg, 0, tmp);
+                ASM::mov_reg64_imm64(buf, tmp, module_id as i64);
+                ASM::mov_mem64_offset32_reg64(buf, addr_reg, 4 + 4, tmp);
+
+                for (i, (value_offset, variable)) in value_offsets.iter().zip(variables).enumerate()
+                {
+                    let lookup_offset = HEADER_SIZE + LOOKUP_SIZE * i as i32;
+                    ASM::add_reg64_reg64_imm32(buf, tmp, offset_reg, *value_offset);
+                    ASM::mov_mem64_offset32_reg64(buf, addr_reg, lookup_offset, tmp);
+                    ASM::mov_reg64_imm64(buf, tmp, variable.index() as i64);
+                    ASM::mov_mem64_offset32_reg64(buf, addr_reg, lookup_offset + 8, tmp);
+                }
+            });
+        });
+
+        self.load_failure_offset(&extra_offset);
+        let extra_offset_reg = self
+            .storage_manager
+            .load_to_general_reg(&mut self.buf, &extra_offset);
+        ASM::add_reg64_reg64_imm32(
+            &mut self.buf,
+            extra_offset_reg,
+            extra_offset_reg,
+            data_offset,
+        );
+
+        for (lookup, value_offset) in lookups.iter().zip(value_offsets) {
+            self.clone_lookup(lookup, value_offset);
+        }
+
+        // Count this failure, and put the next one after the data of this one.
+        let ptr_reg = self
+            .storage_manager
+            .load_to_general_reg(&mut self.buf, &ptr);
+        let extra_offset_reg = self
+            .storage_manager
+            .load_to_general_reg(&mut self.buf, &extra_offset);
+        self.storage_manager
+            .with_tmp_general_reg(&mut self.buf, |_storage_manager, buf, tmp| {
+                ASM::mov_reg64_mem64_offset32(buf, tmp, ptr_reg, 0);
+                ASM::add_reg64_reg64_imm32(buf, tmp, tmp, 1);
+                ASM::mov_mem64_offset32_reg64(buf, ptr_reg, 0, tmp);
+                ASM::mov_mem64_offset32_reg64(buf, ptr_reg, 8, extra_offset_reg);
+            });
+        self.free_symbol(&extra_offset);
+
+        if let Some(notify_fn) = notify_parent {
+            self.build_fn_call(
+                &Symbol::DEV_TMP2,
+                notify_fn.to_string(),
+                &[ptr],
+                &[Layout::U64],
+                &Layout::UNIT,
+            );
+        }
+        self.free_symbol(&ptr);
+    }
+
+    /// Loads the offset of the failure being written, which stays in the shared memory until it is done.
+    fn load_failure_offset(&mut self, dst: &Symbol) {
+        let ptr_reg = self
+            .storage_manager
+            .load_to_general_reg(&mut self.buf, &Symbol::DEV_TMP);
+        let dst_reg = self.storage_manager.claim_general_reg(&mut self.buf, dst);
+        ASM::mov_reg64_mem64_offset32(&mut self.buf, dst_reg, ptr_reg, 8);
+    }
+
+    /// Calls `f` with the offset of the failure being written, and its address.
+    fn with_failure_address<F>(&mut self, f: F)
+    where
+        F: FnOnce(
+            &mut StorageManager<'a, 'r, GeneralReg, FloatReg, ASM, CC>,
+            &mut Vec<'a, u8>,
+            GeneralReg,
+            GeneralReg,
+        ),
+    {
+        self.load_failure_offset(&Symbol::DEV_TMP2);
+        let ptr_reg = self
+            .storage_manager
+            .load_to_general_reg(&mut self.buf, &Symbol::DEV_TMP);
+        let offset_reg = self
+            .storage_manager
+            .load_to_general_reg(&mut self.buf, &Symbol::DEV_TMP2);
+        self.storage_manager.with_tmp_general_reg(
+            &mut self.buf,
+            |storage_manager, buf, addr_reg| {
+                ASM::add_reg64_reg64_reg64(buf, addr_reg, ptr_reg, offset_reg);
+                f(storage_manager, buf, offset_reg, addr_reg);
+            },
+        );
+        self.free_symbol(&Symbol::DEV_TMP2);
+    }
+
+    /// Copies `sym` to `value_offset` from the start of the failure being written,
+    /// and any heap data it has to the extra offset.
+    fn clone_lookup(&mut self, sym: &Symbol, value_offset: i32) {
+        let ptr = Symbol::DEV_TMP;
+        let extra_offset = Symbol::DEV_TMP3;
+        let layout = *self.layout_map.get(sym).unwrap();
+
+        if self.layout_interner.stack_size(layout) == 0 {
+            return;
+        }
+
+        match self.layout_interner.get(layout) {
+            Layout::Builtin(Builtin::Str) => {
+                self.load_failure_offset(&Symbol::DEV_TMP2);
+                let offset_reg = self
+                    .storage_manager
+                    .load_to_general_reg(&mut self.buf, &Symbol::DEV_TMP2);
+                ASM::add_reg64_reg64_imm32(&mut self.buf, offset_reg, offset_reg, value_offset);
+
+                self.build_fn_call(
+                    &Symbol::DEV_TMP4,
+                    bitcode::STR_CLONE_TO.to_string(),
+                    &[*sym, ptr, Symbol::DEV_TMP2, extra_offset],
+                    &[Layout::STR, Layout::U64, Layout::U64, Layout::U64],
+                    &Layout::U64,
+                );
+                self.free_symbol(&Symbol::DEV_TMP2);
+                self.free_symbol(&extra_offset);
+                self.move_general_symbol(&extra_offset, &Symbol::DEV_TMP4);
+            }
+            Layout::Builtin(Builtin::List(elem_layout))
+                if self.layout_interner.safe_to_memcpy(elem_layout) =>
+            {
+                self.storage_manager
+                    .ensure_symbol_on_stack(&mut self.buf, sym);
+                let (list_offset, _) = self.storage_manager.stack_offset_and_size(sym);
+
+                // The elements go to the extra offset, without any spare capacity.
+                let extra_offset_reg = self
+                    .storage_manager
+                    .load_to_general_reg(&mut self.buf, &extra_offset);
+                self.with_failure_address(|storage_manager, buf, _offset_reg, addr_reg| {
+                    storage_manager.with_tmp_general_reg(buf, |_storage_manager, buf, tmp| {
+                        ASM::mov_mem64_offset32_reg64(
+                            buf,
+                            addr_reg,
+                            value_offset,
+                            extra_offset_reg,
+                        );
+                        ASM::mov_reg64_base32(buf, tmp, list_offset + 8);
+                        ASM::mov_mem64_offset32_reg64(buf, addr_reg, value_offset + 8, tmp);
+                        ASM::mov_mem64_offset32_reg64(buf, addr_reg, value_offset + 16, tmp);
+                    });
+                });
+
+                // Copy the elements with roc_memcpy(ptr + extra_offset, elements, len * element_width).
+                let dst_reg = self
+                    .storage_manager
+                    .claim_general_reg(&mut self.buf, &Symbol::DEV_TMP2);
+                let ptr_reg = self
+                    .storage_manager
+                    .load_to_general_reg(&mut self.buf, &ptr);
+                let extra_offset_reg = self
+                    .storage_manager
+                    .load_to_general_reg(&mut self.buf, &extra_offset);
+                ASM::add_reg64_reg64_reg64(&mut self.buf, dst_reg, ptr_reg, extra_offset_reg);
+                self.free_symbol(&extra_offset);
+
+                let src_reg = self
+                    .storage_manager
+                    .claim_general_reg(&mut self.buf, &extra_offset);
+                ASM::mov_reg64_base32(&mut self.buf, src_reg, list_offset);
+
+                let element_width = self.layout_interner.stack_size(elem_layout);
+                let size_reg = self
+                    .storage_manager
+                    .claim_general_reg(&mut self.buf, &Symbol::DEV_TMP5);
+                ASM::mov_reg64_base32(&mut self.buf, size_reg, list_offset + 8);
+                self.storage_manager.with_tmp_general_reg(
+                    &mut self.buf,
+                    |_storage_manager, buf, tmp| {
+                        ASM::mov_reg64_imm64(buf, tmp, element_width as i64);
+                        ASM::imul_reg64_reg64_reg64(buf, size_reg, size_reg, tmp);
+                    },
+                );
+
+                self.build_fn_call(
+                    &Symbol::DEV_TMP4,
+                    "roc_memcpy".to_string(),
+                    &[Symbol::DEV_TMP2, extra_offset, Symbol::DEV_TMP5],
+                    &[Layout::U64, Layout::U64, Layout::U64],
+                    &Layout::UNIT,
+                );
+                self.free_symbol(&extra_offset);
+
+                // The next heap data goes after the elements.
+                let extra_offset_reg = self
+                    .storage_manager
+                    .claim_general_reg(&mut self.buf, &extra_offset);
+                let dst_reg = self
+                    .storage_manager
+                    .load_to_general_reg(&mut self.buf, &Symbol::DEV_TMP2);
+                let ptr_reg = self
+                    .storage_manager
+                    .load_to_general_reg(&mut self.buf, &ptr);
+                let size_reg = self
+                    .storage_manager
+                    .load_to_general_reg(&mut self.buf, &Symbol::DEV_TMP5);
+                ASM::sub_reg64_reg64_reg64(&mut self.buf, extra_offset_reg, dst_reg, ptr_reg);
+                ASM::add_reg64_reg64_reg64(
+                    &mut self.buf,
+                    extra_offset_reg,
+                    extra_offset_reg,
+                    size_reg,
+                );
+                self.free_symbol(&Symbol::DEV_TMP2);
+                self.free_symbol(&Symbol::DEV_TMP5);
+            }
+            _ if self.layout_interner.safe_to_memcpy(layout) => {
+                self.storage_manager
+                    .ensure_symbol_on_stack(&mut self.buf, sym);
+                let (base_offset, _) = self.storage_manager.stack_offset_and_size(sym);
+                let size = self.layout_interner.stack_size(layout) as i32;
+
+                self.with_failure_address(|storage_manager, buf, _offset_reg, addr_reg| {
+                    storage_manager.with_tmp_general_reg(buf, |_storage_manager, buf, tmp| {
+                        for i in (0..size).step_by(8) {
+                            ASM::mov_reg64_base32(buf, tmp, base_offset + i);
+                            ASM::mov_mem64_offset32_reg64(buf, addr_reg, value_offset + i, tmp);
+                        }
+                    });
+                });
+            }
+            _ => user_error!(
+                "The dev backend can't yet show the value of {:?} in a failed expect or dbg, because it has a Str or List inside of another value. Build without `--dev` to see it.",
+                sym
+            ),
+        }
+    }
+
+    /// Calls the zig builtin `fn_name` with src1 and src2, which both have the given layout.
+    fn build_num_binop_fn_call(
+        &mut self,
+        dst: &Symbol,
+        fn_name: &str,
+        src1: &Symbol,
+        src2: &Symbol,
+        layout: &InLayout<'a>,
+        ret_layout: &InLayout<'a>,
+    ) {
+        self.build_fn_call(
+            dst,
+            fn_name.to_string(),
+            &[*src1, *src2],
+            &[*layout, *layout],
+            ret_layout,
+        )
+    }
+
+    /// Stores the result of `op` and whether it overflowed into the struct at dst.
+    /// The overflow flag of the cpu is only right for full registers,
+    /// so narrower integers use the zig builtins instead.
+    fn build_int64_checked<F>(
+        &mut self,
+        dst: &Symbol,
+        src1: &Symbol,
+        src2: &Symbol,
+        return_layout: &InLayout<'a>,
+        op: F,
+    ) where
+        F: FnOnce(&mut Vec<'a, u8>, GeneralReg, GeneralReg, GeneralReg),
+    {
+        let buf = &mut self.buf;
+
+        let struct_size = self.layout_interner.stack_size(*return_layout);
+
+        let base_offset = self.storage_manager.claim_stack_area(dst, struct_size);
+
+        let dst_reg = self
+            .storage_manager
+            .claim_general_reg(buf, &Symbol::DEV_TMP);
+
+        let overflow_reg = self
+            .storage_manager
+            .claim_general_reg(buf, &Symbol::DEV_TMP2);
+
+        let src1_reg = self.storage_manager.load_to_general_reg(buf, src1);
+        let src2_reg = self.storage_manager.load_to_general_reg(buf, src2);
+
+        op(buf, dst_reg, src1_reg, src2_reg);
+        ASM::set_if_overflow(buf, overflow_reg);
+
+        ASM::mov_base32_reg64(buf, base_offset, dst_reg);
+        ASM::mov_base32_reg64(buf, base_offset + 8, overflow_reg);
+
+        self.free_symbol(&Symbol::DEV_TMP);
+        self.free_symbol(&Symbol::DEV_TMP2);
+    }
+
+    /// Stores the result of `op` and whether it overflowed into the struct at dst.
+    /// Like in the zig builtins, a float overflowed if it is not finite, which means all its exponent bits are set.
+    /// (The zig builtins themselves return this struct partly in a float register, which `build_fn_call` can't handle.)
+    fn build_float64_checked<F>(
+        &mut self,
+        dst: &Symbol,
+        src1: &Symbol,
+        src2: &Symbol,
+        return_layout: &InLayout<'a>,
+        op: F,
+    ) where
+        F: FnOnce(&mut Vec<'a, u8>, FloatReg, FloatReg, FloatReg),
+    {
+        let buf = &mut self.buf;
+
+        let struct_size = self.layout_interner.stack_size(*return_layout);
+
+        let base_offset = self.storage_manager.claim_stack_area(dst, struct_size);
+
+        let dst_reg = self.storage_manager.claim_float_reg(buf, &Symbol::DEV_TMP);
+        let src1_reg = self.storage_manager.load_to_float_reg(buf, src1);
+        let src2_reg = self.storage_manager.load_to_float_reg(buf, src2);
+
+        op(buf, dst_reg, src1_reg, src2_reg);
+        ASM::mov_base32_freg64(buf, base_offset, dst_reg);
+
+        self.free_symbol(&Symbol::DEV_TMP);
+
+        self.storage_manager.with_tmp_general_reg(
+            &mut self.buf,
+            |storage_manager, buf, bits_reg| {
+                storage_manager.with_tmp_general_reg(buf, |_, buf, mask_reg| {
+                    ASM::mov_reg64_base32(buf, bits_reg, base_offset);
+                    ASM::mov_reg64_imm64(buf, mask_reg, 0x7FF0_0000_0000_0000);
+                    ASM::and_reg64_reg64_reg64(buf, bits_reg, bits_reg, mask_reg);
+                    ASM::eq_reg64_reg64_reg64(buf, bits_reg, bits_reg, mask_reg);
+                    ASM::mov_base32_reg64(buf, base_offset + 8, bits_reg);
+                });
+            },
+        );
+    }
+
+    /// Compares the low and high halves of two 128-bit numbers with `compare`,
+    /// and stores the two results combined with `combine` into dst.
+    fn build_int128_eq<F, G>(
+        &mut self,
+        dst: &Symbol,
+        src1: &Symbol,
+        src2: &Symbol,
+        compare: F,
+        combine: G,
+    ) where
+        F: Fn(&mut Vec<'a, u8>, GeneralReg, GeneralReg, GeneralReg),
+        G: FnOnce(&mut Vec<'a, u8>, GeneralReg, GeneralReg, GeneralReg),
+    {
+        let (src1_offset, _) = self.storage_manager.stack_offset_and_size(src1);
+        let (src2_offset, _) = self.storage_manager.stack_offset_and_size(src2);
+
+        let dst_reg = self.storage_manager.claim_general_reg(&mut self.buf, dst);
+
+        self.storage_manager
+            .with_tmp_general_reg(&mut self.buf, |storage_manager, buf, reg1| {
+                storage_manager.with_tmp_general_reg(buf, |_, buf, reg2| {
+                    ASM::mov_reg64_base32(buf, reg1, src1_offset);
+                    ASM::mov_reg64_base32(buf, reg2, src2_offset);
+                    compare(buf, dst_reg, reg1, reg2);
+
+                    ASM::mov_reg64_base32(buf, reg1, src1_offset + 8);
+                    ASM::mov_reg64_base32(buf, reg2, src2_offset + 8);
+                    compare(buf, reg1, reg1, reg2);
+
+                    combine(buf, dst_reg, dst_reg, reg1);
+                });
+            });
+    }
+
+    /// Orders two 128-bit numbers with one of the zig comparison builtins.
+    /// A Dec compares like the I128 it wraps.
+    fn build_int128_compare(
+        &mut self,
+        dst: &Symbol,
+        fn_names: &IntrinsicName,
+        src1: &Symbol,
+        src2: &Symbol,
+        arg_layout: &InLayout<'a>,
+    ) {
+        let int_width = match *arg_layout {
+            Layout::U128 => IntWidth::U128,
+            _ => IntWidth::I128,
+        };
+
+        self.build_num_binop_fn_call(
+            dst,
+            &fn_names[int_width],
+            src1,
+            src2,
+            arg_layout,
+            &Layout::BOOL,
+        )
+    }
+
+    /// Moves a value in a general register from `src` to `dst`, freeing `src`.
+    fn move_general_symbol(&mut self, dst: &Symbol, src: &Symbol) {
+        let src_reg = self.storage_manager.load_to_general_reg(&mut self.buf, src);
+        let dst_reg = self.storage_manager.claim_general_reg(&mut self.buf, dst);
+        ASM::mov_reg64_reg64(&mut self.buf, dst_reg, src_reg);
+        self.free_symbol(src);
+    }
+
     /// Updates a jump instruction to a new offset and returns the number of bytes written.
     fn update_jmp_imm32_offset(
         &mut self,
@@ -248,7 +248,7 @@ impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64Syste
 
     #[inline(always)]
     fn load_args<'a, 'r>(
-        _buf: &mut Vec<'a, u8>,
+        buf: &mut Vec<'a, u8>,
         storage_manager: &mut StorageManager<
             'a,
             'r,
@@ -289,6 +289,26 @@ impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64Syste
                         arg_offset += 8;
                     }
                 }
+                Layout::I128 | Layout::U128 | Layout::DEC => {
+                    // 128-bit numbers are passed in two general registers, or entirely on the stack.
+                    if general_i + 1 < Self::GENERAL_PARAM_REGS.len() {
+                        let base_offset = storage_manager.claim_stack_area(sym, stack_size);
+                        X86_64Assembler::mov_base32_reg64(
+                            buf,
+                            base_offset,
+                            Self::GENERAL_PARAM_REGS[general_i],
+                        );
+                        X86_64Assembler::mov_base32_reg64(
+                            buf,
+                            base_offset + 8,
+                            Self::GENERAL_PARAM_REGS[general_i + 1],
+                        );
+                        general_i += 2;
+                    } else {
+                        storage_manager.complex_stack_arg(sym, arg_offset, stack_size);
+                        arg_offset += stack_size as i32;
+                    }
+                }
                 _ if stack_size == 0 => {
                     storage_manager.no_data_arg(sym);
                 }
@@ -386,6 +406,38 @@ impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64Syste
                         tmp_stack_offset += 8;
                     }
                 }
+                Layout::I128 | Layout::U128 | Layout::DEC => {
+                    let (base_offset, size) = storage_manager.stack_offset_and_size(sym);
+                    debug_assert_eq!(base_offset % 8, 0);
+                    if general_i + 1 < Self::GENERAL_PARAM_REGS.len() {
+                        X86_64Assembler::mov_reg64_base32(
+                            buf,
+                            Self::GENERAL_PARAM_REGS[general_i],
+                            base_offset,
+                        );
+                        X86_64Assembler::mov_reg64_base32(
+                            buf,
+                            Self::GENERAL_PARAM_REGS[general_i + 1],
+                            base_offset + 8,
+                        );
+                        general_i += 2;
+                    } else {
+                        // Copy to stack using return reg as buffer.
+                        for i in (0..size as i32).step_by(8) {
+                            X86_64Assembler::mov_reg64_base32(
+                                buf,
+                                Self::GENERAL_RETURN_REGS[0],
+                                base_offset + i,
+                            );
+                            X86_64Assembler::mov_stack32_reg64(
+                                buf,
+                                tmp_stack_offset + i,
+                                Self::GENERAL_RETURN_REGS[0],
+                            );
+                        }
+                        tmp_stack_offset += size as i32;
+                    }
+                }
                 x if layout_interner.stack_size(x) == 0 => {}
                 x if layout_interner.stack_size(x) > 16 => {
                     // TODO: Double check this.
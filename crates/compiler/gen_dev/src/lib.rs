@@ -8,20 +8,21 @@
 use bumpalo::{collections::Vec, Bump};
 use roc_builtins::bitcode::{self, FloatWidth, IntWidth};
 use roc_collections::all::{MutMap, MutSet};
-use roc_error_macros::internal_error;
+use roc_error_macros::{internal_error, user_error};
 use roc_module::ident::ModuleName;
 use roc_module::low_level::{LowLevel, LowLevelWrapperType};
 use roc_module::symbol::{Interns, ModuleId, Symbol};
 use roc_mono::code_gen_help::CodeGenHelp;
 use roc_mono::ir::{
-    BranchInfo, CallType, Expr, JoinPointId, ListLiteralElement, Literal, Param, Proc, ProcLayout,
-    SelfRecursive, Stmt,
+    BranchInfo, CallType, CrashTag, Expr, JoinPointId, ListLiteralElement, Literal, LookupType,
+    Param, Proc, ProcLayout, SelfRecursive, Stmt,
 };
 use roc_mono::layout::{
     Builtin, InLayout, Layout, LayoutId, LayoutIds, LayoutInterner, STLayoutInterner, TagIdIntType,
     UnionLayout,
 };
 use roc_mono::list_element_layout;
+use roc_region::all::Region;
 
 mod generic64;
 mod object_builder;
@@ -34,6 +35,27 @@ pub struct Env<'a> {
     pub exposed_to_host: MutSet<Symbol>,
     pub lazy_literals: bool,
     pub generate_allocators: bool,
+    pub mode: AssemblyBackendMode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssemblyBackendMode {
+    /// `expect` and `dbg` are skipped.
+    Binary,
+    /// Failed expects and dbgs are written to the memory shared with `roc dev`, which is notified of each one.
+    BinaryDev,
+    /// Failed expects are written to the shared buffer that `roc test` reads after running the test.
+    Test,
+}
+
+impl AssemblyBackendMode {
+    fn runs_expects(self) -> bool {
+        match self {
+            AssemblyBackendMode::Binary => false,
+            AssemblyBackendMode::BinaryDev => true,
+            AssemblyBackendMode::Test => true,
+        }
+    }
 }
 
 // These relocations likely will need a length.
@@ -227,7 +249,54 @@ trait Backend<'a> {
                 self.build_jump(id, args, arg_layouts.into_bump_slice(), ret_layout);
                 self.free_symbols(stmt);
             }
-            x => todo!("the statement, {:?}", x),
+            Stmt::Dbg {
+                symbol,
+                variable,
+                remainder,
+            } => {
+                if self.env().mode.runs_expects() {
+                    self.load_literal_symbols(&[*symbol]);
+                    self.build_dbg(symbol, *variable);
+                }
+                self.free_symbols(stmt);
+                self.build_stmt(remainder, ret_layout);
+            }
+            Stmt::Expect {
+                condition,
+                region,
+                lookups,
+                variables,
+                remainder,
+            } => {
+                if self.env().mode.runs_expects() {
+                    self.load_literal_symbols(&[*condition]);
+                    self.load_literal_symbols(lookups);
+                    let notify_parent = self.env().mode == AssemblyBackendMode::BinaryDev;
+                    self.build_expect(condition, *region, lookups, variables, notify_parent);
+                }
+                self.free_symbols(stmt);
+                self.build_stmt(remainder, ret_layout);
+            }
+            Stmt::ExpectFx {
+                condition,
+                region,
+                lookups,
+                variables,
+                remainder,
+            } => {
+                if self.env().mode.runs_expects() {
+                    self.load_literal_symbols(&[*condition]);
+                    self.load_literal_symbols(lookups);
+                    self.build_expect(condition, *region, lookups, variables, false);
+                }
+                self.free_symbols(stmt);
+                self.build_stmt(remainder, ret_layout);
+            }
+            Stmt::Crash(msg, tag) => {
+                self.load_literal_symbols(&[*msg]);
+                self.build_crash(msg, *tag);
+                self.free_symbols(stmt);
+            }
         }
     }
     // build_switch generates a instructions for a switch statement.
@@ -240,6 +309,40 @@ trait Backend<'a> {
         ret_layout: &InLayout<'a>,
     );
 
+    /// build_expect writes a failed expect's region and lookups into the memory shared with the
+    /// process running the expects, if `condition` is false.
+    fn build_expect(
+        &mut self,
+        condition: &Symbol,
+        region: Region,
+        lookups: &'a [Symbol],
+        variables: &'a [LookupType],
+        notify_parent: bool,
+    );
+
+    /// build_dbg writes the value of `symbol` into the memory shared with `roc dev`, and notifies it.
+    fn build_dbg(&mut self, symbol: &Symbol, variable: LookupType);
+
+    /// build_crash passes the message and the source of the crash to roc_panic.
+    fn build_crash(&mut self, msg: &Symbol, tag: CrashTag) {
+        // roc_panic takes the message by reference.
+        self.build_ptr_cast(&Symbol::DEV_TMP, msg);
+        self.load_literal(
+            &Symbol::DEV_TMP2,
+            &Layout::U32,
+            &Literal::Int((tag as u32 as i128).to_ne_bytes()),
+        );
+        self.build_fn_call(
+            &Symbol::DEV_TMP3,
+            "roc_panic".to_string(),
+            &[Symbol::DEV_TMP, Symbol::DEV_TMP2],
+            &[Layout::U64, Layout::U32],
+            &Layout::UNIT,
+        );
+        self.free_symbol(&Symbol::DEV_TMP);
+        self.free_symbol(&Symbol::DEV_TMP2);
+    }
+
     // build_join generates a instructions for a join statement.
     fn build_join(
         &mut self,
@@ -429,6 +532,42 @@ trait Backend<'a> {
             LowLevel::NumAddChecked => {
                 self.build_num_add_checked(sym, &args[0], &args[1], &arg_layouts[0], ret_layout)
             }
+            LowLevel::NumAddWrap => {
+                debug_assert_eq!(
+                    2,
+                    args.len(),
+                    "NumAddWrap: expected to have exactly two argument"
+                );
+                debug_assert_eq!(
+                    arg_layouts[0], arg_layouts[1],
+                    "NumAddWrap: expected all arguments of to have the same layout"
+                );
+                debug_assert_eq!(
+                    arg_layouts[0], *ret_layout,
+                    "NumAddWrap: expected to have the same argument and return layout"
+                );
+                self.build_num_add_wrap(sym, &args[0], &args[1], ret_layout)
+            }
+            LowLevel::NumAddSaturated => match self.interner().get(*ret_layout) {
+                Layout::Builtin(Builtin::Int(int_width)) => self.build_fn_call(
+                    sym,
+                    bitcode::NUM_ADD_SATURATED_INT[int_width].to_string(),
+                    args,
+                    arg_layouts,
+                    ret_layout,
+                ),
+                Layout::Builtin(Builtin::Float(_)) => {
+                    self.build_num_add(sym, &args[0], &args[1], ret_layout)
+                }
+                Layout::Builtin(Builtin::Decimal) => self.build_fn_call(
+                    sym,
+                    bitcode::DEC_ADD_SATURATED.to_string(),
+                    args,
+                    arg_layouts,
+                    ret_layout,
+                ),
+                x => internal_error!("NumAddSaturated is not defined for {:?}", x),
+            },
             LowLevel::NumAcos => self.build_fn_call(
                 sym,
                 bitcode::NUM_ACOS[FloatWidth::F64].to_string(),
@@ -466,6 +605,45 @@ trait Backend<'a> {
                 );
                 self.build_num_mul(sym, &args[0], &args[1], ret_layout)
             }
+            LowLevel::NumMulWrap => {
+                debug_assert_eq!(
+                    2,
+                    args.len(),
+                    "NumMulWrap: expected to have exactly two argument"
+                );
+                debug_assert_eq!(
+                    arg_layouts[0], arg_layouts[1],
+                    "NumMulWrap: expected all arguments of to have the same layout"
+                );
+                debug_assert_eq!(
+                    arg_layouts[0], *ret_layout,
+                    "NumMulWrap: expected to have the same argument and return layout"
+                );
+                self.build_num_mul_wrap(sym, &args[0], &args[1], ret_layout)
+            }
+            LowLevel::NumMulChecked => {
+                self.build_num_mul_checked(sym, &args[0], &args[1], &arg_layouts[0], ret_layout)
+            }
+            LowLevel::NumMulSaturated => match self.interner().get(*ret_layout) {
+                Layout::Builtin(Builtin::Int(int_width)) => self.build_fn_call(
+                    sym,
+                    bitcode::NUM_MUL_SATURATED_INT[int_width].to_string(),
+                    args,
+                    arg_layouts,
+                    ret_layout,
+                ),
+                Layout::Builtin(Builtin::Float(_)) => {
+                    self.build_num_mul(sym, &args[0], &args[1], ret_layout)
+                }
+                Layout::Builtin(Builtin::Decimal) => self.build_fn_call(
+                    sym,
+                    bitcode::DEC_MUL_SATURATED.to_string(),
+                    args,
+                    arg_layouts,
+                    ret_layout,
+                ),
+                x => internal_error!("NumMulSaturated is not defined for {:?}", x),
+            },
             LowLevel::NumDivTruncUnchecked | LowLevel::NumDivFrac => {
                 debug_assert_eq!(
                     2,
@@ -533,6 +711,29 @@ trait Backend<'a> {
                 );
                 self.build_num_sub_wrap(sym, &args[0], &args[1], ret_layout)
             }
+            LowLevel::NumSubChecked => {
+                self.build_num_sub_checked(sym, &args[0], &args[1], &arg_layouts[0], ret_layout)
+            }
+            LowLevel::NumSubSaturated => match self.interner().get(*ret_layout) {
+                Layout::Builtin(Builtin::Int(int_width)) => self.build_fn_call(
+                    sym,
+                    bitcode::NUM_SUB_SATURATED_INT[int_width].to_string(),
+                    args,
+                    arg_layouts,
+                    ret_layout,
+                ),
+                Layout::Builtin(Builtin::Float(_)) => {
+                    self.build_num_sub(sym, &args[0], &args[1], ret_layout)
+                }
+                Layout::Builtin(Builtin::Decimal) => self.build_fn_call(
+                    sym,
+                    bitcode::DEC_SUB_SATURATED.to_string(),
+                    args,
+                    arg_layouts,
+                    ret_layout,
+                ),
+                x => internal_error!("NumSubSaturated is not defined for {:?}", x),
+            },
             LowLevel::NumBitwiseAnd => {
                 if let Layout::Builtin(Builtin::Int(int_width)) = self.interner().get(*ret_layout) {
                     self.build_int_bitwise_and(sym, &args[0], &args[1], int_width)
@@ -786,6 +987,9 @@ trait Backend<'a> {
                 arg_layouts,
                 ret_layout,
             ),
+            LowLevel::ExpectInput => user_error!(
+                "The dev backend can't run property `expect`s yet. Run `roc test` without `--dev` to test them."
+            ),
             x => todo!("low level, {:?}", x),
         }
     }
@@ -830,7 +1034,7 @@ trait Backend<'a> {
                 self.load_literal_symbols(args);
                 self.build_fn_call(sym, fn_name, args, arg_layouts, ret_layout)
             }
-            Symbol::NUM_ADD_CHECKED => {
+            Symbol::NUM_ADD_CHECKED | Symbol::NUM_SUB_CHECKED | Symbol::NUM_MUL_CHECKED => {
                 let layout_id = LayoutIds::default().get(func_sym, ret_layout);
                 let fn_name = self.symbol_to_string(func_sym, layout_id);
                 // Now that the arguments are needed, load them if they are literals.
@@ -868,7 +1072,7 @@ trait Backend<'a> {
     /// build_num_add stores the sum of src1 and src2 into dst.
     fn build_num_add(&mut self, dst: &Symbol, src1: &Symbol, src2: &Symbol, layout: &InLayout<'a>);
 
-    /// build_num_add_checked stores the sum of src1 and src2 into dst.
+    /// build_num_add_checked stores the sum of src1 and src2 into dst, along with whether it overflowed.
     fn build_num_add_checked(
         &mut self,
         dst: &Symbol,
@@ -878,9 +1082,37 @@ trait Backend<'a> {
         return_layout: &InLayout<'a>,
     );
 
+    /// build_num_add_wrap stores the sum of src1 and src2 into dst, wrapping on overflow.
+    fn build_num_add_wrap(
+        &mut self,
+        dst: &Symbol,
+        src1: &Symbol,
+        src2: &Symbol,
+        layout: &InLayout<'a>,
+    );
+
     /// build_num_mul stores `src1 * src2` into dst.
     fn build_num_mul(&mut self, dst: &Symbol, src1: &Symbol, src2: &Symbol, layout: &InLayout<'a>);
 
+    /// build_num_mul_wrap stores `src1 * src2` into dst, wrapping on overflow.
+    fn build_num_mul_wrap(
+        &mut self,
+        dst: &Symbol,
+        src1: &Symbol,
+        src2: &Symbol,
+        layout: &InLayout<'a>,
+    );
+
+    /// build_num_mul_checked stores `src1 * src2` into dst, along with whether it overflowed.
+    fn build_num_mul_checked(
+        &mut self,
+        dst: &Symbol,
+        src1: &Symbol,
+        src2: &Symbol,
+        num_layout: &InLayout<'a>,
+        return_layout: &InLayout<'a>,
+    );
+
     /// build_num_mul stores `src1 / src2` into dst.
     fn build_num_div(&mut self, dst: &Symbol, src1: &Symbol, src2: &Symbol, layout: &InLayout<'a>);
 
@@ -899,6 +1131,16 @@ trait Backend<'a> {
         layout: &InLayout<'a>,
     );
 
+    /// build_num_sub_checked stores the `src1 - src2` difference into dst, along with whether it overflowed.
+    fn build_num_sub_checked(
+        &mut self,
+        dst: &Symbol,
+        src1: &Symbol,
+        src2: &Symbol,
+        num_layout: &InLayout<'a>,
+        return_layout: &InLayout<'a>,
+    );
+
     /// stores the `src1 & src2` into dst.
     fn build_int_bitwise_and(
         &mut self,
@@ -1302,11 +1544,34 @@ trait Backend<'a> {
                 }
             }
 
-            Stmt::Dbg { .. } => todo!("dbg not implemented in the dev backend"),
-            Stmt::Expect { .. } => todo!("expect is not implemented in the dev backend"),
-            Stmt::ExpectFx { .. } => todo!("expect-fx is not implemented in the dev backend"),
+            Stmt::Dbg {
+                symbol, remainder, ..
+            } => {
+                self.set_last_seen(*symbol, stmt);
+                self.scan_ast(remainder);
+            }
+            Stmt::Expect {
+                condition,
+                lookups,
+                remainder,
+                ..
+            }
+            | Stmt::ExpectFx {
+                condition,
+                lookups,
+                remainder,
+                ..
+            } => {
+                self.set_last_seen(*condition, stmt);
+                for sym in *lookups {
+                    self.set_last_seen(*sym, stmt);
+                }
+                self.scan_ast(remainder);
+            }
 
-            Stmt::Crash(..) => todo!("crash is not implemented in the dev backend"),
+            Stmt::Crash(msg, _) => {
+                self.set_last_seen(*msg, stmt);
+            }
         }
     }
 
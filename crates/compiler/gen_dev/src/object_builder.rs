@@ -1,5 +1,5 @@
 use crate::generic64::{aarch64, new_backend_64bit, x86_64};
-use crate::{Backend, Env, Relocation};
+use crate::{AssemblyBackendMode, Backend, Env, Relocation};
 use bumpalo::collections::Vec;
 use object::write::{self, SectionId, SymbolId};
 use object::write::{Object, StandardSection, StandardSegment, Symbol, SymbolSection};
@@ -7,6 +7,7 @@ use object::{
     Architecture, BinaryFormat, Endianness, RelocationEncoding, RelocationKind, SectionKind,
     SymbolFlags, SymbolKind, SymbolScope,
 };
+use roc_builtins::bitcode;
 use roc_collections::all::MutMap;
 use roc_error_macros::internal_error;
 use roc_module::symbol;
@@ -203,11 +204,18 @@ fn build_object<'a, B: Backend<'a>>(
             "roc_dealloc".into(),
             "free".into(),
         );
+        // `roc test` has no host to catch a crash, so the process running the expect stops instead.
+        let panic_fn = match backend.env().mode {
+            AssemblyBackendMode::Test => bitcode::UTILS_EXPECT_PANIC,
+            AssemblyBackendMode::Binary | AssemblyBackendMode::BinaryDev => {
+                bitcode::UTILS_TEST_PANIC
+            }
+        };
         generate_wrapper(
             &mut backend,
             &mut output,
             "roc_panic".into(),
-            "roc_builtins.utils.test_panic".into(),
+            panic_fn.into(),
         );
     }
 
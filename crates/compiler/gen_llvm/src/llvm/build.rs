@@ -198,6 +198,8 @@ pub enum LlvmBackendMode {
     /// Assumes primitives (roc_alloc, roc_panic, etc) are provided by the host
     Binary,
     BinaryDev,
+    /// Like `Binary`, but records a profile of the function calls and allocations
+    BinaryProfile,
     /// Creates a test wrapper around the main roc function to catch and report panics.
     /// Provides a testing implementation of primitives (roc_alloc, roc_panic, etc)
     GenTest,
@@ -210,6 +212,7 @@ impl LlvmBackendMode {
         match self {
             LlvmBackendMode::Binary => true,
             LlvmBackendMode::BinaryDev => true,
+            LlvmBackendMode::BinaryProfile => true,
             LlvmBackendMode::GenTest => false,
             LlvmBackendMode::WasmGenTest => true,
             LlvmBackendMode::CliTest => false,
@@ -221,6 +224,7 @@ impl LlvmBackendMode {
         match self {
             LlvmBackendMode::Binary => false,
             LlvmBackendMode::BinaryDev => false,
+            LlvmBackendMode::BinaryProfile => false,
             LlvmBackendMode::GenTest => true,
             LlvmBackendMode::WasmGenTest => true,
             LlvmBackendMode::CliTest => true,
@@ -231,6 +235,7 @@ impl LlvmBackendMode {
         match self {
             LlvmBackendMode::Binary => false,
             LlvmBackendMode::BinaryDev => true,
+            LlvmBackendMode::BinaryProfile => false,
             LlvmBackendMode::GenTest => false,
             LlvmBackendMode::WasmGenTest => false,
             LlvmBackendMode::CliTest => true,
@@ -4104,7 +4109,7 @@ fn expose_function_to_host_help_c_abi<'a, 'ctx, 'env>(
             )
         }
 
-        LlvmBackendMode::Binary | LlvmBackendMode::BinaryDev => {}
+        LlvmBackendMode::Binary | LlvmBackendMode::BinaryDev | LlvmBackendMode::BinaryProfile => {}
     }
 
     // a generic version that writes the result into a passed *u8 pointer
@@ -4157,7 +4162,7 @@ fn expose_function_to_host_help_c_abi<'a, 'ctx, 'env>(
             roc_call_result_type(env, roc_function.get_type().get_return_type().unwrap()).into()
         }
 
-        LlvmBackendMode::Binary | LlvmBackendMode::BinaryDev => {
+        LlvmBackendMode::Binary | LlvmBackendMode::BinaryDev | LlvmBackendMode::BinaryProfile => {
             basic_type_from_layout(env, layout_interner, return_layout)
         }
     };
@@ -4280,6 +4285,40 @@ pub fn get_panic_msg_ptr<'a, 'ctx, 'env>(env: &Env<'a, 'ctx, 'env>) -> PointerVa
     global.as_pointer_value()
 }
 
+/// Pointer to the table of generated inputs for property `expect`s.
+/// The host fills it in with `set_expect_inputs` before running a property.
+pub fn get_expect_inputs_ptr<'a, 'ctx, 'env>(env: &Env<'a, 'ctx, 'env>) -> PointerValue<'ctx> {
+    let table_typ = env
+        .context
+        .i8_type()
+        .ptr_type(AddressSpace::default())
+        .ptr_type(AddressSpace::default());
+
+    let global_name = "roc_expect_inputs";
+    let global = env.module.get_global(global_name).unwrap_or_else(|| {
+        let global = env.module.add_global(table_typ, None, global_name);
+        global.set_initializer(&table_typ.const_null());
+        global
+    });
+
+    global.as_pointer_value()
+}
+
+/// Pointer to the coverage counters of `roc test --coverage`.
+/// The host fills it in with `set_coverage_counters` before running any expects.
+pub fn get_coverage_counters_ptr<'a, 'ctx, 'env>(env: &Env<'a, 'ctx, 'env>) -> PointerValue<'ctx> {
+    let counters_typ = env.context.i64_type().ptr_type(AddressSpace::default());
+
+    let global_name = "roc_coverage_counters";
+    let global = env.module.get_global(global_name).unwrap_or_else(|| {
+        let global = env.module.add_global(counters_typ, None, global_name);
+        global.set_initializer(&counters_typ.const_null());
+        global
+    });
+
+    global.as_pointer_value()
+}
+
 /// Pointer to the panic tag.
 /// Only non-zero values must be written into here.
 pub fn get_panic_tag_ptr<'a, 'ctx, 'env>(env: &Env<'a, 'ctx, 'env>) -> PointerValue<'ctx> {
@@ -4826,6 +4865,10 @@ fn build_procedures_help<'a, 'ctx, 'env>(
         }
     }
 
+    if let LlvmBackendMode::BinaryProfile = env.mode {
+        crate::llvm::profile::wrap_allocator(env);
+    }
+
     mod_solutions
 }
 
@@ -5221,7 +5264,7 @@ pub fn build_proc<'a, 'ctx, 'env>(
                 GenTest | WasmGenTest | CliTest => {
                     /* no host, or exposing types is not supported */
                 }
-                Binary | BinaryDev => {
+                Binary | BinaryDev | BinaryProfile => {
                     for (alias_name, (generated_function, top_level, layout)) in aliases.iter() {
                         expose_alias_to_host(
                             env,
@@ -5256,6 +5299,13 @@ pub fn build_proc<'a, 'ctx, 'env>(
         scope.insert(*arg_symbol, (*layout, arg_val));
     }
 
+    let profile_caller = match env.mode {
+        LlvmBackendMode::BinaryProfile => {
+            Some(crate::llvm::profile::build_enter(env, proc.name.name()))
+        }
+        _ => None,
+    };
+
     let body = build_exp_stmt(
         env,
         layout_interner,
@@ -5272,6 +5322,10 @@ pub fn build_proc<'a, 'ctx, 'env>(
             builder.build_return(Some(&body));
         }
     }
+
+    if let Some(caller) = profile_caller {
+        crate::llvm::profile::build_exits(env, fn_val, caller);
+    }
 }
 
 pub fn verify_fn(fn_val: FunctionValue<'_>) {
@@ -1,24 +1,41 @@
 use crate::llvm::bitcode::call_void_bitcode_fn;
-use crate::llvm::build::{add_func, get_panic_msg_ptr, get_panic_tag_ptr, BuilderExt, C_CALL_CONV};
+use crate::llvm::build::{
+    add_func, get_coverage_counters_ptr, get_expect_inputs_ptr, get_panic_msg_ptr,
+    get_panic_tag_ptr, BuilderExt, C_CALL_CONV,
+};
 use crate::llvm::build::{CCReturn, Env, FunctionSpec};
 use crate::llvm::convert::zig_str_type;
 use inkwell::module::Linkage;
 use inkwell::types::BasicType;
+use inkwell::values::{BasicMetadataValueEnum, CallableValue};
 use inkwell::AddressSpace;
 use roc_builtins::bitcode;
 
 use super::build::get_sjlj_buffer;
 use super::intrinsics::LLVM_LONGJMP;
 
+/// The globals which `add_roc_externs_with_host_allocator` makes roc_alloc, roc_realloc,
+/// and roc_dealloc call through. The host sets them after loading the module.
+pub const HOST_ALLOC: &str = "roc_host_alloc";
+pub const HOST_REALLOC: &str = "roc_host_realloc";
+pub const HOST_DEALLOC: &str = "roc_host_dealloc";
+
 /// Define functions for roc_alloc, roc_realloc, and roc_dealloc
 /// which use libc implementations (malloc, realloc, and free)
 pub fn add_default_roc_externs(env: &Env<'_, '_, '_>) {
-    let ctx = env.context;
-    let module = env.module;
-    let builder = env.builder;
+    add_roc_externs_help(env, false)
+}
 
-    let usize_type = env.ptr_int();
-    let i8_ptr_type = ctx.i8_type().ptr_type(AddressSpace::default());
+/// Like `add_default_roc_externs`, but roc_alloc, roc_realloc, and roc_dealloc call the host's
+/// allocator through the function pointers in the HOST_ALLOC, HOST_REALLOC, and HOST_DEALLOC
+/// globals. That way, a host that loads the module as a dylib can hand it values to free,
+/// and free the values it returns.
+pub fn add_roc_externs_with_host_allocator(env: &Env<'_, '_, '_>) {
+    add_roc_externs_help(env, true)
+}
+
+fn add_roc_externs_help(env: &Env<'_, '_, '_>, host_allocator: bool) {
+    let module = env.module;
 
     match env.mode {
         super::build::LlvmBackendMode::CliTest => {
@@ -26,6 +43,9 @@ pub fn add_default_roc_externs(env: &Env<'_, '_, '_>) {
             if let Some(fn_val) = module.get_function("set_shared_buffer") {
                 fn_val.set_linkage(Linkage::External);
             }
+
+            add_set_expect_inputs(env);
+            add_set_coverage_counters(env);
         }
         _ => {
             // remove this function from the module
@@ -36,131 +56,195 @@ pub fn add_default_roc_externs(env: &Env<'_, '_, '_>) {
     }
 
     if !env.mode.has_host() {
-        // roc_alloc
-        {
-            // The type of this function (but not the implementation) should have
-            // already been defined by the builtins, which rely on it.
-            let fn_val = module.get_function("roc_alloc").unwrap();
-            let mut params = fn_val.get_param_iter();
-            let size_arg = params.next().unwrap();
-            let _alignment_arg = params.next().unwrap();
-
-            debug_assert!(params.next().is_none());
+        if host_allocator {
+            add_host_allocator(env);
+        } else {
+            add_libc_allocator(env);
+        }
 
-            // Add a basic block for the entry point
-            let entry = ctx.append_basic_block(fn_val, "entry");
+        unreachable_function(env, "roc_getppid");
+        unreachable_function(env, "roc_mmap");
+        unreachable_function(env, "roc_shm_open");
+        unreachable_function(env, "roc_getenv");
 
-            builder.position_at_end(entry);
+        add_sjlj_roc_panic(env)
+    }
+}
 
-            // Call libc malloc()
-            let retval = builder
-                .build_array_malloc(ctx.i8_type(), size_arg.into_int_value(), "call_malloc")
-                .unwrap();
+fn add_libc_allocator(env: &Env<'_, '_, '_>) {
+    let ctx = env.context;
+    let module = env.module;
+    let builder = env.builder;
 
-            builder.build_return(Some(&retval));
+    let usize_type = env.ptr_int();
+    let i8_ptr_type = ctx.i8_type().ptr_type(AddressSpace::default());
 
-            if cfg!(debug_assertions) {
-                crate::llvm::build::verify_fn(fn_val);
-            }
-        }
+    // roc_alloc
+    {
+        // The type of this function (but not the implementation) should have
+        // already been defined by the builtins, which rely on it.
+        let fn_val = module.get_function("roc_alloc").unwrap();
+        let mut params = fn_val.get_param_iter();
+        let size_arg = params.next().unwrap();
+        let _alignment_arg = params.next().unwrap();
 
-        // roc_realloc
-        {
-            let libc_realloc_val = {
-                let fn_spec = FunctionSpec::cconv(
-                    env,
-                    CCReturn::Return,
-                    Some(i8_ptr_type.as_basic_type_enum()),
-                    &[
-                        // ptr: *void
-                        i8_ptr_type.into(),
-                        // size: usize
-                        usize_type.into(),
-                    ],
-                );
-                let fn_val = add_func(env.context, module, "realloc", fn_spec, Linkage::External);
-
-                let mut params = fn_val.get_param_iter();
-                let ptr_arg = params.next().unwrap();
-                let size_arg = params.next().unwrap();
-
-                debug_assert!(params.next().is_none());
-
-                ptr_arg.set_name("ptr");
-                size_arg.set_name("size");
-
-                if cfg!(debug_assertions) {
-                    crate::llvm::build::verify_fn(fn_val);
-                }
+        debug_assert!(params.next().is_none());
 
-                fn_val
-            };
+        // Add a basic block for the entry point
+        let entry = ctx.append_basic_block(fn_val, "entry");
 
-            // The type of this function (but not the implementation) should have
-            // already been defined by the builtins, which rely on it.
-            let fn_val = module.get_function("roc_realloc").unwrap();
-            let mut params = fn_val.get_param_iter();
-            let ptr_arg = params.next().unwrap();
-            let new_size_arg = params.next().unwrap();
-            let _old_size_arg = params.next().unwrap();
-            let _alignment_arg = params.next().unwrap();
+        builder.position_at_end(entry);
 
-            debug_assert!(params.next().is_none());
+        // Call libc malloc()
+        let retval = builder
+            .build_array_malloc(ctx.i8_type(), size_arg.into_int_value(), "call_malloc")
+            .unwrap();
 
-            // Add a basic block for the entry point
-            let entry = ctx.append_basic_block(fn_val, "entry");
+        builder.build_return(Some(&retval));
 
-            builder.position_at_end(entry);
+        if cfg!(debug_assertions) {
+            crate::llvm::build::verify_fn(fn_val);
+        }
+    }
 
-            // Call libc realloc()
-            let call = builder.build_call(
-                libc_realloc_val,
-                &[ptr_arg.into(), new_size_arg.into()],
-                "call_libc_realloc",
+    // roc_realloc
+    {
+        let libc_realloc_val = {
+            let fn_spec = FunctionSpec::cconv(
+                env,
+                CCReturn::Return,
+                Some(i8_ptr_type.as_basic_type_enum()),
+                &[
+                    // ptr: *void
+                    i8_ptr_type.into(),
+                    // size: usize
+                    usize_type.into(),
+                ],
             );
+            let fn_val = add_func(env.context, module, "realloc", fn_spec, Linkage::External);
 
-            call.set_call_convention(C_CALL_CONV);
+            let mut params = fn_val.get_param_iter();
+            let ptr_arg = params.next().unwrap();
+            let size_arg = params.next().unwrap();
 
-            let retval = call.try_as_basic_value().left().unwrap();
+            debug_assert!(params.next().is_none());
 
-            builder.build_return(Some(&retval));
+            ptr_arg.set_name("ptr");
+            size_arg.set_name("size");
 
             if cfg!(debug_assertions) {
                 crate::llvm::build::verify_fn(fn_val);
             }
+
+            fn_val
+        };
+
+        // The type of this function (but not the implementation) should have
+        // already been defined by the builtins, which rely on it.
+        let fn_val = module.get_function("roc_realloc").unwrap();
+        let mut params = fn_val.get_param_iter();
+        let ptr_arg = params.next().unwrap();
+        let new_size_arg = params.next().unwrap();
+        let _old_size_arg = params.next().unwrap();
+        let _alignment_arg = params.next().unwrap();
+
+        debug_assert!(params.next().is_none());
+
+        // Add a basic block for the entry point
+        let entry = ctx.append_basic_block(fn_val, "entry");
+
+        builder.position_at_end(entry);
+
+        // Call libc realloc()
+        let call = builder.build_call(
+            libc_realloc_val,
+            &[ptr_arg.into(), new_size_arg.into()],
+            "call_libc_realloc",
+        );
+
+        call.set_call_convention(C_CALL_CONV);
+
+        let retval = call.try_as_basic_value().left().unwrap();
+
+        builder.build_return(Some(&retval));
+
+        if cfg!(debug_assertions) {
+            crate::llvm::build::verify_fn(fn_val);
         }
+    }
 
-        // roc_dealloc
-        {
-            // The type of this function (but not the implementation) should have
-            // already been defined by the builtins, which rely on it.
-            let fn_val = module.get_function("roc_dealloc").unwrap();
-            let mut params = fn_val.get_param_iter();
-            let ptr_arg = params.next().unwrap();
-            let _alignment_arg = params.next().unwrap();
+    // roc_dealloc
+    {
+        // The type of this function (but not the implementation) should have
+        // already been defined by the builtins, which rely on it.
+        let fn_val = module.get_function("roc_dealloc").unwrap();
+        let mut params = fn_val.get_param_iter();
+        let ptr_arg = params.next().unwrap();
+        let _alignment_arg = params.next().unwrap();
 
-            debug_assert!(params.next().is_none());
+        debug_assert!(params.next().is_none());
 
-            // Add a basic block for the entry point
-            let entry = ctx.append_basic_block(fn_val, "entry");
+        // Add a basic block for the entry point
+        let entry = ctx.append_basic_block(fn_val, "entry");
 
-            builder.position_at_end(entry);
+        builder.position_at_end(entry);
 
-            // Call libc free()
-            builder.build_free(ptr_arg.into_pointer_value());
+        // Call libc free()
+        builder.build_free(ptr_arg.into_pointer_value());
 
-            builder.build_return(None);
+        builder.build_return(None);
 
-            if cfg!(debug_assertions) {
-                crate::llvm::build::verify_fn(fn_val);
-            }
+        if cfg!(debug_assertions) {
+            crate::llvm::build::verify_fn(fn_val);
         }
+    }
+}
 
-        unreachable_function(env, "roc_getppid");
-        unreachable_function(env, "roc_mmap");
-        unreachable_function(env, "roc_shm_open");
+fn add_host_allocator(env: &Env<'_, '_, '_>) {
+    let ctx = env.context;
+    let module = env.module;
+    let builder = env.builder;
 
-        add_sjlj_roc_panic(env)
+    for (name, global_name) in [
+        ("roc_alloc", HOST_ALLOC),
+        ("roc_realloc", HOST_REALLOC),
+        ("roc_dealloc", HOST_DEALLOC),
+    ] {
+        // The type of this function (but not the implementation) should have
+        // already been defined by the builtins, which rely on it.
+        let fn_val = module.get_function(name).unwrap();
+        let fn_ptr_type = fn_val.get_type().ptr_type(AddressSpace::default());
+
+        let global = module.add_global(fn_ptr_type, None, global_name);
+        global.set_initializer(&fn_ptr_type.const_null());
+
+        // Add a basic block for the entry point
+        let entry = ctx.append_basic_block(fn_val, "entry");
+
+        builder.position_at_end(entry);
+
+        // Call the host's function, passing our arguments along unchanged
+        let host_fn = builder
+            .new_build_load(fn_ptr_type, global.as_pointer_value(), "load_host_fn")
+            .into_pointer_value();
+        let args: Vec<BasicMetadataValueEnum> =
+            fn_val.get_param_iter().map(|arg| arg.into()).collect();
+        let call = builder.build_call(
+            CallableValue::try_from(host_fn).unwrap(),
+            &args,
+            "call_host_fn",
+        );
+
+        call.set_call_convention(C_CALL_CONV);
+
+        match call.try_as_basic_value().left() {
+            Some(retval) => builder.build_return(Some(&retval)),
+            None => builder.build_return(None),
+        };
+
+        if cfg!(debug_assertions) {
+            crate::llvm::build::verify_fn(fn_val);
+        }
     }
 }
 
@@ -276,3 +360,60 @@ pub fn build_longjmp_call(env: &Env) {
         let _call = env.build_intrinsic_call(LLVM_LONGJMP, &[jmp_buf_i8p.into()]);
     }
 }
+
+/// Lets the host point property `expect`s at their generated inputs
+fn add_set_expect_inputs(env: &Env<'_, '_, '_>) {
+    let ctx = env.context;
+    let builder = env.builder;
+
+    let table_type = ctx
+        .i8_type()
+        .ptr_type(AddressSpace::default())
+        .ptr_type(AddressSpace::default());
+
+    let fn_val = add_func(
+        ctx,
+        env.module,
+        "set_expect_inputs",
+        FunctionSpec::cconv(env, CCReturn::Void, None, &[table_type.into()]),
+        Linkage::External,
+    );
+
+    let entry = ctx.append_basic_block(fn_val, "entry");
+    builder.position_at_end(entry);
+
+    let table = fn_val.get_nth_param(0).unwrap();
+    builder.build_store(get_expect_inputs_ptr(env), table);
+    builder.build_return(None);
+
+    if cfg!(debug_assertions) {
+        crate::llvm::build::verify_fn(fn_val);
+    }
+}
+
+/// Lets the host point `CoverageHit`s at the counters of `roc test --coverage`
+fn add_set_coverage_counters(env: &Env<'_, '_, '_>) {
+    let ctx = env.context;
+    let builder = env.builder;
+
+    let counters_type = ctx.i64_type().ptr_type(AddressSpace::default());
+
+    let fn_val = add_func(
+        ctx,
+        env.module,
+        "set_coverage_counters",
+        FunctionSpec::cconv(env, CCReturn::Void, None, &[counters_type.into()]),
+        Linkage::External,
+    );
+
+    let entry = ctx.append_basic_block(fn_val, "entry");
+    builder.position_at_end(entry);
+
+    let counters = fn_val.get_nth_param(0).unwrap();
+    builder.build_store(get_coverage_counters_ptr(env), counters);
+    builder.build_return(None);
+
+    if cfg!(debug_assertions) {
+        crate::llvm::build::verify_fn(fn_val);
+    }
+}
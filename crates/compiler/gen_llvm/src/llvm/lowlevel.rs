@@ -4,7 +4,7 @@ use inkwell::{
         BasicValue, BasicValueEnum, FloatValue, FunctionValue, InstructionOpcode, IntValue,
         PointerValue, StructValue,
     },
-    AddressSpace, IntPredicate,
+    AddressSpace, AtomicOrdering, AtomicRMWBinOp, IntPredicate,
 };
 use morphic_lib::{FuncSpec, UpdateMode};
 use roc_builtins::bitcode::{self, FloatWidth, IntWidth};
@@ -25,7 +25,8 @@ use crate::llvm::{
     },
     build::{
         complex_bitcast_check_size, create_entry_block_alloca, function_value_by_func_spec,
-        load_roc_value, roc_function_call, BuilderExt, RocReturn,
+        get_coverage_counters_ptr, get_expect_inputs_ptr, load_roc_value, roc_function_call,
+        BuilderExt, RocReturn,
     },
     build_list::{
         list_append_unsafe, list_capacity, list_concat, list_drop_at, list_get_unsafe, list_len,
@@ -1187,6 +1188,82 @@ pub(crate) fn run_low_level<'a, 'ctx, 'env>(
             unreachable!("Not used in LLVM backend: {:?}", op);
         }
 
+        ExpectInput => {
+            // Property `expect`s read each generated input through the table that the host
+            // set with `set_expect_inputs`. The host owns the inputs; their refcounts are constant.
+            arguments!(index);
+
+            let i8_ptr_type = env.context.i8_type().ptr_type(AddressSpace::default());
+
+            let table = env
+                .builder
+                .new_build_load(
+                    i8_ptr_type.ptr_type(AddressSpace::default()),
+                    get_expect_inputs_ptr(env),
+                    "expect_inputs",
+                )
+                .into_pointer_value();
+
+            let input_ptr_ptr = unsafe {
+                env.builder.new_build_in_bounds_gep(
+                    i8_ptr_type,
+                    table,
+                    &[index.into_int_value()],
+                    "expect_input_ptr_ptr",
+                )
+            };
+            let input_ptr = env
+                .builder
+                .new_build_load(i8_ptr_type, input_ptr_ptr, "expect_input_ptr")
+                .into_pointer_value();
+
+            let basic_type = basic_type_from_layout(env, layout_interner, layout);
+            let input_ptr = env.builder.build_pointer_cast(
+                input_ptr,
+                basic_type.ptr_type(AddressSpace::default()),
+                "expect_input_cast",
+            );
+
+            load_roc_value(env, layout_interner, layout, input_ptr, "expect_input")
+        }
+
+        CoverageHit => {
+            // Expects run in several processes at once, which share the counters that the host
+            // set with `set_coverage_counters`.
+            arguments!(index);
+
+            let i64_type = env.context.i64_type();
+
+            let counters = env
+                .builder
+                .new_build_load(
+                    i64_type.ptr_type(AddressSpace::default()),
+                    get_coverage_counters_ptr(env),
+                    "coverage_counters",
+                )
+                .into_pointer_value();
+
+            let counter_ptr = unsafe {
+                env.builder.new_build_in_bounds_gep(
+                    i64_type,
+                    counters,
+                    &[index.into_int_value()],
+                    "coverage_counter_ptr",
+                )
+            };
+
+            env.builder
+                .build_atomicrmw(
+                    AtomicRMWBinOp::Add,
+                    counter_ptr,
+                    i64_type.const_int(1, false),
+                    AtomicOrdering::Monotonic,
+                )
+                .unwrap();
+
+            basic_type_from_layout(env, layout_interner, layout).const_zero()
+        }
+
         Unreachable => match RocReturn::from_layout(env, layout_interner, layout) {
             RocReturn::Return => {
                 let basic_type = basic_type_from_layout(env, layout_interner, layout);
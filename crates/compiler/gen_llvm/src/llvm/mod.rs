@@ -8,4 +8,5 @@ mod expect;
 pub mod externs;
 mod intrinsics;
 mod lowlevel;
+mod profile;
 pub mod refcounting;
@@ -0,0 +1,114 @@
+//! Instrumentation for `roc run --profile`, used in [LlvmBackendMode::BinaryProfile].
+//!
+//! Every Roc function tells the builtins when it is entered and when it returns, and every call
+//! to `roc_alloc`, `roc_realloc` and `roc_dealloc` is counted. The builtins use this to build the
+//! calling context tree that `roc` reads from shared memory.
+//!
+//! [LlvmBackendMode::BinaryProfile]: crate::llvm::build::LlvmBackendMode::BinaryProfile
+
+use crate::llvm::bitcode::{call_bitcode_fn, call_void_bitcode_fn};
+use crate::llvm::build::Env;
+use inkwell::module::Linkage;
+use inkwell::values::{BasicMetadataValueEnum, FunctionValue, InstructionOpcode, IntValue};
+use roc_builtins::bitcode;
+use roc_module::symbol::Symbol;
+
+/// Tell the builtins that the function `symbol` was entered.
+/// The builder must be at the start of the function's entry block.
+///
+/// Returns the node of the caller, which the exits of the function go back to.
+pub(crate) fn build_enter<'a, 'ctx, 'env>(
+    env: &Env<'a, 'ctx, 'env>,
+    symbol: Symbol,
+) -> IntValue<'ctx> {
+    let function = env.context.i64_type().const_int(symbol.as_u64(), false);
+
+    call_bitcode_fn(env, &[function.into()], bitcode::UTILS_PROFILE_ENTER).into_int_value()
+}
+
+/// Tell the builtins that the function is exited, before every return of `fn_val`.
+///
+/// An exit goes back to the node of the caller rather than popping a stack, so a call that
+/// doesn't return through here, like one that `roc_panic` jumps out of, only affects the
+/// profile until the next exit of a function that was running before it.
+///
+/// This runs before the function pass manager, so a call in tail position is followed by an exit
+/// and is never turned into a jump that would skip it.
+pub(crate) fn build_exits<'a, 'ctx, 'env>(
+    env: &Env<'a, 'ctx, 'env>,
+    fn_val: FunctionValue<'ctx>,
+    caller: IntValue<'ctx>,
+) {
+    for block in fn_val.get_basic_blocks() {
+        if let Some(terminator) = block.get_terminator() {
+            if terminator.get_opcode() == InstructionOpcode::Return {
+                env.builder.position_before(&terminator);
+
+                call_void_bitcode_fn(env, &[caller.into()], bitcode::UTILS_PROFILE_EXIT);
+            }
+        }
+    }
+}
+
+/// Count the allocations of the whole module, by the function that makes them.
+/// This must run after all calls to the allocator have been generated.
+pub(crate) fn wrap_allocator(env: &Env<'_, '_, '_>) {
+    // roc_alloc(size, alignment)
+    wrap_allocator_function(env, "roc_alloc", Some(0));
+
+    // roc_realloc(ptr, new_size, old_size, alignment)
+    wrap_allocator_function(env, "roc_realloc", Some(1));
+
+    // roc_dealloc(ptr, alignment)
+    wrap_allocator_function(env, "roc_dealloc", None);
+}
+
+/// Replace all calls to the host's `name` with an internal function that records the call and then
+/// calls the host. `size_param` is the parameter with the number of bytes that are allocated,
+/// or `None` for a deallocation.
+fn wrap_allocator_function(env: &Env<'_, '_, '_>, name: &str, size_param: Option<u32>) {
+    let host_fn = match env.module.get_function(name) {
+        Some(function) => function,
+        None => return,
+    };
+
+    let wrapper = env.module.add_function(
+        &format!("{name}_profiled"),
+        host_fn.get_type(),
+        Some(Linkage::Internal),
+    );
+    wrapper.set_call_conventions(host_fn.get_call_conventions());
+
+    // this must happen before the wrapper calls the host, or the wrapper would call itself
+    host_fn
+        .as_global_value()
+        .as_pointer_value()
+        .replace_all_uses_with(wrapper.as_global_value().as_pointer_value());
+
+    let entry = env.context.append_basic_block(wrapper, "entry");
+    env.builder.position_at_end(entry);
+
+    match size_param {
+        Some(index) => {
+            let size = wrapper.get_nth_param(index).unwrap();
+            call_void_bitcode_fn(env, &[size], bitcode::UTILS_PROFILE_ALLOC);
+        }
+        None => {
+            call_void_bitcode_fn(env, &[], bitcode::UTILS_PROFILE_DEALLOC);
+        }
+    }
+
+    let args: Vec<BasicMetadataValueEnum> =
+        wrapper.get_param_iter().map(|arg| arg.into()).collect();
+    let call = env.builder.build_call(host_fn, &args, name);
+    call.set_call_convention(host_fn.get_call_conventions());
+
+    match call.try_as_basic_value().left() {
+        Some(value) => env.builder.build_return(Some(&value)),
+        None => env.builder.build_return(None),
+    };
+
+    if cfg!(debug_assertions) {
+        crate::llvm::build::verify_fn(wrapper);
+    }
+}
@@ -5,16 +5,17 @@ use roc_builtins::bitcode::{FloatWidth, IntWidth};
 use roc_collections::all::MutMap;
 use roc_error_macros::internal_error;
 use roc_module::low_level::{LowLevel, LowLevelWrapperType};
-use roc_module::symbol::{Interns, Symbol};
+use roc_module::symbol::{Interns, ModuleId, Symbol};
 use roc_mono::code_gen_help::{CodeGenHelp, HelperOp, REFCOUNT_MAX};
 use roc_mono::ir::{
-    BranchInfo, CallType, CrashTag, Expr, JoinPointId, ListLiteralElement, Literal, ModifyRc,
-    Param, Proc, ProcLayout, Stmt,
+    BranchInfo, CallType, CrashTag, Expr, JoinPointId, ListLiteralElement, Literal, LookupType,
+    ModifyRc, Param, Proc, ProcLayout, Stmt,
 };
 use roc_mono::layout::{
     Builtin, InLayout, Layout, LayoutIds, LayoutInterner, STLayoutInterner, TagIdIntType,
     UnionLayout,
 };
+use roc_region::all::Region;
 use roc_std::RocDec;
 
 use roc_wasm_module::linking::{DataSymbol, WasmObjectSymbol};
@@ -32,7 +33,7 @@ use crate::low_level::{call_higher_order_lowlevel, LowLevelCall};
 use crate::storage::{AddressValue, Storage, StoredValue, StoredVarKind};
 use crate::{
     copy_memory, CopyMemoryConfig, Env, DEBUG_SETTINGS, MEMORY_NAME, PTR_SIZE, PTR_TYPE,
-    TARGET_INFO,
+    ROC_DBG_FN_NAME, ROC_EXPECT_FAILED_FN_NAME, TARGET_INFO,
 };
 
 #[derive(Clone, Copy, Debug)]
@@ -723,9 +724,32 @@ impl<'a, 'r> WasmBackend<'a, 'r> {
 
             Stmt::Refcounting(modify, following) => self.stmt_refcounting(modify, following),
 
-            Stmt::Dbg { .. } => todo!("dbg is not implemented in the wasm backend"),
-            Stmt::Expect { .. } => todo!("expect is not implemented in the wasm backend"),
-            Stmt::ExpectFx { .. } => todo!("expect-fx is not implemented in the wasm backend"),
+            Stmt::Dbg {
+                symbol,
+                variable,
+                remainder,
+            } => {
+                self.stmt_dbg(*symbol, *variable);
+                self.stmt(remainder);
+            }
+
+            Stmt::Expect {
+                condition,
+                region,
+                lookups,
+                variables,
+                remainder,
+            }
+            | Stmt::ExpectFx {
+                condition,
+                region,
+                lookups,
+                variables,
+                remainder,
+            } => {
+                self.stmt_expect(*condition, *region, lookups, variables);
+                self.stmt(remainder);
+            }
 
             Stmt::Crash(sym, tag) => self.stmt_crash(*sym, *tag),
         }
@@ -1031,6 +1055,103 @@ impl<'a, 'r> WasmBackend<'a, 'r> {
         self.code_builder.unreachable_();
     }
 
+    /// Pass a `dbg`'d value to the host's `roc_dbg`, along with its type variable.
+    /// Like the other backends, we put the `dbg` Symbol where the Region would go.
+    /// If the host doesn't provide `roc_dbg`, the statement is skipped.
+    fn stmt_dbg(&mut self, symbol: Symbol, variable: LookupType) {
+        if !self.host_provides(ROC_DBG_FN_NAME) {
+            return;
+        }
+
+        let region = unsafe { std::mem::transmute::<Symbol, Region>(symbol) };
+        self.load_report_header(symbol, region);
+        self.load_address_of_value(symbol);
+        self.code_builder.i32_const(variable.index() as i32);
+        self.call_host_fn_after_loading_args(ROC_DBG_FN_NAME, 5, false);
+    }
+
+    /// If an `expect` condition is false, report the failure to the host's `roc_expect_failed`.
+    /// The host gets the region of the `expect` and an array of `{ address: u32, variable: u32 }`,
+    /// one entry for each value it looks up.
+    /// If the host doesn't provide `roc_expect_failed`, the statement is skipped.
+    fn stmt_expect(
+        &mut self,
+        condition: Symbol,
+        region: Region,
+        lookups: &[Symbol],
+        variables: &[LookupType],
+    ) {
+        if !self.host_provides(ROC_EXPECT_FAILED_FN_NAME) {
+            return;
+        }
+
+        self.storage
+            .load_symbols(&mut self.code_builder, &[condition]);
+        self.code_builder.i32_eqz();
+        self.code_builder.if_();
+        {
+            let entry_size = 2 * PTR_SIZE;
+            let (array_local, array_offset) = self
+                .storage
+                .allocate_anonymous_stack_memory(lookups.len() as u32 * entry_size, PTR_SIZE);
+
+            for (i, (lookup, variable)) in lookups.iter().zip(variables.iter()).enumerate() {
+                let entry_offset = array_offset + i as u32 * entry_size;
+
+                self.code_builder.get_local(array_local);
+                self.load_address_of_value(*lookup);
+                self.code_builder.i32_store(Align::Bytes4, entry_offset);
+
+                self.code_builder.get_local(array_local);
+                self.code_builder.i32_const(variable.index() as i32);
+                self.code_builder
+                    .i32_store(Align::Bytes4, entry_offset + PTR_SIZE);
+            }
+
+            self.load_report_header(condition, region);
+            self.code_builder.get_local(array_local);
+            if array_offset != 0 {
+                self.code_builder.i32_const(array_offset as i32);
+                self.code_builder.i32_add();
+            }
+            self.code_builder.i32_const(lookups.len() as i32);
+            self.call_host_fn_after_loading_args(ROC_EXPECT_FAILED_FN_NAME, 5, false);
+        }
+        self.code_builder.end();
+    }
+
+    /// Load the module ID and region that the host uses to find the source of a report
+    fn load_report_header(&mut self, symbol: Symbol, region: Region) {
+        let module_id = unsafe { std::mem::transmute::<ModuleId, u32>(symbol.module_id()) };
+        self.code_builder.i32_const(module_id as i32);
+        self.code_builder.i32_const(region.start().offset as i32);
+        self.code_builder.i32_const(region.end().offset as i32);
+    }
+
+    /// Load a pointer to a value, first copying it to the stack frame if it's a primitive
+    fn load_address_of_value(&mut self, symbol: Symbol) {
+        let (local_id, offset) = match self.storage.get(&symbol).to_owned() {
+            StoredValue::StackMemory { location, .. } => {
+                location.local_and_offset(self.storage.stack_frame_pointer)
+            }
+            StoredValue::VirtualMachineStack { size, .. } | StoredValue::Local { size, .. } => {
+                let (local_id, offset) = self.storage.allocate_anonymous_stack_memory(size, size);
+                self.storage
+                    .copy_value_to_memory(&mut self.code_builder, local_id, offset, symbol);
+                (local_id, offset)
+            }
+        };
+        self.code_builder.get_local(local_id);
+        if offset != 0 {
+            self.code_builder.i32_const(offset as i32);
+            self.code_builder.i32_add();
+        }
+    }
+
+    fn host_provides(&self, name: &str) -> bool {
+        self.host_lookup.iter().any(|(fn_name, _)| *fn_name == name)
+    }
+
     /**********************************************************
 
             EXPRESSIONS
@@ -41,6 +41,11 @@ pub const MEMORY_NAME: &str = "memory";
 pub const BUILTINS_IMPORT_MODULE_NAME: &str = "env";
 pub const STACK_POINTER_NAME: &str = "__stack_pointer";
 
+/// Optional host functions for reporting `dbg` and failed `expect`s.
+/// If the host doesn't define them, the wasm backend skips those statements.
+pub const ROC_DBG_FN_NAME: &str = "roc_dbg";
+pub const ROC_EXPECT_FAILED_FN_NAME: &str = "roc_expect_failed";
+
 pub struct Env<'a> {
     pub arena: &'a Bump,
     pub module_id: ModuleId,
@@ -1,7 +1,7 @@
 use bumpalo::collections::Vec;
 use bumpalo::Bump;
 use roc_builtins::bitcode::{self, FloatWidth, IntWidth};
-use roc_error_macros::internal_error;
+use roc_error_macros::{internal_error, user_error};
 use roc_module::low_level::LowLevel;
 use roc_module::symbol::Symbol;
 use roc_mono::code_gen_help::HelperOp;
@@ -10,6 +10,7 @@ use roc_mono::layout::{Builtin, FieldOrderHash, InLayout, Layout, LayoutInterner
 use roc_mono::low_level::HigherOrder;
 
 use crate::backend::{ProcLookupData, ProcSource, WasmBackend};
+use crate::code_builder::CodeBuilder;
 use crate::layout::{CallConv, StackMemoryFormat, WasmLayout};
 use crate::storage::{AddressValue, StackMemoryLocation, StoredValue};
 use crate::{PTR_TYPE, TARGET_INFO};
@@ -810,8 +811,7 @@ impl<'a> LowLevelCall<'a> {
             NumAddWrap => match self.ret_layout_raw {
                 Layout::Builtin(Builtin::Int(width)) => match width {
                     IntWidth::I128 | IntWidth::U128 => {
-                        // TODO: don't panic
-                        self.load_args_and_call_zig(backend, &bitcode::NUM_ADD_OR_PANIC_INT[width])
+                        self.load_args_and_call_zig(backend, &bitcode::NUM_ADD_WRAP_INT[width])
                     }
                     IntWidth::I64 | IntWidth::U64 => {
                         self.load_args(backend);
@@ -901,8 +901,7 @@ impl<'a> LowLevelCall<'a> {
             NumSubWrap => match self.ret_layout_raw {
                 Layout::Builtin(Builtin::Int(width)) => match width {
                     IntWidth::I128 | IntWidth::U128 => {
-                        // TODO: don't panic
-                        self.load_args_and_call_zig(backend, &bitcode::NUM_SUB_OR_PANIC_INT[width])
+                        self.load_args_and_call_zig(backend, &bitcode::NUM_SUB_WRAP_INT[width])
                     }
                     IntWidth::I64 | IntWidth::U64 => {
                         self.load_args(backend);
@@ -989,8 +988,7 @@ impl<'a> LowLevelCall<'a> {
             NumMulWrap => match self.ret_layout_raw {
                 Layout::Builtin(Builtin::Int(width)) => match width {
                     IntWidth::I128 | IntWidth::U128 => {
-                        // TODO: don't panic
-                        self.load_args_and_call_zig(backend, &bitcode::NUM_MUL_OR_PANIC_INT[width])
+                        self.load_args_and_call_zig(backend, &bitcode::NUM_MUL_WRAP_INT[width])
                     }
                     IntWidth::I64 | IntWidth::U64 => {
                         self.load_args(backend);
@@ -1055,6 +1053,14 @@ impl<'a> LowLevelCall<'a> {
                     x => internal_error!("NumMulChecked is not defined for {:?}", x),
                 }
             }
+            NumGt | NumGte | NumLt | NumLte
+                if matches!(
+                    CodeGenNumType::for_symbol(backend, self.arguments[0]),
+                    I128 | Decimal
+                ) =>
+            {
+                self.compare_num128(backend, self.lowlevel)
+            }
             NumGt => {
                 self.load_args(backend);
                 match CodeGenNumType::for_symbol(backend, self.arguments[0]) {
@@ -1191,7 +1197,13 @@ impl<'a> LowLevelCall<'a> {
                         backend.code_builder.f64_lt();
                         backend.code_builder.i32_add();
                     }
-                    x => todo!("{:?} for {:?}", self.lowlevel, x),
+                    I128 | Decimal => {
+                        let locations = self.num128_arg_locations(backend);
+                        Self::eq_num128_bytes(backend, locations);
+                        backend.code_builder.i32_eqz();
+                        self.compare_num128(backend, NumLt);
+                        backend.code_builder.i32_add();
+                    }
                 }
             }
             NumDivFrac => {
@@ -1221,6 +1233,12 @@ impl<'a> LowLevelCall<'a> {
                             backend.code_builder.i64_div_u()
                         }
                     }
+                    I128 => match self.ret_layout_raw {
+                        Layout::Builtin(Builtin::Int(width)) => {
+                            self.load_args_and_call_zig(backend, &bitcode::NUM_DIV_TRUNC[width])
+                        }
+                        _ => panic_ret_type(),
+                    },
                     x => todo!("{:?} for {:?}", self.lowlevel, x),
                 }
             }
@@ -1236,6 +1254,14 @@ impl<'a> LowLevelCall<'a> {
                 match CodeGenNumType::for_symbol(backend, self.arguments[0]) {
                     I32 => backend.code_builder.i32_rem_s(),
                     I64 => backend.code_builder.i64_rem_s(),
+                    I128 => {
+                        // from compiler_rt
+                        if symbol_is_signed_int(backend, self.arguments[0]) {
+                            self.load_args_and_call_zig(backend, "__modti3")
+                        } else {
+                            self.load_args_and_call_zig(backend, "__umodti3")
+                        }
+                    }
                     _ => todo!("{:?} for {:?}", self.lowlevel, self.ret_layout),
                 }
             }
@@ -1432,6 +1458,7 @@ impl<'a> LowLevelCall<'a> {
                     }
                     F32 => backend.code_builder.f32_neg(),
                     F64 => backend.code_builder.f64_neg(),
+                    Decimal => self.load_args_and_call_zig(backend, bitcode::DEC_NEGATE),
                     _ => todo!("{:?} for {:?}", self.lowlevel, self.ret_layout),
                 }
             }
@@ -1497,7 +1524,10 @@ impl<'a> LowLevelCall<'a> {
                 let width = match ret_type {
                     CodeGenNumType::I32 => IntWidth::I32,
                     CodeGenNumType::I64 => IntWidth::I64,
-                    CodeGenNumType::I128 => todo!("{:?} for I128", self.lowlevel),
+                    CodeGenNumType::I128 => match self.ret_layout_raw {
+                        Layout::Builtin(Builtin::Int(width)) => width,
+                        _ => panic_ret_type(),
+                    },
                     _ => internal_error!("Invalid return type for round: {:?}", ret_type),
                 };
 
@@ -1532,7 +1562,34 @@ impl<'a> LowLevelCall<'a> {
                     (I32, F64) => backend.code_builder.i32_trunc_s_f64(),
                     (I64, F32) => backend.code_builder.i64_trunc_s_f32(),
                     (I64, F64) => backend.code_builder.i64_trunc_s_f64(),
-                    (I128, _) => todo!("{:?} for I128", self.lowlevel),
+                    (I128, F32 | F64) => {
+                        // The value is already a whole number, so rounding it just converts it.
+                        // The return address has to go below it on the VM stack.
+                        let width = match self.ret_layout_raw {
+                            Layout::Builtin(Builtin::Int(width)) => width,
+                            _ => panic_ret_type(),
+                        };
+                        let (float_type, name) = if arg_type == F32 {
+                            (ValueType::F32, &bitcode::NUM_ROUND_F32[width])
+                        } else {
+                            (ValueType::F64, &bitcode::NUM_ROUND_F64[width])
+                        };
+                        let float = backend.storage.create_anonymous_local(float_type);
+                        backend.code_builder.set_local(float);
+                        let (ret_local, ret_offset) = match &self.ret_storage {
+                            StoredValue::StackMemory { location, .. } => {
+                                location.local_and_offset(backend.storage.stack_frame_pointer)
+                            }
+                            _ => internal_error!("I128 should be in stack memory"),
+                        };
+                        backend.code_builder.get_local(ret_local);
+                        if ret_offset != 0 {
+                            backend.code_builder.i32_const(ret_offset as i32);
+                            backend.code_builder.i32_add();
+                        }
+                        backend.code_builder.get_local(float);
+                        backend.call_host_fn_after_loading_args(name, 2, false);
+                    }
                     _ => panic_ret_type(),
                 }
             }
@@ -1548,7 +1605,10 @@ impl<'a> LowLevelCall<'a> {
                 let width = match ret_type {
                     CodeGenNumType::I32 => IntWidth::I32,
                     CodeGenNumType::I64 => IntWidth::I64,
-                    CodeGenNumType::I128 => todo!("{:?} for I128", self.lowlevel),
+                    CodeGenNumType::I128 => match self.ret_layout_raw {
+                        Layout::Builtin(Builtin::Int(width)) => width,
+                        _ => panic_ret_type(),
+                    },
                     _ => internal_error!("Invalid return type for pow: {:?}", ret_type),
                 };
 
@@ -1577,46 +1637,60 @@ impl<'a> LowLevelCall<'a> {
             },
             NumBytesToU16 => self.load_args_and_call_zig(backend, bitcode::NUM_BYTES_TO_U16),
             NumBytesToU32 => self.load_args_and_call_zig(backend, bitcode::NUM_BYTES_TO_U32),
-            NumBitwiseAnd => {
-                self.load_args(backend);
-                match CodeGenNumType::from(self.ret_layout) {
-                    I32 => backend.code_builder.i32_and(),
-                    I64 => backend.code_builder.i64_and(),
-                    I128 => todo!("{:?} for I128", self.lowlevel),
-                    _ => panic_ret_type(),
+            NumBitwiseAnd => match CodeGenNumType::from(self.ret_layout) {
+                I32 => {
+                    self.load_args(backend);
+                    backend.code_builder.i32_and()
                 }
-            }
-            NumBitwiseXor => {
-                self.load_args(backend);
-                match CodeGenNumType::from(self.ret_layout) {
-                    I32 => backend.code_builder.i32_xor(),
-                    I64 => backend.code_builder.i64_xor(),
-                    I128 => todo!("{:?} for I128", self.lowlevel),
-                    _ => panic_ret_type(),
+                I64 => {
+                    self.load_args(backend);
+                    backend.code_builder.i64_and()
                 }
-            }
-            NumBitwiseOr => {
-                self.load_args(backend);
-                match CodeGenNumType::from(self.ret_layout) {
-                    I32 => backend.code_builder.i32_or(),
-                    I64 => backend.code_builder.i64_or(),
-                    I128 => todo!("{:?} for I128", self.lowlevel),
-                    _ => panic_ret_type(),
+                I128 => self.bitwise_num128(backend, CodeBuilder::i64_and),
+                _ => panic_ret_type(),
+            },
+            NumBitwiseXor => match CodeGenNumType::from(self.ret_layout) {
+                I32 => {
+                    self.load_args(backend);
+                    backend.code_builder.i32_xor()
                 }
-            }
+                I64 => {
+                    self.load_args(backend);
+                    backend.code_builder.i64_xor()
+                }
+                I128 => self.bitwise_num128(backend, CodeBuilder::i64_xor),
+                _ => panic_ret_type(),
+            },
+            NumBitwiseOr => match CodeGenNumType::from(self.ret_layout) {
+                I32 => {
+                    self.load_args(backend);
+                    backend.code_builder.i32_or()
+                }
+                I64 => {
+                    self.load_args(backend);
+                    backend.code_builder.i64_or()
+                }
+                I128 => self.bitwise_num128(backend, CodeBuilder::i64_or),
+                _ => panic_ret_type(),
+            },
             NumShiftLeftBy => {
                 let num = self.arguments[0];
                 let bits = self.arguments[1];
-                backend
-                    .storage
-                    .load_symbols(&mut backend.code_builder, &[num, bits]);
                 match CodeGenNumType::from(self.ret_layout) {
-                    I32 => backend.code_builder.i32_shl(),
+                    I32 => {
+                        backend
+                            .storage
+                            .load_symbols(&mut backend.code_builder, &[num, bits]);
+                        backend.code_builder.i32_shl();
+                    }
                     I64 => {
+                        backend
+                            .storage
+                            .load_symbols(&mut backend.code_builder, &[num, bits]);
                         backend.code_builder.i64_extend_u_i32();
                         backend.code_builder.i64_shl();
                     }
-                    I128 => todo!("{:?} for I128", self.lowlevel),
+                    I128 => self.load_args_and_call_zig(backend, "__ashlti3"), // from compiler_rt
                     _ => panic_ret_type(),
                 }
             }
@@ -1665,7 +1739,7 @@ impl<'a> LowLevelCall<'a> {
                         backend.code_builder.i64_extend_u_i32();
                         backend.code_builder.i64_shr_s();
                     }
-                    I128 => todo!("{:?} for I128", self.lowlevel),
+                    I128 => self.load_args_and_call_zig(backend, "__ashrti3"), // from compiler_rt
                     _ => panic_ret_type(),
                 }
             }
@@ -1902,6 +1976,14 @@ impl<'a> LowLevelCall<'a> {
                 unreachable!("The {:?} operation is turned into mono Expr", self.lowlevel)
             }
 
+            ExpectInput => user_error!(
+                "Property `expect`s can't be compiled to WebAssembly yet. Test them with a native target instead."
+            ),
+
+            CoverageHit => {
+                internal_error!("`roc test --coverage` is only supported by the LLVM backend")
+            }
+
             Unreachable => match self.ret_storage {
                 StoredValue::VirtualMachineStack { value_type, .. }
                 | StoredValue::Local { value_type, .. } => match value_type {
@@ -2074,6 +2156,68 @@ impl<'a> LowLevelCall<'a> {
         backend.code_builder.i32_and();
     }
 
+    /// Stack memory locations of the two arguments of a 128-bit numerical operation
+    fn num128_arg_locations(&self, backend: &WasmBackend<'a, '_>) -> [StackMemoryLocation; 2] {
+        let location = |arg: &Symbol| match backend.storage.get(arg) {
+            StoredValue::StackMemory { location, .. } => location.clone(),
+            _ => internal_error!("128-bit numbers should be in stack memory"),
+        };
+        [location(&self.arguments[0]), location(&self.arguments[1])]
+    }
+
+    /// Ordering comparisons for 128-bit numbers, using the Zig builtins.
+    /// Dec is represented as an i128, so it's compared the same way as I128.
+    /// Greater-than is implemented as less-than with the arguments swapped.
+    fn compare_num128(&self, backend: &mut WasmBackend<'a, '_>, op: LowLevel) {
+        let width = match backend.storage.symbol_layouts[&self.arguments[0]] {
+            Layout::U128 => IntWidth::U128,
+            _ => IntWidth::I128,
+        };
+        let (x, y) = (self.arguments[0], self.arguments[1]);
+        let (name, arguments) = match op {
+            LowLevel::NumLt => (&bitcode::NUM_LESS_THAN[width], [x, y]),
+            LowLevel::NumLte => (&bitcode::NUM_LESS_THAN_OR_EQUAL[width], [x, y]),
+            LowLevel::NumGt => (&bitcode::NUM_LESS_THAN[width], [y, x]),
+            LowLevel::NumGte => (&bitcode::NUM_LESS_THAN_OR_EQUAL[width], [y, x]),
+            _ => internal_error!("{:?} ended up in 128-bit comparison code", op),
+        };
+
+        let (num_wasm_args, has_return_val, _) = backend.storage.load_symbols_for_call(
+            backend.env.arena,
+            &mut backend.code_builder,
+            &arguments,
+            self.ret_symbol,
+            &WasmLayout::new(backend.layout_interner, Layout::BOOL),
+            CallConv::Zig,
+        );
+        backend.call_host_fn_after_loading_args(name, num_wasm_args, has_return_val);
+    }
+
+    /// Bitwise operations on 128-bit integers, one 64-bit half at a time
+    fn bitwise_num128(&self, backend: &mut WasmBackend<'a, '_>, op: fn(&mut CodeBuilder<'a>)) {
+        let [arg0, arg1] = self.num128_arg_locations(backend);
+        let (local0, offset0) = arg0.local_and_offset(backend.storage.stack_frame_pointer);
+        let (local1, offset1) = arg1.local_and_offset(backend.storage.stack_frame_pointer);
+        let (ret_local, ret_offset) = match &self.ret_storage {
+            StoredValue::StackMemory { location, .. } => {
+                location.local_and_offset(backend.storage.stack_frame_pointer)
+            }
+            _ => internal_error!("I128 should be in stack memory"),
+        };
+
+        for half in [0, 8] {
+            backend.code_builder.get_local(ret_local);
+            backend.code_builder.get_local(local0);
+            backend.code_builder.i64_load(Align::Bytes8, offset0 + half);
+            backend.code_builder.get_local(local1);
+            backend.code_builder.i64_load(Align::Bytes8, offset1 + half);
+            op(&mut backend.code_builder);
+            backend
+                .code_builder
+                .i64_store(Align::Bytes8, ret_offset + half);
+        }
+    }
+
     fn num_to_str(&self, backend: &mut WasmBackend<'a, '_>) {
         let arg_layout = backend.storage.symbol_layouts[&self.arguments[0]];
         match backend.layout_interner.get(arg_layout) {
@@ -70,7 +70,7 @@ fn write_types_for_module_dummy(output_path: &Path) {
 fn write_types_for_module_real(module_id: ModuleId, filename: &str, output_path: &Path) {
     use roc_can::module::TypeState;
     use roc_load_internal::file::{LoadingProblem, Threading};
-    use roc_reporting::cli::report_problems;
+    use roc_reporting::cli::{report_problems, MessageFormat};
 
     let arena = Bump::new();
     let cwd = std::env::current_dir().unwrap();
@@ -106,6 +106,7 @@ fn write_types_for_module_real(module_id: ModuleId, filename: &str, output_path:
         &module.interns,
         &mut module.can_problems,
         &mut module.type_problems,
+        MessageFormat::Human,
     );
 
     if problems.errors + problems.warnings > 0 {
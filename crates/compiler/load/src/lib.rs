@@ -18,8 +18,9 @@ const SKIP_SUBS_CACHE: bool = {
 
 pub use roc_load_internal::docs;
 pub use roc_load_internal::file::{
-    EntryPoint, ExecutionMode, ExpectMetadata, Expectations, LoadConfig, LoadResult, LoadStart,
-    LoadedModule, LoadingProblem, MonomorphizedModule, Phase, Threading,
+    keep_types_in_memory, EntryPoint, ExecutionMode, ExpectMetadata, Expectations, LoadConfig,
+    LoadResult, LoadStart, LoadedModule, LoadingProblem, MonomorphizedModule, Phase, TestOptions,
+    Threading,
 };
 
 #[allow(clippy::too_many_arguments)]
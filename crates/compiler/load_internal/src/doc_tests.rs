@@ -0,0 +1,332 @@
+//! Doc tests: the examples in doc comments, which `roc test --doc` tests.
+//!
+//! A fenced `roc` code block in a doc comment is tested if it has `expect`s, like
+//!
+//!     ## ```roc
+//!     ## nums = [1, 2]
+//!     ##
+//!     ## expect List.len nums == 2
+//!     ## ```
+//!
+//! in which case its other defs are in scope in its `expect`s. Otherwise, every example in the
+//! block that compares with `==`, like `List.len [1, 2] == 2`, is tested as an `expect`.
+//!
+//! The code is parsed from a copy of the module's source where everything else is blanked out.
+//! That way, it has the regions of the doc comment, which is where failures are reported.
+use bumpalo::Bump;
+use roc_module::called_via::BinOp;
+use roc_parse::ast::{Defs, Expr, ExtractSpaces, ValueDef};
+use roc_parse::module::module_defs;
+use roc_parse::parser::{Parser, SyntaxError};
+use roc_parse::state::State;
+use roc_region::all::{Loc, Position, Region};
+use std::ops::Range;
+
+/// The lines of a code block, as the ranges of the source that come after the `##`
+type CodeBlock = Vec<Range<usize>>;
+
+/// Add the doc tests of a module to its defs, as top-level `expect`s
+pub(crate) fn add_doc_tests<'a>(
+    arena: &'a Bump,
+    src: &'a str,
+    defs: &mut Defs<'a>,
+) -> Result<(), SyntaxError<'a>> {
+    for block in code_blocks(src) {
+        let has_expects = block
+            .iter()
+            .any(|line| src[line.clone()].split_whitespace().next() == Some("expect"));
+
+        if has_expects {
+            add_expects(arena, src, &block, defs)?;
+        } else {
+            for example in examples(src, &block) {
+                add_example(arena, src, &example, defs)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The fenced `roc` code blocks in the doc comments of the source
+fn code_blocks(src: &str) -> Vec<CodeBlock> {
+    enum Fence {
+        Outside,
+        Roc(CodeBlock),
+        /// A block of some other language, which isn't tested
+        Other,
+    }
+
+    let mut blocks = Vec::new();
+    let mut fence = Fence::Outside;
+    let mut line_start = 0;
+
+    for line in src.split_inclusive('\n') {
+        let line_end = line_start + line.trim_end_matches(&['\n', '\r'][..]).len();
+        let trimmed = line.trim_start();
+        let doc_start = line_start + (line.len() - trimmed.len()) + "##".len();
+        let is_doc_comment = trimmed.starts_with("##");
+
+        line_start += line.len();
+
+        if !is_doc_comment {
+            // A block that isn't closed before the doc comment ends is not tested
+            fence = Fence::Outside;
+            continue;
+        }
+
+        let doc = src[doc_start..line_end].trim();
+
+        fence = match (fence, doc.strip_prefix("```").map(str::trim)) {
+            (Fence::Outside, Some("roc")) => Fence::Roc(Vec::new()),
+            (Fence::Outside, Some(_)) => Fence::Other,
+            (Fence::Outside, None) => Fence::Outside,
+            (Fence::Roc(block), Some(_)) => {
+                blocks.push(block);
+
+                Fence::Outside
+            }
+            (Fence::Roc(mut block), None) => {
+                block.push(doc_start..line_end);
+
+                Fence::Roc(block)
+            }
+            (Fence::Other, Some(_)) => Fence::Outside,
+            (Fence::Other, None) => Fence::Other,
+        };
+    }
+
+    blocks
+}
+
+/// The examples of a block without `expect`s. Each example starts on a line that is indented
+/// no more than the rest of the block, and goes on over the lines that are indented more.
+fn examples(src: &str, block: &[Range<usize>]) -> Vec<CodeBlock> {
+    let indent = |line: &Range<usize>| {
+        let text = &src[line.clone()];
+
+        text.len() - text.trim_start().len()
+    };
+
+    let is_code = |line: &Range<usize>| {
+        let text = src[line.clone()].trim();
+
+        !text.is_empty() && !text.starts_with('#')
+    };
+
+    let min_indent = block.iter().filter(|line| is_code(line)).map(indent).min();
+    let mut examples: Vec<CodeBlock> = Vec::new();
+
+    for line in block {
+        if is_code(line) && Some(indent(line)) == min_indent {
+            examples.push(vec![line.clone()]);
+        } else if let Some(example) = examples.last_mut() {
+            example.push(line.clone());
+        }
+    }
+
+    examples
+}
+
+/// A copy of the source up to the end of the lines, with everything but the lines blanked out.
+/// Newlines are kept, so the code has the same regions as it does in the source.
+fn blank_all_but<'a>(arena: &'a Bump, src: &str, lines: &[Range<usize>]) -> &'a str {
+    let end = lines.last().map(|line| line.end).unwrap_or(0);
+    let mut bytes: Vec<u8> = src.as_bytes()[..end]
+        .iter()
+        .map(|byte| if *byte == b'\n' { b'\n' } else { b' ' })
+        .collect();
+
+    for line in lines {
+        bytes[line.clone()].copy_from_slice(&src.as_bytes()[line.clone()]);
+    }
+
+    // The lines start after `##` and end before a newline, so they are whole characters
+    arena.alloc_str(std::str::from_utf8(&bytes).unwrap())
+}
+
+/// Add the `expect`s of a block, with the block's other defs before each of them
+fn add_expects<'a>(
+    arena: &'a Bump,
+    src: &str,
+    block: &[Range<usize>],
+    defs: &mut Defs<'a>,
+) -> Result<(), SyntaxError<'a>> {
+    let code = blank_all_but(arena, src, block);
+    let block_defs = match module_defs().parse(arena, State::new(code.as_bytes()), 0) {
+        Ok((_, block_defs, _)) => block_defs,
+        Err((_, fail)) => return Err(fail),
+    };
+
+    let mut local_defs = Defs::default();
+
+    for (index, def) in block_defs.defs().enumerate() {
+        let region = block_defs.regions[index];
+
+        match def {
+            Err(ValueDef::Expect { condition, .. }) => {
+                let condition = if local_defs.is_empty() {
+                    **condition
+                } else {
+                    Loc::at(
+                        condition.region,
+                        Expr::Defs(arena.alloc(local_defs.clone()), *condition),
+                    )
+                };
+
+                push_expect(arena, condition, region, defs);
+            }
+            Err(
+                value_def @ (ValueDef::Annotation(..)
+                | ValueDef::Body(..)
+                | ValueDef::AnnotatedBody { .. }),
+            ) => {
+                local_defs.push_value_def(*value_def, region, &[], &[]);
+            }
+            Ok(_) | Err(ValueDef::ExpectFx { .. } | ValueDef::Dbg { .. }) => {
+                // Types can't be defined in an expression, and effects can't run in an `expect`
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Add an example like `List.len [1, 2] == 2` as an `expect`, if it compares with `==`
+fn add_example<'a>(
+    arena: &'a Bump,
+    src: &str,
+    example: &[Range<usize>],
+    defs: &mut Defs<'a>,
+) -> Result<(), SyntaxError<'a>> {
+    let code = blank_all_but(arena, src, example);
+    let loc_expr = match roc_parse::expr::test_parse_expr(0, arena, State::new(code.as_bytes())) {
+        Ok(loc_expr) => loc_expr,
+        // Blocks can also show code that isn't an expression, like the definition of a function
+        Err(_) if !code.contains("==") => return Ok(()),
+        Err(fail) => {
+            let start = example.first().map(|line| line.start).unwrap_or(0);
+
+            return Err(SyntaxError::Expr(fail, Position::new(start as u32)));
+        }
+    };
+
+    let is_comparison = match loc_expr.value.extract_spaces().item {
+        Expr::BinOps(operands, _) => operands.iter().any(|(_, op)| op.value == BinOp::Equals),
+        _ => false,
+    };
+
+    if is_comparison {
+        let region = loc_expr.region;
+
+        push_expect(arena, loc_expr, region, defs);
+    }
+
+    Ok(())
+}
+
+fn push_expect<'a>(arena: &'a Bump, condition: Loc<Expr<'a>>, region: Region, defs: &mut Defs<'a>) {
+    // There's no comment before the expect; the region of the code is enough to report it with
+    let preceding_comment = Region::new(region.start(), region.start());
+
+    defs.push_value_def(
+        ValueDef::Expect {
+            condition: arena.alloc(condition),
+            preceding_comment,
+        },
+        region,
+        &[],
+        &[],
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts<'a>(src: &'a str, blocks: &[CodeBlock]) -> Vec<Vec<&'a str>> {
+        blocks
+            .iter()
+            .map(|block| block.iter().map(|line| &src[line.clone()]).collect())
+            .collect()
+    }
+
+    #[test]
+    fn code_blocks_only_roc() {
+        let src = "## ```sh\n## echo 1 == 1\n## ```\n##\n## ```\n## 2 == 2\n## ```\n##\n## ```roc\n## 3 == 3\n## ```\nx = 1\n";
+
+        assert_eq!(texts(src, &code_blocks(src)), vec![vec![" 3 == 3"]]);
+    }
+
+    #[test]
+    fn code_blocks_unclosed() {
+        // The doc comment ends before the fence is closed
+        let src = "## ```roc\n## 1 == 1\nx = 1\n\n## ```roc\n## 2 == 2\n## ```\ny = 2\n";
+
+        assert_eq!(texts(src, &code_blocks(src)), vec![vec![" 2 == 2"]]);
+
+        // The source ends before the fence is closed
+        let src = "## ```roc\n## 1 == 1\n";
+
+        assert!(code_blocks(src).is_empty());
+    }
+
+    #[test]
+    fn code_blocks_crlf() {
+        let src = "## ```roc\r\n## 1 == 1\r\n##\r\n## 2 == 2\r\n## ```\r\nx = 1\r\n";
+
+        assert_eq!(
+            texts(src, &code_blocks(src)),
+            vec![vec![" 1 == 1", "", " 2 == 2"]]
+        );
+    }
+
+    #[test]
+    fn examples_span_indented_lines() {
+        let src = "## ```roc\n## increment 1 == 2\n##\n## increment -1\n##     == 0\n## ```\n";
+        let blocks = code_blocks(src);
+
+        assert_eq!(
+            texts(src, &examples(src, &blocks[0])),
+            vec![
+                vec![" increment 1 == 2", ""],
+                vec![" increment -1", "     == 0"]
+            ]
+        );
+    }
+
+    #[test]
+    fn examples_skip_leading_comments() {
+        let src = "## ```roc\n##     # not an example\n## 1 == 1\n## ```\n";
+        let blocks = code_blocks(src);
+
+        assert_eq!(
+            texts(src, &examples(src, &blocks[0])),
+            vec![vec![" 1 == 1"]]
+        );
+    }
+
+    #[test]
+    fn blank_all_but_keeps_regions() {
+        let arena = Bump::new();
+        let src = "## ```roc\n## 1 == 1\n## ```\n";
+        let blocks = code_blocks(src);
+
+        assert_eq!(
+            blank_all_but(&arena, src, &blocks[0]),
+            "         \n   1 == 1"
+        );
+    }
+
+    #[test]
+    fn blank_all_but_crlf() {
+        let arena = Bump::new();
+        let src = "## ```roc\r\n## 1 == 1\r\n##\r\n## 2 == 2\r\n## ```\r\n";
+        let blocks = code_blocks(src);
+
+        assert_eq!(
+            blank_all_but(&arena, src, &blocks[0]),
+            "          \n   1 == 1 \n   \n   2 == 2"
+        );
+    }
+}
@@ -30,6 +30,7 @@ use roc_module::symbol::{
     IdentIds, IdentIdsByModule, Interns, ModuleId, ModuleIds, PQModuleName, PackageModuleIds,
     PackageQualified, Symbol,
 };
+use roc_mono::coverage::{CoverageMap, SharedCoverageMap};
 use roc_mono::ir::{
     CapturedSymbols, ExternalSpecializations, PartialProc, Proc, ProcLayout, Procs, ProcsBase,
     UpdateModeIds,
@@ -49,7 +50,8 @@ use roc_parse::module::module_defs;
 use roc_parse::parser::{FileError, Parser, SourceError, SyntaxError};
 use roc_problem::Severity;
 use roc_region::all::{LineInfo, Loc, Region};
-use roc_reporting::report::{Annotation, Palette, RenderTarget};
+use roc_reporting::cli::JsonDiagnostic;
+use roc_reporting::report::{Annotation, Palette, RenderTarget, Report, RocDocAllocator};
 use roc_solve::module::{extract_module_owned_implementations, Solved, SolvedModule};
 use roc_solve_problem::TypeError;
 use roc_target::TargetInfo;
@@ -71,6 +73,8 @@ use {
     roc_packaging::https::PackageMetadata,
 };
 
+pub use crate::type_cache::keep_in_memory as keep_types_in_memory;
+use crate::type_cache::{TypeCache, TypeCacheEntry};
 pub use crate::work::Phase;
 use crate::work::{DepCycle, Dependencies};
 
@@ -111,21 +115,44 @@ pub enum ExecutionMode {
     ExecutableIfCheck,
     /// Test is like [`ExecutionMode::ExecutableIfCheck`], but rather than producing a proper
     /// executable, run tests.
-    Test,
+    Test(TestOptions),
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TestOptions {
+    /// Count how often each top-level def, `when` branch and `if` arm runs
+    pub coverage: bool,
+    /// Also test the examples in doc comments, like `roc test --doc`
+    pub doc_tests: bool,
 }
 
 impl ExecutionMode {
     fn goal_phase(&self) -> Phase {
         match self {
             ExecutionMode::Executable => Phase::MakeSpecializations,
-            ExecutionMode::Check | ExecutionMode::ExecutableIfCheck | ExecutionMode::Test => {
+            ExecutionMode::Check | ExecutionMode::ExecutableIfCheck | ExecutionMode::Test(_) => {
                 Phase::SolveTypes
             }
         }
     }
 
     fn build_if_checks(&self) -> bool {
-        matches!(self, Self::ExecutableIfCheck | Self::Test)
+        matches!(self, Self::ExecutableIfCheck | Self::Test(_))
+    }
+
+    fn is_test(&self) -> bool {
+        matches!(self, Self::Test(_))
+    }
+
+    /// Whether the examples in doc comments are added to modules as `expect`s
+    fn doc_tests(&self) -> bool {
+        matches!(
+            self,
+            Self::Test(TestOptions {
+                doc_tests: true,
+                ..
+            })
+        )
     }
 }
 
@@ -278,7 +305,10 @@ fn start_phase<'a>(
                 // parse the file
                 let header = state.module_cache.headers.remove(&module_id).unwrap();
 
-                BuildTask::Parse { header }
+                BuildTask::Parse {
+                    header,
+                    doc_tests: state.exec_mode.doc_tests(),
+                }
             }
             Phase::CanonicalizeAndConstrain => {
                 // canonicalize the file
@@ -374,6 +404,33 @@ fn start_phase<'a>(
                     }
                 }
 
+                // Every module gets a key, so that editing e.g. a hosted module changes the keys
+                // of the modules that import it. Only interface modules are cached though; the
+                // types of apps, platforms and hosted modules depend on each other through
+                // `requires` and `provides`.
+                if let Some(type_cache) = &state.type_cache {
+                    let imports = parsed
+                        .imported_modules
+                        .keys()
+                        .map(|imported| (*imported, state.type_cache_keys.get(imported)));
+                    let opt_key =
+                        type_cache.key(module_id, parsed.src, state.exec_mode.doc_tests(), imports);
+
+                    if let Some(key) = opt_key {
+                        state.type_cache_keys.insert(module_id, key);
+
+                        if let HeaderType::Interface { .. } = parsed.header_type {
+                            let entry = type_cache.entry(module_id, key);
+
+                            if let Some(type_state) = entry.read() {
+                                state.cached_types.lock().insert(module_id, type_state);
+                            }
+
+                            state.type_cache_entries.insert(module_id, entry);
+                        }
+                    }
+                }
+
                 let skip_constraint_gen = {
                     // Give this its own scope to make sure that the Guard from the lock() is dropped
                     // immediately after contains_key returns
@@ -412,6 +469,8 @@ fn start_phase<'a>(
 
                 let derived_module = SharedDerivedModule::clone(&state.derived_module);
 
+                let type_cache_entry = state.type_cache_entries.remove(&module_id);
+
                 BuildTask::solve_module(
                     module,
                     ident_ids,
@@ -426,6 +485,7 @@ fn start_phase<'a>(
                     dep_idents,
                     declarations,
                     state.cached_types.clone(),
+                    type_cache_entry,
                     derived_module,
                 )
             }
@@ -457,8 +517,7 @@ fn start_phase<'a>(
 
                 let derived_module = SharedDerivedModule::clone(&state.derived_module);
 
-                let build_expects =
-                    matches!(state.exec_mode, ExecutionMode::Test) && expectations.is_some();
+                let build_expects = state.exec_mode.is_test() && expectations.is_some();
 
                 BuildTask::BuildPendingSpecializations {
                     layout_cache,
@@ -473,6 +532,7 @@ fn start_phase<'a>(
                     // TODO: awful, how can we get rid of the clone?
                     exposed_by_module: state.exposed_types.clone(),
                     derived_module,
+                    coverage: state.coverage.clone(),
                     expectations,
                     build_expects,
                 }
@@ -609,6 +669,7 @@ fn start_phase<'a>(
                     // TODO: awful, how can we get rid of the clone?
                     exposed_by_module: state.exposed_types.clone(),
                     derived_module,
+                    coverage: state.coverage.clone(),
                     expectations,
                 }
             }
@@ -746,6 +807,8 @@ struct LateSpecializationsModule<'a> {
 pub struct ToplevelExpects {
     pub pure: VecMap<Symbol, Region>,
     pub fx: VecMap<Symbol, Region>,
+    /// The pure `expect`s that are properties, with the type of each generated input
+    pub properties: VecMap<Symbol, Vec<Variable>>,
 }
 
 #[derive(Debug)]
@@ -765,6 +828,8 @@ pub struct MonomorphizedModule<'a> {
     pub timings: MutMap<ModuleId, ModuleTiming>,
     pub expectations: VecMap<ModuleId, Expectations>,
     pub uses_prebuilt_platform: bool,
+    /// The coverage counters, when loaded with [`TestOptions::coverage`]
+    pub coverage: Option<CoverageMap>,
 }
 
 /// Values used to render expect output
@@ -989,6 +1054,7 @@ struct State<'a> {
     pub arc_shorthands: Arc<Mutex<MutMap<&'a str, ShorthandPath>>>,
     #[allow(unused)]
     pub derived_module: SharedDerivedModule,
+    pub coverage: Option<SharedCoverageMap>,
 
     pub ident_ids_by_module: SharedIdentIdsByModule,
 
@@ -1014,9 +1080,16 @@ struct State<'a> {
 
     make_specializations_pass: MakeSpecializationsPass,
 
-    // cached types (used for builtin modules, could include packages in the future too)
+    // cached types (used for builtin modules, and user modules found in the type cache)
     cached_types: CachedTypeState,
 
+    /// On-disk cache of the solved types of user modules, if we're allowed to use one
+    type_cache: Option<TypeCache>,
+    /// The keys of all user modules whose imports all have keys, cached or not
+    type_cache_keys: MutMap<ModuleId, u64>,
+    /// The entries that the modules being solved are written to
+    type_cache_entries: MutMap<ModuleId, TypeCacheEntry>,
+
     layout_interner: GlobalLayoutInterner<'a>,
 }
 
@@ -1035,6 +1108,7 @@ impl<'a> State<'a> {
         arc_modules: Arc<Mutex<PackageModuleIds<'a>>>,
         ident_ids_by_module: SharedIdentIdsByModule,
         cached_types: MutMap<ModuleId, TypeState>,
+        type_cache: Option<TypeCache>,
         render: RenderTarget,
         palette: Palette,
         number_of_workers: usize,
@@ -1063,6 +1137,11 @@ impl<'a> State<'a> {
             arc_modules,
             arc_shorthands,
             derived_module: Default::default(),
+            coverage: matches!(
+                exec_mode,
+                ExecutionMode::Test(TestOptions { coverage: true, .. })
+            )
+            .then(SharedCoverageMap::default),
             constrained_ident_ids: IdentIds::exposed_builtins(0),
             ident_ids_by_module,
             declarations_by_id: MutMap::default(),
@@ -1070,6 +1149,9 @@ impl<'a> State<'a> {
             timings: MutMap::default(),
             layout_caches: std::vec::Vec::with_capacity(number_of_workers),
             cached_types: Arc::new(Mutex::new(cached_types)),
+            type_cache,
+            type_cache_keys: MutMap::default(),
+            type_cache_entries: MutMap::default(),
             render,
             palette,
             exec_mode,
@@ -1207,6 +1289,8 @@ enum BuildTask<'a> {
     },
     Parse {
         header: ModuleHeader<'a>,
+        /// Add the examples in doc comments as `expect`s
+        doc_tests: bool,
     },
     CanonicalizeAndConstrain {
         parsed: ParsedModule<'a>,
@@ -1231,6 +1315,7 @@ enum BuildTask<'a> {
         declarations: Declarations,
         dep_idents: IdentIdsByModule,
         cached_subs: CachedTypeState,
+        type_cache_entry: Option<TypeCacheEntry>,
         derived_module: SharedDerivedModule,
     },
     BuildPendingSpecializations {
@@ -1245,6 +1330,7 @@ enum BuildTask<'a> {
         exposed_by_module: ExposedByModule,
         abilities_store: AbilitiesStore,
         derived_module: SharedDerivedModule,
+        coverage: Option<SharedCoverageMap>,
         expectations: Option<Expectations>,
         build_expects: bool,
     },
@@ -1259,6 +1345,7 @@ enum BuildTask<'a> {
         exposed_by_module: ExposedByModule,
         world_abilities: WorldAbilities,
         derived_module: SharedDerivedModule,
+        coverage: Option<SharedCoverageMap>,
         expectations: Option<Expectations>,
     },
 }
@@ -1291,6 +1378,8 @@ pub enum LoadingProblem<'a> {
 
     /// a formatted report
     FormattedReport(String),
+    /// a report as a JSON diagnostic, when loading renders to [RenderTarget::Json]
+    JsonDiagnostic(JsonDiagnostic),
 
     ImportCycle(PathBuf, Vec<ModuleId>),
     IncorrectModuleName(FileError<'a, IncorrectModuleName<'a>>),
@@ -1444,11 +1533,9 @@ impl<'a> LoadStart<'a> {
                         .into_inner()
                         .into_module_ids();
 
-                    let report = report_loading_problem(problem, module_ids, render, palette);
-
                     // TODO try to gracefully recover and continue
                     // instead of changing the control flow to exit.
-                    return Err(LoadingProblem::FormattedReport(report));
+                    return Err(report_loading_problem(problem, module_ids, render, palette));
                 }
             }
         };
@@ -1662,6 +1749,7 @@ pub fn load_single_threaded<'a>(
         arc_modules,
         ident_ids_by_module,
         cached_types,
+        TypeCache::new(roc_cache_dir),
         render,
         palette,
         number_of_workers,
@@ -1785,35 +1873,34 @@ fn state_thread_step<'a>(
                     Ok(ControlFlow::Break(LoadResult::Monomorphized(monomorphized)))
                 }
                 Msg::FailedToReadFile { filename, error } => {
-                    let buf = to_file_problem_report(&filename, error);
-                    Err(LoadingProblem::FormattedReport(buf))
+                    Err(to_file_problem_report(&filename, error, state.render))
                 }
 
                 Msg::FailedToParse(problem) => {
                     let module_ids = (*state.arc_modules).lock().clone().into_module_ids();
-                    let buf = to_parse_problem_report(
+
+                    Err(to_parse_problem_report(
                         problem,
                         module_ids,
                         state.constrained_ident_ids,
                         state.render,
                         state.palette,
-                    );
-                    Err(LoadingProblem::FormattedReport(buf))
+                    ))
                 }
                 Msg::IncorrectModuleName(FileError {
                     problem: SourceError { problem, bytes },
                     filename,
                 }) => {
                     let module_ids = (*state.arc_modules).lock().clone().into_module_ids();
-                    let buf = to_incorrect_module_name_report(
+
+                    Err(to_incorrect_module_name_report(
                         module_ids,
                         state.constrained_ident_ids,
                         problem,
                         filename,
                         bytes,
                         state.render,
-                    );
-                    Err(LoadingProblem::FormattedReport(buf))
+                    ))
                 }
                 msg => {
                     // This is where most of the main thread's work gets done.
@@ -1848,27 +1935,27 @@ fn state_thread_step<'a>(
 
                             // if parsing failed, this module did not add anything to IdentIds
                             let root_exposed_ident_ids = IdentIds::exposed_builtins(0);
-                            let buf = to_parse_problem_report(
+
+                            Err(to_parse_problem_report(
                                 problem,
                                 module_ids,
                                 root_exposed_ident_ids,
                                 render,
                                 palette,
-                            );
-                            Err(LoadingProblem::FormattedReport(buf))
+                            ))
                         }
                         Err(LoadingProblem::ImportCycle(filename, cycle)) => {
                             let module_ids = arc_modules.lock().clone().into_module_ids();
 
                             let root_exposed_ident_ids = IdentIds::exposed_builtins(0);
-                            let buf = to_import_cycle_report(
+
+                            return Err(to_import_cycle_report(
                                 module_ids,
                                 root_exposed_ident_ids,
                                 cycle,
                                 filename,
                                 render,
-                            );
-                            return Err(LoadingProblem::FormattedReport(buf));
+                            ));
                         }
                         Err(LoadingProblem::IncorrectModuleName(FileError {
                             problem: SourceError { problem, bytes },
@@ -1877,15 +1964,15 @@ fn state_thread_step<'a>(
                             let module_ids = arc_modules.lock().clone().into_module_ids();
 
                             let root_exposed_ident_ids = IdentIds::exposed_builtins(0);
-                            let buf = to_incorrect_module_name_report(
+
+                            return Err(to_incorrect_module_name_report(
                                 module_ids,
                                 root_exposed_ident_ids,
                                 problem,
                                 filename,
                                 bytes,
                                 render,
-                            );
-                            return Err(LoadingProblem::FormattedReport(buf));
+                            ));
                         }
                         Err(e) => Err(e),
                     }
@@ -1899,12 +1986,12 @@ fn state_thread_step<'a>(
     }
 }
 
-pub fn report_loading_problem(
+pub fn report_loading_problem<'a>(
     problem: LoadingProblem<'_>,
     module_ids: ModuleIds,
     render: RenderTarget,
     palette: Palette,
-) -> String {
+) -> LoadingProblem<'a> {
     match problem {
         LoadingProblem::ParsingFailed(problem) => {
             // if parsing failed, this module did not add anything to IdentIds
@@ -1932,8 +2019,11 @@ pub fn report_loading_problem(
                 render,
             )
         }
-        LoadingProblem::FormattedReport(report) => report,
-        LoadingProblem::FileProblem { filename, error } => to_file_problem_report(&filename, error),
+        LoadingProblem::FormattedReport(report) => LoadingProblem::FormattedReport(report),
+        LoadingProblem::JsonDiagnostic(diagnostic) => LoadingProblem::JsonDiagnostic(diagnostic),
+        LoadingProblem::FileProblem { filename, error } => {
+            to_file_problem_report(&filename, error, render)
+        }
         err => todo!("Loading error: {:?}", err),
     }
 }
@@ -1990,6 +2080,7 @@ fn load_multi_threaded<'a>(
         arc_modules,
         ident_ids_by_module,
         cached_types,
+        TypeCache::new(roc_cache_dir),
         render,
         palette,
         num_workers,
@@ -2227,7 +2318,7 @@ fn worker_task<'a>(
                             BuildTask::LoadModule { module_name, .. } => {
                                 format!("BuildTask::LoadModule({:?})", module_name)
                             }
-                            BuildTask::Parse { header } => {
+                            BuildTask::Parse { header, .. } => {
                                 format!("BuildTask::Parse({})", header.module_path.display())
                             }
                             BuildTask::CanonicalizeAndConstrain { parsed, .. } => format!(
@@ -2772,7 +2863,7 @@ fn update<'a>(
 
             let add_to_host_exposed = is_host_exposed &&
                 // During testing, we don't need to expose anything to the host.
-                !matches!(state.exec_mode, ExecutionMode::Test);
+                !state.exec_mode.is_test();
 
             if add_to_host_exposed {
                 state.exposed_to_host.values.extend(
@@ -2880,6 +2971,9 @@ fn update<'a>(
                         .typechecked
                         .insert(module_id, typechecked);
                 } else {
+                    // Keep the declarations around, so tools like the language server can look
+                    // up definitions in every module of the program, not just the root.
+                    state.declarations_by_id.insert(module_id, decls);
                     state.constrained_ident_ids.insert(module_id, ident_ids);
                     state.timings.insert(module_id, module_timing);
                 }
@@ -2920,6 +3014,10 @@ fn update<'a>(
 
             state.toplevel_expects.pure.extend(toplevel_expects.pure);
             state.toplevel_expects.fx.extend(toplevel_expects.fx);
+            state
+                .toplevel_expects
+                .properties
+                .extend(toplevel_expects.properties);
 
             state
                 .module_cache
@@ -3298,6 +3396,13 @@ fn finish_specialization<'a>(
     ModuleId::DERIVED_SYNTH.register_debug_idents(&derived_synth_ident_ids);
     all_ident_ids.insert(ModuleId::DERIVED_SYNTH, derived_synth_ident_ids);
 
+    let coverage = state.coverage.map(|coverage| {
+        Arc::try_unwrap(coverage)
+            .unwrap_or_else(|_| internal_error!("Outstanding references to the coverage map"))
+            .into_inner()
+            .unwrap()
+    });
+
     let mut interns = Interns {
         module_ids,
         all_ident_ids,
@@ -3328,7 +3433,7 @@ fn finish_specialization<'a>(
 
     let entry_point = {
         match exec_mode {
-            ExecutionMode::Test => EntryPoint::Test,
+            ExecutionMode::Test(_) => EntryPoint::Test,
             ExecutionMode::Executable | ExecutionMode::ExecutableIfCheck => {
                 use PlatformPath::*;
 
@@ -3341,8 +3446,17 @@ fn finish_specialization<'a>(
                     }
                     Valid(To::NewPackage(p_or_p)) => PathBuf::from(p_or_p.as_str()),
                     other => {
-                        let buf = to_missing_platform_report(state.root_id, other);
-                        return Err(LoadingProblem::FormattedReport(buf));
+                        let root_path = sources
+                            .get(&state.root_id)
+                            .map(|(path, _)| path.as_path())
+                            .unwrap_or_else(|| Path::new(""));
+
+                        return Err(to_missing_platform_report(
+                            state.root_id,
+                            other,
+                            root_path,
+                            state.render,
+                        ));
                     }
                 };
 
@@ -3417,6 +3531,7 @@ fn finish_specialization<'a>(
         timings: state.timings,
         toplevel_expects,
         uses_prebuilt_platform,
+        coverage,
     })
 }
 
@@ -4702,6 +4817,7 @@ impl<'a> BuildTask<'a> {
         dep_idents: IdentIdsByModule,
         declarations: Declarations,
         cached_subs: CachedTypeState,
+        type_cache_entry: Option<TypeCacheEntry>,
         derived_module: SharedDerivedModule,
     ) -> Self {
         let exposed_by_module = exposed_types.retain_modules(imported_modules.keys());
@@ -4723,6 +4839,7 @@ impl<'a> BuildTask<'a> {
             dep_idents,
             module_timing,
             cached_subs,
+            type_cache_entry,
             derived_module,
         }
     }
@@ -5099,6 +5216,7 @@ fn run_solve<'a>(
     decls: Declarations,
     dep_idents: IdentIdsByModule,
     cached_types: CachedTypeState,
+    type_cache_entry: Option<TypeCacheEntry>,
     derived_module: SharedDerivedModule,
 ) -> Msg<'a> {
     let solve_start = Instant::now();
@@ -5114,18 +5232,43 @@ fn run_solve<'a>(
     let module = module;
 
     let (solved_subs, solved_implementations, exposed_vars_by_symbol, problems, abilities_store) = {
-        if module_id.is_builtin() {
-            match cached_types.lock().remove(&module_id) {
-                None => run_solve_solve(
-                    exposed_for_module,
-                    types,
-                    constraints,
-                    constraint,
-                    pending_derives,
-                    var_store,
-                    module,
-                    derived_module,
-                ),
+        if module_id.is_builtin() || type_cache_entry.is_some() {
+            // Give this its own binding so the Guard from the lock() is dropped before solving
+            let opt_cached = cached_types.lock().remove(&module_id);
+
+            match opt_cached {
+                None => {
+                    let solved = run_solve_solve(
+                        exposed_for_module,
+                        types,
+                        constraints,
+                        constraint,
+                        pending_derives,
+                        var_store,
+                        module,
+                        derived_module,
+                    );
+
+                    let (
+                        solved_subs,
+                        solved_implementations,
+                        exposed_vars_by_symbol,
+                        problems,
+                        abilities_store,
+                    ) = &solved;
+
+                    // Never cache a module with type errors; we'd lose its reports next time.
+                    if let (Some(entry), true) = (&type_cache_entry, problems.is_empty()) {
+                        entry.write(
+                            solved_subs.inner(),
+                            exposed_vars_by_symbol,
+                            abilities_store,
+                            solved_implementations,
+                        );
+                    }
+
+                    solved
+                }
                 Some(TypeState {
                     subs,
                     exposed_vars_by_symbol,
@@ -5505,12 +5648,16 @@ fn canonicalize_and_constrain<'a>(
     }
 }
 
-fn parse<'a>(arena: &'a Bump, header: ModuleHeader<'a>) -> Result<Msg<'a>, LoadingProblem<'a>> {
+fn parse<'a>(
+    arena: &'a Bump,
+    header: ModuleHeader<'a>,
+    doc_tests: bool,
+) -> Result<Msg<'a>, LoadingProblem<'a>> {
     let mut module_timing = header.module_timing;
     let parse_start = Instant::now();
     let source = header.parse_state.original_bytes();
     let parse_state = header.parse_state;
-    let parsed_defs = match module_defs().parse(arena, parse_state.clone(), 0) {
+    let mut parsed_defs = match module_defs().parse(arena, parse_state.clone(), 0) {
         Ok((_, success, _state)) => success,
         Err((_, fail)) => {
             return Err(LoadingProblem::ParsingFailed(
@@ -5519,6 +5666,18 @@ fn parse<'a>(arena: &'a Bump, header: ModuleHeader<'a>) -> Result<Msg<'a>, Loadi
         }
     };
 
+    // Only the doc comments of the modules being tested are tested, not those of the builtins
+    if doc_tests && !matches!(header.header_type, HeaderType::Builtin { .. }) {
+        // SAFETY: the header parser has already verified that the source is valid UTF-8
+        let src = unsafe { from_utf8_unchecked(source) };
+
+        if let Err(fail) = crate::doc_tests::add_doc_tests(arena, src, &mut parsed_defs) {
+            return Err(LoadingProblem::ParsingFailed(
+                fail.into_file_error(header.module_path, &parse_state),
+            ));
+        }
+    }
+
     // Record the parse end time once, to avoid checking the time a second time
     // immediately afterward (for the beginning of canonicalization).
     let parse_end = Instant::now();
@@ -5615,6 +5774,7 @@ fn make_specializations<'a>(
     world_abilities: WorldAbilities,
     exposed_by_module: &ExposedByModule,
     derived_module: SharedDerivedModule,
+    coverage: Option<SharedCoverageMap>,
     mut expectations: Option<Expectations>,
 ) -> Msg<'a> {
     let make_specializations_start = Instant::now();
@@ -5633,6 +5793,7 @@ fn make_specializations<'a>(
         abilities: AbilitiesView::World(&world_abilities),
         exposed_by_module,
         derived_module: &derived_module,
+        coverage: coverage.as_ref(),
     };
 
     let mut procs = Procs::new_in(arena);
@@ -5695,11 +5856,19 @@ fn build_pending_specializations<'a>(
     exposed_by_module: &ExposedByModule,
     abilities_store: AbilitiesStore,
     derived_module: SharedDerivedModule,
+    coverage: Option<SharedCoverageMap>,
     mut expectations: Option<Expectations>,
     build_expects: bool,
 ) -> Msg<'a> {
     let find_specializations_start = Instant::now();
 
+    if let Some(coverage) = coverage.as_ref().filter(|_| !home.is_builtin()) {
+        coverage
+            .lock()
+            .unwrap()
+            .add_declarations(home, &declarations);
+    }
+
     let mut module_thunks = bumpalo::collections::Vec::new_in(arena);
     let mut toplevel_expects = ToplevelExpects::default();
 
@@ -5729,6 +5898,7 @@ fn build_pending_specializations<'a>(
         abilities: AbilitiesView::Module(&abilities_store),
         exposed_by_module,
         derived_module: &derived_module,
+        coverage: coverage.as_ref(),
     };
 
     // Add modules' decls to Procs
@@ -6009,6 +6179,11 @@ fn build_pending_specializations<'a>(
                     );
                 }
 
+                let inputs = roc_can::expr::toplevel_expect_property_inputs(&body);
+                if !inputs.is_empty() {
+                    toplevel_expects.properties.insert(symbol, inputs);
+                }
+
                 let body = roc_can::expr::toplevel_expect_to_inline_expect_pure(body);
 
                 let proc = PartialProc {
@@ -6206,6 +6381,8 @@ fn load_derived_partial_procs<'a>(
             abilities: AbilitiesView::World(world_abilities),
             exposed_by_module,
             derived_module,
+            // Derived code isn't written by the user, so it isn't counted.
+            coverage: None,
         };
 
         let partial_proc = match derived_expr {
@@ -6289,7 +6466,7 @@ fn run_task<'a>(
             ident_ids_by_module,
         )
         .map(|HeaderOutput { msg, .. }| msg),
-        Parse { header } => parse(arena, header),
+        Parse { header, doc_tests } => parse(arena, header, doc_tests),
         CanonicalizeAndConstrain {
             parsed,
             module_ids,
@@ -6327,6 +6504,7 @@ fn run_task<'a>(
             declarations,
             dep_idents,
             cached_subs,
+            type_cache_entry,
             derived_module,
         } => Ok(run_solve(
             module,
@@ -6341,6 +6519,7 @@ fn run_task<'a>(
             declarations,
             dep_idents,
             cached_subs,
+            type_cache_entry,
             derived_module,
         )),
         BuildPendingSpecializations {
@@ -6355,6 +6534,7 @@ fn run_task<'a>(
             abilities_store,
             exposed_by_module,
             derived_module,
+            coverage,
             expectations,
             build_expects,
         } => Ok(build_pending_specializations(
@@ -6371,6 +6551,7 @@ fn run_task<'a>(
             &exposed_by_module,
             abilities_store,
             derived_module,
+            coverage,
             expectations,
             build_expects,
         )),
@@ -6385,6 +6566,7 @@ fn run_task<'a>(
             world_abilities,
             exposed_by_module,
             derived_module,
+            coverage,
             expectations,
         } => Ok(make_specializations(
             arena,
@@ -6399,6 +6581,7 @@ fn run_task<'a>(
             world_abilities,
             &exposed_by_module,
             derived_module,
+            coverage,
             expectations,
         )),
     }?;
@@ -6410,8 +6593,12 @@ fn run_task<'a>(
     Ok(())
 }
 
-fn to_file_problem_report(filename: &Path, error: io::ErrorKind) -> String {
-    use roc_reporting::report::{Report, RocDocAllocator, DEFAULT_PALETTE};
+fn to_file_problem_report<'a>(
+    filename: &Path,
+    error: io::ErrorKind,
+    render: RenderTarget,
+) -> LoadingProblem<'a> {
+    use roc_reporting::report::DEFAULT_PALETTE;
     use ven_pretty::DocAllocator;
 
     let src_lines: Vec<&str> = Vec::new();
@@ -6484,21 +6671,28 @@ fn to_file_problem_report(filename: &Path, error: io::ErrorKind) -> String {
         }
     };
 
-    let mut buf = String::new();
     let palette = DEFAULT_PALETTE;
-    report.render_color_terminal(&mut buf, &alloc, &palette);
 
-    buf
+    // The file itself is what the problem is about, so there's no region
+    report_to_problem(
+        report,
+        &alloc,
+        &LineInfo::new(""),
+        filename,
+        None,
+        render,
+        &palette,
+    )
 }
 
-fn to_import_cycle_report(
+fn to_import_cycle_report<'a>(
     module_ids: ModuleIds,
     all_ident_ids: IdentIdsByModule,
     import_cycle: Vec<ModuleId>,
     filename: PathBuf,
     render: RenderTarget,
-) -> String {
-    use roc_reporting::report::{Report, RocDocAllocator, DEFAULT_PALETTE};
+) -> LoadingProblem<'a> {
+    use roc_reporting::report::DEFAULT_PALETTE;
     use ven_pretty::DocAllocator;
 
     // import_cycle looks like CycleModule, Import1, ..., ImportN, CycleModule
@@ -6538,16 +6732,23 @@ fn to_import_cycle_report(
     ]);
 
     let report = Report {
-        filename,
+        filename: filename.clone(),
         doc,
         title: "IMPORT CYCLE".to_string(),
         severity: Severity::RuntimeError,
     };
 
-    let mut buf = String::new();
     let palette = DEFAULT_PALETTE;
-    report.render(render, &mut buf, &alloc, &palette);
-    buf
+
+    report_to_problem(
+        report,
+        &alloc,
+        &LineInfo::new(""),
+        &filename,
+        None,
+        render,
+        &palette,
+    )
 }
 
 fn to_incorrect_module_name_report<'a>(
@@ -6557,8 +6758,8 @@ fn to_incorrect_module_name_report<'a>(
     filename: PathBuf,
     src: &'a [u8],
     render: RenderTarget,
-) -> String {
-    use roc_reporting::report::{Report, RocDocAllocator, DEFAULT_PALETTE};
+) -> LoadingProblem<'a> {
+    use roc_reporting::report::DEFAULT_PALETTE;
     use ven_pretty::DocAllocator;
 
     let IncorrectModuleName {
@@ -6587,16 +6788,23 @@ fn to_incorrect_module_name_report<'a>(
     ]);
 
     let report = Report {
-        filename,
+        filename: filename.clone(),
         doc,
         title: "INCORRECT MODULE NAME".to_string(),
         severity: Severity::RuntimeError,
     };
 
-    let mut buf = String::new();
     let palette = DEFAULT_PALETTE;
-    report.render(render, &mut buf, &alloc, &palette);
-    buf
+
+    report_to_problem(
+        report,
+        &alloc,
+        &lines,
+        &filename,
+        Some(found.region),
+        render,
+        &palette,
+    )
 }
 
 fn to_parse_problem_report<'a>(
@@ -6605,8 +6813,8 @@ fn to_parse_problem_report<'a>(
     all_ident_ids: IdentIdsByModule,
     render: RenderTarget,
     palette: Palette,
-) -> String {
-    use roc_reporting::report::{parse_problem, RocDocAllocator};
+) -> LoadingProblem<'a> {
+    use roc_reporting::report::parse_problem;
 
     // TODO this is not in fact safe
     let src = unsafe { from_utf8_unchecked(problem.problem.bytes) };
@@ -6627,24 +6835,46 @@ fn to_parse_problem_report<'a>(
     let starting_line = 0;
 
     let lines = LineInfo::new(src);
+    let filename = problem.filename.clone();
 
-    let report = parse_problem(
-        &alloc,
-        &lines,
-        problem.filename.clone(),
-        starting_line,
-        problem,
-    );
+    let report = parse_problem(&alloc, &lines, filename.clone(), starting_line, problem);
 
-    let mut buf = String::new();
+    // The report highlights where the syntax error is, which becomes the diagnostic's region
+    report_to_problem(report, &alloc, &lines, &filename, None, render, &palette)
+}
 
-    report.render(render, &mut buf, &alloc, &palette);
+/// A problem that stops loading, from its report. With [RenderTarget::Json] it's a diagnostic
+/// that keeps the report's file and region, and otherwise it's the rendered report.
+fn report_to_problem<'a, 'b>(
+    report: Report<'b>,
+    alloc: &'b RocDocAllocator<'b>,
+    lines: &LineInfo,
+    path: &Path,
+    region: Option<Region>,
+    render: RenderTarget,
+    palette: &'b Palette,
+) -> LoadingProblem<'a> {
+    match render {
+        RenderTarget::Json => {
+            LoadingProblem::JsonDiagnostic(JsonDiagnostic::new(report, alloc, lines, path, region))
+        }
+        RenderTarget::ColorTerminal | RenderTarget::Generic => {
+            let mut buf = String::new();
+
+            report.render(render, &mut buf, alloc, palette);
 
-    buf
+            LoadingProblem::FormattedReport(buf)
+        }
+    }
 }
 
-fn to_missing_platform_report(module_id: ModuleId, other: PlatformPath) -> String {
-    use roc_reporting::report::{Report, RocDocAllocator, DEFAULT_PALETTE};
+fn to_missing_platform_report<'a>(
+    module_id: ModuleId,
+    other: PlatformPath,
+    path: &Path,
+    render: RenderTarget,
+) -> LoadingProblem<'a> {
+    use roc_reporting::report::DEFAULT_PALETTE;
     use ven_pretty::DocAllocator;
     use PlatformPath::*;
 
@@ -6721,8 +6951,14 @@ fn to_missing_platform_report(module_id: ModuleId, other: PlatformPath) -> Strin
     };
 
     let palette = DEFAULT_PALETTE;
-    let mut buf = String::new();
-    report.render_color_terminal(&mut buf, &alloc, &palette);
 
-    buf
+    report_to_problem(
+        report,
+        &alloc,
+        &LineInfo::new(""),
+        path,
+        None,
+        render,
+        &palette,
+    )
 }
@@ -4,8 +4,10 @@
 #![allow(clippy::large_enum_variant)]
 
 use roc_module::symbol::ModuleId;
+mod doc_tests;
 pub mod docs;
 pub mod file;
+mod type_cache;
 mod work;
 
 #[cfg(target_family = "wasm")]
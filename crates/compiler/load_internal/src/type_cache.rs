@@ -0,0 +1,295 @@
+//! An on-disk cache of the solved types of user modules.
+//!
+//! The builtins get their `Subs` serialized at build time (see `crates/compiler/load/build.rs`).
+//! User modules can't be handled that way, so instead we write each interface module's solved
+//! [TypeState] to `<roc_cache_dir>/types` once it type-checks without problems, and read it back
+//! on later runs when neither its source nor the source of anything it (transitively) imports
+//! has changed.
+//!
+//! A cache entry is keyed by a hash of:
+//!
+//! - the compiler binary that wrote it (the serialization format is not stable across versions)
+//! - the module's `ModuleId` and source
+//! - whether its doc tests were added to it, since they are defs of the module too
+//! - the keys of all the modules it imports, which are computed like this for every user module
+//!   (hosted modules too), so a key changes when any module it transitively imports is edited
+//!
+//! `ModuleId`s and `IdentId`s are handed out as modules are discovered, and the serialized `Subs`
+//! refer to them directly. Including the `ModuleId`s of a module and all of its dependencies in
+//! the key means that if they get numbered differently on a later run, we simply miss the cache.
+//!
+//! A process that loads the same modules over and over, like `roc check --watch`, can call
+//! [keep_in_memory] so that the entries it reads and writes stay in memory between loads.
+use roc_can::abilities::{serialize_solved_implementations, AbilitiesStore};
+use roc_can::module::{ResolvedImplementations, TypeState};
+use roc_collections::MutMap;
+use roc_module::symbol::{ModuleId, Symbol};
+use roc_packaging::cache::RocCacheDir;
+use roc_types::subs::{Subs, Variable};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Name of the directory (inside the roc cache dir) where cached types are written
+const TYPE_CACHE_DIR_NAME: &str = "types";
+
+/// Every cache file starts with these bytes, so we never try to deserialize something else
+const MAGIC: &[u8; 8] = b"roctypes";
+
+/// magic bytes, cache key, checksum of the serialized types
+const HEADER_LEN: usize = MAGIC.len() + 2 * std::mem::size_of::<u64>();
+
+/// The latest entry this process has read or written for each module, with its key, once
+/// [keep_in_memory] was called. Every edit of a module gives it a new key, so only the latest one
+/// is kept, which bounds the memory by the number of modules. The cache dir is part of the key so
+/// that loads with different cache dirs stay apart.
+#[allow(clippy::type_complexity)]
+static MEMORY: Mutex<Option<MutMap<(Arc<Path>, ModuleId), (u64, Arc<[u8]>)>>> = Mutex::new(None);
+
+/// Keep the cache entries that are read or written from now on in memory as well, so that later
+/// loads in this process don't have to read them from disk again.
+pub fn keep_in_memory() {
+    if let Ok(mut memory) = MEMORY.lock() {
+        memory.get_or_insert_with(MutMap::default);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeCache {
+    dir: Arc<Path>,
+    compiler_hash: u64,
+}
+
+impl TypeCache {
+    /// Returns `None` when caching is not possible, e.g. because we are not allowed to touch the
+    /// roc cache dir, or because we can't tell which compiler binary is running.
+    pub fn new(roc_cache_dir: RocCacheDir<'_>) -> Option<Self> {
+        let cache_dir = match roc_cache_dir {
+            RocCacheDir::Persistent(cache_dir) => cache_dir,
+            _ => return None,
+        };
+
+        Some(Self {
+            dir: Arc::from(cache_dir.join(TYPE_CACHE_DIR_NAME)),
+            compiler_hash: compiler_hash()?,
+        })
+    }
+
+    /// The cache key for a module. The keys of imported modules must be computed first; builtins
+    /// have no key, and contribute just their `ModuleId`. Returns `None` if a user module that is
+    /// imported has no key, because then we can't tell whether it changed.
+    pub fn key<'a>(
+        &self,
+        module_id: ModuleId,
+        src: &str,
+        doc_tests: bool,
+        imports: impl Iterator<Item = (ModuleId, Option<&'a u64>)>,
+    ) -> Option<u64> {
+        // The imports come out of a hash map, so sort them to get a deterministic key
+        let mut import_keys = imports
+            .map(|(import_id, opt_key)| match opt_key {
+                Some(key) => Some(*key),
+                None if import_id.is_builtin() => Some(hash_one(import_id)),
+                None => None,
+            })
+            .collect::<Option<Vec<u64>>>()?;
+
+        import_keys.sort_unstable();
+
+        let mut hasher = DefaultHasher::new();
+
+        self.compiler_hash.hash(&mut hasher);
+        module_id.hash(&mut hasher);
+        src.hash(&mut hasher);
+        doc_tests.hash(&mut hasher);
+        import_keys.hash(&mut hasher);
+
+        Some(hasher.finish())
+    }
+
+    pub fn entry(&self, module_id: ModuleId, key: u64) -> TypeCacheEntry {
+        TypeCacheEntry {
+            dir: Arc::clone(&self.dir),
+            module_id,
+            key,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeCacheEntry {
+    dir: Arc<Path>,
+    module_id: ModuleId,
+    key: u64,
+}
+
+impl TypeCacheEntry {
+    fn path(&self) -> PathBuf {
+        self.dir.join(format!("{:016x}.dat", self.key))
+    }
+
+    /// Returns `None` if there is no (valid) entry for this key
+    pub fn read(&self) -> Option<TypeState> {
+        if let Some(bytes) = self.recall() {
+            return self.deserialize(&bytes);
+        }
+
+        let bytes = std::fs::read(self.path()).ok()?;
+        let type_state = self.deserialize(&bytes)?;
+
+        self.remember(bytes);
+
+        Some(type_state)
+    }
+
+    fn recall(&self) -> Option<Arc<[u8]>> {
+        let memory = MEMORY.lock().ok()?;
+
+        match memory
+            .as_ref()?
+            .get(&(Arc::clone(&self.dir), self.module_id))
+        {
+            Some((key, bytes)) if *key == self.key => Some(Arc::clone(bytes)),
+            _ => None,
+        }
+    }
+
+    fn remember(&self, bytes: Vec<u8>) {
+        if let Ok(mut memory) = MEMORY.lock() {
+            if let Some(memory) = memory.as_mut() {
+                // This replaces the entry of an earlier version of the module
+                memory.insert(
+                    (Arc::clone(&self.dir), self.module_id),
+                    (self.key, Arc::from(bytes)),
+                );
+            }
+        }
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> Option<TypeState> {
+        if bytes.len() < HEADER_LEN || &bytes[..MAGIC.len()] != MAGIC {
+            return None;
+        }
+
+        let (key_bytes, rest) = bytes[MAGIC.len()..].split_at(8);
+        let (checksum_bytes, body) = rest.split_at(8);
+
+        // Deserialization trusts its input, so make sure this is exactly what we wrote
+        if u64::from_le_bytes(key_bytes.try_into().ok()?) != self.key
+            || u64::from_le_bytes(checksum_bytes.try_into().ok()?) != hash_one(body)
+        {
+            return None;
+        }
+
+        let (type_state, offset) = TypeState::deserialize(body);
+
+        if offset == body.len() {
+            Some(type_state)
+        } else {
+            None
+        }
+    }
+
+    /// Write the solved types of a module. This is best-effort: if anything goes wrong, the module
+    /// will just be solved again next time.
+    pub fn write(
+        &self,
+        subs: &Subs,
+        exposed_vars_by_symbol: &[(Symbol, Variable)],
+        abilities: &AbilitiesStore,
+        solved_implementations: &ResolvedImplementations,
+    ) {
+        let _ = self.write_help(
+            subs,
+            exposed_vars_by_symbol,
+            abilities,
+            solved_implementations,
+        );
+    }
+
+    fn write_help(
+        &self,
+        subs: &Subs,
+        exposed_vars_by_symbol: &[(Symbol, Variable)],
+        abilities: &AbilitiesStore,
+        solved_implementations: &ResolvedImplementations,
+    ) -> std::io::Result<()> {
+        // Same layout as `TypeState::serialize`
+        let mut body = Vec::new();
+        subs.serialize(exposed_vars_by_symbol, &mut body)?;
+        abilities.serialize(&mut body)?;
+        serialize_solved_implementations(solved_implementations, &mut body)?;
+
+        let mut bytes = Vec::with_capacity(HEADER_LEN + body.len());
+
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.key.to_le_bytes());
+        bytes.extend_from_slice(&hash_one(&body[..]).to_le_bytes());
+        bytes.extend_from_slice(&body);
+
+        // Even if writing to disk fails below, later loads in this process can use the entry
+        self.remember(bytes.clone());
+
+        std::fs::create_dir_all(&self.dir)?;
+
+        // Write to a temporary file first and then move it into place, so that concurrent
+        // compiler runs never observe a partially written entry.
+        let mut file = tempfile::NamedTempFile::new_in(&self.dir)?;
+
+        file.write_all(&bytes)?;
+
+        file.persist(self.path()).map_err(|err| err.error)?;
+
+        Ok(())
+    }
+}
+
+fn hash_one(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+
+    hasher.finish()
+}
+
+/// Identifies the running compiler binary by its path, size and modification time.
+fn compiler_hash() -> Option<u64> {
+    let exe = std::env::current_exe().ok()?;
+    let metadata = std::fs::metadata(&exe).ok()?;
+
+    Some(hash_one((exe, metadata.len(), metadata.modified().ok()?)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_keeps_latest_entry_per_module() {
+        let dir: Arc<Path> = Arc::from(Path::new("/nonexistent/roc_type_cache_test"));
+        let entry = |key| TypeCacheEntry {
+            dir: Arc::clone(&dir),
+            module_id: ModuleId::NUM,
+            key,
+        };
+
+        keep_in_memory();
+
+        entry(1).remember(vec![1]);
+        entry(2).remember(vec![2]);
+
+        let entries = MEMORY
+            .lock()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .keys()
+            .filter(|(entry_dir, _)| *entry_dir == dir)
+            .count();
+
+        assert_eq!(entries, 1);
+        assert_eq!(entry(1).recall(), None);
+        assert_eq!(entry(2).recall().as_deref(), Some(&[2][..]));
+    }
+}
@@ -39,13 +39,29 @@ fn load_and_typecheck(
     exposed_types: ExposedByModule,
     target_info: TargetInfo,
 ) -> Result<LoadedModule, LoadingProblem> {
+    load_and_typecheck_with_cache_dir(
+        arena,
+        filename,
+        exposed_types,
+        target_info,
+        RocCacheDir::Disallowed,
+    )
+}
+
+fn load_and_typecheck_with_cache_dir<'a>(
+    arena: &'a Bump,
+    filename: PathBuf,
+    exposed_types: ExposedByModule,
+    target_info: TargetInfo,
+    roc_cache_dir: RocCacheDir<'_>,
+) -> Result<LoadedModule, LoadingProblem<'a>> {
     use LoadResult::*;
 
     let load_start = LoadStart::from_path(
         arena,
         filename,
         RenderTarget::Generic,
-        RocCacheDir::Disallowed,
+        roc_cache_dir,
         DEFAULT_PALETTE,
     )?;
     let load_config = LoadConfig {
@@ -61,7 +77,7 @@ fn load_and_typecheck(
         load_start,
         exposed_types,
         Default::default(), // these tests will re-compile the builtins
-        RocCacheDir::Disallowed,
+        roc_cache_dir,
         load_config,
     )? {
         Monomorphized(_) => unreachable!(""),
@@ -537,6 +553,150 @@ fn iface_dep_types() {
     );
 }
 
+#[test]
+fn iface_dep_types_from_type_cache() {
+    let cache_dir = tempfile::tempdir().unwrap();
+    let filename = fixtures_dir()
+        .join("interface_with_deps")
+        .join("Primary.roc");
+
+    // The first load populates the cache, the second one reads from it
+    for _ in 0..2 {
+        let arena = Bump::new();
+        let loaded_module = load_and_typecheck_with_cache_dir(
+            &arena,
+            filename.clone(),
+            Default::default(),
+            TARGET_INFO,
+            RocCacheDir::Persistent(cache_dir.path()),
+        )
+        .expect("Test module failed to load");
+
+        expect_types(
+            loaded_module,
+            hashmap! {
+                "blah2" => "Float *",
+                "blah3" => "Str",
+                "str" => "Str",
+                "alwaysThree" => "* -> Float *",
+                "identity" => "a -> a",
+                "z" => "Float *",
+                "w" => "Dep1.Identity {}",
+                "succeed" => "a -> Dep1.Identity a",
+                "yay" => "Res.Res {} err",
+                "withDefault" => "Res.Res a err, a -> a",
+            },
+        );
+    }
+
+    let cached_modules = std::fs::read_dir(cache_dir.path().join("types"))
+        .unwrap()
+        .count();
+
+    // Primary, Dep1, Dep2, Dep3.Blah, Res
+    assert_eq!(cached_modules, 5);
+}
+
+#[test]
+fn iface_dep_types_from_type_cache_in_memory() {
+    let cache_dir = tempfile::tempdir().unwrap();
+    let types_dir = cache_dir.path().join("types");
+    let filename = fixtures_dir()
+        .join("interface_with_deps")
+        .join("Primary.roc");
+
+    roc_load_internal::file::keep_types_in_memory();
+
+    for run in 0..2 {
+        let arena = Bump::new();
+        let loaded_module = load_and_typecheck_with_cache_dir(
+            &arena,
+            filename.clone(),
+            Default::default(),
+            TARGET_INFO,
+            RocCacheDir::Persistent(cache_dir.path()),
+        )
+        .expect("Test module failed to load");
+
+        expect_types(
+            loaded_module,
+            hashmap! {
+                "blah2" => "Float *",
+                "blah3" => "Str",
+                "str" => "Str",
+                "alwaysThree" => "* -> Float *",
+                "identity" => "a -> a",
+                "z" => "Float *",
+                "w" => "Dep1.Identity {}",
+                "succeed" => "a -> Dep1.Identity a",
+                "yay" => "Res.Res {} err",
+                "withDefault" => "Res.Res a err, a -> a",
+            },
+        );
+
+        if run == 0 {
+            std::fs::remove_dir_all(&types_dir).unwrap();
+            std::fs::create_dir(&types_dir).unwrap();
+        }
+    }
+
+    // The second load found everything in memory, so it had nothing to solve or write to disk
+    assert_eq!(std::fs::read_dir(&types_dir).unwrap().count(), 0);
+}
+
+#[test]
+fn type_cache_misses_after_hosted_dep_changes() {
+    let cache_dir = tempfile::tempdir().unwrap();
+    let src_dir = tempfile::tempdir().unwrap();
+    let effect = src_dir.path().join("Effect.roc");
+    let stdout = src_dir.path().join("Stdout.roc");
+
+    std::fs::write(
+        &stdout,
+        indoc!(
+            r#"
+            interface Stdout
+                exposes [line]
+                imports [Effect]
+
+            line = \str -> Effect.putLine str
+            "#
+        ),
+    )
+    .unwrap();
+
+    let effect_src = indoc!(
+        r#"
+        hosted Effect
+            exposes [Effect, after, map, always, forever, putLine]
+            imports []
+            generates Effect with [after, map, always, forever]
+
+        putLine : Str -> Effect OUTPUT
+        "#
+    );
+
+    // Stdout is cached by the first load, and must not be read back after Effect changed
+    for (output, line_type) in [
+        ("{}", "Str -> Effect.Effect {}"),
+        ("U8", "Str -> Effect.Effect U8"),
+    ] {
+        std::fs::write(&effect, effect_src.replace("OUTPUT", output)).unwrap();
+
+        let arena = Bump::new();
+        let loaded_module = load_and_typecheck_with_cache_dir(
+            &arena,
+            stdout.clone(),
+            Default::default(),
+            TARGET_INFO,
+            RocCacheDir::Persistent(cache_dir.path()),
+        )
+        .expect("Test module failed to load");
+
+        expect_types(loaded_module, hashmap! { "line" => line_type });
+    }
+}
+
 #[test]
 fn app_dep_types() {
     let subs_by_module = Default::default();
@@ -113,6 +113,10 @@ pub enum LowLevel {
     BoxExpr,
     UnboxExpr,
     Unreachable,
+    /// The generated input at the given index, for a property `expect`
+    ExpectInput,
+    /// Increment the coverage counter at the given index, for `roc test --coverage`
+    CoverageHit,
 }
 
 macro_rules! higher_order {
@@ -214,6 +218,8 @@ macro_rules! map_symbol_to_lowlevel {
                 LowLevel::PtrCast => unimplemented!(),
                 LowLevel::RefCountInc => unimplemented!(),
                 LowLevel::RefCountDec => unimplemented!(),
+                LowLevel::ExpectInput => unimplemented!(),
+                LowLevel::CoverageHit => unimplemented!(),
 
                 // these are not implemented, not sure why
                 LowLevel::StrFromInt => unimplemented!(),
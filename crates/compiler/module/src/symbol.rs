@@ -148,6 +148,16 @@ impl Symbol {
         u64::from_ne_bytes(self.to_ne_bytes())
     }
 
+    /// The inverse of [Symbol::as_u64], or `None` if the value is not a valid symbol
+    pub fn from_u64(value: u64) -> Option<Symbol> {
+        let [a, b, c, d, e, f, g, h] = value.to_ne_bytes();
+
+        Some(Self {
+            ident_id: u32::from_ne_bytes([a, b, c, d]),
+            module_id: NonZeroU32::new(u32::from_ne_bytes([e, f, g, h]))?,
+        })
+    }
+
     pub fn fully_qualified(self, interns: &Interns, home: ModuleId) -> ModuleName {
         let module_id = self.module_id();
 
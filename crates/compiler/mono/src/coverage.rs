@@ -0,0 +1,141 @@
+//! Instrumentation for `roc test --coverage`.
+//!
+//! Before specialization, every top-level def, `when` branch and `if` arm of a module gets a
+//! counter. Specializing its code then adds a [`LowLevel::CoverageHit`] that increments the
+//! counter, so code that was never specialized still shows up in the report, with a count of 0.
+//!
+//! [`LowLevel::CoverageHit`]: roc_module::low_level::LowLevel::CoverageHit
+
+use std::sync::{Arc, Mutex};
+
+use roc_can::expr::{DeclarationTag, Declarations, Expr};
+use roc_can::traverse::{walk_expr, Visitor};
+use roc_collections::MutMap;
+use roc_module::symbol::{ModuleId, Symbol};
+use roc_region::all::Region;
+use roc_types::subs::Variable;
+
+/// What a coverage counter counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CoverageKind {
+    /// Calls of a top-level function, or evaluations of a top-level value
+    Def(Symbol),
+    /// A branch of the `when` with this region
+    WhenBranch { when_region: Region },
+    /// An arm of the `if` with this region, including the final `else`
+    IfArm { if_region: Region },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CoverageCounter {
+    pub module_id: ModuleId,
+    /// The def, or the body of the branch
+    pub region: Region,
+    pub kind: CoverageKind,
+}
+
+/// Every coverage counter of the modules being built, across all modules
+#[derive(Debug, Default)]
+pub struct CoverageMap {
+    /// The counters, in the order of their indices
+    pub counters: Vec<CoverageCounter>,
+    defs: MutMap<Symbol, u32>,
+    branches: MutMap<(ModuleId, Region), u32>,
+}
+
+pub type SharedCoverageMap = Arc<Mutex<CoverageMap>>;
+
+impl CoverageMap {
+    fn add(&mut self, counter: CoverageCounter) -> u32 {
+        let index = self.counters.len() as u32;
+        self.counters.push(counter);
+
+        index
+    }
+
+    /// Add counters for the top-level defs of a module, and the branches inside them.
+    /// Top-level `expect`s are tests, not code under test, so they aren't counted.
+    pub fn add_declarations(&mut self, home: ModuleId, decls: &Declarations) {
+        use DeclarationTag::*;
+
+        for (index, tag) in decls.declarations.iter().enumerate() {
+            let loc_symbol = decls.symbols[index];
+            let loc_expr = &decls.expressions[index];
+            let def_region = Region::span_across(&loc_symbol.region, &loc_expr.region);
+
+            match tag {
+                Value | Function(_) | Recursive(_) | TailRecursive(_) => {
+                    let counter = self.add(CoverageCounter {
+                        module_id: home,
+                        region: def_region,
+                        kind: CoverageKind::Def(loc_symbol.value),
+                    });
+                    self.defs.insert(loc_symbol.value, counter);
+                }
+                Destructure(_) => {}
+                Expectation | ExpectationFx | MutualRecursion { .. } => continue,
+            }
+
+            let mut visitor = BranchVisitor { home, map: self };
+            visitor.visit_expr(&loc_expr.value, loc_expr.region, decls.variables[index]);
+        }
+    }
+
+    /// The counter of the body of this top-level def, if it has one
+    pub fn def_counter(&self, symbol: Symbol) -> Option<u32> {
+        self.defs.get(&symbol).copied()
+    }
+
+    /// The counter of the `when` branch or `if` arm with this body, if it has one
+    pub fn branch_counter(&self, home: ModuleId, body_region: Region) -> Option<u32> {
+        self.branches.get(&(home, body_region)).copied()
+    }
+}
+
+struct BranchVisitor<'m> {
+    home: ModuleId,
+    map: &'m mut CoverageMap,
+}
+
+impl BranchVisitor<'_> {
+    fn add_branch(&mut self, body_region: Region, kind: CoverageKind) {
+        let counter = self.map.add(CoverageCounter {
+            module_id: self.home,
+            region: body_region,
+            kind,
+        });
+
+        self.map.branches.insert((self.home, body_region), counter);
+    }
+}
+
+impl Visitor for BranchVisitor<'_> {
+    fn visit_expr(&mut self, expr: &Expr, region: Region, var: Variable) {
+        match expr {
+            Expr::When { branches, .. } => {
+                for branch in branches {
+                    self.add_branch(
+                        branch.value.region,
+                        CoverageKind::WhenBranch {
+                            when_region: region,
+                        },
+                    );
+                }
+            }
+            Expr::If {
+                branches,
+                final_else,
+                ..
+            } => {
+                for (_, loc_then) in branches {
+                    self.add_branch(loc_then.region, CoverageKind::IfArm { if_region: region });
+                }
+
+                self.add_branch(final_else.region, CoverageKind::IfArm { if_region: region });
+            }
+            _ => {}
+        }
+
+        walk_expr(self, expr, var);
+    }
+}
@@ -1,6 +1,7 @@
 #![allow(clippy::manual_map)]
 
 use crate::borrow::Ownership;
+use crate::coverage::SharedCoverageMap;
 use crate::layout::{
     self, Builtin, ClosureCallOptions, ClosureRepresentation, EnumDispatch, InLayout, LambdaName,
     LambdaSet, Layout, LayoutCache, LayoutInterner, LayoutProblem, Niche, RawFunctionLayout,
@@ -1401,6 +1402,8 @@ pub struct Env<'a, 'i> {
     pub abilities: AbilitiesView<'i>,
     pub exposed_by_module: &'i ExposedByModule,
     pub derived_module: &'i SharedDerivedModule,
+    /// [Some] when building for `roc test --coverage`.
+    pub coverage: Option<&'i SharedCoverageMap>,
 }
 
 impl<'a, 'i> Env<'a, 'i> {
@@ -1414,6 +1417,55 @@ impl<'a, 'i> Env<'a, 'i> {
         self.update_mode_ids.next_id()
     }
 
+    /// Count the evaluations of this statement in the given coverage counter, if any.
+    fn count_coverage(&mut self, counter: Option<u32>, stmt: Stmt<'a>) -> Stmt<'a> {
+        let counter = match counter {
+            Some(counter) => counter,
+            None => return stmt,
+        };
+
+        let index_sym = self.unique_symbol();
+        let hit_sym = self.unique_symbol();
+
+        let hit = Expr::Call(Call {
+            call_type: CallType::LowLevel {
+                op: LowLevel::CoverageHit,
+                update_mode: self.next_update_mode_id(),
+            },
+            arguments: self.arena.alloc([index_sym]),
+        });
+
+        let stmt = Stmt::Let(hit_sym, hit, Layout::UNIT, self.arena.alloc(stmt));
+
+        Stmt::Let(
+            index_sym,
+            Expr::Literal(Literal::Int((counter as i128).to_ne_bytes())),
+            Layout::U32,
+            self.arena.alloc(stmt),
+        )
+    }
+
+    /// Count the evaluations of a `when` branch or `if` arm with this body.
+    fn count_branch(&mut self, body_region: Region, stmt: Stmt<'a>) -> Stmt<'a> {
+        let counter = self.coverage.and_then(|coverage| {
+            coverage
+                .lock()
+                .unwrap()
+                .branch_counter(self.home, body_region)
+        });
+
+        self.count_coverage(counter, stmt)
+    }
+
+    /// Count the evaluations of the body of this top-level def.
+    fn count_def(&mut self, symbol: Symbol, stmt: Stmt<'a>) -> Stmt<'a> {
+        let counter = self
+            .coverage
+            .and_then(|coverage| coverage.lock().unwrap().def_counter(symbol));
+
+        self.count_coverage(counter, stmt)
+    }
+
     pub fn next_call_specialization_id(&mut self) -> CallSpecId {
         let id = CallSpecId {
             id: self.call_specialization_counter,
@@ -3362,7 +3414,8 @@ fn specialize_proc_help<'a>(
         }
     };
 
-    let mut specialized_body = from_can(env, body_var, body, procs, layout_cache);
+    let specialized_body = from_can(env, body_var, body, procs, layout_cache);
+    let mut specialized_body = env.count_def(lambda_name.name(), specialized_body);
 
     let specialized_proc = match specialized {
         SpecializedLayout::FunctionPointerBody {
@@ -4386,6 +4439,7 @@ pub fn with_hole<'a>(
                     if is_terminated {
                         let terminator = hole;
 
+                        let final_else_region = final_else.region;
                         let mut stmt = with_hole(
                             env,
                             final_else.value,
@@ -4395,10 +4449,12 @@ pub fn with_hole<'a>(
                             assigned,
                             terminator,
                         );
+                        stmt = env.count_branch(final_else_region, stmt);
 
                         for (loc_cond, loc_then) in branches.into_iter().rev() {
                             let branching_symbol = env.unique_symbol();
 
+                            let then_region = loc_then.region;
                             let then = with_hole(
                                 env,
                                 loc_then.value,
@@ -4408,6 +4464,7 @@ pub fn with_hole<'a>(
                                 assigned,
                                 terminator,
                             );
+                            let then = env.count_branch(then_region, then);
 
                             stmt = cond(env, branching_symbol, cond_layout, then, stmt, ret_layout);
 
@@ -4431,6 +4488,7 @@ pub fn with_hole<'a>(
                             .arena
                             .alloc(Stmt::Jump(id, env.arena.alloc([assigned_in_jump])));
 
+                        let final_else_region = final_else.region;
                         let mut stmt = with_hole(
                             env,
                             final_else.value,
@@ -4440,6 +4498,7 @@ pub fn with_hole<'a>(
                             assigned_in_jump,
                             terminator,
                         );
+                        stmt = env.count_branch(final_else_region, stmt);
 
                         for (loc_cond, loc_then) in branches.into_iter().rev() {
                             let branching_symbol = possible_reuse_symbol_or_specialize(
@@ -4450,6 +4509,7 @@ pub fn with_hole<'a>(
                                 cond_var,
                             );
 
+                            let then_region = loc_then.region;
                             let then = with_hole(
                                 env,
                                 loc_then.value,
@@ -4459,6 +4519,7 @@ pub fn with_hole<'a>(
                                 assigned_in_jump,
                                 terminator,
                             );
+                            let then = env.count_branch(then_region, then);
 
                             stmt = cond(env, branching_symbol, cond_layout, then, stmt, ret_layout);
 
@@ -6403,7 +6464,9 @@ pub fn from_can<'a>(
                 .from_var(env.arena, cond_var, env.subs)
                 .expect("invalid cond_layout");
 
-            let mut stmt = from_can(env, branch_var, final_else.value, procs, layout_cache);
+            let final_else_region = final_else.region;
+            let stmt = from_can(env, branch_var, final_else.value, procs, layout_cache);
+            let mut stmt = env.count_branch(final_else_region, stmt);
 
             for (loc_cond, loc_then) in branches.into_iter().rev() {
                 let branching_symbol = possible_reuse_symbol_or_specialize(
@@ -6414,6 +6477,7 @@ pub fn from_can<'a>(
                     cond_var,
                 );
                 let then = from_can(env, branch_var, loc_then.value, procs, layout_cache);
+                let then = env.count_branch(loc_then.region, then);
 
                 stmt = cond(env, branching_symbol, cond_layout, then, stmt, ret_layout);
 
@@ -6671,7 +6735,7 @@ fn to_opt_branches<'a>(
 ) -> std::vec::Vec<(
     Pattern<'a>,
     Option<Loc<roc_can::expr::Expr>>,
-    roc_can::expr::Expr,
+    Loc<roc_can::expr::Expr>,
 )> {
     debug_assert!(!branches.is_empty());
 
@@ -6706,7 +6770,8 @@ fn to_opt_branches<'a>(
                             loc_expr = Loc::at(region, new_expr);
                         }
 
-                        loc_expr
+                        // Keep the region of the branch body, so coverage can find the branch
+                        Loc::at(when_branch.value.region, loc_expr.value)
                     } else {
                         // This pattern is degenerate; when it's reached we must emit a runtime
                         // error.
@@ -6716,14 +6781,14 @@ fn to_opt_branches<'a>(
                     };
 
                     // TODO remove clone?
-                    opt_branches.push((mono_pattern, when_branch.guard.clone(), loc_expr.value));
+                    opt_branches.push((mono_pattern, when_branch.guard.clone(), loc_expr));
                 }
                 Err(runtime_error) => {
                     // TODO remove clone?
                     opt_branches.push((
                         Pattern::Underscore,
                         when_branch.guard.clone(),
-                        roc_can::expr::Expr::RuntimeError(runtime_error),
+                        Loc::at_zero(roc_can::expr::Expr::RuntimeError(runtime_error)),
                     ));
                 }
             }
@@ -6736,7 +6801,9 @@ fn to_opt_branches<'a>(
         opt_branches.push((
             Pattern::Underscore,
             None,
-            roc_can::expr::Expr::RuntimeError(roc_problem::can::RuntimeError::NonExhaustivePattern),
+            Loc::at_zero(roc_can::expr::Expr::RuntimeError(
+                roc_problem::can::RuntimeError::NonExhaustivePattern,
+            )),
         ));
     }
 
@@ -6777,7 +6844,7 @@ fn from_can_when<'a>(
     let arena = env.arena;
     let it = opt_branches
         .into_iter()
-        .filter_map(|(pattern, opt_guard, can_expr)| {
+        .filter_map(|(pattern, opt_guard, loc_can_expr)| {
             // If the pattern has a void layout we can drop it; however, we must still perform the
             // work of building the body, because that may contain specializations we must
             // discover for use elsewhere. See
@@ -6794,6 +6861,7 @@ fn from_can_when<'a>(
                 None
             };
 
+            let can_expr = loc_can_expr.value;
             let branch_stmt = match join_point {
                 None => from_can(env, expr_var, can_expr, procs, layout_cache),
                 Some(id) => {
@@ -6804,6 +6872,7 @@ fn from_can_when<'a>(
                     with_hole(env, can_expr, expr_var, procs, layout_cache, symbol, jump)
                 }
             };
+            let branch_stmt = env.count_branch(loc_can_expr.region, branch_stmt);
 
             use crate::decision_tree::Guard;
             let result = if let Some(loc_expr) = opt_guard {
@@ -9,6 +9,7 @@
 
 pub mod borrow;
 pub mod code_gen_help;
+pub mod coverage;
 pub mod inc_dec;
 pub mod ir;
 pub mod layout;
@@ -14,13 +14,16 @@ mod solve_expr {
     use regex::Regex;
     use roc_can::{
         abilities::ImplKey,
-        traverse::{find_ability_member_and_owning_type_at, find_type_at},
+        traverse::{
+            find_ability_member_and_owning_type_at, find_closest_type_at, find_symbol_at,
+            find_symbol_definition, find_type_at,
+        },
     };
     use roc_load::LoadedModule;
     use roc_module::symbol::{Interns, ModuleId};
     use roc_packaging::cache::RocCacheDir;
     use roc_problem::can::Problem;
-    use roc_region::all::{LineColumn, LineColumnRegion, LineInfo, Region};
+    use roc_region::all::{LineColumn, LineColumnRegion, LineInfo, Position, Region};
     use roc_reporting::report::{can_problem, type_problem, RocDocAllocator};
     use roc_solve_problem::TypeError;
     use roc_types::{
@@ -8764,4 +8767,76 @@ mod solve_expr {
         @"main : List w_a"
         );
     }
+
+    const TRAVERSE_SRC: &str = indoc!(
+        r#"
+        app "test" provides [main] to "./platform"
+
+        main =
+            double = \n -> n * 2
+
+            double 21
+        "#
+    );
+
+    /// The position of the `nth` occurrence of `needle` in the source, plus `offset`
+    fn position_of(src: &str, needle: &str, nth: usize, offset: usize) -> Position {
+        let (index, _) = src.match_indices(needle).nth(nth).unwrap();
+
+        Position::new((index + offset) as u32)
+    }
+
+    fn region_text(src: &str, region: Region) -> &str {
+        &src[region.start().offset as usize..region.end().offset as usize]
+    }
+
+    #[test]
+    fn find_closest_type_at_finds_innermost_expr() {
+        let (mut loaded, src) = run_load_and_infer(TRAVERSE_SRC).unwrap();
+        let home = loaded.module_id;
+        let decls = loaded.declarations_by_id.remove(&home).unwrap();
+
+        // inside the `21`, which is inside the call, which is inside the body of `main`
+        let (region, var) = find_closest_type_at(position_of(&src, "21", 0, 1), &decls).unwrap();
+        assert_eq!(region_text(&src, region), "21");
+
+        let type_str = name_and_print_var(
+            var,
+            loaded.solved.inner_mut(),
+            home,
+            &loaded.interns,
+            DebugPrint::NOTHING,
+        );
+        assert_eq!(type_str, "Num *");
+
+        // in the `n` argument of the closure, which is a pattern
+        let (region, _) = find_closest_type_at(position_of(&src, "\\n", 0, 1), &decls).unwrap();
+        assert_eq!(region_text(&src, region), "n");
+
+        // the module header has no type
+        assert_eq!(find_closest_type_at(Position::new(0), &decls), None);
+    }
+
+    #[test]
+    fn find_symbol_at_lookup_and_definition() {
+        let (mut loaded, src) = run_load_and_infer(TRAVERSE_SRC).unwrap();
+        let home = loaded.module_id;
+        let decls = loaded.declarations_by_id.remove(&home).unwrap();
+
+        // the lookup of `double` in `double 21`
+        let lookup = find_symbol_at(position_of(&src, "double", 1, 2), &decls).unwrap();
+        assert_eq!(region_text(&src, lookup.region), "double");
+        assert_eq!(lookup.value.as_str(&loaded.interns), "double");
+
+        // the pattern that defines it, which is also found as a symbol
+        let definition = find_symbol_definition(lookup.value, &decls).unwrap();
+        assert_eq!(definition.start(), position_of(&src, "double", 0, 0));
+        assert_eq!(region_text(&src, definition), "double");
+
+        let defined = find_symbol_at(definition.start(), &decls).unwrap();
+        assert_eq!(defined.value, lookup.value);
+
+        // a literal is not a symbol
+        assert_eq!(find_symbol_at(position_of(&src, "21", 0, 0), &decls), None);
+    }
 }
@@ -50,6 +50,36 @@ impl TypeError {
             TypeError::WrongSpecialization { .. } => RuntimeError,
         }
     }
+
+    /// Returns the primary Region of the problem, if it has one.
+    pub fn region(&self) -> Option<Region> {
+        use roc_exhaustive::Error as ExhaustiveError;
+
+        match self {
+            TypeError::BadExpr(region, ..)
+            | TypeError::BadPattern(region, ..)
+            | TypeError::CircularType(region, ..)
+            | TypeError::BadExprMissingAbility(region, ..)
+            | TypeError::BadPatternMissingAbility(region, ..)
+            | TypeError::StructuralSpecialization { region, .. }
+            | TypeError::WrongSpecialization { region, .. } => Some(*region),
+            TypeError::CircularDef(entries) => entries.first().map(|entry| entry.symbol_region),
+            TypeError::UnfulfilledAbility(Unfulfilled::OpaqueUnderivable {
+                derive_region, ..
+            }) => Some(*derive_region),
+            TypeError::UnfulfilledAbility(_) => None,
+            TypeError::UnexposedLookup(_) => None,
+            TypeError::Exhaustive(ExhaustiveError::Incomplete(region, ..))
+            | TypeError::Exhaustive(ExhaustiveError::Redundant {
+                branch_region: region,
+                ..
+            })
+            | TypeError::Exhaustive(ExhaustiveError::Unmatchable {
+                branch_region: region,
+                ..
+            }) => Some(*region),
+        }
+    }
 }
 
 #[derive(PartialEq, Eq, Debug, Clone)]
@@ -0,0 +1,96 @@
+#![cfg(test)]
+// Even with #[allow(non_snake_case)] on individual idents, rust-analyzer issues diagnostics.
+// See https://github.com/rust-lang/rust-analyzer/issues/6541.
+// For the `v!` macro we use uppercase variables when constructing tag unions.
+#![allow(non_snake_case)]
+
+use crate::v;
+use roc_module::symbol::Symbol;
+use roc_types::subs::{Subs, Variable};
+
+use roc_derive_key::{
+    generate::{FlatGenerate, FlatGenerateKey},
+    DeriveError,
+};
+
+fn check<S>(synth: S, expected: Result<FlatGenerate, DeriveError>)
+where
+    S: FnOnce(&mut Subs) -> Variable,
+{
+    let mut subs = Subs::new();
+    let var = synth(&mut subs);
+
+    assert_eq!(FlatGenerate::from_var(&subs, var), expected);
+}
+
+fn check_key<S>(synth: S, key: FlatGenerateKey)
+where
+    S: FnOnce(&mut Subs) -> Variable,
+{
+    check(synth, Ok(FlatGenerate::Key(key)))
+}
+
+fn check_immediate<S>(synth: S, immediate: Symbol)
+where
+    S: FnOnce(&mut Subs) -> Variable,
+{
+    check(synth, Ok(FlatGenerate::Immediate(immediate)))
+}
+
+#[test]
+fn immediates() {
+    check_immediate(v!(U8), Symbol::NUM_U8);
+    check_immediate(v!(U128), Symbol::NUM_U128);
+    check_immediate(v!(I64), Symbol::NUM_I64);
+    check_immediate(v!(NAT), Symbol::NUM_NAT);
+    check_immediate(v!(F32), Symbol::NUM_F32);
+    check_immediate(v!(DEC), Symbol::NUM_DEC);
+    check_immediate(v!(STR), Symbol::STR_STR);
+}
+
+#[test]
+fn list() {
+    check_key(v!(Symbol::LIST_LIST v!(U8)), FlatGenerateKey::List());
+    check_key(v!(Symbol::LIST_LIST v!(STR)), FlatGenerateKey::List());
+}
+
+#[test]
+fn record() {
+    check_key(
+        v!({ b: v!(U8), a: v!(STR), }),
+        FlatGenerateKey::Record(vec!["a".into(), "b".into()]),
+    );
+    check_key(v!(EMPTY_RECORD), FlatGenerateKey::Record(vec![]));
+}
+
+#[test]
+fn tag_union() {
+    check_key(
+        v!([ B v!(STR) v!(U8), A ]),
+        FlatGenerateKey::TagUnion(vec![("A".into(), 0), ("B".into(), 2)]),
+    );
+    check_key(
+        v!(BOOL),
+        FlatGenerateKey::TagUnion(vec![("False".into(), 0), ("True".into(), 0)]),
+    );
+}
+
+#[test]
+fn empty_tuple() {
+    check_key(v!(EMPTY_TUPLE), FlatGenerateKey::Tuple(0));
+}
+
+#[test]
+fn underivable() {
+    check(v!({ ?a: v!(U8), }), Err(DeriveError::Underivable));
+    check(
+        v!([ Nil, Cons v!(^lst) ] as lst),
+        Err(DeriveError::Underivable),
+    );
+    check(v!(EMPTY_TAG_UNION), Err(DeriveError::Underivable));
+}
+
+#[test]
+fn unbound() {
+    check(v!(*), Err(DeriveError::UnboundVar));
+}
@@ -3,6 +3,7 @@
 mod decoding;
 mod encoding;
 mod eq;
+mod generate;
 mod hash;
 
 mod util;
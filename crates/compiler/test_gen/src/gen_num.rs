@@ -31,7 +31,7 @@ fn nat_alias() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
 fn i128_signed_int_alias() {
     assert_evals_to!(
         indoc!(
@@ -196,7 +196,7 @@ fn i8_hex_int_alias() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
 fn u128_signed_int_alias() {
     assert_evals_to!(
         indoc!(
@@ -689,7 +689,7 @@ fn gen_float_eq() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
 fn gen_add_dec() {
     assert_evals_to!(
         indoc!(
@@ -798,7 +798,7 @@ fn gen_div_checked_by_zero_f64() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
 fn gen_div_dec() {
     assert_evals_to!(
         indoc!(
@@ -903,7 +903,7 @@ fn gen_int_less_than() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
 fn gen_dec_eq() {
     assert_evals_to!(
         indoc!(
@@ -923,7 +923,7 @@ fn gen_dec_eq() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
 fn gen_dec_neq() {
     assert_evals_to!(
         indoc!(
@@ -1059,7 +1059,7 @@ fn gen_add_i64() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
 fn gen_sub_dec() {
     assert_evals_to!(
         indoc!(
@@ -1082,7 +1082,7 @@ fn gen_sub_dec() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
 fn gen_mul_dec() {
     assert_evals_to!(
         indoc!(
@@ -1786,6 +1786,40 @@ fn float_compare() {
     assert_evals_to!("Num.compare 3.14 0.01", RocOrder::Gt, RocOrder);
 }
 
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+fn i128_compare() {
+    // the halves disagree: the high half decides
+    assert_evals_to!(
+        "Num.compare -18446744073709551616i128 1i128",
+        RocOrder::Lt,
+        RocOrder
+    );
+    assert_evals_to!(
+        "Num.compare 18446744073709551616i128 18446744073709551616i128",
+        RocOrder::Eq,
+        RocOrder
+    );
+    assert_evals_to!(
+        "Num.compare 18446744073709551616i128 18446744073709551615i128",
+        RocOrder::Gt,
+        RocOrder
+    );
+    assert_evals_to!(
+        "Num.compare 0xffff_ffff_ffff_ffff_ffff_ffff_ffff_ffffu128 1u128",
+        RocOrder::Gt,
+        RocOrder
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+fn dec_compare() {
+    assert_evals_to!("Num.compare -1.5dec 2.25dec", RocOrder::Lt, RocOrder);
+    assert_evals_to!("Num.compare 2.25dec 2.25dec", RocOrder::Eq, RocOrder);
+    assert_evals_to!("Num.compare 2.5dec 2.25dec", RocOrder::Gt, RocOrder);
+}
+
 #[test]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
 fn pow() {
@@ -1852,7 +1886,7 @@ fn int_add_checked_err() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
 fn int_add_wrap() {
     assert_evals_to!(
         "Num.addWrap 9_223_372_036_854_775_807 1",
@@ -1862,7 +1896,7 @@ fn int_add_wrap() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
 fn float_add_checked_pass() {
     assert_evals_to!(
         "Num.addChecked 1.0 0.0",
@@ -1872,7 +1906,7 @@ fn float_add_checked_pass() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
 fn float_add_checked_fail() {
     assert_evals_to!(
         "Num.addChecked 1.7976931348623157e308 1.7976931348623157e308",
@@ -1921,7 +1955,7 @@ fn float_sub_overflow() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
 fn int_sub_checked() {
     assert_evals_to!(
         indoc!(
@@ -2035,7 +2069,7 @@ fn float_negative_mul_overflow() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
 fn int_mul_wrap() {
     assert_evals_to!(
         indoc!(
@@ -2049,7 +2083,7 @@ fn int_mul_wrap() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
 fn int_mul_checked() {
     assert_evals_to!(
         indoc!(
@@ -2077,7 +2111,7 @@ fn int_mul_checked() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
 fn float_mul_checked() {
     assert_evals_to!(
         indoc!(
@@ -2198,7 +2232,7 @@ fn shift_right_cast_i8() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
 fn min_i128() {
     assert_evals_to!(
         indoc!(
@@ -2212,7 +2246,7 @@ fn min_i128() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
 fn max_i128() {
     assert_evals_to!(
         indoc!(
@@ -3316,7 +3350,7 @@ fn u8_mul_greater_than_i8() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
 fn add_saturated() {
     assert_evals_to!(
         indoc!(
@@ -3362,7 +3396,7 @@ fn add_saturated() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
 fn sub_saturated() {
     assert_evals_to!(
         indoc!(
@@ -3406,7 +3440,7 @@ fn sub_saturated() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
 fn mul_saturated() {
     assert_evals_to!(
         indoc!(
@@ -3770,7 +3804,7 @@ fn promote_i128_number_layout() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
 fn promote_u128_number_layout() {
     assert_evals_to!(
         indoc!(
@@ -3783,6 +3817,58 @@ fn promote_u128_number_layout() {
     );
 }
 
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn i128_arithmetic_and_comparison() {
+    assert_evals_to!(
+        "(18446744073709551616i128 * 3 - 1) // 2",
+        27670116110564327423,
+        i128
+    );
+    assert_evals_to!("Num.addWrap Num.maxI128 1", i128::MIN, i128);
+    assert_evals_to!("-18446744073709551616i128 < 1", true, bool);
+    assert_evals_to!(
+        "18446744073709551617u128 >= 18446744073709551616",
+        true,
+        bool
+    );
+    assert_evals_to!(
+        "18446744073709551617u128 == 18446744073709551616",
+        false,
+        bool
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+fn u128_bitwise_and_shift() {
+    assert_evals_to!(
+        "Num.bitwiseAnd 0xffff_0000_0000_0000_0000_0000_0000_ffffu128 0x0ff0_0000_0000_0000_0000_0000_0000_0ff0",
+        0x0ff0_0000_0000_0000_0000_0000_0000_0ff0u128,
+        u128
+    );
+    assert_evals_to!(
+        "Num.bitwiseOr 0xff00_0000_0000_0000_0000_0000_0000_0000u128 0xff",
+        0xff00_0000_0000_0000_0000_0000_0000_00ffu128,
+        u128
+    );
+    assert_evals_to!(
+        "Num.bitwiseXor 0xffff_0000_0000_0000_0000_0000_0000_ffffu128 0x0ff0_0000_0000_0000_0000_0000_0000_0ff0",
+        0xf00f_0000_0000_0000_0000_0000_0000_f00fu128,
+        u128
+    );
+    assert_evals_to!("Num.shiftLeftBy 1u128 100", 1u128 << 100, u128);
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+fn dec_comparison() {
+    assert_evals_to!("1.5dec < 2.25dec", true, bool);
+    assert_evals_to!("-1.5dec > 2.25dec", false, bool);
+    assert_evals_to!("2.25dec >= 2.25dec", true, bool);
+    assert_evals_to!("2.5dec <= 2.25dec", false, bool);
+}
+
 #[test]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
 fn when_on_decimals() {
@@ -4253,3 +4253,88 @@ fn function_specialization_information_in_lambda_set_thunk_independent_defs() {
         u8
     );
 }
+
+#[test]
+#[cfg(feature = "gen-llvm")]
+fn inline_expect_and_dbg() {
+    assert_evals_to!(
+        indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            main =
+                x = 42i64
+                expect x == 42
+                dbg x
+                x
+            "#
+        ),
+        42,
+        i64
+    );
+}
+
+#[test]
+#[cfg(feature = "gen-dev")]
+fn inline_expect_and_dbg_write_to_shared_buffer() {
+    use crate::helpers::dev::{helper, set_shared_buffer, SHARED_BUFFER_WORDS};
+    use roc_gen_dev::run_jit_function_raw;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    let arena = bumpalo::Bump::new();
+    let (main_fn_name, errors, lib) = helper(
+        &arena,
+        indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            main =
+                x = 41i64
+                expect x == 42
+                dbg x
+                x
+            "#
+        ),
+        true,
+        false,
+    );
+
+    let mut buffer = vec![0u64; SHARED_BUFFER_WORDS];
+    set_shared_buffer(&lib, &mut buffer);
+
+    // dbg waits for the parent to read the value, like `roc dev` does
+    let lock = buffer[2..].as_ptr() as usize + 4;
+    let done = Arc::new(AtomicBool::new(false));
+    let parent = {
+        let done = Arc::clone(&done);
+
+        std::thread::spawn(move || {
+            let lock = unsafe { &*(lock as *const AtomicU32) };
+            let mut dbgs = 0;
+
+            while !done.load(Ordering::Acquire) {
+                if lock.load(Ordering::Acquire) == 2 {
+                    dbgs += 1;
+                    lock.store(0, Ordering::Release);
+                }
+
+                std::hint::spin_loop();
+            }
+
+            dbgs
+        })
+    };
+
+    run_jit_function_raw!(lib, main_fn_name, i64, |x| assert_eq!(x, 41), errors);
+
+    done.store(true, Ordering::Release);
+    assert_eq!(parent.join().unwrap(), 1);
+
+    // the failed expect and the dbg
+    assert_eq!(buffer[0], 2);
+
+    // The first failure starts with its region and module, then the lookup of `x`, then `x`.
+    let first = 3 * 8;
+    assert_eq!(buffer[(first + 12 + 12) / 8], 41);
+}
@@ -191,6 +191,7 @@ pub fn helper(
         exposed_to_host: exposed_to_host.values.keys().copied().collect(),
         lazy_literals,
         generate_allocators: true, // Needed for testing, since we don't have a platform
+        mode: roc_gen_dev::AssemblyBackendMode::Test,
     };
 
     let target = target_lexicon::Triple::host();
@@ -240,9 +241,31 @@ pub fn helper(
 
     let lib = unsafe { Library::new(path) }.expect("failed to load shared library");
 
+    // Failed expects are written to the shared buffer, like in `roc test`
+    let buffer = Box::leak(vec![0u64; SHARED_BUFFER_WORDS].into_boxed_slice());
+    set_shared_buffer(&lib, buffer);
+
     (main_fn_name, delayed_errors, lib)
 }
 
+pub const SHARED_BUFFER_WORDS: usize = 512;
+
+/// Make failed expects and dbgs write to `buffer`, which starts out without any failures
+pub fn set_shared_buffer(lib: &Library, buffer: &mut [u64]) {
+    // the number of failures, the offset of the next failure, and the lock
+    buffer[0] = 0;
+    buffer[1] = 3 * 8;
+    buffer[2] = 0;
+
+    unsafe {
+        let set_shared_buffer: libloading::Symbol<unsafe extern "C" fn(*mut u8, usize) -> usize> =
+            lib.get(b"set_shared_buffer")
+                .expect("failed to find `set_shared_buffer`");
+
+        set_shared_buffer(buffer.as_mut_ptr().cast(), buffer.len() * 8);
+    }
+}
+
 #[allow(unused_macros)]
 macro_rules! assert_evals_to {
     ($src:expr, $expected:expr, $ty:ty) => {{
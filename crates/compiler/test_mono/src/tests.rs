@@ -15,10 +15,10 @@ const EXPANDED_STACK_SIZE: usize = 8 * 1024 * 1024;
 
 use bumpalo::Bump;
 use roc_collections::all::MutMap;
-use roc_load::ExecutionMode;
 use roc_load::LoadConfig;
 use roc_load::LoadMonomorphizedError;
 use roc_load::Threading;
+use roc_load::{ExecutionMode, TestOptions};
 use roc_module::symbol::Interns;
 use roc_module::symbol::Symbol;
 use roc_mono::ir::Proc;
@@ -82,7 +82,7 @@ fn compiles_to_ir(test_name: &str, src: &str, mode: &str, no_check: bool) {
 
     let exec_mode = match mode {
         "exec" => ExecutionMode::Executable,
-        "test" => ExecutionMode::Test,
+        "test" => ExecutionMode::Test(TestOptions::default()),
         _ => panic!("Invalid test_mono exec mode {mode}"),
     };
 
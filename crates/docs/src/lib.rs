@@ -13,8 +13,10 @@ use roc_highlight::highlight_parser::{highlight_defs, highlight_expr};
 use roc_load::docs::{DocEntry, TypeAnnotation};
 use roc_load::docs::{ModuleDocumentation, RecordField};
 use roc_load::{ExecutionMode, LoadConfig, LoadedModule, LoadingProblem, Threading};
-use roc_module::symbol::{Interns, Symbol};
+use roc_module::symbol::{ModuleId, Symbol};
 use roc_packaging::cache::{self, RocCacheDir};
+use roc_packaging::https::PackageMetadata;
+use roc_parse::ast::{ExtractSpaces, Header, StrLiteral};
 use roc_parse::ident::{parse_ident, Ident};
 use roc_parse::state::State;
 use roc_region::all::Region;
@@ -24,23 +26,256 @@ use std::path::{Path, PathBuf};
 mod docs_error;
 mod html;
 
-const BUILD_DIR: &str = "./generated-docs";
+/// Where `roc docs` writes the docs, unless it's given an `--output` directory
+pub const DEFAULT_OUTPUT_DIR: &str = "./generated-docs";
+
+/// A package or platform whose docs are generated, along with the packages it depends on
+struct Package {
+    name: String,
+    version: String,
+    /// The directory of the package's main .roc file, which contains all of its modules
+    root_dir: PathBuf,
+    /// Where the package's docs go, relative to the build dir and to the base URL.
+    /// This is empty for the root package, so its docs are where they've always been.
+    docs_dir: String,
+    loaded_module: LoadedModule,
+}
+
+impl Package {
+    fn url(&self) -> String {
+        let mut url = base_url();
+
+        if !self.docs_dir.is_empty() {
+            url.push_str(&self.docs_dir);
+            url.push('/');
+        }
+
+        url
+    }
+
+    fn module_url(&self, module_name: &str) -> String {
+        format!("{}{}", self.url(), module_name)
+    }
+
+    /// Whether the module of this package is documented, and exposes `ident`
+    fn exposes(&self, module_name: &str, ident: &str) -> bool {
+        self.loaded_module
+            .docs_by_module
+            .values()
+            .filter(|module| module.name == module_name)
+            .flat_map(|module| module.entries.iter())
+            .any(|entry| match entry {
+                DocEntry::DocDef(doc_def) => doc_def.name == ident,
+                DocEntry::DetachedDoc(_) => false,
+            })
+    }
+}
+
+/// The package that a module of `package`, or of one of its dependencies, belongs to
+fn package_of_module<'a>(
+    packages: &'a [Package],
+    package: &'a Package,
+    module_id: ModuleId,
+) -> &'a Package {
+    let path = package
+        .loaded_module
+        .sources
+        .get(&module_id)
+        .and_then(|(path, _)| path.canonicalize().ok());
+
+    // Packages can be in the directories of others, e.g. an app's packages in subdirectories
+    // of the app, so the module is in the innermost package whose directory contains it.
+    path.and_then(|path| {
+        packages
+            .iter()
+            .filter(|other| path.starts_with(&other.root_dir))
+            .max_by_key(|other| other.root_dir.components().count())
+    })
+    .unwrap_or(package)
+}
+
+/// The name and version of a package, and where the packages it depends on are.
+///
+/// Package headers have no version of their own, so the version is whatever follows an `@` in
+/// the package's name, like the `0.2.0` in `package "json@0.2.0"`.
+fn read_package_header(root_file: &Path) -> (String, String, Vec<PathBuf>) {
+    let src = fs::read_to_string(root_file).unwrap_or_else(|err| {
+        eprintln!("Could not read {}: {}", root_file.display(), err);
+        std::process::exit(1);
+    });
+
+    let arena = Bump::new();
+    let (name, packages) = match roc_parse::module::parse_header(&arena, State::new(src.as_bytes()))
+    {
+        Ok((module, _)) => match module.header {
+            Header::Package(header) => (header.name.value.to_str(), header.packages.item.items),
+            Header::Platform(header) => (header.name.value.to_str(), header.packages.item.items),
+            Header::App(header) => {
+                let name = match header.name.value {
+                    StrLiteral::PlainLine(name) => name,
+                    _ => "",
+                };
+                let packages = match header.packages {
+                    Some(packages) => packages.item.items,
+                    None => &[],
+                };
+
+                (name, packages)
+            }
+            Header::Interface(_) | Header::Hosted(_) => ("", &[][..]),
+        },
+        // Loading the module reports the syntax error
+        Err(_) => ("", &[][..]),
+    };
+
+    let (name, version) = match name.rsplit_once('@') {
+        Some((name, version)) if !name.is_empty() => (name, version),
+        _ => (name, ""),
+    };
+
+    let name = if name.is_empty() {
+        "Documentation"
+    } else {
+        name
+    };
+
+    let root_dir = root_file.parent().unwrap_or_else(|| Path::new("."));
+    let dependencies = packages
+        .iter()
+        .filter_map(|entry| {
+            let package_name = entry
+                .value
+                .extract_spaces()
+                .item
+                .package_name
+                .value
+                .to_str();
+
+            if package_name.starts_with("https://") {
+                // Loading the dependent package has downloaded this one into the cache
+                let metadata = PackageMetadata::try_from(package_name).ok()?;
+
+                Some(
+                    cache::roc_cache_dir()
+                        .join(metadata.cache_subdir)
+                        .join(metadata.content_hash)
+                        .join(metadata.root_module_filename.unwrap_or("main.roc")),
+                )
+            } else {
+                Some(root_dir.join(package_name))
+            }
+        })
+        .collect();
+
+    (name.to_string(), version.to_string(), dependencies)
+}
+
+/// Load the package at `root_file` and all the packages it depends on, directly or not
+fn load_packages(root_file: PathBuf) -> Vec<Package> {
+    let mut packages: Vec<Package> = Vec::new();
+    let mut pending = vec![root_file];
+
+    while let Some(root_file) = pending.pop() {
+        let root_file = root_file.canonicalize().unwrap_or(root_file);
+        let root_dir = root_file
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .to_path_buf();
+
+        if packages.iter().any(|package| package.root_dir == root_dir) {
+            continue;
+        }
+
+        let (name, version, dependencies) = read_package_header(&root_file);
+
+        // The root package is at the base URL, and every dependency in a directory of its name
+        let docs_dir = if packages.is_empty() {
+            String::new()
+        } else {
+            let dir = docs_dir_of(&name);
+            let mut docs_dir = dir.clone();
+            let mut suffix = 1;
+
+            while packages.iter().any(|package| package.docs_dir == docs_dir) {
+                suffix += 1;
+                docs_dir = format!("{dir}-{suffix}");
+            }
+
+            docs_dir
+        };
+
+        let loaded_module = load_module_for_docs(root_file);
 
-pub fn generate_docs_html(root_file: PathBuf) {
-    let build_dir = Path::new(BUILD_DIR);
-    let loaded_module = load_module_for_docs(root_file);
+        pending.extend(dependencies);
+        packages.push(Package {
+            name,
+            version,
+            root_dir,
+            docs_dir,
+            loaded_module,
+        });
+    }
 
-    // TODO get these from the platform's source file rather than hardcoding them!
-    let package_name = "Documentation".to_string();
-    let version = String::new();
+    packages
+}
 
-    // Clear out the generated-docs dir (we'll create a fresh one at the end)
-    if build_dir.exists() {
+/// The directory that the docs of a dependency named `name` go in, relative to the build dir.
+/// Package names are chosen by their authors, so `..` segments and leading `/`s are dropped to
+/// keep the docs inside the build dir.
+fn docs_dir_of(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' | '/' => c,
+            _ => '-',
+        })
+        .collect();
+
+    let dir = name
+        .split('/')
+        .filter(|segment| !matches!(*segment, "" | "." | ".."))
+        .collect::<Vec<_>>()
+        .join("/");
+
+    if dir.is_empty() {
+        "package".to_string()
+    } else {
+        dir
+    }
+}
+
+/// A file that `roc docs` puts in the build dir, so a later run knows it can empty the dir
+const BUILD_DIR_MARKER: &str = ".roc-docs";
+
+/// Empty the build dir if `roc docs` made it, and create it if it doesn't exist. A directory
+/// that has other files in it is never deleted, because `--output` could be any directory.
+fn prepare_build_dir(build_dir: &Path) {
+    if build_dir.join(BUILD_DIR_MARKER).exists() {
         fs::remove_dir_all(build_dir)
             .expect("TODO gracefully handle being unable to delete build dir");
+    } else if let Ok(mut entries) = fs::read_dir(build_dir) {
+        if entries.next().is_some() {
+            eprintln!(
+                "{} is not empty, and its contents weren't generated by `roc docs`. Empty it, or pick another directory to write the docs to.",
+                build_dir.display()
+            );
+            std::process::exit(1);
+        }
     }
+
     fs::create_dir_all(build_dir).expect("TODO gracefully handle being unable to create build dir");
 
+    fs::write(build_dir.join(BUILD_DIR_MARKER), "")
+        .expect("TODO gracefully handle failing to mark the build dir");
+}
+
+/// Generate the docs of the package at `root_file`, and of the packages it depends on, into
+/// `build_dir`. The docs of a dependency are in a directory named after it.
+pub fn generate_docs_html(root_file: PathBuf, build_dir: &Path) {
+    let packages = load_packages(root_file);
+
+    prepare_build_dir(build_dir);
+
     // Copy over the assets
     fs::write(
         build_dir.join("search.js"),
@@ -60,6 +295,17 @@ pub fn generate_docs_html(root_file: PathBuf) {
     )
     .expect("TODO gracefully handle failing to make the favicon");
 
+    for package in packages.iter() {
+        generate_package_docs_html(&packages, package, build_dir);
+    }
+
+    println!("🎉 Docs generated in {}", build_dir.display());
+}
+
+fn generate_package_docs_html(packages: &[Package], package: &Package, build_dir: &Path) {
+    let loaded_module = &package.loaded_module;
+    let package_dir = build_dir.join(&package.docs_dir);
+
     let template_html = include_str!("./static/index.html")
         .replace("<!-- search.js -->", "/search.js")
         .replace("<!-- styles.css -->", "/styles.css")
@@ -70,7 +316,7 @@ pub fn generate_docs_html(root_file: PathBuf) {
                 .docs_by_module
                 .iter()
                 .map(|(_, module)| {
-                    let href = sidebar_link_url(module.name.as_str());
+                    let href = package.module_url(module.name.as_str());
 
                     format!(r#"<link rel="prefetch" href="{href}"/>"#)
                 })
@@ -80,7 +326,7 @@ pub fn generate_docs_html(root_file: PathBuf) {
         )
         .replace(
             "<!-- Module links -->",
-            render_sidebar(loaded_module.docs_by_module.values()).as_str(),
+            render_sidebar(package, loaded_module.docs_by_module.values()).as_str(),
         );
 
     let all_exposed_symbols = {
@@ -96,7 +342,7 @@ pub fn generate_docs_html(root_file: PathBuf) {
     // Write each package's module docs html file
     for module_docs in loaded_module.docs_by_module.values() {
         let module_name = module_docs.name.as_str();
-        let module_dir = build_dir.join(module_name.replace('.', "/").as_str());
+        let module_dir = package_dir.join(module_name.replace('.', "/").as_str());
 
         fs::create_dir_all(&module_dir)
             .expect("TODO gracefully handle not being able to create the module dir");
@@ -104,27 +350,21 @@ pub fn generate_docs_html(root_file: PathBuf) {
         let rendered_module = template_html
             .replace(
                 "<!-- Page title -->",
-                page_title(package_name.as_str(), module_name).as_str(),
+                page_title(package.name.as_str(), module_name).as_str(),
             )
             .replace(
                 "<!-- Package Name and Version -->",
-                render_name_and_version(package_name.as_str(), version.as_str()).as_str(),
+                render_name_and_version(package).as_str(),
             )
             .replace(
                 "<!-- Module Docs -->",
-                render_module_documentation(module_docs, &loaded_module, &all_exposed_symbols)
+                render_module_documentation(module_docs, packages, package, &all_exposed_symbols)
                     .as_str(),
             );
 
         fs::write(module_dir.join("index.html"), rendered_module)
             .expect("TODO gracefully handle failing to write index.html inside module's dir");
     }
-
-    println!("🎉 Docs generated in {}", build_dir.display());
-}
-
-fn sidebar_link_url(module_name: &str) -> String {
-    format!("{}{}", base_url(), module_name)
 }
 
 fn page_title(package_name: &str, module_name: &str) -> String {
@@ -176,7 +416,8 @@ pub fn syntax_highlight_top_level_defs(code_str: &str) -> DocsResult<String> {
 
 fn render_module_documentation(
     module: &ModuleDocumentation,
-    root_module: &LoadedModule,
+    packages: &[Package],
+    package: &Package,
     all_exposed_symbols: &VecSet<Symbol>,
 ) -> String {
     let mut buf = String::new();
@@ -232,7 +473,8 @@ fn render_module_documentation(
                             all_exposed_symbols,
                             &module.scope,
                             docs,
-                            root_module,
+                            packages,
+                            package,
                         );
                     }
 
@@ -245,7 +487,8 @@ fn render_module_documentation(
                     all_exposed_symbols,
                     &module.scope,
                     docs,
-                    root_module,
+                    packages,
+                    package,
                 );
             }
         };
@@ -309,42 +552,41 @@ fn base_url() -> String {
     }
 }
 
-fn render_name_and_version(name: &str, version: &str) -> String {
+fn render_name_and_version(package: &Package) -> String {
     let mut buf = String::new();
-    let mut url_str = base_url();
-
-    url_str.push_str(name);
+    let url_str = package.url();
 
     push_html(&mut buf, "h1", vec![("class", "pkg-full-name")], {
         let mut link_buf = String::new();
 
-        push_html(&mut link_buf, "a", vec![("href", url_str.as_str())], name);
+        push_html(
+            &mut link_buf,
+            "a",
+            vec![("href", url_str.as_str())],
+            package.name.as_str(),
+        );
 
         link_buf
     });
 
-    let mut versions_url_str = base_url();
-
-    versions_url_str.push('/');
-    versions_url_str.push_str(name);
-    versions_url_str.push('/');
-    versions_url_str.push_str(version);
-
     push_html(
         &mut buf,
         "a",
-        vec![("class", "version"), ("href", versions_url_str.as_str())],
-        version,
+        vec![("class", "version"), ("href", url_str.as_str())],
+        package.version.as_str(),
     );
 
     buf
 }
 
-fn render_sidebar<'a, I: Iterator<Item = &'a ModuleDocumentation>>(modules: I) -> String {
+fn render_sidebar<'a, I: Iterator<Item = &'a ModuleDocumentation>>(
+    package: &Package,
+    modules: I,
+) -> String {
     let mut buf = String::new();
 
     for module in modules {
-        let href = sidebar_link_url(module.name.as_str());
+        let href = package.module_url(module.name.as_str());
         let mut sidebar_entry_content = String::new();
 
         push_html(
@@ -712,10 +954,14 @@ struct DocUrl {
 fn doc_url<'a>(
     all_exposed_symbols: &VecSet<Symbol>,
     scope: &Scope,
-    interns: &'a Interns,
+    packages: &[Package],
+    package: &'a Package,
     mut module_name: &'a str,
     ident: &str,
 ) -> DocUrl {
+    let interns = &package.loaded_module.interns;
+    let module_id;
+
     if module_name.is_empty() {
         // This is an unqualified lookup, so look for the ident
         // in scope!
@@ -726,6 +972,7 @@ fn doc_url<'a>(
                 // module - for example, if this is in scope from an
                 // unqualified import.
                 module_name = symbol.module_string(interns);
+                module_id = symbol.module_id();
             }
             Err(_) => {
                 dbg!(scope);
@@ -738,8 +985,11 @@ fn doc_url<'a>(
         }
     } else {
         match interns.module_ids.get_id(&module_name.into()) {
-            Some(module_id) => {
-                let symbol = interns.symbol(module_id, ident.into());
+            Some(id) => {
+                let symbol = interns.symbol(id, ident.into());
+                let module_package = package_of_module(packages, package, id);
+
+                module_id = id;
 
                 if symbol.is_builtin() {
                     // We can always generate links for builtin modules.
@@ -748,6 +998,15 @@ fn doc_url<'a>(
                     // a link directly to the builtin docs on roc-lang.org instead of to a localhost
                     // URL that will 404.
                     module_name = symbol.module_string(interns);
+                } else if !std::ptr::eq(module_package, package) {
+                    // The module is in another package, whose docs were loaded on their own,
+                    // so its symbols are not the ones in this package's interns.
+                    if !module_package.exposes(module_name, ident) {
+                        // TODO return Err here
+                        panic!(
+                            "Tried to generate an automatic link in docs for `{}.{}`, but the `{}` package does not document `{}` in `{}`.",
+                            module_name, ident, module_package.name, ident, module_name);
+                    }
                 }
                 // Note: You can do qualified lookups on your own module, e.g.
                 // if I'm in the Foo module, I can do a `Foo.bar` lookup.
@@ -760,9 +1019,6 @@ fn doc_url<'a>(
 
                 // This is a valid symbol for this dependency,
                 // so proceed using the current module's name.
-                //
-                // TODO: In the future, this is where we'll
-                // incorporate the package name into the link.
             }
             None => {
                 // TODO return Err here
@@ -771,11 +1027,17 @@ fn doc_url<'a>(
         }
     }
 
-    let mut url = base_url();
+    // Builtins are always at the base URL, like the root package
+    let mut url = if module_id.is_builtin() {
+        base_url()
+    } else {
+        package_of_module(packages, package, module_id).url()
+    };
 
     // Example:
     //
     // module_name: "Str", ident: "join" => "/Str#join"
+    // in a dependency named "json": "/json/Decode#string"
     url.push_str(module_name);
     url.push('#');
     url.push_str(ident);
@@ -791,7 +1053,8 @@ fn markdown_to_html(
     all_exposed_symbols: &VecSet<Symbol>,
     scope: &Scope,
     markdown: &str,
-    loaded_module: &LoadedModule,
+    packages: &[Package],
+    package: &Package,
 ) {
     use pulldown_cmark::{BrokenLink, CodeBlockKind, CowStr, Event, LinkType, Tag::*};
 
@@ -820,7 +1083,8 @@ fn markdown_to_html(
                                 let DocUrl { url, title } = doc_url(
                                     all_exposed_symbols,
                                     scope,
-                                    &loaded_module.interns,
+                                    packages,
+                                    package,
                                     module_name,
                                     symbol_name,
                                 );
@@ -838,13 +1102,8 @@ fn markdown_to_html(
                     Ok((_, Ident::Tag(type_name), _)) => {
                         // This looks like a tag name, but it could
                         // be a type alias that's in scope, e.g. [I64]
-                        let DocUrl { url, title } = doc_url(
-                            all_exposed_symbols,
-                            scope,
-                            &loaded_module.interns,
-                            "",
-                            type_name,
-                        );
+                        let DocUrl { url, title } =
+                            doc_url(all_exposed_symbols, scope, packages, package, "", type_name);
 
                         Some((url.into(), title.into()))
                     }
@@ -0,0 +1,7 @@
+interface Rope
+    exposes [length]
+    imports []
+
+## How long the rope is, in meters.
+length : F64
+length = 30
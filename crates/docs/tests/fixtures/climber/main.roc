@@ -0,0 +1,3 @@
+package "climber@0.1.0"
+    exposes [Rope]
+    packages { escaping: "../escaping/main.roc" }
@@ -0,0 +1,7 @@
+interface Hatch
+    exposes [isOpen]
+    imports []
+
+## Whether the hatch is open.
+isOpen : Bool
+isOpen = Bool.false
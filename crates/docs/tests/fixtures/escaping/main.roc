@@ -0,0 +1,3 @@
+package "../../escaping@0.1.0"
+    exposes [Hatch]
+    packages {}
@@ -0,0 +1,7 @@
+interface Square
+    exposes [side]
+    imports [units.Length]
+
+## The length of each side of the square, in [Length.meters].
+side : F64
+side = Length.meters 2
@@ -0,0 +1,3 @@
+package "shapes@1.0.0"
+    exposes [Square]
+    packages { units: "../units/main.roc" }
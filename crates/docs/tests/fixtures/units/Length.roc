@@ -0,0 +1,7 @@
+interface Length
+    exposes [meters]
+    imports []
+
+## A length in meters.
+meters : F64 -> F64
+meters = \n -> n
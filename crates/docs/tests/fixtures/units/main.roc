@@ -0,0 +1,3 @@
+package "units@0.3.0"
+    exposes [Length]
+    packages {}
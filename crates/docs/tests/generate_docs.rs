@@ -0,0 +1,66 @@
+use roc_docs::generate_docs_html;
+use std::fs;
+use std::path::PathBuf;
+
+#[test]
+fn package_with_dependency() {
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let build_dir = std::env::temp_dir().join(format!("roc_docs_test_{}", std::process::id()));
+
+    generate_docs_html(fixtures.join("shapes/main.roc"), &build_dir);
+
+    let square = fs::read_to_string(build_dir.join("Square/index.html")).unwrap();
+    let length = fs::read_to_string(build_dir.join("units/Length/index.html")).unwrap();
+
+    fs::remove_dir_all(&build_dir).unwrap();
+
+    // The root package's docs are at the root, and its dependency's docs are named after it
+    assert!(square.contains("<title>Square - shapes</title>"));
+    assert!(square.contains(">1.0.0</a>"));
+    assert!(length.contains("<title>Length - units</title>"));
+    assert!(length.contains(">0.3.0</a>"));
+
+    // Links to a dependency go to its docs
+    assert!(square.contains(r#"href="/units/Length#meters""#));
+}
+
+#[test]
+fn dependency_name_stays_in_build_dir() {
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let parent_dir = std::env::temp_dir().join(format!("roc_docs_escape_{}", std::process::id()));
+    let build_dir = parent_dir.join("a/b");
+
+    generate_docs_html(fixtures.join("climber/main.roc"), &build_dir);
+
+    // The dependency is named "../../escaping", which would put its docs in parent_dir
+    let hatch = build_dir.join("escaping/Hatch/index.html").exists();
+    let escaped = parent_dir.join("escaping").exists();
+
+    fs::remove_dir_all(&parent_dir).unwrap();
+
+    assert!(hatch);
+    assert!(!escaped);
+}
+
+#[test]
+fn regenerates_into_its_own_build_dir() {
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let build_dir = std::env::temp_dir().join(format!("roc_docs_rerun_{}", std::process::id()));
+
+    generate_docs_html(fixtures.join("shapes/main.roc"), &build_dir);
+
+    // Docs from an earlier run are replaced, not merged with the new ones
+    let stale = build_dir.join("Stale/index.html");
+    fs::create_dir_all(stale.parent().unwrap()).unwrap();
+    fs::write(&stale, "").unwrap();
+
+    generate_docs_html(fixtures.join("shapes/main.roc"), &build_dir);
+
+    let square = build_dir.join("Square/index.html").exists();
+    let stale_remains = stale.exists();
+
+    fs::remove_dir_all(&build_dir).unwrap();
+
+    assert!(square);
+    assert!(!stale_remains);
+}
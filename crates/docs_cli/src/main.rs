@@ -1,10 +1,11 @@
 //! Provides a binary that is only used for static build servers.
 use clap::{Arg, Command};
-use roc_docs::generate_docs_html;
+use roc_docs::{generate_docs_html, DEFAULT_OUTPUT_DIR};
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 pub const ROC_FILE: &str = "ROC_FILE";
+const FLAG_OUTPUT: &str = "output";
 const DEFAULT_ROC_FILENAME: &str = "main.roc";
 
 fn main() -> io::Result<()> {
@@ -18,10 +19,22 @@ fn main() -> io::Result<()> {
                 .required(false)
                 .default_value(DEFAULT_ROC_FILENAME),
         )
+        .arg(
+            Arg::new(FLAG_OUTPUT)
+                .long(FLAG_OUTPUT)
+                .help("The directory to write the docs to. It must be empty, or have been written by an earlier `roc docs`.")
+                .takes_value(true)
+                .allow_invalid_utf8(true)
+                .required(false)
+                .default_value(DEFAULT_OUTPUT_DIR),
+        )
         .get_matches();
 
     // Populate roc_files
-    generate_docs_html(PathBuf::from(matches.value_of_os(ROC_FILE).unwrap()));
+    generate_docs_html(
+        PathBuf::from(matches.value_of_os(ROC_FILE).unwrap()),
+        Path::new(matches.value_of_os(FLAG_OUTPUT).unwrap()),
+    );
 
     Ok(())
 }
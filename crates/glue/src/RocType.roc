@@ -1,13 +1,26 @@
-platform "roc-lang/rbt"
-    requires {} { makeGlue : Str -> Types }
+platform "roc-lang/glue"
+    requires {} { makeGlue : List Types -> Result (List File) Str }
     exposes []
     packages {}
     imports []
     provides [makeGlueForHost]
 
-makeGlueForHost : Str -> Types
+## A glue script is an app which uses this file as its platform, e.g.
+##
+##     app "my-glue"
+##         packages { pf: "path/to/roc/crates/glue/src/RocType.roc" }
+##         imports []
+##         provides [makeGlue] to pf
+##
+## Running `roc glue my-glue.roc path/to/platform/main.roc out/` passes `makeGlue`
+## the platform's types (one `Types` for each target architecture), and then writes
+## each returned `File` into the `out/` directory.
+makeGlueForHost : List Types -> Result (List File) Str
 makeGlueForHost = makeGlue
 
+## A file to write into the output directory. Its name is relative to that directory.
+File : { name : Str, content : Str }
+
 # TODO move into separate Target.roc interface once glue works across interfaces.
 Target : {
     architecture: Architecture,
@@ -28,21 +41,21 @@ OperatingSystem : [
     Wasi,
 ]
 
-TypeId := Nat
+TypeId : Nat
 
-Types := {
+Types : {
     # These are all indexed by TypeId
     types: List RocType,
     sizes: List U32,
     aligns: List U32,
 
-    # Needed to check for duplicates
-    typesByName: Dict Str TypeId,
+    ## The platform's top-level values, which the host can call, along with their types
+    entryPoints: List { name: Str, type: TypeId },
 
     ## Dependencies - that is, which type depends on which other type.
     ## This is important for declaration order in C; we need to output a
     ## type declaration earlier in the file than where it gets referenced by another type.
-    deps: Dict TypeId (List TypeId),
+    deps: List { type: TypeId, dependsOn: List TypeId },
     target: Target,
 }
 
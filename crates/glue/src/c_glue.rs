@@ -0,0 +1,456 @@
+use crate::types::{RocNum, RocTagUnion, RocType, TypeId, Types};
+use indexmap::IndexMap;
+use roc_target::{Architecture, TargetInfo};
+use std::fmt::Write;
+
+pub static HEADER: &[u8] = include_bytes!("../templates/header.h");
+const INDENT: &str = "    ";
+
+/// For each declared name, the declaration's text along with the targets it's used on.
+/// Most declarations are the same on every target, but struct fields are sorted by
+/// alignment, which can differ between e.g. 32-bit and 64-bit targets.
+type Decls = IndexMap<String, IndexMap<String, Vec<TargetInfo>>>;
+
+/// Add the given declaration body for the given target, unless that target already
+/// has a declaration with this name. (Different types can share a name in C, e.g.
+/// two `Result Str U8`s are both `RocResult_RocStr_uint8_t`, so only declare it once.)
+fn add_decl(decls: &mut Decls, name: String, target_info: TargetInfo, body: String) {
+    let bodies = decls.entry(name).or_default();
+
+    if bodies
+        .values()
+        .any(|targets| targets.contains(&target_info))
+    {
+        return;
+    }
+
+    bodies.entry(body).or_default().push(target_info);
+}
+
+/// Emit C declarations for the given types, plus the entry points the host can call.
+pub fn emit(types_and_targets: &[(Types, TargetInfo)]) -> String {
+    let mut decls = Decls::default();
+
+    for (types, target_info) in types_and_targets {
+        for id in types.sorted_ids() {
+            let mut body = String::new();
+
+            add_type(id, types, &mut body);
+
+            if !body.is_empty() {
+                add_decl(&mut decls, type_name(id, types), *target_info, body);
+            }
+        }
+
+        for (name, id) in types.entry_points() {
+            let mut body = String::new();
+
+            add_entry_point(name, *id, types, &mut body);
+            add_decl(&mut decls, format!("roc__{name}"), *target_info, body);
+        }
+    }
+
+    let mut buf = String::new();
+
+    for bodies in decls.into_values() {
+        match bodies.first() {
+            Some((body, targets))
+                if bodies.len() == 1 && targets.len() == types_and_targets.len() =>
+            {
+                buf.push_str(body);
+            }
+            _ => {
+                // This declaration differs between targets, so pick the right one
+                // with the preprocessor.
+                for (index, (body, targets)) in bodies.iter().enumerate() {
+                    let directive = if index == 0 { "#if" } else { "#elif" };
+                    let condition = targets
+                        .iter()
+                        .map(|target_info| arch_condition(target_info.architecture))
+                        .collect::<Vec<_>>()
+                        .join(" || ");
+
+                    writeln!(buf, "\n{directive} {condition}").unwrap();
+                    buf.push_str(body.trim_start_matches('\n'));
+                }
+
+                writeln!(buf, "#else").unwrap();
+                writeln!(buf, "#error \"Roc glue: unsupported target architecture\"").unwrap();
+                writeln!(buf, "#endif").unwrap();
+            }
+        }
+    }
+
+    buf
+}
+
+/// The preprocessor condition for the given architecture, for both GCC/Clang and MSVC.
+fn arch_condition(arch: Architecture) -> &'static str {
+    match arch {
+        Architecture::Aarch32 => "defined(__arm__) || defined(_M_ARM)",
+        Architecture::Aarch64 => "defined(__aarch64__) || defined(_M_ARM64)",
+        Architecture::Wasm32 => "defined(__wasm32__)",
+        Architecture::X86_32 => "defined(__i386__) || defined(_M_IX86)",
+        Architecture::X86_64 => "defined(__x86_64__) || defined(_M_X64)",
+    }
+}
+
+fn add_type(id: TypeId, types: &Types, buf: &mut String) {
+    match types.get_type(id) {
+        RocType::Struct { name, fields } => {
+            let fields = fields
+                .iter()
+                .map(|(label, field_id)| (escape_kw(label.clone()), *field_id));
+
+            add_struct(name, fields, types, buf)
+        }
+        RocType::TagUnionPayload { name, fields } => {
+            let fields = fields
+                .iter()
+                .map(|(index, field_id)| (format!("f{index}"), *field_id));
+
+            add_struct(name, fields, types, buf)
+        }
+        RocType::TagUnion(tag_union) => match tag_union {
+            RocTagUnion::Enumeration { name, tags, size } => {
+                let name = escape_kw(name.clone());
+                let int_type = discriminant_type(*size);
+
+                writeln!(buf, "\ntypedef {int_type} {name};").unwrap();
+                add_discriminants(&name, tags.iter(), buf);
+            }
+            RocTagUnion::NonRecursive {
+                name,
+                tags,
+                discriminant_size,
+                ..
+            } => {
+                // Empty tag unions can never come up at runtime,
+                // and so don't need declared types.
+                if !tags.is_empty() {
+                    add_tag_union(name, tags, *discriminant_size, types, buf);
+                }
+            }
+            RocTagUnion::Recursive { name, tags, .. } => {
+                let comment = "// If there are few enough tags (up to 8 on 64-bit targets, or 4 on 32-bit targets),\n\
+                    // the discriminant is stored in the low bits of `pointer`.\n\
+                    // Otherwise, it's stored in the heap allocation, after the payload.";
+
+                add_recursive_tag_union(name, tags.iter().map(|(tag, _)| tag), comment, buf);
+            }
+            RocTagUnion::NullableWrapped {
+                name,
+                index_of_null_tag,
+                tags,
+                ..
+            } => {
+                let null_tag = &tags[*index_of_null_tag as usize].0;
+                let comment = format!(
+                    "// `pointer` is NULL for {null_tag}. For the other tags, the discriminant is stored\n\
+                    // the same way as in other recursive tag unions."
+                );
+
+                add_recursive_tag_union(name, tags.iter().map(|(tag, _)| tag), &comment, buf);
+            }
+            RocTagUnion::NullableUnwrapped {
+                name,
+                null_tag,
+                non_null_tag,
+                null_represents_first_tag,
+                ..
+            } => {
+                let comment = format!(
+                    "// `pointer` is NULL for {null_tag}, and points to the payload for {non_null_tag}."
+                );
+                let tags = if *null_represents_first_tag {
+                    [null_tag, non_null_tag]
+                } else {
+                    [non_null_tag, null_tag]
+                };
+
+                add_recursive_tag_union(name, tags, &comment, buf);
+            }
+            RocTagUnion::NonNullableUnwrapped { name, tag_name, .. } => {
+                let comment = format!("// `pointer` always points to the payload for {tag_name}.");
+
+                add_recursive_tag_union(name, [tag_name], &comment, buf);
+            }
+            RocTagUnion::SingleTagStruct {
+                name,
+                payload_fields,
+                ..
+            } => {
+                let fields = payload_fields
+                    .iter()
+                    .enumerate()
+                    .map(|(index, field_id)| (format!("f{index}"), *field_id));
+
+                add_struct(name, fields, types, buf)
+            }
+        },
+        RocType::RocResult(ok_id, err_id) => {
+            let name = type_name(id, types);
+            let ok = field_decl("ok", *ok_id, types);
+            let err = field_decl("err", *err_id, types);
+
+            writeln!(buf, "\ntypedef struct {name} {{").unwrap();
+            writeln!(buf, "{INDENT}union {{").unwrap();
+            for decl in [ok, err].iter().flatten() {
+                writeln!(buf, "{INDENT}{INDENT}{decl};").unwrap();
+            }
+            writeln!(buf, "{INDENT}}} payload;").unwrap();
+            writeln!(buf, "{INDENT}uint8_t tag; // 1 for Ok, 0 for Err").unwrap();
+            writeln!(buf, "}} {name};").unwrap();
+        }
+        // These types don't need to be declared in C, or are declared in the header.
+        RocType::Unit
+        | RocType::EmptyTagUnion
+        | RocType::Num(_)
+        | RocType::Bool
+        | RocType::RocStr
+        | RocType::RocDict(_, _)
+        | RocType::RocSet(_)
+        | RocType::RocList(_)
+        | RocType::RocBox(_) => {}
+        RocType::RecursivePointer { .. } => {
+            // This is recursively pointing to a type that should already have been added,
+            // so no extra work needs to happen.
+        }
+        RocType::Function { .. } => {
+            // Functions are declared as entry points instead.
+        }
+    }
+}
+
+fn add_struct(
+    name: &str,
+    fields: impl Iterator<Item = (String, TypeId)>,
+    types: &Types,
+    buf: &mut String,
+) {
+    let name = escape_kw(name.to_string());
+
+    writeln!(buf, "\ntypedef struct {name} {{").unwrap();
+
+    for (label, field_id) in fields {
+        if let Some(decl) = field_decl(&label, field_id, types) {
+            writeln!(buf, "{INDENT}{decl};").unwrap();
+        }
+    }
+
+    writeln!(buf, "}} {name};").unwrap();
+}
+
+fn add_tag_union(
+    name: &str,
+    tags: &[(String, Option<TypeId>)],
+    discriminant_size: u32,
+    types: &Types,
+    buf: &mut String,
+) {
+    let name = escape_kw(name.to_string());
+
+    add_discriminants(&name, tags.iter().map(|(tag, _)| tag), buf);
+
+    writeln!(buf, "\ntypedef struct {name} {{").unwrap();
+    writeln!(buf, "{INDENT}union {{").unwrap();
+
+    for (tag_name, opt_payload_id) in tags {
+        // Tags without a payload are fully described by the discriminant
+        if let Some(payload_id) = opt_payload_id {
+            if let Some(decl) = field_decl(&escape_kw(tag_name.clone()), *payload_id, types) {
+                writeln!(buf, "{INDENT}{INDENT}{decl};").unwrap();
+            }
+        }
+    }
+
+    writeln!(buf, "{INDENT}}} payload;").unwrap();
+
+    if discriminant_size > 0 {
+        let int_type = discriminant_type(discriminant_size);
+
+        writeln!(buf, "{INDENT}{int_type} discriminant;").unwrap();
+    }
+
+    writeln!(buf, "}} {name};").unwrap();
+}
+
+fn add_recursive_tag_union<'a>(
+    name: &str,
+    tags: impl IntoIterator<Item = &'a String>,
+    comment: &str,
+    buf: &mut String,
+) {
+    let name = escape_kw(name.to_string());
+
+    add_discriminants(&name, tags, buf);
+
+    writeln!(
+        buf,
+        "\n// A recursive tag union, which points to its payload on the heap.\n{comment}"
+    )
+    .unwrap();
+    writeln!(buf, "typedef struct {name} {{").unwrap();
+    writeln!(buf, "{INDENT}void *pointer;").unwrap();
+    writeln!(buf, "}} {name};").unwrap();
+}
+
+/// Tags are numbered in alphabetical order, which is the order we get them in.
+fn add_discriminants<'a>(name: &str, tags: impl IntoIterator<Item = &'a String>, buf: &mut String) {
+    writeln!(buf, "\nenum {{").unwrap();
+
+    for (index, tag_name) in tags.into_iter().enumerate() {
+        writeln!(buf, "{INDENT}{name}_{tag_name} = {index},").unwrap();
+    }
+
+    writeln!(buf, "}};").unwrap();
+}
+
+fn add_entry_point(name: &str, id: TypeId, types: &Types, buf: &mut String) {
+    let (arg_ids, ret_id) = match types.get_type(id) {
+        RocType::Function { args, ret, .. } => (args.as_slice(), *ret),
+        _ => (&[][..], id),
+    };
+
+    // The "generic" entry point always returns by pointer, and takes strings and lists by pointer.
+    let mut params = Vec::with_capacity(arg_ids.len() + 1);
+
+    if !is_zero_sized(ret_id, types) {
+        params.push(format!("{} *ret", type_name(ret_id, types)));
+    }
+
+    for (index, arg_id) in arg_ids.iter().enumerate() {
+        if is_zero_sized(*arg_id, types) {
+            continue;
+        }
+
+        let arg_type = type_name(*arg_id, types);
+
+        if is_passed_by_pointer(*arg_id, types) {
+            params.push(format!("{arg_type} *arg{index}"));
+        } else {
+            params.push(format!("{arg_type} arg{index}"));
+        }
+    }
+
+    let params = if params.is_empty() {
+        "void".to_string()
+    } else {
+        params.join(", ")
+    };
+
+    writeln!(
+        buf,
+        "\nextern void roc__{name}_1_exposed_generic({params});"
+    )
+    .unwrap();
+}
+
+/// A declaration like `int32_t x` or `RocList x`, or `None` if the type takes up no space.
+/// (Zero-sized struct fields aren't allowed in C.)
+fn field_decl(label: &str, id: TypeId, types: &Types) -> Option<String> {
+    if is_zero_sized(id, types) {
+        return None;
+    }
+
+    let decl = match types.get_type(id) {
+        RocType::RocList(elem_id) => {
+            format!("RocList {label}; // of {}", elem_type_name(*elem_id, types))
+        }
+        _ => format!("{} {label}", type_name(id, types)),
+    };
+
+    Some(decl)
+}
+
+fn elem_type_name(id: TypeId, types: &Types) -> String {
+    if is_zero_sized(id, types) {
+        "{}".to_string()
+    } else {
+        type_name(id, types)
+    }
+}
+
+fn type_name(id: TypeId, types: &Types) -> String {
+    match types.get_type(id) {
+        RocType::Unit | RocType::EmptyTagUnion => "void".to_string(),
+        RocType::RocStr => "RocStr".to_string(),
+        RocType::Bool => "bool".to_string(),
+        RocType::Num(RocNum::U8) => "uint8_t".to_string(),
+        RocType::Num(RocNum::U16) => "uint16_t".to_string(),
+        RocType::Num(RocNum::U32) => "uint32_t".to_string(),
+        RocType::Num(RocNum::U64) => "uint64_t".to_string(),
+        RocType::Num(RocNum::U128) => "RocU128".to_string(),
+        RocType::Num(RocNum::I8) => "int8_t".to_string(),
+        RocType::Num(RocNum::I16) => "int16_t".to_string(),
+        RocType::Num(RocNum::I32) => "int32_t".to_string(),
+        RocType::Num(RocNum::I64) => "int64_t".to_string(),
+        RocType::Num(RocNum::I128) => "RocI128".to_string(),
+        RocType::Num(RocNum::F32) => "float".to_string(),
+        RocType::Num(RocNum::F64) => "double".to_string(),
+        RocType::Num(RocNum::Dec) => "RocDec".to_string(),
+        RocType::RocList(_) => "RocList".to_string(),
+        RocType::RocDict(_, _) => "RocDict".to_string(),
+        RocType::RocSet(_) => "RocSet".to_string(),
+        RocType::RocBox(elem_id) => format!("{} *", type_name(*elem_id, types)),
+        RocType::RocResult(ok_id, err_id) => format!(
+            "RocResult_{}_{}",
+            ident_name(*ok_id, types),
+            ident_name(*err_id, types)
+        ),
+        RocType::Struct { name, .. }
+        | RocType::TagUnionPayload { name, .. }
+        | RocType::TagUnion(RocTagUnion::NonRecursive { name, .. })
+        | RocType::TagUnion(RocTagUnion::Recursive { name, .. })
+        | RocType::TagUnion(RocTagUnion::Enumeration { name, .. })
+        | RocType::TagUnion(RocTagUnion::NullableWrapped { name, .. })
+        | RocType::TagUnion(RocTagUnion::NullableUnwrapped { name, .. })
+        | RocType::TagUnion(RocTagUnion::NonNullableUnwrapped { name, .. })
+        | RocType::TagUnion(RocTagUnion::SingleTagStruct { name, .. }) => escape_kw(name.clone()),
+        RocType::RecursivePointer(content) => type_name(*content, types),
+        // Closures aren't supported yet
+        RocType::Function { .. } => "void *".to_string(),
+    }
+}
+
+/// A type's name, made suitable for use as part of another name (e.g. `RocStr *` => `RocStrPtr`)
+fn ident_name(id: TypeId, types: &Types) -> String {
+    type_name(id, types).replace(" *", "Ptr")
+}
+
+fn discriminant_type(size: u32) -> &'static str {
+    match size {
+        1 => "uint8_t",
+        2 => "uint16_t",
+        4 => "uint32_t",
+        8 => "uint64_t",
+        _ => unreachable!("Invalid discriminant size {}", size),
+    }
+}
+
+fn is_zero_sized(id: TypeId, types: &Types) -> bool {
+    matches!(types.get_type(id), RocType::Unit | RocType::EmptyTagUnion)
+}
+
+fn is_passed_by_pointer(id: TypeId, types: &Types) -> bool {
+    matches!(
+        types.get_type(id),
+        RocType::RocStr | RocType::RocList(_) | RocType::RocDict(_, _) | RocType::RocSet(_)
+    )
+}
+
+const RESERVED_KEYWORDS: &[&str] = &[
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else",
+    "enum", "extern", "float", "for", "goto", "if", "inline", "int", "long", "register",
+    "restrict", "return", "short", "signed", "sizeof", "static", "struct", "switch", "typedef",
+    "union", "unsigned", "void", "volatile", "while", "bool", "true", "false",
+];
+
+/// Add an underscore to any names that are reserved words in C
+fn escape_kw(input: String) -> String {
+    if RESERVED_KEYWORDS.contains(&input.as_str()) {
+        format!("{input}_")
+    } else {
+        input
+    }
+}
@@ -25,13 +25,39 @@
 #[repr(C)]
 pub struct Types {
     pub aligns: roc_std::RocList<u32>,
-    pub deps: roc_std::RocDict<u32, roc_std::RocList<u32>>,
+    pub deps: roc_std::RocList<R16>,
+    pub entryPoints: roc_std::RocList<R3>,
     pub sizes: roc_std::RocList<u32>,
     pub types: roc_std::RocList<RocType>,
-    pub typesByName: roc_std::RocDict<roc_std::RocStr, u32>,
     pub target: Target,
 }
 
+#[cfg(any(
+    target_arch = "arm",
+    target_arch = "wasm32",
+    target_arch = "x86"
+))]
+#[derive(Clone, Debug, Default, Eq, Ord, Hash, PartialEq, PartialOrd)]
+#[repr(C)]
+pub struct R16 {
+    pub dependsOn: roc_std::RocList<u32>,
+    pub r#type: u32,
+}
+
+#[cfg(any(
+    target_arch = "arm",
+    target_arch = "aarch64",
+    target_arch = "wasm32",
+    target_arch = "x86",
+    target_arch = "x86_64"
+))]
+#[derive(Clone, Debug, Default, Eq, Ord, Hash, PartialEq, PartialOrd)]
+#[repr(C)]
+pub struct File {
+    pub content: roc_std::RocStr,
+    pub name: roc_std::RocStr,
+}
+
 #[cfg(any(
     target_arch = "arm",
     target_arch = "aarch64",
@@ -626,13 +652,24 @@ pub struct R1 {
 #[repr(C)]
 pub struct Types {
     pub aligns: roc_std::RocList<u32>,
-    pub deps: roc_std::RocDict<u64, roc_std::RocList<u64>>,
+    pub deps: roc_std::RocList<R16>,
+    pub entryPoints: roc_std::RocList<R3>,
     pub sizes: roc_std::RocList<u32>,
     pub types: roc_std::RocList<RocType>,
-    pub typesByName: roc_std::RocDict<roc_std::RocStr, u64>,
     pub target: Target,
 }
 
+#[cfg(any(
+    target_arch = "aarch64",
+    target_arch = "x86_64"
+))]
+#[derive(Clone, Debug, Default, Eq, Ord, Hash, PartialEq, PartialOrd)]
+#[repr(C)]
+pub struct R16 {
+    pub dependsOn: roc_std::RocList<u64>,
+    pub r#type: u64,
+}
+
 #[cfg(any(
     target_arch = "aarch64",
     target_arch = "x86_64"
@@ -1,5 +1,5 @@
-use crate::rust_glue;
 use crate::types::{Env, Types};
+use crate::{c_glue, rust_glue, zig_glue};
 use bumpalo::Bump;
 use roc_load::{ExecutionMode, LoadConfig, LoadedModule, LoadingProblem, Threading};
 use roc_mono::layout::GlobalLayoutInterner;
@@ -18,10 +18,56 @@ pub struct IgnoreErrors {
 }
 
 impl IgnoreErrors {
-    const NONE: Self = IgnoreErrors { can: false };
+    pub(crate) const NONE: Self = IgnoreErrors { can: false };
+}
+
+/// The languages glue can be generated for, chosen by the output file's extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    Rust,
+    C,
+    Zig,
+}
+
+impl Lang {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("rs") => Some(Lang::Rust),
+            Some("h") => Some(Lang::C),
+            Some("zig") => Some(Lang::Zig),
+            _ => None,
+        }
+    }
+
+    pub fn header(self) -> &'static str {
+        let bytes = match self {
+            Lang::Rust => rust_glue::HEADER,
+            Lang::C => c_glue::HEADER,
+            Lang::Zig => zig_glue::HEADER,
+        };
+
+        std::str::from_utf8(bytes).unwrap()
+    }
+
+    pub fn emit(self, types_and_targets: &[(Types, TargetInfo)]) -> String {
+        match self {
+            Lang::Rust => rust_glue::emit(types_and_targets),
+            Lang::C => c_glue::emit(types_and_targets),
+            Lang::Zig => zig_glue::emit(types_and_targets),
+        }
+    }
 }
 
 pub fn generate(input_path: &Path, output_path: &Path) -> io::Result<i32> {
+    let lang = Lang::from_path(output_path).unwrap_or_else(|| {
+        eprintln!(
+            "Unsupported output file {} - the extension must be .rs (Rust), .h (C), or .zig (Zig)",
+            output_path.display()
+        );
+
+        process::exit(1);
+    });
+
     match load_types(
         input_path.to_path_buf(),
         Threading::AllAvailable,
@@ -38,8 +84,8 @@ pub fn generate(input_path: &Path, output_path: &Path) -> io::Result<i32> {
                 process::exit(1);
             });
 
-            let mut buf = std::str::from_utf8(rust_glue::HEADER).unwrap().to_string();
-            let body = rust_glue::emit(&types_and_targets);
+            let mut buf = lang.header().to_string();
+            let body = lang.emit(&types_and_targets);
 
             buf.push_str(&body);
 
@@ -60,20 +106,24 @@ pub fn generate(input_path: &Path, output_path: &Path) -> io::Result<i32> {
 
             Ok(0)
         }
-        Err(err) => match err.kind() {
-            ErrorKind::NotFound => {
-                eprintln!("Platform module file not found: {}", input_path.display());
-                process::exit(1);
-            }
-            error => {
-                eprintln!(
-                    "Error loading platform module file {} - {:?}",
-                    input_path.display(),
-                    error
-                );
-                process::exit(1);
-            }
-        },
+        Err(err) => report_load_error(input_path, err),
+    }
+}
+
+pub(crate) fn report_load_error(input_path: &Path, err: io::Error) -> ! {
+    match err.kind() {
+        ErrorKind::NotFound => {
+            eprintln!("Platform module file not found: {}", input_path.display());
+            process::exit(1);
+        }
+        error => {
+            eprintln!(
+                "Error loading platform module file {} - {:?}",
+                input_path.display(),
+                error
+            );
+            process::exit(1);
+        }
     }
 }
 
@@ -133,7 +183,11 @@ pub fn load_types(
         use roc_can::expr::DeclarationTag::*;
 
         match decls.declarations[index] {
-            Value | Function(_) | Recursive(_) | TailRecursive(_) => Some(decls.variables[index]),
+            Value | Function(_) | Recursive(_) | TailRecursive(_) => {
+                let name = decls.symbols[index].value.as_str(&interns).to_string();
+
+                Some((name, decls.variables[index]))
+            }
             Destructure(_) => {
                 // figure out if we need to export non-identifier defs - when would that
                 // happen?
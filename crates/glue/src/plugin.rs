@@ -0,0 +1,325 @@
+//! Runs glue scripts written in Roc. A glue script is an app whose platform is RocType.roc;
+//! it receives the platform's types and returns the files to write, which means glue for a
+//! new host language can be written without changing this crate.
+use crate::glue;
+use crate::load::{load_types, report_load_error, IgnoreErrors};
+use bumpalo::Bump;
+use inkwell::context::Context;
+use libloading::Library;
+use roc_build::link::llvm_module_to_dylib;
+use roc_build::program::{report_problems_monomorphized, report_problems_typechecked};
+use roc_collections::all::MutSet;
+use roc_gen_llvm::llvm::build::LlvmBackendMode;
+use roc_gen_llvm::llvm::externs::{
+    add_roc_externs_with_host_allocator, HOST_ALLOC, HOST_DEALLOC, HOST_REALLOC,
+};
+use roc_gen_llvm::run_roc::RocCallResult;
+use roc_load::{
+    EntryPoint, ExecutionMode, LoadConfig, LoadMonomorphizedError, LoadingProblem,
+    MonomorphizedModule, Threading,
+};
+use roc_mono::ir::{CrashTag, OptLevel};
+use roc_packaging::cache::{self, RocCacheDir};
+use roc_reporting::cli::MessageFormat;
+use roc_reporting::report::{RenderTarget, DEFAULT_PALETTE};
+use roc_std::{RocList, RocResult, RocStr};
+use roc_target::TargetInfo;
+use std::fs;
+use std::io;
+use std::path::{Component, Path};
+use std::process;
+use std::time::Instant;
+use target_lexicon::Triple;
+
+/// The signature of `makeGlueForHost` in RocType.roc, once it has been wrapped to catch crashes.
+type MakeGlue = unsafe extern "C" fn(
+    *const RocList<glue::Types>,
+    *mut RocCallResult<RocResult<RocList<glue::File>, RocStr>>,
+);
+
+/// Run the glue script at `spec_path` on the types of the platform at `input_path`,
+/// and write the files it returns into `output_dir`.
+pub fn generate(spec_path: &Path, input_path: &Path, output_dir: &Path) -> io::Result<i32> {
+    let types_and_targets = load_types(
+        input_path.to_path_buf(),
+        Threading::AllAvailable,
+        IgnoreErrors::NONE,
+    )
+    .unwrap_or_else(|err| report_load_error(input_path, err));
+
+    let arena = Bump::new();
+    let (lib, main_fn_name) = match compile_script(&arena, spec_path) {
+        Ok(compiled) => compiled,
+        Err(exit_code) => return Ok(exit_code),
+    };
+
+    let roc_types: RocList<glue::Types> = types_and_targets
+        .iter()
+        .map(|(types, target_info)| types.to_roc_types(*target_info))
+        .collect();
+
+    let result = unsafe {
+        share_allocator(&lib);
+
+        let make_glue = lib
+            .get::<MakeGlue>(main_fn_name.as_bytes())
+            .unwrap_or_else(|err| {
+                eprintln!(
+                    "Unable to find {} in the glue script - {:?}",
+                    main_fn_name, err
+                );
+
+                process::exit(1);
+            });
+        let mut result = RocCallResult::new(RocResult::err(RocStr::empty()));
+
+        // The script may or may not take ownership of its argument, so give it a reference
+        // of its own. If it's been released afterwards, our copy of it must not release it again.
+        let arg = roc_types.clone();
+
+        make_glue(&arg, &mut result);
+
+        if roc_types.is_unique() {
+            std::mem::forget(arg);
+        }
+
+        result
+    };
+
+    let result: Result<_, (String, CrashTag)> = result.into();
+    let files = match result {
+        Ok(roc_result) => match Result::<RocList<glue::File>, RocStr>::from(roc_result) {
+            Ok(files) => files,
+            Err(msg) => {
+                eprintln!("The glue script returned an error:\n\n\t{}", msg);
+
+                return Ok(1);
+            }
+        },
+        Err((msg, _)) => {
+            eprintln!("The glue script crashed:\n\n\t{}", msg);
+
+            return Ok(1);
+        }
+    };
+
+    for file in files.iter() {
+        write_file(output_dir, file)?;
+    }
+
+    println!(
+        "🎉 Generated type declarations in:\n\n\t{}",
+        output_dir.display()
+    );
+
+    Ok(0)
+}
+
+fn write_file(output_dir: &Path, file: &glue::File) -> io::Result<()> {
+    let name = Path::new(file.name.as_str());
+
+    // Glue scripts may only write inside the output directory.
+    let is_valid = name
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+
+    if !is_valid || name.as_os_str().is_empty() {
+        eprintln!(
+            "The glue script returned a file with an invalid name: {:?}\n\nFile names must be relative paths inside the output directory.",
+            file.name.as_str()
+        );
+
+        process::exit(1);
+    }
+
+    let path = output_dir.join(name);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(&path, file.content.as_str())
+}
+
+/// Build the glue script into a dylib, and return it along with the name of its entry point.
+/// On failure, the problems have already been reported, and this returns the exit code.
+fn compile_script<'a>(arena: &'a Bump, spec_path: &Path) -> Result<(Library, &'a str), i32> {
+    let start_time = Instant::now();
+    let target = Triple::host();
+    let load_config = LoadConfig {
+        target_info: TargetInfo::from(&target),
+        render: RenderTarget::ColorTerminal,
+        palette: DEFAULT_PALETTE,
+        threading: Threading::AllAvailable,
+        exec_mode: ExecutionMode::ExecutableIfCheck,
+    };
+
+    let load_result = roc_load::load_and_monomorphize(
+        arena,
+        spec_path.to_path_buf(),
+        RocCacheDir::Persistent(cache::roc_cache_dir().as_path()),
+        load_config,
+    );
+
+    let mut loaded = match load_result {
+        Ok(loaded) => loaded,
+        Err(LoadMonomorphizedError::LoadingProblem(LoadingProblem::FormattedReport(report))) => {
+            print!("{}", report);
+
+            return Err(1);
+        }
+        Err(LoadMonomorphizedError::LoadingProblem(problem)) => {
+            print!("Failed with error: {:?}", problem);
+
+            return Err(1);
+        }
+        Err(LoadMonomorphizedError::ErrorModule(mut module)) => {
+            let problems = report_problems_typechecked(&mut module, MessageFormat::Human);
+
+            problems.print_to_stdout(start_time.elapsed());
+            println!();
+
+            return Err(problems.exit_code());
+        }
+    };
+
+    let problems = report_problems_monomorphized(&mut loaded, MessageFormat::Human);
+
+    if problems.warnings > 0 {
+        problems.print_to_stdout(start_time.elapsed());
+        println!("\n");
+    }
+
+    mono_module_to_dylib(arena, target, loaded).map_err(|err| {
+        eprintln!("Unable to load the compiled glue script - {:?}", err);
+
+        1
+    })
+}
+
+fn mono_module_to_dylib<'a>(
+    arena: &'a Bump,
+    target: Triple,
+    loaded: MonomorphizedModule<'a>,
+) -> Result<(Library, &'a str), libloading::Error> {
+    let target_info = TargetInfo::from(&target);
+    let opt_level = OptLevel::Normal;
+
+    let MonomorphizedModule {
+        procedures,
+        entry_point,
+        interns,
+        mut layout_interner,
+        ..
+    } = loaded;
+
+    let context = Context::create();
+    let builder = context.create_builder();
+    let module = arena.alloc(roc_gen_llvm::llvm::build::module_from_builtins(
+        &target, &context, "",
+    ));
+
+    let (module_pass, function_pass) =
+        roc_gen_llvm::llvm::build::construct_optimization_passes(module, opt_level);
+
+    let (dibuilder, compile_unit) = roc_gen_llvm::llvm::build::Env::new_debug_info(module);
+
+    let env = roc_gen_llvm::llvm::build::Env {
+        arena,
+        builder: &builder,
+        dibuilder: &dibuilder,
+        compile_unit: &compile_unit,
+        context: &context,
+        interns,
+        module,
+        target_info,
+        // so crashes in the script are caught and reported, rather than taking down the process
+        mode: LlvmBackendMode::GenTest,
+        // important! we don't want any procedures to get the C calling convention
+        exposed_to_host: MutSet::default(),
+    };
+
+    // Add roc_alloc, roc_realloc, and roc_dealloc, which call the allocator that
+    // `share_allocator` passes in. There's no host to link them in otherwise.
+    add_roc_externs_with_host_allocator(&env);
+
+    let entry_point = match entry_point {
+        EntryPoint::Executable {
+            exposed_to_host,
+            platform_path: _,
+        } => {
+            // RocType.roc only exposes makeGlueForHost
+            debug_assert_eq!(exposed_to_host.len(), 1);
+            let (symbol, layout) = exposed_to_host[0];
+
+            roc_mono::ir::SingleEntryPoint { symbol, layout }
+        }
+        EntryPoint::Test => {
+            unreachable!()
+        }
+    };
+
+    let (main_fn_name, main_fn) = roc_gen_llvm::llvm::build::build_procedures_return_main(
+        &env,
+        &mut layout_interner,
+        opt_level,
+        procedures,
+        entry_point,
+    );
+
+    env.dibuilder.finalize();
+
+    // we don't use the debug info, and it causes weird errors.
+    module.strip_debug_info();
+
+    if main_fn.verify(true) {
+        function_pass.run_on(&main_fn);
+    } else {
+        panic!(
+            "Main function {} failed LLVM verification in build.",
+            main_fn_name
+        );
+    }
+
+    module_pass.run_on(env.module);
+
+    if let Err(errors) = env.module.verify() {
+        panic!("Errors defining module:\n{}", errors.to_string());
+    }
+
+    llvm_module_to_dylib(env.module, &target, opt_level).map(|lib| (lib, main_fn_name))
+}
+
+/// The types we hand to the glue script are allocated with roc_std, and the files it returns
+/// are deallocated with roc_std, so the script has to use the same allocator roc_std does.
+///
+/// # Safety
+/// The library must have been built with `add_roc_externs_with_host_allocator`.
+unsafe fn share_allocator(lib: &Library) {
+    set_global(
+        lib,
+        HOST_ALLOC,
+        roc_std::roc_alloc as unsafe extern "C" fn(_, _) -> _,
+    );
+    set_global(
+        lib,
+        HOST_REALLOC,
+        roc_std::roc_realloc as unsafe extern "C" fn(_, _, _, _) -> _,
+    );
+    set_global(
+        lib,
+        HOST_DEALLOC,
+        roc_std::roc_dealloc as unsafe extern "C" fn(_, _),
+    );
+}
+
+unsafe fn set_global<T>(lib: &Library, name: &str, value: T) {
+    let global = lib.get::<*mut T>(name.as_bytes()).unwrap_or_else(|err| {
+        eprintln!("Unable to find {} in the glue script - {:?}", name, err);
+
+        process::exit(1);
+    });
+    let ptr: *mut T = *global;
+
+    ptr.write(value);
+}
@@ -1,4 +1,5 @@
 use crate::enums::Enums;
+use crate::glue;
 use crate::structs::Structs;
 use bumpalo::Bump;
 use fnv::FnvHashMap;
@@ -15,7 +16,7 @@ use roc_mono::layout::{
     cmp_fields, ext_var_is_empty_tag_union, round_up_to_alignment, Builtin, Discriminant, InLayout,
     Layout, LayoutCache, LayoutInterner, TLLayoutInterner, UnionLayout,
 };
-use roc_target::TargetInfo;
+use roc_target::{Architecture, OperatingSystem, TargetInfo};
 use roc_types::{
     subs::{Content, FlatType, GetSubsSlice, Subs, UnionLabels, UnionTags, Variable},
     types::{AliasKind, RecordField},
@@ -49,6 +50,9 @@ pub struct Types {
     /// This is important for declaration order in C; we need to output a
     /// type declaration earlier in the file than where it gets referenced by another type.
     deps: VecMap<TypeId, Vec<TypeId>>,
+
+    /// The platform's top-level values, which the host can call, along with their types
+    entry_points: Vec<(String, TypeId)>,
 }
 
 impl Types {
@@ -59,6 +63,7 @@ impl Types {
             sizes: Vec::new(),
             aligns: Vec::new(),
             deps: VecMap::with_capacity(cap),
+            entry_points: Vec::new(),
         }
     }
 
@@ -458,6 +463,10 @@ impl Types {
         self.types[id.0] = typ;
     }
 
+    pub fn entry_points(&self) -> &[(String, TypeId)] {
+        &self.entry_points
+    }
+
     pub fn ids(&self) -> impl ExactSizeIterator<Item = TypeId> {
         (0..self.types.len()).map(TypeId)
     }
@@ -488,6 +497,219 @@ impl Types {
     }
 }
 
+impl Types {
+    /// The representation of these types which glue scripts written in Roc receive.
+    /// See RocType.roc for its definition.
+    pub fn to_roc_types(&self, target: TargetInfo) -> glue::Types {
+        glue::Types {
+            aligns: self.aligns.iter().copied().collect(),
+            deps: self
+                .deps
+                .iter()
+                .map(|(id, deps)| glue::R16 {
+                    dependsOn: deps.iter().map(|dep| dep.0 as _).collect(),
+                    r#type: id.0 as _,
+                })
+                .collect(),
+            entryPoints: self
+                .entry_points
+                .iter()
+                .map(|(name, id)| glue::R3 {
+                    name: name.as_str().into(),
+                    r#type: id.0 as _,
+                })
+                .collect(),
+            sizes: self.sizes.iter().copied().collect(),
+            types: self.types.iter().map(glue::RocType::from).collect(),
+            target: glue::Target {
+                architecture: match target.architecture {
+                    Architecture::Aarch32 => glue::Architecture::Aarch32,
+                    Architecture::Aarch64 => glue::Architecture::Aarch64,
+                    Architecture::Wasm32 => glue::Architecture::Wasm32,
+                    Architecture::X86_32 => glue::Architecture::X86x32,
+                    Architecture::X86_64 => glue::Architecture::X86x64,
+                },
+                operatingSystem: match target.operating_system {
+                    OperatingSystem::Windows => glue::OperatingSystem::Windows,
+                    OperatingSystem::Unix => glue::OperatingSystem::Unix,
+                    OperatingSystem::Wasi => glue::OperatingSystem::Wasi,
+                },
+            },
+        }
+    }
+}
+
+impl From<&RocType> for glue::RocType {
+    fn from(typ: &RocType) -> Self {
+        match typ {
+            RocType::RocStr => glue::RocType::RocStr,
+            RocType::Bool => glue::RocType::Bool,
+            RocType::RocResult(ok, err) => glue::RocType::RocResult(ok.0 as _, err.0 as _),
+            RocType::Num(num) => glue::RocType::Num(match num {
+                RocNum::I8 => glue::RocNum::I8,
+                RocNum::U8 => glue::RocNum::U8,
+                RocNum::I16 => glue::RocNum::I16,
+                RocNum::U16 => glue::RocNum::U16,
+                RocNum::I32 => glue::RocNum::I32,
+                RocNum::U32 => glue::RocNum::U32,
+                RocNum::I64 => glue::RocNum::I64,
+                RocNum::U64 => glue::RocNum::U64,
+                RocNum::I128 => glue::RocNum::I128,
+                RocNum::U128 => glue::RocNum::U128,
+                RocNum::F32 => glue::RocNum::F32,
+                RocNum::F64 => glue::RocNum::F64,
+                RocNum::Dec => glue::RocNum::Dec,
+            }),
+            RocType::RocList(elem) => glue::RocType::RocList(elem.0 as _),
+            RocType::RocDict(key, value) => glue::RocType::RocDict(key.0 as _, value.0 as _),
+            RocType::RocSet(elem) => glue::RocType::RocSet(elem.0 as _),
+            RocType::RocBox(elem) => glue::RocType::RocBox(elem.0 as _),
+            RocType::TagUnion(tag_union) => glue::RocType::TagUnion(tag_union.into()),
+            RocType::EmptyTagUnion => glue::RocType::EmptyTagUnion,
+            RocType::Struct { name, fields } => glue::RocType::Struct(glue::R2 {
+                fields: fields
+                    .iter()
+                    .map(|(label, id)| glue::R3 {
+                        name: label.as_str().into(),
+                        r#type: id.0 as _,
+                    })
+                    .collect(),
+                name: name.as_str().into(),
+            }),
+            RocType::TagUnionPayload { name, fields } => {
+                glue::RocType::TagUnionPayload(glue::R14 {
+                    fields: fields
+                        .iter()
+                        .map(|(index, id)| glue::R15 {
+                            discriminant: *index as _,
+                            r#type: id.0 as _,
+                        })
+                        .collect(),
+                    name: name.as_str().into(),
+                })
+            }
+            RocType::RecursivePointer(id) => glue::RocType::RecursivePointer(id.0 as _),
+            RocType::Function { name, args, ret } => glue::RocType::Function(glue::R1 {
+                args: args.iter().map(|arg| arg.0 as _).collect(),
+                name: name.as_str().into(),
+                ret: ret.0 as _,
+            }),
+            RocType::Unit => glue::RocType::Unit,
+        }
+    }
+}
+
+impl From<&RocTagUnion> for glue::RocTagUnion {
+    fn from(tag_union: &RocTagUnion) -> Self {
+        match tag_union {
+            RocTagUnion::Enumeration { name, tags, size } => {
+                glue::RocTagUnion::Enumeration(glue::R4 {
+                    name: name.as_str().into(),
+                    size: *size,
+                    tags: tags.iter().map(|tag| tag.as_str().into()).collect(),
+                })
+            }
+            RocTagUnion::NonRecursive {
+                name,
+                tags,
+                discriminant_size,
+                discriminant_offset,
+            } => glue::RocTagUnion::NonRecursive(glue::R6 {
+                discriminantOffset: *discriminant_offset,
+                discriminantSize: *discriminant_size,
+                name: name.as_str().into(),
+                tags: tags
+                    .iter()
+                    .map(|(tag, payload)| glue::R7 {
+                        name: tag.as_str().into(),
+                        payload: match payload {
+                            Some(id) => glue::U1::Some(id.0 as _),
+                            None => glue::U1::None,
+                        },
+                    })
+                    .collect(),
+            }),
+            RocTagUnion::Recursive {
+                name,
+                tags,
+                discriminant_size,
+                discriminant_offset,
+            } => glue::RocTagUnion::Recursive(glue::R11 {
+                discriminantOffset: *discriminant_offset,
+                discriminantSize: *discriminant_size,
+                name: name.as_str().into(),
+                tags: tags
+                    .iter()
+                    .map(|(tag, payload)| glue::R12 {
+                        name: tag.as_str().into(),
+                        payload: match payload {
+                            Some(id) => glue::U4::Some(id.0 as _),
+                            None => glue::U4::None,
+                        },
+                    })
+                    .collect(),
+            }),
+            RocTagUnion::NonNullableUnwrapped {
+                name,
+                tag_name,
+                payload,
+            } => glue::RocTagUnion::NonNullableUnwrapped(glue::R5 {
+                name: name.as_str().into(),
+                payload: payload.0 as _,
+                tagName: tag_name.as_str().into(),
+            }),
+            RocTagUnion::SingleTagStruct {
+                name,
+                tag_name,
+                payload_fields,
+            } => glue::RocTagUnion::SingleTagStruct(glue::R13 {
+                name: name.as_str().into(),
+                payloadFields: payload_fields.iter().map(|id| id.0 as _).collect(),
+                tagName: tag_name.as_str().into(),
+            }),
+            RocTagUnion::NullableWrapped {
+                name,
+                index_of_null_tag,
+                tags,
+                discriminant_size,
+                discriminant_offset,
+            } => glue::RocTagUnion::NullableWrapped(glue::R9 {
+                discriminantOffset: *discriminant_offset,
+                discriminantSize: *discriminant_size,
+                indexOfNullTag: *index_of_null_tag,
+                name: name.as_str().into(),
+                tags: tags
+                    .iter()
+                    .map(|(tag, payload)| glue::R10 {
+                        name: tag.as_str().into(),
+                        payload: match payload {
+                            Some(id) => glue::U3::Some(id.0 as _),
+                            None => glue::U3::None,
+                        },
+                    })
+                    .collect(),
+            }),
+            RocTagUnion::NullableUnwrapped {
+                name,
+                null_tag,
+                non_null_tag,
+                non_null_payload,
+                null_represents_first_tag,
+            } => glue::RocTagUnion::NullableUnwrapped(glue::R8 {
+                name: name.as_str().into(),
+                nonNullPayload: non_null_payload.0 as _,
+                nonNullTag: non_null_tag.as_str().into(),
+                nullTag: null_tag.as_str().into(),
+                whichTagIsNull: if *null_represents_first_tag {
+                    glue::U2::FirstTagIsNull
+                } else {
+                    glue::U2::SecondTagIsNull
+                },
+            }),
+        }
+    }
+}
+
 enum RocTypeOrPending<'a> {
     Type(&'a RocType),
     /// A pending recursive pointer
@@ -687,14 +909,17 @@ impl<'a> Env<'a> {
         }
     }
 
+    /// Takes the names and variables of the platform's top-level values
     pub fn vars_to_types<I>(&mut self, variables: I) -> Types
     where
-        I: Iterator<Item = Variable>,
+        I: Iterator<Item = (String, Variable)>,
     {
         let mut types = Types::with_capacity(variables.size_hint().0);
 
-        for var in variables {
-            self.add_type(var, &mut types);
+        for (name, var) in variables {
+            let id = self.add_type(var, &mut types);
+
+            types.entry_points.push((name, id));
         }
 
         self.resolve_pending_recursive_types(&mut types);
@@ -0,0 +1,508 @@
+use crate::types::{RocNum, RocTagUnion, RocType, TypeId, Types};
+use indexmap::{IndexMap, IndexSet};
+use roc_target::{Architecture, TargetInfo};
+use std::fmt::Write;
+
+pub static HEADER: &[u8] = include_bytes!("../templates/header.zig");
+const INDENT: &str = "    ";
+
+/// For each declared name, the declaration's (comment, value) along with the targets it's
+/// used on. Most declarations are the same on every target, but struct fields are sorted by
+/// alignment, which can differ between e.g. 32-bit and 64-bit targets.
+type Decls = IndexMap<String, IndexMap<(String, String), Vec<TargetInfo>>>;
+
+/// Add a `pub const {name} = {value};` declaration for the given target.
+fn add_decl(
+    decls: &mut Decls,
+    target_info: TargetInfo,
+    name: String,
+    comment: String,
+    value: String,
+) {
+    let values = decls.entry(name).or_default();
+    let targets = values.entry((comment, value)).or_default();
+
+    targets.push(target_info);
+}
+
+/// Emit Zig declarations for the given types, plus the entry points the host can call.
+pub fn emit(types_and_targets: &[(Types, TargetInfo)]) -> String {
+    let mut decls = Decls::default();
+    // Entry points only refer to types by name, so they're the same on every target.
+    let mut entry_points = IndexSet::new();
+
+    for (types, target_info) in types_and_targets {
+        for id in types.sorted_ids() {
+            add_type(*target_info, id, types, &mut decls);
+        }
+
+        for (name, id) in types.entry_points() {
+            entry_points.insert(entry_point(name, *id, types));
+        }
+    }
+
+    let mut buf = String::new();
+
+    for (name, values) in decls {
+        match values.first() {
+            Some(((comment, value), targets))
+                if values.len() == 1 && targets.len() == types_and_targets.len() =>
+            {
+                writeln!(buf, "\n{comment}pub const {name} = {value};").unwrap();
+            }
+            _ => {
+                // This declaration differs between targets, so pick the right one at comptime.
+                let comment = values.keys().map(|(comment, _)| comment.as_str()).next();
+
+                writeln!(
+                    buf,
+                    "\n{}pub const {name} = switch (builtin.cpu.arch) {{",
+                    comment.unwrap_or_default()
+                )
+                .unwrap();
+
+                for ((_, value), targets) in values.iter() {
+                    let arches = targets
+                        .iter()
+                        .map(|target_info| arch_to_str(target_info.architecture))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    let value = value.replace('\n', &format!("\n{INDENT}"));
+
+                    writeln!(buf, "{INDENT}{arches} => {value},").unwrap();
+                }
+
+                writeln!(
+                    buf,
+                    "{INDENT}else => @compileError(\"Roc glue: unsupported target architecture\"),"
+                )
+                .unwrap();
+                writeln!(buf, "}};").unwrap();
+            }
+        }
+    }
+
+    for entry_point in entry_points {
+        buf.push_str(&entry_point);
+    }
+
+    buf
+}
+
+/// The name of the architecture in Zig's `std.Target.Cpu.Arch`
+fn arch_to_str(arch: Architecture) -> &'static str {
+    match arch {
+        Architecture::Aarch32 => ".arm",
+        Architecture::Aarch64 => ".aarch64",
+        Architecture::Wasm32 => ".wasm32",
+        Architecture::X86_32 => ".i386",
+        Architecture::X86_64 => ".x86_64",
+    }
+}
+
+fn add_type(target_info: TargetInfo, id: TypeId, types: &Types, decls: &mut Decls) {
+    match types.get_type(id) {
+        RocType::Struct { name, fields } => {
+            let fields = fields
+                .iter()
+                .map(|(label, field_id)| (escape_kw(label), *field_id));
+
+            add_struct(name, fields, types, target_info, decls)
+        }
+        RocType::TagUnionPayload { name, fields } => {
+            let fields = fields
+                .iter()
+                .map(|(index, field_id)| (format!("f{index}"), *field_id));
+
+            add_struct(name, fields, types, target_info, decls)
+        }
+        RocType::TagUnion(tag_union) => match tag_union {
+            RocTagUnion::Enumeration { name, tags, size } => {
+                add_tag_enum(&escape_kw(name), tags.iter(), *size, target_info, decls);
+            }
+            RocTagUnion::NonRecursive {
+                name,
+                tags,
+                discriminant_size,
+                ..
+            } => {
+                // Empty tag unions can never come up at runtime,
+                // and so don't need declared types.
+                if !tags.is_empty() {
+                    add_tag_union(name, tags, *discriminant_size, types, target_info, decls);
+                }
+            }
+            RocTagUnion::Recursive {
+                name,
+                tags,
+                discriminant_size,
+                ..
+            } => {
+                let comment = "// If there are few enough tags (up to 8 on 64-bit targets, or 4 on 32-bit targets),\n\
+                    // the tag is stored in the low bits of `pointer`.\n\
+                    // Otherwise, it's stored in the heap allocation, after the payload.";
+
+                add_recursive_tag_union(
+                    name,
+                    tags.iter().map(|(tag, _)| tag),
+                    *discriminant_size,
+                    comment,
+                    target_info,
+                    decls,
+                );
+            }
+            RocTagUnion::NullableWrapped {
+                name,
+                index_of_null_tag,
+                tags,
+                discriminant_size,
+                ..
+            } => {
+                let null_tag = &tags[*index_of_null_tag as usize].0;
+                let comment = format!(
+                    "// `pointer` is null for {null_tag}. For the other tags, the tag is stored\n\
+                    // the same way as in other recursive tag unions."
+                );
+
+                add_recursive_tag_union(
+                    name,
+                    tags.iter().map(|(tag, _)| tag),
+                    *discriminant_size,
+                    &comment,
+                    target_info,
+                    decls,
+                );
+            }
+            RocTagUnion::NullableUnwrapped {
+                name,
+                null_tag,
+                non_null_tag,
+                null_represents_first_tag,
+                ..
+            } => {
+                let comment = format!(
+                    "// `pointer` is null for {null_tag}, and points to the payload for {non_null_tag}."
+                );
+                let tags = if *null_represents_first_tag {
+                    [null_tag, non_null_tag]
+                } else {
+                    [non_null_tag, null_tag]
+                };
+
+                add_recursive_tag_union(name, tags, 1, &comment, target_info, decls);
+            }
+            RocTagUnion::NonNullableUnwrapped { name, tag_name, .. } => {
+                let comment = format!("// `pointer` always points to the payload for {tag_name}.");
+
+                add_recursive_tag_union(name, [tag_name], 1, &comment, target_info, decls);
+            }
+            RocTagUnion::SingleTagStruct {
+                name,
+                payload_fields,
+                ..
+            } => {
+                let fields = payload_fields
+                    .iter()
+                    .enumerate()
+                    .map(|(index, field_id)| (format!("f{index}"), *field_id));
+
+                add_struct(name, fields, types, target_info, decls)
+            }
+        },
+        // These types don't need to be declared in Zig, or are declared in the header.
+        RocType::Unit
+        | RocType::EmptyTagUnion
+        | RocType::Num(_)
+        | RocType::Bool
+        | RocType::RocStr
+        | RocType::RocDict(_, _)
+        | RocType::RocSet(_)
+        | RocType::RocList(_)
+        | RocType::RocBox(_)
+        | RocType::RocResult(_, _) => {}
+        RocType::RecursivePointer { .. } => {
+            // This is recursively pointing to a type that should already have been added,
+            // so no extra work needs to happen.
+        }
+        RocType::Function { .. } => {
+            // Functions are declared as entry points instead.
+        }
+    }
+}
+
+fn add_struct(
+    name: &str,
+    fields: impl Iterator<Item = (String, TypeId)>,
+    types: &Types,
+    target_info: TargetInfo,
+    decls: &mut Decls,
+) {
+    let mut value = String::from("extern struct {\n");
+
+    for (label, field_id) in fields {
+        // Zero-sized fields are left out, to match the C layout.
+        if !is_zero_sized(field_id, types) {
+            writeln!(value, "{INDENT}{label}: {},", type_name(field_id, types)).unwrap();
+        }
+    }
+
+    value.push('}');
+
+    add_decl(decls, target_info, escape_kw(name), String::new(), value);
+}
+
+fn add_tag_union(
+    name: &str,
+    tags: &[(String, Option<TypeId>)],
+    discriminant_size: u32,
+    types: &Types,
+    target_info: TargetInfo,
+    decls: &mut Decls,
+) {
+    let name = escape_kw(name);
+    let tag_type = format!("{name}Tag");
+    let mut value = String::from("extern struct {\n");
+
+    writeln!(value, "{INDENT}payload: extern union {{").unwrap();
+
+    for (tag_name, opt_payload_id) in tags {
+        // Tags without a payload are fully described by the tag
+        if let Some(payload_id) = opt_payload_id {
+            if !is_zero_sized(*payload_id, types) {
+                writeln!(
+                    value,
+                    "{INDENT}{INDENT}{}: {},",
+                    escape_kw(tag_name),
+                    type_name(*payload_id, types)
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    writeln!(value, "{INDENT}}},").unwrap();
+
+    if discriminant_size > 0 {
+        writeln!(value, "{INDENT}tag: {tag_type},").unwrap();
+    }
+
+    value.push('}');
+
+    add_decl(decls, target_info, name, String::new(), value);
+
+    if discriminant_size > 0 {
+        add_tag_enum(
+            &tag_type,
+            tags.iter().map(|(tag, _)| tag),
+            discriminant_size,
+            target_info,
+            decls,
+        );
+    }
+}
+
+fn add_recursive_tag_union<'a>(
+    name: &str,
+    tags: impl IntoIterator<Item = &'a String>,
+    discriminant_size: u32,
+    comment: &str,
+    target_info: TargetInfo,
+    decls: &mut Decls,
+) {
+    let name = escape_kw(name);
+    let comment =
+        format!("// A recursive tag union, which points to its payload on the heap.\n{comment}\n");
+    let value = format!("extern struct {{\n{INDENT}pointer: ?*anyopaque,\n}}");
+
+    add_decl(decls, target_info, name.clone(), comment, value);
+    add_tag_enum(
+        &format!("{name}Tag"),
+        tags,
+        discriminant_size.max(1),
+        target_info,
+        decls,
+    );
+}
+
+/// Tags are numbered in alphabetical order, which is the order we get them in.
+fn add_tag_enum<'a>(
+    name: &str,
+    tags: impl IntoIterator<Item = &'a String>,
+    size: u32,
+    target_info: TargetInfo,
+    decls: &mut Decls,
+) {
+    let mut value = format!("enum({}) {{\n", discriminant_type(size));
+
+    for (index, tag_name) in tags.into_iter().enumerate() {
+        writeln!(value, "{INDENT}{} = {index},", escape_kw(tag_name)).unwrap();
+    }
+
+    value.push('}');
+
+    add_decl(decls, target_info, name.to_string(), String::new(), value);
+}
+
+fn entry_point(name: &str, id: TypeId, types: &Types) -> String {
+    let (arg_ids, ret_id) = match types.get_type(id) {
+        RocType::Function { args, ret, .. } => (args.as_slice(), *ret),
+        _ => (&[][..], id),
+    };
+
+    // The "generic" entry point always returns by pointer, and takes strings and lists by pointer.
+    let mut params = Vec::with_capacity(arg_ids.len() + 1);
+
+    if !is_zero_sized(ret_id, types) {
+        params.push(format!("ret: *{}", type_name(ret_id, types)));
+    }
+
+    for (index, arg_id) in arg_ids.iter().enumerate() {
+        if is_zero_sized(*arg_id, types) {
+            continue;
+        }
+
+        let arg_type = type_name(*arg_id, types);
+
+        if is_passed_by_pointer(*arg_id, types) {
+            params.push(format!("arg{index}: *const {arg_type}"));
+        } else {
+            params.push(format!("arg{index}: {arg_type}"));
+        }
+    }
+
+    format!(
+        "\npub extern fn roc__{name}_1_exposed_generic({}) void;\n",
+        params.join(", ")
+    )
+}
+
+fn type_name(id: TypeId, types: &Types) -> String {
+    match types.get_type(id) {
+        RocType::Unit | RocType::EmptyTagUnion => "void".to_string(),
+        RocType::RocStr => "RocStr".to_string(),
+        RocType::Bool => "bool".to_string(),
+        RocType::Num(RocNum::U8) => "u8".to_string(),
+        RocType::Num(RocNum::U16) => "u16".to_string(),
+        RocType::Num(RocNum::U32) => "u32".to_string(),
+        RocType::Num(RocNum::U64) => "u64".to_string(),
+        RocType::Num(RocNum::U128) => "u128".to_string(),
+        RocType::Num(RocNum::I8) => "i8".to_string(),
+        RocType::Num(RocNum::I16) => "i16".to_string(),
+        RocType::Num(RocNum::I32) => "i32".to_string(),
+        RocType::Num(RocNum::I64) => "i64".to_string(),
+        RocType::Num(RocNum::I128) => "i128".to_string(),
+        RocType::Num(RocNum::F32) => "f32".to_string(),
+        RocType::Num(RocNum::F64) => "f64".to_string(),
+        RocType::Num(RocNum::Dec) => "RocDec".to_string(),
+        RocType::RocList(elem_id) => format!("RocList({})", type_name(*elem_id, types)),
+        RocType::RocDict(_, _) => "RocDict".to_string(),
+        RocType::RocSet(_) => "RocSet".to_string(),
+        RocType::RocBox(elem_id) => format!("*{}", type_name(*elem_id, types)),
+        RocType::RocResult(ok_id, err_id) => format!(
+            "RocResult({}, {})",
+            type_name(*ok_id, types),
+            type_name(*err_id, types)
+        ),
+        RocType::Struct { name, .. }
+        | RocType::TagUnionPayload { name, .. }
+        | RocType::TagUnion(RocTagUnion::NonRecursive { name, .. })
+        | RocType::TagUnion(RocTagUnion::Recursive { name, .. })
+        | RocType::TagUnion(RocTagUnion::Enumeration { name, .. })
+        | RocType::TagUnion(RocTagUnion::NullableWrapped { name, .. })
+        | RocType::TagUnion(RocTagUnion::NullableUnwrapped { name, .. })
+        | RocType::TagUnion(RocTagUnion::NonNullableUnwrapped { name, .. })
+        | RocType::TagUnion(RocTagUnion::SingleTagStruct { name, .. }) => escape_kw(name),
+        RocType::RecursivePointer(content) => type_name(*content, types),
+        // Closures aren't supported yet
+        RocType::Function { .. } => "?*anyopaque".to_string(),
+    }
+}
+
+fn discriminant_type(size: u32) -> &'static str {
+    match size {
+        1 => "u8",
+        2 => "u16",
+        4 => "u32",
+        8 => "u64",
+        _ => unreachable!("Invalid discriminant size {}", size),
+    }
+}
+
+fn is_zero_sized(id: TypeId, types: &Types) -> bool {
+    matches!(types.get_type(id), RocType::Unit | RocType::EmptyTagUnion)
+}
+
+fn is_passed_by_pointer(id: TypeId, types: &Types) -> bool {
+    matches!(
+        types.get_type(id),
+        RocType::RocStr | RocType::RocList(_) | RocType::RocDict(_, _) | RocType::RocSet(_)
+    )
+}
+
+const RESERVED_KEYWORDS: &[&str] = &[
+    "addrspace",
+    "align",
+    "allowzero",
+    "and",
+    "anyframe",
+    "anytype",
+    "asm",
+    "async",
+    "await",
+    "break",
+    "callconv",
+    "catch",
+    "comptime",
+    "const",
+    "continue",
+    "defer",
+    "else",
+    "enum",
+    "errdefer",
+    "error",
+    "export",
+    "extern",
+    "fn",
+    "for",
+    "if",
+    "inline",
+    "linksection",
+    "noalias",
+    "noinline",
+    "nosuspend",
+    "opaque",
+    "or",
+    "orelse",
+    "packed",
+    "pub",
+    "resume",
+    "return",
+    "struct",
+    "suspend",
+    "switch",
+    "test",
+    "threadlocal",
+    "try",
+    "union",
+    "unreachable",
+    "usingnamespace",
+    "var",
+    "volatile",
+    "while",
+    "true",
+    "false",
+    "null",
+    "undefined",
+    "type",
+    "void",
+    "bool",
+];
+
+/// Quote any names that are reserved words in Zig, e.g. `error` => `@"error"`
+fn escape_kw(input: &str) -> String {
+    if RESERVED_KEYWORDS.contains(&input) {
+        format!("@\"{input}\"")
+    } else {
+        input.to_string()
+    }
+}
@@ -0,0 +1,48 @@
+// ⚠️ GENERATED CODE ⚠️ - this entire file was generated by the `roc glue` CLI command
+
+#pragma once
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+// Short strings are stored inline in these three words, rather than on the heap.
+// See roc_std's RocStr for the details of the representation.
+typedef struct RocStr {
+    char *bytes;
+    size_t length;
+    size_t capacity;
+} RocStr;
+
+// The type of the elements is noted in a comment wherever a list is used.
+typedef struct RocList {
+    void *elements;
+    size_t length;
+    size_t capacity;
+} RocList;
+
+// Dicts and Sets are stored as a list of their entries.
+typedef RocList RocDict;
+typedef RocList RocSet;
+
+#if defined(__SIZEOF_INT128__)
+typedef __int128_t RocI128;
+typedef __uint128_t RocU128;
+#else
+// This compiler has no 128-bit integer type (e.g. MSVC), so store the two 64-bit halves,
+// least significant first. Roc aligns 128-bit integers to 16 bytes on every target.
+typedef struct RocI128 {
+    _Alignas(16) uint64_t lo;
+    int64_t hi;
+} RocI128;
+
+typedef struct RocU128 {
+    _Alignas(16) uint64_t lo;
+    uint64_t hi;
+} RocU128;
+#endif
+
+// A fixed-point decimal number, with 18 digits after the decimal point.
+typedef struct RocDec {
+    RocI128 num;
+} RocDec;
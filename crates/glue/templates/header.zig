@@ -0,0 +1,47 @@
+// ⚠️ GENERATED CODE ⚠️ - this entire file was generated by the `roc glue` CLI command
+
+const builtin = @import("builtin");
+
+// Short strings are stored inline in these three words, rather than on the heap.
+// See roc_std's RocStr for the details of the representation.
+pub const RocStr = extern struct {
+    bytes: ?[*]u8,
+    length: usize,
+    capacity: usize,
+};
+
+pub fn RocList(comptime T: type) type {
+    return extern struct {
+        elements: ?[*]T,
+        length: usize,
+        capacity: usize,
+    };
+}
+
+// Dicts and Sets are stored as a list of their entries.
+pub const RocDict = extern struct {
+    elements: ?*anyopaque,
+    length: usize,
+    capacity: usize,
+};
+pub const RocSet = RocDict;
+
+pub fn RocResult(comptime T: type, comptime E: type) type {
+    return extern struct {
+        payload: extern union {
+            ok: T,
+            err: E,
+        },
+        tag: RocResultTag,
+    };
+}
+
+pub const RocResultTag = enum(u8) {
+    err = 0,
+    ok = 1,
+};
+
+// A fixed-point decimal number, with 18 digits after the decimal point.
+pub const RocDec = extern struct {
+    num: i128,
+};
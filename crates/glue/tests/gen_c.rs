@@ -0,0 +1,177 @@
+#[macro_use]
+extern crate pretty_assertions;
+
+#[macro_use]
+extern crate indoc;
+
+mod helpers;
+
+#[cfg(test)]
+mod test_gen_c {
+    use crate::helpers::generate_bindings_with;
+    use roc_glue::c_glue;
+    use std::fs;
+    use std::process::Command;
+
+    fn generate_bindings(decl_src: &str) -> String {
+        generate_bindings_with(decl_src, c_glue::emit)
+    }
+
+    #[test]
+    fn basic_record_aliased() {
+        let module = indoc!(
+            r#"
+            MyRcd : { a : U64, b : I128 }
+
+            main : MyRcd
+            main = { a: 1u64, b: 2i128 }
+        "#
+        );
+
+        assert_eq!(
+            generate_bindings(module)
+                .strip_prefix('\n')
+                .unwrap_or_default(),
+            indoc!(
+                r#"
+                typedef struct MyRcd {
+                    RocI128 b;
+                    uint64_t a;
+                } MyRcd;
+
+                extern void roc__main_1_exposed_generic(MyRcd *ret);
+            "#
+            )
+        );
+    }
+
+    #[test]
+    fn nested_record_aliased() {
+        let module = indoc!(
+            r#"
+            Outer : { x : Inner, y : Str, z : List U8 }
+
+            Inner : { a : U16, b : F32 }
+
+            main : Outer
+            main = { x: { a: 5, b: 24 }, y: "foo", z: [1, 2] }
+        "#
+        );
+
+        assert_eq!(
+            generate_bindings(module)
+                .strip_prefix('\n')
+                .unwrap_or_default(),
+            indoc!(
+                r#"
+                typedef struct Inner {
+                    float b;
+                    uint16_t a;
+                } Inner;
+
+                #if defined(__arm__) || defined(_M_ARM) || defined(__wasm32__) || defined(__i386__) || defined(_M_IX86)
+                typedef struct Outer {
+                    Inner x;
+                    RocStr y;
+                    RocList z; // of uint8_t
+                } Outer;
+                #elif defined(__aarch64__) || defined(_M_ARM64) || defined(__x86_64__) || defined(_M_X64)
+                typedef struct Outer {
+                    RocStr y;
+                    RocList z; // of uint8_t
+                    Inner x;
+                } Outer;
+                #else
+                #error "Roc glue: unsupported target architecture"
+                #endif
+
+                extern void roc__main_1_exposed_generic(Outer *ret);
+            "#
+            )
+        );
+    }
+
+    #[test]
+    fn enumeration() {
+        let module = indoc!(
+            r#"
+            MyEnum : [Foo, Bar, Baz]
+
+            main : MyEnum
+            main = Foo
+        "#
+        );
+
+        assert_eq!(
+            generate_bindings(module)
+                .strip_prefix('\n')
+                .unwrap_or_default(),
+            indoc!(
+                r#"
+                typedef uint8_t MyEnum;
+
+                enum {
+                    MyEnum_Bar = 0,
+                    MyEnum_Baz = 1,
+                    MyEnum_Foo = 2,
+                };
+
+                extern void roc__main_1_exposed_generic(MyEnum *ret);
+            "#
+            )
+        );
+    }
+
+    #[test]
+    fn compiles() {
+        let module = indoc!(
+            r#"
+            Shape : [Circle F64, Rect { w : F64, h : F64 }, Empty]
+
+            Color : [Red, Green, Blue]
+
+            Rcd : {
+                big : I128,
+                money : Dec,
+                shape : Shape,
+                color : Color,
+                first : Result Str U8,
+                second : Result Str U8,
+                names : List Str,
+            }
+
+            main : Rcd
+            main = {
+                big: 1,
+                money: 1.5,
+                shape: Empty,
+                color: Red,
+                first: Ok "",
+                second: Err 0,
+                names: [],
+            }
+        "#
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut glue = String::from_utf8(c_glue::HEADER.to_vec()).unwrap();
+
+        glue.push_str(&generate_bindings(module));
+
+        fs::write(dir.path().join("glue.h"), glue).unwrap();
+        fs::write(dir.path().join("main.c"), "#include \"glue.h\"\n").unwrap();
+
+        let cc = std::env::var("CC").unwrap_or_else(|_| "cc".into());
+        let output = Command::new(cc)
+            .current_dir(dir.path())
+            .args(["-fsyntax-only", "-std=c11", "-Wall", "-Werror", "main.c"])
+            .output()
+            .expect("failed to run the C compiler");
+
+        assert!(
+            output.status.success(),
+            "The generated C glue failed to compile:\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
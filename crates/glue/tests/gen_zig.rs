@@ -0,0 +1,204 @@
+#[macro_use]
+extern crate pretty_assertions;
+
+#[macro_use]
+extern crate indoc;
+
+mod helpers;
+
+#[cfg(test)]
+mod test_gen_zig {
+    use crate::helpers::generate_bindings_with;
+    use roc_glue::zig_glue;
+    use std::fs;
+    use std::process::Command;
+
+    fn generate_bindings(decl_src: &str) -> String {
+        generate_bindings_with(decl_src, zig_glue::emit)
+    }
+
+    #[test]
+    fn basic_record_aliased() {
+        let module = indoc!(
+            r#"
+            MyRcd : { a : U64, b : I128 }
+
+            main : MyRcd
+            main = { a: 1u64, b: 2i128 }
+        "#
+        );
+
+        assert_eq!(
+            generate_bindings(module)
+                .strip_prefix('\n')
+                .unwrap_or_default(),
+            indoc!(
+                r#"
+                pub const MyRcd = extern struct {
+                    b: i128,
+                    a: u64,
+                };
+
+                pub extern fn roc__main_1_exposed_generic(ret: *MyRcd) void;
+            "#
+            )
+        );
+    }
+
+    #[test]
+    fn nested_record_aliased() {
+        let module = indoc!(
+            r#"
+            Outer : { x : Inner, y : Str, z : List U8 }
+
+            Inner : { a : U16, b : F32 }
+
+            main : Outer
+            main = { x: { a: 5, b: 24 }, y: "foo", z: [1, 2] }
+        "#
+        );
+
+        assert_eq!(
+            generate_bindings(module)
+                .strip_prefix('\n')
+                .unwrap_or_default(),
+            indoc!(
+                r#"
+                pub const Inner = extern struct {
+                    b: f32,
+                    a: u16,
+                };
+
+                pub const Outer = switch (builtin.cpu.arch) {
+                    .arm, .wasm32, .i386 => extern struct {
+                        x: Inner,
+                        y: RocStr,
+                        z: RocList(u8),
+                    },
+                    .aarch64, .x86_64 => extern struct {
+                        y: RocStr,
+                        z: RocList(u8),
+                        x: Inner,
+                    },
+                    else => @compileError("Roc glue: unsupported target architecture"),
+                };
+
+                pub extern fn roc__main_1_exposed_generic(ret: *Outer) void;
+            "#
+            )
+        );
+    }
+
+    #[test]
+    fn enumeration() {
+        let module = indoc!(
+            r#"
+            MyEnum : [Foo, Bar, Baz]
+
+            main : MyEnum
+            main = Foo
+        "#
+        );
+
+        assert_eq!(
+            generate_bindings(module)
+                .strip_prefix('\n')
+                .unwrap_or_default(),
+            indoc!(
+                r#"
+                pub const MyEnum = enum(u8) {
+                    Bar = 0,
+                    Baz = 1,
+                    Foo = 2,
+                };
+
+                pub extern fn roc__main_1_exposed_generic(ret: *MyEnum) void;
+            "#
+            )
+        );
+    }
+
+    #[test]
+    fn compiles() {
+        let module = indoc!(
+            r#"
+            Shape : [Circle F64, Rect { w : F64, h : F64 }, Empty]
+
+            Color : [Red, Green, Blue]
+
+            Rcd : {
+                big : I128,
+                money : Dec,
+                shape : Shape,
+                color : Color,
+                result : Result Str U8,
+                names : List Str,
+            }
+
+            main : Rcd
+            main = {
+                big: 1,
+                money: 1.5,
+                shape: Empty,
+                color: Red,
+                result: Ok "",
+                names: [],
+            }
+        "#
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut glue = String::from_utf8(zig_glue::HEADER.to_vec()).unwrap();
+
+        glue.push_str(&generate_bindings(module));
+
+        fs::write(dir.path().join("glue.zig"), glue).unwrap();
+        // Zig only analyzes declarations that are used, so use all of them.
+        fs::write(
+            dir.path().join("main.zig"),
+            indoc!(
+                r#"
+                const glue = @import("glue.zig");
+
+                comptime {
+                    inline for (@typeInfo(glue).Struct.decls) |decl| {
+                        if (decl.is_pub) {
+                            const value = @field(glue, decl.name);
+
+                            if (@TypeOf(value) == type) {
+                                _ = @sizeOf(value);
+                            } else {
+                                _ = value;
+                            }
+                        }
+                    }
+                }
+            "#
+            ),
+        )
+        .unwrap();
+
+        let zig = std::env::var("ROC_ZIG").unwrap_or_else(|_| "zig".into());
+
+        // Check every target, since struct layouts can differ between them.
+        for target in [
+            "aarch64-freestanding",
+            "arm-freestanding",
+            "i386-freestanding",
+            "wasm32-freestanding",
+            "x86_64-freestanding",
+        ] {
+            let output = Command::new(&zig)
+                .current_dir(dir.path())
+                .args(["build-obj", "-fno-emit-bin", "-target", target, "main.zig"])
+                .output()
+                .expect("failed to run zig");
+
+            assert!(
+                output.status.success(),
+                "The generated Zig glue failed to compile for {target}:\n{}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+    }
+}
@@ -0,0 +1,27 @@
+app "struct-names"
+    packages { pf: "../../src/RocType.roc" }
+    imports []
+    provides [makeGlue] to pf
+
+# Writes one file per target architecture, listing the names of the platform's record types.
+makeGlue = \typesByArch ->
+    typesByArch
+    |> List.map \types ->
+        content =
+            types.types
+            |> List.keepOks \type ->
+                when type is
+                    Struct { name } -> Ok name
+                    _ -> Err NotAStruct
+            |> Str.joinWith "\n"
+
+        { name: "\(archName types.target.architecture)/structs.txt", content }
+    |> Ok
+
+archName = \arch ->
+    when arch is
+        Aarch32 -> "aarch32"
+        Aarch64 -> "aarch64"
+        Wasm32 -> "wasm32"
+        X86x32 -> "x86x32"
+        X86x64 -> "x86x64"
@@ -1,6 +1,8 @@
 use roc_glue::load::{load_types, IgnoreErrors};
 use roc_glue::rust_glue;
+use roc_glue::types::Types;
 use roc_load::Threading;
+use roc_target::TargetInfo;
 use std::env;
 use std::fs::File;
 use std::io::Write;
@@ -8,6 +10,15 @@ use std::path::PathBuf;
 
 #[allow(dead_code)]
 pub fn generate_bindings(decl_src: &str) -> String {
+    generate_bindings_with(decl_src, rust_glue::emit)
+}
+
+/// Like `generate_bindings`, but with the given glue backend (e.g. `c_glue::emit`)
+#[allow(dead_code)]
+pub fn generate_bindings_with(
+    decl_src: &str,
+    emit: fn(&[(Types, TargetInfo)]) -> String,
+) -> String {
     use tempfile::tempdir;
 
     let mut src = indoc!(
@@ -45,7 +56,7 @@ pub fn generate_bindings(decl_src: &str) -> String {
         result.expect("had problems loading")
     };
 
-    rust_glue::emit(&pairs)
+    emit(&pairs)
 }
 
 #[allow(dead_code)]
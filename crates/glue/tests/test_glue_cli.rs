@@ -11,7 +11,7 @@ mod helpers;
 
 #[cfg(test)]
 mod glue_cli_run {
-    use crate::helpers::fixtures_dir;
+    use crate::helpers::{fixtures_dir, root_dir};
     use cli_utils::helpers::{run_glue, run_roc, Out};
     use std::fs;
     use std::path::Path;
@@ -129,6 +129,58 @@ mod glue_cli_run {
         "#),
     }
 
+    #[test]
+    fn glue_script() {
+        let platform_module_path = fixtures_dir("basic-record").join("platform.roc");
+        let script_path = fixtures_dir("")
+            .parent()
+            .unwrap()
+            .join("glue-scripts")
+            .join("struct-names.roc");
+        let out_dir = tempfile::tempdir().unwrap();
+
+        let glue_out = run_glue([
+            "glue".to_string(),
+            script_path.to_str().unwrap().to_string(),
+            platform_module_path.to_str().unwrap().to_string(),
+            out_dir.path().to_str().unwrap().to_string(),
+        ]);
+
+        assert!(glue_out.status.success(), "bad status {:?}", glue_out);
+
+        for arch in ["aarch32", "aarch64", "wasm32", "x86x32", "x86x64"] {
+            let path = out_dir.path().join(arch).join("structs.txt");
+            let contents = fs::read_to_string(&path)
+                .unwrap_or_else(|err| panic!("Unable to read {}: {}", path.display(), err));
+
+            assert_eq!(contents, "MyRcd");
+        }
+    }
+
+    /// glue.rs is generated from RocType.roc, so it must not be edited by hand.
+    #[test]
+    fn glue_rs_is_up_to_date() {
+        let src_dir = root_dir().join("crates").join("glue").join("src");
+        let out_dir = tempfile::tempdir().unwrap();
+        let glue_file = out_dir.path().join("glue.rs");
+
+        let glue_out = run_glue([
+            "glue".to_string(),
+            src_dir.join("RocType.roc").to_str().unwrap().to_string(),
+            glue_file.to_str().unwrap().to_string(),
+        ]);
+
+        assert!(glue_out.status.success(), "bad status {:?}", glue_out);
+
+        let expected = fs::read_to_string(&glue_file).unwrap();
+        let actual = fs::read_to_string(src_dir.join("glue.rs")).unwrap();
+
+        assert!(
+            actual == expected,
+            "crates/glue/src/glue.rs is out of date. Regenerate it with:\n\n\troc glue crates/glue/src/RocType.roc crates/glue/src/glue.rs"
+        );
+    }
+
     fn check_for_tests(all_fixtures: &mut roc_collections::VecSet<String>) {
         use roc_collections::VecSet;
 
@@ -0,0 +1,377 @@
+//! Runs the compiler on a document and answers questions about the result.
+use crate::convert::{path_to_uri, Document};
+use bumpalo::Bump;
+use roc_can::traverse::{find_closest_type_at, find_symbol_at, find_symbol_definition};
+use roc_fmt::def::fmt_defs;
+use roc_fmt::module::fmt_module;
+use roc_fmt::{Ast, Buf};
+use roc_load::{LoadedModule, LoadingProblem};
+use roc_module::symbol::ModuleId;
+use roc_packaging::cache::RocCacheDir;
+use roc_parse::module::{self, module_defs};
+use roc_parse::parser::{EExpr, EHeader, Parser, SyntaxError};
+use roc_parse::state::State;
+use roc_problem::Severity;
+use roc_region::all::{Position, Region};
+use roc_reporting::report::{
+    can_problem, type_problem, CiWrite, RenderTarget, Report, RocDocAllocator, DEFAULT_PALETTE,
+};
+use roc_target::TargetInfo;
+use roc_types::pretty_print::{name_and_print_var, DebugPrint};
+use serde_json::{json, Value};
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+/// https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#diagnosticSeverity
+const SEVERITY_ERROR: u8 = 1;
+const SEVERITY_WARNING: u8 = 2;
+
+/// The result of checking a document (and everything it imports).
+pub struct Analysis {
+    /// `None` if we couldn't get as far as type checking, e.g. because of a syntax error
+    loaded: Option<LoadedModule>,
+
+    /// Diagnostics for every module we looked at, including the ones that have none, so that
+    /// stale diagnostics get cleared.
+    pub diagnostics: Vec<(PathBuf, Vec<Value>)>,
+}
+
+pub fn analyze(path: &Path, src: &str, roc_cache_dir: RocCacheDir<'_>) -> Analysis {
+    let result = catch_panic(|| analyze_help(path, src, roc_cache_dir));
+
+    result.unwrap_or_else(|message| {
+        let message = format!(
+            "The compiler crashed while checking this module: {}",
+            message
+        );
+
+        Analysis {
+            loaded: None,
+            diagnostics: vec![(
+                path.to_path_buf(),
+                vec![load_diagnostic(src, None, message)],
+            )],
+        }
+    })
+}
+
+/// Run a part of the compiler, turning a panic into an error so that a compiler bug in one
+/// request doesn't take down the whole server.
+pub fn catch_panic<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| panic_message(&*payload))
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => match payload.downcast_ref::<String>() {
+            Some(message) => message.clone(),
+            None => "unknown panic".to_string(),
+        },
+    }
+}
+
+fn analyze_help(path: &Path, src: &str, roc_cache_dir: RocCacheDir<'_>) -> Analysis {
+    let arena = Bump::new();
+    let src_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+    let result = roc_load::load_and_typecheck_str(
+        &arena,
+        path.to_path_buf(),
+        arena.alloc_str(src),
+        src_dir,
+        TargetInfo::default_x86_64(),
+        RenderTarget::Generic,
+        roc_cache_dir,
+        DEFAULT_PALETTE,
+    );
+
+    match result {
+        Ok(loaded) => {
+            let diagnostics = module_diagnostics(&loaded);
+
+            Analysis {
+                loaded: Some(loaded),
+                diagnostics,
+            }
+        }
+        Err(problem) => {
+            let message = match problem {
+                LoadingProblem::FormattedReport(report) => report,
+                other => format!("{:?}", other),
+            };
+
+            // The loader only gives us the rendered report. If it's about a syntax error in this
+            // document, parsing the document again tells us where the error is.
+            let arena = Bump::new();
+            let position = match parse_all(&arena, src) {
+                Ok(_) => None,
+                Err(problem) => syntax_error_position(&problem),
+            };
+
+            Analysis {
+                loaded: None,
+                diagnostics: vec![(
+                    path.to_path_buf(),
+                    vec![load_diagnostic(src, position, message)],
+                )],
+            }
+        }
+    }
+}
+
+/// A diagnostic for a problem that stopped the loader, at `position` or else at the start of
+/// the document
+fn load_diagnostic(src: &str, position: Option<Position>, message: String) -> Value {
+    let region = position.map(Region::from_pos).unwrap_or_default();
+
+    json!({
+        "range": Document::new(src).to_lsp_range(region),
+        "severity": SEVERITY_ERROR,
+        "source": "roc",
+        "message": message,
+    })
+}
+
+fn syntax_error_position(problem: &SyntaxError) -> Option<Position> {
+    match problem {
+        SyntaxError::Unexpected(region)
+        | SyntaxError::Eof(region)
+        | SyntaxError::ReservedKeyword(region)
+        | SyntaxError::ArgumentsBeforeEquals(region) => Some(region.start()),
+        SyntaxError::Expr(problem, _) => Some(expr_error_position(problem)),
+        SyntaxError::Header(problem) => Some(header_error_position(problem)),
+        SyntaxError::NotEndOfFile(position) => Some(*position),
+        SyntaxError::OutdentedTooFar
+        | SyntaxError::TooManyLines
+        | SyntaxError::InvalidPattern
+        | SyntaxError::BadUtf8
+        | SyntaxError::NotYetImplemented(_)
+        | SyntaxError::Todo
+        | SyntaxError::Type(_)
+        | SyntaxError::Pattern(_)
+        | SyntaxError::Space(_) => None,
+    }
+}
+
+fn expr_error_position(problem: &EExpr) -> Position {
+    use EExpr::*;
+
+    match problem {
+        DefMissingFinalExpr2(problem, _) => expr_error_position(problem),
+        TrailingOperator(position)
+        | Start(position)
+        | End(position)
+        | BadExprEnd(position)
+        | Space(_, position)
+        | Dot(position)
+        | Access(position)
+        | UnaryNot(position)
+        | UnaryNegate(position)
+        | BadOperator(_, position)
+        | DefMissingFinalExpr(position)
+        | Type(_, position)
+        | Pattern(_, position)
+        | Ability(_, position)
+        | IndentDefBody(position)
+        | IndentEquals(position)
+        | IndentAnnotation(position)
+        | Equals(position)
+        | Colon(position)
+        | DoubleColon(position)
+        | Ident(position)
+        | ElmStyleFunction(_, position)
+        | MalformedPattern(position)
+        | QualifiedTag(position)
+        | BackpassComma(position)
+        | BackpassArrow(position)
+        | When(_, position)
+        | If(_, position)
+        | Expect(_, position)
+        | Dbg(_, position)
+        | Closure(_, position)
+        | Underscore(position)
+        | Crash(position)
+        | InParens(_, position)
+        | Record(_, position)
+        | Str(_, position)
+        | Number(_, position)
+        | List(_, position)
+        | IndentStart(position)
+        | IndentEnd(position) => *position,
+    }
+}
+
+fn header_error_position(problem: &EHeader) -> Position {
+    use EHeader::*;
+
+    match problem {
+        InconsistentModuleName(region) => region.start(),
+        Provides(_, position)
+        | Exposes(_, position)
+        | Imports(_, position)
+        | Requires(_, position)
+        | Packages(_, position)
+        | Generates(_, position)
+        | GeneratesWith(_, position)
+        | Space(_, position)
+        | Start(position)
+        | ModuleName(position)
+        | AppName(_, position)
+        | PackageName(_, position)
+        | PlatformName(_, position)
+        | IndentStart(position) => *position,
+    }
+}
+
+fn module_diagnostics(loaded: &LoadedModule) -> Vec<(PathBuf, Vec<Value>)> {
+    let mut all_diagnostics = Vec::new();
+
+    for (module_id, (path, src)) in loaded.sources.iter() {
+        if module_id.is_builtin() {
+            continue;
+        }
+
+        let doc = Document::new(src);
+        let src_lines: Vec<&str> = src.split('\n').collect();
+        let alloc = RocDocAllocator::new(&src_lines, *module_id, &loaded.interns);
+        let mut diagnostics = Vec::new();
+
+        for problem in loaded.can_problems.get(module_id).into_iter().flatten() {
+            let region = problem.region();
+            let report = can_problem(&alloc, &doc.lines, path.clone(), problem.clone());
+
+            diagnostics.push(diagnostic(&doc, region, report));
+        }
+
+        for problem in loaded.type_problems.get(module_id).into_iter().flatten() {
+            let region = problem.region();
+
+            if let Some(report) = type_problem(&alloc, &doc.lines, path.clone(), problem.clone()) {
+                diagnostics.push(diagnostic(&doc, region, report));
+            }
+        }
+
+        all_diagnostics.push((path.clone(), diagnostics));
+    }
+
+    all_diagnostics
+}
+
+fn diagnostic(doc: &Document, region: Option<Region>, report: Report) -> Value {
+    let mut message = String::new();
+
+    // Only the body of the report; editors show the file and position themselves.
+    report
+        .doc
+        .1
+        .render_raw(70, &mut CiWrite::new(&mut message))
+        .expect("<buffer is not a utf-8 encoded string>");
+
+    let severity = match report.severity {
+        Severity::RuntimeError => SEVERITY_ERROR,
+        Severity::Warning => SEVERITY_WARNING,
+    };
+
+    json!({
+        "range": doc.to_lsp_range(region.unwrap_or_default()),
+        "severity": severity,
+        "code": report.title,
+        "source": "roc",
+        "message": message.trim_end(),
+    })
+}
+
+impl Analysis {
+    /// The type of the innermost expression or pattern under the cursor
+    pub fn hover(&mut self, src: &str, position: &Value) -> Option<Value> {
+        let loaded = self.loaded.as_mut()?;
+        let doc = Document::new(src);
+        let position = doc.roc_position(position)?;
+
+        let decls = loaded.declarations_by_id.get(&loaded.module_id)?;
+        let (region, var) = find_closest_type_at(position, decls)?;
+
+        let type_str = name_and_print_var(
+            var,
+            loaded.solved.inner_mut(),
+            loaded.module_id,
+            &loaded.interns,
+            DebugPrint::NOTHING,
+        );
+
+        Some(json!({
+            "contents": {
+                "kind": "markdown",
+                "value": format!("```roc\n{}\n```", type_str),
+            },
+            "range": doc.to_lsp_range(region),
+        }))
+    }
+
+    /// Where the symbol under the cursor was defined, which may be in another module
+    pub fn definition(&self, src: &str, position: &Value) -> Option<Value> {
+        let loaded = self.loaded.as_ref()?;
+        let doc = Document::new(src);
+        let position = doc.roc_position(position)?;
+
+        let home_decls = loaded.declarations_by_id.get(&loaded.module_id)?;
+        let symbol = find_symbol_at(position, home_decls)?.value;
+        let module_id: ModuleId = symbol.module_id();
+
+        // We don't have the builtins' sources on disk
+        if module_id.is_builtin() {
+            return None;
+        }
+
+        let decls = loaded.declarations_by_id.get(&module_id)?;
+        let region = find_symbol_definition(symbol, decls)?;
+
+        let (path, def_src) = loaded.sources.get(&module_id)?;
+
+        let range = if module_id == loaded.module_id {
+            doc.to_lsp_range(region)
+        } else {
+            Document::new(def_src).to_lsp_range(region)
+        };
+
+        Some(json!({
+            "uri": path_to_uri(path),
+            "range": range,
+        }))
+    }
+}
+
+/// Text edits that format the whole document, or `None` if it doesn't parse.
+pub fn format(src: &str) -> Option<Value> {
+    let arena = Bump::new();
+    let ast = arena.alloc(parse_all(&arena, src).ok()?);
+    let mut buf = Buf::new_in(&arena);
+
+    fmt_all(&mut buf, ast);
+
+    let doc = Document::new(src);
+
+    Some(json!([{
+        "range": doc.full_range(),
+        "newText": buf.as_str(),
+    }]))
+}
+
+fn parse_all<'a>(arena: &'a Bump, src: &'a str) -> Result<Ast<'a>, SyntaxError<'a>> {
+    let (module, state) = module::parse_header(arena, State::new(src.as_bytes()))
+        .map_err(|e| SyntaxError::Header(e.problem))?;
+
+    let (_, defs, _) = module_defs().parse(arena, state, 0).map_err(|(_, e)| e)?;
+
+    Ok(Ast { module, defs })
+}
+
+fn fmt_all<'a>(buf: &mut Buf<'a>, ast: &'a Ast) {
+    fmt_module(buf, &ast.module);
+
+    fmt_defs(buf, &ast.defs, 0);
+
+    buf.fmt_end_of_file();
+}
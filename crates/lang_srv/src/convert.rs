@@ -0,0 +1,157 @@
+//! Conversions between Roc's source locations and the Language Server Protocol's.
+//!
+//! Roc regions are byte offsets into the source, whereas LSP positions are a line number and a
+//! column counted in UTF-16 code units.
+use roc_region::all::{LineColumn, LineInfo, Position, Region};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+pub struct Document<'a> {
+    pub src: &'a str,
+    pub lines: LineInfo,
+}
+
+impl<'a> Document<'a> {
+    pub fn new(src: &'a str) -> Self {
+        Self {
+            src,
+            lines: LineInfo::new(src),
+        }
+    }
+
+    pub fn to_lsp_position(&self, position: Position) -> Value {
+        let offset = (position.offset as usize).min(self.src.len());
+        let LineColumn { line, column } = self.lines.convert_offset(offset as u32);
+        let line_start = offset - column as usize;
+
+        let character: usize = self.src[line_start..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum();
+
+        json!({ "line": line, "character": character })
+    }
+
+    pub fn to_lsp_range(&self, region: Region) -> Value {
+        json!({
+            "start": self.to_lsp_position(region.start()),
+            "end": self.to_lsp_position(region.end()),
+        })
+    }
+
+    /// The range covering the whole document
+    pub fn full_range(&self) -> Value {
+        json!({
+            "start": { "line": 0, "character": 0 },
+            "end": self.to_lsp_position(Position::new(self.src.len() as u32)),
+        })
+    }
+
+    /// Returns `None` if the position is past the end of the document.
+    pub fn roc_position(&self, position: &Value) -> Option<Position> {
+        let line = position["line"].as_u64()? as usize;
+        let character = position["character"].as_u64()? as usize;
+
+        let line_start = match line {
+            0 => 0,
+            _ => self
+                .src
+                .match_indices('\n')
+                .nth(line - 1)
+                .map(|(offset, _)| offset + 1)?,
+        };
+
+        let mut utf16_units = 0;
+        let mut offset = line_start;
+
+        for ch in self.src[line_start..].chars() {
+            if utf16_units >= character || ch == '\n' {
+                break;
+            }
+
+            utf16_units += ch.len_utf16();
+            offset += ch.len_utf8();
+        }
+
+        Some(Position::new(offset as u32))
+    }
+}
+
+/// Only `file://` URIs are supported, since we need to read the module's imports from disk.
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok()?;
+                decoded.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    let path = String::from_utf8(decoded).ok()?;
+
+    // On Windows, URIs look like file:///C:/Users/...
+    if cfg!(windows) {
+        Some(PathBuf::from(path.trim_start_matches('/')))
+    } else {
+        Some(PathBuf::from(path))
+    }
+}
+
+pub fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from(if path.starts_with('/') {
+        "file://"
+    } else {
+        "file:///"
+    });
+
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    uri
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn positions_count_utf16_code_units() {
+        let doc = Document::new("x = 1\ny = \"🐦\" |> f\n");
+
+        // the `|` after the emoji, which is 4 bytes but 2 UTF-16 code units
+        let offset = "x = 1\ny = \"🐦\" ".len() as u32;
+        let lsp_position = json!({ "line": 1, "character": 9 });
+
+        assert_eq!(doc.to_lsp_position(Position::new(offset)), lsp_position);
+        assert_eq!(doc.roc_position(&lsp_position), Some(Position::new(offset)));
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn uri_round_trip() {
+        let path = PathBuf::from("/home/roc/my app/main.roc");
+        let uri = path_to_uri(&path);
+
+        assert_eq!(uri, "file:///home/roc/my%20app/main.roc");
+        assert_eq!(uri_to_path(&uri), Some(path));
+    }
+}
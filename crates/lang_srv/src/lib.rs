@@ -0,0 +1,340 @@
+//! A language server for Roc, used by `roc lsp`.
+//!
+//! It speaks the [Language Server Protocol](https://microsoft.github.io/language-server-protocol/)
+//! over stdio, and supports diagnostics, hover (showing the type of the expression under the
+//! cursor), go to definition, and formatting.
+mod analysis;
+mod convert;
+mod rpc;
+
+use analysis::{catch_panic, Analysis};
+use convert::{path_to_uri, uri_to_path};
+use roc_packaging::cache::RocCacheDir;
+use rpc::{error_response, notification, read_message, response, write_message};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+/// https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#textDocumentSyncKind
+const TEXT_DOCUMENT_SYNC_FULL: u8 = 1;
+
+struct OpenDocument {
+    src: String,
+    analysis: Analysis,
+}
+
+struct Server<'a, W> {
+    writer: W,
+    roc_cache_dir: RocCacheDir<'a>,
+    documents: HashMap<String, OpenDocument>,
+    shutdown_requested: bool,
+}
+
+/// Serve requests from stdin until the client sends `exit`. Returns the process exit code.
+pub fn run(roc_cache_dir: RocCacheDir<'_>) -> io::Result<i32> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+
+    serve(&mut stdin.lock(), stdout.lock(), roc_cache_dir)
+}
+
+fn serve(
+    reader: &mut impl BufRead,
+    writer: impl Write,
+    roc_cache_dir: RocCacheDir<'_>,
+) -> io::Result<i32> {
+    let mut server = Server {
+        writer,
+        roc_cache_dir,
+        documents: HashMap::new(),
+        shutdown_requested: false,
+    };
+
+    while let Some(message) = read_message(reader)? {
+        let method = message["method"].as_str().unwrap_or_default();
+
+        if method == "exit" {
+            // The spec asks for a non-zero exit code if the client didn't shut us down first.
+            return Ok(if server.shutdown_requested { 0 } else { 1 });
+        }
+
+        match message.get("id").cloned() {
+            Some(id) => server.handle_request(id, method, &message["params"])?,
+            None => server.handle_notification(method, &message["params"])?,
+        }
+    }
+
+    // The client went away without saying goodbye
+    Ok(1)
+}
+
+impl<'a, W: Write> Server<'a, W> {
+    fn handle_request(&mut self, id: Value, method: &str, params: &Value) -> io::Result<()> {
+        let result = match method {
+            "initialize" => Ok(Some(json!({
+                "capabilities": {
+                    "textDocumentSync": TEXT_DOCUMENT_SYNC_FULL,
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "documentFormattingProvider": true,
+                },
+                "serverInfo": { "name": "roc" },
+            }))),
+            "shutdown" => {
+                self.shutdown_requested = true;
+
+                Ok(None)
+            }
+            "textDocument/hover" => match self.documents.get_mut(document_uri(params)) {
+                Some(doc) => catch_panic(|| doc.analysis.hover(&doc.src, &params["position"])),
+                None => Ok(None),
+            },
+            "textDocument/definition" => match self.documents.get(document_uri(params)) {
+                Some(doc) => catch_panic(|| doc.analysis.definition(&doc.src, &params["position"])),
+                None => Ok(None),
+            },
+            "textDocument/formatting" => match self.documents.get(document_uri(params)) {
+                Some(doc) => catch_panic(|| analysis::format(&doc.src)),
+                None => Ok(None),
+            },
+            _ => {
+                let message = format!("Unsupported request: {}", method);
+
+                return write_message(
+                    &mut self.writer,
+                    &error_response(id, rpc::METHOD_NOT_FOUND, &message),
+                );
+            }
+        };
+
+        match result {
+            Ok(result) => write_message(
+                &mut self.writer,
+                &response(id, result.unwrap_or(Value::Null)),
+            ),
+            Err(panic_message) => {
+                let message = format!("The compiler crashed: {}", panic_message);
+
+                write_message(
+                    &mut self.writer,
+                    &error_response(id, rpc::INTERNAL_ERROR, &message),
+                )
+            }
+        }
+    }
+
+    fn handle_notification(&mut self, method: &str, params: &Value) -> io::Result<()> {
+        match method {
+            "textDocument/didOpen" => {
+                let uri = document_uri(params);
+                let src = params["textDocument"]["text"].as_str().unwrap_or_default();
+
+                self.update(uri, src.to_string())
+            }
+            "textDocument/didChange" => {
+                // We only ask for full syncs, so the last change holds the whole document.
+                let changes = params["contentChanges"].as_array();
+
+                match changes.and_then(|changes| changes.last()) {
+                    Some(change) => {
+                        let src = change["text"].as_str().unwrap_or_default();
+
+                        self.update(document_uri(params), src.to_string())
+                    }
+                    None => Ok(()),
+                }
+            }
+            "textDocument/didClose" => {
+                let uri = document_uri(params);
+
+                match self.documents.remove(uri) {
+                    Some(_) => self.publish_diagnostics(uri, Vec::new()),
+                    None => Ok(()),
+                }
+            }
+            // Everything else (e.g. `initialized`, `$/cancelRequest`) can safely be ignored
+            _ => Ok(()),
+        }
+    }
+
+    fn update(&mut self, uri: &str, src: String) -> io::Result<()> {
+        let path = match uri_to_path(uri) {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let analysis = analysis::analyze(&path, &src, self.roc_cache_dir);
+
+        for (module_path, diagnostics) in analysis.diagnostics.iter() {
+            // Use the client's own URI for the document itself, in case it encodes paths
+            // differently than we do.
+            let module_uri = if *module_path == path {
+                uri.to_string()
+            } else {
+                path_to_uri(module_path)
+            };
+
+            self.publish_diagnostics(&module_uri, diagnostics.clone())?;
+        }
+
+        self.documents
+            .insert(uri.to_string(), OpenDocument { src, analysis });
+
+        Ok(())
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str, diagnostics: Vec<Value>) -> io::Result<()> {
+        let params = json!({ "uri": uri, "diagnostics": diagnostics });
+
+        write_message(
+            &mut self.writer,
+            &notification("textDocument/publishDiagnostics", params),
+        )
+    }
+}
+
+fn document_uri(params: &Value) -> &str {
+    params["textDocument"]["uri"].as_str().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    /// Send the messages to a server, followed by `shutdown` and `exit`, and return its replies
+    fn run_session(messages: &[Value]) -> Vec<Value> {
+        let mut input = Vec::new();
+
+        for message in messages {
+            write_message(&mut input, message).unwrap();
+        }
+
+        write_message(&mut input, &json!({ "id": 999, "method": "shutdown" })).unwrap();
+        write_message(&mut input, &json!({ "method": "exit" })).unwrap();
+
+        let mut output = Vec::new();
+        let exit_code = serve(
+            &mut io::Cursor::new(input),
+            &mut output,
+            RocCacheDir::Disallowed,
+        )
+        .unwrap();
+
+        assert_eq!(exit_code, 0);
+
+        let mut reader = io::Cursor::new(output);
+        let mut replies = Vec::new();
+
+        while let Some(reply) = read_message(&mut reader).unwrap() {
+            replies.push(reply);
+        }
+
+        replies
+    }
+
+    fn result_of(replies: &[Value], id: u64) -> &Value {
+        let reply = replies.iter().find(|reply| reply["id"] == id).unwrap();
+
+        &reply["result"]
+    }
+
+    fn did_open(uri: &str, text: &str) -> Value {
+        json!({
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": uri, "text": text } },
+        })
+    }
+
+    fn request(id: u64, method: &str, uri: &str, position: Value) -> Value {
+        json!({
+            "id": id,
+            "method": method,
+            "params": { "textDocument": { "uri": uri }, "position": position },
+        })
+    }
+
+    const SRC: &str = "interface Test\n    exposes [main]\n    imports []\n\nmain =\n    double = \\n -> n * 2\n\n    double   21\n";
+
+    #[test]
+    fn hover_definition_and_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let uri = path_to_uri(&dir.path().join("Test.roc"));
+
+        let replies = run_session(&[
+            did_open(&uri, SRC),
+            // the `21` in `double   21`
+            request(
+                1,
+                "textDocument/hover",
+                &uri,
+                json!({ "line": 7, "character": 14 }),
+            ),
+            // the `double` in `double   21`
+            request(
+                2,
+                "textDocument/definition",
+                &uri,
+                json!({ "line": 7, "character": 6 }),
+            ),
+            request(3, "textDocument/formatting", &uri, Value::Null),
+        ]);
+
+        let hover = result_of(&replies, 1);
+        assert!(
+            hover["contents"]["value"].as_str().unwrap().contains("Num"),
+            "{hover}"
+        );
+        assert_eq!(
+            hover["range"],
+            json!({
+                "start": { "line": 7, "character": 13 },
+                "end": { "line": 7, "character": 15 },
+            })
+        );
+
+        let definition = result_of(&replies, 2);
+        assert_eq!(definition["uri"], json!(uri));
+        assert_eq!(
+            definition["range"],
+            json!({
+                "start": { "line": 5, "character": 4 },
+                "end": { "line": 5, "character": 10 },
+            })
+        );
+
+        let edits = result_of(&replies, 3);
+        let formatted = edits[0]["newText"].as_str().unwrap();
+        assert!(formatted.contains("    double 21\n"), "{formatted}");
+    }
+
+    #[test]
+    fn syntax_error_is_reported_where_it_is() {
+        let dir = tempfile::tempdir().unwrap();
+        let uri = path_to_uri(&dir.path().join("Test.roc"));
+        let src = "interface Test\n    exposes [main]\n    imports []\n\nmain =\n    x = )\n";
+
+        let replies = run_session(&[did_open(&uri, src)]);
+
+        let diagnostics = replies
+            .iter()
+            .find(|reply| reply["method"] == "textDocument/publishDiagnostics")
+            .map(|reply| &reply["params"]["diagnostics"])
+            .unwrap();
+
+        assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+        assert!(
+            diagnostics[0]["range"]["start"]["line"].as_u64().unwrap() >= 4,
+            "{diagnostics}"
+        );
+    }
+
+    #[test]
+    fn panics_become_errors() {
+        assert_eq!(catch_panic(|| 42), Ok(42));
+        assert_eq!(
+            catch_panic(|| -> i32 { panic!("not yet implemented") }),
+            Err("not yet implemented".to_string())
+        );
+    }
+}
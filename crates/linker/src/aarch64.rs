@@ -0,0 +1,262 @@
+//! Decoding and patching of the aarch64 instructions that the surgical linker relocates.
+//! Every aarch64 instruction is 4 bytes wide and spreads its immediates over bit fields,
+//! so unlike on x86 we can't just overwrite a 32-bit displacement.
+use object::{elf, RelocationEncoding, RelocationKind};
+
+const NOP: u32 = 0xd503_201f;
+
+/// `br x16`; like the stubs generated by system linkers, we use x16 (IP0) as a scratch register.
+const BR_X16: u32 = 0xd61f_0200;
+
+/// A relocation in an aarch64 object file that patches an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Relocation {
+    /// `b` or `bl` to the target (`R_AARCH64_CALL26`, `R_AARCH64_JUMP26`)
+    Branch26,
+    /// `adrp` of the target's 4KiB page (`R_AARCH64_ADR_PREL_PG_HI21`)
+    Page21,
+    /// `add` of the target's offset within its page (`R_AARCH64_ADD_ABS_LO12_NC`)
+    PageOffset12,
+    /// load or store at the target's offset within its page, in units of `1 << scale` bytes
+    /// (`R_AARCH64_LDST{8,16,32,64,128}_ABS_LO12_NC`)
+    LoadStoreOffset12 { scale: u32 },
+    /// `adrp` of the page of the target's GOT entry (`R_AARCH64_ADR_GOT_PAGE`)
+    GotPage21,
+    /// `ldr` of the target's GOT entry (`R_AARCH64_LD64_GOT_LO12_NC`)
+    GotOffset12,
+}
+
+impl Relocation {
+    pub(crate) fn from_object(reloc: &object::Relocation) -> Option<Self> {
+        if reloc.encoding() == RelocationEncoding::AArch64Call {
+            return Some(Relocation::Branch26);
+        }
+
+        let r_type = match reloc.kind() {
+            RelocationKind::Elf(r_type) => r_type,
+            _ => return None,
+        };
+
+        let relocation = match r_type {
+            elf::R_AARCH64_CALL26 | elf::R_AARCH64_JUMP26 => Relocation::Branch26,
+            elf::R_AARCH64_ADR_PREL_PG_HI21 => Relocation::Page21,
+            elf::R_AARCH64_ADD_ABS_LO12_NC => Relocation::PageOffset12,
+            elf::R_AARCH64_LDST8_ABS_LO12_NC => Relocation::LoadStoreOffset12 { scale: 0 },
+            elf::R_AARCH64_LDST16_ABS_LO12_NC => Relocation::LoadStoreOffset12 { scale: 1 },
+            elf::R_AARCH64_LDST32_ABS_LO12_NC => Relocation::LoadStoreOffset12 { scale: 2 },
+            elf::R_AARCH64_LDST64_ABS_LO12_NC => Relocation::LoadStoreOffset12 { scale: 3 },
+            elf::R_AARCH64_LDST128_ABS_LO12_NC => Relocation::LoadStoreOffset12 { scale: 4 },
+            elf::R_AARCH64_ADR_GOT_PAGE => Relocation::GotPage21,
+            elf::R_AARCH64_LD64_GOT_LO12_NC => Relocation::GotOffset12,
+            _ => return None,
+        };
+
+        Some(relocation)
+    }
+
+    /// Patch `inst`, which is located at `pc`, to refer to `target`.
+    /// Returns `None` if the target is out of range of the instruction.
+    ///
+    /// The surgical linker knows where every symbol ends up, so there is no GOT for the app:
+    /// GOT references are relaxed into direct ones, by pointing the `adrp` at the target itself
+    /// and turning the `ldr` from the GOT entry into an `add`.
+    pub(crate) fn apply(self, inst: u32, pc: u64, target: u64) -> Option<u32> {
+        match self {
+            Relocation::Branch26 => encode_branch(inst, target as i64 - pc as i64),
+            Relocation::Page21 | Relocation::GotPage21 => encode_adrp(inst, pc, target),
+            Relocation::PageOffset12 => Some(encode_add_offset(inst, target)),
+            Relocation::LoadStoreOffset12 { scale } => {
+                encode_load_store_offset(inst, target, scale)
+            }
+            Relocation::GotOffset12 => ldr_to_add(inst).map(|add| encode_add_offset(add, target)),
+        }
+    }
+}
+
+pub(crate) fn read_inst(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[..4].try_into().unwrap())
+}
+
+pub(crate) fn write_inst(bytes: &mut [u8], inst: u32) {
+    bytes[..4].copy_from_slice(&inst.to_le_bytes());
+}
+
+fn page(address: u64) -> u64 {
+    address & !0xfff
+}
+
+fn sign_extend(value: u32, bits: u32) -> i64 {
+    let shift = 64 - bits;
+
+    ((value as i64) << shift) >> shift
+}
+
+fn fits_signed(value: i64, bits: u32) -> bool {
+    let limit = 1i64 << (bits - 1);
+
+    (-limit..limit).contains(&value)
+}
+
+/// The number of bytes that a `b` or `bl` instruction jumps by.
+pub(crate) fn decode_branch(inst: u32) -> Option<i64> {
+    if inst & 0x7c00_0000 == 0x1400_0000 {
+        Some(sign_extend(inst & 0x03ff_ffff, 26) << 2)
+    } else {
+        None
+    }
+}
+
+pub(crate) fn encode_branch(inst: u32, offset: i64) -> Option<u32> {
+    if offset % 4 != 0 || !fits_signed(offset >> 2, 26) {
+        return None;
+    }
+
+    Some((inst & !0x03ff_ffff) | ((offset >> 2) as u32 & 0x03ff_ffff))
+}
+
+/// The destination register of an `adrp` instruction at `pc`, and the page address it computes.
+pub(crate) fn decode_adrp(inst: u32, pc: u64) -> Option<(u32, u64)> {
+    if inst & 0x9f00_0000 != 0x9000_0000 {
+        return None;
+    }
+
+    let immlo = (inst >> 29) & 0b11;
+    let immhi = (inst >> 5) & 0x7_ffff;
+    let pages = sign_extend((immhi << 2) | immlo, 21);
+
+    Some((inst & 0x1f, page(pc).wrapping_add((pages << 12) as u64)))
+}
+
+pub(crate) fn encode_adrp(inst: u32, pc: u64, target: u64) -> Option<u32> {
+    let pages = (page(target) as i64 - page(pc) as i64) >> 12;
+
+    if !fits_signed(pages, 21) {
+        return None;
+    }
+
+    let imm = pages as u32 & 0x1f_ffff;
+    let immlo = imm & 0b11;
+    let immhi = imm >> 2;
+
+    Some((inst & !(0b11 << 29 | 0x7_ffff << 5)) | immlo << 29 | immhi << 5)
+}
+
+/// The source register and immediate of an unshifted 64-bit `add` instruction.
+pub(crate) fn decode_add(inst: u32) -> Option<(u32, u64)> {
+    if inst & 0xffc0_0000 == 0x9100_0000 {
+        Some(((inst >> 5) & 0x1f, ((inst >> 10) & 0xfff) as u64))
+    } else {
+        None
+    }
+}
+
+pub(crate) fn encode_add_offset(inst: u32, target: u64) -> u32 {
+    (inst & !(0xfff << 10)) | ((target & 0xfff) as u32) << 10
+}
+
+fn encode_load_store_offset(inst: u32, target: u64, scale: u32) -> Option<u32> {
+    let offset = target & 0xfff;
+
+    if offset % (1 << scale) != 0 {
+        return None;
+    }
+
+    Some((inst & !(0xfff << 10)) | ((offset >> scale) as u32) << 10)
+}
+
+/// Turn `ldr xd, [xn, #imm]` into `add xd, xn, #imm`.
+fn ldr_to_add(inst: u32) -> Option<u32> {
+    if inst & 0xffc0_0000 == 0xf940_0000 {
+        Some(0x9100_0000 | (inst & 0x3ff))
+    } else {
+        None
+    }
+}
+
+/// A 16-byte PLT entry at `pc` that jumps straight to `target`.
+pub(crate) fn plt_stub(pc: u64, target: u64) -> Option<[u8; 16]> {
+    let adrp_x16 = encode_adrp(0x9000_0010, pc, target)?;
+    let add_x16 = encode_add_offset(0x9100_0210, target);
+
+    let mut stub = [0; 16];
+    for (bytes, inst) in stub
+        .chunks_exact_mut(4)
+        .zip([adrp_x16, add_x16, BR_X16, NOP])
+    {
+        write_inst(bytes, inst);
+    }
+
+    Some(stub)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn branch_round_trip() {
+        // bl 0x2000, at 0x1000
+        let bl = 0x9400_0400;
+        assert_eq!(decode_branch(bl), Some(0x1000));
+        assert_eq!(encode_branch(0x9400_0000, 0x1000), Some(bl));
+
+        // b -0x8
+        let b = 0x17ff_fffe;
+        assert_eq!(decode_branch(b), Some(-8));
+        assert_eq!(encode_branch(0x1400_0000, -8), Some(b));
+
+        assert_eq!(encode_branch(0x9400_0000, 1 << 28), None);
+        assert_eq!(decode_branch(NOP), None);
+    }
+
+    #[test]
+    fn adrp_round_trip() {
+        // adrp x0, 0x411000, at 0x400abc
+        let adrp = 0xb000_0080;
+        assert_eq!(decode_adrp(adrp, 0x400abc), Some((0, 0x411000)));
+        assert_eq!(encode_adrp(0x9000_0000, 0x400abc, 0x411234), Some(adrp));
+
+        // adrp x17, 0x3ff000, at 0x400000
+        let adrp = 0xf0ff_fff1;
+        assert_eq!(decode_adrp(adrp, 0x400000), Some((17, 0x3ff000)));
+        assert_eq!(encode_adrp(0x9000_0011, 0x400000, 0x3ff010), Some(adrp));
+
+        assert_eq!(encode_adrp(0x9000_0000, 0, 1 << 33), None);
+    }
+
+    #[test]
+    fn page_offsets() {
+        // add x0, x1, #0x234
+        assert_eq!(decode_add(0x9108_d020), Some((1, 0x234)));
+        assert_eq!(encode_add_offset(0x9100_0020, 0x411234), 0x9108_d020);
+
+        // ldr x0, [x1, #0x238]
+        assert_eq!(
+            encode_load_store_offset(0xf940_0020, 0x411238, 3),
+            Some(0xf941_1c20)
+        );
+        assert_eq!(encode_load_store_offset(0xf940_0020, 0x411234, 3), None);
+
+        // ldr x0, [x1, #8] becomes add x0, x1, #8
+        let add = ldr_to_add(0xf940_0420).unwrap();
+        assert_eq!(encode_add_offset(add, 8), 0x9100_2020);
+    }
+
+    #[test]
+    fn relaxed_got_load() {
+        let ldr = 0xf940_0020;
+        let add = Relocation::GotOffset12.apply(ldr, 0x400004, 0x411234);
+
+        assert_eq!(add, Some(0x9108_d020));
+    }
+
+    #[test]
+    fn plt_stub_jumps_to_target() {
+        let stub = plt_stub(0x400020, 0x411234).unwrap();
+        let insts: Vec<u32> = stub.chunks_exact(4).map(read_inst).collect();
+
+        assert_eq!(decode_adrp(insts[0], 0x400020), Some((16, 0x411000)));
+        assert_eq!(decode_add(insts[1]), Some((16, 0x234)));
+        assert_eq!(insts[2..], [BR_X16, NOP]);
+    }
+}
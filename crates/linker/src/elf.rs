@@ -15,6 +15,7 @@ use std::os::raw::c_char;
 use std::path::Path;
 use std::time::{Duration, Instant};
 
+use crate::aarch64;
 use crate::metadata::{self, Metadata, VirtualOffset};
 
 use crate::{
@@ -27,6 +28,61 @@ const MIN_SECTION_ALIGNMENT: usize = 0x40;
 // TODO: Analyze if this offset is always correct.
 const PLT_ADDRESS_OFFSET: u64 = 0x10;
 
+/// The instruction sets of the ELF hosts we can do surgery on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Arch {
+    X86_64,
+    Aarch64,
+}
+
+impl Arch {
+    fn from_object(architecture: object::Architecture) -> Self {
+        match architecture {
+            object::Architecture::X86_64 => Arch::X86_64,
+            object::Architecture::Aarch64 => Arch::Aarch64,
+            other => {
+                internal_error!("Surgical linking does not support {:?} ELF files", other);
+            }
+        }
+    }
+
+    /// The size of the first PLT entry, which calls the dynamic linker rather than a function.
+    fn plt_header_size(self) -> u64 {
+        match self {
+            Arch::X86_64 => 0x10,
+            Arch::Aarch64 => 0x20,
+        }
+    }
+
+    fn r_none(self) -> u32 {
+        match self {
+            Arch::X86_64 => elf::R_X86_64_NONE,
+            Arch::Aarch64 => elf::R_AARCH64_NONE,
+        }
+    }
+
+    fn r_relative(self) -> u32 {
+        match self {
+            Arch::X86_64 => elf::R_X86_64_RELATIVE,
+            Arch::Aarch64 => elf::R_AARCH64_RELATIVE,
+        }
+    }
+
+    fn r_glob_dat(self) -> u32 {
+        match self {
+            Arch::X86_64 => elf::R_X86_64_GLOB_DAT,
+            Arch::Aarch64 => elf::R_AARCH64_GLOB_DAT,
+        }
+    }
+
+    fn r_jump_slot(self) -> u32 {
+        match self {
+            Arch::X86_64 => elf::R_X86_64_JUMP_SLOT,
+            Arch::Aarch64 => elf::R_AARCH64_JUMP_SLOT,
+        }
+    }
+}
+
 struct ElfDynamicDeps {
     got_app_syms: Vec<(String, usize)>,
     got_sections: Vec<(usize, usize)>,
@@ -81,6 +137,9 @@ fn collect_roc_definitions<'a>(object: &object::File<'a, &'a [u8]>) -> MutMap<St
             "roc_getppid" => Some("getppid"),
             "roc_shm_open" => Some("shm_open"),
 
+            // for `roc run --profile`
+            "roc_getenv" => Some("getenv"),
+
             _ => None,
         };
 
@@ -95,13 +154,18 @@ fn collect_roc_definitions<'a>(object: &object::File<'a, &'a [u8]>) -> MutMap<St
 }
 
 struct Surgeries<'a> {
+    arch: Arch,
     surgeries: MutMap<String, Vec<metadata::SurgeryEntry>>,
     app_func_addresses: MutMap<u64, &'a str>,
     indirect_warning_given: bool,
 }
 
 impl<'a> Surgeries<'a> {
-    fn new(application_symbols: &[Symbol], app_func_addresses: MutMap<u64, &'a str>) -> Self {
+    fn new(
+        arch: Arch,
+        application_symbols: &[Symbol],
+        app_func_addresses: MutMap<u64, &'a str>,
+    ) -> Self {
         let mut surgeries = MutMap::default();
 
         // for each symbol that the host expects from the application
@@ -112,6 +176,7 @@ impl<'a> Surgeries<'a> {
         }
 
         Self {
+            arch,
             surgeries,
             app_func_addresses,
             indirect_warning_given: false,
@@ -172,6 +237,12 @@ impl<'a> Surgeries<'a> {
                 internal_error!("Failed to load text section, {:+x?}: {}", sec, err);
             }
         };
+
+        if self.arch == Arch::Aarch64 {
+            self.append_aarch64_text_section(sec, file_offset, compressed, &data, verbose);
+            return;
+        }
+
         let mut decoder = Decoder::with_ip(64, &data, sec.address(), DecoderOptions::NONE);
         let mut inst = Instruction::default();
 
@@ -263,6 +334,102 @@ impl<'a> Surgeries<'a> {
             }
         }
     }
+
+    fn append_aarch64_text_section(
+        &mut self,
+        sec: &Section,
+        file_offset: u64,
+        compressed: bool,
+        data: &[u8],
+        verbose: bool,
+    ) {
+        // As on x86, calls to the plt are redirected, and anything we miss still goes
+        // through the plt, which is patched to jump to the app function.
+        // Besides branches, we look for an `adrp` directly followed by an `add`, which is how
+        // the address of an app function ends up in a register.
+        let mut previous: Option<(u64, u32)> = None;
+
+        for (i, bytes) in data.chunks_exact(4).enumerate() {
+            let inst = aarch64::read_inst(bytes);
+            let address = sec.address() + 4 * i as u64;
+            let offset = file_offset + 4 * i as u64;
+
+            if let Some(branch_offset) = aarch64::decode_branch(inst) {
+                let target = address.wrapping_add(branch_offset as u64);
+
+                if let Some(func_name) = self.app_func_addresses.get(&target) {
+                    if compressed {
+                        internal_error!(
+                            "Surgical linking does not work with compressed text sections: {:+x?}",
+                            sec
+                        );
+                    }
+
+                    if verbose {
+                        println!(
+                            "Found branch from {:+x} to {:+x}({})",
+                            address, target, func_name
+                        );
+                        println!(
+                            "\tNeed to surgically replace the instruction {:#010x} at file offset {:+x}",
+                            inst, offset,
+                        );
+                    }
+
+                    self.surgeries
+                        .get_mut(*func_name)
+                        .unwrap()
+                        .push(metadata::SurgeryEntry {
+                            file_offset: offset,
+                            virtual_offset: VirtualOffset::Aarch64Branch(address),
+                            size: 4,
+                        });
+                }
+            } else if let Some((register, page_offset)) = aarch64::decode_add(inst) {
+                let adrp = previous.and_then(|(prev_address, prev_inst)| {
+                    aarch64::decode_adrp(prev_inst, prev_address)
+                        .filter(|(dest, _)| *dest == register)
+                        .map(|(_, page)| (prev_address, page))
+                });
+
+                if let Some((adrp_address, page)) = adrp {
+                    let target = page + page_offset;
+
+                    if let Some(func_name) = self.app_func_addresses.get(&target) {
+                        if compressed {
+                            internal_error!("Surgical linking does not work with compressed text sections: {:+x?}", sec);
+                        }
+
+                        if verbose {
+                            println!(
+                                "Found address of {:+x}({}) computed at {:+x}",
+                                target, func_name, adrp_address
+                            );
+                            println!(
+                                "\tNeed to surgically replace 2 instructions at file offset {:+x}",
+                                offset - 4,
+                            );
+                        }
+
+                        let entries = self.surgeries.get_mut(*func_name).unwrap();
+
+                        entries.push(metadata::SurgeryEntry {
+                            file_offset: offset - 4,
+                            virtual_offset: VirtualOffset::Aarch64Page(adrp_address),
+                            size: 4,
+                        });
+                        entries.push(metadata::SurgeryEntry {
+                            file_offset: offset,
+                            virtual_offset: VirtualOffset::Aarch64PageOffset,
+                            size: 4,
+                        });
+                    }
+                }
+            }
+
+            previous = Some((address, inst));
+        }
+    }
 }
 
 /// Constructs a `metadata::Metadata` from a host executable binary, and writes it to disk
@@ -285,6 +452,8 @@ pub(crate) fn preprocess_elf(
         }
     };
 
+    let arch = Arch::from_object(exec_obj.architecture());
+
     let mut md = metadata::Metadata {
         roc_symbol_vaddresses: collect_roc_definitions(&exec_obj),
         ..Default::default()
@@ -361,7 +530,7 @@ pub(crate) fn preprocess_elf(
                 }
             })
             .filter_map(|(_, reloc)| {
-                if let RelocationKind::Elf(7) = reloc.kind() {
+                if reloc.kind() == RelocationKind::Elf(arch.r_jump_slot()) {
                     Some(reloc)
                 } else {
                     None
@@ -370,8 +539,9 @@ pub(crate) fn preprocess_elf(
     for (i, reloc) in plt_relocs.enumerate() {
         for symbol in app_syms.iter() {
             if reloc.target() == RelocationTarget::Symbol(symbol.index()) {
-                let func_address = (i as u64 + 1) * PLT_ADDRESS_OFFSET + plt_address;
-                let func_offset = (i as u64 + 1) * PLT_ADDRESS_OFFSET + plt_offset;
+                let plt_entry_offset = arch.plt_header_size() + i as u64 * PLT_ADDRESS_OFFSET;
+                let func_address = plt_entry_offset + plt_address;
+                let func_offset = plt_entry_offset + plt_offset;
                 app_func_addresses.insert(func_address, symbol.name().unwrap());
                 md.plt_addresses.insert(
                     symbol.name().unwrap().to_string(),
@@ -407,7 +577,7 @@ pub(crate) fn preprocess_elf(
     // look at the text (i.e. code) sections and see collect work needs to be done
     let text_disassembly_start = Instant::now();
 
-    let mut surgeries = Surgeries::new(&app_syms, app_func_addresses);
+    let mut surgeries = Surgeries::new(arch, &app_syms, app_func_addresses);
     surgeries.append_text_sections(exec_data, &exec_obj, verbose);
     md.surgeries = surgeries.surgeries;
 
@@ -427,7 +597,7 @@ pub(crate) fn preprocess_elf(
                 dynamic_lib_count,
                 shared_lib_index,
             } = scan_elf_dynamic_deps(
-                &exec_obj, &mut md, &app_syms, shared_lib, exec_data, verbose,
+                arch, &exec_obj, &mut md, &app_syms, shared_lib, exec_data, verbose,
             );
 
             scanning_dynamic_deps_duration = scanning_dynamic_deps_start.elapsed();
@@ -436,6 +606,7 @@ pub(crate) fn preprocess_elf(
 
             // TODO little endian
             gen_elf_le(
+                arch,
                 exec_data,
                 &mut md,
                 preprocessed_path,
@@ -506,6 +677,7 @@ pub(crate) fn preprocess_elf(
 
 #[allow(clippy::too_many_arguments)]
 fn gen_elf_le(
+    arch: Arch,
     exec_data: &[u8],
     md: &mut metadata::Metadata,
     preprocessed_path: &Path,
@@ -678,18 +850,18 @@ fn gen_elf_le(
                 rel.r_offset = endian::U64::new(LE, r_offset + md.added_byte_count);
                 // Deal with potential adjusts to absolute jumps.
                 // TODO: Verify other relocation types.
-                if rel.r_type(LE, false) == elf::R_X86_64_RELATIVE {
+                if rel.r_type(LE, false) == arch.r_relative() {
                     let r_addend = rel.r_addend.get(LE);
                     rel.r_addend.set(LE, r_addend + md.added_byte_count as i64);
                 }
             }
             // If the relocation goes to a roc function, we need to surgically link it and change it to relative.
             let r_type = rel.r_type(NativeEndian, false);
-            if r_type == elf::R_X86_64_GLOB_DAT {
+            if r_type == arch.r_glob_dat() {
                 let r_sym = rel.r_sym(NativeEndian, false);
                 for (name, index) in got_app_syms.iter() {
                     if *index as u32 == r_sym {
-                        rel.set_r_info(LE, false, 0, elf::R_X86_64_RELATIVE);
+                        rel.set_r_info(LE, false, 0, arch.r_relative());
                         let addend_addr = sec_offset as usize
                             + i * mem::size_of::<elf::Rela64<LE>>()
                             // This 16 skips the first 2 fields and gets to the addend field.
@@ -717,8 +889,7 @@ fn gen_elf_le(
             .filter_map(|(i, rel)| {
                 let r_type = rel.r_type(NativeEndian, false);
                 let r_sym = rel.r_sym(NativeEndian, false);
-                if r_type == elf::R_X86_64_JUMP_SLOT && app_sym_indices.contains(&(r_sym as usize))
-                {
+                if r_type == arch.r_jump_slot() && app_sym_indices.contains(&(r_sym as usize)) {
                     Some(i)
                 } else {
                     None
@@ -734,7 +905,7 @@ fn gen_elf_le(
         for i in to_remove.iter() {
             relocations.swap(*i, j);
             let r_sym = relocations[j].r_sym(NativeEndian, false);
-            relocations[j].set_r_info(LE, false, r_sym, elf::R_X86_64_NONE);
+            relocations[j].set_r_info(LE, false, r_sym, arch.r_none());
             j -= 1;
         }
 
@@ -897,6 +1068,7 @@ fn gen_elf_le(
 }
 
 fn scan_elf_dynamic_deps(
+    arch: Arch,
     exec_obj: &object::File,
     md: &mut metadata::Metadata,
     app_syms: &[Symbol],
@@ -1044,7 +1216,7 @@ fn scan_elf_dynamic_deps(
         }
     })
     .filter_map(|(_, reloc)| {
-        if let RelocationKind::Elf(elf::R_X86_64_GLOB_DAT) = reloc.kind() {
+        if reloc.kind() == RelocationKind::Elf(arch.r_glob_dat()) {
             for symbol in app_syms.iter() {
                 if reloc.target() == RelocationTarget::Symbol(symbol.index()) {
                     return Some((symbol.name().unwrap().to_string(), symbol.index().0));
@@ -1063,7 +1235,7 @@ fn scan_elf_dynamic_deps(
         }
     })
     .filter_map(|(_, reloc)| {
-        if let RelocationKind::Elf(elf::R_X86_64_JUMP_SLOT) = reloc.kind() {
+        if reloc.kind() == RelocationKind::Elf(arch.r_jump_slot()) {
             for symbol in app_syms.iter() {
                 if reloc.target() == RelocationTarget::Symbol(symbol.index()) {
                     return Some(symbol.index().0);
@@ -1183,6 +1355,7 @@ fn surgery_elf_help(
     if !elf64 || !litte_endian {
         internal_error!("Only 64bit little endian elf currently supported for surgery");
     }
+    let arch = Arch::from_object(app_obj.architecture());
     let exec_header = load_struct_inplace::<elf::FileHeader64<LE>>(exec_mmap, 0);
 
     let ph_offset = exec_header.e_phoff.get(NativeEndian);
@@ -1376,6 +1549,37 @@ fn surgery_elf_help(
                     if let Some(target_offset) = target_offset {
                         let virt_base = section_virtual_offset as usize + rel.0 as usize;
                         let base = section_offset as usize + rel.0 as usize;
+
+                        let aarch64_relocation = match arch {
+                            Arch::Aarch64 => aarch64::Relocation::from_object(&rel.1),
+                            Arch::X86_64 => None,
+                        };
+
+                        if let Some(relocation) = aarch64_relocation {
+                            let target = (target_offset + rel.1.addend()) as u64;
+                            let inst = aarch64::read_inst(&exec_mmap[base..]);
+                            let patched = relocation
+                                .apply(inst, virt_base as u64, target)
+                                .unwrap_or_else(|| {
+                                    internal_error!(
+                                        "Relocation target {:+x} is out of range of {:?} at {:+x}",
+                                        target,
+                                        relocation,
+                                        virt_base
+                                    )
+                                });
+                            if verbose {
+                                println!(
+                                    "\t\tRelocation base location: {base:+x} (virt: {virt_base:+x})",
+                                );
+                                println!(
+                                    "\t\tPatched instruction: {inst:#010x} -> {patched:#010x}"
+                                );
+                            }
+                            aarch64::write_inst(&mut exec_mmap[base..], patched);
+                            continue;
+                        }
+
                         let target: i64 = match rel.1.kind() {
                             RelocationKind::Relative | RelocationKind::PltRelative => {
                                 target_offset - virt_base as i64 + rel.1.addend()
@@ -1549,6 +1753,12 @@ fn surgery_elf_help(
             let surgery_virt_offset = match s.virtual_offset {
                 VirtualOffset::Relative(vs) => (vs + md.added_byte_count) as i64,
                 VirtualOffset::Absolute => 0,
+                VirtualOffset::Aarch64Branch(_)
+                | VirtualOffset::Aarch64Page(_)
+                | VirtualOffset::Aarch64PageOffset => {
+                    aarch64_surgery(verbose, md, exec_mmap, s, func_virt_offset);
+                    continue;
+                }
             };
             match s.size {
                 4 => {
@@ -1580,18 +1790,33 @@ fn surgery_elf_help(
         if let Some((plt_off, plt_vaddr)) = md.plt_addresses.get(func_name) {
             let plt_off = (*plt_off + md.added_byte_count) as usize;
             let plt_vaddr = *plt_vaddr + md.added_byte_count;
-            let jmp_inst_len = 5;
-            let target =
-                (func_virt_offset as i64 - (plt_vaddr as i64 + jmp_inst_len as i64)) as i32;
-            if verbose {
-                println!("\tPLT: {:+x}, {:+x}", plt_off, plt_vaddr);
-                println!("\tTarget Jump: {:+x}", target);
-            }
-            let data = target.to_le_bytes();
-            exec_mmap[plt_off] = 0xE9;
-            exec_mmap[plt_off + 1..plt_off + jmp_inst_len].copy_from_slice(&data);
-            for i in jmp_inst_len..PLT_ADDRESS_OFFSET as usize {
-                exec_mmap[plt_off + i] = 0x90;
+            if arch == Arch::Aarch64 {
+                let stub = aarch64::plt_stub(plt_vaddr, func_virt_offset).unwrap_or_else(|| {
+                    internal_error!(
+                        "{} at {:+x} is out of range of its PLT entry at {:+x}",
+                        func_name,
+                        func_virt_offset,
+                        plt_vaddr
+                    )
+                });
+                if verbose {
+                    println!("\tPLT: {:+x}, {:+x}", plt_off, plt_vaddr);
+                }
+                exec_mmap[plt_off..][..stub.len()].copy_from_slice(&stub);
+            } else {
+                let jmp_inst_len = 5;
+                let target =
+                    (func_virt_offset as i64 - (plt_vaddr as i64 + jmp_inst_len as i64)) as i32;
+                if verbose {
+                    println!("\tPLT: {:+x}, {:+x}", plt_off, plt_vaddr);
+                    println!("\tTarget Jump: {:+x}", target);
+                }
+                let data = target.to_le_bytes();
+                exec_mmap[plt_off] = 0xE9;
+                exec_mmap[plt_off + 1..plt_off + jmp_inst_len].copy_from_slice(&data);
+                for i in jmp_inst_len..PLT_ADDRESS_OFFSET as usize {
+                    exec_mmap[plt_off + i] = 0x90;
+                }
             }
         }
 
@@ -1633,6 +1858,55 @@ fn surgery_elf_help(
     *offset_ref = offset;
 }
 
+/// Point an instruction in the host that was found by `append_aarch64_text_section` at the app function.
+fn aarch64_surgery(
+    verbose: bool,
+    md: &metadata::Metadata,
+    exec_mmap: &mut MmapMut,
+    s: &metadata::SurgeryEntry,
+    func_virt_offset: u64,
+) {
+    let file_offset = (s.file_offset + md.added_byte_count) as usize;
+    let inst = aarch64::read_inst(&exec_mmap[file_offset..]);
+
+    let patched = match s.virtual_offset {
+        VirtualOffset::Aarch64Branch(vs) => {
+            let pc = vs + md.added_byte_count;
+
+            // Out of range calls are left alone; they still go through the patched plt.
+            aarch64::encode_branch(inst, func_virt_offset as i64 - pc as i64)
+        }
+        VirtualOffset::Aarch64Page(vs) => {
+            let pc = vs + md.added_byte_count;
+
+            Some(
+                aarch64::encode_adrp(inst, pc, func_virt_offset).unwrap_or_else(|| {
+                    internal_error!(
+                        "Function at {:+x} is out of range of the adrp at {:+x}",
+                        func_virt_offset,
+                        pc
+                    )
+                }),
+            )
+        }
+        VirtualOffset::Aarch64PageOffset => {
+            Some(aarch64::encode_add_offset(inst, func_virt_offset))
+        }
+        VirtualOffset::Absolute | VirtualOffset::Relative(_) => {
+            internal_error!("Not an aarch64 surgery: {:+x?}", s);
+        }
+    };
+
+    if let Some(patched) = patched {
+        if verbose {
+            println!("\tPatched instruction: {:#010x} -> {:#010x}", inst, patched);
+        }
+        aarch64::write_inst(&mut exec_mmap[file_offset..], patched);
+    } else if verbose {
+        println!("\tBranch is out of range, leaving it to the plt");
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1722,6 +1996,11 @@ mod tests {
         );
 
         let zig = std::env::var("ROC_ZIG").unwrap_or_else(|_| "zig".into());
+        let zig_target = match target.architecture {
+            target_lexicon::Architecture::X86_64 => "x86_64-linux-gnu",
+            target_lexicon::Architecture::Aarch64(_) => "aarch64-linux-gnu",
+            other => panic!("no zig target for {:?}", other),
+        };
 
         std::fs::write(dir.join("host.zig"), host_zig.as_bytes()).unwrap();
         std::fs::write(dir.join("app.zig"), app_zig.as_bytes()).unwrap();
@@ -1734,7 +2013,7 @@ mod tests {
                 "app.zig",
                 "-fPIC",
                 "-target",
-                "x86_64-linux-gnu",
+                zig_target,
                 "-OReleaseFast",
             ])
             .output()
@@ -1763,7 +2042,7 @@ mod tests {
                 .collect()
         };
 
-        let dylib_bytes = crate::generate_dylib::create_dylib_elf64(&names).unwrap();
+        let dylib_bytes = crate::generate_dylib::create_dylib_elf64(&names, target).unwrap();
         std::fs::write(dir.join("libapp.so"), dylib_bytes).unwrap();
 
         // now we can compile the host (it uses libapp.so, hence the order here)
@@ -1776,7 +2055,7 @@ mod tests {
                 "-fPIE",
                 "-lc",
                 "-target",
-                "x86_64-linux-gnu",
+                zig_target,
                 "-OReleaseFast",
             ])
             .output()
@@ -1842,4 +2121,62 @@ mod tests {
 
         assert_eq!("Hello foo\n", output);
     }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn zig_host_app_aarch64() {
+        use std::str::FromStr;
+
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+
+        let target = Triple::from_str("aarch64-unknown-linux-gnu").unwrap();
+        zig_host_app_help(dir, &target);
+
+        let object_bytes = std::fs::read(dir.join("final")).unwrap();
+        let object = object::File::parse(object_bytes.as_slice()).unwrap();
+        assert_eq!(object.architecture(), object::Architecture::Aarch64);
+
+        // Run the executable natively on aarch64, or elsewhere under qemu-aarch64 if it's installed.
+        // The executable is dynamically linked against glibc, so qemu needs an aarch64 sysroot.
+        let mut command = if cfg!(target_arch = "aarch64") {
+            std::process::Command::new(&dir.join("final"))
+        } else {
+            let sysroot = std::env::var("QEMU_LD_PREFIX")
+                .unwrap_or_else(|_| "/usr/aarch64-linux-gnu".to_string());
+            let has_qemu = std::process::Command::new("qemu-aarch64")
+                .arg("--version")
+                .output()
+                .map_or(false, |output| output.status.success());
+
+            if !has_qemu || !Path::new(&sysroot).exists() {
+                eprintln!(
+                    "Not running the linked executable: this needs qemu-aarch64 and an aarch64 sysroot at {sysroot} (set QEMU_LD_PREFIX to use another one)"
+                );
+
+                return;
+            }
+
+            let mut command = std::process::Command::new("qemu-aarch64");
+
+            command.arg("-L").arg(&sysroot).arg(dir.join("final"));
+
+            command
+        };
+
+        let output = command.current_dir(dir).output().unwrap();
+
+        if !output.status.success() {
+            use std::io::Write;
+
+            std::io::stdout().write_all(&output.stdout).unwrap();
+            std::io::stderr().write_all(&output.stderr).unwrap();
+
+            panic!("app.exe failed");
+        }
+
+        let output = String::from_utf8_lossy(&output.stdout);
+
+        assert_eq!("Hello foo\n", output);
+    }
 }
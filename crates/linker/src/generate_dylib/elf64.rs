@@ -1,10 +1,21 @@
 use object::{elf, Endianness};
+use target_lexicon::Triple;
 
+use super::GenerateDylibError;
 use crate::pe::next_multiple_of;
 
-pub fn create_dylib_elf64(custom_names: &[String]) -> object::read::Result<Vec<u8>> {
+pub fn create_dylib_elf64(
+    custom_names: &[String],
+    target: &Triple,
+) -> Result<Vec<u8>, GenerateDylibError> {
     let endian = Endianness::Little;
 
+    let e_machine = match target.architecture {
+        target_lexicon::Architecture::X86_64 => elf::EM_X86_64,
+        target_lexicon::Architecture::Aarch64(_) => elf::EM_AARCH64,
+        _ => return Err(GenerateDylibError::UnsupportedTarget(target.clone())),
+    };
+
     let mut out_data = Vec::new();
     let mut writer = object::write::elf::Writer::new(endian, true, &mut out_data);
 
@@ -145,7 +156,7 @@ pub fn create_dylib_elf64(custom_names: &[String]) -> object::read::Result<Vec<u
             os_abi: 0,
             abi_version: 0,
             e_type: 3,
-            e_machine: 62,
+            e_machine,
             e_entry: 0x1000,
             e_flags: 0,
         })
@@ -12,12 +12,39 @@ pub(crate) use elf64::create_dylib_elf64;
 
 pub(crate) use pe::APP_DLL;
 
-pub fn generate(target: &Triple, custom_names: &[String]) -> object::read::Result<Vec<u8>> {
+#[derive(Debug)]
+pub enum GenerateDylibError {
+    /// We don't know how to make a dylib for this target yet
+    UnsupportedTarget(Triple),
+    Object(object::read::Error),
+}
+
+impl std::fmt::Display for GenerateDylibError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GenerateDylibError::UnsupportedTarget(target) => {
+                write!(
+                    f,
+                    "generating a stub dylib for {target} is not supported yet"
+                )
+            }
+            GenerateDylibError::Object(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl From<object::read::Error> for GenerateDylibError {
+    fn from(error: object::read::Error) -> Self {
+        GenerateDylibError::Object(error)
+    }
+}
+
+pub fn generate(target: &Triple, custom_names: &[String]) -> Result<Vec<u8>, GenerateDylibError> {
     match target.binary_format {
-        target_lexicon::BinaryFormat::Elf => elf64::create_dylib_elf64(custom_names),
-        target_lexicon::BinaryFormat::Macho => macho::create_dylib_macho(custom_names, target),
+        target_lexicon::BinaryFormat::Elf => elf64::create_dylib_elf64(custom_names, target),
+        target_lexicon::BinaryFormat::Macho => Ok(macho::create_dylib_macho(custom_names, target)?),
         target_lexicon::BinaryFormat::Coff => Ok(pe::synthetic_dll(custom_names)),
-        other => unimplemented!("dylib creation for {:?}", other),
+        _ => Err(GenerateDylibError::UnsupportedTarget(target.clone())),
     }
 }
 
@@ -55,6 +82,42 @@ mod tests {
         check_exports(&target);
     }
 
+    #[test]
+    fn check_exports_elf64_aarch64() {
+        let target = target_lexicon::Triple {
+            architecture: target_lexicon::Architecture::Aarch64(
+                target_lexicon::Aarch64Architecture::Aarch64,
+            ),
+            operating_system: target_lexicon::OperatingSystem::Linux,
+            binary_format: target_lexicon::BinaryFormat::Elf,
+            ..target_lexicon::Triple::host()
+        };
+
+        let bytes = generate(&target, &[]).unwrap();
+        let object = object::File::parse(bytes.as_slice()).unwrap();
+
+        assert_eq!(object.architecture(), object::Architecture::Aarch64);
+
+        check_exports(&target);
+    }
+
+    #[test]
+    fn unsupported_elf_architecture() {
+        let target = target_lexicon::Triple {
+            architecture: target_lexicon::Architecture::Riscv64(
+                target_lexicon::Riscv64Architecture::Riscv64,
+            ),
+            operating_system: target_lexicon::OperatingSystem::Linux,
+            binary_format: target_lexicon::BinaryFormat::Elf,
+            ..target_lexicon::Triple::host()
+        };
+
+        assert!(matches!(
+            generate(&target, &[]),
+            Err(GenerateDylibError::UnsupportedTarget(_))
+        ));
+    }
+
     #[test]
     fn check_exports_coff() {
         // NOTE: this does not work
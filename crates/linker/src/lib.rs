@@ -6,7 +6,7 @@
 use memmap2::{Mmap, MmapMut};
 use object::Object;
 use roc_build::link::{get_target_triple_str, rebuild_host, LinkType};
-use roc_error_macros::internal_error;
+use roc_error_macros::{internal_error, user_error};
 use roc_load::{EntryPoint, ExecutionMode, LoadConfig, Threading};
 use roc_mono::ir::OptLevel;
 use roc_packaging::cache::RocCacheDir;
@@ -16,6 +16,7 @@ use std::mem;
 use std::path::{Path, PathBuf};
 use target_lexicon::Triple;
 
+mod aarch64;
 mod elf;
 mod macho;
 mod pe;
@@ -27,7 +28,8 @@ pub fn supported(link_type: LinkType, target: &Triple) -> bool {
     if let LinkType::Executable = link_type {
         match target {
             Triple {
-                architecture: target_lexicon::Architecture::X86_64,
+                architecture:
+                    target_lexicon::Architecture::X86_64 | target_lexicon::Architecture::Aarch64(_),
                 operating_system: target_lexicon::OperatingSystem::Linux,
                 binary_format: target_lexicon::BinaryFormat::Elf,
                 ..
@@ -201,8 +203,13 @@ fn make_stub_dll_symbols(
 
 fn generate_dynamic_lib(target: &Triple, stub_dll_symbols: &[String], stub_lib_path: &Path) {
     if !stub_lib_is_up_to_date(target, stub_lib_path, stub_dll_symbols) {
-        let bytes = crate::generate_dylib::generate(target, stub_dll_symbols)
-            .unwrap_or_else(|e| internal_error!("{e}"));
+        let bytes = match crate::generate_dylib::generate(target, stub_dll_symbols) {
+            Ok(bytes) => bytes,
+            Err(e @ generate_dylib::GenerateDylibError::UnsupportedTarget(_)) => {
+                user_error!("{e}")
+            }
+            Err(e) => internal_error!("{e}"),
+        };
 
         if let Err(e) = std::fs::write(stub_lib_path, &bytes) {
             internal_error!("failed to write stub lib to {:?}: {e}", stub_lib_path)
@@ -1568,6 +1568,11 @@ fn surgery_macho_help(
             let surgery_virt_offset = match s.virtual_offset {
                 VirtualOffset::Relative(vs) => (vs + md.added_byte_count) as i64,
                 VirtualOffset::Absolute => 0,
+                VirtualOffset::Aarch64Branch(_)
+                | VirtualOffset::Aarch64Page(_)
+                | VirtualOffset::Aarch64PageOffset => {
+                    internal_error!("Surgery on aarch64 instructions is only supported for ELF");
+                }
             };
             match s.size {
                 4 => {
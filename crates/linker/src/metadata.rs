@@ -12,6 +12,12 @@ use serde::{Deserialize, Serialize};
 pub enum VirtualOffset {
     Absolute,
     Relative(u64),
+    /// The `b` or `bl` instruction at this virtual address.
+    Aarch64Branch(u64),
+    /// The `adrp` instruction at this virtual address.
+    Aarch64Page(u64),
+    /// The `add` instruction that follows an `Aarch64Page`.
+    Aarch64PageOffset,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
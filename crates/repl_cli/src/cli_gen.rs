@@ -11,33 +11,48 @@ use roc_mono::ir::OptLevel;
 use roc_mono::layout::STLayoutInterner;
 use roc_parse::ast::Expr;
 use roc_repl_eval::eval::jit_to_ast;
-use roc_repl_eval::gen::{compile_to_mono, format_answer, Problems, ReplOutput};
+use roc_repl_eval::gen::{compile_to_mono, format_answer, Problems, ReplImports, ReplOutput};
 use roc_repl_eval::{ReplApp, ReplAppMemory};
 use roc_reporting::report::DEFAULT_PALETTE;
 use roc_std::RocStr;
 use roc_target::TargetInfo;
 use roc_types::pretty_print::{name_and_print_var, DebugPrint};
 use roc_types::subs::Subs;
+use std::time::{Duration, Instant};
 use target_lexicon::Triple;
 
+/// How long it took to evaluate an expression, as reported by `:time`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EvalTimings {
+    /// Loading, type checking and code generation
+    pub compile: Duration,
+    /// Running the generated code and turning its result into an expression
+    pub run: Duration,
+}
+
 pub fn gen_and_eval_llvm<'a, I: Iterator<Item = &'a str>>(
+    imports: &ReplImports,
     defs: I,
     src: &str,
     target: Triple,
     opt_level: OptLevel,
+    timings: &mut EvalTimings,
 ) -> (Option<ReplOutput>, Problems) {
     let arena = Bump::new();
     let target_info = TargetInfo::from(&target);
+    let compile_start = Instant::now();
 
     let mut loaded;
     let problems;
 
-    match compile_to_mono(&arena, defs, src, target_info, DEFAULT_PALETTE) {
+    match compile_to_mono(&arena, imports, defs, src, target_info, DEFAULT_PALETTE) {
         (Some(mono), probs) => {
             loaded = mono;
             problems = probs;
         }
         (None, probs) => {
+            timings.compile = compile_start.elapsed();
+
             return (None, probs);
         }
     };
@@ -62,6 +77,8 @@ pub fn gen_and_eval_llvm<'a, I: Iterator<Item = &'a str>>(
             let empty_vec: Vec<String> = Vec::new(); // rustc can't infer the type of this Vec.
             debug_assert_ne!(problems.errors, empty_vec, "Got no errors but also no valid layout for the generated main function in the repl!");
 
+            timings.compile = compile_start.elapsed();
+
             return (None, problems);
         }
     };
@@ -71,6 +88,9 @@ pub fn gen_and_eval_llvm<'a, I: Iterator<Item = &'a str>>(
     let (lib, main_fn_name, subs, layout_interner) =
         mono_module_to_dylib(&arena, target, loaded, opt_level).expect("we produce a valid Dylib");
 
+    timings.compile = compile_start.elapsed();
+    let run_start = Instant::now();
+
     let mut app = CliApp { lib };
 
     let expr = jit_to_ast(
@@ -86,6 +106,8 @@ pub fn gen_and_eval_llvm<'a, I: Iterator<Item = &'a str>>(
     );
     let expr_str = format_answer(&arena, expr).to_string();
 
+    timings.run = run_start.elapsed();
+
     (
         Some(ReplOutput {
             expr: expr_str,
@@ -0,0 +1,191 @@
+//! Tab completion of commands, identifiers, builtin module members and record fields.
+use bumpalo::Bump;
+use roc_collections::MutSet;
+use roc_module::symbol::ModuleId;
+use roc_parse::ast::{ExtractSpaces, Header, Module};
+use roc_parse::state::State;
+use roc_repl_eval::gen::ReplImports;
+use rustyline::completion::FilenameCompleter;
+
+/// Everything that can come after a `:`
+const COMMANDS: &[&str] = &[
+    ":defs", ":exit", ":help", ":load", ":q", ":quit", ":reset", ":time", ":type",
+];
+
+/// The builtin modules whose members are completed after e.g. `Str.`
+const BUILTIN_MODULES: &[ModuleId] = &[
+    ModuleId::BOOL,
+    ModuleId::BOX,
+    ModuleId::DECODE,
+    ModuleId::DICT,
+    ModuleId::ENCODE,
+    ModuleId::HASH,
+    ModuleId::JSON,
+    ModuleId::LIST,
+    ModuleId::NUM,
+    ModuleId::RESULT,
+    ModuleId::SET,
+    ModuleId::STR,
+];
+
+/// The name of an interface module, and the names it exposes
+pub fn parse_interface_header(src: &str) -> Option<(String, Vec<String>)> {
+    let arena = Bump::new();
+
+    match roc_parse::module::parse_header(&arena, State::new(src.as_bytes())) {
+        Ok((
+            Module {
+                header: Header::Interface(header),
+                ..
+            },
+            _,
+        )) => {
+            let exposes = header
+                .exposes
+                .item
+                .items
+                .iter()
+                .map(|exposed| <&str>::from(exposed.value.extract_spaces().item).to_string())
+                .collect();
+
+            Some((<&str>::from(header.name.value).to_string(), exposes))
+        }
+        _ => None,
+    }
+}
+
+/// Complete the word that ends at `pos`. Returns where the completed part starts, and the
+/// candidates for it. `record_fields` gives the fields of an expression, like `user.address`.
+pub fn complete(
+    line: &str,
+    pos: usize,
+    past_def_idents: &MutSet<String>,
+    imports: &ReplImports,
+    record_fields: impl FnOnce(&str) -> Vec<String>,
+) -> (usize, Vec<String>) {
+    if line.trim_start().starts_with(":load ") {
+        return match FilenameCompleter::new().complete_path(line, pos) {
+            Ok((start, pairs)) => (
+                start,
+                pairs.into_iter().map(|pair| pair.replacement).collect(),
+            ),
+            Err(_) => (pos, Vec::new()),
+        };
+    }
+
+    let start = word_start(line, pos);
+    let word = &line[start..pos];
+
+    if line[..start].trim_start() == ":" {
+        let command = &line[start - 1..pos];
+
+        return (start - 1, matching(COMMANDS.iter().copied(), command));
+    }
+
+    if word.is_empty() {
+        return (pos, Vec::new());
+    }
+
+    match word.rsplit_once('.') {
+        // a member of a module, like `Str.concat`
+        Some((module_name, partial))
+            if starts_with_uppercase(module_name) && !module_name.contains('.') =>
+        {
+            let exposes = match imports
+                .modules
+                .iter()
+                .find(|import| import.module_name == module_name)
+            {
+                Some(import) => import.exposes.clone(),
+                None => builtin_exposes(module_name),
+            };
+
+            (
+                pos - partial.len(),
+                matching(exposes.iter().map(String::as_str), partial),
+            )
+        }
+        // a field of a record, like `user.name`
+        Some((expr, partial)) => {
+            let first = expr.split('.').next().unwrap_or_default();
+            let is_known = past_def_idents.contains(first)
+                || imports.modules.iter().any(|import| {
+                    import.module_name == first || import.exposes.iter().any(|name| name == first)
+                });
+
+            let fields = if is_known {
+                record_fields(expr)
+            } else {
+                Vec::new()
+            };
+
+            (
+                pos - partial.len(),
+                matching(fields.iter().map(String::as_str), partial),
+            )
+        }
+        // an identifier, or the name of a module
+        None => {
+            let mut names: Vec<String> = past_def_idents.iter().cloned().collect();
+
+            for import in imports.modules.iter() {
+                names.push(import.module_name.clone());
+                names.extend(import.exposes.iter().cloned());
+            }
+
+            for module_id in BUILTIN_MODULES {
+                if let Some((module_name, _)) =
+                    parse_interface_header(roc_builtins::roc::module_source(*module_id))
+                {
+                    names.push(module_name);
+                }
+            }
+
+            (start, matching(names.iter().map(String::as_str), word))
+        }
+    }
+}
+
+/// Where the word that ends at `pos` starts. Record field access and module members are part of
+/// the word, so `List.ma` is one word.
+fn word_start(line: &str, pos: usize) -> usize {
+    let before = &line[..pos];
+
+    match before.char_indices().rev().find(|(_, c)| !is_word_char(*c)) {
+        Some((index, c)) => index + c.len_utf8(),
+        None => 0,
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.'
+}
+
+fn starts_with_uppercase(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_uppercase())
+}
+
+/// The names a builtin module like `Str` exposes
+fn builtin_exposes(module_name: &str) -> Vec<String> {
+    BUILTIN_MODULES
+        .iter()
+        .filter_map(|module_id| {
+            parse_interface_header(roc_builtins::roc::module_source(*module_id))
+        })
+        .find(|(name, _)| name == module_name)
+        .map(|(_, exposes)| exposes)
+        .unwrap_or_default()
+}
+
+/// The candidates that start with `prefix`, sorted and without duplicates
+fn matching<'a>(candidates: impl Iterator<Item = &'a str>, prefix: &str) -> Vec<String> {
+    let mut matches: Vec<String> = candidates
+        .filter(|candidate| candidate.starts_with(prefix))
+        .map(|candidate| candidate.to_string())
+        .collect();
+
+    matches.sort();
+    matches.dedup();
+
+    matches
+}
@@ -1,11 +1,14 @@
 //! Command Line Interface (CLI) functionality for the Read-Evaluate-Print-Loop (REPL).
 mod cli_gen;
 mod colors;
+mod completion;
+mod platform;
 pub mod repl_state;
 
 use colors::{BLUE, END_COL, PINK};
 use const_format::concatcp;
 use repl_state::ReplState;
+use std::path::{Path, PathBuf};
 
 use crate::repl_state::PROMPT;
 
@@ -25,24 +28,53 @@ pub const WELCOME_MESSAGE: &str = concatcp!(
 // TODO add link to repl tutorial(does not yet exist).
 pub const SHORT_INSTRUCTIONS: &str = "Enter an expression, or :help, or :q to quit.\n\n";
 
-pub fn main() -> i32 {
+/// Run the REPL. With a platform, `Task`s entered at the prompt are run by its host.
+pub fn main(opt_platform: Option<&Path>) -> i32 {
     use rustyline::error::ReadlineError;
     use rustyline::Editor;
 
     // To debug rustyline:
     // <UNCOMMENT> env_logger::init();
     // <RUN WITH:> RUST_LOG=rustyline=debug cargo run repl 2> debug.log
+    let repl_helper = match opt_platform {
+        Some(path) => match platform::load_platform(path) {
+            Ok(platform) => ReplState::with_platform(platform),
+            Err(message) => {
+                eprintln!("{}", message);
+                return 1;
+            }
+        },
+        None => ReplState::new(),
+    };
+
     print!("{}{}", WELCOME_MESSAGE, SHORT_INSTRUCTIONS);
 
     let mut editor = Editor::<ReplState>::new();
-    let repl_helper = ReplState::new();
     editor.set_helper(Some(repl_helper));
 
+    let history_path = history_path();
+
+    if let Some(path) = &history_path {
+        // There is no history yet the first time the REPL runs
+        let _ = editor.load_history(path);
+    }
+
     loop {
         match editor.readline(PROMPT) {
             Ok(line) => {
                 editor.add_history_entry(line.trim());
 
+                if let Some(path) = &history_path {
+                    // Save after every entry, so that the history survives e.g. ctrl-c
+                    if let Err(err) = editor.save_history(path) {
+                        eprintln!(
+                            "Could not save the REPL history to {}: {}",
+                            path.display(),
+                            err
+                        );
+                    }
+                }
+
                 let dimensions = editor.dimensions();
                 let repl_helper = editor.helper_mut().expect("Editor helper was not set");
 
@@ -76,3 +108,14 @@ pub fn main() -> i32 {
         }
     }
 }
+
+/// The history is kept next to the package cache, e.g. in ~/.cache/roc/repl_history
+fn history_path() -> Option<PathBuf> {
+    let dir = roc_packaging::cache::roc_cache_dir()
+        .parent()?
+        .to_path_buf();
+
+    std::fs::create_dir_all(&dir).ok()?;
+
+    Some(dir.join("repl_history"))
+}
@@ -0,0 +1,260 @@
+//! Running effects in the REPL with a platform, from `roc repl --platform`.
+//!
+//! The platform's host is built once, when the REPL starts. An expression that is a `Task` of the
+//! platform is then built into an app that runs it and `dbg`s its result, which is linked with the
+//! host and run with the REPL's terminal. The REPL reads the result from the shared memory that
+//! `dbg` writes to, and prints it like any other value.
+use bumpalo::Bump;
+use roc_build::link::rebuild_host;
+use roc_load::MonomorphizedModule;
+use roc_mono::ir::OptLevel;
+use roc_parse::ast::{ExtractSpaces, Header, Module};
+use roc_parse::state::State;
+use roc_repl_eval::gen::{ReplOutput, ReplPlatform};
+use std::path::{Path, PathBuf};
+use target_lexicon::Triple;
+
+/// A platform with its host, which the `Task`s entered at the prompt are linked with
+pub struct Platform {
+    pub repl: ReplPlatform,
+    host: PathBuf,
+}
+
+/// Read the platform's header, find the modules that apps can import from it, and build its host
+pub fn load_platform(main: &Path) -> Result<Platform, String> {
+    let main = main
+        .canonicalize()
+        .map_err(|err| format!("Could not read the platform {}: {}", main.display(), err))?;
+
+    let src = std::fs::read_to_string(&main)
+        .map_err(|err| format!("Could not read the platform {}: {}", main.display(), err))?;
+
+    let arena = Bump::new();
+    let requires = match roc_parse::module::parse_header(&arena, State::new(src.as_bytes())) {
+        Ok((
+            Module {
+                header: Header::Platform(header),
+                ..
+            },
+            _,
+        )) => header
+            .requires
+            .item
+            .signature
+            .value
+            .extract_spaces()
+            .item
+            .ident
+            .value
+            .to_string(),
+        _ => {
+            return Err(format!(
+                "{} does not start with a platform header.",
+                main.display()
+            ))
+        }
+    };
+
+    let dir = main.parent().unwrap_or_else(|| Path::new("."));
+    let mut modules = Vec::new();
+
+    let entries = std::fs::read_dir(dir)
+        .map_err(|err| format!("Could not read the platform {}: {}", dir.display(), err))?;
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path == main || path.extension().and_then(|ext| ext.to_str()) != Some("roc") {
+            continue;
+        }
+
+        if let Some(module_name) = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|src| module_name(&src))
+        {
+            modules.push(module_name);
+        }
+    }
+
+    modules.sort();
+
+    // Like `roc run`, this writes the host next to the platform's main.roc
+    eprintln!("🔨 Building the platform's host...");
+    let host = rebuild_host(OptLevel::Development, &Triple::host(), &main, None);
+
+    Ok(Platform {
+        repl: ReplPlatform {
+            main,
+            requires,
+            modules,
+        },
+        host,
+    })
+}
+
+/// The name of an interface or hosted module
+fn module_name(src: &str) -> Option<String> {
+    let arena = Bump::new();
+
+    match roc_parse::module::parse_header(&arena, State::new(src.as_bytes())) {
+        Ok((
+            Module {
+                header: Header::Interface(header),
+                ..
+            },
+            _,
+        )) => Some(<&str>::from(header.name.value).to_string()),
+        Ok((
+            Module {
+                header: Header::Hosted(header),
+                ..
+            },
+            _,
+        )) => Some(<&str>::from(header.name.value).to_string()),
+        _ => None,
+    }
+}
+
+/// Generate the app, link it with the platform's host in a temporary directory, and run it with
+/// the REPL's terminal. Returns the result of the `Task`, or why there isn't one.
+#[cfg(not(windows))]
+pub fn run_task<'a>(
+    arena: &'a Bump,
+    platform: &Platform,
+    loaded: MonomorphizedModule<'a>,
+) -> Result<ReplOutput, String> {
+    use roc_build::link::{link, LinkType};
+    use roc_build::program::{self, CodeGenBackend, CodeGenOptions};
+    use roc_load::ExpectMetadata;
+    use roc_repl_eval::gen::format_answer;
+    use roc_repl_expect::run::{dbg_value_in_memory, ChildProcessMsg, ExpectMemory};
+    use std::process::Command;
+    use std::sync::{atomic::AtomicBool, Arc};
+
+    let target = Triple::host();
+    let operating_system = roc_target::OperatingSystem::from(target.operating_system);
+
+    let build_dir = tempfile::tempdir()
+        .map_err(|err| format!("Could not create a directory to build the app in: {}", err))?;
+
+    // This file is never written; code gen only derives the paths of its own outputs from it
+    let app_path = build_dir.path().join("app.roc");
+
+    // Development builds send `dbg` values to the process that runs them
+    let code_gen_options = CodeGenOptions {
+        backend: CodeGenBackend::Llvm,
+        opt_level: OptLevel::Development,
+        emit_debug_info: false,
+        profile: false,
+    };
+
+    let (app_bytes, _, expect_metadata) = program::gen_from_mono_module(
+        arena,
+        loaded,
+        &app_path,
+        &target,
+        code_gen_options,
+        &platform.host,
+        None,
+    );
+
+    let app_o_file = app_path.with_extension(operating_system.object_file_ext());
+    let mut exe_path = build_dir.path().join("app");
+
+    if let Some(extension) = operating_system.executable_file_ext() {
+        exe_path.set_extension(extension);
+    }
+
+    std::fs::write(&app_o_file, &*app_bytes)
+        .map_err(|err| format!("Could not write {}: {}", app_o_file.display(), err))?;
+
+    let inputs = [
+        app_o_file.to_str().unwrap(),
+        platform.host.to_str().unwrap(),
+    ];
+
+    let linked = link(&target, exe_path.clone(), &inputs, LinkType::Executable)
+        .and_then(|(mut child, _)| child.wait());
+
+    match linked {
+        Ok(status) if status.success() => {}
+        Ok(status) => {
+            return Err(format!(
+                "Linking the app with the host failed with {}",
+                status
+            ))
+        }
+        Err(err) => return Err(format!("Could not link the app with the host: {}", err)),
+    }
+
+    let ExpectMetadata {
+        mut expectations,
+        interns,
+        layout_interner,
+    } = expect_metadata;
+
+    let layout_interner = layout_interner.into_global();
+
+    // the app finds this by the process id of its parent
+    let shm_name = format!("/roc_expect_buffer_{}", std::process::id());
+    let mut memory = ExpectMemory::create_or_reuse_mmap(&shm_name);
+
+    let sigchld = Arc::new(AtomicBool::new(false));
+    let sig_id = signal_hook::flag::register(signal_hook::consts::SIGCHLD, Arc::clone(&sigchld))
+        .map_err(|err| format!("Could not wait for the app: {}", err))?;
+
+    let mut child = Command::new(&exe_path)
+        .spawn()
+        .map_err(|err| format!("Could not run the app: {}", err))?;
+
+    // The result is `dbg`ed last, after any `dbg`s of the `Task` itself
+    let mut opt_result = None;
+
+    loop {
+        match memory.wait_for_child(sigchld.clone()) {
+            ChildProcessMsg::Terminate => break,
+            ChildProcessMsg::Expect => {
+                memory.reset();
+            }
+            ChildProcessMsg::Dbg => {
+                let (expr, expr_type) = dbg_value_in_memory(
+                    arena,
+                    &mut expectations,
+                    &interns,
+                    &layout_interner,
+                    &memory,
+                );
+
+                opt_result = Some(ReplOutput {
+                    expr: format_answer(arena, expr).to_string(),
+                    expr_type,
+                });
+
+                memory.reset();
+            }
+        }
+    }
+
+    signal_hook::low_level::unregister(sig_id);
+
+    let status = child
+        .wait()
+        .map_err(|err| format!("Could not wait for the app: {}", err))?;
+
+    match opt_result {
+        Some(result) if status.success() => Ok(result),
+        _ => Err(format!(
+            "The app exited with {} before the task finished.",
+            status
+        )),
+    }
+}
+
+#[cfg(windows)]
+pub fn run_task<'a>(
+    _arena: &'a Bump,
+    _platform: &Platform,
+    _loaded: MonomorphizedModule<'a>,
+) -> Result<ReplOutput, String> {
+    Err("Running tasks in the REPL is not supported on Windows yet.".to_string())
+}
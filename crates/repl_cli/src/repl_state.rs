@@ -1,5 +1,7 @@
-use crate::cli_gen::gen_and_eval_llvm;
+use crate::cli_gen::{gen_and_eval_llvm, EvalTimings};
 use crate::colors::{BLUE, END_COL, GREEN, PINK};
+use crate::completion::{self, parse_interface_header};
+use crate::platform;
 use bumpalo::Bump;
 use const_format::concatcp;
 use roc_collections::MutSet;
@@ -12,11 +14,20 @@ use roc_parse::parser::{EWhen, Either};
 use roc_parse::state::State;
 use roc_parse::{join_alias_to_body, join_ann_to_body};
 use roc_region::all::Loc;
-use roc_repl_eval::gen::{Problems, ReplOutput};
+use roc_repl_eval::gen::{
+    compile_to_platform_mono, compile_to_platform_type, compile_to_record_fields, compile_to_type,
+    PlatformType, Problems, ReplImport, ReplImports, ReplOutput,
+};
+use roc_reporting::report::DEFAULT_PALETTE;
+use roc_target::TargetInfo;
+use rustyline::completion::Completer;
 use rustyline::highlight::{Highlighter, PromptInfo};
 use rustyline::validate::{self, ValidationContext, ValidationResult, Validator};
-use rustyline_derive::{Completer, Helper, Hinter};
+use rustyline::Context;
+use rustyline_derive::{Helper, Hinter};
 use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 use target_lexicon::Triple;
 
 pub const PROMPT: &str = concatcp!(BLUE, "»", END_COL, " ");
@@ -55,6 +66,22 @@ pub const TIPS: &str = concatcp!(
     BLUE,
     "  - ",
     END_COL,
+    ":type <expr> shows the type of an expression, without evaluating it\n\n",
+    BLUE,
+    "  - ",
+    END_COL,
+    ":load <file.roc> brings the values that an interface module exposes into scope\n\n",
+    BLUE,
+    "  - ",
+    END_COL,
+    ":defs lists your definitions so far, and :reset forgets them and the loaded modules\n\n",
+    BLUE,
+    "  - ",
+    END_COL,
+    ":time turns reporting how long each evaluation takes on or off\n\n",
+    BLUE,
+    "  - ",
+    END_COL,
     ":q to quit\n\n",
     BLUE,
     "  - ",
@@ -68,12 +95,17 @@ struct PastDef {
     src: String,
 }
 
-#[derive(Completer, Helper, Hinter)]
+#[derive(Helper, Hinter)]
 pub struct ReplState {
     validator: InputValidator,
     past_defs: Vec<PastDef>,
     past_def_idents: MutSet<String>,
     last_auto_ident: u64,
+    imports: ReplImports,
+    timing: bool,
+    platform: Option<platform::Platform>,
+    /// The past defs that use the platform, which can only be type checked or run with it
+    platform_def_idents: MutSet<String>,
 }
 
 impl Default for ReplState {
@@ -89,6 +121,18 @@ impl ReplState {
             past_defs: Default::default(),
             past_def_idents: Default::default(),
             last_auto_ident: 0,
+            imports: Default::default(),
+            timing: false,
+            platform: None,
+            platform_def_idents: Default::default(),
+        }
+    }
+
+    /// A REPL that runs the `Task`s entered at the prompt with the platform
+    pub(crate) fn with_platform(platform: platform::Platform) -> Self {
+        Self {
+            platform: Some(platform),
+            ..Self::new()
         }
     }
 
@@ -124,10 +168,273 @@ impl ReplState {
                 // TODO add link to repl tutorial(does not yet exist).
                 Ok(TIPS.to_string())
             }
+            ParseOutcome::Type(src) => Ok(self.type_and_format(src, dimensions)),
+            ParseOutcome::Load(path) => Ok(self.load_module(path.trim())),
+            ParseOutcome::Reset => {
+                self.reset();
+
+                Ok("\nCleared all definitions and loaded modules.".to_string())
+            }
+            ParseOutcome::Defs => Ok(self.format_defs()),
+            ParseOutcome::Time => {
+                self.timing = !self.timing;
+
+                if self.timing {
+                    Ok("\nTiming is on.".to_string())
+                } else {
+                    Ok("\nTiming is off.".to_string())
+                }
+            }
             ParseOutcome::Exit => Err(0),
         }
     }
 
+    fn type_and_format(&self, src: &str, dimensions: Option<(usize, usize)>) -> String {
+        if src.trim().is_empty() {
+            return "\nUsage: :type <expr>".to_string();
+        }
+
+        let arena = Bump::new();
+        let (opt_type, problems) = match self.check_with_platform(src) {
+            Some((opt_platform_type, problems)) => {
+                (opt_platform_type.map(|typed| typed.expr_type), problems)
+            }
+            None => compile_to_type(
+                &arena,
+                &self.imports,
+                self.pure_defs(),
+                src,
+                TargetInfo::from(&Triple::host()),
+                DEFAULT_PALETTE,
+            ),
+        };
+
+        let output = opt_type.map(|expr_type| ReplOutput {
+            expr: src.trim().to_string(),
+            expr_type,
+        });
+
+        format_output(output, problems, None, dimensions)
+    }
+
+    fn load_module(&mut self, path: &str) -> String {
+        if path.is_empty() {
+            return "\nUsage: :load <file.roc>".to_string();
+        }
+
+        let path = Path::new(path);
+        let src = match std::fs::read_to_string(path) {
+            Ok(src) => src,
+            Err(err) => return format!("\nCould not read {}: {}", path.display(), err),
+        };
+
+        let (module_name, exposes) = match parse_interface_header(&src) {
+            Some(header) => header,
+            None => {
+                return format!(
+                    "\nOnly interface modules can be loaded, and {} is not one.",
+                    path.display()
+                )
+            }
+        };
+
+        let src_dir = match module_src_dir(path, &module_name) {
+            Some(src_dir) => src_dir,
+            None => {
+                return format!(
+                    "\nThe module {} is not in a directory structure that matches its name.",
+                    module_name
+                )
+            }
+        };
+
+        // All modules are imported from one source directory, like those of an app
+        if let Some(existing_src_dir) = &self.imports.src_dir {
+            if *existing_src_dir != src_dir {
+                return format!(
+                    "\nModules have already been loaded from {}, so {} can't be loaded from {}. Use :reset to start over.",
+                    existing_src_dir.display(),
+                    module_name,
+                    src_dir.display()
+                );
+            }
+        }
+
+        let mut imports = self.imports.clone();
+
+        imports.src_dir = Some(src_dir);
+        imports
+            .modules
+            .retain(|import| import.module_name != module_name);
+        imports.modules.push(ReplImport {
+            module_name: module_name.clone(),
+            exposes: exposes.clone(),
+        });
+
+        // Check the module now, so its errors don't show up on every later input instead
+        let arena = Bump::new();
+        let (_, problems) = compile_to_type(
+            &arena,
+            &imports,
+            self.pure_defs(),
+            "{}",
+            TargetInfo::from(&Triple::host()),
+            DEFAULT_PALETTE,
+        );
+
+        if !problems.errors.is_empty() {
+            return format_output(None, problems, None, None);
+        }
+
+        self.imports = imports;
+
+        if exposes.is_empty() {
+            format!("\nLoaded {GREEN}{module_name}{END_COL}, which exposes nothing.")
+        } else {
+            format!(
+                "\nLoaded {GREEN}{module_name}{END_COL}, exposing {}",
+                exposes.join(", ")
+            )
+        }
+    }
+
+    /// The completions of the word that ends at `pos`, and where the completed part starts
+    pub fn completions(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        completion::complete(line, pos, &self.past_def_idents, &self.imports, |expr| {
+            let arena = Bump::new();
+
+            compile_to_record_fields(
+                &arena,
+                &self.imports,
+                self.pure_defs(),
+                expr,
+                TargetInfo::from(&Triple::host()),
+            )
+        })
+    }
+
+    fn format_defs(&self) -> String {
+        if self.past_defs.is_empty() {
+            return "\nNo definitions yet.".to_string();
+        }
+
+        let mut buf = String::new();
+
+        for def in self.past_defs.iter() {
+            buf.push('\n');
+            buf.push_str(def.src.trim_end());
+            buf.push('\n');
+        }
+
+        buf
+    }
+
+    fn reset(&mut self) {
+        self.past_defs.clear();
+        self.past_def_idents.clear();
+        self.last_auto_ident = 0;
+        self.imports = ReplImports::default();
+        self.platform_def_idents.clear();
+    }
+
+    /// With a platform, type check the input in an app on it, which tells whether the input
+    /// uses the platform
+    fn check_with_platform(&self, src: &str) -> Option<(Option<PlatformType>, Problems)> {
+        let platform = self.platform.as_ref()?;
+        let arena = Bump::new();
+
+        Some(compile_to_platform_type(
+            &arena,
+            &platform.repl,
+            &self.imports,
+            self.past_defs.iter().map(|def| def.src.as_str()),
+            &self.platform_def_idents,
+            src,
+            TargetInfo::from(&Triple::host()),
+            DEFAULT_PALETTE,
+        ))
+    }
+
+    /// The sources of the past defs that don't use the platform, which can be evaluated without it
+    fn pure_defs(&self) -> impl Iterator<Item = &str> {
+        self.past_defs
+            .iter()
+            .filter(|def| !self.platform_def_idents.contains(&def.ident))
+            .map(|def| def.src.as_str())
+    }
+
+    /// Inputs that use the platform can't be evaluated here, so an expression that is a `Task` is
+    /// run by the platform's host instead. Anything else is only type checked, and defs are kept
+    /// to use in later inputs.
+    fn eval_with_platform(
+        &mut self,
+        src: &str,
+        expr_type: String,
+        problems: Problems,
+        pending_past_def: Option<(String, String)>,
+        dimensions: Option<(usize, usize)>,
+    ) -> String {
+        if let Some((ident, def_src)) = pending_past_def {
+            // Don't persist defs that have compile errors
+            if problems.errors.is_empty() {
+                self.platform_def_idents.insert(ident.clone());
+                self.add_past_def(ident.clone(), def_src);
+            }
+
+            let output = ReplOutput {
+                expr: ident,
+                expr_type,
+            };
+
+            return format_output(Some(output), problems, None, dimensions);
+        }
+
+        let platform = match &self.platform {
+            Some(platform) => platform,
+            None => unreachable!("only inputs that use the platform are evaluated with it"),
+        };
+
+        let arena = Bump::new();
+        let start = Instant::now();
+
+        let (opt_loaded, run_problems) = compile_to_platform_mono(
+            &arena,
+            &platform.repl,
+            &self.imports,
+            self.past_defs.iter().map(|def| def.src.as_str()),
+            src,
+            TargetInfo::from(&Triple::host()),
+            DEFAULT_PALETTE,
+        );
+
+        let mut buf = match opt_loaded {
+            Some(loaded) if run_problems.errors.is_empty() => {
+                match platform::run_task(&arena, platform, loaded) {
+                    Ok(output) => format_output(Some(output), problems, None, dimensions),
+                    Err(message) => format!("\n{}", message),
+                }
+            }
+            _ => {
+                // Only a `Task` can be run
+                let output = ReplOutput {
+                    expr: src.trim().to_string(),
+                    expr_type,
+                };
+
+                return format_output(Some(output), problems, None, dimensions);
+            }
+        };
+
+        if self.timing {
+            buf.push_str(&format!(
+                "\n{PINK}built and ran in {:.1} ms{END_COL}",
+                start.elapsed().as_secs_f64() * 1000.0
+            ));
+        }
+
+        buf
+    }
+
     pub fn eval_and_format(&mut self, src: &str, dimensions: Option<(usize, usize)>) -> String {
         let arena = Bump::new();
         let pending_past_def;
@@ -238,9 +545,39 @@ impl ReplState {
                 // can be evaluated as expressions.
                 return String::new();
             }
-            ParseOutcome::Empty | ParseOutcome::Help | ParseOutcome::Exit => unreachable!(),
+            ParseOutcome::Empty
+            | ParseOutcome::Help
+            | ParseOutcome::Type(_)
+            | ParseOutcome::Load(_)
+            | ParseOutcome::Reset
+            | ParseOutcome::Defs
+            | ParseOutcome::Time
+            | ParseOutcome::Exit => unreachable!(),
         };
 
+        match self.check_with_platform(src) {
+            Some((
+                Some(PlatformType {
+                    expr_type,
+                    uses_platform: true,
+                }),
+                problems,
+            )) => {
+                return self.eval_with_platform(
+                    src,
+                    expr_type,
+                    problems,
+                    pending_past_def,
+                    dimensions,
+                );
+            }
+            Some((None, problems)) => return format_output(None, problems, None, dimensions),
+            // Inputs that don't use the platform are evaluated like without one
+            Some((Some(_), _)) | None => {}
+        }
+
+        let mut timings = EvalTimings::default();
+
         // Record e.g. "val1" as a past def, unless our input was exactly the name of
         // an existing identifer (e.g. I just typed "val1" into the prompt - there's no
         // need to reassign "val1" to "val2" just because I wanted to see what its value was!)
@@ -250,18 +587,22 @@ impl ReplState {
                     opt_var_name = Some(existing_ident);
 
                     gen_and_eval_llvm(
-                        self.past_defs.iter().map(|def| def.src.as_str()),
+                        &self.imports,
+                        self.pure_defs(),
                         src,
                         Triple::host(),
                         OptLevel::Normal,
+                        &mut timings,
                     )
                 }
                 None => {
                     let (output, problems) = gen_and_eval_llvm(
-                        self.past_defs.iter().map(|def| def.src.as_str()),
+                        &self.imports,
+                        self.pure_defs(),
                         src,
                         Triple::host(),
                         OptLevel::Normal,
+                        &mut timings,
                     );
 
                     // Don't persist defs that have compile errors
@@ -284,7 +625,21 @@ impl ReplState {
             self.add_past_def(ident, src);
         }
 
-        format_output(output, problems, opt_var_name, dimensions)
+        let mut buf = format_output(output, problems, opt_var_name, dimensions);
+
+        if self.timing {
+            if !buf.ends_with('\n') {
+                buf.push('\n');
+            }
+
+            buf.push_str(&format!(
+                "{PINK}compiled in {:.1} ms, ran in {:.1} ms{END_COL}\n",
+                timings.compile.as_secs_f64() * 1000.0,
+                timings.run.as_secs_f64() * 1000.0
+            ));
+        }
+
+        buf
     }
 
     fn next_auto_ident(&mut self) -> u64 {
@@ -310,13 +665,59 @@ enum ParseOutcome<'a> {
     SyntaxErr,
     Empty,
     Help,
+    /// `:type <expr>`
+    Type(&'a str),
+    /// `:load <file.roc>`
+    Load(&'a str),
+    Reset,
+    Defs,
+    Time,
     Exit,
 }
 
+/// The directory to import a module from: the one that its path starts in,
+/// e.g. `src` for the module `Foo.Bar` in `src/Foo/Bar.roc`
+fn module_src_dir(path: &Path, module_name: &str) -> Option<PathBuf> {
+    let mut dir = path.canonicalize().ok()?.parent()?.to_path_buf();
+
+    for _ in 1..module_name.split('.').count() {
+        dir = dir.parent()?.to_path_buf();
+    }
+
+    Some(dir)
+}
+
+/// The rest of the input after a command that takes an argument, like `:type`
+fn command_argument<'a>(line: &'a str, command: &str) -> Option<&'a str> {
+    let line = line.trim_start();
+    let start = line.get(..command.len())?;
+    let rest = &line[command.len()..];
+
+    // e.g. `:typeof` is not `:type`
+    if start.eq_ignore_ascii_case(command)
+        && (rest.is_empty() || rest.starts_with(char::is_whitespace))
+    {
+        Some(rest.trim_start())
+    } else {
+        None
+    }
+}
+
 fn parse_src<'a>(arena: &'a Bump, line: &'a str) -> ParseOutcome<'a> {
+    if let Some(src) = command_argument(line, ":type") {
+        return ParseOutcome::Type(src);
+    }
+
+    if let Some(path) = command_argument(line, ":load") {
+        return ParseOutcome::Load(path);
+    }
+
     match line.trim().to_lowercase().as_str() {
         "" => ParseOutcome::Empty,
         ":help" => ParseOutcome::Help,
+        ":reset" => ParseOutcome::Reset,
+        ":defs" => ParseOutcome::Defs,
+        ":time" => ParseOutcome::Time,
         ":exit" | ":quit" | ":q" => ParseOutcome::Exit,
         _ => {
             let src_bytes = line.as_bytes();
@@ -509,8 +910,14 @@ pub fn is_incomplete(input: &str) -> bool {
             // them until they enter a blank line!
             !input.ends_with('\n')
         }
+        // The expression of a `:type` can span lines just like any other
+        ParseOutcome::Type(src) => is_incomplete(src),
         ParseOutcome::Empty
         | ParseOutcome::Help
+        | ParseOutcome::Load(_)
+        | ParseOutcome::Reset
+        | ParseOutcome::Defs
+        | ParseOutcome::Time
         | ParseOutcome::Exit
         | ParseOutcome::ValueDef(_)
         | ParseOutcome::TypeDef(_)
@@ -519,6 +926,19 @@ pub fn is_incomplete(input: &str) -> bool {
     }
 }
 
+impl Completer for ReplState {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.completions(line, pos))
+    }
+}
+
 impl Highlighter for ReplState {
     fn has_continuation_prompt(&self) -> bool {
         true
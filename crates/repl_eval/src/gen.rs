@@ -5,13 +5,21 @@ use roc_problem::Severity;
 use roc_reporting::report::Palette;
 use std::path::PathBuf;
 
+use roc_can::expr::Expr as CanExpr;
+use roc_can::traverse::{walk_expr, Visitor};
+use roc_collections::{MutMap, MutSet};
 use roc_fmt::annotation::Formattable;
 use roc_fmt::annotation::{Newlines, Parens};
-use roc_load::{LoadingProblem, MonomorphizedModule};
+use roc_load::{LoadedModule, LoadingProblem, MonomorphizedModule};
+use roc_module::symbol::{Interns, ModuleId};
 use roc_parse::ast::Expr;
-use roc_region::all::LineInfo;
-use roc_reporting::report::{can_problem, type_problem, RocDocAllocator};
+use roc_region::all::{LineInfo, Region};
+use roc_reporting::report::{can_problem, type_problem, Report, RocDocAllocator, DEFAULT_PALETTE};
+use roc_solve_problem::TypeError;
 use roc_target::TargetInfo;
+use roc_types::pretty_print::{name_and_print_var, DebugPrint};
+use roc_types::subs::{Content, FlatType, Variable};
+use roc_types::types::AliasKind;
 
 #[derive(Debug)]
 pub struct ReplOutput {
@@ -44,16 +52,90 @@ impl Problems {
     }
 }
 
+/// An interface module that was brought into scope with `:load`
+#[derive(Debug, Clone)]
+pub struct ReplImport {
+    pub module_name: String,
+    pub exposes: Vec<String>,
+}
+
+/// The modules that the REPL's expressions import
+#[derive(Default, Debug, Clone)]
+pub struct ReplImports {
+    /// The directory that the imported modules are loaded from
+    pub src_dir: Option<PathBuf>,
+    pub modules: Vec<ReplImport>,
+}
+
+impl ReplImports {
+    fn src_dir(&self) -> PathBuf {
+        match &self.src_dir {
+            Some(src_dir) => src_dir.clone(),
+            None => PathBuf::from("fake/test/path"),
+        }
+    }
+}
+
+/// A platform that the REPL runs effects with, from `roc repl --platform`
+#[derive(Debug, Clone)]
+pub struct ReplPlatform {
+    /// The platform's `main.roc`
+    pub main: PathBuf,
+    /// What apps provide to the platform, like `main`
+    pub requires: String,
+    /// The modules next to `main.roc`, which the REPL's apps import like `pf.Stdout`
+    pub modules: Vec<String>,
+}
+
+/// What an app on a [ReplPlatform] provides to the platform
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlatformMain {
+    /// Runs the expression, and `dbg`s its result for the REPL to print.
+    /// This only type checks if the expression is a `Task` of the platform's `Task` module.
+    Run,
+    /// A `crash`, so that the app type checks whatever the type of the expression is
+    Crash,
+}
+
+/// The type of an expression in an app on a [ReplPlatform]
+#[derive(Debug)]
+pub struct PlatformType {
+    pub expr_type: String,
+    /// Whether the expression refers to the platform's modules, or to past defs that do.
+    /// Expressions that don't are evaluated without the platform.
+    pub uses_platform: bool,
+}
+
 pub fn compile_to_mono<'a, 'i, I: Iterator<Item = &'i str>>(
     arena: &'a Bump,
+    imports: &ReplImports,
     defs: I,
     expr: &str,
     target_info: TargetInfo,
     palette: Palette,
+) -> (Option<MonomorphizedModule<'a>>, Problems) {
+    let (bytes_before_expr, module_src) = promote_expr_to_module(arena, imports, defs, expr);
+
+    monomorphize_module(
+        arena,
+        module_src,
+        bytes_before_expr,
+        imports.src_dir(),
+        target_info,
+        palette,
+    )
+}
+
+/// Load the REPL's module up to monomorphization
+fn monomorphize_module<'a>(
+    arena: &'a Bump,
+    module_src: &'a str,
+    bytes_before_expr: usize,
+    src_dir: PathBuf,
+    target_info: TargetInfo,
+    palette: Palette,
 ) -> (Option<MonomorphizedModule<'a>>, Problems) {
     let filename = PathBuf::from("");
-    let src_dir = PathBuf::from("fake/test/path");
-    let (bytes_before_expr, module_src) = promote_expr_to_module(arena, defs, expr);
     let loaded = roc_load::load_and_monomorphize_from_str(
         arena,
         filename,
@@ -92,6 +174,7 @@ pub fn compile_to_mono<'a, 'i, I: Iterator<Item = &'i str>>(
     };
 
     let MonomorphizedModule {
+        module_id,
         interns,
         sources,
         can_problems,
@@ -99,10 +182,311 @@ pub fn compile_to_mono<'a, 'i, I: Iterator<Item = &'i str>>(
         ..
     } = &mut loaded;
 
-    let mut problems = Problems::default();
+    let problems = collect_problems(
+        *module_id,
+        bytes_before_expr,
+        sources,
+        interns,
+        can_problems,
+        type_problems,
+        palette,
+    );
 
-    let errors = &mut problems.errors;
-    let warnings = &mut problems.warnings;
+    (Some(loaded), problems)
+}
+
+/// Infer the type of the expression, without evaluating it
+pub fn compile_to_type<'a, 'i, I: Iterator<Item = &'i str>>(
+    arena: &'a Bump,
+    imports: &ReplImports,
+    defs: I,
+    expr: &str,
+    target_info: TargetInfo,
+    palette: Palette,
+) -> (Option<String>, Problems) {
+    let (bytes_before_expr, module_src) = promote_expr_to_module(arena, imports, defs, expr);
+
+    print_type(typecheck_module(
+        arena,
+        module_src,
+        bytes_before_expr,
+        imports.src_dir(),
+        target_info,
+        palette,
+    ))
+}
+
+/// Infer the type of the expression in an app on the platform, without running it.
+/// `platform_defs` are the names of the past defs that use the platform.
+#[allow(clippy::too_many_arguments)]
+pub fn compile_to_platform_type<'a, 'i, I: Iterator<Item = &'i str>>(
+    arena: &'a Bump,
+    platform: &ReplPlatform,
+    imports: &ReplImports,
+    defs: I,
+    platform_defs: &MutSet<String>,
+    expr: &str,
+    target_info: TargetInfo,
+    palette: Palette,
+) -> (Option<PlatformType>, Problems) {
+    let (bytes_before_expr, module_src) =
+        promote_expr_to_platform_app(arena, platform, imports, defs, expr, PlatformMain::Crash);
+
+    match typecheck_module(
+        arena,
+        module_src,
+        bytes_before_expr,
+        imports.src_dir(),
+        target_info,
+        palette,
+    ) {
+        (Some((mut loaded, expr_var)), problems) => {
+            let uses_platform = uses_platform(&loaded, platform, platform_defs, bytes_before_expr);
+
+            let expr_type = name_and_print_var(
+                expr_var,
+                loaded.solved.inner_mut(),
+                loaded.module_id,
+                &loaded.interns,
+                DebugPrint::NOTHING,
+            );
+
+            let platform_type = PlatformType {
+                expr_type,
+                uses_platform,
+            };
+
+            (Some(platform_type), problems)
+        }
+        (None, problems) => (None, problems),
+    }
+}
+
+/// Monomorphize an app on the platform that runs the expression, which must be a `Task`.
+/// Running it `dbg`s the result of the `Task`.
+pub fn compile_to_platform_mono<'a, 'i, I: Iterator<Item = &'i str>>(
+    arena: &'a Bump,
+    platform: &ReplPlatform,
+    imports: &ReplImports,
+    defs: I,
+    expr: &str,
+    target_info: TargetInfo,
+    palette: Palette,
+) -> (Option<MonomorphizedModule<'a>>, Problems) {
+    let (bytes_before_expr, module_src) =
+        promote_expr_to_platform_app(arena, platform, imports, defs, expr, PlatformMain::Run);
+
+    monomorphize_module(
+        arena,
+        module_src,
+        bytes_before_expr,
+        imports.src_dir(),
+        target_info,
+        palette,
+    )
+}
+
+/// Whether the expression refers to a module of the platform, or to a past def that does
+fn uses_platform(
+    loaded: &LoadedModule,
+    platform: &ReplPlatform,
+    platform_defs: &MutSet<String>,
+    bytes_before_expr: usize,
+) -> bool {
+    let platform_dir = platform.main.parent();
+    let platform_modules = loaded
+        .sources
+        .iter()
+        .filter(|(_, (path, _))| path.parent() == platform_dir)
+        .map(|(module_id, _)| *module_id)
+        .collect();
+
+    let mut visitor = PlatformUse {
+        home: loaded.module_id,
+        interns: &loaded.interns,
+        platform_modules,
+        platform_defs,
+        bytes_before_expr: bytes_before_expr as u32,
+        found: false,
+    };
+
+    if let Some(declarations) = loaded.declarations_by_id.get(&loaded.module_id) {
+        visitor.visit_decls(declarations);
+    }
+
+    visitor.found
+}
+
+/// Looks for lookups of the platform in the expression, which starts at `bytes_before_expr`
+struct PlatformUse<'a> {
+    home: ModuleId,
+    interns: &'a Interns,
+    platform_modules: MutSet<ModuleId>,
+    platform_defs: &'a MutSet<String>,
+    bytes_before_expr: u32,
+    found: bool,
+}
+
+impl Visitor for PlatformUse<'_> {
+    fn visit_expr(&mut self, expr: &CanExpr, region: Region, var: Variable) {
+        if region.start().offset >= self.bytes_before_expr {
+            if let CanExpr::Var(symbol, _) | CanExpr::AbilityMember(symbol, _, _) = expr {
+                let module_id = symbol.module_id();
+
+                self.found |= self.platform_modules.contains(&module_id)
+                    || (module_id == self.home
+                        && self.platform_defs.contains(symbol.as_str(self.interns)));
+            }
+        }
+
+        walk_expr(self, expr, var);
+    }
+}
+
+fn print_type(typed: (Option<(LoadedModule, Variable)>, Problems)) -> (Option<String>, Problems) {
+    match typed {
+        (Some((mut loaded, expr_var)), problems) => {
+            let expr_type = name_and_print_var(
+                expr_var,
+                loaded.solved.inner_mut(),
+                loaded.module_id,
+                &loaded.interns,
+                DebugPrint::NOTHING,
+            );
+
+            (Some(expr_type), problems)
+        }
+        (None, problems) => (None, problems),
+    }
+}
+
+/// The names of the fields of the expression, if it is a record, sorted.
+/// The REPL completes them after a `.`.
+pub fn compile_to_record_fields<'a, 'i, I: Iterator<Item = &'i str>>(
+    arena: &'a Bump,
+    imports: &ReplImports,
+    defs: I,
+    expr: &str,
+    target_info: TargetInfo,
+) -> Vec<String> {
+    let (bytes_before_expr, module_src) = promote_expr_to_module(arena, imports, defs, expr);
+    let typed = typecheck_module(
+        arena,
+        module_src,
+        bytes_before_expr,
+        imports.src_dir(),
+        target_info,
+        DEFAULT_PALETTE,
+    );
+
+    let (loaded, mut var) = match typed {
+        (Some(typed), _) => typed,
+        (None, _) => return Vec::new(),
+    };
+
+    let subs = loaded.solved.inner();
+
+    loop {
+        match subs.get_content_without_compacting(var) {
+            Content::Alias(_, _, real_var, AliasKind::Structural) => {
+                var = *real_var;
+            }
+            Content::Structure(FlatType::Record(fields, ext)) => {
+                let mut names: Vec<String> = match fields.unsorted_iterator(subs, *ext) {
+                    Ok(fields) => fields.map(|(name, _)| name.as_str().to_string()).collect(),
+                    Err(_) => Vec::new(),
+                };
+
+                names.sort();
+
+                return names;
+            }
+            _ => return Vec::new(),
+        }
+    }
+}
+
+/// Type check the REPL's module, and find the type variable of its expression
+fn typecheck_module<'a>(
+    arena: &'a Bump,
+    module_src: &'a str,
+    bytes_before_expr: usize,
+    src_dir: PathBuf,
+    target_info: TargetInfo,
+    palette: Palette,
+) -> (Option<(LoadedModule, Variable)>, Problems) {
+    let filename = PathBuf::from("");
+    let loaded = roc_load::load_and_typecheck_str(
+        arena,
+        filename,
+        module_src,
+        src_dir,
+        target_info,
+        roc_reporting::report::RenderTarget::ColorTerminal,
+        RocCacheDir::Persistent(cache::roc_cache_dir().as_path()),
+        palette,
+    );
+
+    let mut loaded = match loaded {
+        Ok(v) => v,
+        Err(LoadingProblem::FormattedReport(report)) => {
+            return (
+                None,
+                Problems {
+                    errors: vec![report],
+                    warnings: Vec::new(),
+                },
+            );
+        }
+        Err(e) => {
+            todo!("error while loading module: {:?}", e)
+        }
+    };
+
+    let problems = collect_problems(
+        loaded.module_id,
+        bytes_before_expr,
+        &loaded.sources,
+        &loaded.interns,
+        &mut loaded.can_problems,
+        &mut loaded.type_problems,
+        palette,
+    );
+
+    if !problems.errors.is_empty() {
+        return (None, problems);
+    }
+
+    // With a platform, the app provides something other than the expression
+    let opt_expr_var = loaded
+        .declarations_by_id
+        .get(&loaded.module_id)
+        .and_then(|declarations| {
+            declarations
+                .symbols
+                .iter()
+                .position(|symbol| symbol.value.as_str(&loaded.interns) == REPL_OUTPUT)
+                .map(|index| declarations.variables[index])
+        });
+
+    match opt_expr_var {
+        Some(expr_var) => (Some((loaded, expr_var)), problems),
+        None => (None, problems),
+    }
+}
+
+/// Render the problems of all modules. In the REPL module itself, problems in the past defs
+/// (which were reported when they were entered) are left out; imported modules only report errors.
+fn collect_problems(
+    repl_module_id: ModuleId,
+    bytes_before_expr: usize,
+    sources: &MutMap<ModuleId, (PathBuf, Box<str>)>,
+    interns: &Interns,
+    can_problems: &mut MutMap<ModuleId, Vec<roc_problem::can::Problem>>,
+    type_problems: &mut MutMap<ModuleId, Vec<TypeError>>,
+    palette: Palette,
+) -> Problems {
+    let mut problems = Problems::default();
 
     for (home, (module_path, src)) in sources.iter() {
         let can_probs = can_problems.remove(home).unwrap_or_default();
@@ -114,7 +498,8 @@ pub fn compile_to_mono<'a, 'i, I: Iterator<Item = &'i str>>(
             continue;
         }
 
-        let line_info = LineInfo::new(module_src);
+        let is_repl_module = *home == repl_module_id;
+        let line_info = LineInfo::new(src);
         let src_lines: Vec<&str> = src.split('\n').collect();
 
         // Report parsing and canonicalization problems
@@ -125,57 +510,164 @@ pub fn compile_to_mono<'a, 'i, I: Iterator<Item = &'i str>>(
             // because they must be part of the defs (excluding the most renently added def,
             // if that's the one being evaluated) and therefore not things we should show.
             // This filters out things like shadowing warnings and unused def warnings.
-            if problem.region().unwrap_or_default().end().offset as usize >= bytes_before_expr {
+            if !is_repl_module
+                || problem.region().unwrap_or_default().end().offset as usize >= bytes_before_expr
+            {
                 let report = can_problem(&alloc, &line_info, module_path.clone(), problem);
-                let severity = report.severity;
-                let mut buf = String::new();
-
-                report.render_color_terminal(&mut buf, &alloc, &palette);
-
-                match severity {
-                    Severity::Warning => {
-                        warnings.push(buf);
-                    }
-                    Severity::RuntimeError => {
-                        errors.push(buf);
-                    }
-                }
+
+                push_report(report, &alloc, &palette, is_repl_module, &mut problems);
             }
         }
 
         for problem in type_probs {
             if let Some(report) = type_problem(&alloc, &line_info, module_path.clone(), problem) {
-                let severity = report.severity;
-                let mut buf = String::new();
-
-                report.render_color_terminal(&mut buf, &alloc, &palette);
-
-                match severity {
-                    Severity::Warning => {
-                        warnings.push(buf);
-                    }
-                    Severity::RuntimeError => {
-                        errors.push(buf);
-                    }
-                }
+                push_report(report, &alloc, &palette, is_repl_module, &mut problems);
             }
         }
     }
 
-    (Some(loaded), problems)
+    problems
+}
+
+fn push_report<'b>(
+    report: Report<'b>,
+    alloc: &'b RocDocAllocator<'b>,
+    palette: &'b Palette,
+    is_repl_module: bool,
+    problems: &mut Problems,
+) {
+    let severity = report.severity;
+    let mut buf = String::new();
+
+    report.render_color_terminal(&mut buf, alloc, palette);
+
+    match severity {
+        Severity::Warning => {
+            if is_repl_module {
+                problems.warnings.push(buf);
+            }
+        }
+        Severity::RuntimeError => {
+            problems.errors.push(buf);
+        }
+    }
 }
 
+/// The def in the REPL's module that the expression is assigned to
+const REPL_OUTPUT: &str = "replOutput";
+
 fn promote_expr_to_module<'a, 'i, I: Iterator<Item = &'i str>>(
     arena: &'a Bump,
+    imports: &ReplImports,
     defs: I,
     expr: &str,
 ) -> (usize, &'a str) {
-    const REPL_MODULE_HEADER: &str = "app \"app\" provides [replOutput] to \"./platform\"\n\n";
+    let mut buffer = bumpalo::collections::string::String::from_str_in("app \"app\" ", arena);
+
+    push_imports(&mut buffer, &[], imports);
+
+    buffer.push_str("provides [replOutput] to \"./platform\"\n\n");
+
+    let bytes_before_expr = push_defs_and_expr(&mut buffer, defs, expr);
+
+    (bytes_before_expr, buffer.into_bump_str())
+}
+
+fn promote_expr_to_platform_app<'a, 'i, I: Iterator<Item = &'i str>>(
+    arena: &'a Bump,
+    platform: &ReplPlatform,
+    imports: &ReplImports,
+    defs: I,
+    expr: &str,
+    main: PlatformMain,
+) -> (usize, &'a str) {
+    // The warnings about unused imports are in the header, so they aren't reported
+    let platform_modules: Vec<&str> = platform.modules.iter().map(String::as_str).collect();
+
+    // e.g. Windows paths have backslashes, which start escapes in strings
+    let main_path = platform
+        .main
+        .to_string_lossy()
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"");
+
+    let mut buffer =
+        bumpalo::collections::string::String::from_str_in("app \"app\" packages { pf: \"", arena);
+
+    buffer.push_str(&main_path);
+    buffer.push_str("\" } ");
+
+    push_imports(&mut buffer, &platform_modules, imports);
+
+    buffer.push_str("provides [");
+    buffer.push_str(&platform.requires);
+    buffer.push_str("] to pf\n\n");
+
+    let bytes_before_expr = push_defs_and_expr(&mut buffer, defs, expr);
+
+    buffer.push('\n');
+    buffer.push_str(&platform.requires);
+    buffer.push_str(" =\n");
+
+    match main {
+        PlatformMain::Run => {
+            buffer.push_str("    result <- Task.attempt replOutput\n");
+            buffer.push_str("    dbg result\n\n");
+            buffer.push_str("    Task.succeed {}\n");
+        }
+        PlatformMain::Crash => {
+            buffer.push_str("    crash \"The REPL only type checks this app\"\n");
+        }
+    }
+
+    (bytes_before_expr, buffer.into_bump_str())
+}
+
+/// e.g. `imports [pf.Stdout, Geometry.{ area }] `
+fn push_imports(
+    buffer: &mut bumpalo::collections::string::String,
+    platform_modules: &[&str],
+    imports: &ReplImports,
+) {
+    if platform_modules.is_empty() && imports.modules.is_empty() {
+        return;
+    }
+
+    buffer.push_str("imports [");
+
+    let platform_imports = platform_modules
+        .iter()
+        .map(|module_name| format!("pf.{module_name}"));
+
+    let module_imports = imports.modules.iter().map(|import| {
+        if import.exposes.is_empty() {
+            import.module_name.clone()
+        } else {
+            format!("{}.{{ {} }}", import.module_name, import.exposes.join(", "))
+        }
+    });
+
+    for (index, import) in platform_imports.chain(module_imports).enumerate() {
+        if index > 0 {
+            buffer.push_str(", ");
+        }
+
+        buffer.push_str(&import);
+    }
+
+    buffer.push_str("] ");
+}
+
+/// Push the defs, and then the expression as `replOutput`.
+/// Returns the number of bytes before the expression.
+fn push_defs_and_expr<'i, I: Iterator<Item = &'i str>>(
+    buffer: &mut bumpalo::collections::string::String,
+    defs: I,
+    expr: &str,
+) -> usize {
     const REPL_MODULE_MAIN_DEF: &str = "replOutput =\n";
     const INDENT: &str = "    ";
 
-    let mut buffer = bumpalo::collections::string::String::from_str_in(REPL_MODULE_HEADER, arena);
-
     for line in defs {
         // don't indent the defs
         buffer.push_str(line);
@@ -193,5 +685,5 @@ fn promote_expr_to_module<'a, 'i, I: Iterator<Item = &'i str>>(
         buffer.push('\n');
     }
 
-    (bytes_before_expr, buffer.into_bump_str())
+    bytes_before_expr
 }
@@ -0,0 +1,63 @@
+//! The counters of `roc test --coverage`.
+//!
+//! Every top-level `expect` runs in a child process, so the counters live in memory that is
+//! shared with the children, and every child adds its hits to the same counters.
+
+use roc_error_macros::internal_error;
+
+pub struct CoverageCounters {
+    ptr: *mut u64,
+    len: usize,
+}
+
+impl CoverageCounters {
+    pub fn new(len: usize) -> Self {
+        // mmap doesn't accept a size of 0
+        let size = len.max(1) * std::mem::size_of::<u64>();
+
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                size,
+                libc::PROT_WRITE | libc::PROT_READ,
+                libc::MAP_SHARED | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+
+        if ptr == libc::MAP_FAILED {
+            internal_error!("failed to mmap the coverage counters");
+        }
+
+        // anonymous mappings start out zeroed
+        Self {
+            ptr: ptr.cast(),
+            len,
+        }
+    }
+
+    /// Point the `CoverageHit`s of the test dylib at these counters.
+    /// This must happen before any child process is forked.
+    pub fn set_in(&self, lib: &libloading::Library) {
+        let set_coverage_counters: libloading::Symbol<unsafe extern "C" fn(*mut u64)> = unsafe {
+            lib.get(b"set_coverage_counters")
+        }
+        .unwrap_or_else(|error| internal_error!("Unable to find `set_coverage_counters`: {error}"));
+
+        unsafe { set_coverage_counters(self.ptr) };
+    }
+
+    /// How often each counter was hit, by the index of the counter
+    pub fn counts(&self) -> &[u64] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl Drop for CoverageCounters {
+    fn drop(&mut self) {
+        let size = self.len.max(1) * std::mem::size_of::<u64>();
+
+        unsafe { libc::munmap(self.ptr.cast(), size) };
+    }
+}
@@ -1,5 +1,9 @@
 //! Supports evaluating `expect` and printing contextual information when they fail.
-#[cfg(not(windows))]
+use roc_can::expr::ExpectLookup;
+use roc_module::symbol::Symbol;
+use roc_types::subs::Subs;
+
+#[cfg(not(any(windows, target_family = "wasm")))]
 use {
     roc_module::symbol::Interns,
     roc_mono::{
@@ -9,18 +13,23 @@ use {
     roc_parse::ast::Expr,
     roc_repl_eval::{eval::jit_to_ast, ReplAppMemory},
     roc_target::TargetInfo,
-    roc_types::subs::{Subs, Variable},
+    roc_types::subs::Variable,
 };
 
-#[cfg(not(windows))]
+#[cfg(not(any(windows, target_family = "wasm")))]
 mod app;
-#[cfg(not(windows))]
+#[cfg(not(any(windows, target_family = "wasm")))]
+pub mod coverage;
+#[cfg(not(any(windows, target_family = "wasm")))]
+mod property;
+#[cfg(not(any(windows, target_family = "wasm")))]
 pub mod run;
+pub mod wasm;
 
-#[cfg(not(windows))]
+#[cfg(not(any(windows, target_family = "wasm")))]
 use app::{ExpectMemory, ExpectReplApp};
 
-#[cfg(not(windows))]
+#[cfg(not(any(windows, target_family = "wasm")))]
 #[allow(clippy::too_many_arguments)]
 pub fn get_values<'a>(
     target_info: TargetInfo,
@@ -88,13 +97,34 @@ pub fn get_values<'a>(
     (app.offset, result, result_vars)
 }
 
-#[cfg(not(windows))]
+pub(crate) fn split_expect_lookups(subs: &Subs, lookups: &[ExpectLookup]) -> Vec<Symbol> {
+    lookups
+        .iter()
+        .filter_map(
+            |ExpectLookup {
+                 symbol,
+                 var,
+                 ability_info: _,
+             }| {
+                // mono will have dropped lookups that resolve to functions, so we should not keep
+                // them either.
+                if subs.is_function(*var) {
+                    None
+                } else {
+                    Some(*symbol)
+                }
+            },
+        )
+        .collect()
+}
+
+#[cfg(not(any(windows, target_family = "wasm")))]
 #[cfg(test)]
 mod test {
     use indoc::indoc;
     use pretty_assertions::assert_eq;
     use roc_gen_llvm::{llvm::build::LlvmBackendMode, run_roc::RocCallResult, run_roc_dylib};
-    use roc_load::{ExecutionMode, LoadConfig, LoadMonomorphizedError, Threading};
+    use roc_load::{ExecutionMode, LoadConfig, LoadMonomorphizedError, TestOptions, Threading};
     use roc_packaging::cache::RocCacheDir;
     use roc_reporting::report::{RenderTarget, DEFAULT_PALETTE};
     use target_lexicon::Triple;
@@ -124,7 +154,7 @@ mod test {
             render: RenderTarget::ColorTerminal,
             palette: DEFAULT_PALETTE,
             threading: Threading::Single,
-            exec_mode: ExecutionMode::Test,
+            exec_mode: ExecutionMode::Test(TestOptions::default()),
         };
         let loaded = match roc_load::load_and_monomorphize_from_str(
             arena,
@@ -170,7 +200,7 @@ mod test {
         unsafe { set_shared_buffer((shared_buffer.as_mut_ptr(), BUFFER_SIZE), &mut result) };
 
         let mut writer = Vec::with_capacity(1024);
-        let (_failed, _passed) = crate::run::run_expects_with_memory(
+        let _results = crate::run::run_expects_with_memory(
             &mut writer,
             RenderTarget::ColorTerminal,
             arena,
@@ -180,6 +210,7 @@ mod test {
             &mut expectations,
             expects,
             &mut memory,
+            0,
         )
         .unwrap();
 
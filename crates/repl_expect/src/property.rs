@@ -0,0 +1,999 @@
+//! Property `expect`s are top-level `expect`s that are a function, like
+//!
+//!     expect \list -> List.len (List.reverse list) == List.len list
+//!
+//! `roc test` generates their inputs from the types of the arguments, and shrinks an input
+//! that makes the `expect` fail before reporting it. Inputs live in host memory, and the
+//! compiled `expect` reads them through the table that `set_expect_inputs` points it at.
+
+use bumpalo::Bump;
+use roc_builtins::bitcode::{FloatWidth, IntWidth};
+use roc_derive_key::generate::{FlatGenerate, FlatGenerateKey};
+use roc_derive_key::DeriveError;
+use roc_error_macros::internal_error;
+use roc_module::symbol::Symbol;
+use roc_mono::layout::{
+    cmp_fields, GlobalLayoutInterner, InLayout, Layout, LayoutCache, LayoutInterner, UnionLayout,
+};
+use roc_target::{PtrWidth, TargetInfo};
+use roc_types::subs::{Content, FlatType, GetSubsSlice, RecordField, Subs, Variable};
+
+/// A small, seedable random number generator (SplitMix64), so a seed always gives the same inputs
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`, or 0 if `n` is 0
+    fn below(&mut self, n: u64) -> u64 {
+        if n == 0 {
+            0
+        } else {
+            self.next_u64() % n
+        }
+    }
+
+    fn one_in(&mut self, n: u64) -> bool {
+        self.below(n) == 0
+    }
+
+    /// A number in `-size..=size`
+    fn around_zero(&mut self, size: u32) -> i128 {
+        self.below(2 * size as u64 + 1) as i128 - size as i128
+    }
+}
+
+/// How to generate and store a value of one type
+#[derive(Debug)]
+enum Shape {
+    Int(IntWidth),
+    Float(FloatWidth),
+    Dec,
+    Str,
+    List {
+        elem: Box<Shape>,
+        elem_size: usize,
+        elem_alignment: usize,
+    },
+    /// A record, tuple or single-tag union, with each field's offset. The fields are in memory order.
+    Struct(Vec<(Shape, usize)>),
+    /// A tag union without payloads, stored as its tag id in a single byte
+    Enum {
+        tags: u16,
+    },
+    /// A non-recursive tag union, with the payload of each tag in tag id order
+    Union {
+        tags: Vec<Vec<(Shape, usize)>>,
+        tag_id_offset: usize,
+        tag_id_size: usize,
+    },
+}
+
+/// One generated value, shaped like the `Shape` it was generated from
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    /// Integers, and `Dec`s as their underlying `i128`
+    Int(i128),
+    Float(f64),
+    Str(String),
+    List(Vec<Value>),
+    Fields(Vec<Value>),
+    Tag(u16, Vec<Value>),
+}
+
+/// The generated inputs of a property `expect`
+pub(crate) struct Property {
+    inputs: Vec<Shape>,
+}
+
+impl Property {
+    /// Work out how to generate the inputs of these types. Returns a message for the user if
+    /// one of them can't be generated.
+    pub(crate) fn new<'a>(
+        arena: &'a Bump,
+        subs: &Subs,
+        layout_interner: &GlobalLayoutInterner<'a>,
+        target_info: TargetInfo,
+        input_vars: &[Variable],
+    ) -> Result<Self, String> {
+        let mut env = ShapeEnv {
+            arena,
+            subs,
+            layout_cache: LayoutCache::new(layout_interner.fork(), target_info),
+            target_info,
+        };
+
+        let inputs = input_vars
+            .iter()
+            .map(|var| env.shape(*var))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Property { inputs })
+    }
+
+    /// Generate inputs. The larger `size` is, the larger the numbers, strings and lists can be.
+    pub(crate) fn generate(&self, rng: &mut Rng, size: u32) -> Vec<Value> {
+        self.inputs
+            .iter()
+            .map(|shape| generate(shape, rng, size))
+            .collect()
+    }
+
+    /// Inputs that are a bit smaller than these ones, the most promising first
+    pub(crate) fn shrink(&self, inputs: &[Value]) -> Vec<Vec<Value>> {
+        let shapes: Vec<&Shape> = self.inputs.iter().collect();
+
+        shrink_each(&shapes, inputs)
+    }
+
+    /// Store the inputs in host memory, where the `expect` can read them
+    pub(crate) fn encode(&self, inputs: &[Value]) -> EncodedInputs {
+        let mut encoded = EncodedInputs {
+            blocks: Vec::new(),
+            table: Vec::with_capacity(inputs.len()),
+        };
+
+        for (shape, value) in self.inputs.iter().zip(inputs) {
+            let ptr = encoded.alloc(shape.size());
+            unsafe { encode(&mut encoded, shape, value, ptr) };
+            encoded.table.push(ptr);
+        }
+
+        encoded
+    }
+}
+
+/// Inputs in host memory. The memory is freed when this is dropped, so keep it alive until
+/// the `expect` has finished running.
+pub(crate) struct EncodedInputs {
+    /// `u128`s, so that every block is aligned for any value
+    blocks: Vec<Box<[u128]>>,
+    table: Vec<*const u8>,
+}
+
+impl EncodedInputs {
+    /// The pointer to pass to `set_expect_inputs`: one pointer per input, in argument order
+    pub(crate) fn table(&self) -> *const *const u8 {
+        self.table.as_ptr()
+    }
+
+    fn alloc(&mut self, size: usize) -> *mut u8 {
+        let words = (size + 15) / 16;
+        let mut block = vec![0u128; words.max(1)].into_boxed_slice();
+        let ptr = block.as_mut_ptr().cast();
+        self.blocks.push(block);
+
+        ptr
+    }
+
+    /// Room for the elements of a list or string, after a refcount that marks them as
+    /// constant. Roc never frees or mutates them, and copies them before making changes.
+    fn alloc_refcounted(&mut self, size: usize) -> *mut u8 {
+        const REFCOUNT_CONSTANT: isize = 0;
+
+        let ptr = self.alloc(16 + size);
+
+        unsafe {
+            ptr.add(16 - std::mem::size_of::<isize>())
+                .cast::<isize>()
+                .write(REFCOUNT_CONSTANT);
+
+            ptr.add(16)
+        }
+    }
+}
+
+struct ShapeEnv<'a, 's> {
+    arena: &'a Bump,
+    subs: &'s Subs,
+    layout_cache: LayoutCache<'a>,
+    target_info: TargetInfo,
+}
+
+impl<'a, 's> ShapeEnv<'a, 's> {
+    /// Which types have generators is decided by `roc_derive_key`, and how their values are
+    /// stored by their layouts
+    fn shape(&mut self, var: Variable) -> Result<Shape, String> {
+        let flat = FlatGenerate::from_var(self.subs, var).map_err(|error| match error {
+            DeriveError::UnboundVar => {
+                "I could not work out the type of one of its inputs.".to_string()
+            }
+            DeriveError::Underivable => unsupported(self.describe(var)),
+        })?;
+
+        let content = self.content_of(var);
+
+        let key = match flat {
+            FlatGenerate::Immediate(symbol) => return Ok(self.immediate(symbol)),
+            FlatGenerate::Key(key) => key,
+        };
+
+        let shape = match key {
+            FlatGenerateKey::List() => {
+                let elem_var = match content {
+                    Content::Structure(FlatType::Apply(Symbol::LIST_LIST, args)) => {
+                        self.subs.get_subs_slice(*args)[0]
+                    }
+                    _ => internal_error!("a list key for {content:?}"),
+                };
+
+                let elem_layout = self.layout_of(elem_var)?;
+                let interner = &self.layout_cache.interner;
+                let elem_size = interner.stack_size(elem_layout) as usize;
+                let elem_alignment = interner.alignment_bytes(elem_layout) as usize;
+
+                Shape::List {
+                    elem: Box::new(self.shape(elem_var)?),
+                    elem_size,
+                    elem_alignment,
+                }
+            }
+            // Records with one field are stored just like their field
+            FlatGenerateKey::Record(fields) if fields.len() == 1 => {
+                let mut fields = self.struct_fields(content)?;
+                fields.pop().unwrap().0
+            }
+            FlatGenerateKey::Record(_) | FlatGenerateKey::Tuple(_) => {
+                Shape::Struct(self.struct_fields(content)?)
+            }
+            FlatGenerateKey::TagUnion(tags) => match tags.as_slice() {
+                // Tags with one payload are stored just like their payload
+                [(_, 1)] => {
+                    let mut fields = self.struct_fields(content)?;
+                    fields.pop().unwrap().0
+                }
+                [_] => Shape::Struct(self.struct_fields(content)?),
+                _ if tags.iter().all(|(_, payload_size)| *payload_size == 0) => {
+                    if tags.len() > u8::MAX as usize + 1 {
+                        return Err(unsupported("tag unions with more than 256 tags"));
+                    }
+
+                    Shape::Enum {
+                        tags: tags.len() as u16,
+                    }
+                }
+                _ => self.union(var, content)?,
+            },
+        };
+
+        Ok(shape)
+    }
+
+    fn immediate(&self, symbol: Symbol) -> Shape {
+        match symbol {
+            Symbol::NUM_U8 => Shape::Int(IntWidth::U8),
+            Symbol::NUM_U16 => Shape::Int(IntWidth::U16),
+            Symbol::NUM_U32 => Shape::Int(IntWidth::U32),
+            Symbol::NUM_U64 => Shape::Int(IntWidth::U64),
+            Symbol::NUM_U128 => Shape::Int(IntWidth::U128),
+            Symbol::NUM_I8 => Shape::Int(IntWidth::I8),
+            Symbol::NUM_I16 => Shape::Int(IntWidth::I16),
+            Symbol::NUM_I32 => Shape::Int(IntWidth::I32),
+            Symbol::NUM_I64 => Shape::Int(IntWidth::I64),
+            Symbol::NUM_I128 => Shape::Int(IntWidth::I128),
+            Symbol::NUM_NAT => match self.target_info.ptr_width() {
+                PtrWidth::Bytes4 => Shape::Int(IntWidth::U32),
+                PtrWidth::Bytes8 => Shape::Int(IntWidth::U64),
+            },
+            Symbol::NUM_DEC => Shape::Dec,
+            Symbol::NUM_F32 => Shape::Float(FloatWidth::F32),
+            Symbol::NUM_F64 => Shape::Float(FloatWidth::F64),
+            Symbol::STR_STR => Shape::Str,
+            _ => internal_error!("no generator for the immediate {symbol:?}"),
+        }
+    }
+
+    /// A tag union with several tags, some of which have payloads
+    fn union(&mut self, var: Variable, content: &Content) -> Result<Shape, String> {
+        let (tags, ext) = match content {
+            Content::Structure(FlatType::TagUnion(tags, ext)) => (tags, ext),
+            _ => internal_error!("a tag union key for {content:?}"),
+        };
+
+        let layout = self.layout_of(var)?;
+        let tag_layouts = match self.layout_cache.get_in(layout) {
+            Layout::Union(UnionLayout::NonRecursive(tag_layouts)) => tag_layouts,
+            _ => return Err(unsupported("tag unions that are stored like this one")),
+        };
+
+        let mut payloads = Vec::with_capacity(tags.len());
+        for (_, payload_vars) in tags.sorted_iterator_and_ext(self.subs, *ext).0 {
+            payloads.push(self.in_memory_order(payload_vars.iter().map(|v| ((), *v)))?);
+        }
+
+        if payloads.len() != tag_layouts.len() {
+            return Err(unsupported("tag unions with tags that can never happen"));
+        }
+
+        let union_layout = UnionLayout::NonRecursive(tag_layouts);
+        let interner = &self.layout_cache.interner;
+
+        Ok(Shape::Union {
+            tags: payloads,
+            tag_id_offset: union_layout
+                .tag_id_offset(interner, self.target_info)
+                .unwrap() as usize,
+            tag_id_size: union_layout.discriminant().stack_size() as usize,
+        })
+    }
+
+    /// What to call a type that has no generator
+    fn describe(&self, var: Variable) -> &'static str {
+        match self.content_of(var) {
+            Content::Structure(FlatType::Func(..)) | Content::LambdaSet(_) => "functions",
+            Content::Structure(FlatType::RecursiveTagUnion(..)) | Content::RecursionVar { .. } => {
+                "recursive tag unions"
+            }
+            Content::Structure(FlatType::Record(..)) => "records with optional fields",
+            Content::Structure(FlatType::EmptyTagUnion) => "empty tag unions",
+            _ => "this kind of value",
+        }
+    }
+
+    /// The fields of a record, tuple or single-tag union
+    fn struct_fields(&mut self, content: &Content) -> Result<Vec<(Shape, usize)>, String> {
+        match content {
+            Content::Structure(FlatType::Record(fields, ext)) => {
+                let mut field_vars = Vec::with_capacity(fields.len());
+                for (label, field) in fields.sorted_iterator(self.subs, *ext) {
+                    match field {
+                        RecordField::Required(var)
+                        | RecordField::Demanded(var)
+                        | RecordField::RigidRequired(var) => field_vars.push((label, var)),
+                        RecordField::Optional(_) | RecordField::RigidOptional(_) => {
+                            return Err(unsupported("records with optional fields"))
+                        }
+                    }
+                }
+
+                self.in_memory_order(field_vars)
+            }
+            Content::Structure(FlatType::Tuple(elems, ext)) => {
+                let elem_vars: Vec<_> = elems.sorted_iterator(self.subs, *ext).collect();
+
+                self.in_memory_order(elem_vars)
+            }
+            Content::Structure(FlatType::TagUnion(tags, ext)) if tags.len() == 1 => {
+                let (_, payload_vars) = tags
+                    .sorted_iterator_and_ext(self.subs, *ext)
+                    .0
+                    .next()
+                    .unwrap();
+
+                self.in_memory_order(payload_vars.iter().map(|v| ((), *v)))
+            }
+            Content::Structure(
+                FlatType::EmptyRecord
+                | FlatType::EmptyTuple
+                | FlatType::EmptyTagUnion
+                | FlatType::FunctionOrTagUnion(..),
+            ) => Ok(Vec::new()),
+            _ => Err(unsupported("this kind of value")),
+        }
+    }
+
+    /// Sort fields like their layout does: by alignment, largest first, and then by label.
+    /// Tag payloads have `()` labels, so they keep their order when the alignments are equal.
+    fn in_memory_order<L: Ord>(
+        &mut self,
+        fields: impl IntoIterator<Item = (L, Variable)>,
+    ) -> Result<Vec<(Shape, usize)>, String> {
+        let mut fields = fields
+            .into_iter()
+            .map(|(label, var)| Ok((label, var, self.layout_of(var)?)))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let interner = &self.layout_cache.interner;
+        fields.sort_by(|(label1, _, layout1), (label2, _, layout2)| {
+            cmp_fields(
+                interner,
+                label1,
+                *layout1,
+                label2,
+                *layout2,
+                self.target_info,
+            )
+        });
+
+        let mut offset = 0;
+        let mut shapes = Vec::with_capacity(fields.len());
+        for (_, var, layout) in fields {
+            shapes.push((self.shape(var)?, offset));
+            offset += self.layout_cache.interner.stack_size(layout) as usize;
+        }
+
+        Ok(shapes)
+    }
+
+    fn layout_of(&mut self, var: Variable) -> Result<InLayout<'a>, String> {
+        self.layout_cache
+            .from_var(self.arena, var, self.subs)
+            .map_err(|_| "I could not work out the type of one of its inputs.".to_string())
+    }
+
+    /// The content of a variable, looking through aliases and opaque types
+    fn content_of(&self, mut var: Variable) -> &'s Content {
+        loop {
+            match self.subs.get_content_without_compacting(var) {
+                Content::Alias(_, _, real_var, _) => var = *real_var,
+                content => return content,
+            }
+        }
+    }
+}
+
+fn unsupported(what: &str) -> String {
+    format!("I don't know how to generate inputs for {what} yet.")
+}
+
+impl Shape {
+    fn size(&self) -> usize {
+        let word = std::mem::size_of::<usize>();
+
+        match self {
+            Shape::Int(int_width) => int_width.stack_size() as usize,
+            Shape::Float(float_width) => float_width.stack_size() as usize,
+            Shape::Dec => 16,
+            Shape::Str | Shape::List { .. } => 3 * word,
+            Shape::Struct(fields) => fields_size(fields),
+            Shape::Enum { .. } => 1,
+            Shape::Union {
+                tag_id_offset,
+                tag_id_size,
+                ..
+            } => tag_id_offset + tag_id_size,
+        }
+    }
+}
+
+fn fields_size(fields: &[(Shape, usize)]) -> usize {
+    fields
+        .last()
+        .map(|(shape, offset)| offset + shape.size())
+        .unwrap_or(0)
+}
+
+fn int_range(int_width: IntWidth) -> (i128, i128) {
+    use IntWidth::*;
+
+    match int_width {
+        U8 => (0, u8::MAX as i128),
+        U16 => (0, u16::MAX as i128),
+        U32 => (0, u32::MAX as i128),
+        U64 => (0, u64::MAX as i128),
+        // Larger `U128`s are never generated
+        U128 => (0, i128::MAX),
+        I8 => (i8::MIN as i128, i8::MAX as i128),
+        I16 => (i16::MIN as i128, i16::MAX as i128),
+        I32 => (i32::MIN as i128, i32::MAX as i128),
+        I64 => (i64::MIN as i128, i64::MAX as i128),
+        I128 => (i128::MIN, i128::MAX),
+    }
+}
+
+const DEC_ONE: i128 = 1_000_000_000_000_000_000;
+
+/// Characters that often find bugs in string handling
+const INTERESTING_CHARS: [char; 6] = ['\n', '\t', 'é', 'ß', 'λ', '🐦'];
+
+fn generate(shape: &Shape, rng: &mut Rng, size: u32) -> Value {
+    match shape {
+        Shape::Int(int_width) => {
+            let (min, max) = int_range(*int_width);
+
+            let n = if rng.one_in(10) {
+                [min, max, 0, 1][rng.below(4) as usize]
+            } else {
+                rng.around_zero(size)
+            };
+
+            Value::Int(n.clamp(min, max))
+        }
+        Shape::Float(_) => {
+            let f = if rng.one_in(10) {
+                [0.0, 1.0, -1.0, 1e10, -1e10][rng.below(5) as usize]
+            } else {
+                rng.around_zero(size) as f64 + rng.below(1000) as f64 / 1000.0
+            };
+
+            Value::Float(f)
+        }
+        Shape::Dec => {
+            let fraction = if rng.one_in(2) {
+                rng.below(DEC_ONE as u64) as i128
+            } else {
+                0
+            };
+
+            Value::Int(rng.around_zero(size) * DEC_ONE + fraction)
+        }
+        Shape::Str => {
+            let len = rng.below(size as u64 + 1);
+            let string = (0..len)
+                .map(|_| {
+                    if rng.one_in(10) {
+                        INTERESTING_CHARS[rng.below(INTERESTING_CHARS.len() as u64) as usize]
+                    } else {
+                        (b' ' + rng.below(95) as u8) as char
+                    }
+                })
+                .collect();
+
+            Value::Str(string)
+        }
+        Shape::List { elem, .. } => {
+            let len = rng.below(size as u64 + 1);
+
+            Value::List((0..len).map(|_| generate(elem, rng, size)).collect())
+        }
+        Shape::Struct(fields) => Value::Fields(generate_fields(fields, rng, size)),
+        Shape::Enum { tags } => Value::Tag(rng.below(*tags as u64) as u16, Vec::new()),
+        Shape::Union { tags, .. } => {
+            let tag_id = rng.below(tags.len() as u64) as u16;
+
+            Value::Tag(tag_id, generate_fields(&tags[tag_id as usize], rng, size))
+        }
+    }
+}
+
+fn generate_fields(fields: &[(Shape, usize)], rng: &mut Rng, size: u32) -> Vec<Value> {
+    fields
+        .iter()
+        .map(|(shape, _)| generate(shape, rng, size))
+        .collect()
+}
+
+/// Numbers between `n` and 0, closest to 0 first
+fn towards_zero(n: i128) -> Vec<i128> {
+    let mut candidates = Vec::new();
+
+    if n != 0 {
+        candidates.push(0);
+
+        let mut step = n / 2;
+        while step != 0 {
+            candidates.push(n - step);
+            step /= 2;
+        }
+    }
+
+    candidates.dedup();
+    candidates
+}
+
+fn shrink(shape: &Shape, value: &Value) -> Vec<Value> {
+    match (shape, value) {
+        (Shape::Int(_), Value::Int(n)) => towards_zero(*n).into_iter().map(Value::Int).collect(),
+        (Shape::Dec, Value::Int(n)) => {
+            let fraction = n % DEC_ONE;
+            let mut candidates = Vec::new();
+
+            if fraction != 0 {
+                candidates.push(Value::Int(n - fraction));
+            }
+
+            candidates.extend(
+                towards_zero(n / DEC_ONE)
+                    .into_iter()
+                    .map(|whole| Value::Int(whole * DEC_ONE)),
+            );
+
+            candidates
+        }
+        (Shape::Float(_), Value::Float(f)) => {
+            if f.fract() != 0.0 {
+                vec![Value::Float(0.0), Value::Float(f.trunc())]
+            } else if f.abs() < (1u64 << 53) as f64 {
+                towards_zero(*f as i128)
+                    .into_iter()
+                    .map(|n| Value::Float(n as f64))
+                    .collect()
+            } else {
+                vec![Value::Float(0.0)]
+            }
+        }
+        (Shape::Str, Value::Str(string)) => {
+            let chars: Vec<char> = string.chars().collect();
+
+            shrink_sequence(&chars, |_| Vec::new())
+                .into_iter()
+                .map(|chars| Value::Str(chars.into_iter().collect()))
+                .collect()
+        }
+        (Shape::List { elem, .. }, Value::List(elems)) => {
+            shrink_sequence(elems, |elem_value| shrink(elem, elem_value))
+                .into_iter()
+                .map(Value::List)
+                .collect()
+        }
+        (Shape::Struct(fields), Value::Fields(values)) => {
+            let shapes: Vec<&Shape> = fields.iter().map(|(shape, _)| shape).collect();
+
+            shrink_each(&shapes, values)
+                .into_iter()
+                .map(Value::Fields)
+                .collect()
+        }
+        (Shape::Enum { .. }, Value::Tag(tag_id, _)) => (0..*tag_id)
+            .map(|smaller| Value::Tag(smaller, Vec::new()))
+            .collect(),
+        (Shape::Union { tags, .. }, Value::Tag(tag_id, payload)) => {
+            // Earlier tags, with the smallest payloads there are
+            let mut candidates: Vec<Value> = (0..*tag_id)
+                .map(|smaller| {
+                    let fields = &tags[smaller as usize];
+                    Value::Tag(smaller, generate_fields(fields, &mut Rng::new(0), 0))
+                })
+                .collect();
+
+            let shapes: Vec<&Shape> = tags[*tag_id as usize]
+                .iter()
+                .map(|(shape, _)| shape)
+                .collect();
+
+            candidates.extend(
+                shrink_each(&shapes, payload)
+                    .into_iter()
+                    .map(|payload| Value::Tag(*tag_id, payload)),
+            );
+
+            candidates
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Shrink one field at a time, keeping the others as they are
+fn shrink_each(shapes: &[&Shape], values: &[Value]) -> Vec<Vec<Value>> {
+    let mut candidates = Vec::new();
+
+    for (index, (shape, value)) in shapes.iter().zip(values).enumerate() {
+        for smaller in shrink(shape, value) {
+            let mut candidate = values.to_vec();
+            candidate[index] = smaller;
+            candidates.push(candidate);
+        }
+    }
+
+    candidates
+}
+
+/// Shorter sequences first, then ones with a smaller element
+fn shrink_sequence<T: Clone>(items: &[T], shrink_item: impl Fn(&T) -> Vec<T>) -> Vec<Vec<T>> {
+    let mut candidates = Vec::new();
+
+    if items.is_empty() {
+        return candidates;
+    }
+
+    candidates.push(Vec::new());
+
+    if items.len() > 2 {
+        let half = items.len() / 2;
+        candidates.push(items[..half].to_vec());
+        candidates.push(items[half..].to_vec());
+    }
+
+    if items.len() > 1 {
+        for index in 0..items.len() {
+            let mut candidate = items.to_vec();
+            candidate.remove(index);
+            candidates.push(candidate);
+        }
+    }
+
+    for (index, item) in items.iter().enumerate() {
+        for smaller in shrink_item(item) {
+            let mut candidate = items.to_vec();
+            candidate[index] = smaller;
+            candidates.push(candidate);
+        }
+    }
+
+    candidates
+}
+
+/// Write a value the way Roc stores it.
+///
+/// # Safety
+///
+/// `ptr` must point to enough memory for the shape, aligned for it.
+unsafe fn encode(encoded: &mut EncodedInputs, shape: &Shape, value: &Value, ptr: *mut u8) {
+    let word = std::mem::size_of::<usize>();
+
+    match (shape, value) {
+        (Shape::Int(int_width), Value::Int(n)) => {
+            let bytes = n.to_le_bytes();
+            let size = int_width.stack_size() as usize;
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, size);
+        }
+        (Shape::Dec, Value::Int(n)) => ptr.cast::<i128>().write(*n),
+        (Shape::Float(FloatWidth::F32), Value::Float(f)) => ptr.cast::<f32>().write(*f as f32),
+        (Shape::Float(FloatWidth::F64), Value::Float(f)) => ptr.cast::<f64>().write(*f),
+        (Shape::Str, Value::Str(string)) => {
+            let bytes = string.as_bytes();
+
+            if bytes.len() < 3 * word {
+                // A small string is stored inline, with its length in the last byte
+                std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len());
+                ptr.add(3 * word - 1).write(bytes.len() as u8 | 0b1000_0000);
+            } else {
+                let data = encoded.alloc_refcounted(bytes.len());
+                std::ptr::copy_nonoverlapping(bytes.as_ptr(), data, bytes.len());
+                write_heap_header(ptr, data, bytes.len());
+            }
+        }
+        (
+            Shape::List {
+                elem,
+                elem_size,
+                elem_alignment,
+            },
+            Value::List(elems),
+        ) => {
+            if elems.is_empty() {
+                write_heap_header(ptr, std::ptr::null_mut(), 0);
+            } else {
+                debug_assert!(*elem_alignment <= 16);
+
+                let data = encoded.alloc_refcounted(elem_size * elems.len());
+                for (index, elem_value) in elems.iter().enumerate() {
+                    encode(encoded, elem, elem_value, data.add(index * elem_size));
+                }
+
+                write_heap_header(ptr, data, elems.len());
+            }
+        }
+        (Shape::Struct(fields), Value::Fields(values)) => {
+            encode_fields(encoded, fields, values, ptr);
+        }
+        (Shape::Enum { .. }, Value::Tag(tag_id, _)) => ptr.write(*tag_id as u8),
+        (
+            Shape::Union {
+                tags,
+                tag_id_offset,
+                tag_id_size,
+            },
+            Value::Tag(tag_id, payload),
+        ) => {
+            encode_fields(encoded, &tags[*tag_id as usize], payload, ptr);
+
+            let tag_id_ptr = ptr.add(*tag_id_offset);
+            match tag_id_size {
+                0 => {}
+                1 => tag_id_ptr.write(*tag_id as u8),
+                _ => tag_id_ptr.cast::<u16>().write_unaligned(*tag_id),
+            }
+        }
+        (shape, value) => internal_error!("{value:?} was generated for {shape:?}"),
+    }
+}
+
+unsafe fn encode_fields(
+    encoded: &mut EncodedInputs,
+    fields: &[(Shape, usize)],
+    values: &[Value],
+    ptr: *mut u8,
+) {
+    for ((shape, offset), value) in fields.iter().zip(values) {
+        encode(encoded, shape, value, ptr.add(*offset));
+    }
+}
+
+/// The pointer, length and capacity of a list or a string that isn't small
+unsafe fn write_heap_header(ptr: *mut u8, data: *mut u8, len: usize) {
+    let words = ptr.cast::<usize>();
+
+    words.write(data as usize);
+    words.add(1).write(len);
+    words.add(2).write(len);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn list_of(elem: Shape) -> Shape {
+        Shape::List {
+            elem: Box::new(elem),
+            elem_size: 1,
+            elem_alignment: 1,
+        }
+    }
+
+    #[test]
+    fn generate_is_deterministic() {
+        let shape = list_of(Shape::Int(IntWidth::I64));
+
+        let first = generate(&shape, &mut Rng::new(42), 20);
+        let second = generate(&shape, &mut Rng::new(42), 20);
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn generate_stays_in_range() {
+        let mut rng = Rng::new(7);
+
+        for _ in 0..1000 {
+            match generate(&Shape::Int(IntWidth::U8), &mut rng, 1000) {
+                Value::Int(n) => assert!((0..=255).contains(&n), "{n}"),
+                other => panic!("{other:?}"),
+            }
+
+            match generate(&list_of(Shape::Str), &mut rng, 5) {
+                Value::List(elems) => {
+                    assert!(elems.len() <= 5);
+
+                    for elem in elems {
+                        match elem {
+                            Value::Str(string) => assert!(string.chars().count() <= 5),
+                            other => panic!("{other:?}"),
+                        }
+                    }
+                }
+                other => panic!("{other:?}"),
+            }
+
+            match generate(&Shape::Enum { tags: 3 }, &mut rng, 5) {
+                Value::Tag(tag_id, payload) => assert!(tag_id < 3 && payload.is_empty()),
+                other => panic!("{other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn generate_union_payloads() {
+        let shape = Shape::Union {
+            tags: vec![
+                vec![],
+                vec![(Shape::Str, 0), (Shape::Int(IntWidth::U8), 24)],
+            ],
+            tag_id_offset: 25,
+            tag_id_size: 1,
+        };
+        let mut rng = Rng::new(3);
+
+        for _ in 0..100 {
+            match generate(&shape, &mut rng, 5) {
+                Value::Tag(0, payload) => assert!(payload.is_empty()),
+                Value::Tag(1, payload) => {
+                    assert!(matches!(payload.as_slice(), [Value::Str(_), Value::Int(_)]))
+                }
+                other => panic!("{other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn shrink_int_towards_zero() {
+        let candidates = shrink(&Shape::Int(IntWidth::I64), &Value::Int(-100));
+
+        assert_eq!(candidates.first(), Some(&Value::Int(0)));
+        assert!(candidates.iter().all(|candidate| match candidate {
+            Value::Int(n) => (-100 < *n) && (*n <= 0),
+            _ => false,
+        }));
+
+        assert!(shrink(&Shape::Int(IntWidth::I64), &Value::Int(0)).is_empty());
+    }
+
+    #[test]
+    fn shrink_dec_drops_the_fraction_first() {
+        let candidates = shrink(&Shape::Dec, &Value::Int(3 * DEC_ONE + DEC_ONE / 2));
+
+        assert_eq!(candidates[0], Value::Int(3 * DEC_ONE));
+        assert_eq!(candidates[1], Value::Int(0));
+    }
+
+    #[test]
+    fn shrink_list_shorter_first() {
+        let shape = list_of(Shape::Int(IntWidth::U8));
+        let value = Value::List(vec![Value::Int(5), Value::Int(6), Value::Int(7)]);
+
+        let candidates = shrink(&shape, &value);
+
+        assert_eq!(candidates[0], Value::List(vec![]));
+        assert_eq!(candidates[1], Value::List(vec![Value::Int(5)]));
+        assert!(candidates.contains(&Value::List(vec![
+            Value::Int(0),
+            Value::Int(6),
+            Value::Int(7)
+        ])));
+    }
+
+    #[test]
+    fn shrink_str_removes_chars() {
+        let candidates = shrink(&Shape::Str, &Value::Str("ab".to_string()));
+
+        assert_eq!(
+            candidates,
+            vec![
+                Value::Str(String::new()),
+                Value::Str("b".to_string()),
+                Value::Str("a".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn shrink_struct_one_field_at_a_time() {
+        let shape = Shape::Struct(vec![
+            (Shape::Int(IntWidth::I8), 0),
+            (Shape::Int(IntWidth::I8), 1),
+        ]);
+        let value = Value::Fields(vec![Value::Int(2), Value::Int(0)]);
+
+        assert_eq!(
+            shrink(&shape, &value),
+            vec![
+                Value::Fields(vec![Value::Int(0), Value::Int(0)]),
+                Value::Fields(vec![Value::Int(1), Value::Int(0)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn shrink_union_to_earlier_tags() {
+        let shape = Shape::Union {
+            tags: vec![
+                vec![],
+                vec![(Shape::Int(IntWidth::U8), 0)],
+                vec![(Shape::Int(IntWidth::U8), 0)],
+            ],
+            tag_id_offset: 1,
+            tag_id_size: 1,
+        };
+
+        let candidates = shrink(&shape, &Value::Tag(2, vec![Value::Int(1)]));
+
+        assert_eq!(
+            candidates,
+            vec![
+                Value::Tag(0, vec![]),
+                Value::Tag(1, vec![Value::Int(0)]),
+                Value::Tag(2, vec![Value::Int(0)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn encode_small_str_and_tag_id() {
+        let property = Property {
+            inputs: vec![
+                Shape::Str,
+                Shape::Union {
+                    tags: vec![vec![], vec![(Shape::Int(IntWidth::U16), 0)]],
+                    tag_id_offset: 2,
+                    tag_id_size: 1,
+                },
+            ],
+        };
+
+        let encoded = property.encode(&[
+            Value::Str("hi".to_string()),
+            Value::Tag(1, vec![Value::Int(0x1234)]),
+        ]);
+
+        let word = std::mem::size_of::<usize>();
+        let table = unsafe { std::slice::from_raw_parts(encoded.table(), 2) };
+
+        let string = unsafe { std::slice::from_raw_parts(table[0], 3 * word) };
+        assert_eq!(&string[..2], b"hi");
+        assert_eq!(string[3 * word - 1], 2 | 0b1000_0000);
+
+        let union = unsafe { std::slice::from_raw_parts(table[1], 3) };
+        assert_eq!(union, [0x34, 0x12, 1]);
+    }
+}
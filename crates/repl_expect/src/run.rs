@@ -1,16 +1,17 @@
 use std::{
+    io::Write,
     os::unix::process::parent_id,
     sync::{
         atomic::{AtomicBool, AtomicU32},
         Arc,
     },
+    time::{Duration, Instant},
 };
 
 use bumpalo::collections::Vec as BumpVec;
 use bumpalo::Bump;
 use inkwell::context::Context;
-use roc_build::link::llvm_module_to_dylib;
-use roc_can::expr::ExpectLookup;
+use roc_build::link::{dev_object_to_dylib, llvm_module_to_dylib};
 use roc_collections::{MutSet, VecMap};
 use roc_error_macros::internal_error;
 use roc_gen_llvm::{
@@ -18,22 +19,28 @@ use roc_gen_llvm::{
     run_roc::RocCallResult,
     run_roc_dylib,
 };
-use roc_load::{Expectations, MonomorphizedModule};
+use roc_load::{Expectations, MonomorphizedModule, Threading};
 use roc_module::symbol::{Interns, ModuleId, Symbol};
 use roc_mono::{
-    ir::OptLevel,
-    layout::{GlobalLayoutInterner, STLayoutInterner},
+    ir::{OptLevel, ProcLayout},
+    layout::{GlobalLayoutInterner, Layout, LayoutIds, Niche, STLayoutInterner},
 };
+use roc_parse::ast::Expr;
 use roc_region::all::Region;
 use roc_reporting::{error::expect::Renderer, report::RenderTarget};
 use roc_target::TargetInfo;
-use roc_types::subs::Subs;
+use roc_types::pretty_print::{name_and_print_var, DebugPrint};
+use roc_types::subs::Variable;
 use target_lexicon::Triple;
 
+use crate::property::{Property, Rng, Value};
+
 pub struct ExpectMemory<'a> {
     ptr: *mut u8,
     length: usize,
     shm_name: Option<std::ffi::CString>,
+    /// The process that created the shared memory object, and has to unlink it when done
+    owner: Option<u32>,
     _marker: std::marker::PhantomData<&'a ()>,
 }
 
@@ -46,13 +53,17 @@ impl<'a> ExpectMemory<'a> {
             ptr: slice.as_mut_ptr(),
             length: slice.len(),
             shm_name: None,
+            owner: None,
             _marker: std::marker::PhantomData,
         }
     }
 
     pub fn create_or_reuse_mmap(shm_name: &str) -> Self {
         let cstring = std::ffi::CString::new(shm_name).unwrap();
-        Self::mmap_help(cstring, libc::O_RDWR | libc::O_CREAT)
+        let mut memory = Self::mmap_help(cstring, libc::O_RDWR | libc::O_CREAT);
+        memory.owner = Some(std::process::id());
+
+        memory
     }
 
     fn reuse_mmap(&mut self) -> Option<Self> {
@@ -104,6 +115,7 @@ impl<'a> ExpectMemory<'a> {
             ptr: ptr.cast(),
             length: Self::SHM_SIZE,
             shm_name: Some(cstring),
+            owner: None,
             _marker: std::marker::PhantomData,
         }
     }
@@ -125,8 +137,26 @@ impl<'a> ExpectMemory<'a> {
     }
 }
 
+impl Drop for ExpectMemory<'_> {
+    fn drop(&mut self) {
+        let shm_name = match &self.shm_name {
+            Some(shm_name) => shm_name,
+            None => return,
+        };
+
+        unsafe {
+            libc::munmap(self.ptr.cast(), self.length);
+
+            // A forked child has a copy of this value, but the object belongs to the parent
+            if self.owner == Some(std::process::id()) {
+                libc::shm_unlink(shm_name.as_ptr());
+            }
+        }
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
-pub fn run_inline_expects<'a, W: std::io::Write>(
+pub fn run_inline_expects<'a, 'e, W: std::io::Write>(
     writer: &mut W,
     render_target: RenderTarget,
     arena: &'a Bump,
@@ -134,8 +164,8 @@ pub fn run_inline_expects<'a, W: std::io::Write>(
     layout_interner: &GlobalLayoutInterner<'a>,
     lib: &libloading::Library,
     expectations: &mut VecMap<ModuleId, Expectations>,
-    expects: ExpectFunctions<'_>,
-) -> std::io::Result<(usize, usize)> {
+    expects: ExpectFunctions<'e>,
+) -> std::io::Result<Vec<ExpectResult<'e>>> {
     let shm_name = format!("/roc_expect_buffer_{}", std::process::id());
     let mut memory = ExpectMemory::create_or_reuse_mmap(&shm_name);
 
@@ -152,8 +182,38 @@ pub fn run_inline_expects<'a, W: std::io::Write>(
     )
 }
 
+/// How `run_toplevel_expects` spreads the `expect`s over worker processes
+#[derive(Debug, Clone, Copy)]
+pub struct ExpectRunOptions {
+    pub threading: Threading,
+    /// An `expect` that runs for longer than this is killed, and counts as failed
+    pub timeout: Option<Duration>,
+    /// The seed that the inputs of property `expect`s are generated from
+    pub seed: u64,
+}
+
+impl ExpectRunOptions {
+    fn worker_count(&self, expect_count: usize) -> usize {
+        let available = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+
+        let workers = match self.threading {
+            Threading::Single => 1,
+            Threading::AllAvailable => available,
+            Threading::AtMost(at_most) => Ord::min(available, at_most),
+        };
+
+        workers.clamp(1, expect_count.max(1))
+    }
+}
+
+/// Run every top-level `expect` in its own child process, with at most one child per worker.
+/// Each worker has its own shared memory, so children that run at the same time don't
+/// overwrite each other's failures. A child that crashes or times out only fails its own `expect`.
+/// The output is written in the same order as if the `expect`s ran one after another.
 #[allow(clippy::too_many_arguments)]
-pub fn run_toplevel_expects<'a, W: std::io::Write>(
+pub fn run_toplevel_expects<'a, 'e, W: std::io::Write>(
     writer: &mut W,
     render_target: RenderTarget,
     arena: &'a Bump,
@@ -161,26 +221,372 @@ pub fn run_toplevel_expects<'a, W: std::io::Write>(
     layout_interner: &GlobalLayoutInterner<'a>,
     lib: &libloading::Library,
     expectations: &mut VecMap<ModuleId, Expectations>,
-    expects: ExpectFunctions<'_>,
-) -> std::io::Result<(usize, usize)> {
-    let shm_name = format!("/roc_expect_buffer_{}", std::process::id());
-    let mut memory = ExpectMemory::create_or_reuse_mmap(&shm_name);
+    expects: ExpectFunctions<'e>,
+    options: ExpectRunOptions,
+) -> std::io::Result<Vec<ExpectResult<'e>>> {
+    let expects: Vec<ToplevelExpect<'e>> = expects.fx.into_iter().chain(expects.pure).collect();
+
+    let mut memories: Vec<ExpectMemory> = (0..options.worker_count(expects.len()))
+        .map(|worker| {
+            let shm_name = format!("/roc_expect_buffer_{}_{}", std::process::id(), worker);
+            ExpectMemory::create_or_reuse_mmap(&shm_name)
+        })
+        .collect();
+    let mut idle_workers: Vec<usize> = (0..memories.len()).rev().collect();
+
+    let mut running: Vec<ExpectChild> = Vec::with_capacity(memories.len());
+    let mut finished: Vec<Option<(bool, Duration, Vec<u8>)>> =
+        expects.iter().map(|_| None).collect();
+    let mut results = Vec::with_capacity(expects.len());
+
+    let mut next_to_start = 0;
+
+    while results.len() < expects.len() {
+        while next_to_start < expects.len() {
+            let worker = match idle_workers.pop() {
+                Some(worker) => worker,
+                None => break,
+            };
+
+            let child = spawn_expect_child(
+                render_target,
+                arena,
+                interns,
+                layout_interner,
+                lib,
+                expectations,
+                &mut memories[worker],
+                worker,
+                next_to_start,
+                expects[next_to_start],
+                options.seed,
+            )?;
+
+            running.push(child);
+            next_to_start += 1;
+        }
 
-    run_expects_with_memory(
-        writer,
-        render_target,
-        arena,
-        interns,
-        layout_interner,
-        lib,
-        expectations,
-        expects,
-        &mut memory,
-    )
+        for (child, outcome) in wait_for_children(&mut running, options.timeout)? {
+            let expect = expects[child.index];
+            let duration = child.start.elapsed();
+            let mut output = child.output;
+
+            let passed = match outcome {
+                ChildOutcome::Exited(0) => true,
+                ChildOutcome::Exited(EXIT_EXPECT_FAILED) => false,
+                crash => {
+                    let message = crash.describe(options.timeout);
+                    render_crash(
+                        &mut output,
+                        render_target,
+                        arena,
+                        interns,
+                        expectations,
+                        expect,
+                        &message,
+                    )?;
+                    writeln!(output)?;
+
+                    false
+                }
+            };
+
+            finished[child.index] = Some((passed, duration, output));
+            idle_workers.push(child.worker);
+        }
+
+        // Write everything that finished, up to the first `expect` that is still running
+        while let Some((passed, duration, output)) =
+            finished.get_mut(results.len()).and_then(Option::take)
+        {
+            let expect = expects[results.len()];
+            results.push(ExpectResult::new(expect, passed, duration, output, writer)?);
+        }
+    }
+
+    Ok(results)
+}
+
+/// The exit code of a child whose `expect` failed. Anything else but 0 is a crash.
+const EXIT_EXPECT_FAILED: i32 = 1;
+
+/// A child process that is running one top-level `expect`
+struct ExpectChild {
+    pid: libc::pid_t,
+    /// The read end of the pipe that the child writes its rendered output to
+    pipe: std::fs::File,
+    pipe_closed: bool,
+    output: Vec<u8>,
+    start: Instant,
+    /// The index of the `expect`
+    index: usize,
+    worker: usize,
+}
+
+enum ChildOutcome {
+    Exited(i32),
+    Signaled(i32),
+    TimedOut,
+}
+
+impl ChildOutcome {
+    fn describe(&self, timeout: Option<Duration>) -> String {
+        match self {
+            ChildOutcome::Exited(code) => {
+                format!("The process running this expectation exited with code {code}.")
+            }
+            ChildOutcome::Signaled(signal) => {
+                let name = match *signal {
+                    libc::SIGSEGV => "a segmentation fault (SIGSEGV)".to_string(),
+                    libc::SIGBUS => "a bus error (SIGBUS)".to_string(),
+                    libc::SIGILL => "an illegal instruction (SIGILL)".to_string(),
+                    libc::SIGFPE => "an arithmetic error (SIGFPE)".to_string(),
+                    libc::SIGABRT => "an abort (SIGABRT)".to_string(),
+                    other => format!("signal {other}"),
+                };
+
+                format!("The process running this expectation was stopped by {name}.")
+            }
+            ChildOutcome::TimedOut => format!(
+                "This expectation was stopped after running for longer than {:?}.",
+                timeout.unwrap_or_default()
+            ),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_expect_child<'a>(
+    render_target: RenderTarget,
+    arena: &'a Bump,
+    interns: &'a Interns,
+    layout_interner: &GlobalLayoutInterner<'a>,
+    lib: &libloading::Library,
+    expectations: &mut VecMap<ModuleId, Expectations>,
+    memory: &mut ExpectMemory,
+    worker: usize,
+    index: usize,
+    expect: ToplevelExpect<'_>,
+    seed: u64,
+) -> std::io::Result<ExpectChild> {
+    use std::os::unix::io::FromRawFd;
+
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let [read_fd, write_fd] = fds;
+
+    match unsafe { libc::fork() } {
+        0 => unsafe {
+            // we are the child
+            libc::close(read_fd);
+
+            // A Rust panic must not unwind back into the parent's loop
+            let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                memory.set_shared_buffer(lib);
+
+                let mut output = Vec::new();
+                let passed = run_expect_pure(
+                    &mut output,
+                    render_target,
+                    arena,
+                    interns,
+                    layout_interner,
+                    lib,
+                    expectations,
+                    memory,
+                    expect,
+                    seed,
+                )
+                .unwrap_or(false);
+
+                (passed, output)
+            }));
+
+            let (passed, output) = match outcome {
+                Ok(outcome) => outcome,
+                Err(_) => libc::_exit(101),
+            };
+
+            let mut pipe = std::fs::File::from_raw_fd(write_fd);
+            let _ = pipe.write_all(&output);
+
+            // `_exit` skips flushing the copies of the parent's buffers that we inherited
+            libc::_exit(if passed { 0 } else { EXIT_EXPECT_FAILED })
+        },
+        -1 => {
+            let error = std::io::Error::last_os_error();
+            unsafe {
+                libc::close(read_fd);
+                libc::close(write_fd);
+            }
+
+            Err(error)
+        }
+        pid => {
+            // we are the parent
+            unsafe { libc::close(write_fd) };
+
+            Ok(ExpectChild {
+                pid,
+                pipe: unsafe { std::fs::File::from_raw_fd(read_fd) },
+                pipe_closed: false,
+                output: Vec::new(),
+                start: Instant::now(),
+                index,
+                worker,
+            })
+        }
+    }
+}
+
+/// Collect the output of the running children until at least one of them is done.
+/// A child is done when it has closed its pipe and exited, or when it has run out of time.
+fn wait_for_children(
+    running: &mut Vec<ExpectChild>,
+    timeout: Option<Duration>,
+) -> std::io::Result<Vec<(ExpectChild, ChildOutcome)>> {
+    use std::io::Read;
+    use std::os::unix::io::AsRawFd;
+
+    loop {
+        let mut done = Vec::new();
+
+        // Kill the children that ran out of time
+        if let Some(timeout) = timeout {
+            let mut i = 0;
+            while i < running.len() {
+                if running[i].start.elapsed() >= timeout {
+                    let child = running.swap_remove(i);
+                    unsafe { libc::kill(child.pid, libc::SIGKILL) };
+                    wait_for_pid(child.pid)?;
+                    done.push((child, ChildOutcome::TimedOut));
+                } else {
+                    i += 1;
+                }
+            }
+        }
+
+        // Reap the children that closed their pipe. They have exited, or are about to.
+        let mut i = 0;
+        while i < running.len() {
+            if running[i].pipe_closed {
+                let child = running.swap_remove(i);
+                let outcome = wait_for_pid(child.pid)?;
+                done.push((child, outcome));
+            } else {
+                i += 1;
+            }
+        }
+
+        if !done.is_empty() || running.is_empty() {
+            return Ok(done);
+        }
+
+        let poll_timeout_ms = match timeout {
+            None => -1,
+            Some(timeout) => running
+                .iter()
+                .map(|child| timeout.saturating_sub(child.start.elapsed()))
+                .min()
+                .map(|remaining| remaining.as_millis().min(i32::MAX as u128 - 1) as i32 + 1)
+                .unwrap_or(0),
+        };
+
+        let mut pollfds: Vec<libc::pollfd> = running
+            .iter()
+            .map(|child| libc::pollfd {
+                fd: child.pipe.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            })
+            .collect();
+
+        let ready = unsafe {
+            libc::poll(
+                pollfds.as_mut_ptr(),
+                pollfds.len() as libc::nfds_t,
+                poll_timeout_ms,
+            )
+        };
+
+        if ready == -1 {
+            let error = std::io::Error::last_os_error();
+            if error.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(error);
+        }
+
+        for (child, pollfd) in running.iter_mut().zip(pollfds) {
+            if pollfd.revents == 0 {
+                continue;
+            }
+
+            let mut buffer = [0; 4096];
+            match child.pipe.read(&mut buffer) {
+                Ok(0) | Err(_) => child.pipe_closed = true,
+                Ok(n) => child.output.extend_from_slice(&buffer[..n]),
+            }
+        }
+    }
+}
+
+fn wait_for_pid(pid: libc::pid_t) -> std::io::Result<ChildOutcome> {
+    let mut status = 0;
+
+    loop {
+        if unsafe { libc::waitpid(pid, &mut status, 0) } != -1 {
+            break;
+        }
+
+        let error = std::io::Error::last_os_error();
+        if error.kind() != std::io::ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+
+    if libc::WIFSIGNALED(status) {
+        Ok(ChildOutcome::Signaled(libc::WTERMSIG(status)))
+    } else {
+        Ok(ChildOutcome::Exited(libc::WEXITSTATUS(status)))
+    }
+}
+
+/// Report an `expect` whose process crashed or timed out, the same way as a Roc panic
+fn render_crash<'a>(
+    writer: &mut impl std::io::Write,
+    render_target: RenderTarget,
+    arena: &'a Bump,
+    interns: &'a Interns,
+    expectations: &mut VecMap<ModuleId, Expectations>,
+    expect: ToplevelExpect<'_>,
+    message: &str,
+) -> std::io::Result<()> {
+    let module_id = expect.symbol.module_id();
+    let data = expectations.get_mut(&module_id).unwrap();
+
+    let filename = data.path.to_owned();
+    let source = std::fs::read_to_string(&data.path).unwrap();
+
+    let renderer = Renderer::new(arena, interns, render_target, module_id, filename, &source);
+
+    renderer.render_panic(writer, message, expect.region)
+}
+
+/// The outcome of running one top-level `expect`
+#[derive(Debug)]
+pub struct ExpectResult<'a> {
+    pub expect: ToplevelExpect<'a>,
+    pub passed: bool,
+    pub duration: Duration,
+    /// Everything that was written about this `expect`, such as the values in a failure
+    pub output: String,
 }
 
 #[allow(clippy::too_many_arguments)]
-pub(crate) fn run_expects_with_memory<'a, W: std::io::Write>(
+pub(crate) fn run_expects_with_memory<'a, 'e, W: std::io::Write>(
     writer: &mut W,
     render_target: RenderTarget,
     arena: &'a Bump,
@@ -188,15 +594,18 @@ pub(crate) fn run_expects_with_memory<'a, W: std::io::Write>(
     layout_interner: &GlobalLayoutInterner<'a>,
     lib: &libloading::Library,
     expectations: &mut VecMap<ModuleId, Expectations>,
-    expects: ExpectFunctions<'_>,
+    expects: ExpectFunctions<'e>,
     memory: &mut ExpectMemory,
-) -> std::io::Result<(usize, usize)> {
-    let mut failed = 0;
-    let mut passed = 0;
+    seed: u64,
+) -> std::io::Result<Vec<ExpectResult<'e>>> {
+    let mut results = Vec::with_capacity(expects.fx.len() + expects.pure.len());
 
     for expect in expects.fx {
-        let result = run_expect_fx(
-            writer,
+        let mut output = Vec::new();
+        let start = Instant::now();
+
+        let passed = run_expect_fx(
+            &mut output,
             render_target,
             arena,
             interns,
@@ -207,17 +616,23 @@ pub(crate) fn run_expects_with_memory<'a, W: std::io::Write>(
             expect,
         )?;
 
-        match result {
-            true => passed += 1,
-            false => failed += 1,
-        }
+        results.push(ExpectResult::new(
+            expect,
+            passed,
+            start.elapsed(),
+            output,
+            writer,
+        )?);
     }
 
     memory.set_shared_buffer(lib);
 
     for expect in expects.pure {
-        let result = run_expect_pure(
-            writer,
+        let mut output = Vec::new();
+        let start = Instant::now();
+
+        let passed = run_expect_pure(
+            &mut output,
             render_target,
             arena,
             interns,
@@ -226,15 +641,39 @@ pub(crate) fn run_expects_with_memory<'a, W: std::io::Write>(
             expectations,
             memory,
             expect,
+            seed,
         )?;
 
-        match result {
-            true => passed += 1,
-            false => failed += 1,
-        }
+        results.push(ExpectResult::new(
+            expect,
+            passed,
+            start.elapsed(),
+            output,
+            writer,
+        )?);
     }
 
-    Ok((failed, passed))
+    Ok(results)
+}
+
+impl<'a> ExpectResult<'a> {
+    /// Pass along what the `expect` wrote, and keep a copy of it
+    fn new(
+        expect: ToplevelExpect<'a>,
+        passed: bool,
+        duration: Duration,
+        output: Vec<u8>,
+        writer: &mut impl std::io::Write,
+    ) -> std::io::Result<Self> {
+        writer.write_all(&output)?;
+
+        Ok(ExpectResult {
+            expect,
+            passed,
+            duration,
+            output: String::from_utf8_lossy(&output).into_owned(),
+        })
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -248,51 +687,230 @@ fn run_expect_pure<'a, W: std::io::Write>(
     expectations: &mut VecMap<ModuleId, Expectations>,
     shared_memory: &mut ExpectMemory,
     expect: ToplevelExpect<'_>,
+    seed: u64,
 ) -> std::io::Result<bool> {
-    use roc_gen_llvm::try_run_jit_function;
+    if !expect.inputs.is_empty() {
+        return run_expect_property(
+            writer,
+            render_target,
+            arena,
+            interns,
+            layout_interner,
+            lib,
+            expectations,
+            shared_memory,
+            expect,
+            seed,
+        );
+    }
 
     let sequence = ExpectSequence::new(shared_memory.ptr.cast());
 
-    let result: Result<(), (String, _)> = try_run_jit_function!(lib, expect.name, (), |v: ()| v);
+    let result = call_expect(lib, expect);
+
+    if result.is_err() || sequence.count_failures() > 0 {
+        render_pure_failure(
+            writer,
+            render_target,
+            arena,
+            interns,
+            layout_interner,
+            expectations,
+            shared_memory,
+            expect,
+            result,
+            sequence.count_failures(),
+        )?;
+
+        writeln!(writer)?;
+
+        Ok(false)
+    } else {
+        Ok(true)
+    }
+}
 
+/// Render the failures that a pure `expect` wrote to the shared memory, or the panic it crashed with
+#[allow(clippy::too_many_arguments)]
+fn render_pure_failure<'a, W: std::io::Write>(
+    writer: &mut W,
+    render_target: RenderTarget,
+    arena: &'a Bump,
+    interns: &'a Interns,
+    layout_interner: &GlobalLayoutInterner<'a>,
+    expectations: &mut VecMap<ModuleId, Expectations>,
+    shared_memory: &ExpectMemory,
+    expect: ToplevelExpect<'_>,
+    result: Result<(), String>,
+    failures: usize,
+) -> std::io::Result<()> {
     let shared_memory_ptr: *const u8 = shared_memory.ptr.cast();
 
-    if result.is_err() || sequence.count_failures() > 0 {
-        let module_id = expect.symbol.module_id();
-        let data = expectations.get_mut(&module_id).unwrap();
+    let module_id = expect.symbol.module_id();
+    let data = expectations.get_mut(&module_id).unwrap();
 
-        let path = &data.path;
-        let filename = data.path.to_owned();
-        let source = std::fs::read_to_string(path).unwrap();
+    let path = &data.path;
+    let filename = data.path.to_owned();
+    let source = std::fs::read_to_string(path).unwrap();
 
-        let renderer = Renderer::new(arena, interns, render_target, module_id, filename, &source);
+    let renderer = Renderer::new(arena, interns, render_target, module_id, filename, &source);
 
-        if let Err((roc_panic_message, _roc_panic_tag)) = result {
-            renderer.render_panic(writer, &roc_panic_message, expect.region)?;
-        } else {
-            let mut offset = ExpectSequence::START_OFFSET;
+    if let Err(roc_panic_message) = result {
+        renderer.render_panic(writer, &roc_panic_message, expect.region)?;
+    } else {
+        let mut offset = ExpectSequence::START_OFFSET;
+
+        for _ in 0..failures {
+            offset += render_expect_failure(
+                writer,
+                &renderer,
+                arena,
+                Some(expect),
+                expectations,
+                interns,
+                layout_interner,
+                shared_memory_ptr,
+                offset,
+            )?;
+        }
+    }
 
-            for _ in 0..sequence.count_failures() {
-                offset += render_expect_failure(
-                    writer,
-                    &renderer,
-                    arena,
-                    Some(expect),
-                    expectations,
-                    interns,
-                    layout_interner,
-                    shared_memory_ptr,
-                    offset,
-                )?;
+    Ok(())
+}
+
+/// How many sets of inputs a property `expect` is checked with
+const PROPERTY_CASES: u32 = 100;
+
+/// Stop shrinking a failing input after trying this many smaller ones
+const MAX_SHRINK_ATTEMPTS: u32 = 1000;
+
+/// Check a property `expect` with generated inputs. When some inputs make it fail, shrink them
+/// to the smallest inputs that still make it fail, and report the failure with those.
+#[allow(clippy::too_many_arguments)]
+fn run_expect_property<'a, W: std::io::Write>(
+    writer: &mut W,
+    render_target: RenderTarget,
+    arena: &'a Bump,
+    interns: &'a Interns,
+    layout_interner: &GlobalLayoutInterner<'a>,
+    lib: &libloading::Library,
+    expectations: &mut VecMap<ModuleId, Expectations>,
+    shared_memory: &mut ExpectMemory,
+    expect: ToplevelExpect<'_>,
+    seed: u64,
+) -> std::io::Result<bool> {
+    // we always run programs as the host
+    let target_info = (&target_lexicon::Triple::host()).into();
+
+    let module_id = expect.symbol.module_id();
+    let data = expectations.get(&module_id).unwrap();
+
+    let setup = Property::new(
+        arena,
+        &data.subs,
+        layout_interner,
+        target_info,
+        expect.inputs,
+    )
+    .and_then(|property| {
+        // Only the LLVM backend passes generated inputs to the `expect`
+        let set_expect_inputs: libloading::Symbol<unsafe extern "C" fn(*const *const u8)> =
+            unsafe { lib.get(b"set_expect_inputs") }.map_err(|_| {
+                "I can only generate inputs when the tests are built without `--dev`.".to_string()
+            })?;
+
+        Ok((property, set_expect_inputs))
+    });
+
+    let (property, set_expect_inputs) = match setup {
+        Ok(setup) => setup,
+        Err(problem) => {
+            let filename = data.path.to_owned();
+            let source = std::fs::read_to_string(&data.path).unwrap();
+            let renderer =
+                Renderer::new(arena, interns, render_target, module_id, filename, &source);
+
+            renderer.render_property_problem(writer, &problem, expect.region)?;
+            writeln!(writer)?;
+
+            return Ok(false);
+        }
+    };
+
+    let run_case = |inputs: &[Value]| -> (Result<(), String>, usize) {
+        let encoded = property.encode(inputs);
+        unsafe { set_expect_inputs(encoded.table()) };
+
+        let sequence = ExpectSequence::new(shared_memory.ptr.cast());
+
+        let result = call_expect(lib, expect);
+
+        (result, sequence.count_failures())
+    };
+    let fails = |inputs: &[Value]| {
+        let (result, failures) = run_case(inputs);
+        result.is_err() || failures > 0
+    };
+
+    let mut rng = Rng::new(seed);
+
+    for case in 0..PROPERTY_CASES {
+        // Start with small inputs, and make them larger as we go
+        let inputs = property.generate(&mut rng, case);
+
+        if !fails(&inputs) {
+            continue;
+        }
+
+        let mut smallest = inputs;
+        let mut shrinks = 0;
+        let mut attempts = 0;
+
+        'shrink: while attempts < MAX_SHRINK_ATTEMPTS {
+            for candidate in property.shrink(&smallest) {
+                attempts += 1;
+
+                if fails(&candidate) {
+                    smallest = candidate;
+                    shrinks += 1;
+                    continue 'shrink;
+                }
+
+                if attempts == MAX_SHRINK_ATTEMPTS {
+                    break;
+                }
             }
+
+            break;
         }
 
+        // Run the smallest inputs once more, so the shared memory has their failure
+        let (result, failures) = run_case(&smallest);
+
+        render_pure_failure(
+            writer,
+            render_target,
+            arena,
+            interns,
+            layout_interner,
+            expectations,
+            shared_memory,
+            expect,
+            result,
+            failures,
+        )?;
+
+        writeln!(
+            writer,
+            "This property failed on test case {} of {PROPERTY_CASES}, and I shrank its inputs {shrinks} times. To check the same inputs again, use `--seed {seed}`.",
+            case + 1,
+        )?;
         writeln!(writer)?;
 
-        Ok(false)
-    } else {
-        Ok(true)
+        return Ok(false);
     }
+
+    Ok(true)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -315,18 +933,13 @@ fn run_expect_fx<'a, W: std::io::Write>(
         0 => unsafe {
             // we are the child
 
-            use roc_gen_llvm::try_run_jit_function;
-
             let mut child_memory = parent_memory.reuse_mmap().unwrap();
 
             let sequence = ExpectSequence::new(child_memory.ptr);
 
             child_memory.set_shared_buffer(lib);
 
-            let result: Result<(), (String, _)> =
-                try_run_jit_function!(lib, expect.name, (), |v: ()| v);
-
-            if let Err((msg, _)) = result {
+            if let Err(msg) = call_expect(lib, expect) {
                 panic!("roc panic {}", msg);
             }
 
@@ -396,6 +1009,31 @@ fn run_expect_fx<'a, W: std::io::Write>(
     }
 }
 
+/// Run a top-level `expect`, and return the message it crashed with, if it crashed
+fn call_expect(lib: &libloading::Library, expect: ToplevelExpect<'_>) -> Result<(), String> {
+    match expect.backend {
+        ExpectBackend::Llvm => {
+            use roc_gen_llvm::try_run_jit_function;
+
+            let result: Result<(), (String, _)> =
+                try_run_jit_function!(lib, expect.name, (), |v: ()| v);
+
+            result.map_err(|(roc_panic_message, _roc_panic_tag)| roc_panic_message)
+        }
+        ExpectBackend::Dev => {
+            let expect_fn: libloading::Symbol<unsafe extern "C" fn()> = unsafe {
+                lib.get(expect.name.as_bytes())
+            }
+            .unwrap_or_else(|error| internal_error!("Unable to find `{}`: {error}", expect.name));
+
+            // a crash stops the process, so it can't be caught here
+            unsafe { expect_fn() };
+
+            Ok(())
+        }
+    }
+}
+
 pub fn render_expects_in_memory<'a>(
     writer: &mut impl std::io::Write,
     arena: &'a Bump,
@@ -473,25 +1111,43 @@ pub fn render_dbgs_in_memory<'a>(
     )
 }
 
-fn split_expect_lookups(subs: &Subs, lookups: &[ExpectLookup]) -> Vec<Symbol> {
-    lookups
-        .iter()
-        .filter_map(
-            |ExpectLookup {
-                 symbol,
-                 var,
-                 ability_info: _,
-             }| {
-                // mono will have dropped lookups that resolve to functions, so we should not keep
-                // them either.
-                if subs.is_function(*var) {
-                    None
-                } else {
-                    Some(*symbol)
-                }
-            },
-        )
-        .collect()
+/// The value of the `dbg` in shared memory, like the REPL prints it, and its type
+pub fn dbg_value_in_memory<'a>(
+    arena: &'a Bump,
+    expectations: &mut VecMap<ModuleId, Expectations>,
+    interns: &'a Interns,
+    layout_interner: &GlobalLayoutInterner<'a>,
+    memory: &ExpectMemory,
+) -> (Expr<'a>, String) {
+    // we always run programs as the host
+    let target_info = (&target_lexicon::Triple::host()).into();
+
+    let shared_ptr = memory.ptr;
+    let frame = ExpectFrame::at_offset(shared_ptr, ExpectSequence::START_OFFSET);
+    let module_id = frame.module_id;
+
+    let data = expectations.get_mut(&module_id).unwrap();
+
+    let (_offset, mut expressions, variables) = crate::get_values(
+        target_info,
+        arena,
+        &data.subs,
+        interns,
+        layout_interner,
+        shared_ptr,
+        frame.start_offset,
+        1,
+    );
+
+    let expr_type = name_and_print_var(
+        variables[0],
+        &mut data.subs,
+        module_id,
+        interns,
+        DebugPrint::NOTHING,
+    );
+
+    (expressions.remove(0), expr_type)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -569,7 +1225,7 @@ fn render_expect_failure<'a>(
         Some(current) => current,
     };
 
-    let symbols = split_expect_lookups(&data.subs, current);
+    let symbols = crate::split_expect_lookups(&data.subs, current);
 
     let (offset, expressions, variables) = crate::get_values(
         target_info,
@@ -690,6 +1346,18 @@ pub struct ToplevelExpect<'a> {
     pub name: &'a str,
     pub symbol: Symbol,
     pub region: Region,
+    /// The type of each generated input, if this `expect` is a property
+    pub inputs: &'a [Variable],
+    pub backend: ExpectBackend,
+}
+
+/// The backend that compiled the `expect`s, which decides how they are called
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpectBackend {
+    /// The `expect` writes a `RocCallResult`, so a crash can be reported like a failure
+    Llvm,
+    /// The `expect` is called directly, and a crash stops the process that runs it
+    Dev,
 }
 
 #[derive(Debug)]
@@ -753,6 +1421,8 @@ pub fn expect_mono_module_to_dylib<'a>(
     // platform to provide them.
     add_default_roc_externs(&env);
 
+    let properties = toplevel_expects.properties;
+
     let capacity = toplevel_expects.pure.len() + toplevel_expects.fx.len();
     let mut expect_symbols = BumpVec::with_capacity_in(capacity, env.arena);
 
@@ -776,6 +1446,8 @@ pub fn expect_mono_module_to_dylib<'a>(
                 symbol,
                 region,
                 name,
+                inputs: &[],
+                backend: ExpectBackend::Llvm,
             }),
         env.arena,
     );
@@ -789,6 +1461,11 @@ pub fn expect_mono_module_to_dylib<'a>(
                 symbol,
                 region,
                 name,
+                inputs: match properties.get(&symbol) {
+                    Some(inputs) => arena.alloc_slice_copy(inputs),
+                    None => &[],
+                },
+                backend: ExpectBackend::Llvm,
             }),
         env.arena,
     );
@@ -823,3 +1500,95 @@ pub fn expect_mono_module_to_dylib<'a>(
 
     llvm_module_to_dylib(env.module, &target, opt_level).map(|lib| (lib, expects, layout_interner))
 }
+
+/// Like [`expect_mono_module_to_dylib`], but compiles with the dev backend, so it doesn't need LLVM
+pub fn expect_mono_module_to_dylib_dev<'a>(
+    arena: &'a Bump,
+    target: Triple,
+    loaded: MonomorphizedModule<'a>,
+) -> Result<
+    (
+        libloading::Library,
+        ExpectFunctions<'a>,
+        STLayoutInterner<'a>,
+    ),
+    libloading::Error,
+> {
+    let MonomorphizedModule {
+        module_id,
+        toplevel_expects,
+        procedures,
+        mut interns,
+        mut layout_interner,
+        ..
+    } = loaded;
+
+    let mut exposed_to_host = MutSet::default();
+    exposed_to_host.extend(toplevel_expects.pure.keys().copied());
+    exposed_to_host.extend(toplevel_expects.fx.keys().copied());
+
+    let env = roc_gen_dev::Env {
+        arena,
+        module_id,
+        exposed_to_host,
+        lazy_literals: false,
+        // the expects have no platform to provide these
+        generate_allocators: true,
+        mode: roc_gen_dev::AssemblyBackendMode::Test,
+    };
+
+    let object = roc_gen_dev::build_module(
+        &env,
+        &mut interns,
+        &mut layout_interner,
+        &target,
+        procedures,
+    );
+    let object = object.write().expect("failed to build output object");
+
+    // Every expect has a single specialization, so this gives the same name as `build_module` does
+    let top_level = ProcLayout {
+        arguments: &[],
+        result: Layout::UNIT,
+        niche: Niche::NONE,
+    };
+    let mut layout_ids = LayoutIds::default();
+    let mut expect_name = |symbol: Symbol| -> &'a str {
+        let name = layout_ids
+            .get_toplevel(symbol, &top_level)
+            .to_exposed_symbol_string(symbol, &interns);
+
+        arena.alloc_str(&name)
+    };
+
+    let properties = toplevel_expects.properties;
+
+    let mut pure = BumpVec::with_capacity_in(toplevel_expects.pure.len(), arena);
+    for (symbol, region) in toplevel_expects.pure {
+        pure.push(ToplevelExpect {
+            name: expect_name(symbol),
+            symbol,
+            region,
+            inputs: match properties.get(&symbol) {
+                Some(inputs) => arena.alloc_slice_copy(inputs),
+                None => &[],
+            },
+            backend: ExpectBackend::Dev,
+        });
+    }
+
+    let mut fx = BumpVec::with_capacity_in(toplevel_expects.fx.len(), arena);
+    for (symbol, region) in toplevel_expects.fx {
+        fx.push(ToplevelExpect {
+            name: expect_name(symbol),
+            symbol,
+            region,
+            inputs: &[],
+            backend: ExpectBackend::Dev,
+        });
+    }
+
+    let expects = ExpectFunctions { pure, fx };
+
+    dev_object_to_dylib(&object, &target).map(|lib| (lib, expects, layout_interner))
+}
@@ -0,0 +1,323 @@
+//! Render `dbg` and failed `expect` reports from apps built with the wasm backend.
+//!
+//! The wasm backend calls the host's `roc_dbg` and `roc_expect_failed` with the addresses of
+//! the values to render. Those addresses point into the app's 32-bit linear memory, which is
+//! a separate address space from ours, so we read the values from a copy of it.
+//! This code runs natively (when the CLI interprets a wasm app) and in the web REPL.
+
+use std::mem::size_of;
+
+use bumpalo::Bump;
+use roc_collections::VecMap;
+use roc_load::Expectations;
+use roc_module::symbol::{Interns, ModuleId, Symbol};
+use roc_mono::{
+    ir::ProcLayout,
+    layout::{GlobalLayoutInterner, LayoutCache, Niche},
+};
+use roc_parse::ast::Expr;
+use roc_region::all::{Position, Region};
+use roc_repl_eval::{eval::jit_to_ast, ReplApp, ReplAppMemory};
+use roc_reporting::{error::expect::Renderer, report::RenderTarget};
+use roc_target::TargetInfo;
+use roc_types::subs::{Subs, Variable};
+
+/// The arguments the app passed to `roc_dbg` or `roc_expect_failed`
+#[derive(Debug, Clone, Copy)]
+pub struct WasmExpectFrame<'r> {
+    pub module_id: u32,
+    pub region_start: u32,
+    pub region_end: u32,
+    /// The address and type variable of each value looked up
+    pub lookups: &'r [(u32, u32)],
+}
+
+impl WasmExpectFrame<'_> {
+    pub fn module_id(&self) -> ModuleId {
+        unsafe { std::mem::transmute::<u32, ModuleId>(self.module_id) }
+    }
+
+    fn region(&self) -> Region {
+        Region::new(
+            Position::new(self.region_start),
+            Position::new(self.region_end),
+        )
+    }
+}
+
+/// Read the array of `{ address: u32, variable: u32 }` that the app passed to `roc_expect_failed`
+pub fn read_wasm_lookups(memory: &[u8], addr: u32, count: u32) -> Vec<(u32, u32)> {
+    let memory = WasmExpectMemory { bytes: memory };
+    (0..count as usize)
+        .map(|i| {
+            let entry = addr as usize + 8 * i;
+            (memory.deref_u32(entry), memory.deref_u32(entry + 4))
+        })
+        .collect()
+}
+
+/// A copy of a wasm32 app's memory. Pointers and `usize`s are 4 bytes.
+struct WasmExpectMemory<'a> {
+    bytes: &'a [u8],
+}
+
+macro_rules! deref_number {
+    ($name: ident, $t: ty) => {
+        fn $name(&self, addr: usize) -> $t {
+            const N: usize = size_of::<$t>();
+            let mut array = [0; N];
+            array.copy_from_slice(&self.bytes[addr..][..N]);
+            <$t>::from_le_bytes(array)
+        }
+    };
+}
+
+impl ReplAppMemory for WasmExpectMemory<'_> {
+    fn deref_bool(&self, addr: usize) -> bool {
+        self.bytes[addr] != 0
+    }
+
+    deref_number!(deref_u8, u8);
+    deref_number!(deref_u16, u16);
+    deref_number!(deref_u32, u32);
+    deref_number!(deref_u64, u64);
+    deref_number!(deref_u128, u128);
+
+    deref_number!(deref_i8, i8);
+    deref_number!(deref_i16, i16);
+    deref_number!(deref_i32, i32);
+    deref_number!(deref_i64, i64);
+    deref_number!(deref_i128, i128);
+
+    deref_number!(deref_f32, f32);
+    deref_number!(deref_f64, f64);
+
+    fn deref_usize(&self, addr: usize) -> usize {
+        self.deref_u32(addr) as usize
+    }
+
+    fn deref_isize(&self, addr: usize) -> isize {
+        self.deref_i32(addr) as isize
+    }
+
+    fn deref_str(&self, addr: usize) -> &str {
+        let last_byte = self.bytes[addr + 4 + 4 + 3] as i8;
+        let is_small = last_byte < 0;
+
+        let str_bytes = if is_small {
+            let len = (last_byte & 0x7f) as usize;
+            &self.bytes[addr..][..len]
+        } else {
+            let chars_index = self.deref_usize(addr);
+            let len = self.deref_usize(addr + 4);
+            &self.bytes[chars_index..][..len]
+        };
+
+        unsafe { std::str::from_utf8_unchecked(str_bytes) }
+    }
+
+    fn deref_pointer_with_tag_id(&self, addr: usize) -> (u16, u64) {
+        let addr_with_id = self.deref_usize(addr);
+        let tag_id_mask = 0b11;
+
+        let tag_id = addr_with_id & tag_id_mask;
+        let data_addr = addr_with_id & !tag_id_mask;
+        (tag_id as _, data_addr as _)
+    }
+}
+
+/// Reads a value that the app has already computed, at `offset` in its memory
+struct WasmExpectApp<'a> {
+    memory: &'a WasmExpectMemory<'a>,
+    offset: usize,
+}
+
+impl<'a> ReplApp<'a> for WasmExpectApp<'a> {
+    type Memory = WasmExpectMemory<'a>;
+
+    fn call_function<Return, F>(&mut self, _main_fn_name: &str, mut transform: F) -> Expr<'a>
+    where
+        F: FnMut(&'a Self::Memory, Return) -> Expr<'a>,
+        Self::Memory: 'a,
+    {
+        // Only used for numbers and Bool, which have the same size on wasm32 and the host
+        let bytes = &self.memory.bytes[self.offset..][..size_of::<Return>()];
+        let result: Return = unsafe { std::ptr::read_unaligned(bytes.as_ptr().cast()) };
+
+        transform(self.memory, result)
+    }
+
+    fn call_function_returns_roc_list<F>(
+        &mut self,
+        _main_fn_name: &str,
+        mut transform: F,
+    ) -> Expr<'a>
+    where
+        F: FnMut(&'a Self::Memory, (usize, usize, usize)) -> Expr<'a>,
+        Self::Memory: 'a,
+    {
+        let list = (
+            self.memory.deref_usize(self.offset),
+            self.memory.deref_usize(self.offset + 4),
+            self.memory.deref_usize(self.offset + 8),
+        );
+
+        transform(self.memory, list)
+    }
+
+    fn call_function_dynamic_size<T, F>(
+        &mut self,
+        _main_fn_name: &str,
+        _ret_bytes: usize,
+        mut transform: F,
+    ) -> T
+    where
+        F: FnMut(&'a Self::Memory, usize) -> T,
+        Self::Memory: 'a,
+    {
+        transform(self.memory, self.offset)
+    }
+}
+
+fn get_wasm_values<'a>(
+    arena: &'a Bump,
+    subs: &Subs,
+    interns: &'a Interns,
+    layout_interner: &GlobalLayoutInterner<'a>,
+    memory: &[u8],
+    lookups: &[(u32, u32)],
+) -> (Vec<Expr<'a>>, Vec<Variable>) {
+    let target_info = TargetInfo::default_wasm32();
+    let memory = arena.alloc(WasmExpectMemory {
+        bytes: arena.alloc_slice_copy(memory),
+    });
+
+    lookups
+        .iter()
+        .map(|(addr, variable)| {
+            let variable = unsafe { Variable::from_index(*variable) };
+
+            let mut layout_cache = LayoutCache::new(layout_interner.fork(), target_info);
+            let layout = layout_cache.from_var(arena, variable, subs).unwrap();
+
+            let proc_layout = ProcLayout {
+                arguments: &[],
+                result: layout,
+                niche: Niche::NONE,
+            };
+
+            let mut app = WasmExpectApp {
+                memory,
+                offset: *addr as usize,
+            };
+
+            let expr = jit_to_ast(
+                arena,
+                &mut app,
+                "expect_repl_main_fn",
+                proc_layout,
+                variable,
+                subs,
+                interns,
+                layout_interner.fork(),
+                target_info,
+            );
+
+            (expr, variable)
+        })
+        .unzip()
+}
+
+/// Render a call to `roc_dbg`. `source` is the source code of the frame's module.
+#[allow(clippy::too_many_arguments)]
+pub fn render_wasm_dbg<'a>(
+    writer: &mut impl std::io::Write,
+    render_target: RenderTarget,
+    arena: &'a Bump,
+    expectations: &mut VecMap<ModuleId, Expectations>,
+    interns: &'a Interns,
+    layout_interner: &GlobalLayoutInterner<'a>,
+    source: &'a str,
+    memory: &[u8],
+    frame: WasmExpectFrame,
+) -> std::io::Result<()> {
+    let module_id = frame.module_id();
+    let dbg_symbol = unsafe { std::mem::transmute::<Region, Symbol>(frame.region()) };
+
+    let data = expectations.get_mut(&module_id).unwrap();
+    let failure_region = match data.dbgs.get(&dbg_symbol) {
+        None => panic!("symbol {dbg_symbol:?} not in list of dbgs"),
+        Some(current) => current.region,
+    };
+
+    let (expressions, _variables) = get_wasm_values(
+        arena,
+        &data.subs,
+        interns,
+        layout_interner,
+        memory,
+        frame.lookups,
+    );
+
+    let renderer = Renderer::new(
+        arena,
+        interns,
+        render_target,
+        module_id,
+        data.path.to_owned(),
+        source,
+    );
+
+    renderer.render_dbg(writer, &expressions, Some(Region::zero()), failure_region)
+}
+
+/// Render a call to `roc_expect_failed`. `source` is the source code of the frame's module.
+#[allow(clippy::too_many_arguments)]
+pub fn render_wasm_expect_failure<'a>(
+    writer: &mut impl std::io::Write,
+    render_target: RenderTarget,
+    arena: &'a Bump,
+    expectations: &mut VecMap<ModuleId, Expectations>,
+    interns: &'a Interns,
+    layout_interner: &GlobalLayoutInterner<'a>,
+    source: &'a str,
+    memory: &[u8],
+    frame: WasmExpectFrame,
+) -> std::io::Result<()> {
+    let module_id = frame.module_id();
+    let failure_region = frame.region();
+
+    let data = expectations.get_mut(&module_id).unwrap();
+    let symbols = match data.expectations.get(&failure_region) {
+        None => panic!("region {failure_region:?} not in list of expects"),
+        Some(current) => crate::split_expect_lookups(&data.subs, current),
+    };
+
+    let (expressions, variables) = get_wasm_values(
+        arena,
+        &data.subs,
+        interns,
+        layout_interner,
+        memory,
+        frame.lookups,
+    );
+
+    let renderer = Renderer::new(
+        arena,
+        interns,
+        render_target,
+        module_id,
+        data.path.to_owned(),
+        source,
+    );
+
+    renderer.render_failure(
+        writer,
+        &mut data.subs,
+        &symbols,
+        &variables,
+        &expressions,
+        None,
+        failure_region,
+    )
+}
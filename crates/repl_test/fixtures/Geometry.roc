@@ -0,0 +1,7 @@
+interface Geometry
+    exposes [area, unitSquare]
+    imports []
+
+unitSquare = { width: 1, height: 1 }
+
+area = \{ width, height } -> width * height
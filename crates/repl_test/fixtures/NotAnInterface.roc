@@ -0,0 +1,6 @@
+app "not-an-interface"
+    packages { pf: "platform/main.roc" }
+    imports []
+    provides [main] to pf
+
+main = "Hello!"
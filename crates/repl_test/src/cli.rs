@@ -1,7 +1,7 @@
 use std::env;
 use std::io::Write;
 use std::path::PathBuf;
-use std::process::{Command, ExitStatus, Stdio};
+use std::process::{Command, ExitStatus, Output, Stdio};
 
 use roc_repl_cli::{SHORT_INSTRUCTIONS, WELCOME_MESSAGE};
 use roc_test_utils::assert_multiline_str_eq;
@@ -40,38 +40,7 @@ fn path_to_roc_binary() -> PathBuf {
 }
 
 pub fn repl_eval(input: &str) -> Out {
-    let mut cmd = Command::new(path_to_roc_binary());
-
-    cmd.arg("repl");
-
-    let mut child = cmd
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .expect("failed to execute compiled `roc` binary in CLI test");
-
-    {
-        let stdin = child.stdin.as_mut().expect("Failed to open stdin");
-
-        // Send the input expression
-        stdin
-            .write_all(input.as_bytes())
-            .expect("Failed to write input to stdin");
-
-        // Evaluate the expression
-        stdin
-            .write_all(b"\n")
-            .expect("Failed to write newline to stdin");
-
-        // Gracefully exit the repl
-        stdin
-            .write_all(b":exit\n")
-            .expect("Failed to write :exit to stdin");
-    }
-
-    let output = child
-        .wait_with_output()
-        .expect("Error waiting for REPL child process to exit.");
+    let output = run_repl(&[], input);
 
     // Remove the initial instructions from the output.
 
@@ -123,6 +92,54 @@ pub fn repl_eval(input: &str) -> Out {
     }
 }
 
+/// Run `roc repl` with the arguments, enter the input and exit
+fn run_repl(args: &[&str], input: &str) -> Output {
+    let mut cmd = Command::new(path_to_roc_binary());
+
+    cmd.arg("repl").args(args);
+
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to execute compiled `roc` binary in CLI test");
+
+    {
+        let stdin = child.stdin.as_mut().expect("Failed to open stdin");
+
+        // Send the input expression
+        stdin
+            .write_all(input.as_bytes())
+            .expect("Failed to write input to stdin");
+
+        // Evaluate the expression
+        stdin
+            .write_all(b"\n")
+            .expect("Failed to write newline to stdin");
+
+        // Gracefully exit the repl
+        stdin
+            .write_all(b":exit\n")
+            .expect("Failed to write :exit to stdin");
+    }
+
+    child
+        .wait_with_output()
+        .expect("Error waiting for REPL child process to exit.")
+}
+
+/// Enter the input in `roc repl --platform`, and return everything it and the host printed
+pub fn repl_eval_with_platform(platform: &str, input: &str) -> Out {
+    let output = run_repl(&["--platform", platform], input);
+    let stdout = strip_ansi_escapes::strip(output.stdout).unwrap();
+
+    Out {
+        stdout: String::from_utf8(stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap(),
+        status: output.status,
+    }
+}
+
 pub fn expect_success(input: &str, expected: &str) {
     let out = repl_eval(input.trim());
 
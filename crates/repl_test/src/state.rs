@@ -101,6 +101,176 @@ fn standalone_annotation() {
     assert_eq!(state.step(&input, None), Ok(String::new()));
 }
 
+#[test]
+fn type_of_expr() {
+    let mut state = ReplState::new();
+
+    complete("x = 5", &mut state, Ok(("5 : Num *", "x")));
+    command(":type x + 1", &mut state, "x + 1 : Num *");
+    command(":TYPE \"hi\"", &mut state, "\"hi\" : Str");
+
+    // :type doesn't evaluate, so it doesn't name the expr either
+    complete("x", &mut state, Ok(("5 : Num *", "x")));
+    complete("2", &mut state, Ok(("2 : Num *", "val1")));
+}
+
+#[test]
+fn multiline_type() {
+    let mut input = ":type \\x ->".to_string();
+
+    incomplete(&mut input);
+
+    input.push_str("    x + 1");
+
+    command(
+        &input,
+        &mut ReplState::new(),
+        "\\x ->\n    x + 1 : Num a -> Num a",
+    );
+}
+
+#[test]
+fn defs_and_reset() {
+    let mut state = ReplState::new();
+
+    command(":defs", &mut state, "No definitions yet.");
+    complete("x = 5", &mut state, Ok(("5 : Num *", "x")));
+    complete("x + 1", &mut state, Ok(("6 : Num *", "val1")));
+    command(":defs", &mut state, "x = 5\n\nval1 = x + 1");
+    command(
+        ":reset",
+        &mut state,
+        "Cleared all definitions and loaded modules.",
+    );
+    command(":defs", &mut state, "No definitions yet.");
+    complete("2 + 3", &mut state, Ok(("5 : Num *", "val1")));
+}
+
+#[test]
+fn time() {
+    let mut state = ReplState::new();
+
+    command(":time", &mut state, "Timing is on.");
+
+    let output = state.step("1 + 1", None).unwrap();
+    let output = String::from_utf8(strip_ansi_escapes::strip(output).unwrap()).unwrap();
+
+    assert!(output.contains("2 : Num *"), "{output}");
+    assert!(output.contains("compiled in"), "{output}");
+
+    command(":time", &mut state, "Timing is off.");
+}
+
+#[test]
+fn load_module() {
+    let mut state = ReplState::new();
+
+    command(
+        &format!(":load {}", fixture("Geometry.roc")),
+        &mut state,
+        "Loaded Geometry, exposing area, unitSquare",
+    );
+    complete("area unitSquare", &mut state, Ok(("1 : Num *", "val1")));
+    complete(
+        "area { width: 2, height: 3 }",
+        &mut state,
+        Ok(("6 : Num *", "val2")),
+    );
+
+    command(
+        ":reset",
+        &mut state,
+        "Cleared all definitions and loaded modules.",
+    );
+    assert!(state
+        .step("area unitSquare", None)
+        .unwrap()
+        .contains("UNRECOGNIZED NAME"));
+}
+
+#[test]
+fn load_non_interface() {
+    let path = fixture("NotAnInterface.roc");
+
+    command(
+        &format!(":load {path}"),
+        &mut ReplState::new(),
+        &format!("Only interface modules can be loaded, and {path} is not one."),
+    );
+}
+
+#[test]
+fn complete_commands() {
+    let state = ReplState::new();
+
+    assert_eq!(
+        state.completions(":t", 2),
+        (0, vec![":time".to_string(), ":type".to_string()])
+    );
+}
+
+#[test]
+fn complete_past_defs() {
+    let mut state = ReplState::new();
+
+    complete("count = 5", &mut state, Ok(("5 : Num *", "count")));
+    complete("counter = 6", &mut state, Ok(("6 : Num *", "counter")));
+
+    assert_eq!(
+        state.completions("1 + cou", 7),
+        (4, vec!["count".to_string(), "counter".to_string()])
+    );
+}
+
+#[test]
+fn complete_builtin_members() {
+    let state = ReplState::new();
+    let (start, candidates) = state.completions("List.ma", 7);
+
+    assert_eq!(start, 5);
+    assert!(candidates.contains(&"map".to_string()), "{candidates:?}");
+    assert!(candidates.contains(&"map2".to_string()), "{candidates:?}");
+    assert!(candidates.iter().all(|name| name.starts_with("ma")));
+
+    assert_eq!(state.completions("Di", 2), (0, vec!["Dict".to_string()]));
+}
+
+#[test]
+fn complete_record_fields() {
+    let mut state = ReplState::new();
+
+    state
+        .step(
+            "user = { name: \"Sam\", address: { city: \"Lyon\" } }",
+            None,
+        )
+        .unwrap();
+
+    assert_eq!(
+        state.completions("user.", 5),
+        (5, vec!["address".to_string(), "name".to_string()])
+    );
+    assert_eq!(
+        state.completions("user.address.c", 14),
+        (13, vec!["city".to_string()])
+    );
+}
+
+fn fixture(file_name: &str) -> String {
+    format!("{}/fixtures/{}", env!("CARGO_MANIFEST_DIR"), file_name)
+}
+
+/// step a meta-command like `:type`, then check its output with ANSI escape codes stripped.
+fn command(input: &str, state: &mut ReplState, expected_output: &str) {
+    assert!(!is_incomplete(input));
+
+    let escaped = state.step(input, None).map(|string| {
+        std::string::String::from_utf8(strip_ansi_escapes::strip(string.trim()).unwrap()).unwrap()
+    });
+
+    assert_eq!(Ok(expected_output.to_string()), escaped);
+}
+
 /// validate and step the given input, then check the Result vs the output
 /// with ANSI escape codes stripped.
 fn complete(input: &str, state: &mut ReplState, expected_step_result: Result<(&str, &str), i32>) {
@@ -4,7 +4,7 @@ use indoc::indoc;
 use roc_test_utils::assert_multiline_str_eq;
 
 #[cfg(not(feature = "wasm"))]
-use crate::cli::{expect_failure, expect_success, repl_eval};
+use crate::cli::{expect_failure, expect_success, repl_eval, repl_eval_with_platform};
 
 #[cfg(feature = "wasm")]
 #[allow(unused_imports)]
@@ -1268,3 +1268,28 @@ fn enum_tag_union_in_list() {
         r#"[E, F, G, H] : List [E, F, G, H]"#,
     );
 }
+
+#[cfg(not(feature = "wasm"))]
+#[test]
+fn platform_runs_task() {
+    let platform = format!(
+        "{}/../../examples/cli/cli-platform/main.roc",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    let out = repl_eval_with_platform(
+        &platform,
+        r#"Stdout.line "Hello from the host" |> Task.await \{} -> Task.succeed 42"#,
+    );
+
+    assert!(out.status.success());
+    assert!(
+        out.stdout.contains("Hello from the host"),
+        "Unexpected repl output: {}",
+        out.stdout
+    );
+    assert!(
+        out.stdout.contains("Ok 42 :"),
+        "Unexpected repl output: {}",
+        out.stdout
+    );
+}
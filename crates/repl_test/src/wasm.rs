@@ -6,12 +6,46 @@ use roc_wasm_interp::{
 const COMPILER_BYTES: &[u8] =
     include_bytes!("../../../target/wasm32-wasi/release/roc_repl_wasm.wasm");
 
+/// Records calls to `roc_dbg` and `roc_expect_failed`, like the browser does
+#[derive(Default)]
+struct AppDispatcher<'a> {
+    default: DefaultImportDispatcher<'a>,
+    /// The header for `test_get_expect_report`, and a copy of the app's memory
+    expect_reports: Vec<([u32; 6], Vec<u8>)>,
+}
+
+impl<'a> ImportDispatcher for AppDispatcher<'a> {
+    fn dispatch(
+        &mut self,
+        module_name: &str,
+        function_name: &str,
+        arguments: &[Value],
+        app_memory: &mut [u8],
+    ) -> Option<Value> {
+        let kind = match (module_name, function_name) {
+            ("env", "roc_dbg") => 0,
+            ("env", "roc_expect_failed") => 1,
+            _ => {
+                return self
+                    .default
+                    .dispatch(module_name, function_name, arguments, app_memory)
+            }
+        };
+        let mut header = [kind; 6];
+        for (word, arg) in header[1..].iter_mut().zip(arguments) {
+            *word = arg.expect_i32().unwrap() as u32;
+        }
+        self.expect_reports.push((header, app_memory.to_vec()));
+        None
+    }
+}
+
 struct CompilerDispatcher<'a> {
     arena: &'a Bump,
     src: &'a str,
     answer: String,
     wasi: WasiDispatcher<'a>,
-    app: Option<Instance<'a, DefaultImportDispatcher<'a>>>,
+    app: Option<Instance<'a, AppDispatcher<'a>>>,
     result_addr: Option<i32>,
 }
 
@@ -47,7 +81,7 @@ impl<'a> ImportDispatcher for CompilerDispatcher<'a> {
                     let instance = Instance::from_bytes(
                         self.arena,
                         app_bytes,
-                        DefaultImportDispatcher::default(),
+                        AppDispatcher::default(),
                         is_debug_mode,
                     )
                     .unwrap();
@@ -61,6 +95,7 @@ impl<'a> ImportDispatcher for CompilerDispatcher<'a> {
                     assert_eq!(arguments.len(), 0);
                     match &mut self.app {
                         Some(instance) => {
+                            instance.import_dispatcher.expect_reports.clear();
                             let result_addr = instance
                                 .call_export("wrapper", [])
                                 .unwrap()
@@ -90,6 +125,35 @@ impl<'a> ImportDispatcher for CompilerDispatcher<'a> {
                         None => panic!("Trying to get result and memory but there is no app"),
                     }
                 }
+                "test_get_expect_report" => {
+                    // Write the header of a report into the compiler's memory, and return the
+                    // size of its memory snapshot, or 0 if there are no more reports.
+                    // fn test_get_expect_report(index: usize, header_addr: *mut u32) -> usize;
+                    assert_eq!(arguments.len(), 2);
+                    let index = arguments[0].expect_i32().unwrap() as usize;
+                    let header_addr = arguments[1].expect_i32().unwrap() as usize;
+                    let reports = &self.app.as_ref().unwrap().import_dispatcher.expect_reports;
+                    match reports.get(index) {
+                        Some((header, memory)) => {
+                            for (i, word) in header.iter().enumerate() {
+                                compiler_memory[header_addr + 4 * i..][..4]
+                                    .copy_from_slice(&word.to_le_bytes());
+                            }
+                            Some(Value::I32(memory.len() as i32))
+                        }
+                        None => Some(Value::I32(0)),
+                    }
+                }
+                "test_get_expect_report_memory" => {
+                    // fn test_get_expect_report_memory(index: usize, buffer_alloc_addr: *mut u8);
+                    assert_eq!(arguments.len(), 2);
+                    let index = arguments[0].expect_i32().unwrap() as usize;
+                    let buffer_alloc_addr = arguments[1].expect_i32().unwrap() as usize;
+                    let reports = &self.app.as_ref().unwrap().import_dispatcher.expect_reports;
+                    let (_, memory) = &reports[index];
+                    compiler_memory[buffer_alloc_addr..][..memory.len()].copy_from_slice(memory);
+                    None
+                }
                 "test_copy_input_string" => {
                     // Copy the Roc source code from the test into the compiler Wasm instance
                     // fn test_copy_input_string(src_buffer_addr: *mut u8);
@@ -14,6 +14,10 @@ extern "C" {
 
     pub fn js_get_result_and_memory(buffer_alloc_addr: *mut u8) -> usize;
 
+    pub fn js_get_expect_report(index: usize, header_addr: *mut u32) -> usize;
+
+    pub fn js_get_expect_report_memory(index: usize, buffer_alloc_addr: *mut u8);
+
     #[wasm_bindgen(js_namespace = console)]
     fn log(s: &str);
 }
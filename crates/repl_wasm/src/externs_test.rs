@@ -4,6 +4,8 @@ extern "C" {
     fn test_create_app(app_bytes_ptr: *const u8, app_bytes_len: usize) -> u32;
     fn test_run_app() -> usize;
     fn test_get_result_and_memory(buffer_alloc_addr: *mut u8) -> usize;
+    fn test_get_expect_report(index: usize, header_addr: *mut u32) -> usize;
+    fn test_get_expect_report_memory(index: usize, buffer_alloc_addr: *mut u8);
     fn test_copy_input_string(src_buffer_addr: *mut u8);
     fn test_copy_output_string(output_ptr: *const u8, output_len: usize);
 }
@@ -26,6 +28,14 @@ pub fn js_get_result_and_memory(buffer_alloc_addr: *mut u8) -> usize {
     unsafe { test_get_result_and_memory(buffer_alloc_addr) }
 }
 
+pub fn js_get_expect_report(index: usize, header_addr: *mut u32) -> usize {
+    unsafe { test_get_expect_report(index, header_addr) }
+}
+
+pub fn js_get_expect_report_memory(index: usize, buffer_alloc_addr: *mut u8) {
+    unsafe { test_get_expect_report_memory(index, buffer_alloc_addr) }
+}
+
 /// Entrypoint for tests using WASI and a CLI interpreter
 /// - Synchronous API, to avoid the need to run an async executor across the Wasm/native boundary.
 /// - Uses an extra callback to allocate & copy the input string (in the browser version, wasm_bindgen does this)
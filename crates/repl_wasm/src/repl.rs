@@ -1,23 +1,35 @@
 use bumpalo::{collections::vec::Vec, Bump};
 use std::mem::size_of;
 
-use roc_collections::all::MutSet;
+use roc_collections::{all::MutSet, VecMap};
 use roc_gen_wasm::wasm32_result;
-use roc_load::MonomorphizedModule;
+use roc_load::{Expectations, MonomorphizedModule};
+use roc_module::symbol::{Interns, ModuleId};
+use roc_mono::layout::GlobalLayoutInterner;
 use roc_parse::ast::Expr;
 use roc_repl_eval::{
     eval::jit_to_ast,
-    gen::{compile_to_mono, format_answer},
+    gen::{compile_to_mono, format_answer, ReplImports},
     ReplApp, ReplAppMemory,
 };
-use roc_reporting::report::DEFAULT_PALETTE_HTML;
+use roc_repl_expect::wasm::{
+    read_wasm_lookups, render_wasm_dbg, render_wasm_expect_failure, WasmExpectFrame,
+};
+use roc_reporting::report::{RenderTarget, DEFAULT_PALETTE_HTML};
 use roc_target::TargetInfo;
 use roc_types::pretty_print::{name_and_print_var, DebugPrint};
 
-use crate::{js_create_app, js_get_result_and_memory, js_run_app};
+use crate::{
+    js_create_app, js_get_expect_report, js_get_expect_report_memory, js_get_result_and_memory,
+    js_run_app,
+};
 
 const WRAPPER_NAME: &str = "wrapper";
 
+/// The first word of the header from `js_get_expect_report` is 0 for `roc_dbg`
+/// and 1 for `roc_expect_failed`
+const EXPECT_REPORT_DBG: u32 = 0;
+
 pub struct WasmReplApp<'a> {
     arena: &'a Bump,
 }
@@ -179,6 +191,7 @@ pub async fn entrypoint_from_js(src: String) -> Result<String, String> {
     // See the variable by the same name in the CLI REPL for how to do this!
     let mono = match compile_to_mono(
         arena,
+        &ReplImports::default(),
         std::iter::empty(),
         &src,
         target_info,
@@ -209,6 +222,8 @@ pub async fn entrypoint_from_js(src: String) -> Result<String, String> {
         mut subs,
         exposed_to_host,
         mut layout_interner,
+        mut expectations,
+        sources,
         ..
     } = mono;
 
@@ -278,6 +293,7 @@ pub async fn entrypoint_from_js(src: String) -> Result<String, String> {
         .map_err(|js| format!("{:?}", js))?;
 
     let mut app = WasmReplApp { arena };
+    let layout_interner = layout_interner.into_global();
 
     // Run the app and transform the result value to an AST `Expr`
     // Restore type constructor names, and other user-facing info that was erased during compilation.
@@ -289,15 +305,85 @@ pub async fn entrypoint_from_js(src: String) -> Result<String, String> {
         main_fn_var,
         &subs,
         &interns,
-        layout_interner.into_global().fork(),
+        layout_interner.fork(),
         target_info,
     );
 
+    let source = arena.alloc_str(&sources[&module_id].1);
+    let debug_output =
+        render_expect_reports(arena, &mut expectations, &interns, &layout_interner, source);
+
     let var_name = String::new(); // TODO turn this into something like " # val1"
 
     // Transform the Expr to a string
     // `Result::Err` becomes a JS exception that will be caught and displayed
     let expr = format_answer(arena, res_answer);
 
-    Ok(format!("{expr} : {expr_type_str}{var_name}"))
+    Ok(format!("{debug_output}{expr} : {expr_type_str}{var_name}"))
+}
+
+/// Render each `dbg` and failed `expect` from the app's last run.
+/// JS records the arguments of every call to `roc_dbg` and `roc_expect_failed`, along with a
+/// snapshot of the app's memory, since the values may be overwritten before the app returns.
+fn render_expect_reports<'a>(
+    arena: &'a Bump,
+    expectations: &mut VecMap<ModuleId, Expectations>,
+    interns: &'a Interns,
+    layout_interner: &GlobalLayoutInterner<'a>,
+    source: &'a str,
+) -> String {
+    let mut output = std::vec::Vec::new();
+    let mut header = [0; 6];
+
+    for index in 0.. {
+        let memory_size = js_get_expect_report(index, header.as_mut_ptr());
+        if memory_size == 0 {
+            break;
+        }
+        let memory: &mut [u8] = arena.alloc_slice_fill_default(memory_size);
+        js_get_expect_report_memory(index, memory.as_mut_ptr());
+
+        let [kind, module_id, region_start, region_end, arg3, arg4] = header;
+        let is_dbg = kind == EXPECT_REPORT_DBG;
+        let lookups = if is_dbg {
+            vec![(arg3, arg4)]
+        } else {
+            read_wasm_lookups(memory, arg3, arg4)
+        };
+        let frame = WasmExpectFrame {
+            module_id,
+            region_start,
+            region_end,
+            lookups: &lookups,
+        };
+
+        if is_dbg {
+            render_wasm_dbg(
+                &mut output,
+                RenderTarget::Generic,
+                arena,
+                expectations,
+                interns,
+                layout_interner,
+                source,
+                memory,
+                frame,
+            )
+        } else {
+            render_wasm_expect_failure(
+                &mut output,
+                RenderTarget::Generic,
+                arena,
+                expectations,
+                interns,
+                layout_interner,
+                source,
+                memory,
+                frame,
+            )
+        }
+        .unwrap();
+    }
+
+    String::from_utf8(output).unwrap()
 }
@@ -75,3 +75,29 @@ void *roc_memset(void *str, int c, size_t n)
 {
     return memset(str, c, n);
 }
+
+//--------------------------
+
+// The Wasm backend only lowers `dbg` and `expect` if the host defines these functions.
+// We forward them to imports of the same name. The browser (or test interpreter) copies
+// the app's memory on each call, so that the REPL can render the values after the app returns.
+
+__attribute__((import_module("env"), import_name("roc_dbg"))) void
+import_roc_dbg(unsigned int module_id, unsigned int region_start, unsigned int region_end,
+               void *value, unsigned int variable);
+
+__attribute__((import_module("env"), import_name("roc_expect_failed"))) void
+import_roc_expect_failed(unsigned int module_id, unsigned int region_start,
+                         unsigned int region_end, void *lookups, unsigned int lookup_count);
+
+void roc_dbg(unsigned int module_id, unsigned int region_start, unsigned int region_end,
+             void *value, unsigned int variable)
+{
+    import_roc_dbg(module_id, region_start, region_end, value, variable);
+}
+
+void roc_expect_failed(unsigned int module_id, unsigned int region_start,
+                       unsigned int region_end, void *lookups, unsigned int lookup_count)
+{
+    import_roc_expect_failed(module_id, region_start, region_end, lookups, lookup_count);
+}
@@ -1,9 +1,33 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use roc_collections::MutMap;
 use roc_module::symbol::{Interns, ModuleId};
-use roc_region::all::LineInfo;
+use roc_problem::Severity;
+use roc_region::all::{LineColumn, LineColumnRegion, LineInfo, Region};
 use roc_solve_problem::TypeError;
+use serde_json::{json, Value};
+
+use crate::report::{CiWrite, RenderTarget, Report, RocDocAllocator};
+
+/// How problems get printed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MessageFormat {
+    /// Colored reports, meant to be read by people
+    Human,
+    /// One JSON object per line, meant to be read by other programs (CI, editor plugins, etc.)
+    Json,
+}
+
+impl MessageFormat {
+    /// How reports that are rendered while loading (e.g. parse errors) should be rendered
+    pub fn render_target(self) -> RenderTarget {
+        match self {
+            MessageFormat::Human => RenderTarget::ColorTerminal,
+            // Problems that stop loading come back as JSON diagnostics
+            MessageFormat::Json => RenderTarget::Json,
+        }
+    }
+}
 
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
 pub struct Problems {
@@ -48,6 +72,57 @@ impl Problems {
             total_time.as_millis(),
         );
     }
+
+    /// The `--message-format=json` counterpart of [Problems::print_to_stdout]
+    pub fn print_json_to_stdout(&self, total_time: std::time::Duration) {
+        let summary = json!({
+            "kind": "summary",
+            "errors": self.errors,
+            "warnings": self.warnings,
+            "time_ms": total_time.as_millis() as u64,
+        });
+
+        println!("{}", summary);
+    }
+}
+
+/// A problem that stopped loading (e.g. a syntax error) as a JSON diagnostic, which the loader
+/// returns instead of a rendered report when it renders to [RenderTarget::Json]
+#[derive(Debug)]
+pub struct JsonDiagnostic(Value);
+
+impl JsonDiagnostic {
+    pub fn new<'b>(
+        report: Report<'b>,
+        alloc: &'b RocDocAllocator<'b>,
+        lines: &LineInfo,
+        module_path: &Path,
+        region: Option<Region>,
+    ) -> Self {
+        Self(json_diagnostic(report, alloc, lines, module_path, region))
+    }
+}
+
+impl std::fmt::Display for JsonDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Print a report that was already rendered (e.g. by a tool other than the loader) as a JSON
+/// diagnostic. We don't know where such a report points, so it has no file or region.
+pub fn print_json_formatted_report(report: &str) {
+    let diagnostic = json!({
+        "kind": "diagnostic",
+        "severity": "error",
+        "title": Value::Null,
+        "file": Value::Null,
+        "region": Value::Null,
+        "message": report.trim(),
+        "related": [],
+    });
+
+    println!("{}", diagnostic);
 }
 
 pub fn report_problems(
@@ -56,8 +131,9 @@ pub fn report_problems(
     interns: &Interns,
     can_problems: &mut MutMap<ModuleId, Vec<roc_problem::can::Problem>>,
     type_problems: &mut MutMap<ModuleId, Vec<TypeError>>,
+    message_format: MessageFormat,
 ) -> Problems {
-    use crate::report::{can_problem, type_problem, Report, RocDocAllocator, DEFAULT_PALETTE};
+    use crate::report::{can_problem, type_problem, DEFAULT_PALETTE};
     use roc_problem::Severity::*;
     let palette = DEFAULT_PALETTE;
 
@@ -79,11 +155,21 @@ pub fn report_problems(
         let problems = can_problems.remove(home).unwrap_or_default();
 
         for problem in problems.into_iter() {
+            let region = problem.region();
             let report = can_problem(&alloc, &lines, module_path.clone(), problem);
             let severity = report.severity;
-            let mut buf = String::new();
+            let buf = match message_format {
+                MessageFormat::Human => {
+                    let mut buf = String::new();
+
+                    report.render_color_terminal(&mut buf, &alloc, &palette);
 
-            report.render_color_terminal(&mut buf, &alloc, &palette);
+                    buf
+                }
+                MessageFormat::Json => {
+                    json_diagnostic(report, &alloc, &lines, module_path, region).to_string()
+                }
+            };
 
             match severity {
                 Warning => {
@@ -98,11 +184,22 @@ pub fn report_problems(
         let problems = type_problems.remove(home).unwrap_or_default();
 
         for problem in problems {
+            let region = problem.region();
+
             if let Some(report) = type_problem(&alloc, &lines, module_path.clone(), problem) {
                 let severity = report.severity;
-                let mut buf = String::new();
+                let buf = match message_format {
+                    MessageFormat::Human => {
+                        let mut buf = String::new();
+
+                        report.render_color_terminal(&mut buf, &alloc, &palette);
 
-                report.render_color_terminal(&mut buf, &alloc, &palette);
+                        buf
+                    }
+                    MessageFormat::Json => {
+                        json_diagnostic(report, &alloc, &lines, module_path, region).to_string()
+                    }
+                };
 
                 match severity {
                     Warning => {
@@ -116,6 +213,18 @@ pub fn report_problems(
         }
     }
 
+    if message_format == MessageFormat::Json {
+        // Tools can filter by severity themselves, so give them everything.
+        for diagnostic in errors.iter().chain(warnings.iter()) {
+            println!("{}", diagnostic);
+        }
+
+        return Problems {
+            errors: errors.len(),
+            warnings: warnings.len(),
+        };
+    }
+
     let problems_reported;
 
     // Only print warnings if there are no errors
@@ -148,3 +257,83 @@ pub fn report_problems(
         warnings: warnings.len(),
     }
 }
+
+/// A report as a JSON object. Lines and columns are 1-based, and columns count characters.
+///
+/// The main region is the problem's own region when it has one, or else the first region the
+/// report highlights. The other highlighted regions are listed as related regions.
+fn json_diagnostic<'b>(
+    report: Report<'b>,
+    alloc: &'b RocDocAllocator<'b>,
+    lines: &LineInfo,
+    module_path: &Path,
+    region: Option<Region>,
+) -> Value {
+    let mut highlighted = alloc.take_highlighted_regions();
+
+    let region = match region {
+        Some(region) => Some(lines.convert_region(region)),
+        None if highlighted.is_empty() => None,
+        None => Some(highlighted.remove(0)),
+    };
+
+    highlighted.retain(|related| Some(*related) != region);
+
+    let file = module_path.to_string_lossy();
+    let src_lines = alloc.src_lines;
+
+    let related: Vec<Value> = highlighted
+        .into_iter()
+        .map(|related| {
+            json!({
+                "file": file,
+                "region": json_region(src_lines, related),
+            })
+        })
+        .collect();
+
+    let severity = match report.severity {
+        Severity::RuntimeError => "error",
+        Severity::Warning => "warning",
+    };
+
+    // Just the body of the report; the title and file get fields of their own.
+    let mut message = String::new();
+
+    report
+        .doc
+        .1
+        .render_raw(70, &mut CiWrite::new(&mut message))
+        .expect("<buffer is not a utf-8 encoded string>");
+
+    json!({
+        "kind": "diagnostic",
+        "severity": severity,
+        "title": report.title,
+        "file": file,
+        "region": region.map(|region| json_region(src_lines, region)),
+        "message": message.trim(),
+        "related": related,
+    })
+}
+
+fn json_region(src_lines: &[&str], region: LineColumnRegion) -> Value {
+    json!({
+        "start": json_position(src_lines, region.start),
+        "end": json_position(src_lines, region.end),
+    })
+}
+
+fn json_position(src_lines: &[&str], position: LineColumn) -> Value {
+    // LineColumn counts bytes, but most consumers expect characters.
+    let line = src_lines.get(position.line as usize).copied().unwrap_or("");
+    let column = line
+        .get(..position.column as usize)
+        .map(|before| before.chars().count())
+        .unwrap_or(position.column as usize);
+
+    json!({
+        "line": position.line + 1,
+        "column": column + 1,
+    })
+}
@@ -182,12 +182,18 @@ impl<'a> Renderer<'a> {
         W: std::io::Write,
     {
         let line_col_region = self.to_line_col_region(expect_region, dbg_expr_region);
+        let (cyan, reset) = match self.render_target {
+            RenderTarget::ColorTerminal => ("\u{001b}[36m", "\u{001b}[0m"),
+            RenderTarget::Generic | RenderTarget::Json => ("", ""),
+        };
         write!(
             writer,
-            "\u{001b}[36m[{} {}:{}] \u{001b}[0m",
+            "{}[{} {}:{}] {}",
+            cyan,
             self.filename.display(),
             line_col_region.start.line + 1,
-            line_col_region.start.column + 1
+            line_col_region.start.column + 1,
+            reset
         )?;
 
         let expr = expressions[0];
@@ -240,4 +246,47 @@ impl<'a> Renderer<'a> {
 
         write!(writer, "{}", buf)
     }
+
+    /// A property `expect` whose inputs can't be generated
+    pub fn render_property_problem<W>(
+        &self,
+        writer: &mut W,
+        problem: &str,
+        expect_region: Region,
+    ) -> std::io::Result<()>
+    where
+        W: std::io::Write,
+    {
+        use crate::report::Report;
+        use ven_pretty::DocAllocator;
+
+        let line_col_region = self.line_info.convert_region(expect_region);
+
+        let doc = self.alloc.stack([
+            self.alloc.text("I could not check this property:"),
+            self.alloc.region(line_col_region),
+            self.alloc.text(problem),
+            self.alloc.text(
+                "I can generate numbers, strings, lists, records, tuples and tag unions that aren't recursive.",
+            ),
+        ]);
+
+        let report = Report {
+            title: "UNSUPPORTED PROPERTY".into(),
+            doc,
+            filename: self.filename.clone(),
+            severity: Severity::RuntimeError,
+        };
+
+        let mut buf = String::new();
+
+        report.render(
+            self.render_target,
+            &mut buf,
+            &self.alloc,
+            &crate::report::DEFAULT_PALETTE,
+        );
+
+        write!(writer, "{}", buf)
+    }
 }
@@ -3,6 +3,7 @@ use roc_module::ident::{Lowercase, ModuleName, TagName, Uppercase};
 use roc_module::symbol::{Interns, ModuleId, PQModuleName, PackageQualified, Symbol};
 use roc_problem::Severity;
 use roc_region::all::LineColumnRegion;
+use std::cell::RefCell;
 use std::fmt;
 use std::path::{Path, PathBuf};
 use ven_pretty::{BoxAllocator, DocAllocator, DocBuilder, Render, RenderAnnotated};
@@ -104,6 +105,9 @@ pub fn pretty_header_with_path(title: &str, path: &Path) -> String {
 pub enum RenderTarget {
     ColorTerminal,
     Generic,
+    /// For `--message-format=json`. Reports are rendered like [RenderTarget::Generic], except that
+    /// the problems which stop loading are returned as JSON diagnostics, with their file and region.
+    Json,
 }
 
 /// A textual report.
@@ -118,18 +122,18 @@ impl<'b> Report<'b> {
     pub fn render(
         self,
         target: RenderTarget,
-        buf: &'b mut String,
+        buf: &mut String,
         alloc: &'b RocDocAllocator<'b>,
         palette: &'b Palette,
     ) {
         match target {
-            RenderTarget::Generic => self.render_ci(buf, alloc),
+            RenderTarget::Generic | RenderTarget::Json => self.render_ci(buf, alloc),
             RenderTarget::ColorTerminal => self.render_color_terminal(buf, alloc, palette),
         }
     }
 
     /// Render to CI console output, where no colors are available.
-    pub fn render_ci(self, buf: &'b mut String, alloc: &'b RocDocAllocator<'b>) {
+    pub fn render_ci(self, buf: &mut String, alloc: &'b RocDocAllocator<'b>) {
         let err_msg = "<buffer is not a utf-8 encoded string>";
 
         self.pretty(alloc)
@@ -290,6 +294,8 @@ pub struct RocDocAllocator<'a> {
     pub src_lines: &'a [&'a str],
     pub home: ModuleId,
     pub interns: &'a Interns,
+    /// Every region that has been highlighted in a code snippet, in order
+    highlighted_regions: RefCell<Vec<LineColumnRegion>>,
 }
 
 pub type RocDocBuilder<'b> = DocBuilder<'b, RocDocAllocator<'b>, Annotation>;
@@ -326,9 +332,28 @@ impl<'a> RocDocAllocator<'a> {
             home,
             src_lines,
             interns,
+            highlighted_regions: RefCell::new(Vec::new()),
         }
     }
 
+    /// Returns the regions highlighted in code snippets since the last call, without duplicates.
+    /// This lets machine-readable output point at the same places a report's snippets do.
+    pub fn take_highlighted_regions(&self) -> Vec<LineColumnRegion> {
+        let mut regions = self.highlighted_regions.take();
+        let mut seen = Vec::with_capacity(regions.len());
+
+        regions.retain(|region| {
+            if seen.contains(region) {
+                false
+            } else {
+                seen.push(*region);
+                true
+            }
+        });
+
+        regions
+    }
+
     /// vertical concatenation. Adds a newline between elements
     pub fn vcat<A, I>(&'a self, docs: I) -> DocBuilder<'a, Self, A>
     where
@@ -533,6 +558,10 @@ impl<'a> RocDocAllocator<'a> {
         debug_assert!(region.contains(&sub_region1));
         debug_assert!(region.contains(&sub_region2));
 
+        self.highlighted_regions
+            .borrow_mut()
+            .extend([sub_region1, sub_region2]);
+
         // if true, the final line of the snippet will be some ^^^ that point to the region where
         // the problem is. Otherwise, the snippet will have a > on the lines that are in the region
         // where the problem is.
@@ -647,6 +676,8 @@ impl<'a> RocDocAllocator<'a> {
     ) -> DocBuilder<'a, Self, Annotation> {
         // debug_assert!(region.contains(&sub_region));
 
+        self.highlighted_regions.borrow_mut().push(sub_region);
+
         // If the outer region takes more than 1 full screen (~60 lines), only show the inner region
         if region.end().line.saturating_sub(region.start().line) > 60 {
             // If the inner region contains the outer region (or if they are the same),
@@ -0,0 +1,214 @@
+use std::io::{self, BufRead, Write};
+
+use crate::instance::Paused;
+use crate::{ImportDispatcher, Instance};
+
+const HELP: &str = "\
+Commands:
+  break <function>      b    Pause whenever a function is called (by name or index)
+  delete <function>     d    Remove a breakpoint
+  breakpoints                List breakpoints
+  step [count]          s    Execute the next instruction(s), stepping into calls
+  finish                f    Run until the current function returns
+  continue              c    Run until the next breakpoint, or the end of the program
+  where                 w    Show the current function and the next instruction
+  backtrace             bt   Show the call stack, with args, locals and stack values
+  locals                l    Show the arguments and locals of the current function
+  stack                      Show the current function's values on the stack
+  memory <addr> [len]   x    Dump linear memory, e.g. `x 0x1000 64`
+  help                  h    Show this help
+  quit                  q    Stop debugging
+";
+
+/// An interactive debugger for a function call started with `Instance::start_export`.
+/// Reads commands from `input` until the user quits, or the call finishes or fails.
+pub fn run_debugger<I: ImportDispatcher>(
+    instance: &mut Instance<'_, I>,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    writeln!(output, "Type `help` for a list of commands.")?;
+    print_location(instance, output)?;
+
+    let mut line = String::new();
+    loop {
+        write!(output, "(wasm) ")?;
+        output.flush()?;
+
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => continue,
+        };
+        let args: Vec<&str> = words.collect();
+
+        let result = match command {
+            "break" | "b" => {
+                match args.first().and_then(|f| instance.find_function(f)) {
+                    Some(fn_index) => {
+                        instance.add_breakpoint(fn_index);
+                        write!(output, "Breakpoint set on ")?;
+                        print_function(instance, fn_index, output)?;
+                        writeln!(output)?;
+                    }
+                    None => writeln!(output, "I couldn't find that function")?,
+                }
+                continue;
+            }
+            "delete" | "d" => {
+                match args.first().and_then(|f| instance.find_function(f)) {
+                    Some(fn_index) if instance.remove_breakpoint(fn_index) => {}
+                    _ => writeln!(output, "There's no breakpoint on that function")?,
+                }
+                continue;
+            }
+            "breakpoints" => {
+                for &fn_index in instance.breakpoints() {
+                    print_function(instance, fn_index, output)?;
+                    writeln!(output)?;
+                }
+                continue;
+            }
+            "step" | "s" => {
+                let count = match args.first().map(|n| n.parse::<usize>()) {
+                    Some(Ok(count)) => count,
+                    Some(Err(_)) => {
+                        writeln!(output, "Expected a number of steps")?;
+                        continue;
+                    }
+                    None => 1,
+                };
+                let mut result = Ok(Paused::Step);
+                for _ in 0..count {
+                    result = instance.step();
+                    if result != Ok(Paused::Step) {
+                        break;
+                    }
+                }
+                result
+            }
+            "finish" | "f" => instance.step_out(),
+            "continue" | "c" => instance.resume(),
+            "where" | "w" => {
+                print_location(instance, output)?;
+                continue;
+            }
+            "backtrace" | "bt" => {
+                write!(output, "{}", instance.stack_trace())?;
+                continue;
+            }
+            "locals" | "l" => {
+                for (i, value) in instance.locals().enumerate() {
+                    writeln!(output, "{:3}: {:?}", i, value)?;
+                }
+                continue;
+            }
+            "stack" => {
+                let values: Vec<_> = instance.value_stack().collect();
+                writeln!(output, "{:?}", values)?;
+                continue;
+            }
+            "memory" | "x" => {
+                match parse_memory_args(&args) {
+                    Some((addr, len)) => print_memory(&instance.memory, addr, len, output)?,
+                    None => writeln!(output, "Usage: memory <addr> [len]")?,
+                }
+                continue;
+            }
+            "help" | "h" => {
+                write!(output, "{}", HELP)?;
+                continue;
+            }
+            "quit" | "q" => return Ok(()),
+            _ => {
+                writeln!(
+                    output,
+                    "Unknown command `{}`. Type `help` for help.",
+                    command
+                )?;
+                continue;
+            }
+        };
+
+        match result {
+            Ok(Paused::Step) => print_location(instance, output)?,
+            Ok(Paused::Breakpoint(fn_index)) => {
+                write!(output, "Breakpoint: ")?;
+                print_function(instance, fn_index, output)?;
+                writeln!(output)?;
+                print_location(instance, output)?;
+            }
+            Ok(Paused::Finished(value)) => {
+                writeln!(output, "Finished, returning {:?}", value)?;
+                return Ok(());
+            }
+            Err(error) => {
+                writeln!(output, "{}", error.to_string().trim_end())?;
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// `func[12] 'name'`, matching the format of `wasm-objdump`
+fn print_function<I: ImportDispatcher>(
+    instance: &Instance<'_, I>,
+    fn_index: usize,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    write!(output, "func[{}]", fn_index)?;
+    if let Some(name) = instance.function_name(fn_index) {
+        write!(output, " '{}'", name)?;
+    }
+    Ok(())
+}
+
+fn print_location<I: ImportDispatcher>(
+    instance: &Instance<'_, I>,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    print_function(instance, instance.current_function(), output)?;
+    writeln!(
+        output,
+        " {:06x}: {:?}",
+        instance.current_file_offset(),
+        instance.next_instruction()
+    )
+}
+
+fn parse_memory_args(args: &[&str]) -> Option<(usize, usize)> {
+    let parse = |s: &str| match s.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    };
+
+    let addr = parse(args.first()?)?;
+    let len = match args.get(1) {
+        Some(len) => parse(len)?,
+        None => 64,
+    };
+
+    Some((addr, len))
+}
+
+fn print_memory(memory: &[u8], addr: usize, len: usize, output: &mut impl Write) -> io::Result<()> {
+    let end = memory.len().min(addr.saturating_add(len));
+    if addr >= end {
+        return writeln!(output, "That address is outside of memory");
+    }
+
+    for (i, row) in memory[addr..end].chunks(16).enumerate() {
+        write!(output, "{:08x}: ", addr + 16 * i)?;
+        for byte in row {
+            write!(output, "{:02x} ", byte)?;
+        }
+        writeln!(output)?;
+    }
+
+    Ok(())
+}
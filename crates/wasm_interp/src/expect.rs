@@ -0,0 +1,101 @@
+use roc_wasm_module::Value;
+
+use crate::wasi::{read_u32, slice, Errno};
+
+/// Import names for the functions the wasm backend calls for `dbg` and failed `expect`s.
+/// They are in the `env` module, like the other host functions.
+pub const ENV_MODULE_NAME: &str = "env";
+pub const ROC_DBG: &str = "roc_dbg";
+pub const ROC_EXPECT_FAILED: &str = "roc_expect_failed";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpectReportKind {
+    Dbg,
+    ExpectFailed,
+}
+
+/// A `dbg` or failed `expect` reported by a Roc app compiled with the wasm backend
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectReport {
+    pub kind: ExpectReportKind,
+    /// The raw `ModuleId` of the module containing the `dbg` or `expect`
+    pub module_id: u32,
+    /// For an `expect`, the byte offsets of its source region.
+    /// For a `dbg`, the bytes of its `Symbol`, as in the other backends.
+    pub region_start: u32,
+    pub region_end: u32,
+    /// The address in Wasm memory and type variable of each value looked up.
+    /// A `dbg` always has exactly one.
+    pub lookups: Vec<(u32, u32)>,
+}
+
+pub type ExpectHandler<'a> = Box<dyn FnMut(&ExpectReport, &[u8]) + 'a>;
+
+impl ExpectReport {
+    /// Decode the arguments of `roc_dbg` or `roc_expect_failed`.
+    /// Returns None for any other function. The app chooses the arguments, so they may have
+    /// the wrong type, or point outside of its memory. That's an error, not a panic for us.
+    pub fn from_call(
+        function_name: &str,
+        arguments: &[Value],
+        memory: &[u8],
+    ) -> Option<Result<Self, Errno>> {
+        let kind = match function_name {
+            ROC_DBG => ExpectReportKind::Dbg,
+            ROC_EXPECT_FAILED => ExpectReportKind::ExpectFailed,
+            _ => return None,
+        };
+
+        let arg = |i: usize| match arguments.get(i) {
+            Some(Value::I32(value)) => Ok(*value as u32),
+            _ => Err(Errno::Inval),
+        };
+
+        let decode = || {
+            let lookups = match kind {
+                ExpectReportKind::Dbg => vec![(arg(3)?, arg(4)?)],
+                ExpectReportKind::ExpectFailed => {
+                    let count = arg(4)? as usize;
+                    let len = count.checked_mul(8).ok_or(Errno::Fault)?;
+                    let entries = slice(memory, arg(3)? as usize, len)?;
+
+                    entries
+                        .chunks_exact(8)
+                        .map(|entry| Ok((read_u32(entry, 0)?, read_u32(entry, 4)?)))
+                        .collect::<Result<Vec<_>, Errno>>()?
+                }
+            };
+
+            Ok(ExpectReport {
+                kind,
+                module_id: arg(0)?,
+                region_start: arg(1)?,
+                region_end: arg(2)?,
+                lookups,
+            })
+        };
+
+        Some(decode())
+    }
+}
+
+/// Fallback for when no handler is installed. Without the compiler's type info,
+/// all we can do is point at the source and the raw values.
+pub(crate) fn print_report(report: &ExpectReport) {
+    let what = match report.kind {
+        ExpectReportKind::Dbg => "dbg",
+        ExpectReportKind::ExpectFailed => "expect failed",
+    };
+    eprintln!(
+        "[{}] module {} region {}..{}, values at {:?}",
+        what,
+        report.module_id,
+        report.region_start,
+        report.region_end,
+        report
+            .lookups
+            .iter()
+            .map(|(addr, _)| addr)
+            .collect::<Vec<_>>()
+    );
+}
@@ -10,7 +10,7 @@ use roc_wasm_module::{Value, ValueType};
 
 use crate::frame::Frame;
 use crate::value_store::ValueStore;
-use crate::{Error, ImportDispatcher};
+use crate::{CallError, Error, ImportDispatcher};
 
 #[derive(Debug)]
 pub enum Action {
@@ -18,6 +18,30 @@ pub enum Action {
     Break,
 }
 
+/// Limits on the resources a program can use, so that untrusted code can't run forever
+/// or use up all of the host's memory. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Limits {
+    /// Number of instructions that can still be executed. This goes down as the program runs,
+    /// and can be topped up between calls.
+    pub fuel: Option<u64>,
+    /// Maximum size of linear memory, in 64kB pages. Applies to `memory.grow`.
+    pub max_memory_pages: Option<u32>,
+    /// Maximum number of nested function calls, including the exported function itself
+    pub max_call_depth: Option<usize>,
+}
+
+/// Why the debugger stopped executing
+#[derive(Debug, PartialEq)]
+pub enum Paused {
+    /// Executed the requested instructions without hitting a breakpoint
+    Step,
+    /// Entered a function that has a breakpoint on it
+    Breakpoint(usize),
+    /// The exported function returned, with this value
+    Finished(Option<Value>),
+}
+
 #[derive(Debug, Clone, Copy)]
 enum BlockType {
     Loop(usize),         // Loop block, with start address to loop back to
@@ -66,6 +90,12 @@ pub struct Instance<'a, I: ImportDispatcher> {
     import_arguments: Vec<'a, Value>,
     /// temporary storage for output using the --debug option
     debug_string: Option<String>,
+    /// Function indices to pause at, when stepping through the program with the debugger
+    breakpoints: Vec<'a, usize>,
+    /// Whether the debugger has started a call that hasn't finished yet
+    is_running: bool,
+    /// Resource limits for running untrusted code
+    pub limits: Limits,
 }
 
 impl<'a, I: ImportDispatcher> Instance<'a, I> {
@@ -95,6 +125,9 @@ impl<'a, I: ImportDispatcher> Instance<'a, I> {
             import_dispatcher,
             import_arguments: Vec::new_in(arena),
             debug_string: Some(String::new()),
+            breakpoints: Vec::new_in(arena),
+            is_running: false,
+            limits: Limits::default(),
         }
     }
 
@@ -163,10 +196,38 @@ impl<'a, I: ImportDispatcher> Instance<'a, I> {
             import_dispatcher,
             import_arguments: Vec::new_in(arena),
             debug_string,
+            breakpoints: Vec::new_in(arena),
+            is_running: false,
+            limits: Limits::default(),
         })
     }
 
-    pub fn call_export<A>(&mut self, fn_name: &str, arg_values: A) -> Result<Option<Value>, String>
+    /// Whether the module imports `module_name.function_name` from the host
+    pub fn imports_function(&self, module_name: &str, function_name: &str) -> bool {
+        self.module
+            .import
+            .imports
+            .iter()
+            .any(|import| import.module == module_name && import.name == function_name)
+    }
+
+    pub fn call_export<A>(
+        &mut self,
+        fn_name: &str,
+        arg_values: A,
+    ) -> Result<Option<Value>, CallError>
+    where
+        A: IntoIterator<Item = Value>,
+    {
+        let (fn_index, n_args, ret_type) = self.load_export_args(fn_name, arg_values)?;
+        self.call_export_help_after_arg_load(self.module, fn_index, n_args, ret_type)
+    }
+
+    fn load_export_args<A>(
+        &mut self,
+        fn_name: &str,
+        arg_values: A,
+    ) -> Result<(usize, usize, Option<ValueType>), CallError>
     where
         A: IntoIterator<Item = Value>,
     {
@@ -177,15 +238,15 @@ impl<'a, I: ImportDispatcher> Instance<'a, I> {
         for (i, (value, expected_type)) in arg_values.into_iter().zip(param_type_iter).enumerate() {
             let actual_type = ValueType::from(value);
             if actual_type != expected_type {
-                return Err(format!(
+                return Err(CallError::Trap(format!(
                     "Type mismatch on argument {} of {}. Expected {:?} but got {:?}",
                     i, fn_name, expected_type, value
-                ));
+                )));
             }
             self.value_store.push(value);
         }
 
-        self.call_export_help_after_arg_load(self.module, fn_index, n_args, ret_type)
+        Ok((fn_index, n_args, ret_type))
     }
 
     pub fn call_export_from_cli(
@@ -193,7 +254,18 @@ impl<'a, I: ImportDispatcher> Instance<'a, I> {
         module: &WasmModule<'a>,
         fn_name: &str,
         arg_strings: &'a [&'a [u8]],
-    ) -> Result<Option<Value>, String> {
+    ) -> Result<Option<Value>, CallError> {
+        let (fn_index, n_args, ret_type) =
+            self.load_export_args_from_cli(module, fn_name, arg_strings)?;
+        self.call_export_help_after_arg_load(module, fn_index, n_args, ret_type)
+    }
+
+    fn load_export_args_from_cli(
+        &mut self,
+        module: &WasmModule<'a>,
+        fn_name: &str,
+        arg_strings: &'a [&'a [u8]],
+    ) -> Result<(usize, usize, Option<ValueType>), CallError> {
         // We have two different mechanisms for handling CLI arguments!
         // 1. Basic numbers:
         //      e.g. `roc_wasm_interp fibonacci 12`
@@ -218,22 +290,38 @@ impl<'a, I: ImportDispatcher> Instance<'a, I> {
             use ValueType::*;
             let value_str = String::from_utf8_lossy(value_bytes);
             let value = match value_type {
-                I32 => Value::I32(value_str.parse::<i32>().map_err(|e| e.to_string())?),
-                I64 => Value::I64(value_str.parse::<i64>().map_err(|e| e.to_string())?),
-                F32 => Value::F32(value_str.parse::<f32>().map_err(|e| e.to_string())?),
-                F64 => Value::F64(value_str.parse::<f64>().map_err(|e| e.to_string())?),
+                I32 => Value::I32(
+                    value_str
+                        .parse::<i32>()
+                        .map_err(|e| CallError::Trap(e.to_string()))?,
+                ),
+                I64 => Value::I64(
+                    value_str
+                        .parse::<i64>()
+                        .map_err(|e| CallError::Trap(e.to_string()))?,
+                ),
+                F32 => Value::F32(
+                    value_str
+                        .parse::<f32>()
+                        .map_err(|e| CallError::Trap(e.to_string()))?,
+                ),
+                F64 => Value::F64(
+                    value_str
+                        .parse::<f64>()
+                        .map_err(|e| CallError::Trap(e.to_string()))?,
+                ),
             };
             self.value_store.push(value);
         }
 
-        self.call_export_help_after_arg_load(module, fn_index, n_args, ret_type)
+        Ok((fn_index, n_args, ret_type))
     }
 
     fn call_export_help_before_arg_load<'m>(
         &mut self,
         module: &'m WasmModule<'a>,
         fn_name: &str,
-    ) -> Result<(usize, SignatureParamsIter<'m>, Option<ValueType>), String> {
+    ) -> Result<(usize, SignatureParamsIter<'m>, Option<ValueType>), CallError> {
         let fn_index = {
             let mut export_iter = module.export.exports.iter();
             export_iter
@@ -261,10 +349,10 @@ impl<'a, I: ImportDispatcher> Instance<'a, I> {
                     )
                 })
                 .ok_or_else(|| {
-                    format!(
+                    CallError::Trap(format!(
                         "I couldn't find a function '{}' in this WebAssembly module",
                         fn_name
-                    )
+                    ))
                 })? as usize
         };
 
@@ -299,7 +387,31 @@ impl<'a, I: ImportDispatcher> Instance<'a, I> {
         fn_index: usize,
         n_args: usize,
         return_type: Option<ValueType>,
-    ) -> Result<Option<Value>, String> {
+    ) -> Result<Option<Value>, CallError> {
+        self.enter_export(module, fn_index, n_args, return_type);
+
+        loop {
+            match self.execute_next_instruction(module) {
+                Ok(Action::Continue) => {}
+                Ok(Action::Break) => {
+                    break;
+                }
+                Err(e) => {
+                    return Err(self.abort_call(module, e));
+                }
+            };
+        }
+
+        Ok(self.pop_return_value())
+    }
+
+    fn enter_export(
+        &mut self,
+        module: &WasmModule<'a>,
+        fn_index: usize,
+        n_args: usize,
+        return_type: Option<ValueType>,
+    ) {
         self.previous_frames.clear();
         self.blocks.clear();
         self.blocks.push(Block {
@@ -320,29 +432,211 @@ impl<'a, I: ImportDispatcher> Instance<'a, I> {
             ty: BlockType::FunctionBody(fn_index),
             vstack: self.value_store.depth(),
         });
+    }
 
-        loop {
-            match self.execute_next_instruction(module) {
-                Ok(Action::Continue) => {}
-                Ok(Action::Break) => {
-                    break;
-                }
-                Err(e) => {
-                    let file_offset = self.program_counter + module.code.section_offset as usize;
-                    let mut message = e.to_string_at(file_offset);
-                    self.debug_stack_trace(&mut message).unwrap();
-                    return Err(message);
+    fn pop_return_value(&mut self) -> Option<Value> {
+        if !self.value_store.is_empty() {
+            Some(self.value_store.pop())
+        } else {
+            None
+        }
+    }
+
+    /// Describe an error, then clean up after the failed call so that the instance can be reused
+    fn abort_call(&mut self, module: &WasmModule<'a>, e: Error) -> CallError {
+        let error = match e {
+            Error::OutOfFuel => CallError::OutOfFuel,
+            Error::MemoryLimit(pages) => CallError::MemoryLimit { pages },
+            Error::CallDepthLimit(depth) => CallError::CallDepthLimit { depth },
+            Error::Type(..) | Error::StackEmpty | Error::UnreachableOp => {
+                let file_offset = self.program_counter + module.code.section_offset as usize;
+                let mut message = e.to_string_at(file_offset);
+                self.debug_stack_trace(&mut message).unwrap();
+                CallError::Trap(message)
+            }
+        };
+
+        self.value_store.truncate(0);
+        self.previous_frames.clear();
+        self.blocks.clear();
+
+        error
+    }
+
+    /// Set up a call to an exported function, but don't execute anything yet.
+    /// Use `step`, `step_out` and `resume` to run it.
+    pub fn start_export<A>(&mut self, fn_name: &str, arg_values: A) -> Result<(), CallError>
+    where
+        A: IntoIterator<Item = Value>,
+    {
+        let (fn_index, n_args, ret_type) = self.load_export_args(fn_name, arg_values)?;
+        self.enter_export(self.module, fn_index, n_args, ret_type);
+        self.is_running = true;
+        Ok(())
+    }
+
+    /// Like `start_export`, but with arguments from the command line, as in `call_export_from_cli`
+    pub fn start_export_from_cli(
+        &mut self,
+        fn_name: &str,
+        arg_strings: &'a [&'a [u8]],
+    ) -> Result<(), CallError> {
+        let (fn_index, n_args, ret_type) =
+            self.load_export_args_from_cli(self.module, fn_name, arg_strings)?;
+        self.enter_export(self.module, fn_index, n_args, ret_type);
+        self.is_running = true;
+        Ok(())
+    }
+
+    /// Execute a single instruction of a call started with `start_export`.
+    /// A call instruction steps into the called function.
+    pub fn step(&mut self) -> Result<Paused, CallError> {
+        if !self.is_running {
+            return Err(CallError::Trap(
+                "There's no function call in progress".to_string(),
+            ));
+        }
+
+        let frame_depth = self.previous_frames.len();
+
+        match self.execute_next_instruction(self.module) {
+            Ok(Action::Continue) => {
+                let entered_function = self.previous_frames.len() > frame_depth;
+                let fn_index = self.current_frame.fn_index;
+                if entered_function && self.breakpoints.contains(&fn_index) {
+                    Ok(Paused::Breakpoint(fn_index))
+                } else {
+                    Ok(Paused::Step)
                 }
-            };
+            }
+            Ok(Action::Break) => {
+                self.is_running = false;
+                Ok(Paused::Finished(self.pop_return_value()))
+            }
+            Err(e) => {
+                self.is_running = false;
+                Err(self.abort_call(self.module, e))
+            }
         }
+    }
 
-        let return_value = if !self.value_store.is_empty() {
-            Some(self.value_store.pop())
+    /// Run until the current function returns to its caller, or a breakpoint is hit
+    pub fn step_out(&mut self) -> Result<Paused, CallError> {
+        let frame_depth = self.previous_frames.len();
+        loop {
+            match self.step()? {
+                Paused::Step if self.previous_frames.len() >= frame_depth => {}
+                Paused::Step => return Ok(Paused::Step),
+                paused => return Ok(paused),
+            }
+        }
+    }
+
+    /// Run until a breakpoint is hit, or the exported function returns
+    pub fn resume(&mut self) -> Result<Paused, CallError> {
+        loop {
+            match self.step()? {
+                Paused::Step => {}
+                paused => return Ok(paused),
+            }
+        }
+    }
+
+    /// Pause whenever the given function is called
+    pub fn add_breakpoint(&mut self, fn_index: usize) {
+        if !self.breakpoints.contains(&fn_index) {
+            self.breakpoints.push(fn_index);
+        }
+    }
+
+    /// Returns false if there was no breakpoint on this function
+    pub fn remove_breakpoint(&mut self, fn_index: usize) -> bool {
+        let len = self.breakpoints.len();
+        self.breakpoints.retain(|i| *i != fn_index);
+        self.breakpoints.len() < len
+    }
+
+    pub fn breakpoints(&self) -> &[usize] {
+        &self.breakpoints
+    }
+
+    /// Find a function by its name in the NameSection or the exports, or by its index.
+    /// Only functions defined in the module can be found, not imports.
+    pub fn find_function(&self, name_or_index: &str) -> Option<usize> {
+        let fn_index = name_or_index.parse::<usize>().ok().or_else(|| {
+            let names = &self.module.names.function_names;
+            let exports = &self.module.export.exports;
+            names
+                .iter()
+                .find(|(_, name)| *name == name_or_index)
+                .map(|(index, _)| *index as usize)
+                .or_else(|| {
+                    exports
+                        .iter()
+                        .find(|ex| ex.ty == ExportType::Func && ex.name == name_or_index)
+                        .map(|ex| ex.index as usize)
+                })
+        })?;
+
+        let fn_count = self.import_count + self.module.code.function_count as usize;
+        if (self.import_count..fn_count).contains(&fn_index) {
+            Some(fn_index)
         } else {
             None
-        };
+        }
+    }
 
-        Ok(return_value)
+    /// The function's name from the NameSection, if the module has one
+    pub fn function_name(&self, fn_index: usize) -> Option<&'a str> {
+        let names = &self.module.names.function_names;
+        names
+            .iter()
+            .find(|(index, _)| *index as usize == fn_index)
+            .map(|(_, name)| *name)
+    }
+
+    /// Index of the function currently executing
+    pub fn current_function(&self) -> usize {
+        self.current_frame.fn_index
+    }
+
+    /// Indices of the functions on the call stack, starting from the exported function
+    pub fn call_stack(&self) -> impl Iterator<Item = usize> + '_ {
+        let frames = self.previous_frames.iter().chain(once(&self.current_frame));
+        frames.map(|frame| frame.fn_index)
+    }
+
+    /// Offset of the next instruction in the module's file, as shown by `wasm-objdump -d`
+    pub fn current_file_offset(&self) -> usize {
+        self.program_counter + self.module.code.section_offset as usize
+    }
+
+    /// The next instruction to be executed
+    pub fn next_instruction(&self) -> OpCode {
+        OpCode::from(self.module.code.bytes[self.program_counter])
+    }
+
+    /// Arguments and local variables of the current function
+    pub fn locals(&self) -> impl Iterator<Item = Value> + '_ {
+        let Frame {
+            locals_start,
+            locals_count,
+            ..
+        } = self.current_frame;
+        (locals_start..locals_start + locals_count).map(|i| *self.value_store.get(i).unwrap())
+    }
+
+    /// The current function's values on the WebAssembly stack machine, from the bottom up
+    pub fn value_stack(&self) -> impl Iterator<Item = Value> + '_ {
+        let base = self.current_frame.locals_start + self.current_frame.locals_count;
+        self.value_store.iter().skip(base).copied()
+    }
+
+    /// A stack trace with the arguments, locals and stack values of every function call
+    pub fn stack_trace(&self) -> String {
+        let mut buffer = String::new();
+        self.debug_stack_trace(&mut buffer).unwrap();
+        buffer
     }
 
     fn fetch_immediate_u32(&mut self, module: &WasmModule<'a>) -> u32 {
@@ -538,6 +832,13 @@ impl<'a, I: ImportDispatcher> Instance<'a, I> {
                 write!(debug_string, " {}.{}", import.module, import.name).unwrap();
             }
         } else {
+            if let Some(max_depth) = self.limits.max_call_depth {
+                // One frame for each caller, plus the current one, plus the new one
+                if self.previous_frames.len() + 2 > max_depth {
+                    return Err(Error::CallDepthLimit(max_depth));
+                }
+            }
+
             let return_addr = self.program_counter;
             // set PC to start of function bytes
             let internal_fn_index = fn_index - self.import_count;
@@ -600,6 +901,13 @@ impl<'a, I: ImportDispatcher> Instance<'a, I> {
     ) -> Result<Action, Error> {
         use OpCode::*;
 
+        if let Some(fuel) = self.limits.fuel.as_mut() {
+            if *fuel == 0 {
+                return Err(Error::OutOfFuel);
+            }
+            *fuel -= 1;
+        }
+
         let file_offset = self.program_counter as u32 + module.code.section_offset;
         let op_code = OpCode::from(module.code.bytes[self.program_counter]);
         self.program_counter += 1;
@@ -950,14 +1258,29 @@ impl<'a, I: ImportDispatcher> Instance<'a, I> {
                 let old_bytes = self.memory.len() as u32;
                 let old_pages = old_bytes / MemorySection::PAGE_SIZE as u32;
                 let grow_pages = self.value_store.pop_u32()?;
-                let grow_bytes = grow_pages * MemorySection::PAGE_SIZE;
-                let new_bytes = old_bytes + grow_bytes;
 
-                let success = match module.memory.max_bytes().unwrap() {
-                    Some(max_bytes) => new_bytes <= max_bytes,
-                    None => true,
+                // The page count comes from the program, so none of this arithmetic may overflow
+                let new_pages = old_pages.checked_add(grow_pages);
+
+                if let Some(max_pages) = self.limits.max_memory_pages {
+                    if new_pages.map_or(true, |pages| pages > max_pages) {
+                        return Err(Error::MemoryLimit(max_pages));
+                    }
+                }
+
+                // A 32-bit memory can't grow to 4GiB or beyond
+                let new_bytes =
+                    new_pages.and_then(|pages| pages.checked_mul(MemorySection::PAGE_SIZE));
+
+                let grow_bytes = match (new_bytes, module.memory.max_bytes().unwrap()) {
+                    (Some(new_bytes), Some(max_bytes)) if new_bytes <= max_bytes => {
+                        Some(new_bytes - old_bytes)
+                    }
+                    (Some(new_bytes), None) => Some(new_bytes - old_bytes),
+                    _ => None,
                 };
-                if success {
+
+                if let Some(grow_bytes) = grow_bytes {
                     self.memory
                         .extend(iter::repeat(0).take(grow_bytes as usize));
                     self.value_store.push(Value::I32(old_pages as i32));
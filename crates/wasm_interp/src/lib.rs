@@ -1,12 +1,18 @@
+mod debugger;
+pub mod expect;
 mod frame;
 mod instance;
 mod tests;
 mod value_store;
 pub mod wasi;
+mod wasi_fs;
 
 // Main external interface
-pub use instance::Instance;
+pub use debugger::run_debugger;
+pub use expect::{ExpectHandler, ExpectReport, ExpectReportKind};
+pub use instance::{Instance, Limits, Paused};
 pub use wasi::{WasiDispatcher, WasiFile};
+pub use wasi_fs::{MemoryDir, MemoryNode};
 
 pub use roc_wasm_module::Value;
 use roc_wasm_module::ValueType;
@@ -24,20 +30,22 @@ pub trait ImportDispatcher {
 
 impl Default for DefaultImportDispatcher<'_> {
     fn default() -> Self {
-        DefaultImportDispatcher {
-            wasi: WasiDispatcher::new(&[]),
-        }
+        DefaultImportDispatcher::new(&[])
     }
 }
 
 pub struct DefaultImportDispatcher<'a> {
     pub wasi: WasiDispatcher<'a>,
+    /// Called for each `dbg` and failed `expect` in the app, with the app's memory.
+    /// If not set, we print a basic report to stderr.
+    pub expect_handler: Option<ExpectHandler<'a>>,
 }
 
 impl<'a> DefaultImportDispatcher<'a> {
     pub fn new(args: &'a [&'a [u8]]) -> Self {
         DefaultImportDispatcher {
             wasi: WasiDispatcher::new(args),
+            expect_handler: None,
         }
     }
 }
@@ -52,6 +60,19 @@ impl<'a> ImportDispatcher for DefaultImportDispatcher<'a> {
     ) -> Option<Value> {
         if module_name == wasi::MODULE_NAME {
             self.wasi.dispatch(function_name, arguments, memory)
+        } else if let Some(report) = (module_name == expect::ENV_MODULE_NAME)
+            .then(|| ExpectReport::from_call(function_name, arguments, memory))
+            .flatten()
+        {
+            match (report, &mut self.expect_handler) {
+                (Ok(report), Some(handler)) => handler(&report, memory),
+                (Ok(report), None) => expect::print_report(&report),
+                (Err(errno), _) => eprintln!(
+                    "The app called {}.{} with invalid arguments ({:?}), so I skipped it.",
+                    module_name, function_name, errno
+                ),
+            }
+            None
         } else {
             panic!(
                 "DefaultImportDispatcher does not implement {}.{}",
@@ -68,6 +89,9 @@ pub(crate) enum Error {
     Type(ValueType, ValueType),
     StackEmpty,
     UnreachableOp,
+    OutOfFuel,
+    MemoryLimit(u32),
+    CallDepthLimit(usize),
 }
 
 impl Error {
@@ -91,10 +115,71 @@ impl Error {
                     file_offset
                 )
             }
+            Error::OutOfFuel => {
+                format!(
+                    "ERROR: The program ran out of fuel at file offset {:#x}. It may be stuck in an infinite loop.\n",
+                    file_offset
+                )
+            }
+            Error::MemoryLimit(max_pages) => {
+                format!(
+                    "ERROR: The program tried to grow its memory beyond the limit of {} pages at file offset {:#x}.\n",
+                    max_pages, file_offset
+                )
+            }
+            Error::CallDepthLimit(max_depth) => {
+                format!(
+                    "ERROR: The program exceeded the maximum call depth of {} at file offset {:#x}.\n",
+                    max_depth, file_offset
+                )
+            }
         }
     }
 }
 
+/// Why a call into the WebAssembly module failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallError {
+    /// The program used up all of its fuel (see [Limits::fuel]). It may be stuck in an infinite loop.
+    OutOfFuel,
+    /// The program tried to grow its memory beyond [Limits::max_memory_pages]
+    MemoryLimit { pages: u32 },
+    /// Function calls were nested deeper than [Limits::max_call_depth]
+    CallDepthLimit { depth: usize },
+    /// Anything else that stopped the call, like an `unreachable` instruction or a function
+    /// that doesn't exist. The message includes a WebAssembly stack trace, if there was one.
+    Trap(String),
+}
+
+impl std::fmt::Display for CallError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CallError::OutOfFuel => write!(
+                f,
+                "ERROR: The program ran out of fuel. It may be stuck in an infinite loop."
+            ),
+            CallError::MemoryLimit { pages } => write!(
+                f,
+                "ERROR: The program tried to grow its memory beyond the limit of {} pages.",
+                pages
+            ),
+            CallError::CallDepthLimit { depth } => write!(
+                f,
+                "ERROR: The program exceeded the maximum call depth of {}.",
+                depth
+            ),
+            CallError::Trap(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+/// So that code which reports errors as strings can still use `?`
+impl From<CallError> for String {
+    fn from(error: CallError) -> Self {
+        error.to_string()
+    }
+}
+
 impl From<(ValueType, ValueType)> for Error {
     fn from((expected, actual): (ValueType, ValueType)) -> Self {
         Error::Type(expected, actual)
@@ -1,17 +1,24 @@
 use bumpalo::{collections::Vec, Bump};
 use clap::ArgAction;
 use clap::{Arg, Command};
+use std::env;
 use std::fs;
 use std::io;
 use std::iter::once;
 use std::process;
 
-use roc_wasm_interp::{DefaultImportDispatcher, Instance};
+use roc_wasm_interp::{run_debugger, DefaultImportDispatcher, Instance, Limits};
 use roc_wasm_module::WasmModule;
 
 pub const FLAG_FUNCTION: &str = "function";
 pub const FLAG_DEBUG: &str = "debug";
+pub const FLAG_DEBUGGER: &str = "debugger";
 pub const FLAG_HEX: &str = "hex";
+pub const FLAG_DIR: &str = "dir";
+pub const FLAG_ENV: &str = "env";
+pub const FLAG_FUEL: &str = "fuel";
+pub const FLAG_MAX_MEMORY_PAGES: &str = "max-memory-pages";
+pub const FLAG_MAX_CALL_DEPTH: &str = "max-call-depth";
 pub const WASM_FILE: &str = "WASM_FILE";
 pub const ARGS_FOR_APP: &str = "ARGS_FOR_APP";
 
@@ -32,12 +39,50 @@ fn main() -> io::Result<()> {
         .action(ArgAction::SetTrue)
         .required(false);
 
+    let flag_debugger = Arg::new(FLAG_DEBUGGER)
+        .long(FLAG_DEBUGGER)
+        .help("Step through the program interactively, with breakpoints and inspection of the stack, locals and memory.")
+        .action(ArgAction::SetTrue)
+        .required(false);
+
     let flag_hex = Arg::new(FLAG_HEX)
         .long(FLAG_HEX)
         .help("If the called function returns a value, print it in hexadecimal format.")
         .action(ArgAction::SetTrue)
         .required(false);
 
+    let flag_dir = Arg::new(FLAG_DIR)
+        .long(FLAG_DIR)
+        .help("Give the WebAssembly app access to a directory on the host.\nIt sees it at the same path, e.g. `--dir .` or `--dir data`")
+        .action(ArgAction::Append)
+        .takes_value(true)
+        .required(false);
+
+    let flag_env = Arg::new(FLAG_ENV)
+        .long(FLAG_ENV)
+        .help("Pass this environment variable from the host to the WebAssembly app, e.g. `--env HOME`\n(No variables are passed unless asked for.)")
+        .action(ArgAction::Append)
+        .takes_value(true)
+        .required(false);
+
+    let flag_fuel = Arg::new(FLAG_FUEL)
+        .long(FLAG_FUEL)
+        .help("Stop with an error after executing this many instructions")
+        .value_parser(clap::value_parser!(u64))
+        .required(false);
+
+    let flag_max_memory_pages = Arg::new(FLAG_MAX_MEMORY_PAGES)
+        .long(FLAG_MAX_MEMORY_PAGES)
+        .help("Stop with an error if the app tries to grow its memory beyond this many 64kB pages")
+        .value_parser(clap::value_parser!(u32))
+        .required(false);
+
+    let flag_max_call_depth = Arg::new(FLAG_MAX_CALL_DEPTH)
+        .long(FLAG_MAX_CALL_DEPTH)
+        .help("Stop with an error if function calls are nested deeper than this")
+        .value_parser(clap::value_parser!(usize))
+        .required(false);
+
     let wasm_file_to_run = Arg::new(WASM_FILE)
         .help("The .wasm file to run")
         .required(true);
@@ -51,7 +96,13 @@ fn main() -> io::Result<()> {
         .about("Run the given .wasm file")
         .arg(flag_function)
         .arg(flag_debug)
+        .arg(flag_debugger)
         .arg(flag_hex)
+        .arg(flag_dir)
+        .arg(flag_env)
+        .arg(flag_fuel)
+        .arg(flag_max_memory_pages)
+        .arg(flag_max_call_depth)
         .arg(wasm_file_to_run)
         .trailing_var_arg(true)
         .arg(args_for_app);
@@ -61,7 +112,15 @@ fn main() -> io::Result<()> {
     let matches = app.get_matches();
     let start_fn_name = matches.get_one::<String>(FLAG_FUNCTION).unwrap();
     let is_debug_mode = matches.get_flag(FLAG_DEBUG);
+    let is_debugger = matches.get_flag(FLAG_DEBUGGER);
     let is_hex_format = matches.get_flag(FLAG_HEX);
+    let limits = Limits {
+        fuel: matches.get_one::<u64>(FLAG_FUEL).copied(),
+        max_memory_pages: matches.get_one::<u32>(FLAG_MAX_MEMORY_PAGES).copied(),
+        max_call_depth: matches.get_one::<usize>(FLAG_MAX_CALL_DEPTH).copied(),
+    };
+    let preopen_dirs = matches.get_many::<String>(FLAG_DIR).unwrap_or_default();
+    let env_var_names = matches.get_many::<String>(FLAG_ENV).unwrap_or_default();
     let start_arg_strings = matches.get_many::<String>(ARGS_FOR_APP).unwrap_or_default();
     let wasm_path = matches.get_one::<String>(WASM_FILE).unwrap();
     // WASI expects the .wasm file to be argv[0]
@@ -89,15 +148,33 @@ fn main() -> io::Result<()> {
 
     // Create an execution instance
 
-    let dispatcher = DefaultImportDispatcher::new(&wasi_argv);
+    let mut dispatcher = DefaultImportDispatcher::new(&wasi_argv);
+    dispatcher.wasi.env = env_var_names
+        .filter_map(|name| {
+            let value = env::var(name).ok()?;
+            Some(format!("{}={}", name, value))
+        })
+        .collect();
+    for dir in preopen_dirs {
+        dispatcher.wasi.preopen_host_dir(dir, dir);
+    }
     let mut inst =
         Instance::for_module(&arena, &module, dispatcher, is_debug_mode).unwrap_or_else(|e| {
             eprintln!("{}", e);
             process::exit(2);
         });
+    inst.limits = limits;
 
     // Run
 
+    if is_debugger {
+        if let Err(e) = inst.start_export_from_cli(start_fn_name, &wasi_argv) {
+            eprintln!("{}", e);
+            process::exit(3);
+        }
+        return run_debugger(&mut inst, &mut io::stdin().lock(), &mut io::stdout());
+    }
+
     let result = inst.call_export_from_cli(&module, start_fn_name, &wasi_argv);
 
     // Print out return value, if any
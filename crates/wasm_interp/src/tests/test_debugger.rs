@@ -0,0 +1,142 @@
+use super::create_exported_function_no_locals;
+use crate::{run_debugger, DefaultImportDispatcher, Instance, Paused};
+use bumpalo::{collections::Vec, Bump};
+use roc_wasm_module::{
+    opcodes::OpCode, sections::MemorySection, SerialBuffer, Signature, Value, ValueType, WasmModule,
+};
+
+/// double(x) = x + x
+/// main() = double(20) + 2
+fn create_module(arena: &Bump) -> WasmModule<'_> {
+    let mut module = WasmModule::new(arena);
+
+    create_exported_function_no_locals(
+        &mut module,
+        "double",
+        Signature {
+            param_types: bumpalo::vec![in arena; ValueType::I32],
+            ret_type: Some(ValueType::I32),
+        },
+        |buf| {
+            buf.push(OpCode::GETLOCAL as u8);
+            buf.encode_u32(0);
+            buf.push(OpCode::GETLOCAL as u8);
+            buf.encode_u32(0);
+            buf.push(OpCode::I32ADD as u8);
+            buf.push(OpCode::END as u8);
+        },
+    );
+
+    create_exported_function_no_locals(
+        &mut module,
+        "main",
+        Signature {
+            param_types: Vec::new_in(arena),
+            ret_type: Some(ValueType::I32),
+        },
+        |buf| {
+            buf.push(OpCode::I32CONST as u8);
+            buf.encode_i32(20);
+            buf.push(OpCode::CALL as u8);
+            buf.encode_u32(0);
+            buf.push(OpCode::I32CONST as u8);
+            buf.encode_i32(2);
+            buf.push(OpCode::I32ADD as u8);
+            buf.push(OpCode::END as u8);
+        },
+    );
+
+    module.names.append_function(0, "double_the_number");
+    module.names.append_function(1, "main");
+
+    module
+}
+
+#[test]
+fn test_step() {
+    let arena = Bump::new();
+    let module = create_module(&arena);
+    let mut inst =
+        Instance::for_module(&arena, &module, DefaultImportDispatcher::default(), false).unwrap();
+
+    inst.start_export("main", []).unwrap();
+    assert_eq!(inst.current_function(), 1);
+    assert_eq!(inst.next_instruction(), OpCode::I32CONST);
+
+    assert_eq!(inst.step(), Ok(Paused::Step));
+    assert_eq!(
+        inst.value_stack().collect::<std::vec::Vec<_>>(),
+        [Value::I32(20)]
+    );
+    assert_eq!(inst.next_instruction(), OpCode::CALL);
+
+    // Step into the call
+    assert_eq!(inst.step(), Ok(Paused::Step));
+    assert_eq!(inst.current_function(), 0);
+    assert_eq!(inst.call_stack().collect::<std::vec::Vec<_>>(), [1, 0]);
+    assert_eq!(
+        inst.locals().collect::<std::vec::Vec<_>>(),
+        [Value::I32(20)]
+    );
+    assert_eq!(inst.value_stack().count(), 0);
+
+    assert_eq!(inst.step_out(), Ok(Paused::Step));
+    assert_eq!(inst.current_function(), 1);
+    assert_eq!(
+        inst.value_stack().collect::<std::vec::Vec<_>>(),
+        [Value::I32(40)]
+    );
+
+    assert_eq!(inst.resume(), Ok(Paused::Finished(Some(Value::I32(42)))));
+    assert!(inst.step().is_err());
+}
+
+#[test]
+fn test_breakpoints() {
+    let arena = Bump::new();
+    let module = create_module(&arena);
+    let mut inst =
+        Instance::for_module(&arena, &module, DefaultImportDispatcher::default(), false).unwrap();
+
+    // From the NameSection
+    assert_eq!(inst.find_function("double_the_number"), Some(0));
+    // From the exports
+    assert_eq!(inst.find_function("double"), Some(0));
+    assert_eq!(inst.find_function("1"), Some(1));
+    assert_eq!(inst.find_function("2"), None);
+    assert_eq!(inst.find_function("triple"), None);
+
+    inst.add_breakpoint(0);
+    inst.start_export("main", []).unwrap();
+
+    assert_eq!(inst.resume(), Ok(Paused::Breakpoint(0)));
+    assert_eq!(inst.current_function(), 0);
+    assert_eq!(inst.next_instruction(), OpCode::GETLOCAL);
+
+    assert!(inst.remove_breakpoint(0));
+    assert!(!inst.remove_breakpoint(0));
+    assert_eq!(inst.resume(), Ok(Paused::Finished(Some(Value::I32(42)))));
+}
+
+#[test]
+fn test_debugger_repl() {
+    let arena = Bump::new();
+    let mut module = create_module(&arena);
+    module.memory = MemorySection::new(&arena, MemorySection::PAGE_SIZE);
+    let mut inst =
+        Instance::for_module(&arena, &module, DefaultImportDispatcher::default(), false).unwrap();
+    inst.memory[0x10..0x14].copy_from_slice(&[1, 2, 3, 4]);
+
+    inst.start_export("main", []).unwrap();
+
+    let commands = "break double_the_number\ncontinue\nlocals\nx 0x10 4\ncontinue\n";
+    let mut output = std::vec::Vec::new();
+    run_debugger(&mut inst, &mut commands.as_bytes(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    assert!(output.contains("Breakpoint set on func[0] 'double_the_number'"));
+    assert!(output.contains("Breakpoint: func[0] 'double_the_number'"));
+    assert!(output.contains("  0: I32(20)"));
+    assert!(output.contains("00000010: 01 02 03 04"));
+    assert!(output.contains("Finished, returning Some(I32(42))"));
+}
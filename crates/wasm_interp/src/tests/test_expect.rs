@@ -0,0 +1,84 @@
+use crate::wasi::Errno;
+use crate::{DefaultImportDispatcher, ExpectReport, ExpectReportKind, ImportDispatcher};
+use roc_wasm_module::Value;
+use std::cell::RefCell;
+
+const LOOKUPS: usize = 0x100;
+
+fn dispatch_reports(
+    function_name: &str,
+    arguments: &[Value],
+    memory: &mut [u8],
+) -> Vec<ExpectReport> {
+    let reports = RefCell::new(vec![]);
+    {
+        let mut dispatcher = DefaultImportDispatcher::default();
+        dispatcher.expect_handler = Some(Box::new(|report: &ExpectReport, _: &[u8]| {
+            reports.borrow_mut().push(report.clone())
+        }));
+        let result = dispatcher.dispatch("env", function_name, arguments, memory);
+        assert_eq!(result, None);
+    }
+    reports.into_inner()
+}
+
+#[test]
+fn dbg_report() {
+    let mut memory = vec![0; 0x200];
+    let args = [7, 10, 20, 0x180, 42].map(Value::I32);
+    let reports = dispatch_reports("roc_dbg", &args, &mut memory);
+    assert_eq!(
+        reports,
+        vec![ExpectReport {
+            kind: ExpectReportKind::Dbg,
+            module_id: 7,
+            region_start: 10,
+            region_end: 20,
+            lookups: vec![(0x180, 42)],
+        }]
+    );
+}
+
+#[test]
+fn expect_failed_report() {
+    let mut memory = vec![0; 0x200];
+    for (i, word) in [0x180u32, 3, 0x190, 5].iter().enumerate() {
+        memory[LOOKUPS + 4 * i..][..4].copy_from_slice(&word.to_le_bytes());
+    }
+    let args = [1, 30, 45, LOOKUPS as i32, 2].map(Value::I32);
+    let reports = dispatch_reports("roc_expect_failed", &args, &mut memory);
+    assert_eq!(
+        reports,
+        vec![ExpectReport {
+            kind: ExpectReportKind::ExpectFailed,
+            module_id: 1,
+            region_start: 30,
+            region_end: 45,
+            lookups: vec![(0x180, 3), (0x190, 5)],
+        }]
+    );
+}
+
+#[test]
+fn expect_failed_lookups_out_of_bounds() {
+    let mut memory = vec![0; 0x200];
+    let args = [1, 30, 45, 0x1fc, 2].map(Value::I32);
+    let reports = dispatch_reports("roc_expect_failed", &args, &mut memory);
+    assert_eq!(reports, vec![]);
+
+    assert_eq!(
+        ExpectReport::from_call("roc_expect_failed", &args, &memory),
+        Some(Err(Errno::Fault))
+    );
+}
+
+#[test]
+fn dbg_with_missing_arguments() {
+    let memory = vec![0; 0x200];
+    let args = [7, 10, 20].map(Value::I32);
+
+    assert_eq!(
+        ExpectReport::from_call("roc_dbg", &args, &memory),
+        Some(Err(Errno::Inval))
+    );
+}
@@ -0,0 +1,192 @@
+use super::create_exported_function_no_locals;
+use crate::{CallError, DefaultImportDispatcher, Instance, Limits};
+use bumpalo::{collections::Vec, Bump};
+use roc_wasm_module::{
+    opcodes::OpCode, sections::MemorySection, SerialBuffer, Signature, Value, ValueType, WasmModule,
+};
+
+const VOID_BLOCK: u8 = 0x40;
+
+fn void_signature(arena: &Bump) -> Signature<'_> {
+    Signature {
+        param_types: Vec::new_in(arena),
+        ret_type: None,
+    }
+}
+
+/// forever() = loop forever
+/// answer() = 42
+fn create_infinite_loop_module(arena: &Bump) -> WasmModule<'_> {
+    let mut module = WasmModule::new(arena);
+
+    create_exported_function_no_locals(&mut module, "forever", void_signature(arena), |buf| {
+        buf.push(OpCode::LOOP as u8);
+        buf.push(VOID_BLOCK);
+        buf.push(OpCode::BR as u8);
+        buf.encode_u32(0);
+        buf.push(OpCode::END as u8);
+        buf.push(OpCode::END as u8);
+    });
+
+    create_exported_function_no_locals(
+        &mut module,
+        "answer",
+        Signature {
+            param_types: Vec::new_in(arena),
+            ret_type: Some(ValueType::I32),
+        },
+        |buf| {
+            buf.push(OpCode::I32CONST as u8);
+            buf.encode_i32(42);
+            buf.push(OpCode::END as u8);
+        },
+    );
+
+    module
+}
+
+#[test]
+fn test_fuel() {
+    let arena = Bump::new();
+    let module = create_infinite_loop_module(&arena);
+    let mut inst =
+        Instance::for_module(&arena, &module, DefaultImportDispatcher::default(), false).unwrap();
+
+    inst.limits = Limits {
+        fuel: Some(1000),
+        ..Default::default()
+    };
+    assert_eq!(inst.call_export("forever", []), Err(CallError::OutOfFuel));
+    assert_eq!(inst.limits.fuel, Some(0));
+
+    // The instance can be reused after topping up
+    inst.limits.fuel = Some(1000);
+    assert_eq!(inst.call_export("answer", []), Ok(Some(Value::I32(42))));
+    assert_eq!(inst.limits.fuel, Some(998));
+}
+
+#[test]
+fn test_max_memory_pages() {
+    let arena = Bump::new();
+    let mut module = WasmModule::new(&arena);
+    module.memory = MemorySection::new(&arena, MemorySection::PAGE_SIZE);
+
+    // grow(pages) = memory.grow(pages)
+    create_exported_function_no_locals(
+        &mut module,
+        "grow",
+        Signature {
+            param_types: bumpalo::vec![in &arena; ValueType::I32],
+            ret_type: Some(ValueType::I32),
+        },
+        |buf| {
+            buf.push(OpCode::GETLOCAL as u8);
+            buf.encode_u32(0);
+            buf.push(OpCode::GROWMEMORY as u8);
+            buf.encode_u32(0);
+            buf.push(OpCode::END as u8);
+        },
+    );
+
+    let mut inst =
+        Instance::for_module(&arena, &module, DefaultImportDispatcher::default(), false).unwrap();
+    inst.limits.max_memory_pages = Some(3);
+
+    // memory.grow returns the old number of pages
+    assert_eq!(
+        inst.call_export("grow", [Value::I32(2)]),
+        Ok(Some(Value::I32(1)))
+    );
+
+    assert_eq!(
+        inst.call_export("grow", [Value::I32(1)]),
+        Err(CallError::MemoryLimit { pages: 3 })
+    );
+    assert_eq!(inst.memory.len(), 3 * MemorySection::PAGE_SIZE as usize);
+
+    assert_eq!(
+        inst.call_export("grow", [Value::I32(0)]),
+        Ok(Some(Value::I32(3)))
+    );
+
+    // u32::MAX pages overflow the page count, which must not panic
+    assert_eq!(
+        inst.call_export("grow", [Value::I32(-1)]),
+        Err(CallError::MemoryLimit { pages: 3 })
+    );
+    assert_eq!(inst.memory.len(), 3 * MemorySection::PAGE_SIZE as usize);
+
+    // Without a limit, a memory.grow that overflows fails like any other
+    inst.limits.max_memory_pages = None;
+
+    assert_eq!(
+        inst.call_export("grow", [Value::I32(-1)]),
+        Ok(Some(Value::I32(-1)))
+    );
+    assert_eq!(
+        inst.call_export("grow", [Value::I32(65535)]),
+        Ok(Some(Value::I32(-1)))
+    );
+    assert_eq!(inst.memory.len(), 3 * MemorySection::PAGE_SIZE as usize);
+}
+
+#[test]
+fn test_max_call_depth() {
+    let arena = Bump::new();
+    let mut module = WasmModule::new(&arena);
+
+    // recurse() = recurse()
+    create_exported_function_no_locals(&mut module, "recurse", void_signature(&arena), |buf| {
+        buf.push(OpCode::CALL as u8);
+        buf.encode_u32(0);
+        buf.push(OpCode::END as u8);
+    });
+
+    // call_once() = nop()
+    create_exported_function_no_locals(&mut module, "call_once", void_signature(&arena), |buf| {
+        buf.push(OpCode::CALL as u8);
+        buf.encode_u32(2);
+        buf.push(OpCode::END as u8);
+    });
+
+    create_exported_function_no_locals(&mut module, "nop", void_signature(&arena), |buf| {
+        buf.push(OpCode::END as u8);
+    });
+
+    let mut inst =
+        Instance::for_module(&arena, &module, DefaultImportDispatcher::default(), false).unwrap();
+    inst.limits.max_call_depth = Some(2);
+
+    assert_eq!(
+        inst.call_export("recurse", []),
+        Err(CallError::CallDepthLimit { depth: 2 })
+    );
+
+    assert_eq!(inst.call_export("call_once", []), Ok(None));
+}
+
+#[test]
+fn test_traps() {
+    let arena = Bump::new();
+    let mut module = WasmModule::new(&arena);
+
+    create_exported_function_no_locals(&mut module, "crash", void_signature(&arena), |buf| {
+        buf.push(OpCode::UNREACHABLE as u8);
+        buf.push(OpCode::END as u8);
+    });
+
+    let mut inst =
+        Instance::for_module(&arena, &module, DefaultImportDispatcher::default(), false).unwrap();
+
+    match inst.call_export("crash", []) {
+        Err(CallError::Trap(message)) => assert!(message.contains("unreachable"), "{}", message),
+        other => panic!("Expected a trap, but got {:?}", other),
+    }
+
+    match inst.call_export("missing", []) {
+        Err(CallError::Trap(message)) => {
+            assert!(message.contains("couldn't find a function"), "{}", message)
+        }
+        other => panic!("Expected a trap, but got {:?}", other),
+    }
+}
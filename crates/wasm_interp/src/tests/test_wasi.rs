@@ -0,0 +1,571 @@
+use crate::wasi::Errno;
+use crate::{MemoryDir, WasiDispatcher, WasiFile};
+use roc_wasm_module::Value;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// Where the tests put things in memory
+const PATH: usize = 0x100;
+const IOVS: usize = 0x200;
+const OUT: usize = 0x300;
+const BUF: usize = 0x400;
+
+const OFLAGS_CREAT: i32 = 1;
+const RIGHTS_READ_WRITE: i64 = (1 << 1) | (1 << 6);
+
+fn setup() -> (WasiDispatcher<'static>, Rc<RefCell<MemoryDir>>, Vec<u8>) {
+    let mut root = MemoryDir::new();
+    root.insert_file("hello.txt", "Hello, world!");
+    root.insert_file("data/numbers.txt", "123");
+    let root = Rc::new(RefCell::new(root));
+
+    let mut wasi = WasiDispatcher::default();
+    wasi.files = vec![
+        WasiFile::ReadOnly(vec![]),
+        WasiFile::WriteOnly(vec![]),
+        WasiFile::WriteOnly(vec![]),
+    ];
+    let fd = wasi.preopen_memory_dir(".", root.clone());
+    assert_eq!(fd, 3);
+
+    (wasi, root, vec![0; 0x1000])
+}
+
+/// Call a WASI function and return its error code
+fn call(wasi: &mut WasiDispatcher, memory: &mut [u8], name: &str, args: &[Value]) -> i32 {
+    match wasi.dispatch(name, args, memory) {
+        Some(Value::I32(code)) => code,
+        other => panic!("{} returned {:?}", name, other),
+    }
+}
+
+fn read_u32(memory: &[u8], addr: usize) -> u32 {
+    u32::from_le_bytes(memory[addr..][..4].try_into().unwrap())
+}
+
+fn set_path(memory: &mut [u8], path: &str) -> [Value; 2] {
+    memory[PATH..][..path.len()].copy_from_slice(path.as_bytes());
+    [Value::I32(PATH as i32), Value::I32(path.len() as i32)]
+}
+
+/// Write a single iovec pointing at BUF
+fn set_iov(memory: &mut [u8], len: usize) {
+    memory[IOVS..][..4].copy_from_slice(&(BUF as u32).to_le_bytes());
+    memory[IOVS + 4..][..4].copy_from_slice(&(len as u32).to_le_bytes());
+}
+
+fn path_open(wasi: &mut WasiDispatcher, memory: &mut [u8], path: &str, oflags: i32) -> i32 {
+    let [ptr_path, path_len] = set_path(memory, path);
+    let args = [
+        Value::I32(3),
+        Value::I32(0),
+        ptr_path,
+        path_len,
+        Value::I32(oflags),
+        Value::I64(RIGHTS_READ_WRITE),
+        Value::I64(RIGHTS_READ_WRITE),
+        Value::I32(0),
+        Value::I32(OUT as i32),
+    ];
+    call(wasi, memory, "path_open", &args)
+}
+
+fn fd_read(wasi: &mut WasiDispatcher, memory: &mut [u8], fd: i32, len: usize) -> Vec<u8> {
+    set_iov(memory, len);
+    let args = [
+        Value::I32(fd),
+        Value::I32(IOVS as i32),
+        Value::I32(1),
+        Value::I32(OUT as i32),
+    ];
+    assert_eq!(call(wasi, memory, "fd_read", &args), Errno::Success as i32);
+    let n_read = read_u32(memory, OUT) as usize;
+    memory[BUF..][..n_read].to_vec()
+}
+
+#[test]
+fn test_preopens() {
+    let (mut wasi, _, mut memory) = setup();
+
+    let args = [Value::I32(3), Value::I32(OUT as i32)];
+    assert_eq!(
+        call(&mut wasi, &mut memory, "fd_prestat_get", &args),
+        Errno::Success as i32
+    );
+    assert_eq!(read_u32(&memory, OUT), 0); // directory
+    assert_eq!(read_u32(&memory, OUT + 4), 1); // name length
+
+    let args = [Value::I32(3), Value::I32(BUF as i32), Value::I32(1)];
+    assert_eq!(
+        call(&mut wasi, &mut memory, "fd_prestat_dir_name", &args),
+        Errno::Success as i32
+    );
+    assert_eq!(&memory[BUF..][..1], b".");
+
+    // stdio and nonexistent file descriptors are not preopens
+    for fd in [0, 1, 2, 4] {
+        let args = [Value::I32(fd), Value::I32(OUT as i32)];
+        assert_eq!(
+            call(&mut wasi, &mut memory, "fd_prestat_get", &args),
+            Errno::Badf as i32
+        );
+    }
+}
+
+#[test]
+fn test_read_file() {
+    let (mut wasi, _, mut memory) = setup();
+
+    assert_eq!(
+        path_open(&mut wasi, &mut memory, "hello.txt", 0),
+        Errno::Success as i32
+    );
+    let fd = read_u32(&memory, OUT) as i32;
+    assert_eq!(fd, 4);
+
+    assert_eq!(fd_read(&mut wasi, &mut memory, fd, 5), b"Hello");
+    assert_eq!(fd_read(&mut wasi, &mut memory, fd, 100), b", world!");
+    assert_eq!(fd_read(&mut wasi, &mut memory, fd, 100), b"");
+
+    // Seek back to the start
+    let args = [
+        Value::I32(fd),
+        Value::I64(0),
+        Value::I32(0),
+        Value::I32(OUT as i32),
+    ];
+    assert_eq!(
+        call(&mut wasi, &mut memory, "fd_seek", &args),
+        Errno::Success as i32
+    );
+    assert_eq!(fd_read(&mut wasi, &mut memory, fd, 5), b"Hello");
+
+    assert_eq!(
+        call(&mut wasi, &mut memory, "fd_close", &[Value::I32(fd)]),
+        Errno::Success as i32
+    );
+    assert_eq!(
+        call(&mut wasi, &mut memory, "fd_close", &[Value::I32(fd)]),
+        Errno::Badf as i32
+    );
+}
+
+#[test]
+fn test_create_and_write_file() {
+    let (mut wasi, root, mut memory) = setup();
+
+    assert_eq!(
+        path_open(&mut wasi, &mut memory, "data/out.txt", OFLAGS_CREAT),
+        Errno::Success as i32
+    );
+    let fd = read_u32(&memory, OUT) as i32;
+
+    memory[BUF..][..5].copy_from_slice(b"roc!\n");
+    set_iov(&mut memory, 5);
+    let args = [
+        Value::I32(fd),
+        Value::I32(IOVS as i32),
+        Value::I32(1),
+        Value::I32(OUT as i32),
+    ];
+    assert_eq!(
+        call(&mut wasi, &mut memory, "fd_write", &args),
+        Errno::Success as i32
+    );
+    assert_eq!(read_u32(&memory, OUT), 5);
+
+    assert_eq!(
+        root.borrow().read_file("data/out.txt"),
+        Some(b"roc!\n".to_vec())
+    );
+}
+
+#[test]
+fn test_missing_file() {
+    let (mut wasi, _, mut memory) = setup();
+
+    assert_eq!(
+        path_open(&mut wasi, &mut memory, "nope.txt", 0),
+        Errno::Noent as i32
+    );
+}
+
+#[test]
+fn test_cannot_escape_preopen() {
+    let (mut wasi, _, mut memory) = setup();
+
+    assert_eq!(
+        path_open(&mut wasi, &mut memory, "../hello.txt", 0),
+        Errno::Notcapable as i32
+    );
+    assert_eq!(
+        path_open(&mut wasi, &mut memory, "/hello.txt", 0),
+        Errno::Notcapable as i32
+    );
+
+    // Going up is fine as long as we stay inside
+    assert_eq!(
+        path_open(&mut wasi, &mut memory, "data/../hello.txt", 0),
+        Errno::Success as i32
+    );
+}
+
+#[test]
+fn test_readdir() {
+    let (mut wasi, _, mut memory) = setup();
+
+    let args = [
+        Value::I32(3),
+        Value::I32(BUF as i32),
+        Value::I32(0x200),
+        Value::I64(0),
+        Value::I32(OUT as i32),
+    ];
+    assert_eq!(
+        call(&mut wasi, &mut memory, "fd_readdir", &args),
+        Errno::Success as i32
+    );
+    let used = read_u32(&memory, OUT) as usize;
+
+    let mut names = vec![];
+    let mut offset = 0;
+    while offset < used {
+        let dirent = &memory[BUF + offset..];
+        let name_len = read_u32(dirent, 16) as usize;
+        names.push(String::from_utf8(dirent[24..][..name_len].to_vec()).unwrap());
+        offset += 24 + name_len;
+    }
+
+    assert_eq!(names, [".", "..", "data", "hello.txt"]);
+}
+
+#[test]
+fn test_remove_and_rename() {
+    let (mut wasi, root, mut memory) = setup();
+
+    let [ptr_old, old_len] = set_path(&mut memory, "hello.txt");
+    let new_path = "data/renamed.txt";
+    memory[BUF..][..new_path.len()].copy_from_slice(new_path.as_bytes());
+    let args = [
+        Value::I32(3),
+        ptr_old,
+        old_len,
+        Value::I32(3),
+        Value::I32(BUF as i32),
+        Value::I32(new_path.len() as i32),
+    ];
+    assert_eq!(
+        call(&mut wasi, &mut memory, "path_rename", &args),
+        Errno::Success as i32
+    );
+    assert_eq!(root.borrow().read_file("hello.txt"), None);
+    assert_eq!(
+        root.borrow().read_file("data/renamed.txt"),
+        Some(b"Hello, world!".to_vec())
+    );
+
+    // The directory isn't empty yet
+    let [ptr_path, path_len] = set_path(&mut memory, "data");
+    let args = [Value::I32(3), ptr_path, path_len];
+    assert_eq!(
+        call(&mut wasi, &mut memory, "path_remove_directory", &args),
+        Errno::Notempty as i32
+    );
+
+    for file in ["data/renamed.txt", "data/numbers.txt"] {
+        let [ptr_path, path_len] = set_path(&mut memory, file);
+        let args = [Value::I32(3), ptr_path, path_len];
+        assert_eq!(
+            call(&mut wasi, &mut memory, "path_unlink_file", &args),
+            Errno::Success as i32
+        );
+    }
+
+    let [ptr_path, path_len] = set_path(&mut memory, "data");
+    let args = [Value::I32(3), ptr_path, path_len];
+    assert_eq!(
+        call(&mut wasi, &mut memory, "path_remove_directory", &args),
+        Errno::Success as i32
+    );
+    assert_eq!(
+        path_open(&mut wasi, &mut memory, "data", 0),
+        Errno::Noent as i32
+    );
+}
+
+#[test]
+fn test_read_stdin_buffer_across_iovecs() {
+    let (mut wasi, _, mut memory) = setup();
+    wasi.files[0] = WasiFile::ReadOnly(b"abcdef".to_vec());
+
+    // Two iovecs of 2 bytes each
+    for (i, addr) in [BUF, BUF + 0x10].into_iter().enumerate() {
+        memory[IOVS + 8 * i..][..4].copy_from_slice(&(addr as u32).to_le_bytes());
+        memory[IOVS + 8 * i + 4..][..4].copy_from_slice(&2u32.to_le_bytes());
+    }
+    let args = [
+        Value::I32(0),
+        Value::I32(IOVS as i32),
+        Value::I32(2),
+        Value::I32(OUT as i32),
+    ];
+    assert_eq!(
+        call(&mut wasi, &mut memory, "fd_read", &args),
+        Errno::Success as i32
+    );
+    assert_eq!(read_u32(&memory, OUT), 4);
+    assert_eq!(&memory[BUF..][..2], b"ab");
+    assert_eq!(&memory[BUF + 0x10..][..2], b"cd");
+
+    // The next read continues where the last one left off
+    assert_eq!(fd_read(&mut wasi, &mut memory, 0, 100), b"ef");
+}
+
+#[test]
+fn test_environ() {
+    let (mut wasi, _, mut memory) = setup();
+    wasi.env = vec!["HOME=/home/roc".to_string(), "X=1".to_string()];
+
+    let args = [Value::I32(OUT as i32), Value::I32(OUT as i32 + 4)];
+    assert_eq!(
+        call(&mut wasi, &mut memory, "environ_sizes_get", &args),
+        Errno::Success as i32
+    );
+    assert_eq!(read_u32(&memory, OUT), 2);
+    assert_eq!(read_u32(&memory, OUT + 4), 19);
+
+    let args = [Value::I32(OUT as i32), Value::I32(BUF as i32)];
+    assert_eq!(
+        call(&mut wasi, &mut memory, "environ_get", &args),
+        Errno::Success as i32
+    );
+    assert_eq!(read_u32(&memory, OUT), BUF as u32);
+    assert_eq!(read_u32(&memory, OUT + 4), BUF as u32 + 15);
+    assert_eq!(&memory[BUF..][..19], b"HOME=/home/roc\0X=1\0");
+}
+
+#[test]
+#[cfg(unix)]
+fn test_symlinks_cannot_escape_host_preopen() {
+    use std::os::unix::fs::symlink;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path().join("root");
+    let outside = tmp.path().join("outside");
+    std::fs::create_dir_all(root.join("data")).unwrap();
+    std::fs::create_dir(&outside).unwrap();
+    std::fs::write(root.join("data/inside.txt"), "inside").unwrap();
+    std::fs::write(outside.join("secret.txt"), "secret").unwrap();
+
+    // A dangling link, whose target would be created outside the root by following it
+    symlink(outside.join("new.txt"), root.join("dangling")).unwrap();
+    symlink(outside.join("secret.txt"), root.join("secret")).unwrap();
+    symlink(&outside, root.join("outside_dir")).unwrap();
+    symlink(root.join("data/inside.txt"), root.join("inside")).unwrap();
+
+    let mut wasi = WasiDispatcher::default();
+    assert_eq!(wasi.preopen_host_dir(".", &root), 3);
+    let mut memory = vec![0; 0x1000];
+
+    for path in [
+        "dangling",
+        "secret",
+        "outside_dir/secret.txt",
+        "outside_dir/new.txt",
+    ] {
+        assert_eq!(
+            path_open(&mut wasi, &mut memory, path, OFLAGS_CREAT),
+            Errno::Notcapable as i32,
+            "{}",
+            path
+        );
+    }
+    assert!(!outside.join("new.txt").exists());
+
+    // Links that stay inside the root are fine
+    assert_eq!(
+        path_open(&mut wasi, &mut memory, "inside", 0),
+        Errno::Success as i32
+    );
+    let fd = read_u32(&memory, OUT) as i32;
+    assert_eq!(fd_read(&mut wasi, &mut memory, fd, 100), b"inside");
+
+    // Unlinking a dangling link removes the link, without following it
+    let [ptr_path, path_len] = set_path(&mut memory, "dangling");
+    let args = [Value::I32(3), ptr_path, path_len];
+    assert_eq!(
+        call(&mut wasi, &mut memory, "path_unlink_file", &args),
+        Errno::Success as i32
+    );
+    assert!(root.join("dangling").symlink_metadata().is_err());
+}
+
+#[test]
+fn test_bad_pointers_are_faults() {
+    let (mut wasi, _, mut memory) = setup();
+    let past_the_end = Value::I32(memory.len() as i32 - 2);
+
+    // Out param past the end of memory. The file is not opened.
+    let [ptr_path, path_len] = set_path(&mut memory, "hello.txt");
+    let args = [
+        Value::I32(3),
+        Value::I32(0),
+        ptr_path,
+        path_len,
+        Value::I32(0),
+        Value::I64(RIGHTS_READ_WRITE),
+        Value::I64(RIGHTS_READ_WRITE),
+        Value::I32(0),
+        past_the_end,
+    ];
+    assert_eq!(
+        call(&mut wasi, &mut memory, "path_open", &args),
+        Errno::Fault as i32
+    );
+    assert_eq!(wasi.files.len(), 4);
+
+    // A path that runs off the end of memory
+    let args = [Value::I32(3), past_the_end, Value::I32(100)];
+    assert_eq!(
+        call(&mut wasi, &mut memory, "path_create_directory", &args),
+        Errno::Fault as i32
+    );
+
+    // Iovecs whose buffers run off the end of memory
+    for (base, len) in [(BUF as u32, 0x10_0000), (u32::MAX - 1, 4)] {
+        memory[IOVS..][..4].copy_from_slice(&base.to_le_bytes());
+        memory[IOVS + 4..][..4].copy_from_slice(&len.to_le_bytes());
+        let args = [
+            Value::I32(1),
+            Value::I32(IOVS as i32),
+            Value::I32(1),
+            Value::I32(OUT as i32),
+        ];
+        assert_eq!(
+            call(&mut wasi, &mut memory, "fd_write", &args),
+            Errno::Fault as i32
+        );
+    }
+
+    let args = [past_the_end, Value::I32(16)];
+    assert_eq!(
+        call(&mut wasi, &mut memory, "random_get", &args),
+        Errno::Fault as i32
+    );
+
+    let args = [Value::I32(3), past_the_end];
+    assert_eq!(
+        call(&mut wasi, &mut memory, "fd_filestat_get", &args),
+        Errno::Fault as i32
+    );
+}
+
+#[test]
+fn test_memory_file_size_is_bounded() {
+    let (mut wasi, root, mut memory) = setup();
+
+    assert_eq!(
+        path_open(&mut wasi, &mut memory, "big.txt", OFLAGS_CREAT),
+        Errno::Success as i32
+    );
+    let fd = read_u32(&memory, OUT) as i32;
+
+    // Seeking far past the end is allowed, but writing there would need a huge allocation
+    let args = [
+        Value::I32(fd),
+        Value::I64(1 << 40),
+        Value::I32(0),
+        Value::I32(OUT as i32),
+    ];
+    assert_eq!(
+        call(&mut wasi, &mut memory, "fd_seek", &args),
+        Errno::Success as i32
+    );
+    memory[BUF..][..3].copy_from_slice(b"abc");
+    set_iov(&mut memory, 3);
+    let args = [
+        Value::I32(fd),
+        Value::I32(IOVS as i32),
+        Value::I32(1),
+        Value::I32(OUT as i32),
+    ];
+    assert_eq!(
+        call(&mut wasi, &mut memory, "fd_write", &args),
+        Errno::Fbig as i32
+    );
+
+    let args = [Value::I32(fd), Value::I64(1 << 40)];
+    assert_eq!(
+        call(&mut wasi, &mut memory, "fd_filestat_set_size", &args),
+        Errno::Fbig as i32
+    );
+
+    let args = [Value::I32(fd), Value::I64(i64::MAX), Value::I64(i64::MAX)];
+    assert_eq!(
+        call(&mut wasi, &mut memory, "fd_allocate", &args),
+        Errno::Fbig as i32
+    );
+
+    assert_eq!(root.borrow().read_file("big.txt"), Some(vec![]));
+}
+
+#[test]
+fn test_fdstat_rights() {
+    const RIGHTS_FD_READ: u64 = 1 << 1;
+    const RIGHTS_FD_SEEK: u64 = 1 << 2;
+    const RIGHTS_FD_WRITE: u64 = 1 << 6;
+    const RIGHTS_PATH_OPEN: u64 = 1 << 13;
+    const RIGHTS_PATH_SYMLINK: u64 = 1 << 24;
+
+    let (mut wasi, _, mut memory) = setup();
+
+    fn fdstat(wasi: &mut WasiDispatcher, memory: &mut [u8], fd: i32) -> (u8, u64, u64) {
+        let args = [Value::I32(fd), Value::I32(OUT as i32)];
+        assert_eq!(
+            call(wasi, memory, "fd_fdstat_get", &args),
+            Errno::Success as i32
+        );
+        let rights = u64::from_le_bytes(memory[OUT + 8..][..8].try_into().unwrap());
+        let inheriting = u64::from_le_bytes(memory[OUT + 16..][..8].try_into().unwrap());
+        (memory[OUT], rights, inheriting)
+    }
+
+    // A read-only stream can't be written to, or seek
+    let (filetype, rights, _) = fdstat(&mut wasi, &mut memory, 0);
+    assert_eq!(filetype, 2);
+    assert_eq!(rights & (RIGHTS_FD_WRITE | RIGHTS_FD_SEEK), 0);
+    assert_ne!(rights & RIGHTS_FD_READ, 0);
+
+    // Directories can open files, but can't be read like one. Nothing can make symlinks.
+    let (filetype, rights, inheriting) = fdstat(&mut wasi, &mut memory, 3);
+    assert_eq!(filetype, 3);
+    assert_ne!(rights & RIGHTS_PATH_OPEN, 0);
+    assert_eq!(rights & (RIGHTS_FD_READ | RIGHTS_PATH_SYMLINK), 0);
+    assert_ne!(inheriting & RIGHTS_FD_READ, 0);
+
+    // A file opened for reading only
+    let [ptr_path, path_len] = set_path(&mut memory, "hello.txt");
+    let args = [
+        Value::I32(3),
+        Value::I32(0),
+        ptr_path,
+        path_len,
+        Value::I32(0),
+        Value::I64(RIGHTS_FD_READ as i64),
+        Value::I64(0),
+        Value::I32(0),
+        Value::I32(BUF as i32),
+    ];
+    assert_eq!(
+        call(&mut wasi, &mut memory, "path_open", &args),
+        Errno::Success as i32
+    );
+    let fd = read_u32(&memory, BUF) as i32;
+    let (filetype, rights, _) = fdstat(&mut wasi, &mut memory, fd);
+    assert_eq!(filetype, 4);
+    assert_eq!(
+        rights & (RIGHTS_FD_READ | RIGHTS_FD_SEEK),
+        RIGHTS_FD_READ | RIGHTS_FD_SEEK
+    );
+    assert_eq!(rights & RIGHTS_FD_WRITE, 0);
+}
@@ -1,12 +1,75 @@
+use crate::wasi_fs::{
+    resolve, Directory, FileSystem, Filestat, Filetype, MemoryDir, OpenFile, OpenFlags, Opened,
+};
 use rand::prelude::*;
 use roc_wasm_module::Value;
-use std::io::{self, Read, StderrLock, StdoutLock, Write};
+use std::cell::RefCell;
+use std::io::{self, Read, SeekFrom, StderrLock, StdoutLock, Write};
+use std::path::PathBuf;
 use std::process::exit;
+use std::rc::Rc;
 
 pub const MODULE_NAME: &str = "wasi_snapshot_preview1";
 
+// https://github.com/WebAssembly/WASI/blob/main/legacy/preview1/docs.md
+const OFLAGS_CREAT: i32 = 1 << 0;
+const OFLAGS_DIRECTORY: i32 = 1 << 1;
+const OFLAGS_EXCL: i32 = 1 << 2;
+const OFLAGS_TRUNC: i32 = 1 << 3;
+const FDFLAGS_APPEND: i32 = 1 << 0;
+const RIGHTS_FD_DATASYNC: i64 = 1 << 0;
+const RIGHTS_FD_READ: i64 = 1 << 1;
+const RIGHTS_FD_SEEK: i64 = 1 << 2;
+const RIGHTS_FD_FDSTAT_SET_FLAGS: i64 = 1 << 3;
+const RIGHTS_FD_SYNC: i64 = 1 << 4;
+const RIGHTS_FD_TELL: i64 = 1 << 5;
+const RIGHTS_FD_WRITE: i64 = 1 << 6;
+const RIGHTS_FD_ADVISE: i64 = 1 << 7;
+const RIGHTS_FD_ALLOCATE: i64 = 1 << 8;
+const RIGHTS_PATH_CREATE_DIRECTORY: i64 = 1 << 9;
+const RIGHTS_PATH_CREATE_FILE: i64 = 1 << 10;
+const RIGHTS_PATH_OPEN: i64 = 1 << 13;
+const RIGHTS_FD_READDIR: i64 = 1 << 14;
+const RIGHTS_PATH_RENAME_SOURCE: i64 = 1 << 16;
+const RIGHTS_PATH_RENAME_TARGET: i64 = 1 << 17;
+const RIGHTS_PATH_FILESTAT_GET: i64 = 1 << 18;
+const RIGHTS_FD_FILESTAT_GET: i64 = 1 << 21;
+const RIGHTS_FD_FILESTAT_SET_SIZE: i64 = 1 << 22;
+const RIGHTS_PATH_REMOVE_DIRECTORY: i64 = 1 << 25;
+const RIGHTS_PATH_UNLINK_FILE: i64 = 1 << 26;
+// The rights we report for each kind of file descriptor, which are the calls we implement for it.
+// Links, timestamps and polling are missing because we don't support them.
+const RIGHTS_STREAM_READ: i64 = RIGHTS_FD_READ | RIGHTS_FD_FILESTAT_GET;
+const RIGHTS_STREAM_WRITE: i64 = RIGHTS_FD_WRITE | RIGHTS_FD_FILESTAT_GET;
+const RIGHTS_FILE: i64 = RIGHTS_FD_DATASYNC
+    | RIGHTS_FD_SEEK
+    | RIGHTS_FD_FDSTAT_SET_FLAGS
+    | RIGHTS_FD_SYNC
+    | RIGHTS_FD_TELL
+    | RIGHTS_FD_ADVISE
+    | RIGHTS_FD_FILESTAT_GET;
+const RIGHTS_FILE_READ: i64 = RIGHTS_FD_READ;
+const RIGHTS_FILE_WRITE: i64 = RIGHTS_FD_WRITE | RIGHTS_FD_ALLOCATE | RIGHTS_FD_FILESTAT_SET_SIZE;
+const RIGHTS_DIRECTORY: i64 = RIGHTS_PATH_CREATE_DIRECTORY
+    | RIGHTS_PATH_CREATE_FILE
+    | RIGHTS_PATH_OPEN
+    | RIGHTS_FD_READDIR
+    | RIGHTS_PATH_RENAME_SOURCE
+    | RIGHTS_PATH_RENAME_TARGET
+    | RIGHTS_PATH_FILESTAT_GET
+    | RIGHTS_FD_FILESTAT_GET
+    | RIGHTS_PATH_REMOVE_DIRECTORY
+    | RIGHTS_PATH_UNLINK_FILE;
+const WHENCE_SET: i32 = 0;
+const WHENCE_CUR: i32 = 1;
+const WHENCE_END: i32 = 2;
+const PREOPENTYPE_DIR: u32 = 0;
+const DIRENT_SIZE: usize = 24;
+
 pub struct WasiDispatcher<'a> {
     pub args: &'a [&'a [u8]],
+    /// Environment variables, each in the form `NAME=value`
+    pub env: Vec<String>,
     pub rng: ThreadRng,
     pub files: Vec<WasiFile>,
 }
@@ -18,16 +81,26 @@ impl Default for WasiDispatcher<'_> {
 }
 
 pub enum WasiFile {
+    /// A stream of bytes to read, like a pipe. Bytes are removed as they are read.
     ReadOnly(Vec<u8>),
+    /// Collects everything that gets written
     WriteOnly(Vec<u8>),
     ReadWrite(Vec<u8>),
+    /// The host's stdin, stdout or stderr
     HostSystemFile,
+    /// A directory, possibly one that was preopened for the module
+    Directory(Directory),
+    /// A file that the module opened with `path_open`
+    File(OpenFile),
+    /// A file descriptor that was closed. Its number can be reused.
+    Closed,
 }
 
 enum WriteLock<'a> {
     StdOut(StdoutLock<'a>),
     Stderr(StderrLock<'a>),
     RegularFile(&'a mut Vec<u8>),
+    OpenFile(&'a mut OpenFile),
 }
 
 /// Implementation of WASI syscalls
@@ -38,6 +111,7 @@ impl<'a> WasiDispatcher<'a> {
     pub fn new(args: &'a [&'a [u8]]) -> Self {
         WasiDispatcher {
             args,
+            env: Vec::new(),
             rng: thread_rng(),
             files: vec![
                 WasiFile::HostSystemFile,
@@ -47,13 +121,49 @@ impl<'a> WasiDispatcher<'a> {
         }
     }
 
+    /// Give the module access to a directory on the host, under the given name (e.g. `"."`).
+    /// Returns the new file descriptor.
+    pub fn preopen_host_dir(&mut self, name: &str, host_path: impl Into<PathBuf>) -> u32 {
+        self.preopen(name, FileSystem::Host(host_path.into()))
+    }
+
+    /// Give the module access to an in-memory directory tree, under the given name.
+    /// Returns the new file descriptor.
+    pub fn preopen_memory_dir(&mut self, name: &str, dir: Rc<RefCell<MemoryDir>>) -> u32 {
+        self.preopen(name, FileSystem::Memory(dir))
+    }
+
+    fn preopen(&mut self, name: &str, fs: FileSystem) -> u32 {
+        // WASI libc finds the preopens by asking about every file descriptor from 3 upwards,
+        // until one doesn't exist. So they have to be consecutive.
+        self.files.push(WasiFile::Directory(Directory {
+            fs,
+            path: PathBuf::new(),
+            preopen_name: Some(name.to_string()),
+        }));
+
+        (self.files.len() - 1) as u32
+    }
+
     pub fn dispatch(
         &mut self,
         function_name: &str,
         arguments: &[Value],
         memory: &mut [u8],
     ) -> Option<Value> {
-        let success_code = Some(Value::I32(Errno::Success as i32));
+        match self.dispatch_help(function_name, arguments, memory) {
+            Ok(value) => value,
+            Err(errno) => Some(Value::I32(errno as i32)),
+        }
+    }
+
+    fn dispatch_help(
+        &mut self,
+        function_name: &str,
+        arguments: &[Value],
+        memory: &mut [u8],
+    ) -> Result<Option<Value>, Errno> {
+        let success_code = Ok(Some(Value::I32(Errno::Success as i32)));
 
         match function_name {
             "args_get" => {
@@ -63,10 +173,8 @@ impl<'a> WasiDispatcher<'a> {
                 let mut ptr_argv_buf = arguments[1].expect_i32().unwrap() as usize;
 
                 for arg in self.args {
-                    write_u32(memory, ptr_ptr_argv, ptr_argv_buf as u32);
-                    let bytes_target = &mut memory[ptr_argv_buf..][..arg.len()];
-                    bytes_target.copy_from_slice(arg);
-                    memory[ptr_argv_buf + arg.len()] = 0; // C string zero termination
+                    write_u32(memory, ptr_ptr_argv, ptr_argv_buf as u32)?;
+                    write_c_string(memory, ptr_argv_buf, arg)?;
                     ptr_argv_buf += arg.len() + 1;
                     ptr_ptr_argv += 4;
                 }
@@ -82,28 +190,133 @@ impl<'a> WasiDispatcher<'a> {
                 let ptr_argv_buf_size = arguments[1].expect_i32().unwrap() as usize;
 
                 let argc = self.args.len() as u32;
-                write_u32(memory, ptr_argc, argc);
+                write_u32(memory, ptr_argc, argc)?;
 
                 let argv_buf_size: u32 = self.args.iter().map(|a| 1 + a.len() as u32).sum();
-                write_u32(memory, ptr_argv_buf_size, argv_buf_size);
+                write_u32(memory, ptr_argv_buf_size, argv_buf_size)?;
+
+                success_code
+            }
+            "environ_get" => {
+                // uint8_t ** environ,
+                let mut ptr_ptr_environ = arguments[0].expect_i32().unwrap() as usize;
+                // uint8_t * environ_buf
+                let mut ptr_environ_buf = arguments[1].expect_i32().unwrap() as usize;
+
+                for var in self.env.iter() {
+                    write_u32(memory, ptr_ptr_environ, ptr_environ_buf as u32)?;
+                    write_c_string(memory, ptr_environ_buf, var.as_bytes())?;
+                    ptr_environ_buf += var.len() + 1;
+                    ptr_ptr_environ += 4;
+                }
+
+                success_code
+            }
+            "environ_sizes_get" => {
+                // number of environment variables
+                let ptr_count = arguments[0].expect_i32().unwrap() as usize;
+                // size of environment variables buffer
+                let ptr_buf_size = arguments[1].expect_i32().unwrap() as usize;
+
+                write_u32(memory, ptr_count, self.env.len() as u32)?;
+
+                let buf_size: u32 = self.env.iter().map(|var| 1 + var.len() as u32).sum();
+                write_u32(memory, ptr_buf_size, buf_size)?;
 
                 success_code
             }
-            "environ_get" => todo!("WASI {}({:?})", function_name, arguments),
-            "environ_sizes_get" => todo!("WASI {}({:?})", function_name, arguments),
             "clock_res_get" => success_code, // this dummy implementation seems to be good enough for some functions
             "clock_time_get" => success_code,
-            "fd_advise" => todo!("WASI {}({:?})", function_name, arguments),
-            "fd_allocate" => todo!("WASI {}({:?})", function_name, arguments),
-            "fd_close" => todo!("WASI {}({:?})", function_name, arguments),
-            "fd_datasync" => todo!("WASI {}({:?})", function_name, arguments),
-            "fd_fdstat_get" => todo!("WASI {}({:?})", function_name, arguments),
-            "fd_fdstat_set_flags" => todo!("WASI {}({:?})", function_name, arguments),
-            "fd_fdstat_set_rights" => todo!("WASI {}({:?})", function_name, arguments),
-            "fd_filestat_get" => todo!("WASI {}({:?})", function_name, arguments),
-            "fd_filestat_set_size" => todo!("WASI {}({:?})", function_name, arguments),
-            "fd_filestat_set_times" => todo!("WASI {}({:?})", function_name, arguments),
-            "fd_pread" => todo!("WASI {}({:?})", function_name, arguments),
+            "fd_advise" => {
+                // fd, offset, len, advice. Advice is optional, so we can ignore it.
+                let fd = arguments[0].expect_i32().unwrap() as usize;
+                errno(self.file(fd).map(|_| ()))
+            }
+            "fd_allocate" => {
+                let fd = arguments[0].expect_i32().unwrap() as usize;
+                let offset = arguments[1].expect_i64().unwrap() as u64;
+                let len = arguments[2].expect_i64().unwrap() as u64;
+                let end = offset.checked_add(len).ok_or(Errno::Fbig)?;
+                errno(self.open_file(fd).and_then(|file| {
+                    if file.filestat()?.size < end {
+                        file.set_size(end)
+                    } else {
+                        Ok(())
+                    }
+                }))
+            }
+            "fd_close" => {
+                let fd = arguments[0].expect_i32().unwrap() as usize;
+                errno(self.file(fd).map(|file| *file = WasiFile::Closed))
+            }
+            "fd_datasync" | "fd_sync" => {
+                // Our writes go straight to the host (or to memory), so there's nothing to flush
+                let fd = arguments[0].expect_i32().unwrap() as usize;
+                errno(self.file(fd).map(|_| ()))
+            }
+            "fd_fdstat_get" => {
+                let fd = arguments[0].expect_i32().unwrap() as usize;
+                // Out param: fdstat struct (24 bytes)
+                let ptr_buf = arguments[1].expect_i32().unwrap() as usize;
+                errno(self.fd_fdstat_get(fd, memory, ptr_buf))
+            }
+            "fd_fdstat_set_flags" => {
+                let fd = arguments[0].expect_i32().unwrap() as usize;
+                let flags = arguments[1].expect_i32().unwrap();
+                errno(self.file(fd).map(|file| {
+                    if let WasiFile::File(file) = file {
+                        file.append = flags & FDFLAGS_APPEND != 0;
+                    }
+                }))
+            }
+            "fd_fdstat_set_rights" => {
+                // We don't track rights beyond reading and writing, and those can't be added
+                let fd = arguments[0].expect_i32().unwrap() as usize;
+                errno(self.file(fd).map(|_| ()))
+            }
+            "fd_filestat_get" => {
+                let fd = arguments[0].expect_i32().unwrap() as usize;
+                // Out param: filestat struct (64 bytes)
+                let ptr_buf = arguments[1].expect_i32().unwrap() as usize;
+                errno(
+                    self.fd_filestat_get(fd)
+                        .and_then(|stat| stat.write_to(memory, ptr_buf)),
+                )
+            }
+            "fd_filestat_set_size" => {
+                let fd = arguments[0].expect_i32().unwrap() as usize;
+                let size = arguments[1].expect_i64().unwrap() as u64;
+                errno(self.open_file(fd).and_then(|file| file.set_size(size)))
+            }
+            "fd_filestat_set_times" => Err(Errno::Nosys),
+            "fd_pread" => {
+                let fd = arguments[0].expect_i32().unwrap() as usize;
+                let ptr_iovs = arguments[1].expect_i32().unwrap() as usize;
+                let iovs_len = arguments[2].expect_i32().unwrap() as usize;
+                let offset = arguments[3].expect_i64().unwrap() as u64;
+                // Out param: number of bytes read
+                let ptr_nread = arguments[4].expect_i32().unwrap() as usize;
+
+                let iovs = iovecs(memory, ptr_iovs, iovs_len)?;
+                let result = self.open_file(fd).and_then(|file| {
+                    let original_position = file.seek(SeekFrom::Current(0))?;
+                    file.seek(SeekFrom::Start(offset))?;
+
+                    let mut n_read = 0;
+                    for (iov_base, iov_len) in iovs {
+                        let n = file.read(slice_mut(memory, iov_base, iov_len)?)?;
+                        n_read += n;
+                        if n < iov_len {
+                            break;
+                        }
+                    }
+
+                    file.seek(SeekFrom::Start(original_position))?;
+                    Ok(n_read)
+                });
+
+                errno(result.and_then(|n_read| write_u32(memory, ptr_nread, n_read as u32)))
+            }
             "fd_prestat_get" => {
                 // The preopened file descriptor to query
                 let fd = arguments[0].expect_i32().unwrap() as usize;
@@ -111,19 +324,64 @@ impl<'a> WasiDispatcher<'a> {
                 //  preopen type: 4 bytes, where 0=dir is the only one supported, it seems
                 //  preopen name length: 4 bytes
                 let ptr_buf = arguments[1].expect_i32().unwrap() as usize;
-                memory[ptr_buf..][..8].copy_from_slice(&0u64.to_le_bytes());
-                if fd < self.files.len() {
-                    success_code
-                } else {
-                    println!("WASI warning: file descriptor {} does not exist", fd);
-                    Some(Value::I32(Errno::Badf as i32))
+
+                match self.files.get(fd) {
+                    Some(WasiFile::Directory(Directory {
+                        preopen_name: Some(name),
+                        ..
+                    })) => {
+                        write_u32(memory, ptr_buf, PREOPENTYPE_DIR)?;
+                        write_u32(memory, ptr_buf + 4, name.len() as u32)?;
+                        success_code
+                    }
+                    // This is how WASI libc knows it has found all the preopens
+                    _ => Err(Errno::Badf),
                 }
             }
             "fd_prestat_dir_name" => {
-                // We're not giving names to any of our files so just return success
-                success_code
+                let fd = arguments[0].expect_i32().unwrap() as usize;
+                // Where to write the name, and how much space there is
+                let ptr_path = arguments[1].expect_i32().unwrap() as usize;
+                let path_len = arguments[2].expect_i32().unwrap() as usize;
+
+                match self.files.get(fd) {
+                    Some(WasiFile::Directory(Directory {
+                        preopen_name: Some(name),
+                        ..
+                    })) => {
+                        let len = name.len().min(path_len);
+                        slice_mut(memory, ptr_path, len)?.copy_from_slice(&name.as_bytes()[..len]);
+                        success_code
+                    }
+                    _ => Err(Errno::Badf),
+                }
+            }
+            "fd_pwrite" => {
+                let fd = arguments[0].expect_i32().unwrap() as usize;
+                let ptr_iovs = arguments[1].expect_i32().unwrap() as usize;
+                let iovs_len = arguments[2].expect_i32().unwrap() as usize;
+                let offset = arguments[3].expect_i64().unwrap() as u64;
+                // Out param: number of bytes written
+                let ptr_nwritten = arguments[4].expect_i32().unwrap() as usize;
+
+                let iovs = iovecs(memory, ptr_iovs, iovs_len)?;
+                let result = self.open_file(fd).and_then(|file| {
+                    let original_position = file.seek(SeekFrom::Current(0))?;
+                    file.seek(SeekFrom::Start(offset))?;
+
+                    let mut n_written = 0;
+                    for (iov_base, iov_len) in iovs {
+                        n_written += file.write(slice(memory, iov_base, iov_len)?)?;
+                    }
+
+                    file.seek(SeekFrom::Start(original_position))?;
+                    Ok(n_written)
+                });
+
+                errno(
+                    result.and_then(|n_written| write_u32(memory, ptr_nwritten, n_written as u32)),
+                )
             }
-            "fd_pwrite" => todo!("WASI {}({:?})", function_name, arguments),
             "fd_read" => {
                 use WasiFile::*;
 
@@ -132,57 +390,128 @@ impl<'a> WasiDispatcher<'a> {
                 // Array of IO vectors
                 let ptr_iovs = arguments[1].expect_i32().unwrap() as usize;
                 // Length of array
-                let iovs_len = arguments[2].expect_i32().unwrap();
+                let iovs_len = arguments[2].expect_i32().unwrap() as usize;
                 // Out param: number of bytes read
                 let ptr_nread = arguments[3].expect_i32().unwrap() as usize;
 
-                // https://man7.org/linux/man-pages/man2/readv.2.html
-                // struct iovec {
-                //     void  *iov_base;    /* Starting address */
-                //     size_t iov_len;     /* Number of bytes to transfer */
-                // };
-
                 let mut n_read: usize = 0;
-                match self.files.get(fd) {
-                    Some(ReadOnly(content) | ReadWrite(content)) => {
-                        for _ in 0..iovs_len {
-                            let iov_base = read_u32(memory, ptr_iovs) as usize;
-                            let iov_len = read_i32(memory, ptr_iovs + 4) as usize;
-                            let remaining = content.len() - n_read;
-                            let len = remaining.min(iov_len);
-                            if len == 0 {
+                for (iov_base, iov_len) in iovecs(memory, ptr_iovs, iovs_len)? {
+                    let buf = slice_mut(memory, iov_base, iov_len)?;
+
+                    let result = match self.files.get_mut(fd) {
+                        Some(ReadOnly(content) | ReadWrite(content)) => {
+                            let len = content.len().min(iov_len);
+                            buf[..len].copy_from_slice(&content[..len]);
+                            content.drain(..len);
+                            Ok(len)
+                        }
+                        Some(HostSystemFile) if fd == 0 => {
+                            io::stdin().read(buf).map_err(|_| Errno::Io)
+                        }
+                        Some(File(file)) => file.read(buf),
+                        Some(Directory(_)) => Err(Errno::Isdir),
+                        _ => Err(Errno::Badf),
+                    };
+
+                    match result {
+                        Ok(n) => {
+                            n_read += n;
+
+                            // Don't block waiting for more than is available right now
+                            if n < iov_len {
                                 break;
                             }
-                            memory[iov_base..][..len].copy_from_slice(&content[n_read..][..len]);
-                            n_read += len;
                         }
+                        Err(e) if n_read == 0 => return Err(e),
+                        Err(_) => break,
                     }
-                    Some(HostSystemFile) if fd == 0 => {
-                        let mut stdin = io::stdin();
-                        for _ in 0..iovs_len {
-                            let iov_base = read_u32(memory, ptr_iovs) as usize;
-                            let iov_len = read_i32(memory, ptr_iovs + 4) as usize;
-                            match stdin.read(&mut memory[iov_base..][..iov_len]) {
-                                Ok(n) => {
-                                    n_read += n;
-                                }
-                                Err(_) => {
-                                    break;
-                                }
-                            }
-                        }
+                }
+
+                write_u32(memory, ptr_nread, n_read as u32)?;
+                success_code
+            }
+            "fd_readdir" => {
+                let fd = arguments[0].expect_i32().unwrap() as usize;
+                // Where to write the entries, and how much space there is
+                let ptr_buf = arguments[1].expect_i32().unwrap() as usize;
+                let buf_len = arguments[2].expect_i32().unwrap() as usize;
+                // The index of the first entry to write
+                let cookie = arguments[3].expect_i64().unwrap() as usize;
+                // Out param: number of bytes written. Less than buf_len means we reached the end.
+                let ptr_bufused = arguments[4].expect_i32().unwrap() as usize;
+
+                let entries = self
+                    .directory(fd)
+                    .and_then(|dir| dir.fs.read_dir(&dir.path))?;
+
+                let buf = slice_mut(memory, ptr_buf, buf_len)?;
+                let mut used = 0;
+
+                for (index, entry) in entries.iter().enumerate().skip(cookie) {
+                    // https://github.com/WebAssembly/WASI/blob/main/legacy/preview1/docs.md#-dirent-record
+                    let mut dirent = [0; DIRENT_SIZE];
+                    dirent[0..8].copy_from_slice(&(index as u64 + 1).to_le_bytes()); // d_next
+                    dirent[8..16].copy_from_slice(&(index as u64 + 1).to_le_bytes()); // d_ino
+                    dirent[16..20].copy_from_slice(&(entry.name.len() as u32).to_le_bytes());
+                    dirent[20] = entry.filetype as u8;
+
+                    // If an entry doesn't fit, write as much as we can. WASI libc will
+                    // notice, and ask again with a bigger buffer.
+                    for bytes in [&dirent[..], entry.name.as_bytes()] {
+                        let len = bytes.len().min(buf_len - used);
+                        buf[used..][..len].copy_from_slice(&bytes[..len]);
+                        used += len;
                     }
-                    _ => return Some(Value::I32(Errno::Badf as i32)),
-                };
 
-                memory[ptr_nread..][..4].copy_from_slice(&(n_read as u32).to_le_bytes());
+                    if used == buf_len {
+                        break;
+                    }
+                }
+
+                write_u32(memory, ptr_bufused, used as u32)?;
                 success_code
             }
-            "fd_readdir" => todo!("WASI {}({:?})", function_name, arguments),
-            "fd_renumber" => todo!("WASI {}({:?})", function_name, arguments),
-            "fd_seek" => todo!("WASI {}({:?})", function_name, arguments),
-            "fd_sync" => todo!("WASI {}({:?})", function_name, arguments),
-            "fd_tell" => todo!("WASI {}({:?})", function_name, arguments),
+            "fd_renumber" => {
+                let from = arguments[0].expect_i32().unwrap() as usize;
+                let to = arguments[1].expect_i32().unwrap() as usize;
+
+                let result = self.file(to).map(|_| ()).and_then(|_| {
+                    let file = std::mem::replace(self.file(from)?, WasiFile::Closed);
+                    self.files[to] = file;
+                    Ok(())
+                });
+
+                errno(result)
+            }
+            "fd_seek" => {
+                let fd = arguments[0].expect_i32().unwrap() as usize;
+                let offset = arguments[1].expect_i64().unwrap();
+                let whence = arguments[2].expect_i32().unwrap();
+                // Out param: the new offset, relative to the start of the file
+                let ptr_newoffset = arguments[3].expect_i32().unwrap() as usize;
+
+                let seek_from = match whence {
+                    WHENCE_SET if offset >= 0 => SeekFrom::Start(offset as u64),
+                    WHENCE_CUR => SeekFrom::Current(offset),
+                    WHENCE_END => SeekFrom::End(offset),
+                    _ => return Err(Errno::Inval),
+                };
+
+                let result = self.open_file(fd).and_then(|file| file.seek(seek_from));
+
+                errno(result.and_then(|position| write_u64(memory, ptr_newoffset, position)))
+            }
+            "fd_tell" => {
+                let fd = arguments[0].expect_i32().unwrap() as usize;
+                // Out param: the current offset, relative to the start of the file
+                let ptr_offset = arguments[1].expect_i32().unwrap() as usize;
+
+                let result = self
+                    .open_file(fd)
+                    .and_then(|file| file.seek(SeekFrom::Current(0)));
+
+                errno(result.and_then(|position| write_u64(memory, ptr_offset, position)))
+            }
             "fd_write" => {
                 use WasiFile::*;
 
@@ -201,24 +530,26 @@ impl<'a> WasiDispatcher<'a> {
                     Some(HostSystemFile) => match fd {
                         1 => WriteLock::StdOut(io::stdout().lock()),
                         2 => WriteLock::Stderr(io::stderr().lock()),
-                        _ => return Some(Value::I32(Errno::Inval as i32)),
+                        _ => return Err(Errno::Inval),
                     },
                     Some(WriteOnly(content) | ReadWrite(content)) => {
                         WriteLock::RegularFile(content)
                     }
-                    _ => return Some(Value::I32(Errno::Badf as i32)),
+                    Some(File(file)) => WriteLock::OpenFile(file),
+                    Some(Directory(_)) => return Err(Errno::Isdir),
+                    _ => return Err(Errno::Badf),
                 };
 
                 let mut n_written: i32 = 0;
                 let mut negative_length_count = 0;
-                for _ in 0..iovs_len {
+                for i in 0..iovs_len as usize {
                     // https://man7.org/linux/man-pages/man2/readv.2.html
                     // struct iovec {
                     //     void  *iov_base;    /* Starting address */
                     //     size_t iov_len;     /* Number of bytes to transfer */
                     // };
-                    let iov_base = read_u32(memory, ptr_iovs) as usize;
-                    let iov_len = read_i32(memory, ptr_iovs + 4);
+                    let iov_base = read_u32(memory, ptr_iovs + 8 * i)? as usize;
+                    let iov_len = read_i32(memory, ptr_iovs + 8 * i + 4)?;
                     if iov_len < 0 {
                         // I found negative-length iov's when I implemented this in JS for the web REPL (see wasi.js)
                         // I'm not sure why, but this solution worked, and it's the same WASI libc - there's only one.
@@ -226,7 +557,7 @@ impl<'a> WasiDispatcher<'a> {
                         negative_length_count += 1;
                         continue;
                     }
-                    let bytes = &memory[iov_base..][..iov_len as usize];
+                    let bytes = slice(memory, iov_base, iov_len as usize)?;
 
                     match &mut write_lock {
                         WriteLock::StdOut(stdout) => {
@@ -239,10 +570,15 @@ impl<'a> WasiDispatcher<'a> {
                             content.extend_from_slice(bytes);
                             n_written += bytes.len() as i32;
                         }
+                        WriteLock::OpenFile(file) => match file.write(bytes) {
+                            Ok(n) => n_written += n as i32,
+                            Err(e) if n_written == 0 => return Err(e),
+                            Err(_) => break,
+                        },
                     }
                 }
 
-                write_i32(memory, ptr_nwritten, n_written);
+                write_i32(memory, ptr_nwritten, n_written)?;
                 if negative_length_count > 0 {
                     // Let's see if we ever get this message. If not, we can remove this negative-length stuff.
                     eprintln!(
@@ -253,59 +589,322 @@ impl<'a> WasiDispatcher<'a> {
 
                 success_code
             }
-            "path_create_directory" => todo!("WASI {}({:?})", function_name, arguments),
-            "path_filestat_get" => todo!("WASI {}({:?})", function_name, arguments),
-            "path_filestat_set_times" => todo!("WASI {}({:?})", function_name, arguments),
-            "path_link" => todo!("WASI {}({:?})", function_name, arguments),
-            "path_open" => todo!("WASI {}({:?})", function_name, arguments),
-            "path_readlink" => todo!("WASI {}({:?})", function_name, arguments),
-            "path_remove_directory" => todo!("WASI {}({:?})", function_name, arguments),
-            "path_rename" => todo!("WASI {}({:?})", function_name, arguments),
-            "path_symlink" => todo!("WASI {}({:?})", function_name, arguments),
-            "path_unlink_file" => todo!("WASI {}({:?})", function_name, arguments),
-            "poll_oneoff" => todo!("WASI {}({:?})", function_name, arguments),
+            "path_create_directory" => {
+                let fd = arguments[0].expect_i32().unwrap() as usize;
+                let ptr_path = arguments[1].expect_i32().unwrap() as usize;
+                let path_len = arguments[2].expect_i32().unwrap() as usize;
+
+                let result = self
+                    .resolve_path(fd, memory, ptr_path, path_len)
+                    .and_then(|(fs, path)| fs.create_dir(&path));
+
+                errno(result)
+            }
+            "path_filestat_get" => {
+                // Arguments: fd, lookup flags, path, path length, out param for the filestat.
+                // Symlinks are always followed (as long as they stay inside the preopen), so
+                // there's no need to look at the flags.
+                let fd = arguments[0].expect_i32().unwrap() as usize;
+                let ptr_path = arguments[2].expect_i32().unwrap() as usize;
+                let path_len = arguments[3].expect_i32().unwrap() as usize;
+                let ptr_buf = arguments[4].expect_i32().unwrap() as usize;
+
+                let result = self
+                    .resolve_path(fd, memory, ptr_path, path_len)
+                    .and_then(|(fs, path)| fs.filestat(&path));
+
+                errno(result.and_then(|stat| stat.write_to(memory, ptr_buf)))
+            }
+            "path_filestat_set_times" => Err(Errno::Nosys),
+            // Links could point outside the sandbox, so we don't support them
+            "path_link" | "path_readlink" | "path_symlink" => Err(Errno::Nosys),
+            "path_open" => {
+                let fd = arguments[0].expect_i32().unwrap() as usize;
+                // arguments[1] is the lookup flags, which are only about symlinks
+                let ptr_path = arguments[2].expect_i32().unwrap() as usize;
+                let path_len = arguments[3].expect_i32().unwrap() as usize;
+                let oflags = arguments[4].expect_i32().unwrap();
+                let rights_base = arguments[5].expect_i64().unwrap();
+                // arguments[6] is the rights for files opened from this one, which we don't track
+                let fdflags = arguments[7].expect_i32().unwrap();
+                // Out param: the new file descriptor
+                let ptr_fd = arguments[8].expect_i32().unwrap() as usize;
+
+                // Check the out param first, so we never open a file the module can't be told about
+                slice(memory, ptr_fd, 4)?;
+
+                let flags = OpenFlags {
+                    create: oflags & OFLAGS_CREAT != 0,
+                    directory: oflags & OFLAGS_DIRECTORY != 0,
+                    exclusive: oflags & OFLAGS_EXCL != 0,
+                    truncate: oflags & OFLAGS_TRUNC != 0,
+                    read: rights_base & RIGHTS_FD_READ != 0,
+                    write: rights_base & RIGHTS_FD_WRITE != 0,
+                    append: fdflags & FDFLAGS_APPEND != 0,
+                };
+
+                let opened = self
+                    .resolve_path(fd, memory, ptr_path, path_len)
+                    .and_then(|(fs, path)| fs.open(&path, flags))?;
+
+                let file = match opened {
+                    Opened::Directory(dir) => WasiFile::Directory(dir),
+                    Opened::File(file) => WasiFile::File(file),
+                };
+
+                let new_fd = self.insert_file(file);
+                write_u32(memory, ptr_fd, new_fd)?;
+                success_code
+            }
+            "path_remove_directory" => {
+                let fd = arguments[0].expect_i32().unwrap() as usize;
+                let ptr_path = arguments[1].expect_i32().unwrap() as usize;
+                let path_len = arguments[2].expect_i32().unwrap() as usize;
+
+                let result = self
+                    .resolve_path(fd, memory, ptr_path, path_len)
+                    .and_then(|(fs, path)| fs.remove_dir(&path));
+
+                errno(result)
+            }
+            "path_rename" => {
+                let old_fd = arguments[0].expect_i32().unwrap() as usize;
+                let ptr_old_path = arguments[1].expect_i32().unwrap() as usize;
+                let old_path_len = arguments[2].expect_i32().unwrap() as usize;
+                let new_fd = arguments[3].expect_i32().unwrap() as usize;
+                let ptr_new_path = arguments[4].expect_i32().unwrap() as usize;
+                let new_path_len = arguments[5].expect_i32().unwrap() as usize;
+
+                let result = self
+                    .resolve_path(old_fd, memory, ptr_old_path, old_path_len)
+                    .and_then(|(old_fs, old_path)| {
+                        let (new_fs, new_path) =
+                            self.resolve_path(new_fd, memory, ptr_new_path, new_path_len)?;
+
+                        if old_fs.is_same(&new_fs) {
+                            old_fs.rename(&old_path, &new_path)
+                        } else {
+                            Err(Errno::Xdev)
+                        }
+                    });
+
+                errno(result)
+            }
+            "path_unlink_file" => {
+                let fd = arguments[0].expect_i32().unwrap() as usize;
+                let ptr_path = arguments[1].expect_i32().unwrap() as usize;
+                let path_len = arguments[2].expect_i32().unwrap() as usize;
+
+                let result = self
+                    .resolve_path(fd, memory, ptr_path, path_len)
+                    .and_then(|(fs, path)| fs.remove_file(&path));
+
+                errno(result)
+            }
+            "poll_oneoff" => Err(Errno::Nosys),
             "proc_exit" => {
                 let exit_code = arguments[0].expect_i32().unwrap();
                 exit(exit_code);
             }
-            "proc_raise" => todo!("WASI {}({:?})", function_name, arguments),
-            "sched_yield" => todo!("WASI {}({:?})", function_name, arguments),
+            "proc_raise" => Err(Errno::Nosys),
+            "sched_yield" => success_code,
             "random_get" => {
                 // A pointer to a buffer where the random bytes will be written
                 let ptr_buf = arguments[0].expect_i32().unwrap() as usize;
                 // The number of bytes that will be written
                 let buf_len = arguments[1].expect_i32().unwrap() as usize;
-                for i in 0..buf_len {
-                    memory[ptr_buf + i] = self.rng.gen();
-                }
+                self.rng.fill(slice_mut(memory, ptr_buf, buf_len)?);
                 success_code
             }
-            "sock_recv" => todo!("WASI {}({:?})", function_name, arguments),
-            "sock_send" => todo!("WASI {}({:?})", function_name, arguments),
-            "sock_shutdown" => todo!("WASI {}({:?})", function_name, arguments),
+            // We never hand out sockets
+            "sock_recv" | "sock_send" | "sock_shutdown" => Err(Errno::Notsock),
             _ => panic!("Unknown WASI function {}({:?})", function_name, arguments),
         }
     }
+
+    /// Put a newly opened file in the lowest free slot, and return its file descriptor
+    fn insert_file(&mut self, file: WasiFile) -> u32 {
+        match self
+            .files
+            .iter()
+            .position(|f| matches!(f, WasiFile::Closed))
+        {
+            Some(fd) => {
+                self.files[fd] = file;
+                fd as u32
+            }
+            None => {
+                self.files.push(file);
+                (self.files.len() - 1) as u32
+            }
+        }
+    }
+
+    fn file(&mut self, fd: usize) -> Result<&mut WasiFile, Errno> {
+        match self.files.get_mut(fd) {
+            None | Some(WasiFile::Closed) => Err(Errno::Badf),
+            Some(file) => Ok(file),
+        }
+    }
+
+    /// A file opened with `path_open`. Other kinds of files can't seek, so they're an error.
+    fn open_file(&mut self, fd: usize) -> Result<&mut OpenFile, Errno> {
+        match self.file(fd)? {
+            WasiFile::File(file) => Ok(file),
+            WasiFile::Directory(_) => Err(Errno::Isdir),
+            _ => Err(Errno::Spipe),
+        }
+    }
+
+    fn directory(&mut self, fd: usize) -> Result<&Directory, Errno> {
+        match self.file(fd)? {
+            WasiFile::Directory(dir) => Ok(dir),
+            _ => Err(Errno::Notdir),
+        }
+    }
+
+    /// Resolve a path argument against a directory file descriptor
+    fn resolve_path(
+        &mut self,
+        fd: usize,
+        memory: &[u8],
+        ptr_path: usize,
+        path_len: usize,
+    ) -> Result<(FileSystem, PathBuf), Errno> {
+        let path =
+            std::str::from_utf8(slice(memory, ptr_path, path_len)?).map_err(|_| Errno::Ilseq)?;
+        let dir = self.directory(fd)?;
+
+        Ok((dir.fs.clone(), resolve(&dir.path, path)?))
+    }
+
+    fn fd_fdstat_get(&mut self, fd: usize, memory: &mut [u8], ptr_buf: usize) -> Result<(), Errno> {
+        use WasiFile::*;
+
+        let (filetype, flags, rights, rights_inheriting) = match self.file(fd)? {
+            // Pretend to be a terminal: a character device that can't seek
+            ReadOnly(_) => (Filetype::CharacterDevice, 0, RIGHTS_STREAM_READ, 0),
+            WriteOnly(_) => (Filetype::CharacterDevice, 0, RIGHTS_STREAM_WRITE, 0),
+            ReadWrite(_) => (
+                Filetype::CharacterDevice,
+                0,
+                RIGHTS_STREAM_READ | RIGHTS_STREAM_WRITE,
+                0,
+            ),
+            HostSystemFile if fd == 0 => (Filetype::CharacterDevice, 0, RIGHTS_STREAM_READ, 0),
+            HostSystemFile => (Filetype::CharacterDevice, 0, RIGHTS_STREAM_WRITE, 0),
+            Directory(_) => (
+                Filetype::Directory,
+                0,
+                RIGHTS_DIRECTORY,
+                RIGHTS_DIRECTORY | RIGHTS_FILE | RIGHTS_FILE_READ | RIGHTS_FILE_WRITE,
+            ),
+            File(file) => {
+                let mut rights = RIGHTS_FILE;
+                if file.readable {
+                    rights |= RIGHTS_FILE_READ;
+                }
+                if file.writable {
+                    rights |= RIGHTS_FILE_WRITE;
+                }
+
+                let flags = if file.append { FDFLAGS_APPEND } else { 0 };
+
+                (Filetype::RegularFile, flags, rights, 0)
+            }
+            Closed => return Err(Errno::Badf),
+        };
+
+        // https://github.com/WebAssembly/WASI/blob/main/legacy/preview1/docs.md#-fdstat-record
+        let buf = slice_mut(memory, ptr_buf, 24)?;
+        buf.fill(0);
+        buf[0] = filetype as u8;
+        buf[2..4].copy_from_slice(&(flags as u16).to_le_bytes());
+        buf[8..16].copy_from_slice(&rights.to_le_bytes());
+        buf[16..24].copy_from_slice(&rights_inheriting.to_le_bytes());
+
+        Ok(())
+    }
+
+    fn fd_filestat_get(&mut self, fd: usize) -> Result<Filestat, Errno> {
+        use WasiFile::*;
+
+        match self.file(fd)? {
+            ReadOnly(_) | WriteOnly(_) | ReadWrite(_) | HostSystemFile => Ok(Filestat {
+                filetype: Filetype::CharacterDevice,
+                size: 0,
+                mtim: 0,
+            }),
+            Directory(dir) => dir.fs.filestat(&dir.path),
+            File(file) => file.filestat(),
+            Closed => Err(Errno::Badf),
+        }
+    }
 }
 
-fn read_u32(memory: &[u8], addr: usize) -> u32 {
-    let mut bytes = [0; 4];
-    bytes.copy_from_slice(&memory[addr..][..4]);
-    u32::from_le_bytes(bytes)
+/// The (base address, length) of each buffer in an array of `iovec`s
+fn iovecs(memory: &[u8], ptr_iovs: usize, iovs_len: usize) -> Result<Vec<(usize, usize)>, Errno> {
+    // https://man7.org/linux/man-pages/man2/readv.2.html
+    // struct iovec {
+    //     void  *iov_base;    /* Starting address */
+    //     size_t iov_len;     /* Number of bytes to transfer */
+    // };
+    (0..iovs_len)
+        .map(|i| {
+            let iov_base = read_u32(memory, ptr_iovs + 8 * i)? as usize;
+            let iov_len = read_u32(memory, ptr_iovs + 8 * i + 4)? as usize;
+            Ok((iov_base, iov_len))
+        })
+        .collect()
 }
 
-fn read_i32(memory: &[u8], addr: usize) -> i32 {
+/// The return value of a function that only reports success or an error code
+fn errno(result: Result<(), Errno>) -> Result<Option<Value>, Errno> {
+    result.map(|()| Some(Value::I32(Errno::Success as i32)))
+}
+
+/// A range of the module's memory. The module chooses the address and length, so
+/// they may be out of bounds. That's an `Errno::Fault` for the module, not a panic for us.
+pub(crate) fn slice(memory: &[u8], addr: usize, len: usize) -> Result<&[u8], Errno> {
+    let end = addr.checked_add(len).ok_or(Errno::Fault)?;
+    memory.get(addr..end).ok_or(Errno::Fault)
+}
+
+pub(crate) fn slice_mut(memory: &mut [u8], addr: usize, len: usize) -> Result<&mut [u8], Errno> {
+    let end = addr.checked_add(len).ok_or(Errno::Fault)?;
+    memory.get_mut(addr..end).ok_or(Errno::Fault)
+}
+
+pub(crate) fn read_u32(memory: &[u8], addr: usize) -> Result<u32, Errno> {
     let mut bytes = [0; 4];
-    bytes.copy_from_slice(&memory[addr..][..4]);
-    i32::from_le_bytes(bytes)
+    bytes.copy_from_slice(slice(memory, addr, 4)?);
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_i32(memory: &[u8], addr: usize) -> Result<i32, Errno> {
+    Ok(read_u32(memory, addr)? as i32)
+}
+
+fn write_u32(memory: &mut [u8], addr: usize, value: u32) -> Result<(), Errno> {
+    slice_mut(memory, addr, 4)?.copy_from_slice(&value.to_le_bytes());
+    Ok(())
+}
+
+fn write_i32(memory: &mut [u8], addr: usize, value: i32) -> Result<(), Errno> {
+    write_u32(memory, addr, value as u32)
 }
 
-fn write_u32(memory: &mut [u8], addr: usize, value: u32) {
-    memory[addr..][..4].copy_from_slice(&value.to_le_bytes());
+fn write_u64(memory: &mut [u8], addr: usize, value: u64) -> Result<(), Errno> {
+    slice_mut(memory, addr, 8)?.copy_from_slice(&value.to_le_bytes());
+    Ok(())
 }
 
-fn write_i32(memory: &mut [u8], addr: usize, value: i32) {
-    memory[addr..][..4].copy_from_slice(&value.to_le_bytes());
+/// Write a zero-terminated string
+fn write_c_string(memory: &mut [u8], addr: usize, bytes: &[u8]) -> Result<(), Errno> {
+    let buf = slice_mut(memory, addr, bytes.len() + 1)?;
+    buf[..bytes.len()].copy_from_slice(bytes);
+    buf[bytes.len()] = 0;
+    Ok(())
 }
 
 /// Error codes returned by functions.
@@ -313,7 +912,7 @@ fn write_i32(memory: &mut [u8], addr: usize, value: i32) {
 /// API; some are used in higher-level library layers, and others are provided
 /// merely for alignment with POSIX.
 #[repr(u8)]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Errno {
     /// No error occurred. System call completed successfully.
     Success,
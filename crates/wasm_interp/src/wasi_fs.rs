@@ -0,0 +1,745 @@
+//! Sandboxed filesystems for WASI preopened directories
+//!
+//! A WebAssembly module can only reach files through a directory that was preopened for it.
+//! Each preopened directory is backed either by a directory on the host, or by an in-memory
+//! tree (handy for tests). Paths are always resolved relative to a directory file descriptor,
+//! and may not escape the preopened root, either with `..` or (on the host) through symlinks.
+use crate::wasi::{slice_mut, Errno};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+use std::time::UNIX_EPOCH;
+
+/// The contents of a file in a [MemoryDir], shared between the tree and any open handles
+pub type MemoryFile = Rc<RefCell<Vec<u8>>>;
+
+/// In-memory files can't grow beyond this, however far the module seeks before writing
+pub const MAX_MEMORY_FILE_SIZE: u64 = 1 << 30;
+
+#[derive(Clone, Debug, Default)]
+pub struct MemoryDir {
+    entries: BTreeMap<String, MemoryNode>,
+}
+
+#[derive(Clone, Debug)]
+pub enum MemoryNode {
+    File(MemoryFile),
+    Dir(MemoryDir),
+}
+
+impl MemoryDir {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create or overwrite a file, creating any missing parent directories.
+    /// Panics if the path is invalid, since this is meant for setting up tests.
+    pub fn insert_file(&mut self, path: &str, contents: impl Into<Vec<u8>>) {
+        let path = resolve(Path::new(""), path).expect("invalid path");
+        let (parent, name) = split_parent(&path).expect("invalid path");
+
+        let mut dir = self;
+        for component in parent.iter() {
+            let name = component.to_string_lossy().into_owned();
+            let node = dir
+                .entries
+                .entry(name)
+                .or_insert_with(|| MemoryNode::Dir(MemoryDir::new()));
+
+            dir = match node {
+                MemoryNode::Dir(subdir) => subdir,
+                MemoryNode::File(_) => panic!("{:?} is a file", component),
+            };
+        }
+
+        dir.entries.insert(
+            name,
+            MemoryNode::File(Rc::new(RefCell::new(contents.into()))),
+        );
+    }
+
+    /// The contents of a file, or `None` if there's no file at this path
+    pub fn read_file(&self, path: &str) -> Option<Vec<u8>> {
+        let path = resolve(Path::new(""), path).ok()?;
+
+        match self.lookup(&path).ok()? {
+            MemoryNode::File(contents) => Some(contents.borrow().clone()),
+            MemoryNode::Dir(_) => None,
+        }
+    }
+
+    /// Look up a node. An empty path can't be looked up, since the root is not a node.
+    fn lookup(&self, path: &Path) -> Result<&MemoryNode, Errno> {
+        let (parent, name) = split_parent(path)?;
+
+        self.dir(parent)?.entries.get(&name).ok_or(Errno::Noent)
+    }
+
+    fn dir(&self, path: &Path) -> Result<&MemoryDir, Errno> {
+        let mut dir = self;
+
+        for component in path.iter() {
+            match dir.entries.get(component.to_string_lossy().as_ref()) {
+                Some(MemoryNode::Dir(subdir)) => dir = subdir,
+                Some(MemoryNode::File(_)) => return Err(Errno::Notdir),
+                None => return Err(Errno::Noent),
+            }
+        }
+
+        Ok(dir)
+    }
+
+    fn dir_mut(&mut self, path: &Path) -> Result<&mut MemoryDir, Errno> {
+        let mut dir = self;
+
+        for component in path.iter() {
+            match dir.entries.get_mut(component.to_string_lossy().as_ref()) {
+                Some(MemoryNode::Dir(subdir)) => dir = subdir,
+                Some(MemoryNode::File(_)) => return Err(Errno::Notdir),
+                None => return Err(Errno::Noent),
+            }
+        }
+
+        Ok(dir)
+    }
+}
+
+/// What a preopened directory is backed by
+#[derive(Clone, Debug)]
+pub enum FileSystem {
+    /// A directory on the host
+    Host(PathBuf),
+    /// An in-memory tree, shared with whoever set up the preopen so they can inspect it later
+    Memory(Rc<RefCell<MemoryDir>>),
+}
+
+/// A directory file descriptor
+#[derive(Debug)]
+pub struct Directory {
+    pub fs: FileSystem,
+    /// Relative to the root of the filesystem, and never containing `..`
+    pub path: PathBuf,
+    /// Only set for preopened directories. This is the path the module sees them at.
+    pub preopen_name: Option<String>,
+}
+
+/// A regular file descriptor, returned from `path_open`
+#[derive(Debug)]
+pub struct OpenFile {
+    handle: FileHandle,
+    pub readable: bool,
+    pub writable: bool,
+    pub append: bool,
+}
+
+#[derive(Debug)]
+enum FileHandle {
+    Host(fs::File),
+    Memory { contents: MemoryFile, position: u64 },
+}
+
+/// Flags for [FileSystem::open], decoded from `path_open`'s arguments
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OpenFlags {
+    pub create: bool,
+    pub directory: bool,
+    pub exclusive: bool,
+    pub truncate: bool,
+    pub read: bool,
+    pub write: bool,
+    pub append: bool,
+}
+
+pub enum Opened {
+    Directory(Directory),
+    File(OpenFile),
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filetype {
+    Unknown = 0,
+    CharacterDevice = 2,
+    Directory = 3,
+    RegularFile = 4,
+    SymbolicLink = 7,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Filestat {
+    pub filetype: Filetype,
+    pub size: u64,
+    /// Last modification time, in nanoseconds since the Unix epoch
+    pub mtim: u64,
+}
+
+impl Filestat {
+    /// https://github.com/WebAssembly/WASI/blob/main/legacy/preview1/docs.md#-filestat-record
+    pub fn write_to(&self, memory: &mut [u8], addr: usize) -> Result<(), Errno> {
+        let buf = slice_mut(memory, addr, 64)?;
+        buf.fill(0);
+        buf[16] = self.filetype as u8;
+        buf[24..32].copy_from_slice(&1u64.to_le_bytes()); // nlink
+        buf[32..40].copy_from_slice(&self.size.to_le_bytes());
+        buf[40..48].copy_from_slice(&self.mtim.to_le_bytes()); // atim
+        buf[48..56].copy_from_slice(&self.mtim.to_le_bytes()); // mtim
+        buf[56..64].copy_from_slice(&self.mtim.to_le_bytes()); // ctim
+
+        Ok(())
+    }
+
+    fn directory() -> Self {
+        Filestat {
+            filetype: Filetype::Directory,
+            size: 0,
+            mtim: 0,
+        }
+    }
+
+    fn memory_file(contents: &MemoryFile) -> Self {
+        Filestat {
+            filetype: Filetype::RegularFile,
+            size: contents.borrow().len() as u64,
+            mtim: 0,
+        }
+    }
+
+    fn host(metadata: &fs::Metadata) -> Self {
+        let mtim = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or(0);
+
+        Filestat {
+            filetype: Filetype::host(metadata.file_type()),
+            size: metadata.len(),
+            mtim,
+        }
+    }
+}
+
+impl Filetype {
+    fn host(file_type: fs::FileType) -> Self {
+        if file_type.is_dir() {
+            Filetype::Directory
+        } else if file_type.is_file() {
+            Filetype::RegularFile
+        } else if file_type.is_symlink() {
+            Filetype::SymbolicLink
+        } else {
+            Filetype::Unknown
+        }
+    }
+}
+
+pub struct DirEntry {
+    pub name: String,
+    pub filetype: Filetype,
+}
+
+/// Resolve a path from the WebAssembly module against a directory, without touching the
+/// filesystem. Fails if the path is absolute or climbs out of the root.
+pub fn resolve(base: &Path, path: &str) -> Result<PathBuf, Errno> {
+    let mut resolved = base.to_path_buf();
+
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(name) => resolved.push(name),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !resolved.pop() {
+                    return Err(Errno::Notcapable);
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return Err(Errno::Notcapable),
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// Split a resolved path into its parent directory and final name
+fn split_parent(path: &Path) -> Result<(&Path, String), Errno> {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => Ok((parent, name.to_string_lossy().into_owned())),
+        // The root of a preopened directory has no parent we're allowed to touch
+        _ => Err(Errno::Notcapable),
+    }
+}
+
+fn io_errno(error: io::Error) -> Errno {
+    match error.kind() {
+        io::ErrorKind::NotFound => Errno::Noent,
+        io::ErrorKind::PermissionDenied => Errno::Access,
+        io::ErrorKind::AlreadyExists => Errno::Exist,
+        io::ErrorKind::InvalidInput => Errno::Inval,
+        io::ErrorKind::Unsupported => Errno::Notsup,
+        _ => Errno::Io,
+    }
+}
+
+impl FileSystem {
+    /// Whether two file descriptors refer to the same filesystem, so files can move between them
+    pub fn is_same(&self, other: &FileSystem) -> bool {
+        match (self, other) {
+            (FileSystem::Host(a), FileSystem::Host(b)) => a == b,
+            (FileSystem::Memory(a), FileSystem::Memory(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    /// The host path for a resolved path, making sure symlinks don't lead out of the root.
+    ///
+    /// Every component is checked before the next one is looked at. Symlinks are resolved
+    /// as we go, and must point at something that exists inside the root. (A dangling link
+    /// could be pointing anywhere, and following it to create a file would escape.)
+    /// When `follow_last` is false, a symlink at the end of the path is left as it is,
+    /// so that e.g. unlinking it removes the link rather than its target.
+    fn host_path(root: &Path, path: &Path, follow_last: bool) -> Result<PathBuf, Errno> {
+        let canonical_root = root.canonicalize().map_err(io_errno)?;
+        let mut host_path = canonical_root.clone();
+        let mut components = path.iter().peekable();
+
+        while let Some(name) = components.next() {
+            let is_last = components.peek().is_none();
+            host_path.push(name);
+
+            match fs::symlink_metadata(&host_path) {
+                Ok(metadata) if metadata.file_type().is_symlink() => {
+                    if is_last && !follow_last {
+                        break;
+                    }
+
+                    let target = host_path.canonicalize().map_err(|_| Errno::Notcapable)?;
+
+                    if !target.starts_with(&canonical_root) {
+                        return Err(Errno::Notcapable);
+                    }
+
+                    host_path = target;
+                }
+                Ok(_) => {}
+                Err(_) => {
+                    // Nothing exists here, so there are no more links to worry about
+                    host_path.extend(components);
+                    break;
+                }
+            }
+        }
+
+        Ok(host_path)
+    }
+
+    pub fn open(&self, path: &Path, flags: OpenFlags) -> Result<Opened, Errno> {
+        let directory = || Directory {
+            fs: self.clone(),
+            path: path.to_path_buf(),
+            preopen_name: None,
+        };
+
+        match self {
+            FileSystem::Host(root) => {
+                let host_path = Self::host_path(root, path, true)?;
+
+                match fs::metadata(&host_path) {
+                    Ok(metadata) if metadata.is_dir() => {
+                        if flags.create && flags.exclusive {
+                            Err(Errno::Exist)
+                        } else if flags.write || flags.truncate {
+                            Err(Errno::Isdir)
+                        } else {
+                            Ok(Opened::Directory(directory()))
+                        }
+                    }
+                    Ok(_) if flags.directory => Err(Errno::Notdir),
+                    Err(_) if flags.directory => Err(Errno::Noent),
+                    _ => {
+                        // The host only lets us create or truncate files it can write to,
+                        // but the module may still only be allowed to read from it.
+                        let file = fs::OpenOptions::new()
+                            .read(true)
+                            .write(flags.write || flags.create || flags.truncate)
+                            .append(flags.append)
+                            .create(flags.create && !flags.exclusive)
+                            .create_new(flags.create && flags.exclusive)
+                            .truncate(flags.truncate)
+                            .open(&host_path)
+                            .map_err(io_errno)?;
+
+                        Ok(Opened::File(OpenFile {
+                            handle: FileHandle::Host(file),
+                            readable: flags.read,
+                            writable: flags.write,
+                            append: flags.append,
+                        }))
+                    }
+                }
+            }
+            FileSystem::Memory(root) => {
+                let mut root = root.borrow_mut();
+
+                let existing = if path.as_os_str().is_empty() {
+                    None
+                } else {
+                    match root.lookup(path) {
+                        Ok(node) => Some(node.clone()),
+                        Err(Errno::Noent) => None,
+                        Err(errno) => return Err(errno),
+                    }
+                };
+
+                let contents = match existing {
+                    None if path.as_os_str().is_empty() => {
+                        return if flags.create && flags.exclusive {
+                            Err(Errno::Exist)
+                        } else {
+                            Ok(Opened::Directory(directory()))
+                        };
+                    }
+                    Some(MemoryNode::Dir(_)) => {
+                        return if flags.create && flags.exclusive {
+                            Err(Errno::Exist)
+                        } else if flags.write || flags.truncate {
+                            Err(Errno::Isdir)
+                        } else {
+                            Ok(Opened::Directory(directory()))
+                        };
+                    }
+                    Some(MemoryNode::File(_)) if flags.directory => return Err(Errno::Notdir),
+                    Some(MemoryNode::File(_)) if flags.create && flags.exclusive => {
+                        return Err(Errno::Exist)
+                    }
+                    Some(MemoryNode::File(contents)) => contents,
+                    None if flags.create && !flags.directory => {
+                        let (parent, name) = split_parent(path)?;
+                        let contents: MemoryFile = Rc::default();
+
+                        root.dir_mut(parent)?
+                            .entries
+                            .insert(name, MemoryNode::File(Rc::clone(&contents)));
+
+                        contents
+                    }
+                    None => return Err(Errno::Noent),
+                };
+
+                if flags.truncate {
+                    contents.borrow_mut().clear();
+                }
+
+                Ok(Opened::File(OpenFile {
+                    handle: FileHandle::Memory {
+                        contents,
+                        position: 0,
+                    },
+                    readable: flags.read,
+                    writable: flags.write,
+                    append: flags.append,
+                }))
+            }
+        }
+    }
+
+    pub fn filestat(&self, path: &Path) -> Result<Filestat, Errno> {
+        match self {
+            FileSystem::Host(root) => {
+                let host_path = Self::host_path(root, path, true)?;
+                let metadata = fs::metadata(host_path).map_err(io_errno)?;
+
+                Ok(Filestat::host(&metadata))
+            }
+            FileSystem::Memory(root) => {
+                if path.as_os_str().is_empty() {
+                    return Ok(Filestat::directory());
+                }
+
+                match root.borrow().lookup(path)? {
+                    MemoryNode::File(contents) => Ok(Filestat::memory_file(contents)),
+                    MemoryNode::Dir(_) => Ok(Filestat::directory()),
+                }
+            }
+        }
+    }
+
+    /// The entries of a directory, including `.` and `..`, in a stable order
+    pub fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, Errno> {
+        let mut entries = vec![
+            DirEntry {
+                name: ".".into(),
+                filetype: Filetype::Directory,
+            },
+            DirEntry {
+                name: "..".into(),
+                filetype: Filetype::Directory,
+            },
+        ];
+
+        match self {
+            FileSystem::Host(root) => {
+                let host_path = Self::host_path(root, path, true)?;
+                let mut host_entries = Vec::new();
+
+                for entry in fs::read_dir(host_path).map_err(io_errno)? {
+                    let entry = entry.map_err(io_errno)?;
+                    let file_type = entry.file_type().map_err(io_errno)?;
+
+                    host_entries.push(DirEntry {
+                        name: entry.file_name().to_string_lossy().into_owned(),
+                        filetype: Filetype::host(file_type),
+                    });
+                }
+
+                // The host doesn't promise any order, but cookies are indices into this list
+                host_entries.sort_by(|a, b| a.name.cmp(&b.name));
+                entries.extend(host_entries);
+            }
+            FileSystem::Memory(root) => {
+                let root = root.borrow();
+
+                for (name, node) in root.dir(path)?.entries.iter() {
+                    entries.push(DirEntry {
+                        name: name.clone(),
+                        filetype: match node {
+                            MemoryNode::File(_) => Filetype::RegularFile,
+                            MemoryNode::Dir(_) => Filetype::Directory,
+                        },
+                    });
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    pub fn create_dir(&self, path: &Path) -> Result<(), Errno> {
+        match self {
+            FileSystem::Host(root) => {
+                fs::create_dir(Self::host_path(root, path, true)?).map_err(io_errno)
+            }
+            FileSystem::Memory(root) => {
+                let (parent, name) = split_parent(path)?;
+                let mut root = root.borrow_mut();
+                let parent = root.dir_mut(parent)?;
+
+                if parent.entries.contains_key(&name) {
+                    return Err(Errno::Exist);
+                }
+
+                parent
+                    .entries
+                    .insert(name, MemoryNode::Dir(MemoryDir::new()));
+
+                Ok(())
+            }
+        }
+    }
+
+    pub fn remove_file(&self, path: &Path) -> Result<(), Errno> {
+        match self {
+            FileSystem::Host(root) => {
+                let host_path = Self::host_path(root, path, false)?;
+
+                if fs::symlink_metadata(&host_path).map_err(io_errno)?.is_dir() {
+                    return Err(Errno::Isdir);
+                }
+
+                fs::remove_file(host_path).map_err(io_errno)
+            }
+            FileSystem::Memory(root) => {
+                let (parent, name) = split_parent(path)?;
+                let mut root = root.borrow_mut();
+                let parent = root.dir_mut(parent)?;
+
+                match parent.entries.get(&name) {
+                    Some(MemoryNode::File(_)) => {
+                        parent.entries.remove(&name);
+                        Ok(())
+                    }
+                    Some(MemoryNode::Dir(_)) => Err(Errno::Isdir),
+                    None => Err(Errno::Noent),
+                }
+            }
+        }
+    }
+
+    pub fn remove_dir(&self, path: &Path) -> Result<(), Errno> {
+        match self {
+            FileSystem::Host(root) => {
+                let host_path = Self::host_path(root, path, false)?;
+
+                if !fs::symlink_metadata(&host_path).map_err(io_errno)?.is_dir() {
+                    return Err(Errno::Notdir);
+                }
+
+                if fs::read_dir(&host_path).map_err(io_errno)?.next().is_some() {
+                    return Err(Errno::Notempty);
+                }
+
+                fs::remove_dir(host_path).map_err(io_errno)
+            }
+            FileSystem::Memory(root) => {
+                let (parent, name) = split_parent(path)?;
+                let mut root = root.borrow_mut();
+                let parent = root.dir_mut(parent)?;
+
+                match parent.entries.get(&name) {
+                    Some(MemoryNode::Dir(dir)) if dir.entries.is_empty() => {
+                        parent.entries.remove(&name);
+                        Ok(())
+                    }
+                    Some(MemoryNode::Dir(_)) => Err(Errno::Notempty),
+                    Some(MemoryNode::File(_)) => Err(Errno::Notdir),
+                    None => Err(Errno::Noent),
+                }
+            }
+        }
+    }
+
+    /// Both paths must be in this filesystem
+    pub fn rename(&self, from: &Path, to: &Path) -> Result<(), Errno> {
+        match self {
+            FileSystem::Host(root) => {
+                let from = Self::host_path(root, from, false)?;
+                let to = Self::host_path(root, to, false)?;
+
+                fs::rename(from, to).map_err(io_errno)
+            }
+            FileSystem::Memory(root) => {
+                if to.starts_with(from) && to != from {
+                    // Can't move a directory inside itself
+                    return Err(Errno::Inval);
+                }
+
+                let (from_parent, from_name) = split_parent(from)?;
+                let (to_parent, to_name) = split_parent(to)?;
+                let mut root = root.borrow_mut();
+
+                let node = root.lookup(from)?.clone();
+
+                match (&node, root.dir(to_parent)?.entries.get(&to_name)) {
+                    (MemoryNode::File(_), Some(MemoryNode::Dir(_))) => return Err(Errno::Isdir),
+                    (MemoryNode::Dir(_), Some(MemoryNode::File(_))) => return Err(Errno::Notdir),
+                    (MemoryNode::Dir(_), Some(MemoryNode::Dir(dir))) if !dir.entries.is_empty() => {
+                        return Err(Errno::Notempty)
+                    }
+                    _ => {}
+                }
+
+                root.dir_mut(from_parent)?.entries.remove(&from_name);
+                root.dir_mut(to_parent)?.entries.insert(to_name, node);
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl OpenFile {
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, Errno> {
+        if !self.readable {
+            return Err(Errno::Badf);
+        }
+
+        match &mut self.handle {
+            FileHandle::Host(file) => file.read(buf).map_err(io_errno),
+            FileHandle::Memory { contents, position } => {
+                let contents = contents.borrow();
+                let start = (*position).min(contents.len() as u64) as usize;
+                let len = buf.len().min(contents.len() - start);
+
+                buf[..len].copy_from_slice(&contents[start..][..len]);
+                *position += len as u64;
+
+                Ok(len)
+            }
+        }
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) -> Result<usize, Errno> {
+        if !self.writable {
+            return Err(Errno::Badf);
+        }
+
+        match &mut self.handle {
+            FileHandle::Host(file) => file.write(bytes).map_err(io_errno),
+            FileHandle::Memory { contents, position } => {
+                let mut contents = contents.borrow_mut();
+
+                if self.append {
+                    *position = contents.len() as u64;
+                }
+
+                let end = position
+                    .checked_add(bytes.len() as u64)
+                    .filter(|&end| end <= MAX_MEMORY_FILE_SIZE)
+                    .ok_or(Errno::Fbig)?;
+                let (start, end) = (*position as usize, end as usize);
+
+                if contents.len() < end {
+                    contents.resize(end, 0);
+                }
+
+                contents[start..end].copy_from_slice(bytes);
+                *position = end as u64;
+
+                Ok(bytes.len())
+            }
+        }
+    }
+
+    pub fn seek(&mut self, seek_from: SeekFrom) -> Result<u64, Errno> {
+        match &mut self.handle {
+            FileHandle::Host(file) => file.seek(seek_from).map_err(io_errno),
+            FileHandle::Memory { contents, position } => {
+                let new_position = match seek_from {
+                    SeekFrom::Start(offset) => Some(offset),
+                    SeekFrom::Current(delta) => offset_by(*position, delta),
+                    SeekFrom::End(delta) => offset_by(contents.borrow().len() as u64, delta),
+                };
+
+                *position = new_position.ok_or(Errno::Inval)?;
+
+                Ok(*position)
+            }
+        }
+    }
+
+    pub fn filestat(&self) -> Result<Filestat, Errno> {
+        match &self.handle {
+            FileHandle::Host(file) => Ok(Filestat::host(&file.metadata().map_err(io_errno)?)),
+            FileHandle::Memory { contents, .. } => Ok(Filestat::memory_file(contents)),
+        }
+    }
+
+    pub fn set_size(&mut self, size: u64) -> Result<(), Errno> {
+        if !self.writable {
+            return Err(Errno::Badf);
+        }
+
+        match &mut self.handle {
+            FileHandle::Host(file) => file.set_len(size).map_err(io_errno),
+            FileHandle::Memory { contents, .. } => {
+                if size > MAX_MEMORY_FILE_SIZE {
+                    return Err(Errno::Fbig);
+                }
+
+                contents.borrow_mut().resize(size as usize, 0);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Like `u64::checked_add_signed`, which needs a newer Rust version than we use
+fn offset_by(position: u64, delta: i64) -> Option<u64> {
+    if delta >= 0 {
+        position.checked_add(delta as u64)
+    } else {
+        position.checked_sub(delta.unsigned_abs())
+    }
+}
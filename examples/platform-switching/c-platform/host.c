@@ -59,6 +59,10 @@ int roc_getppid() {
 #endif
 }
 
+char* roc_getenv(const char* name) {
+    return getenv(name);
+}
+
 struct RocStr {
   char* bytes;
   size_t len;
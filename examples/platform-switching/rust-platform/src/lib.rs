@@ -60,6 +60,12 @@ pub unsafe extern "C" fn roc_getppid() -> libc::pid_t {
     libc::getppid()
 }
 
+#[cfg(unix)]
+#[no_mangle]
+pub unsafe extern "C" fn roc_getenv(name: *const libc::c_char) -> *const libc::c_char {
+    libc::getenv(name)
+}
+
 #[cfg(unix)]
 #[no_mangle]
 pub unsafe extern "C" fn roc_mmap(
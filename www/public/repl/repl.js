@@ -3,6 +3,8 @@ function setGlobalsForWasmBindgen() {
   window.js_create_app = js_create_app;
   window.js_run_app = js_run_app;
   window.js_get_result_and_memory = js_get_result_and_memory;
+  window.js_get_expect_report = js_get_expect_report;
+  window.js_get_expect_report_memory = js_get_expect_report_memory;
 
   // The only place we use console.error is in wasm_bindgen, where it gets a single string argument.
   console.error = function displayErrorInHistoryPanel(string) {
@@ -36,6 +38,9 @@ const repl = {
 
   // Temporary storage for values passing back and forth between JS and Wasm
   result: { addr: 0, buffer: new ArrayBuffer() },
+
+  // Calls to roc_dbg and roc_expect_failed during the last run of the app
+  expectReports: [],
 };
 
 // Initialise
@@ -146,6 +151,11 @@ async function processInputQueue() {
 async function js_create_app(wasm_module_bytes) {
   const wasiLinkObject = {}; // gives the WASI functions a reference to the app so they can write to its memory
   const importObj = getMockWasiImports(wasiLinkObject);
+  importObj.env = {
+    roc_dbg: (...args) => recordExpectReport(EXPECT_REPORT_DBG, args),
+    roc_expect_failed: (...args) =>
+      recordExpectReport(EXPECT_REPORT_EXPECT_FAILED, args),
+  };
   const { instance } = await WebAssembly.instantiate(
     wasm_module_bytes,
     importObj
@@ -158,6 +168,7 @@ async function js_create_app(wasm_module_bytes) {
 // Cache the result and return the size of the app's memory
 function js_run_app() {
   const { wrapper, memory } = repl.app.exports;
+  repl.expectReports = [];
   const addr = wrapper();
   const { buffer } = memory;
   repl.result = { addr, buffer };
@@ -177,6 +188,38 @@ function js_get_result_and_memory(buffer_alloc_addr) {
   return addr;
 }
 
+// The Roc values that a dbg or expect refers to may be overwritten before the app returns,
+// so we take a copy of the app's memory on every call.
+const EXPECT_REPORT_DBG = 0;
+const EXPECT_REPORT_EXPECT_FAILED = 1;
+
+function recordExpectReport(kind, args) {
+  const { buffer } = repl.app.exports.memory;
+  repl.expectReports.push({ header: [kind, ...args], memory: buffer.slice(0) });
+}
+
+// Write the header of a report into the compiler's memory, and return the size of its memory
+// snapshot. Returns 0 when there are no more reports.
+function js_get_expect_report(index, header_addr) {
+  const report = repl.expectReports[index];
+  if (!report) {
+    return 0;
+  }
+  const compilerMemory32 = new Uint32Array(
+    repl.compiler.memory.buffer,
+    header_addr,
+    report.header.length
+  );
+  compilerMemory32.set(report.header.map((word) => word >>> 0));
+  return report.memory.byteLength;
+}
+
+function js_get_expect_report_memory(index, buffer_alloc_addr) {
+  const appMemory = new Uint8Array(repl.expectReports[index].memory);
+  const compilerMemory = new Uint8Array(repl.compiler.memory.buffer);
+  compilerMemory.set(appMemory, buffer_alloc_addr);
+}
+
 // ----------------------------------------------------------------------------
 // Rendering
 // ----------------------------------------------------------------------------